@@ -0,0 +1,30 @@
+#!/bin/sh
+# Writes a development CA and the certificates of the gRPC links next to this script: one per
+# signer, valid for its compose service name, localhost and 127.0.0.1, and one the coordinator
+# presents to signers requiring client certificates. Existing files are kept; delete them to
+# issue new ones. Production keys come from your own CA.
+set -eu
+cd "$(dirname "$0")"
+
+if [ ! -f ca.pem ]; then
+    openssl req -x509 -newkey rsa:2048 -nodes -days 3650 -subj "/CN=TSS dev CA" \
+        -keyout ca.key -out ca.pem
+fi
+
+issue() {
+    name=$1
+    san=$2
+    [ -f "$name.pem" ] && return
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=$name" -keyout "$name.key" -out "$name.csr"
+    printf 'subjectAltName=%s\nextendedKeyUsage=serverAuth,clientAuth\n' "$san" > "$name.ext"
+    openssl x509 -req -in "$name.csr" -CA ca.pem -CAkey ca.key -CAcreateserial -days 825 \
+        -extfile "$name.ext" -out "$name.pem"
+    rm "$name.csr" "$name.ext"
+}
+
+for id in 1 2 3; do
+    issue "signer$id" "DNS:signer$id,DNS:localhost,IP:127.0.0.1"
+done
+issue coordinator "DNS:coordinator"
+# The containers run as other users than the one writing the keys
+chmod 644 ./*.key
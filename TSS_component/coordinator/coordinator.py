@@ -2,6 +2,7 @@ import asyncio
 import httpx
 import json
 import logging
+import math
 from typing import List, Tuple
 import os
 import requests
@@ -41,6 +42,24 @@ if not signer_urls_str:
 
 SIGNERS = signer_urls_str.split(",")
 
+# Network profile of the bridge, as the ZKP binaries and the Rust coordinator take it:
+# mainnet | testnet | signet | regtest | litecoin | litecoin-testnet
+NETWORK = os.getenv("BRIDGE_PROFILE", "testnet")
+MEMPOOL_API = {
+    "mainnet": "https://mempool.space/api",
+    "testnet": "https://mempool.space/testnet/api",
+    "signet": "https://mempool.space/signet/api",
+    "litecoin": "https://litecoinspace.org/api",
+    "litecoin-testnet": "https://litecoinspace.org/testnet/api",
+}
+
+
+def bitcoinlib_network(network: str) -> str:
+    # bitcoinlib names mainnet "bitcoin" and Litecoin's testnet "litecoin_testnet"
+    if network == "mainnet":
+        return "bitcoin"
+    return network.replace("-", "_")
+
 
 
 
@@ -190,17 +209,19 @@ async def coordinate_frost_sign(message: str, pubkp_hex: str):
         logger.info(f"Aggregated Schnorr Signature: {aggregated_sig}")
         return aggregated_sig
 
-def broadcast_tx(tx_hex: str, network: str = "testnet") -> str:
+def broadcast_tx(tx_hex: str, network: str = NETWORK) -> str:
     """
     Broadcast the signed transaction using Bitcoinlib.
     """
-    service = Service(network=network)
+    service = Service(network=bitcoinlib_network(network))
     txid = service.sendrawtransaction(tx_hex)
     logger.info(f"Broadcasted transaction. TXID: {txid}")
     return txid
 
-def broadcast_tx_mempool(tx_hex: str):
-    url = "https://mempool.space/testnet/api/tx"
+def broadcast_tx_mempool(tx_hex: str, network: str = NETWORK):
+    if network not in MEMPOOL_API:
+        raise Exception(f"mempool.space does not serve {network}")
+    url = f"{MEMPOOL_API[network]}/tx"
     headers = {"Content-Type": "text/plain"}
     response = requests.post(url, data=tx_hex, headers=headers)
     
@@ -210,7 +231,7 @@ def broadcast_tx_mempool(tx_hex: str):
     txid = response.text.strip()
     return txid
 
-def fetch_fee_rate(network='testnet', priority='medium') -> int:
+def fetch_fee_rate(network=NETWORK, priority='medium') -> int:
     """
     Fetch estimated fee rate (sats/vbyte) using bitcoinlib's estimatefee.
     
@@ -221,7 +242,7 @@ def fetch_fee_rate(network='testnet', priority='medium') -> int:
     Returns:
         int: fee rate in sats/vbyte
     """
-    service = Service(network=network)
+    service = Service(network=bitcoinlib_network(network))
     fee_per_kb = service.estimatefee(priority=priority)
 
     if not isinstance(fee_per_kb, int):
@@ -236,6 +257,31 @@ def fetch_fee_rate(network='testnet', priority='medium') -> int:
     
     print(f"Estimated fee rate ({priority}): {fee_per_byte} sats/vbyte")
     return fee_per_byte
+
+def fetch_oracle_fee_rate(network=NETWORK) -> int:
+    """
+    Fetch the medium fee rate (sats/vbyte) from the bridge fee oracle at FEE_ORACLE_URL,
+    falling back to bitcoinlib's estimate when the oracle is unset or unreachable.
+
+    Returns:
+        int: fee rate in sats/vbyte, rounded up
+    """
+    oracle_url = os.getenv("FEE_ORACLE_URL")
+    if oracle_url:
+        try:
+            response = requests.get(f"{oracle_url.rstrip('/')}/fees", timeout=10)
+            response.raise_for_status()
+            btc = response.json().get("btc")
+            if btc is not None:
+                return max(1, math.ceil(btc["medium"]))
+            logger.warning("Fee oracle has no BTC fee rate, falling back to bitcoinlib")
+        except Exception as e:
+            logger.warning(f"Fee oracle unavailable ({e}), falling back to bitcoinlib")
+    try:
+        return fetch_fee_rate(network)
+    except Exception as e:
+        logger.warning(f"Fee estimation failed ({e}), using the minimum fee rate")
+        return 5
 def decode_signed_tx(signed_tx_hex: str):
     """
     Decode and inspect a signed Bitcoin transaction hex.
@@ -304,9 +350,7 @@ async def propose_tx_and_sign(
     """
 
     logger.info("Fetching fee rate...")
-    fee_rate = 2
-    if fee_rate == 0:
-        fee_rate = 5  # Fallback minimum fee rate
+    fee_rate = fetch_oracle_fee_rate(network)
     logger.info(f"Estimated fee rate: {fee_rate} sats/vbyte")
 
 
@@ -339,7 +383,7 @@ async def propose_tx_and_sign(
 if __name__ == "__main__":
     try:
         group_vk_hex,pubkp_hex = asyncio.run(run_dkg())
-        taproot_address = rust_tss.derive_taproot_address(group_vk_hex, "testnet")
+        taproot_address = rust_tss.derive_taproot_address(group_vk_hex, NETWORK)
         logger.info(f"Taproot Address:{taproot_address}")
         
         
@@ -370,7 +414,7 @@ if __name__ == "__main__":
         # to_address = "<RECIPIENT_ADDRESS>"
         # send_value = <AMOUNT_TO_SEND_IN_SATS>
         # change_address = "<YOUR_CHANGE_ADDRESS>"
-        # network = "<NETWORK_NAME>"  # e.g., "testnet" or "mainnet"
+        # network = NETWORK  # BRIDGE_PROFILE, e.g. "testnet" or "mainnet"
         # logger.info(f"Preparing to propose transaction with UTXO {utxo_txid}:{utxo_vout} of value {utxo_value} sats")
         # signed_tx_hex = asyncio.run(
         #     propose_tx_and_sign(
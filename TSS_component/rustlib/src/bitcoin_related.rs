@@ -1,4 +1,6 @@
 use bitcoin::{
+    base58,
+    bech32::{self, Hrp},
     blockdata::witness::Witness,
     consensus::encode::{serialize, deserialize},
     hashes::Hash,
@@ -6,9 +8,9 @@ use bitcoin::{
     sighash::{Prevouts, SighashCache, TapSighashType},
     taproot,
     transaction::Version,
-    Address, Amount, Network, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Address, Amount, Network, PubkeyHash, Script, ScriptBuf, ScriptHash, Sequence, Transaction,
+    TxIn, TxOut, Txid, WitnessProgram, WitnessVersion,
 };
-use bitcoin::address::KnownHrp;
 use bitcoin::secp256k1::schnorr::Signature as SchnorrSig;
 use pyo3::prelude::*;
 use std::str::FromStr;
@@ -62,7 +64,7 @@ pub fn prepare_unsigned_tx_and_sighash(
     change_address: &str,
     network_str: &str,
 ) -> PyResult<(String, String)> {
-    let network = parse_network(network_str)?;
+    let chain = ChainParams::from_name(network_str)?;
     let tx = create_unsigned_tx(
         utxo_txid,
         utxo_vout,
@@ -71,7 +73,7 @@ pub fn prepare_unsigned_tx_and_sighash(
         send_value,
         fee_rate_sat_per_vbyte,
         change_address,
-        network,
+        &chain,
     )?;
     let prev_spk_bytes = hex::decode(prev_spk_hex)
         .map_err(|e| BtcError::General(format!("bad prev_spk_hex: {e}")))?;
@@ -97,14 +99,11 @@ pub fn finalize_signed_tx_from_hex(
     Ok(hex::encode(signed_tx_bytes))
 }
 
-/// Derive a Taproot address from an x-only pubkey and network.
+/// Derive a Taproot address from an x-only pubkey and network ("mainnet", "testnet", "signet",
+/// "regtest", "litecoin" or "litecoin-testnet").
 #[pyfunction]
 pub fn derive_taproot_address(x_only_hex: &str, network_str: &str) -> PyResult<String> {
-    let hrp = match network_str {
-        "mainnet" => KnownHrp::Mainnet,
-        "testnet" => KnownHrp::Testnets,
-        _ => return Err(BtcError::InvalidNetwork(format!("Invalid network: {}", network_str)).into()),
-    };
+    let chain = ChainParams::from_name(network_str)?;
     let pubkey_bytes = hex::decode(x_only_hex)
         .map_err(|e| BtcError::General(format!("Failed to decode public key: {}", e)))?;
     let pubkey_xonly = if pubkey_bytes.len() == 33 && (pubkey_bytes[0] == 0x02 || pubkey_bytes[0] == 0x03) {
@@ -120,19 +119,73 @@ pub fn derive_taproot_address(x_only_hex: &str, network_str: &str) -> PyResult<S
     let _tweaked_key = pubkey_xonly.add_tweak(&secp, &Scalar::ZERO)
         .map_err(|e| BtcError::General(format!("Failed to tweak key: {e}")))?;
 
-    let address = Address::p2tr(&secp, pubkey_xonly, None, hrp);
-    Ok(address.to_string())
+    let script = ScriptBuf::new_p2tr(&secp, pubkey_xonly, None);
+    Ok(chain.address_from_script(&script)?)
 }
 
 // ===================== Internal Helpers =====================
 
-fn parse_network(network_str: &str) -> Result<Network, BtcError> {
-    match network_str {
-        "mainnet" => Ok(Network::Bitcoin),
-        "testnet" => Ok(Network::Testnet),
-        "signet" => Ok(Network::Signet),
-        "regtest" => Ok(Network::Regtest),
-        _ => Err(BtcError::InvalidNetwork(network_str.to_string())),
+/// Address encoding of a source chain, as the ZKP `chain` module defines it.
+struct ChainParams {
+    bech32_hrp: &'static str,
+    p2pkh_prefix: u8,
+    p2sh_prefix: u8,
+}
+
+impl ChainParams {
+    fn from_name(network_str: &str) -> Result<Self, BtcError> {
+        let (bech32_hrp, p2pkh_prefix, p2sh_prefix) = match network_str {
+            "mainnet" => ("bc", 0, 5),
+            "testnet" | "signet" => ("tb", 111, 196),
+            "regtest" => ("bcrt", 111, 196),
+            "litecoin" => ("ltc", 48, 50),
+            "litecoin-testnet" => ("tltc", 111, 58),
+            "litecoin-regtest" => ("rltc", 111, 58),
+            _ => return Err(BtcError::InvalidNetwork(network_str.to_string())),
+        };
+        Ok(ChainParams { bech32_hrp, p2pkh_prefix, p2sh_prefix })
+    }
+
+    fn script_pubkey(&self, address: &str) -> Result<ScriptBuf, BtcError> {
+        let invalid = |e: String| BtcError::General(format!("Invalid address {address}: {e}"));
+        let hrp = address.rsplit_once('1').map(|(hrp, _)| hrp);
+        if hrp.is_some_and(|hrp| hrp.eq_ignore_ascii_case(self.bech32_hrp)) {
+            let (_, version, program) =
+                bech32::segwit::decode(address).map_err(|e| invalid(e.to_string()))?;
+            let version = WitnessVersion::try_from(version).map_err(|e| invalid(e.to_string()))?;
+            let program =
+                WitnessProgram::new(version, &program).map_err(|e| invalid(e.to_string()))?;
+            return Ok(ScriptBuf::new_witness_program(&program));
+        }
+        let payload = base58::decode_check(address).map_err(|e| invalid(e.to_string()))?;
+        match payload.split_first() {
+            Some((&prefix, hash)) if prefix == self.p2pkh_prefix && hash.len() == 20 => Ok(
+                ScriptBuf::new_p2pkh(&PubkeyHash::from_slice(hash).map_err(|e| invalid(e.to_string()))?),
+            ),
+            Some((&prefix, hash)) if prefix == self.p2sh_prefix && hash.len() == 20 => Ok(
+                ScriptBuf::new_p2sh(&ScriptHash::from_slice(hash).map_err(|e| invalid(e.to_string()))?),
+            ),
+            _ => Err(invalid("not an address of this network".to_string())),
+        }
+    }
+
+    fn address_from_script(&self, script: &Script) -> Result<String, BtcError> {
+        let bytes = script.as_bytes();
+        if let Some(version) = script.witness_version() {
+            let hrp = Hrp::parse(self.bech32_hrp).map_err(|e| BtcError::General(e.to_string()))?;
+            return bech32::segwit::encode(hrp, version.into(), &bytes[2..])
+                .map_err(|e| BtcError::General(e.to_string()));
+        }
+        let (prefix, hash) = if script.is_p2pkh() {
+            (self.p2pkh_prefix, &bytes[3..23])
+        } else if script.is_p2sh() {
+            (self.p2sh_prefix, &bytes[2..22])
+        } else {
+            return Err(BtcError::General(format!("Script {script} has no address")));
+        };
+        let mut payload = vec![prefix];
+        payload.extend_from_slice(hash);
+        Ok(base58::encode_check(&payload))
     }
 }
 
@@ -172,11 +225,10 @@ fn create_unsigned_tx(
     send_value: u64,
     fee_rate_sat_per_vbyte: u64,
     change_address: &str,
-    network: Network,
+    chain: &ChainParams,
 ) -> Result<Transaction, BtcError> {
     let txid = Txid::from_str(utxo_txid)?;
-    let to_addr = Address::from_str(to_address)?.require_network(network)?;
-    let to_script = to_addr.script_pubkey();
+    let to_script = chain.script_pubkey(to_address)?;
 
     let maybe_change_value = calculate_change(utxo_value, send_value, fee_rate_sat_per_vbyte, true)?;
 
@@ -186,10 +238,9 @@ fn create_unsigned_tx(
     }];
 
     if let Some(change_val) = maybe_change_value {
-        let change_addr = Address::from_str(change_address)?.require_network(network)?;
         tx_outs.push(TxOut {
             value: Amount::from_sat(change_val),
-            script_pubkey: change_addr.script_pubkey(),
+            script_pubkey: chain.script_pubkey(change_address)?,
         });
     }
 
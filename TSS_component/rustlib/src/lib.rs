@@ -253,6 +253,61 @@ fn init(self_id: u16) -> PyResult<(bool, String,String,String)> {
 }
 
 
+// --- Backup (using u16 ID) ---
+// Raw KeyPackage and PublicKeyPackage of this signer, hex, for signer/backup.py to encrypt
+// and split. None before the DKG.
+#[pyfunction]
+fn export_key_material(self_id: u16) -> PyResult<Option<(String, String, String)>> {
+    let self_id_hex = identifier_to_hex(identifier_from_u16(self_id)?);
+    let kp = DB.get(format!("keypkg_{}", self_id_hex)).map_err(FfiError::Sled)?;
+    let pubkp = DB.get(format!("pubkeypkg_{}", self_id_hex)).map_err(FfiError::Sled)?;
+    match (kp, pubkp) {
+        (Some(kp), Some(pubkp)) => Ok(Some((hex::encode(kp), hex::encode(pubkp), self_id_hex))),
+        _ => Ok(None),
+    }
+}
+
+// Writes back the packages of a restored backup, once they are checked to be this signer's
+// share of one group key. Keys already held are only replaced with `overwrite`. Returns the
+// group verifying key, hex.
+#[pyfunction]
+fn import_key_material(self_id: u16, key_package_hex: String, public_key_package_hex: String, overwrite: bool) -> PyResult<String> {
+    let self_id = identifier_from_u16(self_id)?;
+    let self_id_hex = identifier_to_hex(self_id);
+    let kp_bytes = hex::decode(&key_package_hex).map_err(FfiError::Hex)?;
+    let pubkp_bytes = hex::decode(&public_key_package_hex).map_err(FfiError::Hex)?;
+    let kp = KeyPackage::deserialize(&kp_bytes)
+        .map_err(|e| FfiError::Serde(format!("Deserialize keypkg failed: {}", e)))?;
+    let pubkp = PublicKeyPackage::deserialize(&pubkp_bytes)
+        .map_err(|e| FfiError::Serde(format!("Deserialize pubkeypkg failed: {}", e)))?;
+
+    if *kp.identifier() != self_id {
+        return Err(FfiError::State(format!("The backup holds the share of {}, not of {}", identifier_to_hex(*kp.identifier()), self_id_hex)).into());
+    }
+    if kp.verifying_key() != pubkp.verifying_key() {
+        return Err(FfiError::State("The key package and the public key package are of different group keys".into()).into());
+    }
+    if pubkp.verifying_shares().get(&self_id) != Some(kp.verifying_share()) {
+        return Err(FfiError::State("The public key package does not hold the verifying share of the key package".into()).into());
+    }
+
+    let kp_key = format!("keypkg_{}", self_id_hex);
+    let pubkp_key = format!("pubkeypkg_{}", self_id_hex);
+    if !overwrite {
+        if let Some(held) = DB.get(&kp_key).map_err(FfiError::Sled)? {
+            if held.as_ref() != kp_bytes.as_slice() {
+                return Err(FfiError::State(format!("Signer {} already holds a different share", self_id_hex)).into());
+            }
+        }
+    }
+    DB.insert(kp_key, kp_bytes).map_err(FfiError::Sled)?;
+    DB.insert(pubkp_key, pubkp_bytes).map_err(FfiError::Sled)?;
+    DB.flush().map_err(FfiError::Sled)?;
+
+    let serialized_verify_key = pubkp.verifying_key().serialize().map_err(|e| FfiError::Serde(format!("Serialize VerifyingKey failed: {}", e)))?;
+    Ok(hex::encode(serialized_verify_key))
+}
+
 
 // --- Signing Round 1 ---
 #[pyfunction]
@@ -272,8 +327,17 @@ fn sign_round1(self_id: String) -> PyResult<String> {
 }
 
 // --- Signing Round 2 ---
+// A malformed merkle root is the caller's error: it surfaces as a Python ValueError rather
+// than a panic across the FFI boundary.
+fn decode_merkle_root(merkle_root_hex: Option<String>) -> Result<Option<Vec<u8>>, FfiError> {
+    Ok(merkle_root_hex.map(hex::decode).transpose()?)
+}
+
+// `merkle_root_hex` is the script tree root the spent output's key is tweaked with (a
+// per-request deposit address); without it, the key-path-only vault key is used.
 #[pyfunction]
-fn sign_round2(self_id: String, message_hex: String, commitments: Vec<(String, String)>) -> PyResult<String> {
+#[pyo3(signature = (self_id, message_hex, commitments, merkle_root_hex=None))]
+fn sign_round2(self_id: String, message_hex: String, commitments: Vec<(String, String)>, merkle_root_hex: Option<String>) -> PyResult<String> {
     let key_pkg: KeyPackage = get_key_package(self_id.clone())?
         .ok_or_else(|| FfiError::MissingData(format!("Missing KeyPackage for ID {}", self_id)))?;
     
@@ -294,7 +358,8 @@ fn sign_round2(self_id: String, message_hex: String, commitments: Vec<(String, S
     let message = hex::decode(message_hex).unwrap();
     let signing_package = SigningPackage::new(commitments_map, &message);
 
-    let sig_share = round2::sign_with_tweak(&signing_package, &nonces, &key_pkg,None).unwrap();
+    let merkle_root = decode_merkle_root(merkle_root_hex)?;
+    let sig_share = round2::sign_with_tweak(&signing_package, &nonces, &key_pkg, merkle_root.as_deref()).unwrap();
     let serialized = sig_share.serialize();
         
     Ok(hex::encode(serialized))
@@ -302,7 +367,8 @@ fn sign_round2(self_id: String, message_hex: String, commitments: Vec<(String, S
 
 // --- Aggregation (Coordinator) ---
 #[pyfunction]
-fn aggregate_signature(message_hex: String, sig_shares: Vec<(String, String)>, commitments: Vec<(String, String)>, pubkey_hex: String) -> PyResult<String> {
+#[pyo3(signature = (message_hex, sig_shares, commitments, pubkey_hex, merkle_root_hex=None))]
+fn aggregate_signature(message_hex: String, sig_shares: Vec<(String, String)>, commitments: Vec<(String, String)>, pubkey_hex: String, merkle_root_hex: Option<String>) -> PyResult<String> {
     let mut sig_map = BTreeMap::new();
     let mut commitments_map = BTreeMap::new();
 
@@ -325,8 +391,9 @@ fn aggregate_signature(message_hex: String, sig_shares: Vec<(String, String)>, c
 
     let pubkey_bytes = hex::decode(pubkey_hex).unwrap();
     let pubkey = PublicKeyPackage::deserialize(&pubkey_bytes).unwrap();
-    let tweaked_pubkey = pubkey.clone().tweak(None::<&[u8]>);
-    let group_signature: Signature = frost_secp256k1_tr::aggregate_with_tweak(&signing_package, &sig_map, &pubkey,None).unwrap();
+    let merkle_root = decode_merkle_root(merkle_root_hex)?;
+    let tweaked_pubkey = pubkey.clone().tweak(merkle_root.as_deref());
+    let group_signature: Signature = frost_secp256k1_tr::aggregate_with_tweak(&signing_package, &sig_map, &pubkey, merkle_root.as_deref()).unwrap();
 
 
     // println!("[Rust] Public verifying key: {}", hex::encode(pubkey.verifying_key().serialize().unwrap()));
@@ -354,7 +421,11 @@ fn rust_tss(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(dkg_round1, m)?)?;
     m.add_function(wrap_pyfunction!(dkg_round2, m)?)?;
     m.add_function(wrap_pyfunction!(dkg_round3, m)?)?;
-    m.add_function(wrap_pyfunction!(init, m)?)?; 
+    m.add_function(wrap_pyfunction!(init, m)?)?;
+
+    // Backup related functions
+    m.add_function(wrap_pyfunction!(export_key_material, m)?)?; // Raw key packages, for signer/backup.py
+    m.add_function(wrap_pyfunction!(import_key_material, m)?)?; // Restore them from a backup
 
     // TSS related functions
     m.add_function(wrap_pyfunction!(sign_round1, m)?)?; // round1 sign
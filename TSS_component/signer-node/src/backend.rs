@@ -0,0 +1,214 @@
+//! Bitcoin backend the node looks the outputs a transaction spends up in before signing it, so
+//! the prevout values the coordinator sends, which the sighash commits to but which the
+//! transaction itself does not carry, are checked rather than trusted.
+
+use bitcoin::{Amount, Network, OutPoint, ScriptBuf, TxOut};
+use clap::ValueEnum;
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+
+pub type BackendError = Box<dyn Error + Send + Sync>;
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum SourceType {
+    Rpc,
+    Esplora,
+}
+
+/// Network and backend settings, named as in the ZKP binaries.
+#[derive(clap::Args, Debug)]
+pub struct BackendArgs {
+    /// Network of the group's addresses, as payouts are reported and checked against the
+    /// policy's address lists.
+    #[clap(long, env = "BITCOIN_NETWORK", default_value = "testnet")]
+    pub network: Network,
+    /// Backend the spent outputs are looked up in; without one, the prevout values sent by
+    /// the coordinator are taken as they are.
+    #[clap(long, value_enum, env = "BITCOIN_SOURCE")]
+    pub source: Option<SourceType>,
+    #[clap(
+        long,
+        env = "BITCOIN_RPC_URL",
+        default_value = "http://127.0.0.1:18332"
+    )]
+    pub rpc_url: String,
+    #[clap(long, env = "BITCOIN_RPC_USER")]
+    pub rpc_user: Option<String>,
+    #[clap(long, env = "BITCOIN_RPC_PASSWORD")]
+    pub rpc_password: Option<String>,
+    /// Base URL of the Esplora API, used with `--source esplora`.
+    #[clap(
+        long,
+        env = "ESPLORA_URL",
+        default_value = "https://blockstream.info/testnet/api"
+    )]
+    pub esplora_url: String,
+}
+
+impl BackendArgs {
+    /// Client of the selected backend, if any.
+    pub fn connect(&self) -> Option<Backend> {
+        let client = Client::new();
+        match self.source? {
+            SourceType::Rpc => Some(Backend::Rpc {
+                client,
+                url: self.rpc_url.clone(),
+                auth: self
+                    .rpc_user
+                    .clone()
+                    .map(|user| (user, self.rpc_password.clone())),
+            }),
+            SourceType::Esplora => Some(Backend::Esplora {
+                client,
+                url: self.esplora_url.trim_end_matches('/').to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcScript {
+    hex: String,
+}
+
+/// `gettxout` result.
+#[derive(Deserialize)]
+struct RpcTxOut {
+    /// In BTC.
+    value: f64,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: RpcScript,
+}
+
+#[derive(Deserialize)]
+struct EsploraVout {
+    scriptpubkey: String,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct EsploraTx {
+    vout: Vec<EsploraVout>,
+}
+
+#[derive(Deserialize)]
+struct EsploraOutspend {
+    spent: bool,
+}
+
+pub enum Backend {
+    Rpc {
+        client: Client,
+        url: String,
+        auth: Option<(String, Option<String>)>,
+    },
+    Esplora {
+        client: Client,
+        url: String,
+    },
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Rpc { .. } => "rpc",
+            Backend::Esplora { .. } => "esplora",
+        }
+    }
+
+    /// The output `outpoint` if it exists and is unspent, counting the mempool.
+    pub async fn unspent_output(&self, outpoint: &OutPoint) -> Result<Option<TxOut>, BackendError> {
+        match self {
+            Backend::Rpc { client, url, auth } => {
+                let params = json!([outpoint.txid.to_string(), outpoint.vout, true]);
+                let output: Option<RpcTxOut> =
+                    rpc_call(client, url, auth.as_ref(), "gettxout", params).await?;
+                output
+                    .map(|output| {
+                        Ok(TxOut {
+                            value: Amount::from_btc(output.value)?,
+                            script_pubkey: ScriptBuf::from_hex(&output.script_pubkey.hex)?,
+                        })
+                    })
+                    .transpose()
+            }
+            Backend::Esplora { client, url } => {
+                let tx: Option<EsploraTx> =
+                    esplora_get(client, &format!("{}/tx/{}", url, outpoint.txid)).await?;
+                let Some(output) =
+                    tx.and_then(|tx| tx.vout.into_iter().nth(outpoint.vout as usize))
+                else {
+                    return Ok(None);
+                };
+                let outspend: Option<EsploraOutspend> = esplora_get(
+                    client,
+                    &format!("{}/tx/{}/outspend/{}", url, outpoint.txid, outpoint.vout),
+                )
+                .await?;
+                if outspend.is_none_or(|outspend| outspend.spent) {
+                    return Ok(None);
+                }
+                Ok(Some(TxOut {
+                    value: Amount::from_sat(output.value),
+                    script_pubkey: ScriptBuf::from_hex(&output.scriptpubkey)?,
+                }))
+            }
+        }
+    }
+}
+
+async fn rpc_call<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    auth: Option<&(String, Option<String>)>,
+    method: &str,
+    params: Value,
+) -> Result<Option<T>, BackendError> {
+    let mut request = client.post(url).json(&json!({
+        "jsonrpc": "1.0",
+        "id": "signer-node",
+        "method": method,
+        "params": params,
+    }));
+    if let Some((user, password)) = auth {
+        request = request.basic_auth(user, password.as_ref());
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Err("RPC authentication failed; check --rpc-user and --rpc-password".into());
+    }
+    let response: RpcResponse<T> = response.json().await?;
+    match response.error {
+        Some(error) => {
+            Err(format!("RPC {} failed ({}): {}", method, error.code, error.message).into())
+        }
+        None => Ok(response.result),
+    }
+}
+
+/// `GET url` as JSON; `None` if Esplora does not know the object.
+async fn esplora_get<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+) -> Result<Option<T>, BackendError> {
+    let response = client.get(url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.json().await?))
+}
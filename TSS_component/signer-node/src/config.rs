@@ -0,0 +1,227 @@
+//! `signer-node.toml`: the identity, peers, database, listen address, TLS files, Bitcoin
+//! backend and payout policy of a signer node, in one file.
+//!
+//! Every setting maps to the environment variable the node reads through clap, so precedence
+//! is: command-line flag, then the process environment, then the config file, then the
+//! built-in default, as with the ZKP `bridge.toml`.
+
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Environment variable naming the config file when `--config` is not passed.
+pub const CONFIG_ENV: &str = "SIGNER_CONFIG";
+
+/// Contents of `signer-node.toml`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    /// `--party-id` / `PARTY_ID`.
+    pub party_id: Option<u16>,
+    /// Signers of the group: `--total` / `TOTAL`.
+    pub total: Option<u16>,
+    /// Signers needed to sign: `--threshold` / `THRESHOLD`.
+    pub threshold: Option<u16>,
+    /// gRPC address served: `--listen` / `SIGNER_LISTEN`.
+    pub listen: Option<String>,
+    /// sled database of the key share and nonces: `--db` / `SIGNER_DB`.
+    pub db: Option<PathBuf>,
+    /// The other signers of the group: `--peer` / `SIGNER_PEERS`.
+    pub peers: Vec<Peer>,
+    pub tls: TlsConfig,
+    pub bitcoin: BitcoinConfig,
+    pub policy: PolicyConfig,
+}
+
+/// PEM files of the gRPC links, which are TLS only.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// `--tls-cert` / `GRPC_TLS_CERT`.
+    pub cert: Option<PathBuf>,
+    /// `--tls-key` / `GRPC_TLS_KEY`.
+    pub key: Option<PathBuf>,
+    /// `--tls-ca` / `GRPC_TLS_CA`.
+    pub ca: Option<PathBuf>,
+    /// `--tls-client-ca` / `GRPC_TLS_CLIENT_CA`.
+    pub client_ca: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BitcoinConfig {
+    /// `--network` / `BITCOIN_NETWORK`.
+    pub network: Option<String>,
+    /// `--source` / `BITCOIN_SOURCE`.
+    pub source: Option<String>,
+    /// `--rpc-url` / `BITCOIN_RPC_URL`.
+    pub rpc_url: Option<String>,
+    /// `--rpc-user` / `BITCOIN_RPC_USER`.
+    pub rpc_user: Option<String>,
+    /// `--rpc-password` / `BITCOIN_RPC_PASSWORD`.
+    pub rpc_password: Option<String>,
+    /// `--esplora-url` / `ESPLORA_URL`.
+    pub esplora_url: Option<String>,
+}
+
+/// Limits on the payouts the node signs; the `--policy-*` flags of the coordinator.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// `POLICY_MAX_PAYOUT_SATS`.
+    pub max_payout_sats: Option<u64>,
+    /// `POLICY_PAYOUT_ALLOW`.
+    pub payout_allow: Vec<String>,
+    /// `POLICY_DENY`.
+    pub deny: Vec<String>,
+    /// `POLICY_PAYOUT_VOLUME_SATS`.
+    pub payout_volume_sats: Option<u64>,
+    /// `POLICY_EPOCH_SECS`.
+    pub epoch_secs: Option<u64>,
+    /// `POLICY_PAYOUT_WINDOW_SATS`.
+    pub payout_window_sats: Option<u64>,
+    /// `POLICY_PAYOUT_WINDOW_COUNT`.
+    pub payout_window_count: Option<u64>,
+    /// `POLICY_WINDOW_SECS`.
+    pub window_secs: Option<u64>,
+}
+
+/// Another signer of the group: its party id and gRPC endpoint, written `<party id>=<url>` on
+/// the command line.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Peer {
+    pub party_id: u16,
+    pub url: String,
+}
+
+impl FromStr for Peer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (party_id, url) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid peer {}, expected <party id>=<url>", s))?;
+        Ok(Peer {
+            party_id: party_id
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid party id {}: {}", party_id, e))?,
+            url: url.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.party_id, self.url)
+    }
+}
+
+impl NodeConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Settings present in the file, as `(environment variable, value)` pairs.
+    pub fn env_values(&self) -> Vec<(&'static str, String)> {
+        let list = |values: &[String]| match values.is_empty() {
+            true => None,
+            false => Some(values.join(",")),
+        };
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|path| path.display().to_string());
+        let peers: Vec<String> = self.peers.iter().map(Peer::to_string).collect();
+        let values = [
+            ("PARTY_ID", self.party_id.map(|id| id.to_string())),
+            ("TOTAL", self.total.map(|total| total.to_string())),
+            (
+                "THRESHOLD",
+                self.threshold.map(|threshold| threshold.to_string()),
+            ),
+            ("SIGNER_LISTEN", self.listen.clone()),
+            (
+                "SIGNER_DB",
+                self.db.as_ref().map(|path| path.display().to_string()),
+            ),
+            ("SIGNER_PEERS", list(&peers)),
+            ("GRPC_TLS_CERT", path(&self.tls.cert)),
+            ("GRPC_TLS_KEY", path(&self.tls.key)),
+            ("GRPC_TLS_CA", path(&self.tls.ca)),
+            ("GRPC_TLS_CLIENT_CA", path(&self.tls.client_ca)),
+            ("BITCOIN_NETWORK", self.bitcoin.network.clone()),
+            ("BITCOIN_SOURCE", self.bitcoin.source.clone()),
+            ("BITCOIN_RPC_URL", self.bitcoin.rpc_url.clone()),
+            ("BITCOIN_RPC_USER", self.bitcoin.rpc_user.clone()),
+            ("BITCOIN_RPC_PASSWORD", self.bitcoin.rpc_password.clone()),
+            ("ESPLORA_URL", self.bitcoin.esplora_url.clone()),
+            (
+                "POLICY_MAX_PAYOUT_SATS",
+                self.policy.max_payout_sats.map(|sats| sats.to_string()),
+            ),
+            ("POLICY_PAYOUT_ALLOW", list(&self.policy.payout_allow)),
+            ("POLICY_DENY", list(&self.policy.deny)),
+            (
+                "POLICY_PAYOUT_VOLUME_SATS",
+                self.policy.payout_volume_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_EPOCH_SECS",
+                self.policy.epoch_secs.map(|secs| secs.to_string()),
+            ),
+            (
+                "POLICY_PAYOUT_WINDOW_SATS",
+                self.policy.payout_window_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_PAYOUT_WINDOW_COUNT",
+                self.policy
+                    .payout_window_count
+                    .map(|count| count.to_string()),
+            ),
+            (
+                "POLICY_WINDOW_SECS",
+                self.policy.window_secs.map(|secs| secs.to_string()),
+            ),
+        ];
+        values
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+    }
+}
+
+/// Path given with `--config <path>` or `--config=<path>`, else `SIGNER_CONFIG`.
+fn config_path() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os(CONFIG_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Loads the config file, if one is given, into the environment variables clap reads, without
+/// replacing variables that are already set. Must run before `Args::parse`. Exits if the file
+/// cannot be read or parsed.
+pub fn apply_config_file() {
+    let Some(path) = config_path() else {
+        return;
+    };
+    let config = NodeConfig::load(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to load config {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    for (name, value) in config.env_values() {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+}
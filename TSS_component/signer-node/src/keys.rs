@@ -0,0 +1,333 @@
+//! The signer's share of the group key and its FROST rounds, kept in sled with the layout of
+//! the Python signer's `rust_tss` library (`r1_`, `r2_`, `keypkg_`, `pubkeypkg_` and
+//! `nonces_`, each followed by the hex identifier), so a `/state` volume written by it, or
+//! restored by `signer/backup.py`, is read as is.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use frost_secp256k1_tr::keys::dkg::{self, round1 as dkg_round1, round2 as dkg_round2};
+use frost_secp256k1_tr::keys::{KeyPackage, PublicKeyPackage};
+use frost_secp256k1_tr::round1::{self, SigningCommitments, SigningNonces};
+use frost_secp256k1_tr::{round2, Identifier, SigningPackage};
+use rand::rngs::OsRng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use tonic::Status;
+
+/// Why a round failed, reported to the coordinator as the matching gRPC status.
+#[derive(Debug)]
+pub enum KeyError {
+    /// The request is malformed: a package, commitment or message does not decode.
+    Invalid(String),
+    /// The signer is not in the state the round needs, e.g. signing before the DKG.
+    State(String),
+    /// The database failed.
+    Storage(String),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Invalid(e) | KeyError::State(e) | KeyError::Storage(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<sled::Error> for KeyError {
+    fn from(e: sled::Error) -> Self {
+        KeyError::Storage(format!("Database error: {}", e))
+    }
+}
+
+impl From<KeyError> for Status {
+    fn from(e: KeyError) -> Self {
+        match e {
+            KeyError::Invalid(e) => Status::invalid_argument(e),
+            KeyError::State(e) => Status::failed_precondition(e),
+            KeyError::Storage(e) => Status::internal(e),
+        }
+    }
+}
+
+pub fn identifier_hex(id: Identifier) -> String {
+    hex::encode(id.serialize())
+}
+
+pub fn identifier(id_hex: &str) -> Result<Identifier, KeyError> {
+    hex::decode(id_hex)
+        .ok()
+        .and_then(|bytes| Identifier::deserialize(&bytes).ok())
+        .ok_or_else(|| KeyError::Invalid(format!("Invalid identifier {}", id_hex)))
+}
+
+/// Identifier of the signer with `party_id`.
+pub fn party_identifier(party_id: u16) -> Result<Identifier, KeyError> {
+    Identifier::try_from(party_id)
+        .map_err(|e| KeyError::Invalid(format!("Invalid party id {}: {}", party_id, e)))
+}
+
+fn decode(what: &str, hex_str: &str) -> Result<Vec<u8>, KeyError> {
+    hex::decode(hex_str).map_err(|e| KeyError::Invalid(format!("Invalid {} hex: {}", what, e)))
+}
+
+/// x-only group key of `pubkp`.
+pub fn group_key(pubkp: &PublicKeyPackage) -> Result<XOnlyPublicKey, KeyError> {
+    let bytes = pubkp
+        .verifying_key()
+        .serialize()
+        .map_err(|e| KeyError::State(format!("Serialize verifying key failed: {}", e)))?;
+    let x_only = match bytes.len() {
+        33 => &bytes[1..],
+        _ => &bytes[..],
+    };
+    XOnlyPublicKey::from_slice(x_only)
+        .map_err(|e| KeyError::State(format!("Invalid group key: {}", e)))
+}
+
+pub struct KeyStore {
+    db: sled::Db,
+    id: Identifier,
+    id_hex: String,
+}
+
+impl KeyStore {
+    /// Opens the database at `path` for the signer with `party_id`, creating it if needed.
+    pub fn open(path: &Path, party_id: u16) -> Result<Self, Box<dyn std::error::Error>> {
+        let id = party_identifier(party_id)?;
+        Ok(KeyStore {
+            db: sled::open(path)?,
+            id,
+            id_hex: identifier_hex(id),
+        })
+    }
+
+    pub fn id(&self) -> Identifier {
+        self.id
+    }
+
+    pub fn id_hex(&self) -> &str {
+        &self.id_hex
+    }
+
+    /// Tree of the database the policy hooks record signed payouts in.
+    pub fn tree(&self, name: &str) -> Result<sled::Tree, KeyError> {
+        Ok(self.db.open_tree(name)?)
+    }
+
+    fn key(&self, prefix: &str) -> String {
+        format!("{}_{}", prefix, self.id_hex)
+    }
+
+    fn get(&self, prefix: &str) -> Result<Option<sled::IVec>, KeyError> {
+        Ok(self.db.get(self.key(prefix))?)
+    }
+
+    fn put(&self, prefix: &str, bytes: Vec<u8>) -> Result<(), KeyError> {
+        self.db.insert(self.key(prefix), bytes)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn load<T>(
+        &self,
+        prefix: &str,
+        what: &str,
+        deserialize: impl Fn(&[u8]) -> Result<T, frost_secp256k1_tr::Error>,
+    ) -> Result<Option<T>, KeyError> {
+        self.get(prefix)?
+            .map(|bytes| {
+                deserialize(&bytes)
+                    .map_err(|e| KeyError::Storage(format!("Corrupt {}: {}", what, e)))
+            })
+            .transpose()
+    }
+
+    pub fn key_package(&self) -> Result<KeyPackage, KeyError> {
+        self.load("keypkg", "key package", KeyPackage::deserialize)?
+            .ok_or_else(|| KeyError::State("No key share, run the DKG first".into()))
+    }
+
+    pub fn public_key_package(&self) -> Result<Option<PublicKeyPackage>, KeyError> {
+        self.load(
+            "pubkeypkg",
+            "public key package",
+            PublicKeyPackage::deserialize,
+        )
+    }
+
+    /// x-only group key and public key package, hex, once the DKG has completed.
+    pub fn status(&self) -> Result<Option<(String, String)>, KeyError> {
+        if self.get("keypkg")?.is_none() {
+            return Ok(None);
+        }
+        let Some(pubkp) = self.public_key_package()? else {
+            return Ok(None);
+        };
+        let pubkp_bytes = pubkp
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize public key package failed: {}", e)))?;
+        Ok(Some((
+            group_key(&pubkp)?.to_string(),
+            hex::encode(pubkp_bytes),
+        )))
+    }
+
+    /// DKG part 1; returns the broadcast package, hex.
+    pub fn dkg_round1(&self, total: u16, threshold: u16) -> Result<String, KeyError> {
+        let (secret, package) = dkg::part1(self.id, total, threshold, OsRng)
+            .map_err(|e| KeyError::Invalid(format!("DKG part 1 failed: {}", e)))?;
+        let secret = secret
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize round-1 secret failed: {}", e)))?;
+        self.put("r1", secret)?;
+        let package = package
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize round-1 package failed: {}", e)))?;
+        Ok(hex::encode(package))
+    }
+
+    /// DKG part 2 over the other signers' round-1 packages, `(id hex, package hex)`; returns
+    /// one round-2 package per recipient, the same way.
+    pub fn dkg_round2(
+        &self,
+        round1_packages: &[(String, String)],
+    ) -> Result<Vec<(String, String)>, KeyError> {
+        let secret = self
+            .load(
+                "r1",
+                "round-1 secret",
+                dkg_round1::SecretPackage::deserialize,
+            )?
+            .ok_or_else(|| KeyError::State("No round-1 secret, run DKG round 1 first".into()))?;
+        let round1 = round1_map(round1_packages)?;
+        let (secret, packages) = dkg::part2(secret, &round1)
+            .map_err(|e| KeyError::Invalid(format!("DKG part 2 failed: {}", e)))?;
+        let secret = secret
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize round-2 secret failed: {}", e)))?;
+        self.put("r2", secret)?;
+        packages
+            .into_iter()
+            .map(|(id, package)| {
+                let bytes = package.serialize().map_err(|e| {
+                    KeyError::State(format!("Serialize round-2 package failed: {}", e))
+                })?;
+                Ok((identifier_hex(id), hex::encode(bytes)))
+            })
+            .collect()
+    }
+
+    /// DKG part 3; persists the key share and returns the public key package and the x-only
+    /// group key, hex.
+    pub fn dkg_round3(
+        &self,
+        round1_packages: &[(String, String)],
+        round2_packages: &[(String, String)],
+    ) -> Result<(String, String), KeyError> {
+        let secret = self
+            .load(
+                "r2",
+                "round-2 secret",
+                dkg_round2::SecretPackage::deserialize,
+            )?
+            .ok_or_else(|| KeyError::State("No round-2 secret, run DKG round 2 first".into()))?;
+        let round1 = round1_map(round1_packages)?;
+        let mut round2 = BTreeMap::new();
+        for (id_hex, pkg_hex) in round2_packages {
+            let package =
+                dkg_round2::Package::deserialize(&decode("package", pkg_hex)?).map_err(|e| {
+                    KeyError::Invalid(format!("Invalid round-2 package of {}: {}", id_hex, e))
+                })?;
+            round2.insert(identifier(id_hex)?, package);
+        }
+        let (key_package, pubkp) = dkg::part3(&secret, &round1, &round2)
+            .map_err(|e| KeyError::Invalid(format!("DKG part 3 failed: {}", e)))?;
+        let key_bytes = key_package
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize key package failed: {}", e)))?;
+        let pubkp_bytes = pubkp
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize public key package failed: {}", e)))?;
+        self.put("keypkg", key_bytes)?;
+        self.put("pubkeypkg", pubkp_bytes.clone())?;
+        Ok((hex::encode(pubkp_bytes), group_key(&pubkp)?.to_string()))
+    }
+
+    /// Signing round 1: draws fresh nonces, replacing those of the last round 1, and returns
+    /// their commitment, hex.
+    pub fn sign_round1(&self) -> Result<String, KeyError> {
+        let key_package = self.key_package()?;
+        let (nonces, commitments) = round1::commit(key_package.signing_share(), &mut OsRng);
+        let nonces = nonces
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize nonces failed: {}", e)))?;
+        self.db.remove(self.key("nonce_use"))?;
+        self.put("nonces", nonces)?;
+        let commitments = commitments
+            .serialize()
+            .map_err(|e| KeyError::State(format!("Serialize commitments failed: {}", e)))?;
+        Ok(hex::encode(commitments))
+    }
+
+    /// Signing round 2 over `message` with the nonces of the last round 1, for the group key
+    /// tweaked with `merkle_root`. The nonces sign one message only: repeating the round for
+    /// the same message and commitments, as a retrying coordinator does, is allowed, signing
+    /// another one needs a new round 1.
+    pub fn sign_round2(
+        &self,
+        message: &[u8],
+        commitments: &[(String, String)],
+        merkle_root: Option<&[u8]>,
+    ) -> Result<String, KeyError> {
+        let key_package = self.key_package()?;
+        let nonces = self
+            .load("nonces", "nonces", SigningNonces::deserialize)?
+            .ok_or_else(|| KeyError::State("No nonces, run signing round 1 first".into()))?;
+
+        let mut engine = sha256::Hash::engine();
+        engine.input(message);
+        engine.input(merkle_root.unwrap_or_default());
+        let mut commitments_map = BTreeMap::new();
+        for (id_hex, commitment_hex) in commitments {
+            let bytes = decode("commitment", commitment_hex)?;
+            engine.input(id_hex.as_bytes());
+            engine.input(&bytes);
+            let commitment = SigningCommitments::deserialize(&bytes).map_err(|e| {
+                KeyError::Invalid(format!("Invalid commitment of {}: {}", id_hex, e))
+            })?;
+            commitments_map.insert(identifier(id_hex)?, commitment);
+        }
+        let nonce_use = sha256::Hash::from_engine(engine).to_byte_array();
+        match self.get("nonce_use")? {
+            Some(used) if used.as_ref() != nonce_use => {
+                return Err(KeyError::State(
+                    "The nonces of the last round 1 already signed another message".into(),
+                ))
+            }
+            Some(_) => {}
+            None => self.put("nonce_use", nonce_use.to_vec())?,
+        }
+
+        let signing_package = SigningPackage::new(commitments_map, message);
+        let share = round2::sign_with_tweak(&signing_package, &nonces, &key_package, merkle_root)
+            .map_err(|e| KeyError::Invalid(format!("Signing failed: {}", e)))?;
+        Ok(hex::encode(share.serialize()))
+    }
+}
+
+fn round1_map(
+    packages: &[(String, String)],
+) -> Result<BTreeMap<Identifier, dkg_round1::Package>, KeyError> {
+    let mut map = BTreeMap::new();
+    for (id_hex, pkg_hex) in packages {
+        let package =
+            dkg_round1::Package::deserialize(&decode("package", pkg_hex)?).map_err(|e| {
+                KeyError::Invalid(format!("Invalid round-1 package of {}: {}", id_hex, e))
+            })?;
+        map.insert(identifier(id_hex)?, package);
+    }
+    Ok(map)
+}
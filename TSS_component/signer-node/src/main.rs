@@ -0,0 +1,230 @@
+//! Signer node: one binary serving the `Signer` gRPC service the coordinator drives, holding the
+//! signer's key share in a local sled database, and checking what it is asked to sign on its
+//! own before producing a signature share.
+//!
+//! A transaction input is only signed if the message is its sighash, its payouts pass the
+//! node's `--policy-*` hooks, the same ones the coordinator enforces, and, with a Bitcoin
+//! backend, the outputs it spends exist unspent with the values the sighash commits to. So a
+//! compromised coordinator cannot have the group sign a payout the signers' own policy denies.
+//!
+//! Settings come from flags, the environment, or `signer-node.toml` (`--config`, or
+//! `SIGNER_CONFIG`).
+
+// tonic::Status is the error of every call, large as it is
+#![allow(clippy::result_large_err)]
+
+// Usage Example:
+// cargo run --release --bin signer-node -- --config ./signer-node.toml
+// cargo run --release --bin signer-node -- --party-id 1 --total 3 --threshold 2 --db ./signer1-db --peer 2=https://localhost:50052,3=https://localhost:50053 --tls-cert ./certs/signer1.pem --tls-key ./certs/signer1.key --tls-ca ./certs/ca.pem --tls-client-ca ./certs/ca.pem
+// cargo run --release --bin signer-node -- --config ./signer-node.toml --source esplora --policy-max-payout-sats 10000000
+mod backend;
+mod config;
+mod keys;
+#[path = "../../tss-coordinator/src/policy.rs"]
+mod policy;
+mod service;
+mod telemetry;
+mod tls;
+mod validate;
+
+pub mod proto {
+    tonic::include_proto!("tss");
+}
+
+use backend::BackendArgs;
+use clap::Parser;
+use config::{Peer, CONFIG_ENV};
+use keys::KeyStore;
+use policy::{PolicyArgs, PolicyEngine};
+use proto::signer_client::SignerClient;
+use proto::signer_server::SignerServer;
+use service::SignerService;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tls::TlsArgs;
+use tonic::transport::{ClientTlsConfig, Endpoint, Server};
+use tracing::{error, info, warn};
+use validate::Validator;
+
+/// Signer node of the bridge's FROST threshold group.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Config file whose settings apply where no flag or environment variable is given.
+    #[clap(long, env = CONFIG_ENV)]
+    config: Option<PathBuf>,
+    /// Party id of this signer, from 1; its FROST identifier.
+    #[clap(long, env = "PARTY_ID")]
+    party_id: u16,
+    /// Signers of the group.
+    #[clap(long, env = "TOTAL")]
+    total: u16,
+    /// Signers needed to sign.
+    #[clap(long, env = "THRESHOLD")]
+    threshold: u16,
+    /// Address the gRPC service listens on.
+    #[clap(long, env = "SIGNER_LISTEN", default_value = "0.0.0.0:50051")]
+    listen: SocketAddr,
+    /// sled database of the key share and nonces; `/state/nonces_db` is where the Python
+    /// signer kept them.
+    #[clap(long, env = "SIGNER_DB", default_value = "/state/nonces_db")]
+    db: PathBuf,
+    /// The other signers, as `<party id>=<https gRPC url>`; repeat the flag or separate them
+    /// with commas. When set, packages and commitments from any other signer are refused, and
+    /// the peers are asked for their group key on start.
+    #[clap(long = "peer", env = "SIGNER_PEERS", value_delimiter = ',')]
+    peers: Vec<Peer>,
+    #[clap(flatten)]
+    tls: TlsArgs,
+    #[clap(flatten)]
+    bitcoin: BackendArgs,
+    #[clap(flatten)]
+    policy: PolicyArgs,
+}
+
+impl Args {
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        if self.party_id == 0 {
+            return Err("--party-id starts at 1".into());
+        }
+        if self.threshold < 2 || self.threshold > self.total {
+            return Err(
+                format!("--threshold must be between 2 and --total ({})", self.total).into(),
+            );
+        }
+        if !self.peers.is_empty() && self.peers.len() + 1 != self.total as usize {
+            return Err(format!(
+                "{} peers configured for a group of {} signers",
+                self.peers.len(),
+                self.total
+            )
+            .into());
+        }
+        if let Some(peer) = self
+            .peers
+            .iter()
+            .find(|peer| peer.party_id == self.party_id)
+        {
+            return Err(format!("Peer {} has this signer's own party id", peer).into());
+        }
+        // tonic would connect to an `http` endpoint in plain text despite the TLS config
+        if let Some(peer) = self
+            .peers
+            .iter()
+            .find(|peer| !peer.url.starts_with("https://"))
+        {
+            return Err(format!("Peer {} is not an https endpoint", peer).into());
+        }
+        Ok(())
+    }
+}
+
+/// Asks every peer for its group key and logs whether it is this signer's; a peer that is
+/// down or holds another key is only warned about, as the coordinator runs the DKG.
+async fn check_peers(peers: Vec<Peer>, tls: ClientTlsConfig, own_key: Option<String>) {
+    for peer in peers {
+        let status = async {
+            let channel = Endpoint::from_shared(peer.url.clone())?
+                .tls_config(tls.clone())?
+                .connect_timeout(Duration::from_secs(5))
+                .timeout(Duration::from_secs(10))
+                .connect()
+                .await?;
+            let status = SignerClient::new(channel)
+                .dkg_status(proto::DkgStatusRequest {})
+                .await?
+                .into_inner();
+            Ok::<_, Box<dyn Error + Send + Sync>>(status)
+        }
+        .await;
+        match (status, &own_key) {
+            (Err(e), _) => warn!(peer = %peer, error = %e, "Peer unreachable"),
+            (Ok(status), Some(own_key)) if status.verify_key_hex == *own_key => {
+                info!(peer = %peer, "Peer holds a share of the group key")
+            }
+            (Ok(status), Some(_)) if status.has_keys => {
+                warn!(peer = %peer, group_key = status.verify_key_hex, "Peer holds a share of another group key")
+            }
+            (Ok(status), _) if status.has_keys => {
+                warn!(peer = %peer, "Peer holds a key share, this signer none")
+            }
+            (Ok(_), _) => info!(peer = %peer, "Peer holds no key share yet"),
+        }
+    }
+}
+
+async fn serve(args: Args) -> Result<(), Box<dyn Error>> {
+    args.check()?;
+    let keys = KeyStore::open(&args.db, args.party_id)
+        .map_err(|e| format!("Failed to open {}: {}", args.db.display(), e))?;
+    let policy = PolicyEngine::new(
+        &args.policy,
+        keys.tree("policy_volume")?,
+        keys.tree("policy_window")?,
+        keys.tree("policy_signed")?,
+    );
+    let backend = args.bitcoin.connect();
+    let own_key = keys.status()?.map(|(verify_key_hex, _)| verify_key_hex);
+    info!(
+        party_id = args.party_id,
+        id_hex = keys.id_hex(),
+        total = args.total,
+        threshold = args.threshold,
+        group_key = own_key.as_deref().unwrap_or("none"),
+        policy = !policy.is_empty(),
+        backend = backend.as_ref().map_or("none", |backend| backend.name()),
+        "Signer node starting"
+    );
+    if policy.is_empty() {
+        warn!("No payout policy set, so the coordinator's bare messages are signed unchecked");
+    }
+    let tls = args.tls.server_config()?;
+    tokio::spawn(check_peers(
+        args.peers.clone(),
+        args.tls.client_config()?,
+        own_key,
+    ));
+
+    let service = SignerService::new(
+        args.party_id,
+        args.total,
+        args.threshold,
+        keys,
+        &args.peers,
+        Validator {
+            policy,
+            backend,
+            network: args.bitcoin.network,
+        },
+    )?;
+    info!(
+        listen = %args.listen,
+        client_certificates = args.tls.tls_client_ca.is_some(),
+        "gRPC signer listening over TLS"
+    );
+    Server::builder()
+        .tls_config(tls)?
+        .add_service(SignerServer::new(service))
+        .serve_with_shutdown(args.listen, async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    config::apply_config_file();
+    let args = Args::parse();
+    let provider = telemetry::init(args.party_id);
+    let result = serve(args).await;
+    if let Err(e) = &result {
+        error!(error = %e, "Signer node failed");
+    }
+    telemetry::shutdown(provider).await;
+    if result.is_err() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,233 @@
+//! The `Signer` gRPC service of `proto/tss.proto`, driven by the coordinator.
+
+use crate::config::Peer;
+use crate::keys::{self, KeyError, KeyStore};
+use crate::proto::signer_server::Signer;
+use crate::proto::*;
+use crate::telemetry;
+use crate::validate::Validator;
+use frost_secp256k1_tr::Identifier;
+use std::collections::BTreeSet;
+use std::future::Future;
+use tonic::{Request, Response, Status};
+use tracing::{error, field, info, info_span, Instrument};
+
+pub struct SignerService {
+    pub party_id: u16,
+    pub total: u16,
+    pub threshold: u16,
+    pub keys: KeyStore,
+    /// Identifiers of the configured peers; empty to accept packages from any signer.
+    pub peers: BTreeSet<Identifier>,
+    pub validator: Validator,
+}
+
+impl SignerService {
+    pub fn new(
+        party_id: u16,
+        total: u16,
+        threshold: u16,
+        keys: KeyStore,
+        peers: &[Peer],
+        validator: Validator,
+    ) -> Result<Self, KeyError> {
+        let peers = peers
+            .iter()
+            .map(|peer| keys::party_identifier(peer.party_id))
+            .collect::<Result<_, _>>()?;
+        Ok(SignerService {
+            party_id,
+            total,
+            threshold,
+            keys,
+            peers,
+            validator,
+        })
+    }
+
+    /// Runs a call in a span joining the coordinator's trace, logging its outcome.
+    async fn run<T>(
+        &self,
+        what: &str,
+        session_id: &str,
+        metadata: &tonic::metadata::MetadataMap,
+        call: impl Future<Output = Result<T, Status>>,
+    ) -> Result<Response<T>, Status> {
+        let span = info_span!(
+            "signer_call",
+            call = what,
+            party_id = self.party_id,
+            session_id,
+            swap_id = field::Empty
+        );
+        let swap_id = telemetry::incoming(metadata, &span);
+        if !swap_id.is_empty() {
+            span.record("swap_id", swap_id.as_str());
+        }
+        async {
+            info!("{} for session {}", what, session_id);
+            call.await.map(Response::new).inspect_err(
+                |status| error!(code = ?status.code(), "{} failed: {}", what, status.message()),
+            )
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// `(id hex, package hex)` pairs of `packages`, refusing any from outside the group.
+    fn packages(&self, packages: &[Package]) -> Result<Vec<(String, String)>, Status> {
+        if packages.is_empty() {
+            return Err(Status::invalid_argument("No packages"));
+        }
+        for package in packages {
+            let id = keys::identifier(&package.id_hex)?;
+            if id != self.keys.id() && !self.peers.is_empty() && !self.peers.contains(&id) {
+                return Err(Status::permission_denied(format!(
+                    "{} is not a configured peer",
+                    package.id_hex
+                )));
+            }
+        }
+        Ok(packages
+            .iter()
+            .map(|package| (package.id_hex.clone(), package.pkg_hex.clone()))
+            .collect())
+    }
+
+    async fn sign(&self, request: &SignRound2Request) -> Result<SignRound2Response, Status> {
+        let message = hex::decode(&request.message_hex)
+            .map_err(|e| Status::invalid_argument(format!("Invalid message hex: {}", e)))?;
+        let merkle_root = match request.merkle_root_hex.as_str() {
+            "" => None,
+            merkle_root_hex => Some(hex::decode(merkle_root_hex).map_err(|e| {
+                Status::invalid_argument(format!("Invalid merkle root hex: {}", e))
+            })?),
+        };
+        let commitments = self.packages(&request.commitments)?;
+        let pubkp = self
+            .keys
+            .public_key_package()?
+            .ok_or_else(|| Status::failed_precondition("No key share, run the DKG first"))?;
+        let payouts = self
+            .validator
+            .check(
+                &message,
+                keys::group_key(&pubkp)?,
+                merkle_root.as_deref(),
+                request.context.as_ref(),
+            )
+            .await?;
+        let signature_share_hex =
+            self.keys
+                .sign_round2(&message, &commitments, merkle_root.as_deref())?;
+        self.validator
+            .policy
+            .record(&payouts)
+            .map_err(|e| Status::internal(format!("Failed to record the payouts: {}", e)))?;
+        Ok(SignRound2Response {
+            id_hex: self.keys.id_hex().to_string(),
+            signature_share_hex,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Signer for SignerService {
+    async fn dkg_status(
+        &self,
+        request: Request<DkgStatusRequest>,
+    ) -> Result<Response<DkgStatusResponse>, Status> {
+        self.run("DKG status", "-", request.metadata(), async {
+            let (verify_key_hex, pubkp_hex) = self.keys.status()?.unzip();
+            Ok(DkgStatusResponse {
+                has_keys: pubkp_hex.is_some(),
+                id_hex: self.keys.id_hex().to_string(),
+                verify_key_hex: verify_key_hex.unwrap_or_default(),
+                pubkp_hex: pubkp_hex.unwrap_or_default(),
+            })
+        })
+        .await
+    }
+
+    async fn dkg_round1(
+        &self,
+        request: Request<DkgRound1Request>,
+    ) -> Result<Response<DkgRound1Response>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        self.run("DKG round 1", &request.session_id, &metadata, async {
+            Ok(DkgRound1Response {
+                id_hex: self.keys.id_hex().to_string(),
+                pkg_hex: self.keys.dkg_round1(self.total, self.threshold)?,
+            })
+        })
+        .await
+    }
+
+    async fn dkg_round2(
+        &self,
+        request: Request<DkgRound2Request>,
+    ) -> Result<Response<DkgRound2Response>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        self.run("DKG round 2", &request.session_id, &metadata, async {
+            let round1 = self.packages(&request.round1_packages)?;
+            let round2_packages = self
+                .keys
+                .dkg_round2(&round1)?
+                .into_iter()
+                .map(|(id_hex, pkg_hex)| Package { id_hex, pkg_hex })
+                .collect();
+            Ok(DkgRound2Response {
+                id_hex: self.keys.id_hex().to_string(),
+                round2_packages,
+            })
+        })
+        .await
+    }
+
+    async fn dkg_round3(
+        &self,
+        request: Request<DkgRound3Request>,
+    ) -> Result<Response<DkgRound3Response>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        self.run("DKG round 3", &request.session_id, &metadata, async {
+            let round1 = self.packages(&request.round1_packages)?;
+            let round2 = self.packages(&request.round2_packages)?;
+            let (pubkp_hex, verify_key_hex) = self.keys.dkg_round3(&round1, &round2)?;
+            info!(verify_key_hex, "Key share persisted");
+            Ok(DkgRound3Response {
+                id_hex: self.keys.id_hex().to_string(),
+                verify_key_hex,
+                pubkp_hex,
+            })
+        })
+        .await
+    }
+
+    async fn sign_round1(
+        &self,
+        request: Request<SignRound1Request>,
+    ) -> Result<Response<SignRound1Response>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        self.run("Signing round 1", &request.session_id, &metadata, async {
+            Ok(SignRound1Response {
+                id_hex: self.keys.id_hex().to_string(),
+                commitment_hex: self.keys.sign_round1()?,
+            })
+        })
+        .await
+    }
+
+    async fn sign_round2(
+        &self,
+        request: Request<SignRound2Request>,
+    ) -> Result<Response<SignRound2Response>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        self.run(
+            "Signing round 2",
+            &request.session_id,
+            &metadata,
+            self.sign(&request),
+        )
+        .await
+    }
+}
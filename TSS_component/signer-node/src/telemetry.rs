@@ -0,0 +1,107 @@
+//! OpenTelemetry tracing of the signer node. With `OTEL_EXPORTER_OTLP_ENDPOINT` set, every
+//! call the coordinator makes is exported as a span of the service `signer-<PARTY_ID>` (or
+//! `OTEL_SERVICE_NAME`), in the coordinator's trace: its `traceparent` comes in the call
+//! metadata, along with the swap ids of the run.
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::error::Error;
+use tonic::metadata::{KeyRef, MetadataMap};
+use tracing::level_filters::LevelFilter;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+/// gRPC metadata key of the swap ids, comma-separated, as the coordinator sends them.
+const SWAP_ID_METADATA: &str = "swap-id";
+
+fn provider(party_id: u16) -> Result<TracerProvider, Box<dyn Error>> {
+    let exporter = SpanExporter::builder().with_tonic().build()?;
+    let mut resource = Resource::default();
+    if std::env::var_os(SERVICE_NAME_ENV).is_none() {
+        resource = resource.merge(&Resource::new([KeyValue::new(
+            "service.name",
+            format!("signer-{}", party_id),
+        )]));
+    }
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource)
+        .build())
+}
+
+/// Installs the subscriber logging to stderr, and exporting the spans when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set. Returns the provider to shut down before exiting.
+pub fn init(party_id: u16) -> Option<TracerProvider> {
+    let provider = match std::env::var_os(OTLP_ENDPOINT_ENV).map(|_| provider(party_id)) {
+        Some(Ok(provider)) => Some(provider),
+        Some(Err(e)) => {
+            eprintln!("OTLP export disabled: {}", e);
+            None
+        }
+        None => None,
+    };
+    if provider.is_some() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+    }
+    let otlp = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("signer")));
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(otlp)
+        .with(LevelFilter::INFO)
+        .init();
+    provider
+}
+
+/// Exports the spans still batched and stops the exporter.
+pub async fn shutdown(provider: Option<TracerProvider>) {
+    let Some(provider) = provider else {
+        return;
+    };
+    // Shutting down blocks until the batch task, on this runtime, has exported
+    let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+    if let Ok(Err(e)) = result {
+        eprintln!("Failed to export spans: {}", e);
+    }
+}
+
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|key| match key {
+                KeyRef::Ascii(key) => Some(key.as_str()),
+                KeyRef::Binary(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Makes `span` part of the caller's trace, and returns the swap ids the call is for.
+pub fn incoming(metadata: &MetadataMap, span: &Span) -> String {
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&MetadataExtractor(metadata))
+    });
+    span.set_parent(parent);
+    metadata
+        .get(SWAP_ID_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
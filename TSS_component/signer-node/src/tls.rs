@@ -0,0 +1,53 @@
+//! TLS of the node's gRPC links. The service is only served over TLS, with `--tls-cert` and
+//! `--tls-key`; with `--tls-client-ca`, clients without a certificate chaining to it are
+//! refused (mTLS). The peers are reached at `https` endpoints whose certificates chain to
+//! `--tls-ca`, presenting the node's own certificate.
+
+use clap::Args;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+
+#[derive(Args, Debug)]
+pub struct TlsArgs {
+    /// PEM certificate the node presents, to its clients and to its peers.
+    #[clap(long, env = "GRPC_TLS_CERT")]
+    pub tls_cert: PathBuf,
+    /// PEM key of `--tls-cert`.
+    #[clap(long, env = "GRPC_TLS_KEY")]
+    pub tls_key: PathBuf,
+    /// PEM CA certificate the peers' certificates must chain to.
+    #[clap(long, env = "GRPC_TLS_CA")]
+    pub tls_ca: PathBuf,
+    /// PEM CA certificate the clients' certificates must chain to; clients without one are
+    /// refused when set.
+    #[clap(long, env = "GRPC_TLS_CLIENT_CA")]
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e).into())
+}
+
+impl TlsArgs {
+    fn identity(&self) -> Result<Identity, Box<dyn Error>> {
+        Ok(Identity::from_pem(
+            read(&self.tls_cert)?,
+            read(&self.tls_key)?,
+        ))
+    }
+
+    pub fn server_config(&self) -> Result<ServerTlsConfig, Box<dyn Error>> {
+        let mut config = ServerTlsConfig::new().identity(self.identity()?);
+        if let Some(client_ca) = &self.tls_client_ca {
+            config = config.client_ca_root(Certificate::from_pem(read(client_ca)?));
+        }
+        Ok(config)
+    }
+
+    pub fn client_config(&self) -> Result<ClientTlsConfig, Box<dyn Error>> {
+        Ok(ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(read(&self.tls_ca)?))
+            .identity(self.identity()?))
+    }
+}
@@ -0,0 +1,201 @@
+//! Checks a signer runs on a message before signing it, from the context the coordinator sends
+//! with it. A transaction input's sighash is recomputed, the transaction's payouts are checked
+//! against the node's own policy hooks, and the outputs it spends against the Bitcoin backend;
+//! a tagged message is rehashed from its preimage. A bare message is only signed without a
+//! policy, as its payouts cannot be checked.
+
+use crate::backend::Backend;
+use crate::policy::{Payout, PolicyEngine};
+use crate::proto::signing_context::Kind;
+use crate::proto::{SigningContext, TaggedMessage, TransactionInput};
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::TapNodeHash;
+use bitcoin::{Address, Amount, Network, ScriptBuf, Transaction, TxOut};
+use tonic::Status;
+use tracing::info;
+
+/// Tags of the messages signed besides transactions: a proof's attestation, and a pause or
+/// resume notice. Must match `ATTESTATION_TAG` and `PAUSE_TAG` of the coordinator.
+const TAGS: [&str; 2] = ["ZKBTC/attestation", "ZKBTC/pause"];
+
+pub struct Validator {
+    pub policy: PolicyEngine,
+    pub backend: Option<Backend>,
+    pub network: Network,
+}
+
+impl Validator {
+    /// Checks that `message` is what `context` says it is, for the group key tweaked with
+    /// `merkle_root`, and that it may be signed. Returns the payouts to record once it is.
+    pub async fn check(
+        &self,
+        message: &[u8],
+        group_key: XOnlyPublicKey,
+        merkle_root: Option<&[u8]>,
+        context: Option<&SigningContext>,
+    ) -> Result<Vec<Payout>, Status> {
+        match context.and_then(|context| context.kind.as_ref()) {
+            Some(Kind::Transaction(input)) => {
+                let merkle_root = merkle_root
+                    .map(|root| {
+                        <[u8; 32]>::try_from(root)
+                            .map(TapNodeHash::from_byte_array)
+                            .map_err(|_| Status::invalid_argument("Invalid merkle root"))
+                    })
+                    .transpose()?;
+                let spent_spk =
+                    ScriptBuf::new_p2tr(&Secp256k1::verification_only(), group_key, merkle_root);
+                self.check_transaction(message, input, &spent_spk).await
+            }
+            Some(Kind::Tagged(tagged)) => {
+                check_tagged(message, tagged)?;
+                Ok(Vec::new())
+            }
+            None if self.policy.is_empty() => Ok(Vec::new()),
+            None => Err(Status::permission_denied(
+                "A payout policy is set, so only messages with their context are signed",
+            )),
+        }
+    }
+
+    async fn check_transaction(
+        &self,
+        message: &[u8],
+        input: &TransactionInput,
+        spent_spk: &ScriptBuf,
+    ) -> Result<Vec<Payout>, Status> {
+        let tx: Transaction = hex::decode(&input.tx_hex)
+            .ok()
+            .and_then(|bytes| deserialize(&bytes).ok())
+            .ok_or_else(|| Status::invalid_argument("Invalid transaction"))?;
+        if input.prevout_sats.len() != tx.input.len() {
+            return Err(Status::invalid_argument(format!(
+                "Got {} prevout values for {} inputs",
+                input.prevout_sats.len(),
+                tx.input.len()
+            )));
+        }
+        let index = input.input_index as usize;
+        if index >= tx.input.len() {
+            return Err(Status::invalid_argument(format!(
+                "No input {} in a transaction of {} inputs",
+                index,
+                tx.input.len()
+            )));
+        }
+
+        // The message must be the sighash of that input, spending outputs of the group key
+        let prevouts: Vec<TxOut> = input
+            .prevout_sats
+            .iter()
+            .map(|&sats| TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: spent_spk.clone(),
+            })
+            .collect();
+        let sighash = SighashCache::new(&tx)
+            .taproot_key_spend_signature_hash(
+                index,
+                &Prevouts::All(&prevouts),
+                TapSighashType::Default,
+            )
+            .map_err(|e| Status::invalid_argument(format!("Sighash failed: {}", e)))?;
+        if sighash.as_byte_array()[..] != *message {
+            return Err(Status::invalid_argument(format!(
+                "The message is not the sighash of input {} of {}",
+                index,
+                tx.compute_txid()
+            )));
+        }
+
+        // Outputs going back to the spent key are change; every other one is a payout
+        let txid = tx.compute_txid();
+        let payouts: Vec<Payout> = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.script_pubkey != *spent_spk)
+            .map(|(vout, output)| Payout {
+                id: format!("{}:{}", txid, vout),
+                address: Address::from_script(&output.script_pubkey, self.network)
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|_| output.script_pubkey.to_hex_string()),
+                amount_sats: output.value.to_sat(),
+            })
+            .collect();
+        let denial = self
+            .policy
+            .check(&payouts)
+            .map_err(|e| Status::internal(format!("Policy check failed: {}", e)))?;
+        if let Some(reason) = denial {
+            return Err(Status::permission_denied(format!(
+                "Refusing to sign {}, denied by policy: {}",
+                txid, reason
+            )));
+        }
+
+        if let Some(backend) = &self.backend {
+            for (spent, prevout) in tx.input.iter().zip(&prevouts) {
+                let found = backend
+                    .unspent_output(&spent.previous_output)
+                    .await
+                    .map_err(|e| {
+                        Status::unavailable(format!(
+                            "Looking up {} on {} failed: {}",
+                            spent.previous_output,
+                            backend.name(),
+                            e
+                        ))
+                    })?;
+                match found {
+                    Some(found) if found == *prevout => {}
+                    Some(found) => {
+                        return Err(Status::permission_denied(format!(
+                            "{} holds {} to {}, not {} to the group key",
+                            spent.previous_output,
+                            found.value,
+                            found.script_pubkey.to_hex_string(),
+                            prevout.value
+                        )))
+                    }
+                    None => {
+                        return Err(Status::permission_denied(format!(
+                            "{} is spent or unknown to {}",
+                            spent.previous_output,
+                            backend.name()
+                        )))
+                    }
+                }
+            }
+        }
+        info!(%txid, input = index, payouts = payouts.len(), "Transaction input checked");
+        Ok(payouts)
+    }
+}
+
+/// The message must be the BIP-340 tagged hash of the preimage under a known tag.
+fn check_tagged(message: &[u8], tagged: &TaggedMessage) -> Result<(), Status> {
+    if !TAGS.contains(&tagged.tag.as_str()) {
+        return Err(Status::permission_denied(format!(
+            "Unknown message tag {}",
+            tagged.tag
+        )));
+    }
+    let preimage = hex::decode(&tagged.preimage_hex)
+        .map_err(|e| Status::invalid_argument(format!("Invalid preimage hex: {}", e)))?;
+    let tag = sha256::Hash::hash(tagged.tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_byte_array());
+    engine.input(tag.as_byte_array());
+    engine.input(&preimage);
+    if sha256::Hash::from_engine(engine).as_byte_array()[..] != *message {
+        return Err(Status::invalid_argument(format!(
+            "The message is not the {} hash of its preimage",
+            tagged.tag
+        )));
+    }
+    Ok(())
+}
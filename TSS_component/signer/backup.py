@@ -0,0 +1,396 @@
+"""Offline paper backup of a signer's key share.
+
+`export` encrypts the signer's KeyPackage and PublicKeyPackage under a passphrase, splits the
+ciphertext into M Shamir fragments any K of which rebuild it, and writes each fragment as a
+printable sheet and a QR code. `restore` walks an operator through typing or scanning K
+fragments back in, decrypts them and writes the share back into the signer's sled database;
+`verify` does the same without writing, for backup drills.
+
+Run it in the signer's container, with the signer stopped, since sled locks /state/nonces_db:
+
+    docker compose stop signer1
+    docker compose run --rm --no-deps -v $PWD/backup:/backup signer1 \
+        python backup.py export --threshold 2 --fragments 3 --out-dir /backup
+    docker compose run --rm --no-deps signer1 python backup.py restore
+"""
+
+import argparse
+import base64
+import getpass
+import hashlib
+import os
+import secrets
+import struct
+import sys
+import time
+
+import qrcode
+import qrcode.image.svg
+from cryptography.exceptions import InvalidTag
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+import rust_tss as rust_tss
+
+MAGIC = b"ZKBTC-BK1"
+# Fragments scanned from a QR code start with this; typed ones are read line by line
+QR_PREFIX = "ZKBTCBK1"
+FRAGMENT_VERSION = 1
+SALT_LEN = 16
+NONCE_LEN = 12
+CHECKSUM_LEN = 4
+# scrypt cost: 32 MiB and about a second per guess
+SCRYPT_N = 2 ** 15
+SCRYPT_R = 8
+SCRYPT_P = 1
+LINE_GROUPS = 8
+GROUP_LEN = 4
+
+
+class BackupError(Exception):
+    pass
+
+
+# --- GF(256) arithmetic, for a byte-wise Shamir split ---
+def _gf_mul(a, b):
+    product = 0
+    while b:
+        if b & 1:
+            product ^= a
+        a <<= 1
+        if a & 0x100:
+            a ^= 0x11B
+        b >>= 1
+    return product
+
+
+def _gf_inv(a):
+    # a^254 is the inverse of a in GF(256)
+    result, power, exponent = 1, a, 254
+    while exponent:
+        if exponent & 1:
+            result = _gf_mul(result, power)
+        power = _gf_mul(power, power)
+        exponent >>= 1
+    return result
+
+
+def split_secret(secret, threshold, count):
+    """Shares of `secret` at x = 1..count, any `threshold` of which rebuild it."""
+    shares = {x: bytearray() for x in range(1, count + 1)}
+    for byte in secret:
+        coefficients = [byte] + [secrets.randbelow(256) for _ in range(threshold - 1)]
+        for x, share in shares.items():
+            y = 0
+            for coefficient in reversed(coefficients):
+                y = _gf_mul(y, x) ^ coefficient
+            share.append(y)
+    return {x: bytes(share) for x, share in shares.items()}
+
+
+def combine_shares(shares):
+    """Rebuilds the secret from a {x: share} map by Lagrange interpolation at x = 0."""
+    xs = list(shares)
+    length = len(shares[xs[0]])
+    secret = bytearray(length)
+    for x in xs:
+        # Lagrange basis polynomial of x, evaluated at 0
+        basis = 1
+        for other in xs:
+            if other != x:
+                basis = _gf_mul(basis, _gf_mul(other, _gf_inv(other ^ x)))
+        for i, y in enumerate(shares[x]):
+            secret[i] ^= _gf_mul(basis, y)
+    return bytes(secret)
+
+
+# --- Encryption of the key material ---
+def _derive_key(passphrase, salt):
+    return hashlib.scrypt(
+        passphrase.encode(), salt=salt, n=SCRYPT_N, r=SCRYPT_R, p=SCRYPT_P,
+        maxmem=64 * 1024 * 1024, dklen=32,
+    )
+
+
+def encrypt_backup(party_id, key_package, public_key_package, passphrase):
+    plaintext = struct.pack(">HQH", party_id, int(time.time()), len(key_package)) + key_package
+    plaintext += struct.pack(">H", len(public_key_package)) + public_key_package
+    salt = secrets.token_bytes(SALT_LEN)
+    nonce = secrets.token_bytes(NONCE_LEN)
+    header = MAGIC + salt + nonce
+    return header + ChaCha20Poly1305(_derive_key(passphrase, salt)).encrypt(nonce, plaintext, header)
+
+
+def decrypt_backup(blob, passphrase):
+    """Returns (party id, created at, key package, public key package)."""
+    header_len = len(MAGIC) + SALT_LEN + NONCE_LEN
+    if not blob.startswith(MAGIC) or len(blob) < header_len:
+        raise BackupError("The fragments do not rebuild a signer backup")
+    salt = blob[len(MAGIC):len(MAGIC) + SALT_LEN]
+    nonce = blob[len(MAGIC) + SALT_LEN:header_len]
+    try:
+        plaintext = ChaCha20Poly1305(_derive_key(passphrase, salt)).decrypt(
+            nonce, blob[header_len:], blob[:header_len]
+        )
+    except InvalidTag:
+        raise BackupError("Wrong passphrase, or fragments of different backups")
+    party_id, created_at, kp_len = struct.unpack_from(">HQH", plaintext)
+    offset = struct.calcsize(">HQH")
+    key_package = plaintext[offset:offset + kp_len]
+    (pubkp_len,) = struct.unpack_from(">H", plaintext, offset + kp_len)
+    public_key_package = plaintext[offset + kp_len + 2:offset + kp_len + 2 + pubkp_len]
+    return party_id, created_at, key_package, public_key_package
+
+
+# --- Fragment encoding ---
+class Fragment:
+    def __init__(self, set_id, threshold, count, x, share):
+        self.set_id = set_id
+        self.threshold = threshold
+        self.count = count
+        self.x = x
+        self.share = share
+
+    def to_bytes(self):
+        body = struct.pack(">B4sBBB", FRAGMENT_VERSION, self.set_id, self.threshold, self.count, self.x)
+        body += self.share
+        return body + hashlib.sha256(body).digest()[:CHECKSUM_LEN]
+
+    @classmethod
+    def from_bytes(cls, data):
+        body, checksum = data[:-CHECKSUM_LEN], data[-CHECKSUM_LEN:]
+        if len(body) < 8 or hashlib.sha256(body).digest()[:CHECKSUM_LEN] != checksum:
+            raise BackupError("The fragment's checksum does not match: a character is wrong or missing")
+        version, set_id, threshold, count, x = struct.unpack_from(">B4sBBB", body)
+        if version != FRAGMENT_VERSION:
+            raise BackupError(f"Unknown fragment version {version}")
+        return cls(set_id, threshold, count, x, body[8:])
+
+    def text(self):
+        return base64.b32encode(self.to_bytes()).decode().rstrip("=")
+
+    @classmethod
+    def from_text(cls, text):
+        text = "".join(text.split()).replace("-", "").upper()
+        if text.startswith(QR_PREFIX):
+            text = text[len(QR_PREFIX):]
+        try:
+            data = base64.b32decode(text + "=" * (-len(text) % 8))
+        except ValueError:
+            raise BackupError("The fragment holds characters outside A-Z and 2-7")
+        return cls.from_bytes(data)
+
+    def lines(self):
+        """Printed lines: a number, 8 groups of 4 characters, and a 2-character check of the
+        line, so a typo is caught on the line it is in."""
+        text = self.text()
+        width = LINE_GROUPS * GROUP_LEN
+        lines = []
+        for number, start in enumerate(range(0, len(text), width), 1):
+            chunk = text[start:start + width]
+            groups = " ".join(chunk[i:i + GROUP_LEN] for i in range(0, len(chunk), GROUP_LEN))
+            lines.append(f"{number:02d}  {groups}  {line_check(number, chunk)}")
+        return lines
+
+    def label(self):
+        return f"{self.set_id.hex()} fragment {self.x} of {self.count} (any {self.threshold} restore)"
+
+
+def line_check(number, chunk):
+    digest = hashlib.sha256(f"{number}:{chunk}".encode()).digest()
+    return base64.b32encode(digest).decode()[:2]
+
+
+def parse_line(line):
+    """(number, characters) of a typed line, checked against its line check."""
+    parts = line.split()
+    if len(parts) < 3 or not parts[0].isdigit():
+        raise BackupError("Type the line number, the groups and the check, as printed")
+    number, check = int(parts[0]), parts[-1].upper()
+    chunk = "".join(parts[1:-1]).upper()
+    if line_check(number, chunk) != check:
+        raise BackupError(f"Line {number} does not match its check {check}: a character is wrong")
+    return number, chunk
+
+
+def fragment_from_sheet(text):
+    """Fragment of a printed sheet, or of the text scanned from its QR code."""
+    if text.strip().upper().startswith(QR_PREFIX):
+        return Fragment.from_text(text)
+    lines = dict(parse_line(line) for line in text.splitlines() if line[:2].isdigit())
+    return Fragment.from_text("".join(lines[n] for n in sorted(lines)))
+
+
+def write_sheet(fragment, out_dir, party_id, group_key):
+    name = f"signer{party_id}-{fragment.set_id.hex()}-fragment-{fragment.x}-of-{fragment.count}"
+    sheet = [
+        "ZKBTC SIGNER SHARE BACKUP - KEEP OFFLINE",
+        "",
+        f"Signer:    {party_id}",
+        f"Group key: {group_key}",
+        f"Fragment:  {fragment.label()}",
+        f"Created:   {time.strftime('%Y-%m-%d %H:%M UTC', time.gmtime())}",
+        "",
+        "Restoring takes this many fragments and the backup passphrase:",
+        "    python backup.py restore",
+        "",
+        *fragment.lines(),
+        "",
+    ]
+    text_path = os.path.join(out_dir, name + ".txt")
+    with open(text_path, "w") as f:
+        f.write("\n".join(sheet))
+    # Upper-case base32 fits the QR alphanumeric mode
+    qr = qrcode.QRCode(error_correction=qrcode.constants.ERROR_CORRECT_M)
+    qr.add_data(QR_PREFIX + fragment.text())
+    qr.make(fit=True)
+    svg_path = os.path.join(out_dir, name + ".svg")
+    qr.make_image(image_factory=qrcode.image.svg.SvgPathImage).save(svg_path)
+    return text_path, svg_path
+
+
+# --- Commands ---
+def passphrase_from(confirm):
+    if os.environ.get("BACKUP_PASSPHRASE"):
+        return os.environ["BACKUP_PASSPHRASE"]
+    passphrase = getpass.getpass("Backup passphrase: ")
+    if confirm:
+        if len(passphrase) < 12:
+            raise BackupError("Use a passphrase of at least 12 characters")
+        if getpass.getpass("Repeat the passphrase: ") != passphrase:
+            raise BackupError("The passphrases differ")
+    return passphrase
+
+
+def export(args):
+    if not 1 <= args.threshold <= args.fragments <= 255:
+        raise BackupError("Need 1 <= --threshold <= --fragments <= 255")
+    material = rust_tss.export_key_material(args.party_id)
+    if material is None:
+        raise BackupError(f"Signer {args.party_id} holds no key share yet: run the DKG first")
+    kp_hex, pubkp_hex, _id_hex = material
+    _, group_key, _, _ = rust_tss.init(args.party_id)
+    passphrase = passphrase_from(confirm=True)
+
+    blob = encrypt_backup(args.party_id, bytes.fromhex(kp_hex), bytes.fromhex(pubkp_hex), passphrase)
+    set_id = hashlib.sha256(blob).digest()[:4]
+    os.makedirs(args.out_dir, exist_ok=True)
+    for x, share in split_secret(blob, args.threshold, args.fragments).items():
+        fragment = Fragment(set_id, args.threshold, args.fragments, x, share)
+        text_path, svg_path = write_sheet(fragment, args.out_dir, args.party_id, group_key)
+        print(f"{fragment.label()}: {text_path}, {svg_path}")
+    print(
+        f"Print each sheet, hand the fragments to {args.fragments} different custodians and delete "
+        f"{args.out_dir}. Any {args.threshold} of them and the passphrase restore signer {args.party_id}."
+    )
+
+
+def read_fragment(index, needed):
+    """Prompts for one fragment, scanned or typed, until it reads back intact."""
+    while True:
+        print(f"\nFragment {index} of {needed}: scan its QR code, or type its numbered lines, "
+              "then an empty line.")
+        lines, first = {}, True
+        while True:
+            line = input("> ").strip()
+            if not line:
+                break
+            try:
+                if first and line.upper().startswith(QR_PREFIX):
+                    return Fragment.from_text(line)
+                first = False
+                number, chunk = parse_line(line)
+                lines[number] = chunk
+            except BackupError as e:
+                print(f"  {e}; type that line again.")
+        if not lines:
+            continue
+        missing = [n for n in range(1, max(lines) + 1) if n not in lines]
+        if missing:
+            print(f"  Lines {missing} are missing; start the fragment again.")
+            continue
+        try:
+            return Fragment.from_text("".join(lines[n] for n in sorted(lines)))
+        except BackupError as e:
+            print(f"  {e}; start the fragment again.")
+
+
+def gather(args):
+    """Reads fragments from --fragment files, then interactively, until enough of one set."""
+    fragments = {}
+    for path in args.fragment or []:
+        with open(path) as f:
+            fragment = fragment_from_sheet(f.read())
+        fragments[fragment.x] = fragment
+    first = next(iter(fragments.values()), None)
+    while first is None or len(fragments) < first.threshold:
+        needed = first.threshold if first else "?"
+        fragment = read_fragment(len(fragments) + 1, needed)
+        if first is None:
+            first = fragment
+        if fragment.set_id != first.set_id:
+            print(f"  That is a fragment of backup {fragment.set_id.hex()}, not {first.set_id.hex()}.")
+            continue
+        if fragment.x in fragments:
+            print(f"  Fragment {fragment.x} was already given.")
+            continue
+        fragments[fragment.x] = fragment
+        print(f"  Read {fragment.label()}.")
+    if any(f.set_id != first.set_id for f in fragments.values()):
+        raise BackupError("The fragment files are of different backups")
+    chosen = list(fragments.values())[:first.threshold]
+    return combine_shares({f.x: f.share for f in chosen})
+
+
+def recover(args):
+    blob = gather(args)
+    party_id, created_at, kp, pubkp = decrypt_backup(blob, passphrase_from(confirm=False))
+    created = time.strftime("%Y-%m-%d %H:%M UTC", time.gmtime(created_at))
+    print(f"\nThe fragments rebuild the share of signer {party_id}, backed up {created}.")
+    return party_id, kp, pubkp
+
+
+def verify(args):
+    recover(args)
+    print("The backup is intact. Nothing was written.")
+
+
+def restore(args):
+    party_id, kp, pubkp = recover(args)
+    if party_id != args.party_id:
+        raise BackupError(f"This is the backup of signer {party_id}, and this is signer {args.party_id}")
+    if input(f"Write it into the database of signer {party_id}? [y/N] ").strip().lower() != "y":
+        print("Nothing was written.")
+        return
+    group_key = rust_tss.import_key_material(party_id, kp.hex(), pubkp.hex(), args.force)
+    print(f"Signer {party_id} holds its share of group key {group_key} again. Start the signer.")
+
+
+def main():
+    parser = argparse.ArgumentParser(description="Paper backup of a signer's key share")
+    parser.add_argument("--party-id", type=int, default=os.environ.get("PARTY_ID"),
+                        help="signer whose share is backed up or restored (PARTY_ID)")
+    commands = parser.add_subparsers(dest="command", required=True)
+    export_parser = commands.add_parser("export", help="encrypt and split the share into fragments")
+    export_parser.add_argument("--threshold", type=int, required=True, help="fragments needed to restore")
+    export_parser.add_argument("--fragments", type=int, required=True, help="fragments to write")
+    export_parser.add_argument("--out-dir", required=True, help="directory the sheets and QR codes go in")
+    for name, help_text in (("verify", "check fragments and passphrase, writing nothing"),
+                            ("restore", "write the share back from fragments")):
+        command = commands.add_parser(name, help=help_text)
+        command.add_argument("--fragment", action="append",
+                             help="printed sheet or scanned QR text of a fragment; the rest are prompted for")
+        if name == "restore":
+            command.add_argument("--force", action="store_true",
+                                 help="replace a different share the signer already holds")
+    args = parser.parse_args()
+    if args.party_id is None:
+        parser.error("--party-id or PARTY_ID is required")
+    args.party_id = int(args.party_id)
+    try:
+        {"export": export, "verify": verify, "restore": restore}[args.command](args)
+    except BackupError as e:
+        sys.exit(f"Error: {e}")
+
+
+if __name__ == "__main__":
+    main()
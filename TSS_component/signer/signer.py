@@ -8,6 +8,7 @@ import logging
 
 # Assuming your compiled rust lib is importable as rust_ffi
 import rust_tss as rust_tss
+from signer_grpc import start_grpc_server
 
 logging.basicConfig(
     level=logging.INFO,
@@ -35,6 +36,20 @@ logger.info(f"Party ID (u16): {PID}")
 logger.info(f"DKG Config: T={T}, N={N}")
 
 app = FastAPI()
+grpc_server = None
+
+
+# --- gRPC service for the Rust coordinator ---
+@app.on_event("startup")
+async def start_grpc():
+    global grpc_server
+    grpc_server = await start_grpc_server()
+
+
+@app.on_event("shutdown")
+async def stop_grpc():
+    if grpc_server is not None:
+        await grpc_server.stop(grace=5)
 
 
 # --- Helper function for error handling ---
@@ -0,0 +1,143 @@
+import asyncio
+import json
+import logging
+import os
+
+import grpc
+
+# Generated from proto/tss.proto when the image is built
+import tss_pb2
+import tss_pb2_grpc
+import rust_tss as rust_tss
+import telemetry
+
+logger = logging.getLogger(__name__)
+
+PID = int(os.environ["PARTY_ID"])
+N = int(os.environ["TOTAL"])
+T = int(os.environ["THRESHOLD"])
+GRPC_PORT = int(os.environ.get("GRPC_PORT", "50051"))
+# PEM certificate and key the service presents; it is only served over TLS
+GRPC_TLS_CERT = os.environ.get("GRPC_TLS_CERT")
+GRPC_TLS_KEY = os.environ.get("GRPC_TLS_KEY")
+# PEM CA the coordinator's client certificate must chain to; when set, clients without one
+# are refused (mTLS)
+GRPC_TLS_CLIENT_CA = os.environ.get("GRPC_TLS_CLIENT_CA")
+
+telemetry.setup(PID)
+
+
+def pairs(packages):
+    # (id_hex, pkg_hex) tuples, as the Rust library takes them
+    return [(p.id_hex, p.pkg_hex) for p in packages]
+
+
+class SignerService(tss_pb2_grpc.SignerServicer):
+    """gRPC face of the signer, driven by the Rust coordinator. Every call runs the same
+    rust_tss function as the matching HTTP endpoint of signer.py."""
+
+    def __init__(self):
+        _, _, _, self.id_hex = rust_tss.init(PID)
+
+    async def _run(self, context, what, session_id, fn, *args):
+        parent, swap_id = telemetry.incoming(context)
+        for_swap = f" (swap {swap_id})" if swap_id else ""
+        logger.info(f"[{PID}] {what} for session {session_id}{for_swap}")
+        attributes = {
+            "party_id": PID, "session_id": session_id, "swap_id": swap_id, "rust_tss.function": fn.__name__,
+        }
+        with telemetry.tracer.start_as_current_span(what, context=parent, attributes=attributes):
+            try:
+                # The Rust calls block, so they run on a worker thread, not the event loop
+                return await asyncio.to_thread(fn, *args)
+            except ValueError as e:
+                logger.error(f"[{PID}] {what} rejected: {e}")
+                await context.abort(grpc.StatusCode.INVALID_ARGUMENT, f"{what}: {e}")
+            except ConnectionError as e:
+                logger.error(f"[{PID}] {what} failed: {e}")
+                await context.abort(grpc.StatusCode.UNAVAILABLE, f"{what}: {e}")
+            except Exception as e:
+                logger.error(f"[{PID}] {what} failed: {e}")
+                await context.abort(grpc.StatusCode.INTERNAL, f"{what}: {e}")
+
+    async def DkgStatus(self, request, context):
+        has_keys, verify_key_hex, pubkp_hex, id_hex = await self._run(
+            context, "DKG status", "-", rust_tss.init, PID
+        )
+        return tss_pb2.DkgStatusResponse(
+            has_keys=has_keys, id_hex=id_hex, verify_key_hex=verify_key_hex, pubkp_hex=pubkp_hex
+        )
+
+    async def DkgRound1(self, request, context):
+        id_hex, pkg_hex = await self._run(
+            context, "DKG round 1", request.session_id, rust_tss.dkg_round1, PID, N, T
+        )
+        return tss_pb2.DkgRound1Response(id_hex=id_hex, pkg_hex=pkg_hex)
+
+    async def DkgRound2(self, request, context):
+        if not request.round1_packages:
+            await context.abort(grpc.StatusCode.INVALID_ARGUMENT, "No round-1 packages")
+        pkgs2_json = await self._run(
+            context, "DKG round 2", request.session_id,
+            rust_tss.dkg_round2, self.id_hex, pairs(request.round1_packages),
+        )
+        packages = [tss_pb2.Package(id_hex=to, pkg_hex=pkg) for to, pkg in json.loads(pkgs2_json)]
+        return tss_pb2.DkgRound2Response(id_hex=self.id_hex, round2_packages=packages)
+
+    async def DkgRound3(self, request, context):
+        if not request.round1_packages or not request.round2_packages:
+            await context.abort(grpc.StatusCode.INVALID_ARGUMENT, "Missing round-1 or round-2 packages")
+        pubkp_hex, verify_key_hex = await self._run(
+            context, "DKG round 3", request.session_id,
+            rust_tss.dkg_round3, self.id_hex,
+            pairs(request.round1_packages), pairs(request.round2_packages),
+        )
+        logger.info(f"[{PID}] Group verifying key (x-only hex): {verify_key_hex}")
+        return tss_pb2.DkgRound3Response(
+            id_hex=self.id_hex, verify_key_hex=verify_key_hex, pubkp_hex=pubkp_hex
+        )
+
+    async def SignRound1(self, request, context):
+        commitment_hex = await self._run(
+            context, "Signing round 1", request.session_id, rust_tss.sign_round1, self.id_hex
+        )
+        return tss_pb2.SignRound1Response(id_hex=self.id_hex, commitment_hex=commitment_hex)
+
+    async def SignRound2(self, request, context):
+        signature_share_hex = await self._run(
+            context, "Signing round 2", request.session_id,
+            rust_tss.sign_round2, self.id_hex, request.message_hex, pairs(request.commitments),
+            request.merkle_root_hex or None,
+        )
+        return tss_pb2.SignRound2Response(id_hex=self.id_hex, signature_share_hex=signature_share_hex)
+
+
+def read(path):
+    with open(path, "rb") as f:
+        return f.read()
+
+
+def server_credentials():
+    """TLS credentials of the service, from GRPC_TLS_CERT and GRPC_TLS_KEY, asking clients
+    for a certificate when GRPC_TLS_CLIENT_CA is set."""
+    if not GRPC_TLS_CERT or not GRPC_TLS_KEY:
+        raise RuntimeError("GRPC_TLS_CERT and GRPC_TLS_KEY are required, the gRPC signer is only served over TLS")
+    client_ca = read(GRPC_TLS_CLIENT_CA) if GRPC_TLS_CLIENT_CA else None
+    return grpc.ssl_server_credentials(
+        [(read(GRPC_TLS_KEY), read(GRPC_TLS_CERT))],
+        root_certificates=client_ca,
+        require_client_auth=client_ca is not None,
+    )
+
+
+async def start_grpc_server():
+    """Serves the Signer gRPC service next to the HTTP API, in the same process so both use
+    the one sled database rust_tss opens."""
+    credentials = server_credentials()
+    server = grpc.aio.server()
+    tss_pb2_grpc.add_SignerServicer_to_server(SignerService(), server)
+    server.add_secure_port(f"[::]:{GRPC_PORT}", credentials)
+    await server.start()
+    mtls = " with client certificates" if GRPC_TLS_CLIENT_CA else ""
+    logger.info(f"[{PID}] gRPC signer listening on port {GRPC_PORT} over TLS{mtls}")
+    return server
@@ -0,0 +1,34 @@
+"""OpenTelemetry tracing of the gRPC calls. With OTEL_EXPORTER_OTLP_ENDPOINT set, every call
+the coordinator makes is exported as a span of service signer-<PARTY_ID> (or
+OTEL_SERVICE_NAME), in the coordinator's trace: its traceparent comes in the call metadata,
+along with the swap ids of the run."""
+import os
+
+from opentelemetry import propagate, trace
+
+# gRPC metadata key of the swap ids, comma-separated, as the coordinator sends them
+SWAP_ID_METADATA = "swap-id"
+
+
+def setup(party_id):
+    if not os.environ.get("OTEL_EXPORTER_OTLP_ENDPOINT"):
+        return
+    from opentelemetry.exporter.otlp.proto.grpc.trace_exporter import OTLPSpanExporter
+    from opentelemetry.sdk.resources import Resource
+    from opentelemetry.sdk.trace import TracerProvider
+    from opentelemetry.sdk.trace.export import BatchSpanProcessor
+
+    # Resource.create reads OTEL_SERVICE_NAME, but an explicit service.name would override it
+    attributes = {} if os.environ.get("OTEL_SERVICE_NAME") else {"service.name": f"signer-{party_id}"}
+    provider = TracerProvider(resource=Resource.create(attributes))
+    provider.add_span_processor(BatchSpanProcessor(OTLPSpanExporter()))
+    trace.set_tracer_provider(provider)
+
+
+tracer = trace.get_tracer("signer")
+
+
+def incoming(context):
+    """Trace context and swap ids of an incoming call."""
+    metadata = {key: value for key, value in context.invocation_metadata()}
+    return propagate.extract(metadata), metadata.get(SWAP_ID_METADATA, "")
@@ -0,0 +1,314 @@
+//! Key ceremonies: a fresh DKG whose broadcasts are written to a transcript, from which anyone
+//! recomputes the group key without the signers. The transcript holds every signer's round-1
+//! package, its coefficient commitments and proof of knowledge, and the public key package each
+//! signer derived. Round-2 packages carry secret shares, so only their SHA-256 is kept, showing
+//! which were delivered without revealing them.
+//!
+//! [`verify`] checks a transcript on its own and returns the ceremony report: the group key
+//! and verifying shares the commitments give, and whether every signer ended with them.
+
+use crate::frost;
+use crate::store::{now, DkgSession, Package, Round2Package};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Network;
+use frost_secp256k1_tr::keys::dkg::round1;
+use frost_secp256k1_tr::keys::PublicKeyPackage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+/// Version of the transcript format.
+pub const TRANSCRIPT_VERSION: u8 = 1;
+
+/// A round-2 package, by its SHA-256.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Round2Digest {
+    pub from_hex: String,
+    pub to_hex: String,
+    pub sha256_hex: String,
+}
+
+/// What a signer derived in round 3.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct SignerResult {
+    pub id_hex: String,
+    pub verify_key_hex: String,
+    pub pubkp_hex: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    pub version: u8,
+    /// DKG session of the coordinator.
+    pub session_id: String,
+    /// Signer endpoints, in the order of `round1`.
+    pub signers: Vec<String>,
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// Round-1 package each signer broadcast.
+    pub round1: Vec<Package>,
+    pub round2: Vec<Round2Digest>,
+    /// One per signer, in the order they answered round 3.
+    pub results: Vec<SignerResult>,
+}
+
+impl Transcript {
+    /// Transcript of `session`, whose rounds gave `round1`, `round2` and `results`.
+    pub fn new(
+        session: &DkgSession,
+        round1: &[Package],
+        round2: &[Round2Package],
+        results: Vec<SignerResult>,
+    ) -> Self {
+        Transcript {
+            version: TRANSCRIPT_VERSION,
+            session_id: session.id.clone(),
+            signers: session.signers.clone(),
+            started_at: session.created_at,
+            finished_at: now(),
+            round1: round1.to_vec(),
+            round2: round2
+                .iter()
+                .map(|package| Round2Digest {
+                    from_hex: package.from_hex.clone(),
+                    to_hex: package.to_hex.clone(),
+                    sha256_hex: sha256::Hash::hash(package.pkg_hex.as_bytes()).to_string(),
+                })
+                .collect(),
+            results,
+        }
+    }
+
+    /// SHA-256 of the transcript's JSON, as the report names it.
+    pub fn sha256_hex(&self) -> Result<String, Box<dyn Error>> {
+        Ok(sha256::Hash::hash(&serde_json::to_vec(self)?).to_string())
+    }
+}
+
+/// One check of the transcript.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Check {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// What a transcript shows, for third parties to compare with their own [`verify`].
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CeremonyReport {
+    pub session_id: String,
+    pub transcript_sha256: String,
+    pub signers: usize,
+    /// Signers needed to sign, the length of the coefficient commitments.
+    pub threshold: Option<usize>,
+    /// x-only group key the commitments give.
+    pub group_key: Option<String>,
+    pub taproot_address: Option<String>,
+    /// Verifying share of each signer the commitments give, compressed.
+    pub verifying_shares: Vec<Package>,
+    pub checks: Vec<Check>,
+    /// Every check passed.
+    pub verified: bool,
+}
+
+impl CeremonyReport {
+    fn check(&mut self, name: &str, result: Result<String, String>) -> bool {
+        let passed = result.is_ok();
+        self.checks.push(Check {
+            name: name.to_string(),
+            passed,
+            detail: result.unwrap_or_else(|e| e),
+        });
+        passed
+    }
+}
+
+/// x coordinate of a compressed or x-only key, hex; the Taproot ciphersuite may negate the
+/// shares after the DKG, which leaves it unchanged.
+fn x_only(key_hex: &str) -> Result<String, Box<dyn Error>> {
+    Ok(frost::group_key(key_hex)?.to_string())
+}
+
+/// Checks `transcript` on its own and reports the group key it gives on `network`.
+pub fn verify(transcript: &Transcript, network: Network) -> Result<CeremonyReport, Box<dyn Error>> {
+    let mut report = CeremonyReport {
+        session_id: transcript.session_id.clone(),
+        transcript_sha256: transcript.sha256_hex()?,
+        signers: transcript.round1.len(),
+        threshold: None,
+        group_key: None,
+        taproot_address: None,
+        verifying_shares: Vec::new(),
+        checks: Vec::new(),
+        verified: false,
+    };
+    report.check(
+        "version",
+        if transcript.version == TRANSCRIPT_VERSION {
+            Ok(format!("version {}", transcript.version))
+        } else {
+            Err(format!("unknown version {}", transcript.version))
+        },
+    );
+
+    // Round 1: one package per signer, with commitments of one length
+    let mut packages = BTreeMap::new();
+    let round1 = (|| {
+        if transcript.round1.len() != transcript.signers.len() {
+            return Err(format!(
+                "{} round-1 packages for {} signers",
+                transcript.round1.len(),
+                transcript.signers.len()
+            ));
+        }
+        for package in &transcript.round1 {
+            let id = frost::identifier(&package.id_hex).map_err(|e| e.to_string())?;
+            let parsed = hex::decode(&package.pkg_hex)
+                .ok()
+                .and_then(|bytes| round1::Package::deserialize(&bytes).ok())
+                .ok_or_else(|| format!("invalid round-1 package of {}", package.id_hex))?;
+            if packages.insert(id, parsed).is_some() {
+                return Err(format!("two round-1 packages of {}", package.id_hex));
+            }
+        }
+        let lengths = packages
+            .values()
+            .map(|package| package.commitment().serialize().map(|c| c.len()))
+            .collect::<Result<BTreeSet<_>, _>>()
+            .map_err(|e| format!("invalid commitment: {}", e))?;
+        match lengths.into_iter().collect::<Vec<_>>()[..] {
+            [threshold] if threshold >= 2 && threshold <= packages.len() => {
+                report.threshold = Some(threshold);
+                Ok(format!(
+                    "{} signers committed to polynomials of degree {}",
+                    packages.len(),
+                    threshold - 1
+                ))
+            }
+            [threshold] => Err(format!(
+                "a threshold of {} for {} signers",
+                threshold,
+                packages.len()
+            )),
+            _ => Err("the signers committed to polynomials of different degrees".to_string()),
+        }
+    })();
+    if !report.check("round 1", round1) {
+        return Ok(report);
+    }
+
+    // Round 2: one package from every signer to every other one
+    let ids: BTreeSet<&str> = transcript
+        .round1
+        .iter()
+        .map(|package| package.id_hex.as_str())
+        .collect();
+    let mut pairs = BTreeSet::new();
+    let round2 = transcript
+        .round2
+        .iter()
+        .try_for_each(|digest| {
+            if !ids.contains(digest.from_hex.as_str()) || !ids.contains(digest.to_hex.as_str()) {
+                return Err(format!(
+                    "package from {} to {}, not between signers",
+                    digest.from_hex, digest.to_hex
+                ));
+            }
+            if digest.from_hex == digest.to_hex || !pairs.insert((&digest.from_hex, &digest.to_hex))
+            {
+                return Err(format!(
+                    "extra package from {} to {}",
+                    digest.from_hex, digest.to_hex
+                ));
+            }
+            Ok(())
+        })
+        .and_then(|()| match ids.len() * (ids.len() - 1) {
+            expected if pairs.len() == expected => {
+                Ok(format!("{} packages, one per pair of signers", expected))
+            }
+            expected => Err(format!("{} packages of {}", pairs.len(), expected)),
+        });
+    report.check("round 2", round2);
+
+    // The group key and verifying shares the commitments give
+    let commitments = packages
+        .iter()
+        .map(|(id, package)| (*id, package.commitment()))
+        .collect();
+    let derived = PublicKeyPackage::from_dkg_commitments(&commitments)
+        .map_err(|e| format!("Deriving the group key failed: {}", e))?;
+    let group_key_hex = hex::encode(
+        derived
+            .verifying_key()
+            .serialize()
+            .map_err(|e| format!("Serialize group key failed: {}", e))?,
+    );
+    let group_key = x_only(&group_key_hex)?;
+    report.taproot_address = Some(frost::taproot_address(&group_key, network)?.to_string());
+    report.group_key = Some(group_key.clone());
+    for (id, share) in derived.verifying_shares() {
+        report.verifying_shares.push(Package {
+            id_hex: hex::encode(id.serialize()),
+            pkg_hex: hex::encode(
+                share
+                    .serialize()
+                    .map_err(|e| format!("Serialize verifying share failed: {}", e))?,
+            ),
+        });
+    }
+
+    // Every signer ended with that key and those shares
+    let results = (|| {
+        let answered: BTreeSet<&str> = transcript
+            .results
+            .iter()
+            .map(|result| result.id_hex.as_str())
+            .collect();
+        if answered != ids || transcript.results.len() != ids.len() {
+            return Err("not one round-3 result per signer".to_string());
+        }
+        for result in &transcript.results {
+            if x_only(&result.verify_key_hex).map_err(|e| e.to_string())? != group_key {
+                return Err(format!(
+                    "signer {} derived group key {}",
+                    result.id_hex, result.verify_key_hex
+                ));
+            }
+            let pubkp = hex::decode(&result.pubkp_hex)
+                .ok()
+                .and_then(|bytes| PublicKeyPackage::deserialize(&bytes).ok())
+                .ok_or_else(|| format!("invalid public key package of {}", result.id_hex))?;
+            let shares: BTreeMap<String, String> = pubkp
+                .verifying_shares()
+                .iter()
+                .map(|(id, share)| {
+                    let share = share.serialize().map_err(|e| e.to_string())?;
+                    Ok((
+                        hex::encode(id.serialize()),
+                        x_only(&hex::encode(share)).map_err(|e| e.to_string())?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?;
+            for expected in &report.verifying_shares {
+                if shares.get(&expected.id_hex)
+                    != Some(&x_only(&expected.pkg_hex).map_err(|e| e.to_string())?)
+                {
+                    return Err(format!(
+                        "signer {} holds another verifying share of {}",
+                        result.id_hex, expected.id_hex
+                    ));
+                }
+            }
+        }
+        Ok(format!(
+            "every signer derived group key {} and the verifying shares",
+            group_key
+        ))
+    })();
+    report.check("group key", results);
+    report.verified = report.checks.iter().all(|check| check.passed);
+    Ok(report)
+}
@@ -0,0 +1,188 @@
+//! gRPC clients of the signer nodes, with retry of transient failures. Signers are only
+//! reached over TLS, at `https` endpoints whose certificates chain to `--tls-ca`; with
+//! `--tls-cert` and `--tls-key`, the coordinator presents a certificate to signers requiring
+//! one (mTLS).
+
+use crate::proto::signer_client::SignerClient;
+use crate::telemetry::TraceContext;
+use clap::Args;
+use futures::future::try_join_all;
+use std::error::Error;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tonic::{Code, Status};
+use tracing::warn;
+
+/// TLS settings of the links to the signers.
+#[derive(Args, Debug)]
+pub struct TlsArgs {
+    /// PEM CA certificate the signers' certificates must chain to.
+    #[clap(long, env = "GRPC_TLS_CA")]
+    pub tls_ca: Option<PathBuf>,
+    /// PEM certificate presented to signers requiring a client certificate.
+    #[clap(long, env = "GRPC_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM key of `--tls-cert`.
+    #[clap(long, env = "GRPC_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e).into())
+}
+
+impl TlsArgs {
+    pub fn config(&self) -> Result<ClientTlsConfig, Box<dyn Error>> {
+        let ca = self
+            .tls_ca
+            .as_deref()
+            .ok_or("--tls-ca is required, signers are only reached over TLS")?;
+        let mut config = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(read(ca)?));
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            config = config.identity(Identity::from_pem(read(cert)?, read(key)?));
+        }
+        Ok(config)
+    }
+}
+
+/// How calls to a signer are retried.
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy {
+    /// Attempts per call, including the first.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled before each next one.
+    pub delay: Duration,
+}
+
+/// Whether a failed call may succeed when repeated: the signer was unreachable or overloaded,
+/// not refusing the request.
+fn is_transient(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+    )
+}
+
+/// Client of a signer, sending the trace context and swap ids with every call.
+pub type Client = SignerClient<InterceptedService<Channel, TraceContext>>;
+
+#[derive(Clone)]
+pub struct Signer {
+    pub url: String,
+    client: Client,
+}
+
+impl Signer {
+    /// Runs `call` against the signer, retrying transient failures with exponential backoff.
+    /// Every signer call is safe to repeat: a repeated round overwrites the state the lost
+    /// response was computed from, and only the response that arrives is used.
+    pub async fn call<T, F, Fut>(
+        &self,
+        policy: RetryPolicy,
+        what: &str,
+        call: F,
+    ) -> Result<T, Box<dyn Error>>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, Status>>,
+    {
+        let mut delay = policy.delay;
+        let mut attempt = 1;
+        loop {
+            match call(self.client.clone()).await {
+                Ok(response) => return Ok(response.into_inner()),
+                Err(status) if is_transient(&status) && attempt < policy.attempts => {
+                    warn!(
+                        signer = self.url,
+                        attempt,
+                        code = ?status.code(),
+                        message = status.message(),
+                        "{} failed, retrying",
+                        what
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(status) => {
+                    return Err(format!(
+                        "{} failed on {}: {:?}: {}",
+                        what,
+                        self.url,
+                        status.code(),
+                        status.message()
+                    )
+                    .into())
+                }
+            }
+        }
+    }
+}
+
+/// Every signer of the group, in `--signers` order.
+pub struct Signers {
+    pub signers: Vec<Signer>,
+    pub policy: RetryPolicy,
+}
+
+impl Signers {
+    /// TLS clients of `urls`, tagging their calls with `swap_ids`. Connections are opened on
+    /// first use, so a signer that is still starting is retried like any other unreachable
+    /// signer.
+    pub fn new(
+        urls: &[String],
+        tls: &ClientTlsConfig,
+        timeout: Duration,
+        policy: RetryPolicy,
+        swap_ids: &[String],
+    ) -> Result<Self, Box<dyn Error>> {
+        let trace_context = TraceContext::new(swap_ids)?;
+        let signers = urls
+            .iter()
+            .map(|url| {
+                // tonic would connect to an `http` endpoint in plain text despite the TLS config
+                if !url.starts_with("https://") {
+                    return Err(format!(
+                        "Signer {} is not an https endpoint, signers are only reached over TLS",
+                        url
+                    )
+                    .into());
+                }
+                let channel = Endpoint::from_shared(url.clone())?
+                    .tls_config(tls.clone())?
+                    .connect_timeout(timeout)
+                    .timeout(timeout)
+                    .connect_lazy();
+                Ok(Signer {
+                    url: url.clone(),
+                    client: SignerClient::with_interceptor(channel, trace_context.clone()),
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        Ok(Signers { signers, policy })
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.signers
+            .iter()
+            .map(|signer| signer.url.clone())
+            .collect()
+    }
+
+    /// Runs `call` on every signer concurrently, each with retry, returning the responses in
+    /// signer order; fails if any signer fails.
+    pub async fn all<T, F, Fut>(&self, what: &str, call: F) -> Result<Vec<T>, Box<dyn Error>>
+    where
+        F: Fn(usize, Client) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, Status>>,
+    {
+        try_join_all(self.signers.iter().enumerate().map(|(index, signer)| {
+            let call = &call;
+            signer.call(self.policy, what, move |client| call(index, client))
+        }))
+        .await
+    }
+}
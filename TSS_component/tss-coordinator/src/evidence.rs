@@ -0,0 +1,385 @@
+//! Evidence of signer misbehavior, packaged for governance or slashing on the EVM side. Each
+//! record keeps the raw data a third party needs to check it on its own, and is summarized in
+//! a fixed-size ABI encoding whose SHA-256 a contract recomputes with the `sha256` precompile:
+//!
+//! - `invalid_share`: a signature share that failed aggregation, with the signer FROST named as
+//!   its culprit, and the session's message, commitments and the signer's verifying share.
+//! - `unauthorized_spend`: a spend of a group key output, reported by the watchtower, that no
+//!   approved payout accounts for, its witness signature verifying under the group key.
+//! - `non_template_signature`: a group signature over a message that is neither the sighash of
+//!   a transaction input nor a tagged hash the coordinator had the signers check.
+//!
+//! A threshold of the group makes a signature, and which signers did cannot be told from it, so
+//! the offender of the last two is the group key.
+
+use crate::frost;
+use crate::store::{GroupKey, Package, SessionStore, SigningSession, SigningState};
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::{Amount, ScriptBuf, Transaction, TxOut};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Version of the ABI encoding, its first word.
+pub const EVIDENCE_VERSION: u8 = 1;
+
+/// What was done, with the data it is checked from.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum EvidenceDetails {
+    InvalidShare {
+        session_id: String,
+        message_hex: String,
+        merkle_root_hex: Option<String>,
+        /// Every signer's nonce commitment of the session.
+        commitments: Vec<Package>,
+        /// The culprit's signature share.
+        share_hex: String,
+        /// The culprit's verifying share, from the public key package.
+        verifying_share_hex: String,
+        pubkp_hex: String,
+    },
+    UnauthorizedSpend {
+        /// The spending transaction, with its witnesses.
+        tx_hex: String,
+        /// Value and script of each output it spends, in input order.
+        prevout_sats: Vec<u64>,
+        prevout_scripts_hex: Vec<String>,
+        input_index: u32,
+        merkle_root_hex: Option<String>,
+        /// Witness signature of the input.
+        signature_hex: String,
+        /// Coordinator session that signed the input's sighash, if any: with one, the
+        /// coordinator was used to sign it, without one the signers bypassed it.
+        session_id: Option<String>,
+        /// Why the watchtower holds the spend unauthorized.
+        reason: String,
+    },
+    NonTemplateSignature {
+        message_hex: String,
+        merkle_root_hex: Option<String>,
+        signature_hex: String,
+        /// Coordinator session of the bare message, if it signed it.
+        session_id: Option<String>,
+    },
+}
+
+impl EvidenceDetails {
+    /// Code of the kind in the ABI encoding.
+    pub fn code(&self) -> u8 {
+        match self {
+            EvidenceDetails::InvalidShare { .. } => 1,
+            EvidenceDetails::UnauthorizedSpend { .. } => 2,
+            EvidenceDetails::NonTemplateSignature { .. } => 3,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvidenceDetails::InvalidShare { .. } => "invalid share",
+            EvidenceDetails::UnauthorizedSpend { .. } => "unauthorized spend",
+            EvidenceDetails::NonTemplateSignature { .. } => "non-template signature",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Evidence {
+    pub id: String,
+    /// Who misbehaved, 32 bytes hex: the culprit's FROST identifier, or the x-only group key
+    /// for what a threshold of the group signed.
+    pub offender: String,
+    /// What the evidence is about, 32 bytes hex: the message signed, or the spend's txid.
+    pub subject: String,
+    /// x-only group key, hex.
+    pub group_key: String,
+    /// Unix time the evidence was recorded at.
+    pub recorded_at: u64,
+    pub details: EvidenceDetails,
+}
+
+/// A 32-byte field from its hex.
+fn word(name: &str, value: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| format!("Invalid {} {}, not 32 bytes", name, value).into())
+}
+
+/// `value` right-aligned in a 32-byte word, as the ABI encodes a `uint`.
+fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+impl Evidence {
+    /// SHA-256 of the details' JSON, binding them to the encoding.
+    pub fn details_hash(&self) -> Result<[u8; 32], Box<dyn Error>> {
+        Ok(sha256::Hash::hash(&serde_json::to_vec(&self.details)?).to_byte_array())
+    }
+
+    /// `abi.encode(uint8 version, uint8 kind, bytes32 offender, bytes32 subject, bytes32
+    /// groupKey, uint64 recordedAt, bytes32 detailsHash)`.
+    pub fn abi_encoded(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let words = [
+            uint_word(EVIDENCE_VERSION.into()),
+            uint_word(self.details.code().into()),
+            word("offender", &self.offender)?,
+            word("subject", &self.subject)?,
+            word("group key", &self.group_key)?,
+            uint_word(self.recorded_at),
+            self.details_hash()?,
+        ];
+        Ok(words.concat())
+    }
+
+    /// SHA-256 of the ABI encoding, the id of the evidence on the EVM side.
+    pub fn digest(&self) -> Result<[u8; 32], Box<dyn Error>> {
+        Ok(sha256::Hash::hash(&self.abi_encoded()?).to_byte_array())
+    }
+
+    /// The evidence with its encoding and digest, as exported.
+    pub fn package(&self) -> Result<EvidencePackage, Box<dyn Error>> {
+        Ok(EvidencePackage {
+            evidence: self.clone(),
+            abi_encoded: format!("0x{}", hex::encode(self.abi_encoded()?)),
+            digest: format!("0x{}", hex::encode(self.digest()?)),
+        })
+    }
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} by {} over {} at {}",
+            self.id,
+            self.details.name(),
+            self.offender,
+            self.subject,
+            self.recorded_at
+        )
+    }
+}
+
+/// Evidence as handed to governance: the record, its ABI encoding and its digest, `0x` hex.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EvidencePackage {
+    pub evidence: Evidence,
+    pub abi_encoded: String,
+    pub digest: String,
+}
+
+/// Output key of the group key tweaked with `merkle_root_hex`, which its signatures verify
+/// under.
+fn output_key(
+    key: &GroupKey,
+    merkle_root_hex: Option<&str>,
+) -> Result<(ScriptBuf, XOnlyPublicKey), Box<dyn Error>> {
+    let spk = frost::script_pubkey(&key.verify_key_hex, merkle_root_hex)?;
+    let output_key = XOnlyPublicKey::from_slice(&spk.as_bytes()[2..])
+        .map_err(|e| format!("Invalid output key: {}", e))?;
+    Ok((spk, output_key))
+}
+
+/// Session over `message_hex` that is done, if the coordinator signed it.
+fn signed_session(
+    store: &SessionStore,
+    message_hex: &str,
+    merkle_root_hex: Option<&str>,
+) -> Result<Option<SigningSession>, Box<dyn Error>> {
+    Ok(store
+        .find_signing(message_hex, merkle_root_hex)?
+        .filter(|session| matches!(session.state, SigningState::Done { .. })))
+}
+
+/// Records the signer `invalid` names as having sent a bad share in `session`.
+pub fn invalid_share(
+    store: &SessionStore,
+    key: &GroupKey,
+    session: &SigningSession,
+    commitments: &[Package],
+    shares: &[Package],
+    invalid: &frost::InvalidShare,
+) -> Result<Evidence, Box<dyn Error>> {
+    let share = shares
+        .iter()
+        .find(|share| share.id_hex == invalid.id_hex)
+        .ok_or_else(|| format!("No share of signer {}", invalid.id_hex))?;
+    store.record_evidence(
+        &invalid.id_hex,
+        &session.message_hex,
+        &frost::group_key(&key.verify_key_hex)?.to_string(),
+        EvidenceDetails::InvalidShare {
+            session_id: session.id.clone(),
+            message_hex: session.message_hex.clone(),
+            merkle_root_hex: session.merkle_root_hex.clone(),
+            commitments: commitments.to_vec(),
+            share_hex: share.pkg_hex.clone(),
+            verifying_share_hex: invalid.verifying_share_hex.clone(),
+            pubkp_hex: key.pubkp_hex.clone(),
+        },
+    )
+}
+
+/// Records the spend `tx_hex` as unauthorized, once per input spending an output of the group
+/// key (tweaked with `merkle_root_hex`) with a key-path signature that verifies under it. The
+/// spent outputs pay the group key unless `prevout_scripts_hex` gives each one's script.
+pub fn unauthorized_spend(
+    store: &SessionStore,
+    key: &GroupKey,
+    tx_hex: &str,
+    prevout_sats: &[u64],
+    prevout_scripts_hex: &[String],
+    merkle_root_hex: Option<&str>,
+    reason: &str,
+) -> Result<Vec<Evidence>, Box<dyn Error>> {
+    let tx: Transaction =
+        deserialize(&hex::decode(tx_hex).map_err(|e| format!("Invalid transaction hex: {}", e))?)?;
+    let txid = tx.compute_txid();
+    if prevout_sats.len() != tx.input.len() {
+        return Err(format!(
+            "Got {} prevout values for {} inputs",
+            prevout_sats.len(),
+            tx.input.len()
+        )
+        .into());
+    }
+    let (spent_spk, output_key) = output_key(key, merkle_root_hex)?;
+    let scripts = if prevout_scripts_hex.is_empty() {
+        vec![spent_spk.clone(); tx.input.len()]
+    } else if prevout_scripts_hex.len() == tx.input.len() {
+        prevout_scripts_hex
+            .iter()
+            .map(|script_hex| ScriptBuf::from_hex(script_hex))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid prevout script: {}", e))?
+    } else {
+        return Err(format!(
+            "Got {} prevout scripts for {} inputs",
+            prevout_scripts_hex.len(),
+            tx.input.len()
+        )
+        .into());
+    };
+    let prevouts: Vec<TxOut> = prevout_sats
+        .iter()
+        .zip(scripts)
+        .map(|(&sats, script_pubkey)| TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey,
+        })
+        .collect();
+    let prevout_scripts_hex: Vec<String> = prevouts
+        .iter()
+        .map(|prevout| prevout.script_pubkey.to_hex_string())
+        .collect();
+
+    let secp = Secp256k1::verification_only();
+    let group_key = frost::group_key(&key.verify_key_hex)?.to_string();
+    let mut cache = SighashCache::new(&tx);
+    let mut recorded = Vec::new();
+    for (index, input) in tx.input.iter().enumerate() {
+        // A key-path spend's witness is the signature alone
+        let signature_bytes = match input.witness.nth(0) {
+            Some(bytes)
+                if prevouts[index].script_pubkey == spent_spk && input.witness.len() == 1 =>
+            {
+                bytes
+            }
+            _ => continue,
+        };
+        let signature = bitcoin::taproot::Signature::from_slice(signature_bytes)
+            .map_err(|e| format!("Invalid signature of input {} of {}: {}", index, txid, e))?;
+        let sighash = cache.taproot_key_spend_signature_hash(
+            index,
+            &Prevouts::All(&prevouts),
+            signature.sighash_type,
+        )?;
+        secp.verify_schnorr(
+            &signature.signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            &output_key,
+        )
+        .map_err(|_| {
+            format!(
+                "The signature of input {} of {} does not verify under the group key",
+                index, txid
+            )
+        })?;
+        let message_hex = hex::encode(sighash.to_byte_array());
+        let session_id =
+            signed_session(store, &message_hex, merkle_root_hex)?.map(|session| session.id);
+        recorded.push(store.record_evidence(
+            &group_key,
+            &txid.to_string(),
+            &group_key,
+            EvidenceDetails::UnauthorizedSpend {
+                tx_hex: tx_hex.to_string(),
+                prevout_sats: prevout_sats.to_vec(),
+                prevout_scripts_hex: prevout_scripts_hex.clone(),
+                input_index: index as u32,
+                merkle_root_hex: merkle_root_hex.map(str::to_string),
+                signature_hex: hex::encode(signature_bytes),
+                session_id,
+                reason: reason.to_string(),
+            },
+        )?);
+    }
+    if recorded.is_empty() {
+        return Err(format!("{} spends no output of the group key by its key path", txid).into());
+    }
+    Ok(recorded)
+}
+
+/// Records the group signature `signature_hex` over `message_hex` as made outside any
+/// template. Refused when the signature does not verify, or when the coordinator signed the
+/// message with its context, a transaction input or a tagged preimage, checked.
+pub fn non_template_signature(
+    store: &SessionStore,
+    key: &GroupKey,
+    message_hex: &str,
+    signature_hex: &str,
+    merkle_root_hex: Option<&str>,
+) -> Result<Evidence, Box<dyn Error>> {
+    let message = word("message", message_hex)?;
+    let signature = hex::decode(signature_hex)
+        .ok()
+        .and_then(|bytes| schnorr::Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| format!("Invalid signature {}", signature_hex))?;
+    let (_, output_key) = output_key(key, merkle_root_hex)?;
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature, &Message::from_digest(message), &output_key)
+        .map_err(|_| "The signature does not verify under the group key")?;
+    let session = signed_session(store, message_hex, merkle_root_hex)?;
+    if let Some(SigningSession {
+        id,
+        context: Some(_),
+        ..
+    }) = &session
+    {
+        return Err(format!(
+            "{} was signed in session {} with its context checked",
+            message_hex, id
+        )
+        .into());
+    }
+    let group_key = frost::group_key(&key.verify_key_hex)?.to_string();
+    store.record_evidence(
+        &group_key,
+        message_hex,
+        &group_key,
+        EvidenceDetails::NonTemplateSignature {
+            message_hex: message_hex.to_string(),
+            merkle_root_hex: merkle_root_hex.map(str::to_string),
+            signature_hex: signature_hex.to_string(),
+            session_id: session.map(|session| session.id),
+        },
+    )
+}
@@ -0,0 +1,152 @@
+//! Coordinator side of FROST: aggregating the signers' shares into a BIP-340 signature.
+
+use crate::store::Package;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::TapNodeHash;
+use bitcoin::{Address, Network, ScriptBuf};
+use frost_secp256k1_tr::keys::{PublicKeyPackage, Tweak};
+use frost_secp256k1_tr::round1::SigningCommitments;
+use frost_secp256k1_tr::round2::SignatureShare;
+use frost_secp256k1_tr::{Identifier, SigningPackage};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// A signature share that does not verify against its signer's verifying share, failing the
+/// aggregation; the culprit FROST names.
+#[derive(Debug)]
+pub struct InvalidShare {
+    /// Identifier of the signer that sent the share, hex.
+    pub id_hex: String,
+    /// Its verifying share in the group's public key package, hex.
+    pub verifying_share_hex: String,
+}
+
+impl fmt::Display for InvalidShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid signature share from signer {}", self.id_hex)
+    }
+}
+
+impl Error for InvalidShare {}
+
+pub fn identifier(id_hex: &str) -> Result<Identifier, Box<dyn Error>> {
+    Identifier::deserialize(&hex::decode(id_hex)?)
+        .map_err(|e| format!("Invalid identifier {}: {}", id_hex, e).into())
+}
+
+/// Aggregates the signature shares over `message_hex` and checks the result against the group
+/// key, tweaked with the script tree root `merkle_root_hex` as the signers sign for a key-path
+/// spend; an empty tree without it. Returns the 64-byte signature, hex-encoded. A bad share
+/// fails it with an [`InvalidShare`] naming the signer that sent it.
+pub fn aggregate(
+    message_hex: &str,
+    commitments: &[Package],
+    shares: &[Package],
+    pubkp_hex: &str,
+    merkle_root_hex: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let merkle_root = merkle_root_hex
+        .map(hex::decode)
+        .transpose()
+        .map_err(|e| format!("Invalid merkle root: {}", e))?;
+    let mut commitments_map = BTreeMap::new();
+    for commitment in commitments {
+        let parsed = SigningCommitments::deserialize(&hex::decode(&commitment.pkg_hex)?)
+            .map_err(|e| format!("Invalid commitment of {}: {}", commitment.id_hex, e))?;
+        commitments_map.insert(identifier(&commitment.id_hex)?, parsed);
+    }
+    let mut shares_map = BTreeMap::new();
+    for share in shares {
+        let parsed = SignatureShare::deserialize(&hex::decode(&share.pkg_hex)?)
+            .map_err(|e| format!("Invalid signature share of {}: {}", share.id_hex, e))?;
+        shares_map.insert(identifier(&share.id_hex)?, parsed);
+    }
+
+    let message = hex::decode(message_hex)?;
+    let signing_package = SigningPackage::new(commitments_map, &message);
+    let pubkp = PublicKeyPackage::deserialize(&hex::decode(pubkp_hex)?)
+        .map_err(|e| format!("Invalid public key package: {}", e))?;
+    let signature = frost_secp256k1_tr::aggregate_with_tweak(
+        &signing_package,
+        &shares_map,
+        &pubkp,
+        merkle_root.as_deref(),
+    )
+    .map_err(|e| -> Box<dyn Error> {
+        match e.culprit() {
+            Some(culprit) => Box::new(InvalidShare {
+                id_hex: hex::encode(culprit.serialize()),
+                verifying_share_hex: pubkp
+                    .verifying_shares()
+                    .get(&culprit)
+                    .and_then(|share| share.serialize().ok())
+                    .map(hex::encode)
+                    .unwrap_or_default(),
+            }),
+            None => format!("Aggregation failed: {}", e).into(),
+        }
+    })?;
+    pubkp
+        .tweak(merkle_root.as_deref())
+        .verifying_key()
+        .verify(&message, &signature)
+        .map_err(|e| format!("Aggregated signature does not verify: {}", e))?;
+    let bytes = signature
+        .serialize()
+        .map_err(|e| format!("Serialize signature failed: {}", e))?;
+    Ok(hex::encode(bytes))
+}
+
+/// BIP-340 tagged hash of `data` under `tag`.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_byte_array());
+    engine.input(tag.as_byte_array());
+    engine.input(data);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// x-only group key from its hex, which is x-only or compressed.
+pub fn group_key(verify_key_hex: &str) -> Result<XOnlyPublicKey, Box<dyn Error>> {
+    let bytes = hex::decode(verify_key_hex)?;
+    let x_only = match bytes.len() {
+        32 => &bytes[..],
+        33 if bytes[0] == 0x02 || bytes[0] == 0x03 => &bytes[1..],
+        len => return Err(format!("Invalid group key length: {}", len).into()),
+    };
+    Ok(XOnlyPublicKey::from_slice(x_only).map_err(|e| format!("Invalid group key: {}", e))?)
+}
+
+/// Taproot address of the group key on `network`; the key is x-only or compressed.
+pub fn taproot_address(verify_key_hex: &str, network: Network) -> Result<Address, Box<dyn Error>> {
+    Ok(Address::p2tr(
+        &Secp256k1::verification_only(),
+        group_key(verify_key_hex)?,
+        None,
+        network,
+    ))
+}
+
+/// Script of the output the group key spends: the vault, or with `merkle_root_hex` the
+/// deposit address committing to that script tree.
+pub fn script_pubkey(
+    verify_key_hex: &str,
+    merkle_root_hex: Option<&str>,
+) -> Result<ScriptBuf, Box<dyn Error>> {
+    let merkle_root = merkle_root_hex
+        .map(|merkle_root_hex| {
+            let bytes: [u8; 32] = hex::decode(merkle_root_hex)?
+                .try_into()
+                .map_err(|_| format!("Invalid merkle root {}", merkle_root_hex))?;
+            Ok::<_, Box<dyn Error>>(TapNodeHash::from_byte_array(bytes))
+        })
+        .transpose()?;
+    Ok(ScriptBuf::new_p2tr(
+        &Secp256k1::verification_only(),
+        group_key(verify_key_hex)?,
+        merkle_root,
+    ))
+}
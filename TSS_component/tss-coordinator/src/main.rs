@@ -0,0 +1,1338 @@
+//! Coordinator of the signer nodes: drives the three DKG rounds and the two FROST signing
+//! rounds over gRPC, relaying each round's packages between the signers and aggregating the
+//! signature shares. Every round's result is persisted before the next round starts, and
+//! calls failing on an unreachable signer are retried.
+//!
+//! Given a whole transaction (`sign --tx-hex`), the coordinator computes its inputs' sighashes
+//! itself and checks its payouts against the `--policy-*` hooks before anything is signed. With
+//! a policy set, bare messages are refused, as their payouts cannot be checked. Each message
+//! goes to the signers with what it is the hash of, the transaction input or the preimage of
+//! a tagged hash, so a signer node can check it on its own.
+//!
+//! `attest` co-signs a proof's statement for the ZKP `submit`: the group key signs a tagged hash
+//! of the verification key and the public values hash, which no Bitcoin sighash can equal, so
+//! it is allowed under a policy.
+//!
+//! `pause` stops the bridge in an emergency: the signers sign a pause notice, and from then on
+//! the coordinator refuses to sign or attest anything. The notice is handed to the ZKP
+//! services, which stop submitting and paying out. Only `unpause`, naming the pause it lifts,
+//! has a resume notice signed and signing start again.
+//!
+//! `evidence` keeps what signers were caught doing, for governance or slashing on the EVM side:
+//! a share failing aggregation is recorded against the signer FROST names as its culprit, and
+//! a vault spend the watchtower reports or a group signature outside any template against the
+//! group key, once its signature verifies. `evidence export` hands the records out with their
+//! ABI encoding.
+//!
+//! `ceremony run` is the DKG of a key ceremony: it runs a fresh one, writes the broadcast
+//! packages and every signer's result to a transcript, and checks the group key against it.
+//! `ceremony verify` rechecks a transcript and its report with no signers, so anyone can.
+
+// Usage Example:
+// cargo run --release --bin coordinator -- --signers https://localhost:50051,https://localhost:50052,https://localhost:50053 --tls-ca ./certs/ca.pem --tls-cert ./certs/coordinator.pem --tls-key ./certs/coordinator.key --db ./coordinator-db dkg
+// cargo run --release --bin coordinator -- --db ./coordinator-db sign --message-hex <32-byte sighash hex>
+// cargo run --release --bin coordinator -- --db ./coordinator-db sign --message-hex <sighash hex> --merkle-root-hex <deposit tweak hex>
+// cargo run --release --bin coordinator -- --db ./coordinator-db sign --message-hex <input 0 sighash>,<input 1 sighash>
+// cargo run --release --bin coordinator -- --db ./coordinator-db --policy-max-payout-sats 10000000 sign --tx-hex <unsigned tx hex> --prevout-sats 150000,80000
+// cargo run --release --bin coordinator -- --db ./coordinator-db attest --vkey-hex <32-byte vkey> --public-values-hash-hex <keccak256 of the public values>
+// cargo run --release --bin coordinator -- --db ./coordinator-db resume
+// cargo run --release --bin coordinator -- --db ./coordinator-db pause --reason "payout to an unknown address" --out ./pause-notice.json
+// cargo run --release --bin coordinator -- --db ./coordinator-db unpause --pause-sequence 1 --reason "vault key rotated" --out ./pause-notice.json
+// cargo run --release --bin coordinator -- --db ./coordinator-db sessions
+// cargo run --release --bin coordinator -- --db ./coordinator-db evidence spend --tx-hex <signed tx hex> --prevout-sats 150000 --reason "no payout job spends this output"
+// cargo run --release --bin coordinator -- --db ./coordinator-db evidence export --out ./evidence.json
+// cargo run --release --bin coordinator -- --db ./coordinator-db ceremony run --transcript ./ceremony-transcript.json --report ./ceremony-report.json
+// cargo run --release --bin coordinator -- ceremony verify --transcript ./ceremony-transcript.json --report ./ceremony-report.json
+// OTEL_EXPORTER_OTLP_ENDPOINT=http://127.0.0.1:4317 cargo run --release --bin coordinator -- --db ./coordinator-db --swap-id burn:3 sign --tx-hex <unsigned tx hex> --prevout-sats 150000
+mod ceremony;
+mod client;
+mod evidence;
+mod frost;
+mod pause;
+mod policy;
+mod store;
+mod telemetry;
+
+pub mod proto {
+    tonic::include_proto!("tss");
+}
+
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::Hash;
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::{Address, Amount, Transaction, TxOut};
+use ceremony::{CeremonyReport, SignerResult, Transcript};
+use clap::{Parser, Subcommand};
+use client::{RetryPolicy, Signers, TlsArgs};
+use pause::{PauseAction, PauseNotice, PAUSE_TAG};
+use policy::{Payout, PolicyArgs, PolicyEngine};
+use serde::Serialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use store::{
+    now, DkgSession, DkgState, GroupKey, Package, Round2Package, SessionStore, SigningContext,
+    SigningSession, SigningState,
+};
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// Tag of the hash `attest` signs; must match `TSS_ATTESTATION_TAG` of the ZKP `lib_struct`.
+const ATTESTATION_TAG: &str = "ZKBTC/attestation";
+
+/// Network profiles of the ZKP `profile` module; the coordinator only takes their network.
+#[derive(Copy, Clone, clap::ValueEnum, Debug)]
+enum Profile {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Profile {
+    fn network(self) -> bitcoin::Network {
+        match self {
+            Profile::Mainnet => bitcoin::Network::Bitcoin,
+            Profile::Testnet => bitcoin::Network::Testnet,
+            Profile::Signet => bitcoin::Network::Signet,
+            Profile::Regtest => bitcoin::Network::Regtest,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// gRPC endpoints of the signer nodes, `https` URLs, comma-separated. Every listed signer
+    /// takes part in each DKG and signing session.
+    #[clap(long, env = "SIGNER_URLS", value_delimiter = ',')]
+    signers: Vec<String>,
+    /// sled database of the sessions and the group key, kept across restarts so that an
+    /// interrupted session resumes; without it, sessions are lost on exit.
+    #[clap(long, env = "COORDINATOR_DB")]
+    db: Option<PathBuf>,
+    /// Bitcoin network of the group's Taproot address; the profile's network, else testnet, by
+    /// default.
+    #[clap(long, env = "BITCOIN_NETWORK")]
+    network: Option<bitcoin::Network>,
+    /// Network profile of the bridge, as the ZKP binaries take it.
+    #[clap(long, value_enum, env = "BRIDGE_PROFILE")]
+    profile: Option<Profile>,
+    /// Attempts per signer call before the session fails.
+    #[clap(long, env = "SIGNER_RETRIES", default_value_t = 5)]
+    retries: u32,
+    /// Milliseconds before the first retry; doubled before each next one.
+    #[clap(long, default_value_t = 500)]
+    retry_delay_ms: u64,
+    /// Seconds a signer call may take.
+    #[clap(long, default_value_t = 30)]
+    timeout: u64,
+    /// Swaps the run is for, e.g. `burn:3`; repeat the flag or separate them with commas.
+    /// Tagged on the run's spans and sent to the signers with the trace context.
+    #[clap(long = "swap-id", env = "SWAP_IDS", value_delimiter = ',')]
+    swap_ids: Vec<String>,
+    #[clap(flatten)]
+    tls: TlsArgs,
+    #[clap(flatten)]
+    policy: PolicyArgs,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+impl Args {
+    fn bitcoin_network(&self) -> bitcoin::Network {
+        self.network
+            .or(self.profile.map(Profile::network))
+            .unwrap_or(bitcoin::Network::Testnet)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the DKG, unless every signer already holds a share of the same group key. Prints
+    /// the group key and its Taproot address.
+    Dkg {
+        /// Run a new DKG even though some signers already hold shares, replacing them.
+        #[clap(long)]
+        force: bool,
+    },
+    /// Sign messages, e.g. the Taproot sighashes of a transaction's inputs, with the group key,
+    /// one session after the other. Prints one signature per line, in the order of the
+    /// messages; a message already signed returns its recorded signature.
+    Sign {
+        /// Messages to sign, hex-encoded; repeat the flag or separate them with commas.
+        #[clap(
+            long,
+            value_delimiter = ',',
+            required_unless_present = "tx_hex",
+            conflicts_with = "tx_hex"
+        )]
+        message_hex: Vec<String>,
+        /// Unsigned transaction whose inputs, all spending the group key's output, are signed;
+        /// its payouts are checked against the policy first.
+        #[clap(long, requires = "prevout_sats")]
+        tx_hex: Option<String>,
+        /// Value of each output `--tx-hex` spends, in input order.
+        #[clap(long, value_delimiter = ',')]
+        prevout_sats: Vec<u64>,
+        /// Script tree root of the spent output's key, to spend a per-request deposit address:
+        /// its `merkle_root_hex` in the deposit address book. Without it, the vault is spent.
+        #[clap(long)]
+        merkle_root_hex: Option<String>,
+    },
+    /// Sign the statement of a proof fixture, its verification key and the keccak256 hash of
+    /// its public values, for its submission. Prints the group signature.
+    Attest {
+        /// 32-byte verification key hash of the proof's program, hex.
+        #[clap(long)]
+        vkey_hex: String,
+        /// keccak256 hash of the proof's ABI-encoded public values, hex.
+        #[clap(long)]
+        public_values_hash_hex: String,
+    },
+    /// Finish the sessions an earlier run left unfinished.
+    Resume,
+    /// Print the recorded sessions and exit.
+    Sessions,
+    /// Stop signing: have the signers sign a pause notice, printed as JSON for the ZKP
+    /// services. Once paused, `sign`, `attest` and `resume` are refused.
+    Pause {
+        /// Why the bridge is paused, carried in the signed notice.
+        #[clap(long)]
+        reason: String,
+        /// File the notice is also written to.
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+    /// Lift a pause: have the signers sign a resume notice, printed as JSON for the ZKP
+    /// services, and sign again.
+    Unpause {
+        /// Sequence of the pause being lifted, as printed by `pause` and `sessions`; any other
+        /// pause is refused.
+        #[clap(long)]
+        pause_sequence: u64,
+        /// Why signing may resume, carried in the signed notice.
+        #[clap(long)]
+        reason: String,
+        /// File the notice is also written to.
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+    /// Run a key ceremony, or check one's transcript.
+    Ceremony {
+        #[clap(subcommand)]
+        command: CeremonyCommand,
+    },
+    /// Record or export evidence of signer misbehavior. Needs no signers.
+    Evidence {
+        #[clap(subcommand)]
+        command: EvidenceCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CeremonyCommand {
+    /// Run a fresh DKG across the signers, refused if they already hold shares unless
+    /// `--force` is set. Writes its transcript, checks the group key against it and prints the
+    /// ceremony report; fails if a check did not pass.
+    Run {
+        /// File the transcript is written to.
+        #[clap(long)]
+        transcript: PathBuf,
+        /// File the report is also written to.
+        #[clap(long)]
+        report: Option<PathBuf>,
+        /// Replace the shares the signers hold.
+        #[clap(long)]
+        force: bool,
+    },
+    #[clap(flatten)]
+    Offline(OfflineCeremonyCommand),
+}
+
+/// `ceremony` commands that need no signers.
+#[derive(Subcommand, Debug)]
+enum OfflineCeremonyCommand {
+    /// Write the transcript of a finished DKG session, the latest with one by default, and
+    /// print its report. Needs no signers.
+    Transcript {
+        /// DKG session id, as printed by `sessions`.
+        #[clap(long)]
+        session: Option<String>,
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Check a transcript on its own and print its report; with `--report`, the report
+    /// published with it must be the same. Needs no signers nor database, and takes the
+    /// network the report's Taproot address is on.
+    Verify {
+        #[clap(long)]
+        transcript: PathBuf,
+        #[clap(long)]
+        report: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum EvidenceCommand {
+    /// Print the recorded evidence.
+    List,
+    /// Print evidence as JSON, with its ABI encoding and digest for the EVM side; all of it
+    /// without ids.
+    Export {
+        /// Ids of the evidence, as printed by `evidence list`.
+        ids: Vec<String>,
+        /// File the JSON is also written to.
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+    /// Record a spend of the group key's outputs no approved payout accounts for, as the
+    /// watchtower reports it. Every input spending the group key by a signature that verifies
+    /// under it is recorded; prints the evidence as `export` does.
+    Spend {
+        /// The spending transaction, signed.
+        #[clap(long)]
+        tx_hex: String,
+        /// Value of each output it spends, in input order.
+        #[clap(long, value_delimiter = ',', required = true)]
+        prevout_sats: Vec<u64>,
+        /// Script of each output it spends, hex, in input order; all paying the group key by
+        /// default.
+        #[clap(long = "prevout-script-hex", value_delimiter = ',')]
+        prevout_scripts_hex: Vec<String>,
+        /// Script tree root the spent key is tweaked with, for a per-request deposit address.
+        #[clap(long)]
+        merkle_root_hex: Option<String>,
+        /// Why the spend is unauthorized.
+        #[clap(long)]
+        reason: String,
+    },
+    /// Record a group signature over a message that is no transaction input's sighash or
+    /// tagged hash the signers checked.
+    Signature {
+        #[clap(long)]
+        message_hex: String,
+        /// 64-byte BIP-340 signature, hex.
+        #[clap(long)]
+        signature_hex: String,
+        /// Script tree root the key is tweaked with.
+        #[clap(long)]
+        merkle_root_hex: Option<String>,
+    },
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Dkg { .. } => "dkg",
+            Command::Sign { .. } => "sign",
+            Command::Attest { .. } => "attest",
+            Command::Resume => "resume",
+            Command::Sessions => "sessions",
+            Command::Pause { .. } => "pause",
+            Command::Unpause { .. } => "unpause",
+            Command::Ceremony { .. } => "ceremony",
+            Command::Evidence { .. } => "evidence",
+        }
+    }
+}
+
+struct Coordinator {
+    signers: Signers,
+    store: SessionStore,
+    policy: PolicyEngine,
+    network: bitcoin::Network,
+}
+
+/// Packages of every signer but the one with `id_hex`.
+fn others(packages: &[Package], id_hex: &str) -> Vec<proto::Package> {
+    packages
+        .iter()
+        .filter(|package| package.id_hex != id_hex)
+        .map(to_proto)
+        .collect()
+}
+
+fn to_proto(package: &Package) -> proto::Package {
+    proto::Package {
+        id_hex: package.id_hex.clone(),
+        pkg_hex: package.pkg_hex.clone(),
+    }
+}
+
+fn context_to_proto(context: &SigningContext) -> proto::SigningContext {
+    use proto::signing_context::Kind;
+    let kind = match context {
+        SigningContext::Transaction {
+            tx_hex,
+            prevout_sats,
+            input_index,
+        } => Kind::Transaction(proto::TransactionInput {
+            tx_hex: tx_hex.clone(),
+            prevout_sats: prevout_sats.clone(),
+            input_index: *input_index,
+        }),
+        SigningContext::Tagged { tag, preimage_hex } => Kind::Tagged(proto::TaggedMessage {
+            tag: tag.clone(),
+            preimage_hex: preimage_hex.clone(),
+        }),
+    };
+    proto::SigningContext { kind: Some(kind) }
+}
+
+impl Coordinator {
+    /// Group key held by every signer, or `None` if none holds one. Signers holding different
+    /// keys, or only some of them holding one, is an error unless `force` is set.
+    async fn existing_key(&self, force: bool) -> Result<Option<GroupKey>, Box<dyn Error>> {
+        let statuses = self
+            .signers
+            .all("DKG status", |_, mut client| async move {
+                client.dkg_status(proto::DkgStatusRequest {}).await
+            })
+            .await?;
+        let holding = statuses.iter().filter(|status| status.has_keys).count();
+        if holding == 0 || force {
+            return Ok(None);
+        }
+        if holding < statuses.len() {
+            return Err(format!(
+                "{} of {} signers already hold a key share; a new DKG would replace them, pass --force to run it anyway",
+                holding,
+                statuses.len()
+            )
+            .into());
+        }
+        let first = &statuses[0];
+        if statuses
+            .iter()
+            .any(|status| status.pubkp_hex != first.pubkp_hex)
+        {
+            return Err("Signers hold shares of different group keys".into());
+        }
+        Ok(Some(GroupKey {
+            verify_key_hex: first.verify_key_hex.clone(),
+            pubkp_hex: first.pubkp_hex.clone(),
+        }))
+    }
+
+    /// Latest DKG session an earlier run left unfinished.
+    fn unfinished_dkg(&self) -> Result<Option<DkgSession>, Box<dyn Error>> {
+        Ok(self
+            .store
+            .dkg_sessions()?
+            .into_iter()
+            .rev()
+            .find(|session| {
+                !matches!(
+                    session.state,
+                    DkgState::Done { .. } | DkgState::Failed { .. }
+                )
+            }))
+    }
+
+    /// Resumes an unfinished DKG session, as some signers may already hold its key shares;
+    /// otherwise runs a new one.
+    async fn dkg(&self, force: bool) -> Result<GroupKey, Box<dyn Error>> {
+        if let Some(session) = self.unfinished_dkg()? {
+            info!(session = session.id, "Resuming DKG");
+            return self.drive_dkg(session).await;
+        }
+        if let Some(key) = self.existing_key(force).await? {
+            info!("Every signer holds a share of the group key, no DKG needed");
+            self.store.set_group_key(&key)?;
+            return Ok(key);
+        }
+        let session = self.store.create_dkg(self.signers.urls())?;
+        self.drive_dkg(session).await
+    }
+
+    /// Runs the remaining rounds of `session`, persisting it after each. A round that fails
+    /// after its retries fails the session.
+    async fn drive_dkg(&self, mut session: DkgSession) -> Result<GroupKey, Box<dyn Error>> {
+        if session.signers != self.signers.urls() {
+            return Err(format!(
+                "DKG session {} was started with signers {:?}",
+                session.id, session.signers
+            )
+            .into());
+        }
+        loop {
+            let span = info_span!("dkg_round", session = session.id);
+            let next = match self.dkg_round(&session).instrument(span).await {
+                Ok(next) => next,
+                Err(e) => DkgState::Failed {
+                    reason: e.to_string(),
+                },
+            };
+            session.state = next;
+            session.updated_at = now();
+            self.store.put_dkg(&session)?;
+            match &session.state {
+                DkgState::Done { key, .. } => {
+                    info!(session = session.id, "DKG completed");
+                    self.store.set_group_key(key)?;
+                    return Ok(key.clone());
+                }
+                DkgState::Failed { reason } => {
+                    return Err(format!("DKG session {} failed: {}", session.id, reason).into())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Runs the round `session` waits for and returns the state after it.
+    async fn dkg_round(&self, session: &DkgSession) -> Result<DkgState, Box<dyn Error>> {
+        let session_id = session.id.clone();
+        match &session.state {
+            DkgState::Round1 => {
+                info!(session = session_id, "Starting DKG round 1");
+                let responses = self
+                    .signers
+                    .all("DKG round 1", |_, mut client| {
+                        let session_id = session_id.clone();
+                        async move {
+                            client
+                                .dkg_round1(proto::DkgRound1Request { session_id })
+                                .await
+                        }
+                    })
+                    .await?;
+                let round1 = responses
+                    .into_iter()
+                    .map(|response| Package {
+                        id_hex: response.id_hex,
+                        pkg_hex: response.pkg_hex,
+                    })
+                    .collect();
+                Ok(DkgState::Round2 { round1 })
+            }
+            DkgState::Round2 { round1 } => {
+                info!(session = session_id, "Starting DKG round 2");
+                let responses = self
+                    .signers
+                    .all("DKG round 2", |index, mut client| {
+                        let request = proto::DkgRound2Request {
+                            session_id: session_id.clone(),
+                            round1_packages: others(round1, &round1[index].id_hex),
+                        };
+                        async move { client.dkg_round2(request).await }
+                    })
+                    .await?;
+                let round2 = responses
+                    .into_iter()
+                    .flat_map(|response| {
+                        let from_hex = response.id_hex;
+                        response
+                            .round2_packages
+                            .into_iter()
+                            .map(move |package| Round2Package {
+                                from_hex: from_hex.clone(),
+                                to_hex: package.id_hex,
+                                pkg_hex: package.pkg_hex,
+                            })
+                    })
+                    .collect();
+                Ok(DkgState::Round3 {
+                    round1: round1.clone(),
+                    round2,
+                })
+            }
+            DkgState::Round3 { round1, round2 } => {
+                info!(session = session_id, "Starting DKG round 3");
+                let responses = self
+                    .signers
+                    .all("DKG round 3", |index, mut client| {
+                        let id_hex = &round1[index].id_hex;
+                        let request = proto::DkgRound3Request {
+                            session_id: session_id.clone(),
+                            round1_packages: others(round1, id_hex),
+                            round2_packages: round2
+                                .iter()
+                                .filter(|package| &package.to_hex == id_hex)
+                                .map(|package| proto::Package {
+                                    id_hex: package.from_hex.clone(),
+                                    pkg_hex: package.pkg_hex.clone(),
+                                })
+                                .collect(),
+                        };
+                        async move { client.dkg_round3(request).await }
+                    })
+                    .await?;
+                let first = &responses[0];
+                if responses
+                    .iter()
+                    .any(|response| response.pubkp_hex != first.pubkp_hex)
+                {
+                    return Err("Signers derived different group keys".into());
+                }
+                let key = GroupKey {
+                    verify_key_hex: first.verify_key_hex.clone(),
+                    pubkp_hex: first.pubkp_hex.clone(),
+                };
+                let results = responses
+                    .into_iter()
+                    .map(|response| SignerResult {
+                        id_hex: response.id_hex,
+                        verify_key_hex: response.verify_key_hex,
+                        pubkp_hex: response.pubkp_hex,
+                    })
+                    .collect();
+                Ok(DkgState::Done {
+                    key,
+                    transcript: Some(Transcript::new(session, round1, round2, results)),
+                })
+            }
+            DkgState::Done { .. } | DkgState::Failed { .. } => {
+                Err(format!("DKG session {} is finished", session.id).into())
+            }
+        }
+    }
+
+    /// Runs a fresh DKG for a key ceremony and returns its transcript. Refused while a DKG is
+    /// unfinished, or while the signers hold shares unless `force` is set.
+    async fn ceremony(&self, force: bool) -> Result<Transcript, Box<dyn Error>> {
+        if let Some(session) = self.unfinished_dkg()? {
+            return Err(format!(
+                "DKG session {} is unfinished, finish it with `resume` first",
+                session.id
+            )
+            .into());
+        }
+        if let Some(key) = self.existing_key(force).await? {
+            return Err(format!(
+                "Every signer holds a share of group key {}, pass --force to replace it",
+                key.verify_key_hex
+            )
+            .into());
+        }
+        let session = self.store.create_dkg(self.signers.urls())?;
+        let id = session.id.clone();
+        info!(session = id, "Starting key ceremony");
+        self.drive_dkg(session).await?;
+        match self.store.dkg_session(&id)?.map(|session| session.state) {
+            Some(DkgState::Done {
+                transcript: Some(transcript),
+                ..
+            }) => Ok(transcript),
+            _ => Err(format!("DKG session {} kept no transcript", id).into()),
+        }
+    }
+
+    /// Group key from the last DKG, else from the signers.
+    async fn group_key(&self) -> Result<GroupKey, Box<dyn Error>> {
+        if let Some(key) = self.store.group_key()? {
+            return Ok(key);
+        }
+        let key = self
+            .existing_key(false)
+            .await?
+            .ok_or("The signers hold no key share, run `coordinator dkg` first")?;
+        self.store.set_group_key(&key)?;
+        Ok(key)
+    }
+
+    /// Signs `message_hex`, sending the signers its `context` to check it against.
+    async fn sign(
+        &self,
+        message_hex: &str,
+        merkle_root_hex: Option<&str>,
+        context: Option<SigningContext>,
+    ) -> Result<String, Box<dyn Error>> {
+        hex::decode(message_hex).map_err(|e| format!("Invalid message hex: {}", e))?;
+        if let Some(merkle_root_hex) = merkle_root_hex {
+            match hex::decode(merkle_root_hex) {
+                Ok(bytes) if bytes.len() == 32 => {}
+                _ => return Err(format!("Invalid merkle root {}", merkle_root_hex).into()),
+            }
+        }
+        let session = match self.store.find_signing(message_hex, merkle_root_hex)? {
+            Some(SigningSession {
+                state: SigningState::Done { signature_hex },
+                id,
+                ..
+            }) => {
+                info!(session = id, "Message already signed");
+                return Ok(signature_hex);
+            }
+            Some(mut unfinished) => {
+                unfinished.context = unfinished.context.or(context);
+                unfinished
+            }
+            None => self.store.create_signing(
+                message_hex.to_string(),
+                merkle_root_hex.map(str::to_string),
+                context,
+            )?,
+        };
+        self.drive_signing(session).await
+    }
+
+    /// Signs every input of the unsigned transaction `tx_hex`, whose inputs spend outputs of
+    /// `prevout_sats` paying the group key (tweaked with `merkle_root_hex`), once the policy
+    /// approves its payouts. Returns one signature per input.
+    async fn sign_transaction(
+        &self,
+        tx_hex: &str,
+        prevout_sats: &[u64],
+        merkle_root_hex: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let tx: Transaction = deserialize(
+            &hex::decode(tx_hex).map_err(|e| format!("Invalid transaction hex: {}", e))?,
+        )?;
+        if prevout_sats.len() != tx.input.len() {
+            return Err(format!(
+                "Got {} prevout values for {} inputs",
+                prevout_sats.len(),
+                tx.input.len()
+            )
+            .into());
+        }
+        let key = self.group_key().await?;
+        let spent_spk = frost::script_pubkey(&key.verify_key_hex, merkle_root_hex)?;
+
+        // Outputs going back to the spent key are change; every other one is a payout
+        let txid = tx.compute_txid();
+        let payouts: Vec<Payout> = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.script_pubkey != spent_spk)
+            .map(|(vout, output)| Payout {
+                id: format!("{}:{}", txid, vout),
+                address: Address::from_script(&output.script_pubkey, self.network)
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|_| output.script_pubkey.to_hex_string()),
+                amount_sats: output.value.to_sat(),
+            })
+            .collect();
+        if let Some(reason) = self.policy.check(&payouts)? {
+            return Err(format!("Refusing to sign {}, denied by policy: {}", txid, reason).into());
+        }
+
+        let prevouts: Vec<TxOut> = prevout_sats
+            .iter()
+            .map(|&sats| TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: spent_spk.clone(),
+            })
+            .collect();
+        let mut cache = SighashCache::new(&tx);
+        let mut signatures = Vec::with_capacity(tx.input.len());
+        for index in 0..tx.input.len() {
+            let sighash = cache.taproot_key_spend_signature_hash(
+                index,
+                &Prevouts::All(&prevouts),
+                TapSighashType::Default,
+            )?;
+            let context = SigningContext::Transaction {
+                tx_hex: tx_hex.to_string(),
+                prevout_sats: prevout_sats.to_vec(),
+                input_index: index as u32,
+            };
+            signatures.push(
+                self.sign(
+                    &hex::encode(sighash.to_byte_array()),
+                    merkle_root_hex,
+                    Some(context),
+                )
+                .await?,
+            );
+        }
+        self.policy.record(&payouts)?;
+        info!(%txid, payouts = payouts.len(), "Transaction signed");
+        Ok(signatures)
+    }
+
+    /// Signs the attestation of a proof's statement: the BIP-340 tagged hash, under
+    /// `ZKBTC/attestation`, of `vkey || public_values_hash`.
+    async fn attest(
+        &self,
+        vkey_hex: &str,
+        public_values_hash_hex: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let field = |name: &str, value: &str| match hex::decode(value.trim_start_matches("0x")) {
+            Ok(bytes) if bytes.len() == 32 => Ok(bytes),
+            _ => Err(format!("Invalid {} {}", name, value)),
+        };
+        let vkey = field("verification key", vkey_hex)?;
+        let public_values_hash = field("public values hash", public_values_hash_hex)?;
+        let preimage = [vkey.as_slice(), public_values_hash.as_slice()].concat();
+        let digest = frost::tagged_hash(ATTESTATION_TAG, &preimage);
+        info!(
+            vkey = hex::encode(&vkey),
+            public_values_hash = hex::encode(&public_values_hash),
+            "Attesting proof statement"
+        );
+        let context = SigningContext::Tagged {
+            tag: ATTESTATION_TAG.to_string(),
+            preimage_hex: hex::encode(preimage),
+        };
+        self.sign(&hex::encode(digest), None, Some(context)).await
+    }
+
+    /// Has the signers sign a notice following the latest one, and records it.
+    async fn sign_notice(
+        &self,
+        action: PauseAction,
+        reason: &str,
+    ) -> Result<PauseNotice, Box<dyn Error>> {
+        let key = self.group_key().await?;
+        let sequence = match self.store.pause_notice()? {
+            Some(latest) => latest.sequence + 1,
+            None => 1,
+        };
+        let issued_at = now();
+        let digest = PauseNotice::digest(action, sequence, issued_at, reason);
+        let context = SigningContext::Tagged {
+            tag: PAUSE_TAG.to_string(),
+            preimage_hex: hex::encode(PauseNotice::preimage(action, sequence, issued_at, reason)),
+        };
+        let signature = self.sign(&hex::encode(digest), None, Some(context)).await?;
+        let notice = PauseNotice {
+            action,
+            sequence,
+            issued_at,
+            reason: reason.to_string(),
+            group_key: frost::group_key(&key.verify_key_hex)?.to_string(),
+            signature,
+        };
+        self.store.set_pause_notice(&notice)?;
+        Ok(notice)
+    }
+
+    /// Pauses signing, unless it already is; returns the pause notice in force.
+    async fn pause(&self, reason: &str) -> Result<PauseNotice, Box<dyn Error>> {
+        if let Some(notice) = self.store.pause_notice()?.filter(PauseNotice::is_pause) {
+            warn!(sequence = notice.sequence, "Already paused");
+            return Ok(notice);
+        }
+        let notice = self.sign_notice(PauseAction::Pause, reason).await?;
+        warn!(sequence = notice.sequence, reason, "Signing paused");
+        Ok(notice)
+    }
+
+    /// Lifts the pause with `pause_sequence`, the one in force.
+    async fn unpause(
+        &self,
+        pause_sequence: u64,
+        reason: &str,
+    ) -> Result<PauseNotice, Box<dyn Error>> {
+        match self.store.pause_notice()?.filter(PauseNotice::is_pause) {
+            Some(notice) if notice.sequence == pause_sequence => {}
+            Some(notice) => {
+                return Err(format!(
+                    "The pause in force is {}, not {}",
+                    notice.sequence, pause_sequence
+                )
+                .into())
+            }
+            None => return Err("Signing is not paused".into()),
+        }
+        let notice = self.sign_notice(PauseAction::Resume, reason).await?;
+        info!(sequence = notice.sequence, reason, "Signing resumed");
+        Ok(notice)
+    }
+
+    /// Runs `session` from round 1, persisting it after each round. An interrupted session
+    /// restarts at round 1 rather than reusing its recorded commitments: a signer keeps only
+    /// the nonces of its last round 1, which may belong to a later session by now.
+    async fn drive_signing(&self, mut session: SigningSession) -> Result<String, Box<dyn Error>> {
+        let key = self.group_key().await?;
+        let span = info_span!("signing", session = session.id);
+        let result = self
+            .signing_rounds(&mut session, &key)
+            .instrument(span)
+            .await;
+        session.state = match &result {
+            Ok(signature_hex) => SigningState::Done {
+                signature_hex: signature_hex.clone(),
+            },
+            Err(e) => SigningState::Failed {
+                reason: e.to_string(),
+            },
+        };
+        session.updated_at = now();
+        self.store.put_signing(&session)?;
+        match result {
+            Ok(signature_hex) => {
+                info!(session = session.id, "Signature aggregated");
+                Ok(signature_hex)
+            }
+            Err(e) => Err(format!("Signing session {} failed: {}", session.id, e).into()),
+        }
+    }
+
+    async fn signing_rounds(
+        &self,
+        session: &mut SigningSession,
+        key: &GroupKey,
+    ) -> Result<String, Box<dyn Error>> {
+        let session_id = session.id.clone();
+        info!(session = session_id, "Starting signing round 1");
+        let commitments: Vec<Package> = self
+            .signers
+            .all("Signing round 1", |_, mut client| {
+                let session_id = session_id.clone();
+                async move {
+                    client
+                        .sign_round1(proto::SignRound1Request { session_id })
+                        .await
+                }
+            })
+            .await?
+            .into_iter()
+            .map(|response| Package {
+                id_hex: response.id_hex,
+                pkg_hex: response.commitment_hex,
+            })
+            .collect();
+        session.state = SigningState::Round2 {
+            commitments: commitments.clone(),
+        };
+        session.updated_at = now();
+        self.store.put_signing(session)?;
+
+        info!(session = session_id, "Starting signing round 2");
+        let shares: Vec<Package> = self
+            .signers
+            .all("Signing round 2", |_, mut client| {
+                let request = proto::SignRound2Request {
+                    session_id: session_id.clone(),
+                    message_hex: session.message_hex.clone(),
+                    commitments: commitments.iter().map(to_proto).collect(),
+                    merkle_root_hex: session.merkle_root_hex.clone().unwrap_or_default(),
+                    context: session.context.as_ref().map(context_to_proto),
+                };
+                async move { client.sign_round2(request).await }
+            })
+            .await?
+            .into_iter()
+            .map(|response| Package {
+                id_hex: response.id_hex,
+                pkg_hex: response.signature_share_hex,
+            })
+            .collect();
+        let result = frost::aggregate(
+            &session.message_hex,
+            &commitments,
+            &shares,
+            &key.pubkp_hex,
+            session.merkle_root_hex.as_deref(),
+        );
+        if let Some(invalid) = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<frost::InvalidShare>())
+        {
+            let evidence =
+                evidence::invalid_share(&self.store, key, session, &commitments, &shares, invalid)?;
+            error!(
+                session = session_id,
+                signer = invalid.id_hex,
+                evidence = evidence.id,
+                "Signer sent an invalid signature share"
+            );
+        }
+        result
+    }
+
+    /// Finishes the latest unfinished DKG session and every unfinished signing session.
+    async fn resume(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(session) = self.unfinished_dkg()? {
+            info!(session = session.id, "Resuming DKG");
+            let key = self.drive_dkg(session).await?;
+            self.print_key(&key)?;
+        }
+        for session in self.store.signing_sessions()? {
+            if matches!(
+                session.state,
+                SigningState::Round1 | SigningState::Round2 { .. }
+            ) {
+                info!(session = session.id, "Resuming signing");
+                let id = session.id.clone();
+                match self.drive_signing(session).await {
+                    Ok(signature_hex) => println!("{}: {}", id, signature_hex),
+                    Err(e) => warn!(error = %e, "Resumed signing failed"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_key(&self, key: &GroupKey) -> Result<(), Box<dyn Error>> {
+        println!("group verifying key: {}", key.verify_key_hex);
+        println!(
+            "taproot address ({}): {}",
+            self.network,
+            frost::taproot_address(&key.verify_key_hex, self.network)?
+        );
+        Ok(())
+    }
+}
+
+/// Refuses a command while a pause is in force.
+fn check_not_paused(store: &SessionStore) -> Result<(), Box<dyn Error>> {
+    match store.pause_notice()?.filter(PauseNotice::is_pause) {
+        Some(notice) => Err(format!(
+            "Signing is paused ({}); lift it with `unpause --pause-sequence {}`",
+            notice, notice.sequence
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Prints `value`, e.g. a notice, as JSON, and writes it to `out`.
+fn publish<T: Serialize + ?Sized>(value: &T, out: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let json = serde_json::to_string_pretty(value)?;
+    if let Some(out) = out {
+        std::fs::write(out, &json)?;
+    }
+    println!("{}", json);
+    Ok(())
+}
+
+/// Writes `transcript` to `path`.
+fn write_transcript(transcript: &Transcript, path: &Path) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, serde_json::to_string_pretty(transcript)?)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+}
+
+/// Runs a `ceremony` command that needs no signers.
+fn ceremony(
+    store: &SessionStore,
+    command: OfflineCeremonyCommand,
+    network: bitcoin::Network,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        OfflineCeremonyCommand::Transcript { session, out } => {
+            let transcript = store
+                .dkg_sessions()?
+                .into_iter()
+                .rev()
+                .filter(|recorded| session.as_ref().is_none_or(|id| recorded.id == *id))
+                .find_map(|recorded| match recorded.state {
+                    DkgState::Done { transcript, .. } => transcript,
+                    _ => None,
+                })
+                .ok_or_else(|| match &session {
+                    Some(id) => format!("DKG session {} has no transcript", id),
+                    None => "No DKG session has a transcript".to_string(),
+                })?;
+            write_transcript(&transcript, &out)?;
+            publish(&ceremony::verify(&transcript, network)?, None)
+        }
+        OfflineCeremonyCommand::Verify { transcript, report } => {
+            let transcript: Transcript = serde_json::from_slice(&std::fs::read(&transcript)?)
+                .map_err(|e| format!("Invalid transcript {}: {}", transcript.display(), e))?;
+            let recomputed = ceremony::verify(&transcript, network)?;
+            publish(&recomputed, None)?;
+            if let Some(report) = report {
+                let published: CeremonyReport = serde_json::from_slice(&std::fs::read(&report)?)
+                    .map_err(|e| format!("Invalid report {}: {}", report.display(), e))?;
+                if published != recomputed {
+                    return Err(format!(
+                        "{} is not the report of the transcript",
+                        report.display()
+                    )
+                    .into());
+                }
+            }
+            if !recomputed.verified {
+                return Err("The transcript does not verify".into());
+            }
+            info!(
+                session = recomputed.session_id,
+                group_key = recomputed.group_key,
+                "Ceremony transcript verified"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Runs an `evidence` command against the recorded group key.
+fn evidence(store: &SessionStore, command: EvidenceCommand) -> Result<(), Box<dyn Error>> {
+    let group_key = || {
+        store.group_key()?.ok_or_else(|| {
+            Box::<dyn Error>::from("No group key recorded, run `coordinator dkg` first")
+        })
+    };
+    match command {
+        EvidenceCommand::List => {
+            for evidence in store.evidence_list()? {
+                println!("{}", evidence);
+            }
+            Ok(())
+        }
+        EvidenceCommand::Export { ids, out } => {
+            let records = if ids.is_empty() {
+                store.evidence_list()?
+            } else {
+                ids.iter()
+                    .map(|id| {
+                        store
+                            .evidence(id)?
+                            .ok_or_else(|| format!("No evidence {}", id).into())
+                    })
+                    .collect::<Result<_, Box<dyn Error>>>()?
+            };
+            let packages = records
+                .iter()
+                .map(|evidence| evidence.package())
+                .collect::<Result<Vec<_>, _>>()?;
+            publish(&packages, out.as_deref())
+        }
+        EvidenceCommand::Spend {
+            tx_hex,
+            prevout_sats,
+            prevout_scripts_hex,
+            merkle_root_hex,
+            reason,
+        } => {
+            let records = evidence::unauthorized_spend(
+                store,
+                &group_key()?,
+                &tx_hex,
+                &prevout_sats,
+                &prevout_scripts_hex,
+                merkle_root_hex.as_deref(),
+                &reason,
+            )?;
+            for evidence in &records {
+                error!(
+                    evidence = evidence.id,
+                    txid = evidence.subject,
+                    reason,
+                    "Unauthorized spend recorded"
+                );
+            }
+            let packages = records
+                .iter()
+                .map(|evidence| evidence.package())
+                .collect::<Result<Vec<_>, _>>()?;
+            publish(&packages, None)
+        }
+        EvidenceCommand::Signature {
+            message_hex,
+            signature_hex,
+            merkle_root_hex,
+        } => {
+            let evidence = evidence::non_template_signature(
+                store,
+                &group_key()?,
+                &message_hex,
+                &signature_hex,
+                merkle_root_hex.as_deref(),
+            )?;
+            error!(
+                evidence = evidence.id,
+                message = message_hex,
+                "Non-template signature recorded"
+            );
+            publish(&[evidence.package()?], None)
+        }
+    }
+}
+
+/// Prints the recorded sessions, oldest first.
+fn list(store: &SessionStore) -> Result<(), Box<dyn Error>> {
+    if let Some(notice) = store.pause_notice()? {
+        println!("latest notice: {}", notice);
+    }
+    for session in store.dkg_sessions()? {
+        let state = match session.state {
+            DkgState::Round1 => "waiting for round 1".to_string(),
+            DkgState::Round2 { .. } => "waiting for round 2".to_string(),
+            DkgState::Round3 { .. } => "waiting for round 3".to_string(),
+            DkgState::Done { key, .. } => format!("done, group key {}", key.verify_key_hex),
+            DkgState::Failed { reason } => format!("failed: {}", reason),
+        };
+        println!(
+            "{} ({} signers): {}",
+            session.id,
+            session.signers.len(),
+            state
+        );
+    }
+    for session in store.signing_sessions()? {
+        let state = match session.state {
+            SigningState::Round1 => "waiting for round 1".to_string(),
+            SigningState::Round2 { .. } => "waiting for round 2".to_string(),
+            SigningState::Done { signature_hex } => format!("signed, {}", signature_hex),
+            SigningState::Failed { reason } => format!("failed: {}", reason),
+        };
+        let tweak = session
+            .merkle_root_hex
+            .map(|merkle_root_hex| format!(" (tweak {})", merkle_root_hex))
+            .unwrap_or_default();
+        println!(
+            "{} over {}{}: {}",
+            session.id, session.message_hex, tweak, state
+        );
+    }
+    Ok(())
+}
+
+async fn run(args: Args, store: SessionStore) -> Result<(), Box<dyn Error>> {
+    let network = args.bitcoin_network();
+    let pausable = matches!(
+        args.command,
+        Command::Sign { .. } | Command::Attest { .. } | Command::Resume
+    );
+    // Commands that need the signers connect to them first; `sign`, `attest` and `resume` are
+    // refused while paused
+    let connect = |store: SessionStore| -> Result<Coordinator, Box<dyn Error>> {
+        if args.signers.is_empty() {
+            return Err("--signers is required".into());
+        }
+        let policy = RetryPolicy {
+            attempts: args.retries.max(1),
+            delay: Duration::from_millis(args.retry_delay_ms),
+        };
+        if pausable {
+            check_not_paused(&store)?;
+        }
+        Ok(Coordinator {
+            signers: Signers::new(
+                &args.signers,
+                &args.tls.config()?,
+                Duration::from_secs(args.timeout),
+                policy,
+                &args.swap_ids,
+            )?,
+            policy: PolicyEngine::new(
+                &args.policy,
+                store.policy_volume()?,
+                store.policy_window()?,
+                store.policy_signed()?,
+            ),
+            store,
+            network,
+        })
+    };
+    match args.command {
+        Command::Dkg { force } => {
+            let coordinator = connect(store)?;
+            let key = coordinator.dkg(force).await?;
+            coordinator.print_key(&key)
+        }
+        Command::Sign {
+            message_hex,
+            tx_hex,
+            prevout_sats,
+            merkle_root_hex,
+        } => {
+            let coordinator = connect(store)?;
+            // A signer keeps the nonces of one session only, so the sessions never overlap
+            let signatures = match tx_hex {
+                Some(tx_hex) => {
+                    coordinator
+                        .sign_transaction(&tx_hex, &prevout_sats, merkle_root_hex.as_deref())
+                        .await?
+                }
+                None if !coordinator.policy.is_empty() => {
+                    return Err(
+                        "A payout policy is set, so only whole transactions are signed: pass --tx-hex and --prevout-sats"
+                            .into(),
+                    )
+                }
+                None => {
+                    let mut signatures = Vec::with_capacity(message_hex.len());
+                    for message_hex in &message_hex {
+                        signatures.push(
+                            coordinator
+                                .sign(message_hex, merkle_root_hex.as_deref(), None)
+                                .await?,
+                        );
+                    }
+                    signatures
+                }
+            };
+            for signature_hex in signatures {
+                println!("{}", signature_hex);
+            }
+            Ok(())
+        }
+        Command::Attest {
+            vkey_hex,
+            public_values_hash_hex,
+        } => {
+            let coordinator = connect(store)?;
+            let signature_hex = coordinator
+                .attest(&vkey_hex, &public_values_hash_hex)
+                .await?;
+            println!("{}", signature_hex);
+            Ok(())
+        }
+        Command::Resume => connect(store)?.resume().await,
+        Command::Pause { reason, out } => {
+            publish(&connect(store)?.pause(&reason).await?, out.as_deref())
+        }
+        Command::Unpause {
+            pause_sequence,
+            reason,
+            out,
+        } => publish(
+            &connect(store)?.unpause(pause_sequence, &reason).await?,
+            out.as_deref(),
+        ),
+        Command::Ceremony {
+            command:
+                CeremonyCommand::Run {
+                    transcript,
+                    report,
+                    force,
+                },
+        } => {
+            let coordinator = connect(store)?;
+            let recorded = coordinator.ceremony(force).await?;
+            write_transcript(&recorded, &transcript)?;
+            let ceremony_report = ceremony::verify(&recorded, coordinator.network)?;
+            publish(&ceremony_report, report.as_deref())?;
+            if !ceremony_report.verified {
+                return Err("The group key does not match the ceremony transcript".into());
+            }
+            info!(
+                session = recorded.session_id,
+                transcript = %transcript.display(),
+                "Key ceremony completed"
+            );
+            Ok(())
+        }
+        Command::Sessions => list(&store),
+        Command::Ceremony {
+            command: CeremonyCommand::Offline(command),
+        } => ceremony(&store, command, network),
+        Command::Evidence { command } => evidence(&store, command),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let provider = telemetry::init();
+    let args = Args::parse();
+
+    let store = match SessionStore::open(args.db.as_deref()) {
+        Ok(store) => store,
+        Err(e) => {
+            error!(error = %e, "Failed to open the session database");
+            telemetry::shutdown(provider).await;
+            std::process::exit(1);
+        }
+    };
+    let span = info_span!(
+        "coordinator",
+        command = args.command.name(),
+        swap_id = args.swap_ids.join(",")
+    );
+    telemetry::set_parent_from_env(&span);
+    let result = run(args, store).instrument(span).await;
+    if let Err(e) = &result {
+        error!(error = %e, "Coordinator failed");
+    }
+    telemetry::shutdown(provider).await;
+    if result.is_err() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,74 @@
+//! Emergency pause notices. `coordinator pause` has the signers sign a notice with the group
+//! key, and stops signing until `coordinator unpause` lifts that very pause with a resume
+//! notice, signed the same way. The ZKP services check the notices against the group key, so
+//! one signed message stops the relayer and the payouts as well.
+
+use crate::frost::tagged_hash;
+use bitcoin::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Tag of the hash a notice is signed under; must match `PAUSE_TAG` of the ZKP `pause` module.
+pub const PAUSE_TAG: &str = "ZKBTC/pause";
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseAction {
+    Pause,
+    Resume,
+}
+
+/// A pause or its lifting, as distributed to the services.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseNotice {
+    pub action: PauseAction,
+    /// One above the notice it follows, so an older notice never undoes a newer one.
+    pub sequence: u64,
+    /// Unix time the notice was signed at.
+    pub issued_at: u64,
+    pub reason: String,
+    /// x-only group key, hex.
+    pub group_key: String,
+    /// 64-byte BIP-340 signature of [`PauseNotice::digest`] by the group key's key-path-only
+    /// Taproot output key, hex.
+    pub signature: String,
+}
+
+impl PauseNotice {
+    /// The action byte (1 to pause, 0 to resume), the big-endian sequence and issue time, and
+    /// the SHA-256 of the reason.
+    pub fn preimage(action: PauseAction, sequence: u64, issued_at: u64, reason: &str) -> Vec<u8> {
+        let mut preimage = vec![u8::from(action == PauseAction::Pause)];
+        preimage.extend_from_slice(&sequence.to_be_bytes());
+        preimage.extend_from_slice(&issued_at.to_be_bytes());
+        preimage.extend_from_slice(sha256::Hash::hash(reason.as_bytes()).as_byte_array());
+        preimage
+    }
+
+    /// BIP-340 tagged hash of the [`PauseNotice::preimage`] under `ZKBTC/pause`.
+    pub fn digest(action: PauseAction, sequence: u64, issued_at: u64, reason: &str) -> [u8; 32] {
+        tagged_hash(
+            PAUSE_TAG,
+            &Self::preimage(action, sequence, issued_at, reason),
+        )
+    }
+
+    pub fn is_pause(&self) -> bool {
+        self.action == PauseAction::Pause
+    }
+}
+
+impl fmt::Display for PauseNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            PauseAction::Pause => "pause",
+            PauseAction::Resume => "resume",
+        };
+        write!(
+            f,
+            "{} {} at {}: {}",
+            action, self.sequence, self.issued_at, self.reason
+        )
+    }
+}
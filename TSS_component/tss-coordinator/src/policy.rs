@@ -0,0 +1,162 @@
+//! Policy hooks the coordinator checks a transaction's payouts against before any of its inputs
+//! is signed: a hook approves a payout or denies it with a reason, and the first denial stops
+//! the signing. The hooks are the ZKP component's `bridge_core::policy` ones, each payout
+//! checked as a burn [`Transfer`], and `signer-node` includes this file to enforce the same
+//! hooks on each signer. The volume limits keep the sled layout of the coordinator's former
+//! copies of them, so the payouts already recorded in `--db` still count.
+
+pub use bridge_core::policy::EpochVolume;
+pub use bridge_core::rate_limit::{RateLimit, VolumeLedger};
+
+use bridge_core::policy::{self, AddressList, AmountCap, Decision, Transfer};
+use bridge_core::Direction;
+use clap::Args;
+use std::error::Error;
+
+/// An output of the transaction to sign that does not go back to the spent key.
+#[derive(Clone, Debug)]
+pub struct Payout {
+    /// `txid:vout` of the output; a payout is recorded once per id.
+    pub id: String,
+    /// Address paid, or the script's hex if it has no address.
+    pub address: String,
+    pub amount_sats: u64,
+}
+
+impl Payout {
+    /// The payout as the burn transfer the hooks decide on.
+    fn transfer(&self) -> Transfer {
+        Transfer {
+            direction: Direction::Burn,
+            id: self.id.clone(),
+            amount_sats: self.amount_sats,
+            recipient: self.address.clone(),
+        }
+    }
+}
+
+/// Limits on the payouts the coordinator signs; a hook is only enabled when its setting is
+/// given. With any of them set, only whole transactions are signed (`sign --tx-hex`), so their
+/// payouts can be checked.
+#[derive(Args, Debug)]
+pub struct PolicyArgs {
+    /// Largest payout, in sats.
+    #[clap(long, env = "POLICY_MAX_PAYOUT_SATS")]
+    pub policy_max_payout_sats: Option<u64>,
+    /// BTC addresses payouts may go to; repeat the flag or separate them with commas. When
+    /// set, payouts to any other address are denied.
+    #[clap(long, env = "POLICY_PAYOUT_ALLOW", value_delimiter = ',')]
+    pub policy_payout_allow: Vec<String>,
+    /// BTC addresses never paid.
+    #[clap(long, env = "POLICY_DENY", value_delimiter = ',')]
+    pub policy_deny: Vec<String>,
+    /// Sats that may be paid out per epoch, recorded in `--db`.
+    #[clap(long, env = "POLICY_PAYOUT_VOLUME_SATS")]
+    pub policy_payout_volume_sats: Option<u64>,
+    /// Length of a volume epoch, in seconds.
+    #[clap(long, env = "POLICY_EPOCH_SECS", default_value_t = 86_400)]
+    pub policy_epoch_secs: u64,
+    /// Sats that may be paid out over any rolling window, recorded in `--db`.
+    #[clap(long, env = "POLICY_PAYOUT_WINDOW_SATS")]
+    pub policy_payout_window_sats: Option<u64>,
+    /// Payouts allowed over any rolling window.
+    #[clap(long, env = "POLICY_PAYOUT_WINDOW_COUNT")]
+    pub policy_payout_window_count: Option<u64>,
+    /// Length of the rolling window, in seconds.
+    #[clap(long, env = "POLICY_WINDOW_SECS", default_value_t = 86_400)]
+    pub policy_window_secs: u64,
+}
+
+/// Hooks run in order; without hooks every payout is approved. Signed payouts are recorded by
+/// txid in a sled tree, `txid:vout` to the sats, so signing a transaction again, e.g. to
+/// finish its sessions, neither checks nor counts its payouts twice.
+pub struct PolicyEngine {
+    hooks: policy::PolicyEngine,
+    signed: sled::Tree,
+}
+
+impl PolicyEngine {
+    /// Engine with the hooks `args` sets; the epoch volume is recorded in `tree`, the payouts
+    /// of the rolling window in `window`, and the signed payouts in `signed`.
+    pub fn new(
+        args: &PolicyArgs,
+        tree: sled::Tree,
+        window: sled::Tree,
+        signed: sled::Tree,
+    ) -> Self {
+        let mut hooks = policy::PolicyEngine::new();
+        if let Some(max_sats) = args.policy_max_payout_sats {
+            hooks = hooks.with(AmountCap {
+                direction: Direction::Burn,
+                max_sats,
+            });
+        }
+        if !args.policy_payout_allow.is_empty() || !args.policy_deny.is_empty() {
+            hooks = hooks.with(AddressList {
+                direction: Direction::Burn,
+                allow: args.policy_payout_allow.clone(),
+                deny: args.policy_deny.clone(),
+            });
+        }
+        if let Some(limit_sats) = args.policy_payout_volume_sats {
+            hooks = hooks.with(EpochVolume::new(
+                Direction::Burn,
+                limit_sats,
+                args.policy_epoch_secs,
+                tree,
+            ));
+        }
+        if args.policy_payout_window_sats.is_some() || args.policy_payout_window_count.is_some() {
+            hooks = hooks.with(RateLimit::new(
+                Direction::Burn,
+                args.policy_window_secs,
+                args.policy_payout_window_sats,
+                args.policy_payout_window_count,
+                VolumeLedger::new(window),
+            ));
+        }
+        PolicyEngine { hooks, signed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// The payouts of `payouts` not recorded yet.
+    fn unrecorded(&self, payouts: &[Payout]) -> Result<Vec<Payout>, Box<dyn Error>> {
+        let mut unrecorded = Vec::with_capacity(payouts.len());
+        for payout in payouts {
+            if !self.signed.contains_key(payout.id.as_bytes())? {
+                unrecorded.push(payout.clone());
+            }
+        }
+        Ok(unrecorded)
+    }
+
+    /// Checks every payout not recorded yet, each together with the ones before it. Returns
+    /// the first denial, naming the payout and the hook.
+    pub fn check(&self, payouts: &[Payout]) -> Result<Option<String>, Box<dyn Error>> {
+        let transfers: Vec<Transfer> = self
+            .unrecorded(payouts)?
+            .iter()
+            .map(Payout::transfer)
+            .collect();
+        for (index, transfer) in transfers.iter().enumerate() {
+            if let Decision::Deny { reason } = self.hooks.check(transfer, &transfers[..index])? {
+                return Ok(Some(format!("payout {}: {}", transfer.id, reason)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lets every hook count the signed payouts not recorded yet, then records them.
+    pub fn record(&self, payouts: &[Payout]) -> Result<(), Box<dyn Error>> {
+        for payout in self.unrecorded(payouts)? {
+            self.hooks.record(&payout.transfer())?;
+            self.signed
+                .insert(payout.id.as_bytes(), &payout.amount_sats.to_be_bytes())?;
+        }
+        self.signed.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+//! sled database of the coordinator's sessions. Each round's result is written before the next
+//! round starts, so a restarted coordinator resumes a DKG where it stopped instead of asking
+//! the signers for new key material, and never signs the same message twice.
+
+use crate::ceremony::Transcript;
+use crate::evidence::{Evidence, EvidenceDetails};
+use crate::pause::PauseNotice;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Key of the group key in the default tree.
+const GROUP_KEY: &[u8] = b"group_key";
+/// Key of the latest pause notice in the default tree.
+const PAUSE_NOTICE: &[u8] = b"pause_notice";
+
+/// A package from `id_hex`, or addressed to it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Package {
+    pub id_hex: String,
+    pub pkg_hex: String,
+}
+
+/// A round-2 package, with both ends.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Round2Package {
+    pub from_hex: String,
+    pub to_hex: String,
+    pub pkg_hex: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum DkgState {
+    /// Waiting for the round-1 packages.
+    Round1,
+    /// Round-1 packages collected, one per signer in `--signers` order.
+    Round2 {
+        round1: Vec<Package>,
+    },
+    /// Round-2 packages collected.
+    Round3 {
+        round1: Vec<Package>,
+        round2: Vec<Round2Package>,
+    },
+    Done {
+        key: GroupKey,
+        /// Transcript of the rounds, for a key ceremony; `None` for sessions recorded before
+        /// transcripts were kept.
+        #[serde(default)]
+        transcript: Option<Transcript>,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DkgSession {
+    pub id: String,
+    /// Signer endpoints, in the order the round-1 packages are stored.
+    pub signers: Vec<String>,
+    pub state: DkgState,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum SigningState {
+    /// Waiting for the nonce commitments.
+    Round1,
+    /// Commitments collected; waiting for the signature shares.
+    Round2 {
+        commitments: Vec<Package>,
+    },
+    Done {
+        signature_hex: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+/// What a signing session's message is the hash of, sent along with it for the signers to
+/// check.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum SigningContext {
+    /// Taproot sighash of input `input_index` of the unsigned transaction `tx_hex`.
+    Transaction {
+        tx_hex: String,
+        prevout_sats: Vec<u64>,
+        input_index: u32,
+    },
+    /// BIP-340 tagged hash of `preimage_hex` under `tag`.
+    Tagged { tag: String, preimage_hex: String },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SigningSession {
+    pub id: String,
+    pub message_hex: String,
+    /// Script tree root the group key is tweaked with for this spend; `None` for the vault.
+    #[serde(default)]
+    pub merkle_root_hex: Option<String>,
+    /// `None` for a bare `--message-hex` message.
+    #[serde(default)]
+    pub context: Option<SigningContext>,
+    pub state: SigningState,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Key material of the group, as returned by the last DKG.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct GroupKey {
+    /// x-only group verifying key.
+    pub verify_key_hex: String,
+    /// Public key package, needed to aggregate signature shares.
+    pub pubkp_hex: String,
+}
+
+/// Current Unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+pub struct SessionStore {
+    db: sled::Db,
+    /// DKG sessions keyed by id.
+    dkg: sled::Tree,
+    /// Signing sessions keyed by id.
+    signing: sled::Tree,
+    /// Evidence of misbehavior keyed by id.
+    evidence: sled::Tree,
+}
+
+impl SessionStore {
+    /// Opens the database at `path`, creating it if needed; without a path the database is
+    /// temporary and dropped on exit.
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let config = match path {
+            Some(path) => sled::Config::new().path(path),
+            None => sled::Config::new().temporary(true),
+        };
+        let db = config.open()?;
+        let dkg = db.open_tree("dkg")?;
+        let signing = db.open_tree("signing")?;
+        let evidence = db.open_tree("evidence")?;
+        Ok(SessionStore {
+            db,
+            dkg,
+            signing,
+            evidence,
+        })
+    }
+
+    /// A new session id with `prefix`; ids sort in creation order.
+    fn next_id(&self, prefix: &str) -> Result<String, Box<dyn Error>> {
+        Ok(format!("{}-{:010}", prefix, self.db.generate_id()?))
+    }
+
+    pub fn group_key(&self) -> Result<Option<GroupKey>, Box<dyn Error>> {
+        self.db
+            .get(GROUP_KEY)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn set_group_key(&self, key: &GroupKey) -> Result<(), Box<dyn Error>> {
+        self.db.insert(GROUP_KEY, serde_json::to_vec(key)?)?;
+        self.flush()
+    }
+
+    /// Latest pause notice signed, pausing or resuming.
+    pub fn pause_notice(&self) -> Result<Option<PauseNotice>, Box<dyn Error>> {
+        self.db
+            .get(PAUSE_NOTICE)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn set_pause_notice(&self, notice: &PauseNotice) -> Result<(), Box<dyn Error>> {
+        self.db.insert(PAUSE_NOTICE, serde_json::to_vec(notice)?)?;
+        self.flush()
+    }
+
+    pub fn create_dkg(&self, signers: Vec<String>) -> Result<DkgSession, Box<dyn Error>> {
+        let session = DkgSession {
+            id: self.next_id("dkg")?,
+            signers,
+            state: DkgState::Round1,
+            created_at: now(),
+            updated_at: now(),
+        };
+        self.put_dkg(&session)?;
+        Ok(session)
+    }
+
+    /// Persists `session` and flushes it, so a crash right after a round keeps its result.
+    pub fn put_dkg(&self, session: &DkgSession) -> Result<(), Box<dyn Error>> {
+        self.dkg
+            .insert(session.id.as_bytes(), serde_json::to_vec(session)?)?;
+        self.flush()
+    }
+
+    pub fn dkg_session(&self, id: &str) -> Result<Option<DkgSession>, Box<dyn Error>> {
+        self.dkg
+            .get(id.as_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Every DKG session, oldest first.
+    pub fn dkg_sessions(&self) -> Result<Vec<DkgSession>, Box<dyn Error>> {
+        self.dkg
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    pub fn create_signing(
+        &self,
+        message_hex: String,
+        merkle_root_hex: Option<String>,
+        context: Option<SigningContext>,
+    ) -> Result<SigningSession, Box<dyn Error>> {
+        let session = SigningSession {
+            id: self.next_id("sign")?,
+            message_hex,
+            merkle_root_hex,
+            context,
+            state: SigningState::Round1,
+            created_at: now(),
+            updated_at: now(),
+        };
+        self.put_signing(&session)?;
+        Ok(session)
+    }
+
+    pub fn put_signing(&self, session: &SigningSession) -> Result<(), Box<dyn Error>> {
+        self.signing
+            .insert(session.id.as_bytes(), serde_json::to_vec(session)?)?;
+        self.flush()
+    }
+
+    /// Every signing session, oldest first.
+    pub fn signing_sessions(&self) -> Result<Vec<SigningSession>, Box<dyn Error>> {
+        self.signing
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Latest session over `message_hex` with tweak `merkle_root_hex` that has not failed.
+    pub fn find_signing(
+        &self,
+        message_hex: &str,
+        merkle_root_hex: Option<&str>,
+    ) -> Result<Option<SigningSession>, Box<dyn Error>> {
+        Ok(self.signing_sessions()?.into_iter().rev().find(|session| {
+            session.message_hex == message_hex
+                && session.merkle_root_hex.as_deref() == merkle_root_hex
+                && !matches!(session.state, SigningState::Failed { .. })
+        }))
+    }
+
+    /// Records evidence of `details` against `offender` over `subject`; evidence of the same
+    /// kind, offender and subject already recorded is returned instead.
+    pub fn record_evidence(
+        &self,
+        offender: &str,
+        subject: &str,
+        group_key: &str,
+        details: EvidenceDetails,
+    ) -> Result<Evidence, Box<dyn Error>> {
+        if let Some(recorded) = self.evidence_list()?.into_iter().find(|evidence| {
+            evidence.details.code() == details.code()
+                && evidence.offender == offender
+                && evidence.subject == subject
+        }) {
+            return Ok(recorded);
+        }
+        let evidence = Evidence {
+            id: self.next_id("evidence")?,
+            offender: offender.to_string(),
+            subject: subject.to_string(),
+            group_key: group_key.to_string(),
+            recorded_at: now(),
+            details,
+        };
+        self.evidence
+            .insert(evidence.id.as_bytes(), serde_json::to_vec(&evidence)?)?;
+        self.flush()?;
+        Ok(evidence)
+    }
+
+    pub fn evidence(&self, id: &str) -> Result<Option<Evidence>, Box<dyn Error>> {
+        self.evidence
+            .get(id.as_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Every piece of evidence, oldest first.
+    pub fn evidence_list(&self) -> Result<Vec<Evidence>, Box<dyn Error>> {
+        self.evidence
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Tree the policy's epoch volume is recorded in.
+    pub fn policy_volume(&self) -> Result<sled::Tree, Box<dyn Error>> {
+        Ok(self.db.open_tree("policy_volume")?)
+    }
+
+    /// Tree the policy's rolling window records signed payouts in.
+    pub fn policy_window(&self) -> Result<sled::Tree, Box<dyn Error>> {
+        Ok(self.db.open_tree("policy_window")?)
+    }
+
+    /// Tree the policy records signed payouts in by txid.
+    pub fn policy_signed(&self) -> Result<sled::Tree, Box<dyn Error>> {
+        Ok(self.db.open_tree("policy_signed")?)
+    }
+
+    /// Writes the pending changes to disk.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! OpenTelemetry tracing of the coordinator. With `OTEL_EXPORTER_OTLP_ENDPOINT` set, its spans
+//! are exported over OTLP/gRPC under the service name `tss-coordinator` (or
+//! `OTEL_SERVICE_NAME`). A run started for a swap, e.g. by the ZKP `payout-scheduler`, joins
+//! the caller's trace through `TRACEPARENT` and tags its spans with the caller's `SWAP_IDS`;
+//! both go on to the signers in the metadata of every gRPC call.
+
+use opentelemetry::propagation::Injector;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use std::error::Error;
+use tonic::metadata::{MetadataKey, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::level_filters::LevelFilter;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+/// gRPC metadata key of the swap ids, comma-separated, next to `traceparent`.
+const SWAP_ID_METADATA: &str = "swap-id";
+
+fn provider() -> Result<TracerProvider, Box<dyn Error>> {
+    let exporter = SpanExporter::builder().with_tonic().build()?;
+    let mut resource = Resource::default();
+    if std::env::var_os(SERVICE_NAME_ENV).is_none() {
+        resource = resource.merge(&Resource::new([KeyValue::new(
+            "service.name",
+            "tss-coordinator",
+        )]));
+    }
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource)
+        .build())
+}
+
+/// Installs the subscriber logging to stderr, and exporting the spans when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set. Returns the provider to shut down before exiting.
+pub fn init() -> Option<TracerProvider> {
+    let provider = match std::env::var_os(OTLP_ENDPOINT_ENV).map(|_| provider()) {
+        Some(Ok(provider)) => Some(provider),
+        Some(Err(e)) => {
+            eprintln!("OTLP export disabled: {}", e);
+            None
+        }
+        None => None,
+    };
+    if provider.is_some() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+    }
+    let otlp = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("tss-coordinator"))
+    });
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(otlp)
+        .with(LevelFilter::INFO)
+        .init();
+    provider
+}
+
+/// Exports the spans still batched and stops the exporter.
+pub async fn shutdown(provider: Option<TracerProvider>) {
+    let Some(provider) = provider else {
+        return;
+    };
+    // Shutting down blocks until the batch task, on this runtime, has exported
+    let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+    if let Ok(Err(e)) = result {
+        eprintln!("Failed to export spans: {}", e);
+    }
+}
+
+/// Makes `span` part of the trace in `TRACEPARENT`, when the coordinator runs for another
+/// process of the swap.
+pub fn set_parent_from_env(span: &Span) {
+    let fields: HashMap<String, String> =
+        [("traceparent", "TRACEPARENT"), ("tracestate", "TRACESTATE")]
+            .into_iter()
+            .filter_map(|(field, var)| Some((field.to_string(), std::env::var(var).ok()?)))
+            .collect();
+    if fields.is_empty() {
+        return;
+    }
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&fields));
+    span.set_parent(parent);
+}
+
+struct MetadataInjector<'a>(&'a mut tonic::metadata::MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+/// Adds the trace context of the current span and the swap ids to every signer call.
+#[derive(Clone)]
+pub struct TraceContext {
+    swap_ids: Option<MetadataValue<tonic::metadata::Ascii>>,
+}
+
+impl TraceContext {
+    pub fn new(swap_ids: &[String]) -> Result<Self, Box<dyn Error>> {
+        let swap_ids = match swap_ids.is_empty() {
+            true => None,
+            false => Some(
+                MetadataValue::try_from(swap_ids.join(","))
+                    .map_err(|e| format!("Invalid swap id: {}", e))?,
+            ),
+        };
+        Ok(TraceContext { swap_ids })
+    }
+}
+
+impl Interceptor for TraceContext {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(
+                &Span::current().context(),
+                &mut MetadataInjector(request.metadata_mut()),
+            )
+        });
+        if let Some(swap_ids) = &self.swap_ids {
+            request
+                .metadata_mut()
+                .insert(SWAP_ID_METADATA, swap_ids.clone());
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,337 @@
+//! Lifecycle of a bridge swap, shared by the services that move it forward.
+//!
+//! A mint swap goes `requested → deposited → proving → minted`, and a burn swap goes
+//! `burned → signing → broadcast → proving → settled`. Either can end in `failed`. A mint whose
+//! deposit cannot be minted, as its proof rejects it or the policy denies it, is refunded
+//! instead: `deposited` or `proving` → `refunding → refunded`. Every
+//! change is an event applied through [`Swap::apply`], which checks it against the transition
+//! table and records it in the swap's history. [`store::SwapStore`] persists each transition
+//! atomically. An event that was already applied is accepted as a no-op, so a handler that
+//! crashed after persisting can simply run again. [`Swap::next_action`] tells a restarted
+//! service what each unfinished swap is waiting for.
+//!
+//! [`policy`] holds the hooks that approve or deny a mint or a payout before it goes ahead, and
+//! [`rate_limit`] the volume over a rolling window one of them caps.
+
+pub mod policy;
+pub mod rate_limit;
+pub mod store;
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which way the swap moves value.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// BTC deposited to the vault, zkBTC minted on Ethereum.
+    Mint,
+    /// zkBTC burned on Ethereum, BTC paid out of the vault.
+    Burn,
+}
+
+/// Id correlating one swap's logs and traces across processes: `mint:<deposit txid>` or
+/// `burn:<burn request id>`. Every process derives it from the transaction or request it
+/// handles, as the numeric [`Swap::id`] is only known to the swap API.
+pub fn swap_id(direction: Direction, reference: impl fmt::Display) -> String {
+    match direction {
+        Direction::Mint => format!("mint:{}", reference),
+        Direction::Burn => format!("burn:{}", reference),
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapState {
+    /// Mint swap registered; waiting for the BTC deposit.
+    Requested,
+    /// Deposit final on Bitcoin; waiting for its mint proof to be requested.
+    Deposited,
+    /// Proof requested from the proof service; waiting for it to settle on Ethereum.
+    Proving,
+    /// zkBTC minted.
+    Minted,
+    /// Burn request final on Ethereum; waiting for the signers.
+    Burned,
+    /// TSS signing session of the payout running.
+    Signing,
+    /// Payout broadcast on Bitcoin; waiting for its burn proof to be requested.
+    Broadcast,
+    /// Burn proof accepted by the contract.
+    Settled,
+    /// Deposit rejected; its BTC is being paid back to the depositor.
+    Refunding,
+    /// Refund final on Bitcoin.
+    Refunded,
+    /// Given up; the reason is in the event that led here.
+    Failed,
+}
+
+impl SwapState {
+    /// Whether the swap can no longer change.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            SwapState::Minted | SwapState::Settled | SwapState::Refunded | SwapState::Failed
+        )
+    }
+}
+
+impl fmt::Display for SwapState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", name.as_str().unwrap_or_default())
+    }
+}
+
+/// Something that happened to a swap.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum SwapEvent {
+    /// The deposit reached the finality threshold.
+    DepositConfirmed { txid: String, amount_sats: u64 },
+    /// A mint or burn proof was queued on the proof service.
+    ProofRequested { job_id: u64 },
+    /// The proof could not be generated; the swap goes back to be proven again.
+    ProofFailed { reason: String },
+    /// The contract accepted the proof.
+    ProofSettled { eth_tx: String },
+    /// The signers started a session for the payout.
+    SigningStarted { session_id: String },
+    /// The signing session ended without a signature; the swap goes back to be signed again.
+    SigningFailed { reason: String },
+    /// The signed payout was broadcast.
+    PayoutBroadcast { txid: String },
+    /// The deposit cannot be minted, and is to be refunded to `address`, the address that
+    /// funded it.
+    RefundRequested { address: String, reason: String },
+    /// The refund to the depositor is final.
+    RefundPaid { txid: String },
+    /// The block holding the swap's Bitcoin transaction left the best chain after it was
+    /// counted final. A mint goes back to wait for its deposit, a burn proof to be requested
+    /// again once the payout is final, and any proof about the orphaned block is dropped.
+    ChainReorganized { block_hash: String },
+    /// The swap is abandoned.
+    Failed { reason: String },
+}
+
+/// What an unfinished swap waits for, so a restarted service knows where to resume.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    AwaitDeposit,
+    RequestMintProof,
+    AwaitProof { job_id: u64 },
+    StartSigning,
+    AwaitSignatures { session_id: String },
+    RequestBurnProof,
+    AwaitRefund,
+}
+
+/// A state change, as persisted in the swap's history.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Transition {
+    pub from: SwapState,
+    pub to: SwapState,
+    #[serde(flatten)]
+    pub event: SwapEvent,
+    /// Unix time the transition was applied.
+    pub at: u64,
+}
+
+/// Outcome of applying an event.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Applied {
+    Changed(SwapState),
+    /// The event is already in the history; nothing changed.
+    Duplicate,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Swap {
+    pub id: u64,
+    pub direction: Direction,
+    pub state: SwapState,
+    /// Ethereum recipient of a mint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    /// Burn request id on the contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_id: Option<u64>,
+    /// Burner's BTC address a burn is paid to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub btc_address: Option<String>,
+    /// Sats deposited for a mint, or owed for a burn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_sats: Option<u64>,
+    /// Deposit or payout transaction on Bitcoin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub btc_txid: Option<String>,
+    /// Proof service job of the latest proof request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_job: Option<u64>,
+    /// TSS session of the latest signing attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_session: Option<String>,
+    /// Ethereum transaction that settled the proof.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_tx: Option<String>,
+    /// BTC address a rejected deposit is refunded to, and the refund transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_txid: Option<String>,
+    pub created_at: u64,
+    pub history: Vec<Transition>,
+}
+
+/// Current Unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// State `event` moves a swap of `direction` to from `state`, or `None` if it does not apply.
+pub fn next_state(direction: Direction, state: SwapState, event: &SwapEvent) -> Option<SwapState> {
+    use Direction::{Burn, Mint};
+    use SwapState::*;
+    let next = match (direction, state, event) {
+        (_, state, SwapEvent::Failed { .. }) if !state.is_terminal() => Failed,
+        (Mint, Requested, SwapEvent::DepositConfirmed { .. }) => Deposited,
+        (Mint, Deposited, SwapEvent::ProofRequested { .. }) => Proving,
+        (Mint, Proving, SwapEvent::ProofFailed { .. }) => Deposited,
+        (Mint, Proving, SwapEvent::ProofSettled { .. }) => Minted,
+        (Mint, Deposited | Proving, SwapEvent::ChainReorganized { .. }) => Requested,
+        (Mint, Deposited | Proving, SwapEvent::RefundRequested { .. }) => Refunding,
+        (Mint, Refunding, SwapEvent::RefundPaid { .. }) => Refunded,
+        (Burn, Burned, SwapEvent::SigningStarted { .. }) => Signing,
+        (Burn, Signing, SwapEvent::SigningFailed { .. }) => Burned,
+        (Burn, Signing, SwapEvent::PayoutBroadcast { .. }) => Broadcast,
+        (Burn, Broadcast, SwapEvent::ProofRequested { .. }) => Proving,
+        (Burn, Proving, SwapEvent::ProofFailed { .. }) => Broadcast,
+        (Burn, Proving, SwapEvent::ProofSettled { .. }) => Settled,
+        (Burn, Proving, SwapEvent::ChainReorganized { .. }) => Broadcast,
+        _ => return None,
+    };
+    Some(next)
+}
+
+impl Swap {
+    /// A mint swap waiting for its deposit.
+    pub fn mint(id: u64, recipient: String) -> Self {
+        let mut swap = Swap::new(id, Direction::Mint, SwapState::Requested);
+        swap.recipient = Some(recipient);
+        swap
+    }
+
+    /// A burn swap for a final burn request, waiting for the signers.
+    pub fn burn(id: u64, burn_id: u64, btc_address: String, amount_sats: u64) -> Self {
+        let mut swap = Swap::new(id, Direction::Burn, SwapState::Burned);
+        swap.burn_id = Some(burn_id);
+        swap.btc_address = Some(btc_address);
+        swap.amount_sats = Some(amount_sats);
+        swap
+    }
+
+    fn new(id: u64, direction: Direction, state: SwapState) -> Self {
+        Swap {
+            id,
+            direction,
+            state,
+            recipient: None,
+            burn_id: None,
+            btc_address: None,
+            amount_sats: None,
+            btc_txid: None,
+            proof_job: None,
+            signing_session: None,
+            eth_tx: None,
+            refund_address: None,
+            refund_txid: None,
+            created_at: now(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Applies `event`, recording the transition. An event already in the history is a no-op,
+    /// so redelivering it after a crash is harmless; any other event the current state does
+    /// not accept is an error. A failed attempt only repeats the latest transition, as the
+    /// next attempt may fail the same way. A reorganization starts the history over: the
+    /// events it undid may happen again.
+    pub fn apply(&mut self, event: SwapEvent) -> Result<Applied, Box<dyn Error>> {
+        let since_reorg = self
+            .history
+            .iter()
+            .rposition(|transition| matches!(transition.event, SwapEvent::ChainReorganized { .. }))
+            .map_or(&self.history[..], |index| &self.history[index..]);
+        let applied = match &event {
+            SwapEvent::ProofFailed { .. } | SwapEvent::SigningFailed { .. } => since_reorg
+                .last()
+                .is_some_and(|transition| transition.event == event),
+            _ => since_reorg
+                .iter()
+                .any(|transition| transition.event == event),
+        };
+        if applied {
+            return Ok(Applied::Duplicate);
+        }
+        let to = next_state(self.direction, self.state, &event).ok_or_else(|| {
+            format!(
+                "Swap {} is {}: {:?} does not apply",
+                self.id, self.state, event
+            )
+        })?;
+        match &event {
+            SwapEvent::DepositConfirmed { txid, amount_sats } => {
+                self.btc_txid = Some(txid.clone());
+                self.amount_sats = Some(*amount_sats);
+            }
+            SwapEvent::ProofRequested { job_id } => self.proof_job = Some(*job_id),
+            SwapEvent::ProofSettled { eth_tx } => self.eth_tx = Some(eth_tx.clone()),
+            SwapEvent::SigningStarted { session_id } => {
+                self.signing_session = Some(session_id.clone())
+            }
+            SwapEvent::PayoutBroadcast { txid } => self.btc_txid = Some(txid.clone()),
+            SwapEvent::RefundRequested { address, .. } => {
+                self.refund_address = Some(address.clone())
+            }
+            SwapEvent::RefundPaid { txid } => self.refund_txid = Some(txid.clone()),
+            SwapEvent::ProofFailed { .. }
+            | SwapEvent::SigningFailed { .. }
+            | SwapEvent::ChainReorganized { .. }
+            | SwapEvent::Failed { .. } => {}
+        }
+        self.history.push(Transition {
+            from: self.state,
+            to,
+            event,
+            at: now(),
+        });
+        self.state = to;
+        Ok(Applied::Changed(to))
+    }
+
+    /// What the swap waits for; `None` once it is terminal.
+    pub fn next_action(&self) -> Option<Action> {
+        let action = match self.state {
+            SwapState::Requested => Action::AwaitDeposit,
+            SwapState::Deposited => Action::RequestMintProof,
+            SwapState::Proving => Action::AwaitProof {
+                job_id: self.proof_job?,
+            },
+            SwapState::Burned => Action::StartSigning,
+            SwapState::Signing => Action::AwaitSignatures {
+                session_id: self.signing_session.clone()?,
+            },
+            SwapState::Broadcast => Action::RequestBurnProof,
+            SwapState::Refunding => Action::AwaitRefund,
+            SwapState::Minted | SwapState::Settled | SwapState::Refunded | SwapState::Failed => {
+                return None
+            }
+        };
+        Some(action)
+    }
+}
@@ -0,0 +1,285 @@
+//! Policy and compliance hooks consulted before value moves: before a mint is submitted to the
+//! bridge contract and before a payout is signed. A [`PolicyHook`] approves a transfer or denies
+//! it with a reason; a [`PolicyEngine`] runs its hooks in order and the first denial wins.
+//!
+//! A transfer is checked together with the transfers already approved alongside it, e.g. the
+//! other payouts of a batch, so limits over several transfers see all of them. Once a transfer
+//! went through, [`PolicyEngine::record`] lets stateful hooks count it.
+
+use crate::{now, Direction};
+use std::error::Error;
+use std::fmt;
+
+/// Value about to leave the bridge, in either direction.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Transfer {
+    pub direction: Direction,
+    /// Deposit txid of a mint, burn id of a payout; a transfer is recorded once per id.
+    pub id: String,
+    pub amount_sats: u64,
+    /// Ethereum account minted to, or BTC address paid out to.
+    pub recipient: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Decision {
+    Approve,
+    Deny { reason: String },
+}
+
+impl Decision {
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Decision::Deny {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Approve => write!(f, "approved"),
+            Decision::Deny { reason } => write!(f, "denied: {}", reason),
+        }
+    }
+}
+
+/// A rule a transfer must pass. An error means the hook could not decide, and the transfer
+/// must not go ahead either.
+pub trait PolicyHook: Send + Sync {
+    /// Short name the hook's denials are reported under.
+    fn name(&self) -> &str;
+
+    /// Decides on `transfer`; `pending` are the transfers already approved with it and not
+    /// recorded yet.
+    fn check(&self, transfer: &Transfer, pending: &[Transfer]) -> Result<Decision, Box<dyn Error>>;
+
+    /// Counts a transfer that went through. Recording the same transfer again is a no-op.
+    fn record(&self, _transfer: &Transfer) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Whether a denial can lift with time, as a volume limit's does once the volume ages out;
+    /// the transfer is then held rather than refused for good.
+    fn transient(&self) -> bool {
+        false
+    }
+}
+
+/// Denies transfers of one direction above `max_sats`.
+pub struct AmountCap {
+    pub direction: Direction,
+    pub max_sats: u64,
+}
+
+impl PolicyHook for AmountCap {
+    fn name(&self) -> &str {
+        "amount cap"
+    }
+
+    fn check(
+        &self,
+        transfer: &Transfer,
+        _pending: &[Transfer],
+    ) -> Result<Decision, Box<dyn Error>> {
+        if transfer.direction != self.direction || transfer.amount_sats <= self.max_sats {
+            return Ok(Decision::Approve);
+        }
+        Ok(Decision::deny(format!(
+            "{} sats is above the cap of {} sats",
+            transfer.amount_sats, self.max_sats
+        )))
+    }
+}
+
+/// Recipients a direction may or may not pay: a denied recipient is refused, and with a
+/// non-empty allow list, so is every recipient not on it. Addresses compare case-insensitively,
+/// as both checksummed Ethereum and bech32 addresses may come in either case.
+pub struct AddressList {
+    pub direction: Direction,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl PolicyHook for AddressList {
+    fn name(&self) -> &str {
+        "address list"
+    }
+
+    fn check(
+        &self,
+        transfer: &Transfer,
+        _pending: &[Transfer],
+    ) -> Result<Decision, Box<dyn Error>> {
+        if transfer.direction != self.direction {
+            return Ok(Decision::Approve);
+        }
+        let listed = |list: &[String]| {
+            list.iter()
+                .any(|address| address.eq_ignore_ascii_case(&transfer.recipient))
+        };
+        if listed(&self.deny) {
+            return Ok(Decision::deny(format!(
+                "recipient {} is on the deny list",
+                transfer.recipient
+            )));
+        }
+        if !self.allow.is_empty() && !listed(&self.allow) {
+            return Ok(Decision::deny(format!(
+                "recipient {} is not on the allow list",
+                transfer.recipient
+            )));
+        }
+        Ok(Decision::Approve)
+    }
+}
+
+/// Caps the volume of one direction per epoch of `epoch_secs` seconds, counted from the Unix
+/// epoch. Recorded transfers are kept in a sled tree, keyed by big-endian epoch then transfer
+/// id, so the volume survives restarts and a transfer is counted once.
+pub struct EpochVolume {
+    pub direction: Direction,
+    pub limit_sats: u64,
+    pub epoch_secs: u64,
+    tree: sled::Tree,
+}
+
+impl EpochVolume {
+    pub fn new(direction: Direction, limit_sats: u64, epoch_secs: u64, tree: sled::Tree) -> Self {
+        EpochVolume {
+            direction,
+            limit_sats,
+            epoch_secs: epoch_secs.max(1),
+            tree,
+        }
+    }
+
+    fn epoch(&self) -> u64 {
+        now() / self.epoch_secs
+    }
+
+    fn key(epoch: u64, id: &str) -> Vec<u8> {
+        let mut key = epoch.to_be_bytes().to_vec();
+        key.extend_from_slice(id.as_bytes());
+        key
+    }
+
+    /// Sats recorded in the current epoch, leaving out the transfer with `except` id.
+    pub fn volume(&self, except: Option<&str>) -> Result<u64, Box<dyn Error>> {
+        let epoch = self.epoch();
+        let except = except.map(|id| Self::key(epoch, id));
+        let mut volume = 0u64;
+        for entry in self.tree.scan_prefix(epoch.to_be_bytes()) {
+            let (key, value) = entry?;
+            if except.as_deref() == Some(&key[..]) {
+                continue;
+            }
+            let amount: [u8; 8] = value
+                .as_ref()
+                .try_into()
+                .map_err(|_| "Corrupt epoch volume entry")?;
+            volume = volume.saturating_add(u64::from_be_bytes(amount));
+        }
+        Ok(volume)
+    }
+}
+
+impl PolicyHook for EpochVolume {
+    fn name(&self) -> &str {
+        "epoch volume"
+    }
+
+    fn check(&self, transfer: &Transfer, pending: &[Transfer]) -> Result<Decision, Box<dyn Error>> {
+        if transfer.direction != self.direction {
+            return Ok(Decision::Approve);
+        }
+        // A transfer checked again after it was recorded is not counted twice
+        let recorded = self.volume(Some(&transfer.id))?;
+        let pending: u64 = pending
+            .iter()
+            .filter(|other| other.direction == self.direction && other.id != transfer.id)
+            .map(|other| other.amount_sats)
+            .sum();
+        let total = recorded
+            .saturating_add(pending)
+            .saturating_add(transfer.amount_sats);
+        if total <= self.limit_sats {
+            return Ok(Decision::Approve);
+        }
+        Ok(Decision::deny(format!(
+            "{} sats would bring this epoch's volume to {} sats, above the limit of {} sats",
+            transfer.amount_sats, total, self.limit_sats
+        )))
+    }
+
+    fn transient(&self) -> bool {
+        true
+    }
+
+    fn record(&self, transfer: &Transfer) -> Result<(), Box<dyn Error>> {
+        if transfer.direction != self.direction {
+            return Ok(());
+        }
+        self.tree.insert(
+            Self::key(self.epoch(), &transfer.id),
+            &transfer.amount_sats.to_be_bytes(),
+        )?;
+        self.tree.flush()?;
+        Ok(())
+    }
+}
+
+/// Hooks run in the order they were added. Without hooks every transfer is approved.
+#[derive(Default)]
+pub struct PolicyEngine {
+    hooks: Vec<Box<dyn PolicyHook>>,
+}
+
+impl PolicyEngine {
+    pub fn new() -> Self {
+        PolicyEngine::default()
+    }
+
+    pub fn with(mut self, hook: impl PolicyHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Runs the hooks over `transfer`; the first denial is returned, its reason prefixed with
+    /// the hook's name.
+    pub fn check(
+        &self,
+        transfer: &Transfer,
+        pending: &[Transfer],
+    ) -> Result<Decision, Box<dyn Error>> {
+        for hook in &self.hooks {
+            if let Decision::Deny { reason } = hook.check(transfer, pending)? {
+                return Ok(Decision::deny(format!("{}: {}", hook.name(), reason)));
+            }
+        }
+        Ok(Decision::Approve)
+    }
+
+    /// The first denial of `transfer` by a hook whose denials do not lift with time, its reason
+    /// prefixed as `check` does; such a transfer will never be approved.
+    pub fn lasting_denial(&self, transfer: &Transfer) -> Result<Option<String>, Box<dyn Error>> {
+        for hook in self.hooks.iter().filter(|hook| !hook.transient()) {
+            if let Decision::Deny { reason } = hook.check(transfer, &[])? {
+                return Ok(Some(format!("{}: {}", hook.name(), reason)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lets every hook count `transfer`.
+    pub fn record(&self, transfer: &Transfer) -> Result<(), Box<dyn Error>> {
+        for hook in &self.hooks {
+            hook.record(transfer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Volume minted and redeemed over a rolling window, and the [`RateLimit`] hook capping it.
+//!
+//! [`policy::EpochVolume`](crate::policy::EpochVolume) counts fixed epochs, so up to twice its
+//! limit can pass around an epoch boundary. A rolling window counts the transfers of the last
+//! `window_secs` seconds whenever it is checked, which bounds what any stretch of that length
+//! can move: the damage a bug or a compromised key can do before someone steps in.
+
+use crate::policy::{Decision, PolicyHook, Transfer};
+use crate::{now, Direction};
+use sled::transaction::ConflictableTransactionError;
+use std::error::Error;
+
+/// Transfers are keyed `t`, direction, big-endian time, id; their amount is the value.
+const TRANSFER_PREFIX: u8 = b't';
+/// Time each transfer was recorded at, keyed `i`, direction, id; a transfer is counted once.
+const INDEX_PREFIX: u8 = b'i';
+
+fn direction_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Mint => b'm',
+        Direction::Burn => b'b',
+    }
+}
+
+fn transfer_key(direction: Direction, at: u64, id: &str) -> Vec<u8> {
+    let mut key = vec![TRANSFER_PREFIX, direction_byte(direction)];
+    key.extend_from_slice(&at.to_be_bytes());
+    key.extend_from_slice(id.as_bytes());
+    key
+}
+
+fn index_key(direction: Direction, id: &str) -> Vec<u8> {
+    let mut key = vec![INDEX_PREFIX, direction_byte(direction)];
+    key.extend_from_slice(id.as_bytes());
+    key
+}
+
+fn be_u64(bytes: &[u8]) -> Result<u64, Box<dyn Error>> {
+    let bytes: [u8; 8] = bytes
+        .try_into()
+        .map_err(|_| "Corrupt volume ledger entry")?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Sats and number of transfers of one direction in a window.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct WindowVolume {
+    pub sats: u64,
+    pub transfers: u64,
+}
+
+/// Minted and redeemed transfers with the time they went through, in a sled tree, so the
+/// volume survives restarts.
+#[derive(Clone)]
+pub struct VolumeLedger {
+    tree: sled::Tree,
+}
+
+impl VolumeLedger {
+    pub fn new(tree: sled::Tree) -> Self {
+        VolumeLedger { tree }
+    }
+
+    /// Volume of `direction` recorded after `since`, leaving out the transfer with `except` id.
+    pub fn window(
+        &self,
+        direction: Direction,
+        since: u64,
+        except: Option<&str>,
+    ) -> Result<WindowVolume, Box<dyn Error>> {
+        let start = transfer_key(direction, since.saturating_add(1), "");
+        let end = [TRANSFER_PREFIX, direction_byte(direction) + 1];
+        let mut volume = WindowVolume::default();
+        for entry in self.tree.range(start..end.to_vec()) {
+            let (key, value) = entry?;
+            if except.is_some_and(|id| key[10..] == *id.as_bytes()) {
+                continue;
+            }
+            volume.sats = volume.sats.saturating_add(be_u64(&value)?);
+            volume.transfers += 1;
+        }
+        Ok(volume)
+    }
+
+    /// Records `transfer` as gone through at `at`. Returns false if it already was.
+    pub fn record(&self, transfer: &Transfer, at: u64) -> Result<bool, Box<dyn Error>> {
+        let index = index_key(transfer.direction, &transfer.id);
+        let key = transfer_key(transfer.direction, at, &transfer.id);
+        let recorded = self
+            .tree
+            .transaction(|tx| {
+                if tx.get(&index)?.is_some() {
+                    return Ok(false);
+                }
+                tx.insert(index.as_slice(), &at.to_be_bytes())?;
+                tx.insert(key.as_slice(), &transfer.amount_sats.to_be_bytes())?;
+                Ok::<_, ConflictableTransactionError<()>>(true)
+            })
+            .map_err(|e| format!("Failed to record transfer {}: {:?}", transfer.id, e))?;
+        self.tree.flush()?;
+        Ok(recorded)
+    }
+
+    /// Drops the transfers of `direction` recorded at or before `before`. Returns how many.
+    pub fn prune(&self, direction: Direction, before: u64) -> Result<usize, Box<dyn Error>> {
+        let start = transfer_key(direction, 0, "");
+        let end = transfer_key(direction, before.saturating_add(1), "");
+        let mut pruned = 0;
+        for entry in self.tree.range(start..end) {
+            let (key, _) = entry?;
+            let id = String::from_utf8_lossy(&key[10..]).into_owned();
+            self.tree.remove(&key)?;
+            self.tree.remove(index_key(direction, &id))?;
+            pruned += 1;
+        }
+        Ok(pruned)
+    }
+}
+
+/// Caps the sats and the number of transfers of one direction over the last `window_secs`
+/// seconds. Each cap is only checked when set. Transfers that fell out of the window are
+/// dropped from the ledger as new ones are recorded.
+pub struct RateLimit {
+    pub direction: Direction,
+    pub window_secs: u64,
+    pub max_sats: Option<u64>,
+    pub max_transfers: Option<u64>,
+    ledger: VolumeLedger,
+}
+
+impl RateLimit {
+    pub fn new(
+        direction: Direction,
+        window_secs: u64,
+        max_sats: Option<u64>,
+        max_transfers: Option<u64>,
+        ledger: VolumeLedger,
+    ) -> Self {
+        RateLimit {
+            direction,
+            window_secs: window_secs.max(1),
+            max_sats,
+            max_transfers,
+            ledger,
+        }
+    }
+
+    /// Volume of the current window, leaving out the transfer with `except` id.
+    pub fn volume(&self, except: Option<&str>) -> Result<WindowVolume, Box<dyn Error>> {
+        self.ledger.window(
+            self.direction,
+            now().saturating_sub(self.window_secs),
+            except,
+        )
+    }
+}
+
+impl PolicyHook for RateLimit {
+    fn name(&self) -> &str {
+        "rate limit"
+    }
+
+    fn check(&self, transfer: &Transfer, pending: &[Transfer]) -> Result<Decision, Box<dyn Error>> {
+        if transfer.direction != self.direction {
+            return Ok(Decision::Approve);
+        }
+        // A transfer checked again after it was recorded is not counted twice
+        let mut volume = self.volume(Some(&transfer.id))?;
+        for other in pending
+            .iter()
+            .filter(|other| other.direction == self.direction && other.id != transfer.id)
+            .chain([transfer])
+        {
+            volume.sats = volume.sats.saturating_add(other.amount_sats);
+            volume.transfers += 1;
+        }
+        if let Some(max_sats) = self.max_sats.filter(|&max_sats| volume.sats > max_sats) {
+            return Ok(Decision::deny(format!(
+                "{} sats would bring the volume of the last {}s to {} sats, above the cap of {} sats",
+                transfer.amount_sats, self.window_secs, volume.sats, max_sats
+            )));
+        }
+        if let Some(max_transfers) = self
+            .max_transfers
+            .filter(|&max_transfers| volume.transfers > max_transfers)
+        {
+            return Ok(Decision::deny(format!(
+                "it would be transfer {} of the last {}s, above the cap of {}",
+                volume.transfers, self.window_secs, max_transfers
+            )));
+        }
+        Ok(Decision::Approve)
+    }
+
+    fn record(&self, transfer: &Transfer) -> Result<(), Box<dyn Error>> {
+        if transfer.direction != self.direction {
+            return Ok(());
+        }
+        let at = now();
+        self.ledger.record(transfer, at)?;
+        self.ledger
+            .prune(self.direction, at.saturating_sub(self.window_secs))?;
+        Ok(())
+    }
+
+    fn transient(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,176 @@
+//! sled database of the swaps. A swap and its lookup keys are written in one transaction, so a
+//! crash leaves either the whole transition or none of it.
+
+use crate::{Action, Applied, Swap, SwapEvent};
+use serde::de::DeserializeOwned;
+use sled::transaction::{abort, ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+use std::error::Error;
+use std::path::Path;
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+fn deposit_key(txid: &str) -> Vec<u8> {
+    format!("deposit:{}", txid).into_bytes()
+}
+
+fn burn_key(burn_id: u64) -> Vec<u8> {
+    format!("burn:{}", burn_id).into_bytes()
+}
+
+/// Turns an aborted or failed transaction into an error.
+fn transaction_error(e: TransactionError<String>) -> Box<dyn Error> {
+    match e {
+        TransactionError::Abort(message) => message.into(),
+        TransactionError::Storage(e) => e.into(),
+    }
+}
+
+/// Aborts a transaction with the error's message.
+fn abort_with<E: ToString>(e: E) -> ConflictableTransactionError<String> {
+    ConflictableTransactionError::Abort(e.to_string())
+}
+
+pub struct SwapStore {
+    db: sled::Db,
+    /// Swaps keyed by big-endian id.
+    swaps: sled::Tree,
+    /// Swap ids keyed by deposit txid and by burn id.
+    index: sled::Tree,
+}
+
+impl SwapStore {
+    /// Opens the database at `path`, creating it if needed; without a path the database is
+    /// temporary and dropped on exit.
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let config = match path {
+            Some(path) => sled::Config::new().path(path),
+            None => sled::Config::new().temporary(true),
+        };
+        let db = config.open()?;
+        let swaps = db.open_tree("swaps")?;
+        let index = db.open_tree("index")?;
+        Ok(SwapStore { db, swaps, index })
+    }
+
+    /// A new swap id. Ids start at 1, as a request id of 0 means none in deposit memos.
+    fn next_id(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.db.generate_id()? + 1)
+    }
+
+    /// Registers a mint swap for `recipient`, waiting for its deposit.
+    pub fn create_mint(&self, recipient: String) -> Result<Swap, Box<dyn Error>> {
+        let swap = Swap::mint(self.next_id()?, recipient);
+        self.swaps
+            .insert(swap.id.to_be_bytes(), serde_json::to_vec(&swap)?)?;
+        Ok(swap)
+    }
+
+    /// Registers the burn swap of a final burn request. A burn id is registered once: calling
+    /// again returns the existing swap.
+    pub fn create_burn(
+        &self,
+        burn_id: u64,
+        btc_address: String,
+        amount_sats: u64,
+    ) -> Result<Swap, Box<dyn Error>> {
+        let id = self.next_id()?;
+        (&self.swaps, &self.index)
+            .transaction(|(swaps, index)| {
+                if let Some(existing) = index.get(burn_key(burn_id))? {
+                    let bytes = swaps
+                        .get(&existing)?
+                        .ok_or_else(|| abort_with(format!("Burn {} has no swap", burn_id)))?;
+                    return decode(&bytes).map_err(abort_with);
+                }
+                let swap = Swap::burn(id, burn_id, btc_address.clone(), amount_sats);
+                swaps.insert(
+                    &id.to_be_bytes(),
+                    serde_json::to_vec(&swap).map_err(abort_with)?,
+                )?;
+                index.insert(burn_key(burn_id), &id.to_be_bytes())?;
+                Ok(swap)
+            })
+            .map_err(transaction_error)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<Swap>, Box<dyn Error>> {
+        self.swaps
+            .get(id.to_be_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    fn find(&self, key: &[u8]) -> Result<Option<Swap>, Box<dyn Error>> {
+        match self.index.get(key)? {
+            Some(id) => self.swaps.get(id)?.map(|bytes| decode(&bytes)).transpose(),
+            None => Ok(None),
+        }
+    }
+
+    /// Swap funded by deposit `txid`.
+    pub fn find_by_deposit(&self, txid: &str) -> Result<Option<Swap>, Box<dyn Error>> {
+        self.find(&deposit_key(txid))
+    }
+
+    /// Swap of burn request `burn_id`.
+    pub fn find_by_burn(&self, burn_id: u64) -> Result<Option<Swap>, Box<dyn Error>> {
+        self.find(&burn_key(burn_id))
+    }
+
+    /// Applies `event` to swap `id` and persists the transition. A deposit funds one swap
+    /// only: confirming a deposit already credited to another swap is an error.
+    pub fn apply(&self, id: u64, event: SwapEvent) -> Result<(Swap, Applied), Box<dyn Error>> {
+        (&self.swaps, &self.index)
+            .transaction(|(swaps, index)| {
+                let bytes = swaps
+                    .get(id.to_be_bytes())?
+                    .ok_or_else(|| abort_with(format!("No swap {}", id)))?;
+                let mut swap: Swap = decode(&bytes).map_err(abort_with)?;
+                let applied = swap.apply(event.clone()).map_err(abort_with)?;
+                if applied == Applied::Duplicate {
+                    return Ok((swap, applied));
+                }
+                if let SwapEvent::DepositConfirmed { txid, .. } = &event {
+                    if let Some(owner) = index.get(deposit_key(txid))? {
+                        if owner.as_ref() != id.to_be_bytes() {
+                            return abort(format!("Deposit {} already funds another swap", txid));
+                        }
+                    }
+                    index.insert(deposit_key(txid), &id.to_be_bytes())?;
+                }
+                swaps.insert(
+                    &id.to_be_bytes(),
+                    serde_json::to_vec(&swap).map_err(abort_with)?,
+                )?;
+                Ok((swap, applied))
+            })
+            .map_err(transaction_error)
+    }
+
+    /// Every swap, oldest first.
+    pub fn swaps(&self) -> Result<Vec<Swap>, Box<dyn Error>> {
+        self.swaps
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Unfinished swaps with what each waits for, for a service resuming after a restart.
+    pub fn recover(&self) -> Result<Vec<(Swap, Action)>, Box<dyn Error>> {
+        Ok(self
+            .swaps()?
+            .into_iter()
+            .filter_map(|swap| swap.next_action().map(|action| (swap, action)))
+            .collect())
+    }
+
+    /// Writes the pending changes to disk.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
@@ -36,6 +36,14 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
 
     // Mapping to track processed transaction IDs
     mapping(bytes32 => bool) public processedTxIds;
+    // Mapping to track Bitcoin payout transactions already used to fulfill a burn
+    mapping(bytes32 => bool) public processedPayoutTxIds;
+    // keccak256 of the vault scriptPubKey payouts must spend from; set at deployment
+    bytes32 public vaultScriptHash;
+    // P2P magic of the network proofs must be about; fixed at deployment, one network per contract
+    bytes4 public immutable networkMagic;
+    // Blocks the owner vouches for; a proof's confirmation chain must build on one of them
+    mapping(bytes32 => bool) public checkpoints;
 
     // Burn request structure
     struct BurnRequest {
@@ -89,6 +97,13 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
     error ReclaimNotRequester();
     error BurnAmountZero();
     error OperatorSendWrongRecipent();
+    error PayoutAlreadyProcessed();
+    error BurnIdMismatch();
+    error UnauthorizedVault();
+    error WrongDestinationChain();
+    error WrongNetwork();
+    error UnknownCheckpoint();
+    error VaultScriptUnset();
     error BurnAmountTooSmall();
     error BurnInsufficientBalance();
     error BurnRequestStillOpen();
@@ -113,12 +128,17 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
         bytes32 _programVKey_mint,
         bytes32 _programVKey_burn,
         string memory _bridge_address,
+        bytes memory _vault_script,
+        bytes4 _network_magic,
         address[] memory _stakers
     ) ERC20("Zero-Knowledge Bitcoin", "ZKBTC") Ownable(msg.sender) {
         verifier = _verifier;
         programVKey_mint = _programVKey_mint;
         programVKey_burn = _programVKey_burn;
         BRIDGE_ADDRESS = _bridge_address;
+        require(_vault_script.length > 0, VaultScriptUnset());
+        vaultScriptHash = keccak256(_vault_script);
+        networkMagic = _network_magic;
 
         require(_stakers.length > 0, "Stakers required");
         for (uint256 i = 0; i < _stakers.length; i++) {
@@ -184,6 +204,15 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
 
         require(!processedTxIds[tx_id], MintingRequestAlreadyProcessed());
         require(is_valid, InvalidProof());
+        // The deposit must pay the vault this contract trusts: word 22 is its script's hash
+        require(bytes32(_publicValues[704:736]) == vaultScriptHash, UnauthorizedVault());
+        // and name this chain in its memo (word 14), so one deposit cannot mint on two chains
+        require(uint256(bytes32(_publicValues[448:480])) == block.chainid, WrongDestinationChain());
+        // The bundle picks the network its headers are checked against; word 21 commits it
+        require(bytes4(_publicValues[672:676]) == networkMagic, WrongNetwork());
+        // The bundle's own checkpoint is optional, so anchor the chain here: word 11 is the
+        // parent of its first header
+        require(checkpoints[bytes32(_publicValues[352:384])], UnknownCheckpoint());
         require(amount > 0, MintingAmountZero());
         require(depositer_address != address(0), InvalidAddress());
 
@@ -269,9 +298,26 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
         catch {
             revert("Invalid proof");
         }
-        (string memory user_btc_address, uint256 amount,bool is_valid) = abi.decode(_publicValues, (string, uint256, bool));
+        (
+            string memory user_btc_address,
+            uint256 amount,
+            bool is_valid,
+            bytes32 payout_tx_id,
+            bytes memory vault_script_pubkey,
+            uint256 proof_burn_id
+        ) = abi.decode(_publicValues, (string, uint256, bool, bytes32, bytes, uint256));
 
         require(is_valid, InvalidProof());
+        require(proof_burn_id == burnId, BurnIdMismatch());
+        require(!processedPayoutTxIds[payout_tx_id], PayoutAlreadyProcessed());
+        // The circuit proves the payout was signed by the key it was given, so only this check
+        // ties that key to the bridge's vault
+        require(vaultScriptHash != bytes32(0), VaultScriptUnset());
+        require(keccak256(vault_script_pubkey) == vaultScriptHash, UnauthorizedVault());
+        // Word 15 is the network the payout was proven on
+        require(bytes4(_publicValues[480:484]) == networkMagic, WrongNetwork());
+        // and word 20 the parent of its first confirmation header
+        require(checkpoints[bytes32(_publicValues[640:672])], UnknownCheckpoint());
         require(
             keccak256(abi.encodePacked(burnRequests[burnId].btcAddress)) == 
             keccak256(abi.encodePacked(user_btc_address)),
@@ -280,6 +326,7 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
         require(amount >= burnRequests[burnId].exactBtcUserReceive, OperatorUnderpaid()); 
 
         request.fulfilled = true;
+        processedPayoutTxIds[payout_tx_id] = true;
 
         // Send escrowed ZKBTC to submitter
         _transfer(address(this), msg.sender, request.zkbtcToReimburse);
@@ -323,6 +370,21 @@ contract ZKBTC is ERC20, Ownable, ReentrancyGuard {
         programVKey_burn = new_pvkey;
     }
 
+    /// @notice Trusts `blockHash` (in RPC byte order) as the parent of proven confirmation chains
+    function add_checkpoint(bytes32 blockHash) external onlyOwner {
+        checkpoints[blockHash] = true;
+    }
+
+    function remove_checkpoint(bytes32 blockHash) external onlyOwner {
+        delete checkpoints[blockHash];
+    }
+
+    /// @notice Rotates the vault generation burn payouts must spend from
+    function change_vault_script(bytes calldata new_vault_script) external onlyOwner {
+        require(new_vault_script.length > 0, VaultScriptUnset());
+        vaultScriptHash = keccak256(new_vault_script);
+    }
+
 
     // -------------------- Reward claiming related functions ----------------------
     function _addRewardToStakers(uint256 totalReward) internal {
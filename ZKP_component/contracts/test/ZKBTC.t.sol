@@ -23,6 +23,8 @@ contract MockSP1Verifier is ISP1Verifier {
 }
 
 contract ZKBTCTest is Test {
+    using stdStorage for StdStorage;
+
     ZKBTC zkbtc;
     MockSP1Verifier verifier;
     address owner = address(0x1);
@@ -37,24 +39,92 @@ contract ZKBTCTest is Test {
     bytes32 constant PROGRAM_VKEY_BURN = keccak256("burn");
     uint256 constant SATOSHI_TO_ZKBTC = 10**10;
     uint256 constant SUBMISSION_PERIOD = 1 days;
+    bytes constant VAULT_SCRIPT = hex"5120192c2a30cbd7d0352f94d4c31a43f767f1ef43d7ff6438839880ec075d81a9f6";
+    bytes4 constant NETWORK_MAGIC = 0x0b110907; // testnet
+    bytes32 constant CHECKPOINT = keccak256("checkpoint");
 
     function setUp() public {
         vm.startPrank(owner);
         verifier = new MockSP1Verifier(true);
-        zkbtc = new ZKBTC(address(verifier), PROGRAM_VKEY_MINT, PROGRAM_VKEY_BURN, bridge,stakers);
+        zkbtc = new ZKBTC(address(verifier), PROGRAM_VKEY_MINT, PROGRAM_VKEY_BURN, bridge, VAULT_SCRIPT, NETWORK_MAGIC, stakers);
+        zkbtc.add_checkpoint(CHECKPOINT);
         vm.stopPrank();
     }
+    // Public values of a mint proof: the words the contract reads, the others zero. Word 11 is
+    // the checkpoint, word 14 the destination chain of the memo, word 21 the network magic and
+    // word 22 the hash of the vault script the deposit paid.
+    function mintValues(bytes32 txId, address _user, uint256 satoshis, bool isValid)
+        internal
+        view
+        returns (bytes memory)
+    {
+        return mintValuesOnChain(txId, _user, satoshis, isValid, block.chainid);
+    }
+    function mintValuesOnChain(bytes32 txId, address _user, uint256 satoshis, bool isValid, uint256 chainId)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        return mintValuesOn(txId, _user, satoshis, isValid, chainId, NETWORK_MAGIC);
+    }
+    function mintValuesOn(
+        bytes32 txId,
+        address _user,
+        uint256 satoshis,
+        bool isValid,
+        uint256 chainId,
+        bytes4 networkMagic
+    ) internal pure returns (bytes memory) {
+        return bytes.concat(
+            abi.encode(txId, _user, satoshis, isValid),
+            new bytes(7 * 32),
+            abi.encode(CHECKPOINT),
+            new bytes(2 * 32),
+            abi.encode(chainId),
+            new bytes(6 * 32),
+            abi.encode(networkMagic),
+            abi.encode(keccak256(VAULT_SCRIPT))
+        );
+    }
+    // Public values of a burn proof paying "btcAddress" from VAULT_SCRIPT, as the circuit
+    // commits them: 23 head words, then the address and the vault script. Word 15 is the
+    // network magic and word 20 the checkpoint; the words the contract does not read are zero.
+    function burnValues(uint256 satoshis, uint256 burnId) internal pure returns (bytes memory) {
+        return burnValuesOn(satoshis, burnId, NETWORK_MAGIC);
+    }
+    function burnValuesOn(uint256 satoshis, uint256 burnId, bytes4 networkMagic)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        bytes memory addressTail = abiTail(bytes("btcAddress"));
+        return bytes.concat(
+            abi.encode(23 * 32, satoshis, true, keccak256("payout1"), 23 * 32 + addressTail.length, burnId),
+            new bytes(9 * 32),
+            abi.encode(networkMagic),
+            new bytes(4 * 32),
+            abi.encode(CHECKPOINT),
+            new bytes(2 * 32),
+            addressTail,
+            abiTail(VAULT_SCRIPT)
+        );
+    }
+    // Tail of a `bytes` or `string` value in an ABI encoding: its length, then the data padded
+    // to whole words
+    function abiTail(bytes memory data) internal pure returns (bytes memory) {
+        return bytes.concat(abi.encode(data.length), data, new bytes((32 - data.length % 32) % 32));
+    }
     // Helper function to mint tokens for testing
     function mintForUser(address _user, uint256 satoshis) internal {
         vm.startPrank(operator2);
-        bytes memory publicValues = abi.encode(keccak256("tx1"), _user, satoshis, true);
+        bytes memory publicValues = mintValues(keccak256("tx1"), _user, satoshis, true);
         bytes memory proofBytes = hex"1234";
         zkbtc.verifyAndMint(publicValues, proofBytes);
         vm.stopPrank();
     }
     function mintForUser_2(address _user, uint256 satoshis) internal {
         vm.startPrank(operator2);
-        bytes memory publicValues = abi.encode(keccak256("tx2"), _user, satoshis, true);
+        bytes memory publicValues = mintValues(keccak256("tx2"), _user, satoshis, true);
         bytes memory proofBytes = hex"1234";
         zkbtc.verifyAndMint(publicValues, proofBytes);
         vm.stopPrank();
@@ -64,7 +134,7 @@ contract ZKBTCTest is Test {
     // Minting Tests
     function testVerifyAndMintHappyPath() public {
         uint256 satoshis = 100000;
-        bytes memory publicValues = abi.encode(keccak256("tx1"), user, satoshis, true);
+        bytes memory publicValues = mintValues(keccak256("tx1"), user, satoshis, true);
         bytes memory proofBytes = hex"1234";
 
         vm.prank(operator);
@@ -89,7 +159,7 @@ contract ZKBTCTest is Test {
 
     function testVerifyAndMintMinAmount() public {
         uint256 satoshis = 1; // 1 satoshi = 10,000 ZKBTC units, above min 15,000
-        bytes memory publicValues = abi.encode(keccak256("tx2"), user, satoshis, true);
+        bytes memory publicValues = mintValues(keccak256("tx2"), user, satoshis, true);
         bytes memory proofBytes = hex"1234";
 
         vm.prank(operator);
@@ -102,7 +172,7 @@ contract ZKBTCTest is Test {
 
     function testVerifyAndMintBelowMinAmount() public {
         uint256 satoshis = 0; // Will result in < 15,000 ZKBTC units
-        bytes memory publicValues = abi.encode(keccak256("tx3"), user, satoshis, true);
+        bytes memory publicValues = mintValues(keccak256("tx3"), user, satoshis, true);
         bytes memory proofBytes = hex"1234";
 
         vm.prank(operator);
@@ -112,7 +182,7 @@ contract ZKBTCTest is Test {
 
     function testVerifyAndMintInvalidProof() public {
         verifier.setShouldPass(false);
-        bytes memory publicValues = abi.encode(keccak256("tx4"), user, 100_000, true);
+        bytes memory publicValues = mintValues(keccak256("tx4"), user, 100_000, true);
         bytes memory proofBytes = hex"1234";
 
         vm.prank(operator);
@@ -121,7 +191,7 @@ contract ZKBTCTest is Test {
     }
 
     function testVerifyAndMintReuseTxId() public {
-        bytes memory publicValues = abi.encode(keccak256("tx1"), user, 100_000, true);
+        bytes memory publicValues = mintValues(keccak256("tx1"), user, 100_000, true);
         bytes memory proofBytes = hex"1234";
 
         vm.startPrank(operator);
@@ -131,6 +201,145 @@ contract ZKBTCTest is Test {
         vm.stopPrank();
     }
 
+    function testVerifyAndMintUnauthorizedVault() public {
+        // A deposit to another vault, here the bridge's previous generation, mints nothing
+        bytes memory publicValues = mintValues(keccak256("tx5"), user, 100_000, true);
+        vm.prank(owner);
+        zkbtc.change_vault_script(hex"51200000000000000000000000000000000000000000000000000000000000000001");
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.UnauthorizedVault.selector);
+        zkbtc.verifyAndMint(publicValues, hex"1234");
+        assertFalse(zkbtc.processedTxIds(keccak256("tx5")));
+        assertEq(zkbtc.balanceOf(user), 0);
+    }
+
+    function testVerifyAndMintWrongDestinationChain() public {
+        // A memo naming another chain, or none as v1 memos do, mints nothing here
+        uint256[2] memory chainIds = [block.chainid + 1, 0];
+        for (uint256 i = 0; i < chainIds.length; i++) {
+            bytes memory publicValues = mintValuesOnChain(keccak256("tx6"), user, 100_000, true, chainIds[i]);
+            vm.prank(operator);
+            vm.expectRevert(ZKBTC.WrongDestinationChain.selector);
+            zkbtc.verifyAndMint(publicValues, hex"1234");
+        }
+        assertFalse(zkbtc.processedTxIds(keccak256("tx6")));
+        assertEq(zkbtc.balanceOf(user), 0);
+    }
+
+    function testVerifyAndMintWrongNetwork() public {
+        // A bundle proven against another network's headers, here mainnet and regtest, mints nothing
+        bytes4[2] memory magics = [bytes4(0xf9beb4d9), bytes4(0xfabfb5da)];
+        for (uint256 i = 0; i < magics.length; i++) {
+            bytes memory publicValues = mintValuesOn(keccak256("tx7"), user, 100_000, true, block.chainid, magics[i]);
+            vm.prank(operator);
+            vm.expectRevert(ZKBTC.WrongNetwork.selector);
+            zkbtc.verifyAndMint(publicValues, hex"1234");
+        }
+        assertFalse(zkbtc.processedTxIds(keccak256("tx7")));
+        assertEq(zkbtc.balanceOf(user), 0);
+    }
+
+    function testVerifyAndMintUnknownCheckpoint() public {
+        // A chain building on a block the owner does not vouch for mints nothing
+        bytes memory publicValues = mintValues(keccak256("tx8"), user, 100_000, true);
+        vm.prank(owner);
+        zkbtc.remove_checkpoint(CHECKPOINT);
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.UnknownCheckpoint.selector);
+        zkbtc.verifyAndMint(publicValues, hex"1234");
+        assertFalse(zkbtc.processedTxIds(keccak256("tx8")));
+
+        vm.prank(owner);
+        zkbtc.add_checkpoint(CHECKPOINT);
+        vm.prank(operator);
+        zkbtc.verifyAndMint(publicValues, hex"1234");
+        assertTrue(zkbtc.processedTxIds(keccak256("tx8")));
+    }
+
+    function testAddCheckpointOnlyOwner() public {
+        vm.prank(operator);
+        vm.expectRevert();
+        zkbtc.add_checkpoint(keccak256("other"));
+        assertFalse(zkbtc.checkpoints(keccak256("other")));
+    }
+
+    function testNetworkMagicSetAtDeploy() public view {
+        assertEq(zkbtc.networkMagic(), NETWORK_MAGIC);
+    }
+
+    // Mint conformance vectors checked in by `gen-vectors`: regtest deposits to the manifest's
+    // vault whose memo names this chain, and their public values as the circuit commits them
+    string constant MINT_VECTORS = "../vectors/manifest.json";
+    bytes4 constant REGTEST_MAGIC = 0xfabfb5da;
+    // The regtest genesis block the vectors' headers build on, in display byte order
+    bytes32 constant REGTEST_GENESIS = 0x0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206;
+    address constant VECTOR_RECIPIENT = 0xa86Ed347B8D1043533fe30c07Fc47f3E3b849a42;
+
+    function testMintVectorsDecode() public view {
+        string memory manifest = vm.readFile(MINT_VECTORS);
+        bytes[] memory publicValues = vm.parseJsonBytesArray(manifest, ".vectors[*].expected.publicValues");
+        bool[] memory valid = vm.parseJsonBoolArray(manifest, ".vectors[*].expected.isValid");
+        bytes32[] memory txIds = vm.parseJsonBytes32Array(manifest, ".vectors[*].expected.fields.tx_id");
+        bytes32 vaultScriptHash = keccak256(vm.parseJsonBytes(manifest, ".vaultScript"));
+        assertGt(publicValues.length, 1);
+
+        for (uint256 i = 0; i < publicValues.length; i++) {
+            (bytes32 txId, address depositer, uint256 amount, bool isValid) =
+                abi.decode(publicValues[i], (bytes32, address, uint256, bool));
+            assertEq(txId, txIds[i]);
+            assertEq(isValid, valid[i]);
+            assertEq(depositer, isValid ? VECTOR_RECIPIENT : address(0));
+            assertEq(amount > 0, isValid);
+            // The words verifyAndMint reads by offset
+            assertEq(bytes4(this.slice(publicValues[i], 672, 676)), REGTEST_MAGIC);
+            assertEq(bytes32(this.slice(publicValues[i], 704, 736)), vaultScriptHash);
+            if (isValid) {
+                assertEq(bytes32(this.slice(publicValues[i], 352, 384)), REGTEST_GENESIS);
+                assertEq(uint256(bytes32(this.slice(publicValues[i], 448, 480))), block.chainid);
+            }
+        }
+    }
+
+    function testMintVectorsAgainstContract() public {
+        string memory manifest = vm.readFile(MINT_VECTORS);
+        bytes[] memory publicValues = vm.parseJsonBytesArray(manifest, ".vectors[*].expected.publicValues");
+        vm.startPrank(owner);
+        ZKBTC regtestBridge = new ZKBTC(
+            address(verifier), PROGRAM_VKEY_MINT, PROGRAM_VKEY_BURN, bridge,
+            vm.parseJsonBytes(manifest, ".vaultScript"), REGTEST_MAGIC, stakers
+        );
+        regtestBridge.add_checkpoint(REGTEST_GENESIS);
+        vm.stopPrank();
+
+        // Rejections first: some share the valid deposit's txid, which a mint marks as processed
+        for (uint256 i = 0; i < publicValues.length; i++) {
+            (,,, bool isValid) = abi.decode(publicValues[i], (bytes32, address, uint256, bool));
+            if (isValid) continue;
+            vm.prank(operator);
+            vm.expectRevert(ZKBTC.InvalidProof.selector);
+            regtestBridge.verifyAndMint(publicValues[i], hex"1234");
+        }
+        uint256 minted;
+        for (uint256 i = 0; i < publicValues.length; i++) {
+            (,, uint256 satoshis, bool isValid) = abi.decode(publicValues[i], (bytes32, address, uint256, bool));
+            if (!isValid) continue;
+            vm.prank(operator);
+            (, address depositer, uint256 amount,) = regtestBridge.verifyAndMint(publicValues[i], hex"1234");
+            assertEq(depositer, VECTOR_RECIPIENT);
+            assertEq(amount, (satoshis * SATOSHI_TO_ZKBTC * 9900) / 10000);
+            minted += amount;
+        }
+        assertGt(minted, 0);
+        assertEq(regtestBridge.balanceOf(VECTOR_RECIPIENT), minted);
+    }
+
+    // Calldata slice of `data`, for reading public values by the offsets the contract uses
+    function slice(bytes calldata data, uint256 start, uint256 end) external pure returns (bytes memory) {
+        return data[start:end];
+    }
+
     // Burning Tests
     function testInitiateBurnHappyPath() public {
         mintForUser(user, 100_0000_0000_0000); // Mint 100,000 satoshis worth
@@ -227,7 +436,7 @@ contract ZKBTCTest is Test {
             reclaimed: reclaimed
         });
 
-        bytes memory publicValues = abi.encode("btcAddress", (burnAmount* 9900 / SATOSHI_TO_ZKBTC), true);
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
         bytes memory proofBytes = hex"5678";
 
         vm.prank(operator);
@@ -269,13 +478,141 @@ contract ZKBTCTest is Test {
         assertEq(zkbtc.balanceOf(operator), request.zkbtcToReimburse + request.rewardOperator);
     }
 
+    function testSubmitBurnProofReplayedPayout() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user) / 2;
+
+        vm.startPrank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+        vm.stopPrank();
+
+        // Both burns claim the same Bitcoin payout transaction
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
+        bytes memory proofBytes = hex"5678";
+
+        vm.startPrank(operator);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+        assertTrue(zkbtc.processedPayoutTxIds(keccak256("payout1")));
+        publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 1);
+        vm.expectRevert(ZKBTC.PayoutAlreadyProcessed.selector);
+        zkbtc.submitBurnProof(1, publicValues, proofBytes);
+        vm.stopPrank();
+    }
+
+    function testSubmitBurnProofWrongBurnId() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user) / 2;
+
+        vm.startPrank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+        vm.stopPrank();
+
+        // Proof generated for burn 1 cannot settle burn 0 with the same address and amount
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 1);
+        bytes memory proofBytes = hex"5678";
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.BurnIdMismatch.selector);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+    }
+
+    function testSubmitBurnProofUnauthorizedVault() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user);
+
+        vm.prank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+
+        // The vault is rotated, so a payout from the previous generation is refused
+        vm.prank(owner);
+        zkbtc.change_vault_script(hex"51200000000000000000000000000000000000000000000000000000000000000001");
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
+        bytes memory proofBytes = hex"5678";
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.UnauthorizedVault.selector);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+
+        vm.prank(owner);
+        zkbtc.change_vault_script(VAULT_SCRIPT);
+        vm.prank(operator);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+        assertTrue(zkbtc.processedPayoutTxIds(keccak256("payout1")));
+    }
+
+    function testSubmitBurnProofWrongNetwork() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user);
+
+        vm.prank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+
+        // A payout proven on another network, here mainnet, fulfills nothing
+        bytes memory publicValues = burnValuesOn(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0, 0xf9beb4d9);
+        bytes memory proofBytes = hex"5678";
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.WrongNetwork.selector);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+        assertFalse(zkbtc.processedPayoutTxIds(keccak256("payout1")));
+    }
+
+    function testSubmitBurnProofUnknownCheckpoint() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user);
+
+        vm.prank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+
+        vm.prank(owner);
+        zkbtc.remove_checkpoint(CHECKPOINT);
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
+        bytes memory proofBytes = hex"5678";
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.UnknownCheckpoint.selector);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+        assertFalse(zkbtc.processedPayoutTxIds(keccak256("payout1")));
+    }
+
+    function testSubmitBurnProofVaultScriptUnset() public {
+        mintForUser(user, 100_0000_0000);
+        uint256 burnAmount = zkbtc.balanceOf(user);
+
+        vm.prank(user);
+        zkbtc.initiateBurn(burnAmount, "btcAddress");
+
+        // Without a vault to check against, no payout proves anything
+        stdstore.target(address(zkbtc)).sig("vaultScriptHash()").checked_write(bytes32(0));
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
+        bytes memory proofBytes = hex"5678";
+
+        vm.prank(operator);
+        vm.expectRevert(ZKBTC.VaultScriptUnset.selector);
+        zkbtc.submitBurnProof(0, publicValues, proofBytes);
+        assertFalse(zkbtc.processedPayoutTxIds(keccak256("payout1")));
+    }
+
+    function testDeployWithoutVaultScriptReverts() public {
+        vm.expectRevert(ZKBTC.VaultScriptUnset.selector);
+        new ZKBTC(address(verifier), PROGRAM_VKEY_MINT, PROGRAM_VKEY_BURN, bridge, "", NETWORK_MAGIC, stakers);
+    }
+
+    function testChangeVaultScriptToEmptyReverts() public {
+        vm.prank(owner);
+        vm.expectRevert(ZKBTC.VaultScriptUnset.selector);
+        zkbtc.change_vault_script("");
+    }
+
     function testSubmitBurnProofAfterSubmissionPeriod()  public {
         mintForUser(user, 100_0000_0000); // Mint 100,000 satoshis worth
         uint256 burnAmount = zkbtc.balanceOf(user); // 1M ZKBTC units
 
         vm.prank(user);
         zkbtc.initiateBurn(burnAmount, "btcAddress");
-        bytes memory publicValues = abi.encode("btcAddress", (burnAmount* 9900 / SATOSHI_TO_ZKBTC), true);
+        bytes memory publicValues = burnValues(burnAmount * 9900 / SATOSHI_TO_ZKBTC, 0);
         bytes memory proofBytes = hex"5678";
 
         vm.prank(operator);
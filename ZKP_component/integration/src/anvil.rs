@@ -0,0 +1,233 @@
+//! Anvil chain with the ZKBTC contract. Its proofs are checked by SP1's mock verifier, which
+//! accepts the empty proofs of the SP1 mock prover, so the real circuits run without proving.
+//! Transactions are sent from Anvil's unlocked default accounts.
+
+use crate::{free_port, run, wait_for, Process, RpcResponse};
+use alloy_primitives::{Address, Bytes, FixedBytes, U256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use bitcoin::Network;
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// First three of Anvil's default accounts: the operator relaying the proofs, the user
+/// minting and burning, and the contract's only staker.
+pub const OPERATOR: Address = alloy_primitives::address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+pub const USER: Address = alloy_primitives::address!("70997970C51812dc3A010C7d01b50e0d17dc79C8");
+pub const STAKER: Address = alloy_primitives::address!("3C44CdDdB6a900fa2b585dd299e03d12FA4293BC");
+/// Key of `OPERATOR`, for `submit`.
+pub const OPERATOR_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+sol! {
+    interface IZKBTC {
+        function balanceOf(address account) external view returns (uint256);
+        function initiateBurn(uint256 amountRequestBurnZkbtc, string calldata btcAddress) external;
+        function nextBurnId() external view returns (uint256);
+        function add_checkpoint(bytes32 blockHash) external;
+        function burnRequests(uint256 burnId) external view returns (address user, uint256 total_amount, uint256 zkbtcToReimburse, uint256 exactBtcUserReceive, uint256 rewardOperator, uint256 rewardStaker, uint256 dust, string btcAddress, uint256 timestamp, bool fulfilled, bool reclaimed);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Receipt {
+    status: String,
+    contract_address: Option<Address>,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    bytecode: ArtifactBytecode,
+}
+
+#[derive(Deserialize)]
+struct ArtifactBytecode {
+    object: String,
+}
+
+pub struct Anvil {
+    _process: Process,
+    pub rpc_url: String,
+    client: Client,
+}
+
+fn request<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> Result<T, Box<dyn Error>> {
+    let response: RpcResponse<T> = client
+        .post(url)
+        .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+        .send()?
+        .json()?;
+    response.into_result(method)
+}
+
+/// Creation code of `contract` from the artifacts `forge build` wrote for `source`.
+fn creation_code(
+    contracts_dir: &Path,
+    source: &str,
+    contract: &str,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let path = contracts_dir
+        .join("out")
+        .join(source)
+        .join(format!("{}.json", contract));
+    let artifact: Artifact = serde_json::from_reader(
+        std::fs::File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?,
+    )?;
+    Ok(hex::decode(artifact.bytecode.object.trim_start_matches("0x"))?)
+}
+
+impl Anvil {
+    /// Starts `anvil` (or `ANVIL_BIN`) on a free port.
+    pub fn start(workdir: &Path) -> Result<Self, Box<dyn Error>> {
+        let port = free_port()?;
+        let mut command =
+            Command::new(std::env::var_os("ANVIL_BIN").unwrap_or_else(|| "anvil".into()));
+        command.args(["--port", &port.to_string()]);
+        let mut process = Process::spawn("anvil", workdir, command)?;
+        let rpc_url = format!("http://127.0.0.1:{}", port);
+        let client = Client::new();
+        wait_for("anvil", Duration::from_secs(30), || {
+            process.check_running()?;
+            request::<Value>(&client, &rpc_url, "eth_chainId", json!([]))?;
+            Ok(true)
+        })?;
+        Ok(Anvil {
+            _process: process,
+            rpc_url,
+            client,
+        })
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Box<dyn Error>> {
+        request(&self.client, &self.rpc_url, method, params)
+    }
+
+    /// Sends a transaction from the unlocked account `from` and returns its receipt once
+    /// mined; a reverted transaction is an error.
+    fn send(
+        &self,
+        from: Address,
+        to: Option<Address>,
+        data: Vec<u8>,
+    ) -> Result<Receipt, Box<dyn Error>> {
+        let mut tx = json!({ "from": from, "data": Bytes::from(data) });
+        if let Some(to) = to {
+            tx["to"] = json!(to);
+        }
+        let hash: String = self.call("eth_sendTransaction", json!([tx]))?;
+        let mut receipt = None;
+        wait_for("the receipt", Duration::from_secs(30), || {
+            receipt = self.call::<Option<Receipt>>("eth_getTransactionReceipt", json!([hash]))?;
+            Ok(receipt.is_some())
+        })?;
+        let receipt = receipt.ok_or("No receipt")?;
+        if receipt.status != "0x1" {
+            return Err(format!("Transaction {} reverted", hash).into());
+        }
+        Ok(receipt)
+    }
+
+    fn view<C: SolCall>(&self, to: Address, call: C) -> Result<C::Return, Box<dyn Error>> {
+        let returns: Bytes = self.call(
+            "eth_call",
+            json!([{ "to": to, "data": Bytes::from(call.abi_encode()) }, "latest"]),
+        )?;
+        Ok(C::abi_decode_returns(&returns)?)
+    }
+
+    /// Builds the contracts with `forge` and deploys the mock verifier and ZKBTC behind it,
+    /// paying out of `vault_script` and accepting proofs about regtest, with `STAKER` as its only
+    /// staker. Returns the ZKBTC address.
+    pub fn deploy_bridge(
+        &self,
+        contracts_dir: &Path,
+        bridge_address: &str,
+        vault_script: &[u8],
+    ) -> Result<Address, Box<dyn Error>> {
+        let mut forge = Command::new(std::env::var_os("FORGE_BIN").unwrap_or_else(|| "forge".into()));
+        forge.arg("build").current_dir(contracts_dir);
+        run("forge build", forge)?;
+
+        let verifier = self
+            .send(
+                OPERATOR,
+                None,
+                creation_code(contracts_dir, "SP1MockVerifier.sol", "SP1MockVerifier")?,
+            )?
+            .contract_address
+            .ok_or("Verifier deployment created no contract")?;
+        // The mock verifier checks no program key, so none is needed
+        let mut code = creation_code(contracts_dir, "ZKBTC.sol", "ZKBTC")?;
+        code.extend(
+            (
+                verifier,
+                FixedBytes::<32>::ZERO,
+                FixedBytes::<32>::ZERO,
+                bridge_address.to_string(),
+                Bytes::copy_from_slice(vault_script),
+                FixedBytes::<4>::from(Network::Regtest.magic().to_bytes()),
+                vec![STAKER],
+            )
+                .abi_encode_params(),
+        );
+        Ok(self
+            .send(OPERATOR, None, code)?
+            .contract_address
+            .ok_or("ZKBTC deployment created no contract")?)
+    }
+
+    pub fn balance_of(&self, zkbtc: Address, account: Address) -> Result<U256, Box<dyn Error>> {
+        self.view(zkbtc, IZKBTC::balanceOfCall { account })
+    }
+
+    /// Trusts `block_hash`, in RPC byte order, as the parent of proven confirmation chains.
+    /// Sent by `OPERATOR`, which deployed the contract and owns it.
+    pub fn add_checkpoint(&self, zkbtc: Address, block_hash: &str) -> Result<(), Box<dyn Error>> {
+        let call = IZKBTC::add_checkpointCall {
+            blockHash: block_hash.parse()?,
+        };
+        self.send(OPERATOR, Some(zkbtc), call.abi_encode())?;
+        Ok(())
+    }
+
+    /// Burns `amount` ZKBTC units of `user` for a payout to `btc_address`, and returns the
+    /// burn id.
+    pub fn initiate_burn(
+        &self,
+        zkbtc: Address,
+        user: Address,
+        amount: U256,
+        btc_address: &str,
+    ) -> Result<u64, Box<dyn Error>> {
+        let burn_id = self.view(zkbtc, IZKBTC::nextBurnIdCall {})?;
+        let call = IZKBTC::initiateBurnCall {
+            amountRequestBurnZkbtc: amount,
+            btcAddress: btc_address.to_string(),
+        };
+        self.send(user, Some(zkbtc), call.abi_encode())?;
+        Ok(u64::try_from(burn_id)?)
+    }
+
+    pub fn burn_request(
+        &self,
+        zkbtc: Address,
+        burn_id: u64,
+    ) -> Result<IZKBTC::burnRequestsReturn, Box<dyn Error>> {
+        self.view(
+            zkbtc,
+            IZKBTC::burnRequestsCall {
+                burnId: U256::from(burn_id),
+            },
+        )
+    }
+}
@@ -0,0 +1,209 @@
+//! Regtest `bitcoind` with a funded wallet, started in the work directory.
+
+use crate::{free_port, wait_for, Process, RpcResponse};
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::{Address, Amount, Network, ScriptBuf, Transaction, Txid};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Blocks a regtest coinbase needs before the wallet can spend it.
+pub const COINBASE_MATURITY: u32 = 101;
+
+const RPC_USER: &str = "bridge";
+const RPC_PASSWORD: &str = "bridge";
+const WALLET: &str = "integration";
+
+pub struct Bitcoind {
+    _process: Process,
+    pub rpc_url: String,
+    client: Client,
+}
+
+#[derive(Deserialize)]
+struct RawTransaction {
+    hex: String,
+}
+
+#[derive(Deserialize)]
+struct SignedTransaction {
+    hex: String,
+    complete: bool,
+}
+
+#[derive(Deserialize)]
+struct ScanResult {
+    total_amount: f64,
+}
+
+fn request<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> Result<T, Box<dyn Error>> {
+    let response: RpcResponse<T> = client
+        .post(url)
+        .basic_auth(RPC_USER, Some(RPC_PASSWORD))
+        .json(&json!({ "jsonrpc": "1.0", "id": "integration", "method": method, "params": params }))
+        .send()?
+        .json()?;
+    response.into_result(method)
+}
+
+impl Bitcoind {
+    /// Starts `bitcoind` (or `BITCOIND_BIN`) on free ports with `-txindex`, and creates the
+    /// wallet the deposits and payouts are funded from, with mature coins.
+    pub fn start(workdir: &Path) -> Result<Self, Box<dyn Error>> {
+        let datadir = workdir.join("bitcoind");
+        std::fs::create_dir_all(&datadir)?;
+        let rpc_port = free_port()?;
+        let mut command = Command::new(
+            std::env::var_os("BITCOIND_BIN").unwrap_or_else(|| "bitcoind".into()),
+        );
+        command.args([
+            "-regtest".to_string(),
+            format!("-datadir={}", datadir.display()),
+            format!("-rpcport={}", rpc_port),
+            format!("-port={}", free_port()?),
+            format!("-rpcuser={}", RPC_USER),
+            format!("-rpcpassword={}", RPC_PASSWORD),
+            "-txindex=1".to_string(),
+            "-fallbackfee=0.0001".to_string(),
+            "-listen=0".to_string(),
+        ]);
+        let mut process = Process::spawn("bitcoind", workdir, command)?;
+        let rpc_url = format!("http://127.0.0.1:{}", rpc_port);
+        let client = Client::new();
+        wait_for("bitcoind", Duration::from_secs(30), || {
+            process.check_running()?;
+            request::<Value>(&client, &rpc_url, "getblockchaininfo", json!([]))?;
+            Ok(true)
+        })?;
+        let bitcoind = Bitcoind {
+            _process: process,
+            rpc_url,
+            client,
+        };
+        bitcoind.call::<Value>("", "createwallet", json!([WALLET]))?;
+        bitcoind.mine(COINBASE_MATURITY)?;
+        Ok(bitcoind)
+    }
+
+    /// Source flags of the script binaries reading this node.
+    pub fn source_args(&self) -> Vec<String> {
+        vec![
+            "--source".to_string(),
+            "rpc".to_string(),
+            "--rpc-url".to_string(),
+            self.rpc_url.clone(),
+            "--rpc-user".to_string(),
+            RPC_USER.to_string(),
+            "--rpc-password".to_string(),
+            RPC_PASSWORD.to_string(),
+            "--network".to_string(),
+            "regtest".to_string(),
+        ]
+    }
+
+    fn call<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn Error>> {
+        request(
+            &self.client,
+            &format!("{}{}", self.rpc_url, path),
+            method,
+            params,
+        )
+    }
+
+    fn wallet_call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn Error>> {
+        self.call(&format!("/wallet/{}", WALLET), method, params)
+    }
+
+    /// Mines `blocks` blocks to the wallet and returns their hashes.
+    pub fn mine(&self, blocks: u32) -> Result<Vec<String>, Box<dyn Error>> {
+        let address = self.new_address()?;
+        self.call("", "generatetoaddress", json!([blocks, address.to_string()]))
+    }
+
+    /// Hash of the chain tip.
+    pub fn best_block_hash(&self) -> Result<String, Box<dyn Error>> {
+        self.call("", "getbestblockhash", json!([]))
+    }
+
+    /// A fresh address of the wallet.
+    pub fn new_address(&self) -> Result<Address, Box<dyn Error>> {
+        let address: String = self.wallet_call("getnewaddress", json!([]))?;
+        Ok(Address::from_str(&address)?.require_network(Network::Regtest)?)
+    }
+
+    /// Pays `sats` to `script` from the wallet.
+    pub fn send_to(&self, script: &ScriptBuf, sats: u64) -> Result<Txid, Box<dyn Error>> {
+        let address = Address::from_script(script, Network::Regtest)?;
+        let txid: String = self.wallet_call(
+            "sendtoaddress",
+            json!([address.to_string(), Amount::from_sat(sats).to_btc()]),
+        )?;
+        Ok(Txid::from_str(&txid)?)
+    }
+
+    /// Sends a deposit from the wallet: `sats` to `vault_script`, then `memo` as the OP_RETURN
+    /// data the mint circuit reads the recipient from.
+    pub fn send_deposit(
+        &self,
+        vault_script: &ScriptBuf,
+        memo: &[u8],
+        sats: u64,
+    ) -> Result<Txid, Box<dyn Error>> {
+        let vault_address = Address::from_script(vault_script, Network::Regtest)?;
+        let mut vault_output = Map::new();
+        vault_output.insert(
+            vault_address.to_string(),
+            json!(Amount::from_sat(sats).to_btc()),
+        );
+        let outputs = json!([vault_output, { "data": hex::encode(memo) }]);
+        let unfunded: String = self.call("", "createrawtransaction", json!([[], outputs]))?;
+        let funded: RawTransaction = self.wallet_call(
+            "fundrawtransaction",
+            json!([unfunded, { "changePosition": 2 }]),
+        )?;
+        let signed: SignedTransaction =
+            self.wallet_call("signrawtransactionwithwallet", json!([funded.hex]))?;
+        if !signed.complete {
+            return Err("The wallet could not sign the deposit".into());
+        }
+        let txid: String = self.call("", "sendrawtransaction", json!([signed.hex]))?;
+        Ok(Txid::from_str(&txid)?)
+    }
+
+    pub fn transaction(&self, txid: &Txid) -> Result<Transaction, Box<dyn Error>> {
+        let raw: String = self.call("", "getrawtransaction", json!([txid.to_string()]))?;
+        Ok(deserialize(&hex::decode(raw)?)?)
+    }
+
+    pub fn broadcast(&self, tx: &Transaction) -> Result<Txid, Box<dyn Error>> {
+        let txid: String = self.call("", "sendrawtransaction", json!([serialize_hex(tx)]))?;
+        Ok(Txid::from_str(&txid)?)
+    }
+
+    /// Confirmed sats held by the unspent outputs paying `script`.
+    pub fn balance(&self, script: &ScriptBuf) -> Result<u64, Box<dyn Error>> {
+        let descriptor = format!("raw({})", script.to_hex_string());
+        let result: ScanResult = self.call("", "scantxoutset", json!(["start", [descriptor]]))?;
+        Ok(Amount::from_btc(result.total_amount)?.to_sat())
+    }
+}
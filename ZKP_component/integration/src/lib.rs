@@ -0,0 +1,239 @@
+//! End-to-end harness of the whole bridge on one machine:
+//! - a regtest `bitcoind` ([`bitcoind::Bitcoind`]);
+//! - an Anvil chain with the ZKBTC contract behind SP1's mock verifier ([`anvil::Anvil`]);
+//! - the TSS signers of `TSS_component/docker-compose.yml`, driven by the Rust coordinator
+//!   ([`tss::Signers`], [`tss::Coordinator`]);
+//! - the script binaries, proving with the SP1 mock prover and relaying the fixtures with
+//!   `submit` ([`Bins`]).
+//!
+//! `tests/mint_burn.rs` drives a mint and a burn through all of them and checks the balances on
+//! both chains. Every process is stopped when its handle is dropped, so a failing run leaves
+//! nothing behind.
+
+pub mod anvil;
+pub mod bitcoind;
+pub mod tss;
+
+use serde::Deserialize;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Keeps the work directory after the run when set, for its logs and bundles.
+pub const KEEP_WORKDIR_ENV: &str = "BRIDGE_KEEP_WORKDIR";
+
+/// Root of the repository, above `ZKP_component`.
+pub fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+/// A port nothing listens on right now.
+pub fn free_port() -> Result<u16, Box<dyn Error>> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Polls `ready` every half second until it returns true, for at most `timeout`. Errors of
+/// `ready` count as not ready yet; the last one is reported on timeout.
+pub fn wait_for(
+    what: &str,
+    timeout: Duration,
+    mut ready: impl FnMut() -> Result<bool, Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let mut last_error = None;
+    while start.elapsed() < timeout {
+        match ready() {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => last_error = Some(e.to_string()),
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Err(match last_error {
+        Some(e) => format!("Timed out waiting for {}: {}", what, e),
+        None => format!("Timed out waiting for {}", what),
+    }
+    .into())
+}
+
+/// JSON-RPC reply of `bitcoind` and Anvil.
+#[derive(Deserialize)]
+pub(crate) struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl<T> RpcResponse<T> {
+    pub(crate) fn into_result(self, method: &str) -> Result<T, Box<dyn Error>> {
+        match (self.result, self.error) {
+            (_, Some(error)) => {
+                Err(format!("{} failed ({}): {}", method, error.code, error.message).into())
+            }
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(format!("{} returned no result", method).into()),
+        }
+    }
+}
+
+/// Directory of one run's databases, logs and fixtures, removed on drop unless
+/// `BRIDGE_KEEP_WORKDIR` is set.
+pub struct Workdir {
+    path: PathBuf,
+}
+
+impl Workdir {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "bridge-integration-{}-{}",
+            std::process::id(),
+            nanos
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok(Workdir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Workdir {
+    fn drop(&mut self) {
+        if std::env::var_os(KEEP_WORKDIR_ENV).is_some() {
+            eprintln!("Work directory kept at {}", self.path.display());
+            return;
+        }
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A child process, killed on drop. Its stdout and stderr go to `<name>.log` in the work
+/// directory.
+pub struct Process {
+    name: String,
+    child: Child,
+}
+
+impl Process {
+    pub fn spawn(name: &str, workdir: &Path, mut command: Command) -> Result<Self, Box<dyn Error>> {
+        let log = File::create(workdir.join(format!("{}.log", name)))?;
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", name, e))?;
+        Ok(Process {
+            name: name.to_string(),
+            child,
+        })
+    }
+
+    /// Error if the process already exited, e.g. because its port was taken.
+    pub fn check_running(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.child.try_wait()? {
+            Some(status) => Err(format!("{} exited with {}", self.name, status).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Runs `command` to completion and returns its stdout; a non-zero exit is an error carrying
+/// its stderr.
+pub fn run(name: &str, mut command: Command) -> Result<String, Box<dyn Error>> {
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Where the binaries under test are: the script binaries in `BRIDGE_BIN_DIR` (default
+/// `ZKP_component/target/release`), the TSS coordinator at `COORDINATOR_BIN` (default
+/// `TSS_component/tss-coordinator/target/release/coordinator`). Both are built beforehand.
+/// They run in the work directory, so their journals and caches stay in it.
+pub struct Bins {
+    pub script_dir: PathBuf,
+    pub coordinator: PathBuf,
+    workdir: PathBuf,
+}
+
+impl Bins {
+    pub fn from_env(workdir: &Path) -> Self {
+        let root = repo_root();
+        Bins {
+            workdir: workdir.to_path_buf(),
+            script_dir: std::env::var_os("BRIDGE_BIN_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| root.join("ZKP_component/target/release")),
+            coordinator: std::env::var_os("COORDINATOR_BIN")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| {
+                    root.join("TSS_component/tss-coordinator/target/release/coordinator")
+                }),
+        }
+    }
+
+    /// Errors naming the first binary that was not built.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        for name in ["build-bundle", "evm", "submit"] {
+            let path = self.script_dir.join(name);
+            if !path.exists() {
+                return Err(format!(
+                    "{} not found; run `cargo build --release` in ZKP_component/script or set BRIDGE_BIN_DIR",
+                    path.display()
+                )
+                .into());
+            }
+        }
+        if !self.coordinator.exists() {
+            return Err(format!(
+                "{} not found; run `cargo build --release` in TSS_component/tss-coordinator or set COORDINATOR_BIN",
+                self.coordinator.display()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Runs script binary `name` with `args` and the mock prover, ignoring `BRIDGE_CONFIG`.
+    pub fn script<I, S>(&self, name: &str, args: I) -> Result<String, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(self.script_dir.join(name));
+        command
+            .args(args)
+            .env_remove("BRIDGE_CONFIG")
+            .env("SP1_PROVER", "mock")
+            .current_dir(&self.workdir);
+        run(name, command)
+    }
+}
@@ -0,0 +1,167 @@
+//! The TSS signers of `TSS_component/docker-compose.yml` and the Rust coordinator driving them.
+//! The signers keep their state under `/state`, so they run in containers of a compose project
+//! of their own, taken down with its volumes on drop. Their gRPC is TLS only, with the
+//! development certificates of `TSS_component/certs`.
+
+use crate::{repo_root, run, wait_for};
+use std::error::Error;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Signer services of the compose file and the gRPC ports they publish.
+const SIGNERS: [(&str, u16); 3] = [("signer1", 50051), ("signer2", 50052), ("signer3", 50053)];
+
+pub struct Signers {
+    project: String,
+}
+
+/// Directory of the development CA and certificates the compose file mounts.
+fn certs_dir() -> PathBuf {
+    repo_root().join("TSS_component/certs")
+}
+
+fn compose(project: &str) -> Command {
+    let mut command = Command::new("docker");
+    command.args(["compose", "-f"]).arg(repo_root().join("TSS_component/docker-compose.yml"));
+    command.args(["-p", project]);
+    command
+}
+
+impl Signers {
+    /// Builds and starts the three signers, and waits until their gRPC ports accept
+    /// connections.
+    pub fn start() -> Result<Self, Box<dyn Error>> {
+        let mut gen_certs = Command::new("sh");
+        gen_certs.arg(certs_dir().join("gen-certs.sh"));
+        run("gen-certs.sh", gen_certs)?;
+        let project = format!("bridge-integration-{}", std::process::id());
+        let mut up = compose(&project);
+        up.args(["up", "-d", "--build"])
+            .args(SIGNERS.iter().map(|(service, _)| service));
+        // Taken down on drop even if `up` fails half-way
+        let signers = Signers { project };
+        run("docker compose up", up)?;
+        for (service, port) in SIGNERS {
+            wait_for(service, Duration::from_secs(120), || {
+                Ok(TcpStream::connect(("127.0.0.1", port)).is_ok())
+            })?;
+        }
+        Ok(signers)
+    }
+
+    /// `--signers` of the coordinator.
+    pub fn urls(&self) -> String {
+        SIGNERS
+            .iter()
+            .map(|(_, port)| format!("https://localhost:{}", port))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl Drop for Signers {
+    fn drop(&mut self) {
+        let mut down = compose(&self.project);
+        down.args(["down", "-v"]);
+        if let Err(e) = run("docker compose down", down) {
+            eprintln!("Failed to stop the signers: {}", e);
+        }
+    }
+}
+
+/// Group key the signers hold, as `dkg` prints it.
+pub struct GroupKey {
+    /// x-only key, as `--group-key` and `TSS_GROUP_KEY` take it.
+    pub x_only_hex: String,
+    /// Taproot address of the key on regtest: the vault.
+    pub address: String,
+}
+
+/// The `coordinator` binary, on regtest with its session database in the work directory.
+pub struct Coordinator {
+    bin: PathBuf,
+    db: PathBuf,
+    signer_urls: String,
+}
+
+impl Coordinator {
+    pub fn new(bin: &Path, workdir: &Path, signers: &Signers) -> Self {
+        Coordinator {
+            bin: bin.to_path_buf(),
+            db: workdir.join("coordinator-db"),
+            signer_urls: signers.urls(),
+        }
+    }
+
+    fn command<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let certs = certs_dir();
+        let mut command = Command::new(&self.bin);
+        command
+            .arg("--signers")
+            .arg(&self.signer_urls)
+            .arg("--tls-ca")
+            .arg(certs.join("ca.pem"))
+            .arg("--tls-cert")
+            .arg(certs.join("coordinator.pem"))
+            .arg("--tls-key")
+            .arg(certs.join("coordinator.key"))
+            .arg("--db")
+            .arg(&self.db)
+            .args(["--network", "regtest", "--retries", "10"])
+            .args(args);
+        command
+    }
+
+    /// Runs the DKG across the signers and returns the group key.
+    pub fn dkg(&self) -> Result<GroupKey, Box<dyn Error>> {
+        let output = run("coordinator dkg", self.command(["dkg"]))?;
+        let field = |prefix: &str| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(prefix))
+                .map(|value| value.split_once(": ").map_or(value, |(_, value)| value))
+                .map(|value| value.trim().to_string())
+                .ok_or_else(|| format!("coordinator dkg printed no {}", prefix.trim()))
+        };
+        let verify_key_hex = field("group verifying key")?;
+        // The key may be printed compressed; the vault descriptor wants it x-only
+        let x_only_hex = match verify_key_hex.len() {
+            66 => verify_key_hex[2..].to_string(),
+            _ => verify_key_hex,
+        };
+        Ok(GroupKey {
+            x_only_hex,
+            address: field("taproot address")?,
+        })
+    }
+
+    /// Signs every input of the unsigned transaction `tx_hex` with the group key and returns
+    /// one Schnorr signature per input, in hex.
+    pub fn sign_transaction(
+        &self,
+        tx_hex: &str,
+        prevout_sats: &[u64],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let prevout_sats = prevout_sats
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let output = run(
+            "coordinator sign",
+            self.command(["sign", "--tx-hex", tx_hex, "--prevout-sats", &prevout_sats]),
+        )?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
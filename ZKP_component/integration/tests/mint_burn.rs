@@ -0,0 +1,223 @@
+//! A mint and a burn through the whole bridge: the deposit is proven with the mock prover and
+//! minted on Anvil, then the burn is paid out of the TSS vault on regtest, proven and settled.
+//! Needs `bitcoind`, `anvil`, `forge` and `docker` on the PATH and the release binaries built;
+//! see "Integration tests" in the README.
+
+use alloy_primitives::U256;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::{
+    absolute::LockTime, transaction::Version, Address, Amount, Network, OutPoint, ScriptBuf,
+    Sequence, Transaction, TxIn, TxOut, Witness,
+};
+use bridge_integration::anvil::{Anvil, OPERATOR, OPERATOR_KEY, USER};
+use bridge_integration::bitcoind::Bitcoind;
+use bridge_integration::tss::{Coordinator, GroupKey, Signers};
+use bridge_integration::{repo_root, Bins, Workdir};
+use serde_json::Value;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+const SATOSHI_TO_ZKBTC: u64 = 10_000_000_000;
+const DEPOSIT_SATS: u64 = 1_000_000;
+const BURN_SATS: u64 = 500_000;
+const VAULT_SATS: u64 = 2_000_000;
+const PAYOUT_FEE_SATS: u64 = 1_000;
+/// Blocks the bundles put in `chains`, counting the transaction's block.
+const CONFIRMATIONS: u32 = 6;
+/// Chain id of Anvil; the contract only mints deposits whose memo names it.
+const ANVIL_CHAIN_ID: u64 = 31_337;
+
+/// Proves `bundle` with the mock prover and returns the fixture's path.
+fn prove(bins: &Bins, workdir: &Path, circuit: &str, bundle: &Path) -> Result<String, Box<dyn Error>> {
+    let journal = workdir.join(format!("journal-{}.json", circuit));
+    let fixture = bins.script(
+        "evm",
+        [
+            "--prover".to_string(),
+            "mock".to_string(),
+            "--system".to_string(),
+            "groth16".to_string(),
+            "--circuit".to_string(),
+            circuit.to_string(),
+            "--network".to_string(),
+            "regtest".to_string(),
+            "--input-json".to_string(),
+            bundle.display().to_string(),
+            "--journal".to_string(),
+            journal.display().to_string(),
+            "--stdout".to_string(),
+        ],
+    )?;
+    let path = workdir.join(format!("fixture-{}.json", circuit));
+    std::fs::write(&path, fixture)?;
+    Ok(path.display().to_string())
+}
+
+/// Relays `fixture` to the contract from the operator account.
+fn submit(
+    bins: &Bins,
+    anvil: &Anvil,
+    contract: &str,
+    circuit: &str,
+    fixture: &str,
+    group_key: &GroupKey,
+) -> Result<(), Box<dyn Error>> {
+    bins.script(
+        "submit",
+        [
+            "--fixture",
+            fixture,
+            "--circuit",
+            circuit,
+            "--rpc",
+            &anvil.rpc_url,
+            "--contract",
+            contract,
+            "--private-key",
+            OPERATOR_KEY,
+            "--group-key",
+            &group_key.x_only_hex,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sats the mint proof commits to: the third word of its public values.
+fn minted_sats(fixture: &str) -> Result<u64, Box<dyn Error>> {
+    let fixture: Value = serde_json::from_reader(std::fs::File::open(fixture)?)?;
+    let public_values = fixture["publicValue"]
+        .as_str()
+        .ok_or("Fixture has no public values")?;
+    let bytes = hex::decode(public_values.trim_start_matches("0x"))?;
+    let amount = bytes.get(64..96).ok_or("Public values too short")?;
+    Ok(u64::try_from(U256::from_be_slice(amount))?)
+}
+
+#[test]
+#[ignore = "needs bitcoind, anvil, forge, docker and the release binaries"]
+fn mint_and_burn() -> Result<(), Box<dyn Error>> {
+    let workdir = Workdir::new()?;
+    let work = workdir.path();
+    let bins = Bins::from_env(work);
+    bins.check()?;
+
+    let bitcoind = Bitcoind::start(work)?;
+    let anvil = Anvil::start(work)?;
+    let signers = Signers::start()?;
+    let coordinator = Coordinator::new(&bins.coordinator, work, &signers);
+    let group_key = coordinator.dkg()?;
+    let vault_script = Address::from_str(&group_key.address)?
+        .require_network(Network::Regtest)?
+        .script_pubkey();
+    let contracts_dir = repo_root().join("ZKP_component/contracts");
+    let zkbtc = anvil.deploy_bridge(&contracts_dir, &group_key.address, vault_script.as_bytes())?;
+    let contract = zkbtc.to_string();
+
+    // Mint: deposit to the vault with a v2 memo naming Anvil and the user
+    let memo = [&[0x02][..], &ANVIL_CHAIN_ID.to_be_bytes(), USER.as_slice()].concat();
+    let deposit = bitcoind.send_deposit(&vault_script, &memo, DEPOSIT_SATS)?;
+    // The confirmation chain builds on the current tip, which the owner vouches for
+    anvil.add_checkpoint(zkbtc, &bitcoind.best_block_hash()?)?;
+    bitcoind.mine(CONFIRMATIONS)?;
+    let mint_bundle = work.join("mint.json");
+    let mut args = vec![
+        "--circuit".to_string(),
+        "mint".to_string(),
+        "--txid".to_string(),
+        deposit.to_string(),
+        "--group-key".to_string(),
+        group_key.x_only_hex.clone(),
+        "--output".to_string(),
+        mint_bundle.display().to_string(),
+    ];
+    args.extend(bitcoind.source_args());
+    bins.script("build-bundle", &args)?;
+    let mint_fixture = prove(&bins, work, "mint", &mint_bundle)?;
+    submit(&bins, &anvil, &contract, "mint", &mint_fixture, &group_key)?;
+
+    let minted = U256::from(minted_sats(&mint_fixture)?) * U256::from(SATOSHI_TO_ZKBTC);
+    let user_zkbtc = minted * U256::from(9_900) / U256::from(10_000);
+    assert_eq!(anvil.balance_of(zkbtc, USER)?, user_zkbtc);
+    assert_eq!(bitcoind.balance(&vault_script)?, DEPOSIT_SATS);
+
+    // Burn: the user burns, the vault pays them out through the TSS, the operator is reimbursed
+    let payout_address = bitcoind.new_address()?;
+    let burn_id = anvil.initiate_burn(
+        zkbtc,
+        USER,
+        U256::from(BURN_SATS) * U256::from(SATOSHI_TO_ZKBTC),
+        &payout_address.to_string(),
+    )?;
+    let request = anvil.burn_request(zkbtc, burn_id)?;
+    let owed_sats = u64::try_from(request.exactBtcUserReceive)?;
+
+    let funding = bitcoind.send_to(&vault_script, VAULT_SATS)?;
+    bitcoind.mine(1)?;
+    let vout = bitcoind
+        .transaction(&funding)?
+        .output
+        .iter()
+        .position(|output| output.script_pubkey == vault_script)
+        .ok_or("Funding transaction does not pay the vault")?;
+    let change_sats = VAULT_SATS - owed_sats - PAYOUT_FEE_SATS;
+    let mut payout = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(funding, u32::try_from(vout)?),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![
+            TxOut {
+                value: Amount::from_sat(owed_sats),
+                script_pubkey: payout_address.script_pubkey(),
+            },
+            TxOut {
+                value: Amount::from_sat(change_sats),
+                script_pubkey: vault_script.clone(),
+            },
+        ],
+    };
+    let signatures = coordinator.sign_transaction(&serialize_hex(&payout), &[VAULT_SATS])?;
+    let signature = hex::decode(signatures.first().ok_or("coordinator printed no signature")?)?;
+    payout.input[0].witness = Witness::from_slice(&[signature]);
+    let payout_txid = bitcoind.broadcast(&payout)?;
+    anvil.add_checkpoint(zkbtc, &bitcoind.best_block_hash()?)?;
+    bitcoind.mine(CONFIRMATIONS)?;
+
+    let burn_bundle = work.join("burn.json");
+    let mut args = vec![
+        "--circuit".to_string(),
+        "burn".to_string(),
+        "--txid".to_string(),
+        payout_txid.to_string(),
+        "--burn-id".to_string(),
+        burn_id.to_string(),
+        "--group-key".to_string(),
+        group_key.x_only_hex.clone(),
+        "--eth-rpc-url".to_string(),
+        anvil.rpc_url.clone(),
+        "--contract".to_string(),
+        contract.clone(),
+        "--output".to_string(),
+        burn_bundle.display().to_string(),
+    ];
+    args.extend(bitcoind.source_args());
+    bins.script("build-bundle", &args)?;
+    let burn_fixture = prove(&bins, work, "burn", &burn_bundle)?;
+    let operator_before = anvil.balance_of(zkbtc, OPERATOR)?;
+    submit(&bins, &anvil, &contract, "burn", &burn_fixture, &group_key)?;
+
+    assert!(anvil.burn_request(zkbtc, burn_id)?.fulfilled);
+    assert_eq!(
+        anvil.balance_of(zkbtc, OPERATOR)?,
+        operator_before + request.zkbtcToReimburse + request.rewardOperator
+    );
+    assert_eq!(bitcoind.balance(&payout_address.script_pubkey())?, owed_sats);
+    // The deposit stays in the vault; the payout spent the funding output
+    assert_eq!(bitcoind.balance(&vault_script)?, DEPOSIT_SATS + change_sats);
+    Ok(())
+}
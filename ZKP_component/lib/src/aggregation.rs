@@ -0,0 +1,206 @@
+//! Merkle commitment over the public values of aggregated mint or burn proofs.
+//!
+//! Leaves are `keccak256(public_values)` and inner nodes hash the sorted pair, matching
+//! OpenZeppelin's `MerkleProof.verify` so a contract can check a single mint or burn against
+//! the committed root. An odd node at the end of a level is carried up unchanged.
+//!
+//! The verification key the proofs were checked against is committed as the `bytes32` an SP1
+//! verifier takes, so the contract can compare it with the program key it stores.
+
+use crate::{
+    AggregationInputStruct, ZkpBurnAggregationPublicValuesStruct,
+    ZkpMintAggregationPublicValuesStruct,
+};
+use alloy_primitives::{keccak256, B256, U256};
+use sha2::{Digest, Sha256};
+
+/// Bits per word of a verification key hash: each is a BabyBear element, below 2^31.
+const VKEY_WORD_BITS: usize = 31;
+
+/// Encodes a verification key hash given as `HashableKey::hash_u32` words into the `bytes32`
+/// returned by `HashableKey::bytes32`: the words packed big-endian, 31 bits each, into one
+/// BN254 field element. The eight words fill 248 bits, so the top byte is always zero.
+pub fn vkey_bytes32(vkey: &[u32; 8]) -> B256 {
+    let packed = vkey.iter().fold(U256::ZERO, |acc, word| {
+        (acc << VKEY_WORD_BITS) | U256::from(*word)
+    });
+    B256::from(packed.to_be_bytes::<32>())
+}
+
+/// Hashes one proof's ABI-encoded public values into a leaf.
+pub fn public_values_leaf(public_values: &[u8]) -> B256 {
+    keccak256(public_values)
+}
+
+/// Hashes two nodes in sorted order.
+fn hash_pair(a: B256, b: B256) -> B256 {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut concat = [0u8; 64];
+    concat[..32].copy_from_slice(left.as_slice());
+    concat[32..].copy_from_slice(right.as_slice());
+    keccak256(concat)
+}
+
+/// Reduces one tree level to the next.
+fn next_level(level: &[B256]) -> Vec<B256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_pair(*left, *right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Computes the Merkle root of `leaves`; zero for an empty set.
+pub fn public_values_root(leaves: &[B256]) -> B256 {
+    if leaves.is_empty() {
+        return B256::ZERO;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// What the mint and burn aggregation circuits commit, whichever public-value type holds it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aggregate {
+    pub vkey_hash: B256,
+    pub public_values_root: B256,
+    pub proof_count: U256,
+}
+
+/// Verifies every proof of `input` and folds its public values into the committed root.
+/// `verify_proof` is given the key words and the SHA-256 digest of one proof's public values,
+/// as `sp1_zkvm::lib::verify::verify_sp1_proof` takes them.
+pub fn verify_and_fold(
+    input: &AggregationInputStruct,
+    mut verify_proof: impl FnMut(&[u32; 8], &[u8; 32]),
+) -> Aggregate {
+    let mut leaves = Vec::with_capacity(input.public_values.len());
+    for public_values in &input.public_values {
+        let public_values_digest: [u8; 32] = Sha256::digest(public_values).into();
+        verify_proof(&input.vkey, &public_values_digest);
+        leaves.push(public_values_leaf(public_values));
+    }
+    Aggregate {
+        vkey_hash: vkey_bytes32(&input.vkey),
+        public_values_root: public_values_root(&leaves),
+        proof_count: U256::from(leaves.len()),
+    }
+}
+
+impl From<Aggregate> for ZkpMintAggregationPublicValuesStruct {
+    fn from(aggregate: Aggregate) -> Self {
+        ZkpMintAggregationPublicValuesStruct {
+            mint_vkey_hash: aggregate.vkey_hash,
+            public_values_root: aggregate.public_values_root,
+            proof_count: aggregate.proof_count,
+        }
+    }
+}
+
+impl From<Aggregate> for ZkpBurnAggregationPublicValuesStruct {
+    fn from(aggregate: Aggregate) -> Self {
+        ZkpBurnAggregationPublicValuesStruct {
+            burn_vkey_hash: aggregate.vkey_hash,
+            public_values_root: aggregate.public_values_root,
+            proof_count: aggregate.proof_count,
+        }
+    }
+}
+
+/// Builds the sibling path proving `leaves[index]` under `public_values_root(leaves)`.
+pub fn public_values_proof(leaves: &[B256], mut index: usize) -> Vec<B256> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < level.len() {
+            proof.push(level[sibling]);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits a `bytes32` verification key back into its 31-bit words.
+    fn vkey_words(vkey: B256) -> [u32; 8] {
+        let packed = U256::from_be_bytes(vkey.0);
+        let mask = (U256::from(1u8) << VKEY_WORD_BITS) - U256::from(1u8);
+        std::array::from_fn(|i| {
+            let shift = VKEY_WORD_BITS * (7 - i);
+            ((packed >> shift) & mask).to::<u32>()
+        })
+    }
+
+    #[test]
+    fn vkey_words_pack_big_endian() {
+        assert_eq!(
+            vkey_bytes32(&[0, 0, 0, 0, 0, 0, 0, 1]),
+            B256::with_last_byte(1)
+        );
+        let mut high = [0u8; 32];
+        // 1 << (7 * 31): bit 217 is bit 1 of byte 4
+        high[4] = 0x02;
+        assert_eq!(vkey_bytes32(&[1, 0, 0, 0, 0, 0, 0, 0]), B256::from(high));
+    }
+
+    #[test]
+    fn child_vkey_round_trips() {
+        // Canonical BabyBear words, the first one below the modulus 2^31 - 2^27 + 1
+        let vkey = [
+            0x7800_0000,
+            0x1234_5678,
+            0x0000_0001,
+            0x6abc_def0,
+            0x0fed_cba9,
+            0x7777_7777,
+            0x0000_0000,
+            0x5555_aaaa,
+        ];
+        let bytes32 = vkey_bytes32(&vkey);
+        assert_eq!(bytes32[0], 0);
+        assert_eq!(vkey_words(bytes32), vkey);
+    }
+
+    #[test]
+    fn every_proof_is_verified_and_folded() {
+        let input = AggregationInputStruct {
+            vkey: [7; 8],
+            public_values: vec![vec![1], vec![2, 2], vec![3, 3, 3]],
+        };
+        let mut verified = Vec::new();
+        let aggregate = verify_and_fold(&input, |vkey, digest| {
+            assert_eq!(vkey, &input.vkey);
+            verified.push(*digest);
+        });
+        let digests: Vec<[u8; 32]> = input
+            .public_values
+            .iter()
+            .map(|public_values| Sha256::digest(public_values).into())
+            .collect();
+        assert_eq!(verified, digests);
+        let leaves: Vec<B256> = input
+            .public_values
+            .iter()
+            .map(|public_values| public_values_leaf(public_values))
+            .collect();
+        assert_eq!(
+            aggregate,
+            Aggregate {
+                vkey_hash: vkey_bytes32(&input.vkey),
+                public_values_root: public_values_root(&leaves),
+                proof_count: U256::from(3),
+            }
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Parameters of the source chains the bridge verifies: Bitcoin's networks and Litecoin's.
+//!
+//! Everything chain-specific the circuits check goes through [`ChainParams`]: the magic they
+//! commit, the address encodings, the proof-of-work hash and limit, and the retarget rules.
+//! Bitcoin's networks take their rules from `bitcoin::params::Params` as before, so a Bitcoin
+//! bundle verifies exactly as it did without this module.
+
+use crate::{double_sha256, verify::DEFAULT_NETWORK};
+use bitcoin::base58;
+use bitcoin::bech32::{self, Hrp};
+use bitcoin::hash_types::BlockHash;
+use bitcoin::hashes::Hash;
+use bitcoin::network::Network;
+use bitcoin::p2p::Magic;
+use bitcoin::params::Params;
+use bitcoin::pow::Target;
+use bitcoin::script::{Script, ScriptBuf};
+use bitcoin::{
+    Address as BitcoinAddress, CompactTarget, PubkeyHash, ScriptHash, WitnessProgram,
+    WitnessVersion,
+};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Bundle `network` names of the Litecoin chains.
+pub const LITECOIN: &str = "litecoin";
+pub const LITECOIN_TESTNET: &str = "litecoin-testnet";
+pub const LITECOIN_REGTEST: &str = "litecoin-regtest";
+
+/// Hash a header must bring under its target.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PowHash {
+    /// Double SHA-256, the block hash itself.
+    Sha256d,
+    /// scrypt with N = 1024, r = 1, p = 1 over the header, salted with the header.
+    Scrypt,
+}
+
+/// Chain-specific constants of a source chain.
+#[derive(Clone, Debug)]
+pub struct ChainParams {
+    /// Name bundles carry in their `network` field.
+    pub name: String,
+    /// Bitcoin network, for the chains rust-bitcoin knows; addresses are then handled by it.
+    pub network: Option<Network>,
+    /// Message start bytes, committed so a proof names the chain it was made on. Unique per
+    /// chain, which is what a contract pins.
+    pub magic: [u8; 4],
+    /// Human-readable part of segwit addresses.
+    pub bech32_hrp: &'static str,
+    /// Version byte of base58 P2PKH addresses.
+    pub p2pkh_prefix: u8,
+    /// Version byte of base58 P2SH addresses.
+    pub p2sh_prefix: u8,
+    pub pow_hash: PowHash,
+    /// Whether a retarget measures its timespan from the last block of the previous epoch
+    /// instead of the first block of the current one, as Litecoin does against time warps.
+    pub retarget_spans_previous_block: bool,
+    /// Proof-of-work limit, spacing, retarget timespan and testnet rules.
+    pub pow: Params,
+}
+
+impl ChainParams {
+    pub fn bitcoin(network: Network) -> Self {
+        let (bech32_hrp, p2pkh_prefix, p2sh_prefix) = match network {
+            Network::Bitcoin => ("bc", 0, 5),
+            Network::Regtest => ("bcrt", 111, 196),
+            _ => ("tb", 111, 196),
+        };
+        ChainParams {
+            name: network.to_string(),
+            network: Some(network),
+            magic: network.magic().to_bytes(),
+            bech32_hrp,
+            p2pkh_prefix,
+            p2sh_prefix,
+            pow_hash: PowHash::Sha256d,
+            retarget_spans_previous_block: false,
+            pow: Params::new(network),
+        }
+    }
+
+    /// Litecoin mainnet: 2.5-minute blocks retargeting every 2016 blocks (3.5 days).
+    pub fn litecoin() -> Self {
+        ChainParams {
+            name: LITECOIN.to_string(),
+            network: None,
+            magic: [0xfb, 0xc0, 0xb6, 0xdb],
+            bech32_hrp: "ltc",
+            p2pkh_prefix: 48,
+            p2sh_prefix: 50,
+            pow_hash: PowHash::Scrypt,
+            retarget_spans_previous_block: true,
+            pow: litecoin_pow(false, false, 0x1e0fffff),
+        }
+    }
+
+    /// Litecoin testnet4, which allows minimum-difficulty blocks like Bitcoin's testnet.
+    pub fn litecoin_testnet() -> Self {
+        ChainParams {
+            name: LITECOIN_TESTNET.to_string(),
+            magic: [0xfd, 0xd2, 0xc8, 0xf1],
+            bech32_hrp: "tltc",
+            p2pkh_prefix: 111,
+            p2sh_prefix: 58,
+            pow: litecoin_pow(true, false, 0x1e0fffff),
+            ..Self::litecoin()
+        }
+    }
+
+    /// A local `litecoind -regtest`, which never retargets. Litecoin Core reuses Bitcoin
+    /// regtest's message start, so the circuits commit `fabfb5db` instead: a contract pinned to
+    /// Bitcoin regtest must not accept a bundle that merely names Litecoin's.
+    pub fn litecoin_regtest() -> Self {
+        ChainParams {
+            name: LITECOIN_REGTEST.to_string(),
+            magic: [0xfa, 0xbf, 0xb5, 0xdb],
+            bech32_hrp: "rltc",
+            pow: litecoin_pow(true, true, 0x207fffff),
+            ..Self::litecoin_testnet()
+        }
+    }
+
+    /// Chain committing `magic`. Every chain commits its own, so each contract deployment
+    /// accepts proofs about exactly one.
+    pub fn from_magic(magic: [u8; 4]) -> Option<Self> {
+        if let Some(network) = Network::from_magic(Magic::from_bytes(magic)) {
+            return Some(ChainParams::bitcoin(network));
+        }
+        [
+            ChainParams::litecoin(),
+            ChainParams::litecoin_testnet(),
+            ChainParams::litecoin_regtest(),
+        ]
+        .into_iter()
+        .find(|chain| chain.magic == magic)
+    }
+
+    /// Height of the block whose timestamp starts the timespan of the retarget at `boundary`.
+    pub fn epoch_start_height(&self, boundary: u32) -> u32 {
+        let interval = self.pow.difficulty_adjustment_interval() as u32;
+        if self.retarget_spans_previous_block && boundary > interval {
+            boundary - interval - 1
+        } else {
+            boundary.saturating_sub(interval)
+        }
+    }
+
+    /// Bits the block at a retarget boundary must carry, given the bits before it and the
+    /// timespan of the epoch.
+    pub fn next_work_required(&self, last: CompactTarget, timespan: u64) -> CompactTarget {
+        if self.network.is_some() || self.pow.no_pow_retargeting {
+            return CompactTarget::from_next_work_required(last, timespan, &self.pow);
+        }
+        // Litecoin's limit is close enough to 2^256 that the product can overflow, so Litecoin
+        // Core halves the target around the multiplication, losing its lowest bit
+        let target_timespan = self.pow.pow_target_timespan;
+        let timespan = timespan.clamp(target_timespan / 4, target_timespan * 4);
+        let limit = self.pow.max_attainable_target;
+        let mut target = limbs(Target::from_compact(last));
+        let shift = bit_length(&target) > bit_length(&limbs(limit)) - 1;
+        if shift {
+            target = shr1(target);
+        }
+        target = div_u64(mul_u64(target, timespan), target_timespan);
+        if shift {
+            target = shl1(target);
+        }
+        let target = Target::from_be_bytes(from_limbs(target));
+        target.min(limit).to_compact_lossy()
+    }
+
+    /// Hash compared against the target of an 80-byte header.
+    pub fn pow_hash(&self, header: &[u8; 80]) -> Result<BlockHash, Box<dyn Error>> {
+        match self.pow_hash {
+            PowHash::Sha256d => Ok(BlockHash::from_byte_array(double_sha256(header))),
+            PowHash::Scrypt => {
+                let params = scrypt::Params::new(10, 1, 1, 32)
+                    .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+                let mut hash = [0u8; 32];
+                scrypt::scrypt(header, header, &params, &mut hash)
+                    .map_err(|e| format!("scrypt failed: {}", e))?;
+                Ok(BlockHash::from_byte_array(hash))
+            }
+        }
+    }
+
+    /// scriptPubKey of `address` on this chain. Covers P2PKH, P2SH and every segwit version.
+    pub fn address_script_pubkey(&self, address: &str) -> Result<ScriptBuf, Box<dyn Error>> {
+        if let Some(network) = self.network {
+            return Ok(BitcoinAddress::from_str(address)?
+                .require_network(network)?
+                .script_pubkey());
+        }
+        let hrp = address.rsplit_once('1').map(|(hrp, _)| hrp);
+        if hrp.is_some_and(|hrp| hrp.eq_ignore_ascii_case(self.bech32_hrp)) {
+            let (_, version, program) = bech32::segwit::decode(address)
+                .map_err(|e| format!("Invalid {} address {}: {}", self.name, address, e))?;
+            let program = WitnessProgram::new(WitnessVersion::try_from(version)?, &program)?;
+            return Ok(ScriptBuf::new_witness_program(&program));
+        }
+        let payload = base58::decode_check(address)
+            .map_err(|_| format!("Address {} is not a {} address", address, self.name))?;
+        match payload.split_first() {
+            Some((&prefix, hash)) if prefix == self.p2pkh_prefix && hash.len() == 20 => {
+                Ok(ScriptBuf::new_p2pkh(&PubkeyHash::from_slice(hash)?))
+            }
+            Some((&prefix, hash)) if prefix == self.p2sh_prefix && hash.len() == 20 => {
+                Ok(ScriptBuf::new_p2sh(&ScriptHash::from_slice(hash)?))
+            }
+            _ => Err(format!("Address {} is not a {} address", address, self.name).into()),
+        }
+    }
+
+    /// Address of a standard output script on this chain.
+    pub fn address_from_script(&self, script: &Script) -> Result<String, Box<dyn Error>> {
+        if let Some(network) = self.network {
+            return Ok(BitcoinAddress::from_script(script, network)?.to_string());
+        }
+        let bytes = script.as_bytes();
+        if let Some(version) = script.witness_version() {
+            let hrp = Hrp::parse(self.bech32_hrp)?;
+            return Ok(bech32::segwit::encode(hrp, version.into(), &bytes[2..])?);
+        }
+        let (prefix, hash) = if script.is_p2pkh() {
+            (self.p2pkh_prefix, &bytes[3..23])
+        } else if script.is_p2sh() {
+            (self.p2sh_prefix, &bytes[2..22])
+        } else {
+            return Err(format!("Script {} has no {} address", script, self.name).into());
+        };
+        let mut payload = vec![prefix];
+        payload.extend_from_slice(hash);
+        Ok(base58::encode_check(&payload))
+    }
+}
+
+impl From<Network> for ChainParams {
+    fn from(network: Network) -> Self {
+        ChainParams::bitcoin(network)
+    }
+}
+
+impl FromStr for ChainParams {
+    type Err = Box<dyn Error>;
+
+    /// Accepts the Bitcoin network names and `litecoin`, `litecoin-testnet` and
+    /// `litecoin-regtest`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            LITECOIN => Ok(ChainParams::litecoin()),
+            LITECOIN_TESTNET => Ok(ChainParams::litecoin_testnet()),
+            LITECOIN_REGTEST => Ok(ChainParams::litecoin_regtest()),
+            name => Ok(ChainParams::bitcoin(Network::from_str(name)?)),
+        }
+    }
+}
+
+impl fmt::Display for ChainParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Parses the chain a bundle's `network` field names, falling back to [`DEFAULT_NETWORK`].
+pub fn parse_chain(name: Option<&str>) -> Result<ChainParams, Box<dyn Error>> {
+    match name {
+        Some(name) => name.parse(),
+        None => Ok(ChainParams::bitcoin(DEFAULT_NETWORK)),
+    }
+}
+
+fn litecoin_pow(testnet: bool, regtest: bool, limit_bits: u32) -> Params {
+    let mut params = Params::new(Network::Bitcoin);
+    params.max_attainable_target = Target::from_compact(CompactTarget::from_consensus(limit_bits));
+    params.pow_target_spacing = 150;
+    params.pow_target_timespan = 302_400;
+    params.allow_min_difficulty_blocks = testnet;
+    params.no_pow_retargeting = regtest;
+    params
+}
+
+// 256-bit arithmetic for the Litecoin retarget, on big-endian 64-bit limbs
+
+fn limbs(target: Target) -> [u64; 4] {
+    let bytes = target.to_be_bytes();
+    std::array::from_fn(|i| u64::from_be_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+fn from_limbs(limbs: [u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    bytes
+}
+
+fn bit_length(limbs: &[u64; 4]) -> u32 {
+    limbs
+        .iter()
+        .position(|&limb| limb != 0)
+        .map_or(0, |i| (4 - i as u32) * 64 - limbs[i].leading_zeros())
+}
+
+fn shr1(limbs: [u64; 4]) -> [u64; 4] {
+    std::array::from_fn(|i| (limbs[i] >> 1) | if i > 0 { limbs[i - 1] << 63 } else { 0 })
+}
+
+fn shl1(limbs: [u64; 4]) -> [u64; 4] {
+    std::array::from_fn(|i| (limbs[i] << 1) | if i < 3 { limbs[i + 1] >> 63 } else { 0 })
+}
+
+/// Product modulo 2^256, as Litecoin Core's `arith_uint256` computes it.
+fn mul_u64(limbs: [u64; 4], factor: u64) -> [u64; 4] {
+    let mut out = [0u64; 4];
+    let mut carry = 0u128;
+    for (out, limb) in out.iter_mut().zip(limbs).rev() {
+        let product = limb as u128 * factor as u128 + carry;
+        *out = product as u64;
+        carry = product >> 64;
+    }
+    out
+}
+
+fn div_u64(limbs: [u64; 4], divisor: u64) -> [u64; 4] {
+    let mut out = [0u64; 4];
+    let mut remainder = 0u128;
+    for (out, limb) in out.iter_mut().zip(limbs) {
+        let dividend = (remainder << 64) | limb as u128;
+        *out = (dividend / divisor as u128) as u64;
+        remainder = dividend % divisor as u128;
+    }
+    out
+}
@@ -1,22 +1,307 @@
+pub mod aggregation;
+pub mod chain;
+pub mod verify;
+
+use alloy_primitives::{keccak256, B256};
 use alloy_sol_types::sol;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::error::Error;
 sol! {
     /// The public values encoded as a struct that can be easily deserialized inside Solidity.
+    /// Committed with `abi_encode_params`, i.e. `abi.encode(tx_id, depositer_address, ...)`,
+    /// so contracts can decode a prefix of the fields even though the struct is dynamic.
     struct ZkpMintPublicValuesStruct {
         bytes32 tx_id;  // Record txid prevent re-entry.
         address depositer_address; // Address to send money.
-        uint256 amount; // Amount to mint.
+        uint256 amount; // Amount to mint, net of bridge fees.
         bool is_valid;
+        uint256 min_difficulty; // Lowest difficulty among the confirmation headers.
+        uint256 chain_work; // Cumulative work of the confirmation headers.
+        uint8 reject_reason; // MintRejectReason code, 0 when the deposit is valid.
+        uint256 min_deposit_sats; // Policy minimum the deposit was checked against.
+        uint256 gross_amount; // Amount paid to the bridge before fees.
+        uint256 fee_bps; // Policy fee in basis points.
+        uint256 flat_fee_sats; // Policy flat fee in satoshis.
+        bytes32 checkpoint_hash; // Block the confirmation chain attaches to.
+        string refund_btc_address; // Address funding the first input; empty if not proven.
+        bool witness_verified; // Whether the wtxid was checked against the witness commitment.
+        uint256 dest_chain_id; // Destination chain id from a v2 memo; 0 for v1 memos.
+        uint256 memo_nonce; // Nonce from a v2 memo; 0 when absent.
+        uint256 vault_output_count; // Non-dust outputs paying the bridge.
+        uint256 tip_timestamp; // Timestamp of the last confirmation header.
+        uint256 reference_time; // Reference time the header timestamps were checked against.
+        uint256 request_id; // Swap request id from an ABI-encoded memo; 0 when absent.
+        uint32[] vault_output_indices; // Indices of the outputs counted in vault_output_count.
+        bytes4 network_magic; // P2P magic of the Bitcoin network the deposit was proven on.
+        bytes32 vault_script_hash; // keccak256 of the vault script of the bundle's group key.
+    }
+}
+sol! {
+    /// One deposit entry committed by the batch mint circuit.
+    struct MintDepositStruct {
+        bytes32 tx_id;
+        address depositer_address;
+        uint256 amount; // Net of bridge fees.
+        bool is_valid;
+        uint8 reject_reason;
+        uint256 gross_amount;
+        uint256 dest_chain_id;
+        uint256 memo_nonce;
+        uint256 vault_output_count;
+        uint256 request_id;
+        uint32[] vault_output_indices;
+    }
+
+    /// The public values of the batch mint circuit: every deposit shares one confirmation chain.
+    struct ZkpMintBatchPublicValuesStruct {
+        MintDepositStruct[] deposits;
+        uint256 min_difficulty;
+        uint256 chain_work;
+        uint256 min_deposit_sats;
+        uint256 fee_bps;
+        uint256 flat_fee_sats;
+        bytes32 checkpoint_hash;
+        uint256 tip_timestamp;
+        uint256 reference_time;
+        bytes4 network_magic; // P2P magic of the Bitcoin network the deposits were proven on.
+        bytes32 vault_script_hash; // keccak256 of the vault script of the bundle's group key.
+    }
+
+    /// The public values of the mint aggregation circuit.
+    struct ZkpMintAggregationPublicValuesStruct {
+        bytes32 mint_vkey_hash; // Key every aggregated proof was checked against, in `bytes32` form.
+        bytes32 public_values_root; // Merkle root over keccak256 of each proof's public values.
+        uint256 proof_count;
+    }
+
+    /// The public values of the burn aggregation circuit.
+    struct ZkpBurnAggregationPublicValuesStruct {
+        bytes32 burn_vkey_hash; // Key every aggregated proof was checked against, in `bytes32` form.
+        bytes32 public_values_root; // Merkle root over keccak256 of each proof's public values.
+        uint256 proof_count;
     }
 }
 sol! {
     /// The public values encoded as a struct that can be easily deserialized inside Solidity.
+    /// Committed with `abi_encode_params`, like the mint public values.
     struct ZkpBurnPublicValuesStruct {
         string burner_btc_address; // Address to send money.
         uint256 amount; // Amount to mint.
         bool is_valid;
+        bytes32 payout_tx_id; // Bitcoin payout txid, recorded on-chain to prevent replay.
+        bytes vault_script_pubkey; // Vault script spent by at least one payout input.
+        uint256 burn_id; // Burn request on the contract this payout fulfills.
+        uint8 reject_reason; // BurnRejectReason code, 0 when the payout is valid.
+        uint256 expected_payout_sats; // Expected burn amount net of declared fees.
+        uint256 payout_tolerance_sats; // Shortfall tolerated before flagging underpayment.
+        bool underpaid; // Payout fell below expected_payout_sats - payout_tolerance_sats.
+        uint256 min_difficulty; // Lowest difficulty among the confirmation headers.
+        uint256 chain_work; // Cumulative work of the confirmation headers.
+        uint256 confirmations; // Number of confirmation headers verified.
+        bytes32 vault_public_key; // x-only group key that signed the vault input.
+        uint256 vault_change_sats; // Satoshis the payout returns to the vault script.
+        bytes4 network_magic; // P2P magic of the Bitcoin network the payout was proven on.
+        uint256 payout_block_height; // Height of the payout's block, 0 when not proven.
+        bool rbf_signaled; // Some payout input opted in to replacement (BIP-125).
+        uint256 fee_sats; // Miner fee paid by the payout.
+        uint256 max_fee_sats; // Fee cap of the burn policy, 0 when uncapped.
+        bytes32 checkpoint_hash; // Parent of the first confirmation header.
+        bool contested; // A valid competing chain replaces the payout's block.
+        bool tagged; // The payout's OP_RETURN tag names burn_id.
+    }
+
+    /// One burn request paid by a batched payout transaction.
+    struct BurnPayoutStruct {
+        string burner_btc_address;
+        uint256 amount; // Satoshis paid to burner_btc_address.
+        uint256 burn_id;
+        bool is_valid;
+        uint8 reject_reason;
+        uint256 expected_payout_sats;
+        bool underpaid;
+    }
+
+    /// The public values of the batch burn circuit: one payout transaction settles every entry.
+    struct ZkpBurnBatchPublicValuesStruct {
+        bytes32 payout_tx_id;
+        bytes vault_script_pubkey;
+        BurnPayoutStruct[] payouts;
+        uint256 vault_change_sats; // Satoshis the payout returns to the vault script.
+        bytes4 network_magic; // P2P magic of the Bitcoin network the payout was proven on.
+    }
+}
+
+/// Reason codes committed by the mint circuit alongside `is_valid = false`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MintRejectReason {
+    /// The deposit was accepted.
+    None = 0,
+    /// The transaction carries no OP_RETURN memo.
+    MissingMemo = 1,
+    /// The OP_RETURN memo is not a recognised recipient encoding.
+    InvalidMemo = 2,
+    /// The Merkle proof does not lead to the block's Merkle root.
+    MerkleRootMismatch = 3,
+    /// The Merkle proof could not be parsed.
+    InvalidMerkleProof = 4,
+    /// The confirmation chain failed validation.
+    InvalidChain = 5,
+    /// The transaction already appears earlier in the same batch.
+    DuplicateDeposit = 6,
+    /// The value paid to the bridge is below the policy minimum.
+    BelowMinimumDeposit = 7,
+    /// The bridge fees consume the whole deposit.
+    FeeExceedsDeposit = 8,
+    /// The deposit's witness data is not covered by the coinbase witness commitment.
+    WitnessCommitmentMismatch = 9,
+    /// The supplied per-request deposit script is not derived from the group key and request id.
+    DepositScriptMismatch = 10,
+    /// The transaction's lock time was not satisfied in the block that includes it.
+    NonFinalTransaction = 11,
+    /// The bundle names no vault public key, or one that is not a hex x-only key.
+    InvalidVaultKey = 12,
+    /// The deposit transaction could not be decoded; the committed txid is zero.
+    InvalidTransaction = 13,
+    /// The bundle names an unknown source chain; the committed network magic is zero.
+    UnknownChain = 14,
+    /// The deposit's outputs could not be read.
+    InvalidDeposit = 15,
+}
+
+impl MintRejectReason {
+    /// The reason a committed code stands for; `None` for an unknown code.
+    pub fn from_code(code: u8) -> Option<Self> {
+        [
+            MintRejectReason::None,
+            MintRejectReason::MissingMemo,
+            MintRejectReason::InvalidMemo,
+            MintRejectReason::MerkleRootMismatch,
+            MintRejectReason::InvalidMerkleProof,
+            MintRejectReason::InvalidChain,
+            MintRejectReason::DuplicateDeposit,
+            MintRejectReason::BelowMinimumDeposit,
+            MintRejectReason::FeeExceedsDeposit,
+            MintRejectReason::WitnessCommitmentMismatch,
+            MintRejectReason::DepositScriptMismatch,
+            MintRejectReason::NonFinalTransaction,
+            MintRejectReason::InvalidVaultKey,
+            MintRejectReason::InvalidTransaction,
+            MintRejectReason::UnknownChain,
+            MintRejectReason::InvalidDeposit,
+        ]
+        .into_iter()
+        .find(|reason| *reason as u8 == code)
+    }
+
+    /// Whether a deposit rejected for this reason is owed a refund: it paid the bridge, but its
+    /// memo or amount cannot be minted. The other reasons are about the proof, not the deposit.
+    pub fn is_refundable(self) -> bool {
+        matches!(
+            self,
+            MintRejectReason::MissingMemo
+                | MintRejectReason::InvalidMemo
+                | MintRejectReason::BelowMinimumDeposit
+                | MintRejectReason::FeeExceedsDeposit
+        )
+    }
+}
+
+/// Reason codes committed by the burn circuit alongside `is_valid = false`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BurnRejectReason {
+    /// The payout was accepted.
+    None = 0,
+    /// The Merkle proof does not lead to the block's Merkle root.
+    MerkleRootMismatch = 1,
+    /// The Merkle proof could not be parsed.
+    InvalidMerkleProof = 2,
+    /// The confirmation chain failed validation.
+    InvalidChain = 3,
+    /// No input is proven to spend from the vault script.
+    NotFromVault = 4,
+    /// The burner address already appears earlier in the same batch.
+    DuplicateRecipient = 5,
+    /// The burner address is not a valid address on the bridge network.
+    InvalidRecipient = 6,
+    /// The vault input is not a key-path spend signed by the vault group key.
+    InvalidVaultSignature = 7,
+    /// An output pays neither a burner address, the vault change script nor an OP_RETURN.
+    UnexpectedOutput = 8,
+    /// The coinbase proof does not prove the claimed block height.
+    InvalidBlockHeight = 9,
+    /// The payout's miner fee exceeds the policy's fee cap.
+    FeeTooHigh = 10,
+    /// The payout's OP_RETURN burn tag is malformed or names another burn.
+    BurnTagMismatch = 11,
+    /// The payout transaction could not be decoded; the committed txid is zero.
+    InvalidTransaction = 12,
+    /// The bundle names no burner address or no burn request id.
+    MissingBurnRequest = 13,
+    /// The bundle names an unknown source chain; the committed network magic is zero.
+    UnknownChain = 14,
+    /// The bundle names no vault public key, or one that is not a hex x-only key.
+    InvalidVaultKey = 15,
+}
+
+/// Bridge policy applied by the mint circuits and committed with the public values.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MintPolicyStruct {
+    /// Deposits paying less than this to the vault are rejected.
+    pub min_deposit_sats: u64,
+    /// Proportional bridge fee in basis points of the gross deposit.
+    pub fee_bps: u64,
+    /// Flat bridge fee in satoshis, charged on top of the proportional fee.
+    pub flat_fee_sats: u64,
+}
+
+impl MintPolicyStruct {
+    /// Applies the fee schedule to a gross deposit.
+    /// Returns `None` when the fees leave nothing to mint.
+    pub fn net_amount(&self, gross_sats: u64) -> Option<u64> {
+        let proportional_fee = (gross_sats as u128 * self.fee_bps as u128) / 10_000;
+        let total_fee = proportional_fee.checked_add(self.flat_fee_sats as u128)?;
+        let net = (gross_sats as u128).checked_sub(total_fee)?;
+        if net == 0 {
+            return None;
+        }
+        Some(net as u64)
+    }
+}
+
+/// Payout owed to the redeemer, checked by the burn circuit and committed with the public values.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BurnPayoutPolicyStruct {
+    /// Burn amount in satoshis before fees.
+    pub expected_amount_sats: u64,
+    /// Fees declared by the burn request, deducted from the expected amount.
+    pub declared_fee_sats: u64,
+    /// Shortfall in satoshis tolerated before the payout counts as underpaid.
+    pub tolerance_sats: u64,
+    /// Largest miner fee in satoshis the payout may pay; 0 disables the cap.
+    #[serde(default)]
+    pub max_fee_sats: u64,
+}
+
+impl BurnPayoutPolicyStruct {
+    /// Satoshis the burner address should receive: the expected amount minus declared fees.
+    pub fn expected_payout(&self) -> u64 {
+        self.expected_amount_sats
+            .saturating_sub(self.declared_fee_sats)
+    }
+
+    /// Whether `paid_sats` falls short of the expected payout by more than the tolerance.
+    pub fn is_underpaid(&self, paid_sats: u64) -> bool {
+        paid_sats.saturating_add(self.tolerance_sats) < self.expected_payout()
+    }
+
+    /// Whether `fee_sats` exceeds the fee cap, if one is set.
+    pub fn exceeds_fee_cap(&self, fee_sats: u64) -> bool {
+        self.max_fee_sats != 0 && fee_sats > self.max_fee_sats
     }
 }
 
@@ -45,6 +330,17 @@ pub struct MerkleProof {
     pub pos: u32,
 }
 
+/// Auxiliary data for validating a difficulty retarget inside the confirmation window.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetargetInfo {
+    /// Height of the first block in the confirmation chain.
+    pub start_height: u32,
+    /// Timestamp of the first block of the epoch preceding the retarget boundary; on Litecoin,
+    /// of the block before it (`ChainParams::epoch_start_height`).
+    pub epoch_start_timestamp: u32,
+}
+
 // Data that retrieved by Bitcoin trx fetch module
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -68,6 +364,222 @@ pub struct BundleInfoStruct {
     pub chains: Chain,
     pub bit_tx_info: BitcoinTrxInfoStruct,
     pub burner_btc_address: Option<String>,
+    /// Ethereum burn request id the payout fulfills.
+    #[serde(default)]
+    pub burn_id: Option<u64>,
+    #[serde(default)]
+    pub burn_policy: BurnPayoutPolicyStruct,
+    /// Bitcoin network name ("bitcoin", "testnet", "testnet4", "signet" or "regtest");
+    /// the circuits default to testnet.
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Headers the burn circuit requires in `chains`; defaults to six, and lower values are
+    /// raised to six.
+    #[serde(default)]
+    pub confirmations: Option<u32>,
+    /// Competing header chain from the same parent, used by the burn circuit to flag a fork.
+    #[serde(default)]
+    pub competing_chain: Option<Chain>,
+    #[serde(default)]
+    pub retarget_info: Option<RetargetInfo>,
+    #[serde(default)]
+    pub mint_policy: MintPolicyStruct,
+    /// Trusted block hash the first block of `chains` must build on.
+    #[serde(default)]
+    pub checkpoint_hash: Option<String>,
+    /// Unix time no header may be more than two hours ahead of.
+    #[serde(default)]
+    pub reference_time: Option<u32>,
+    /// Number of transactions in the first block of `chains`, used to bound Merkle proofs.
+    #[serde(default)]
+    pub tx_count: Option<u32>,
+    /// Height of the first block of `chains`, used to check height lock times.
+    #[serde(default)]
+    pub block_height: Option<u32>,
+    /// Transaction spent by the deposit's first input, used to prove the refund address.
+    #[serde(default)]
+    pub prevout_tx_info: Option<BitcoinTrxInfoStruct>,
+    /// Transactions spent by the payout's inputs, used to prove it spends from the vault.
+    #[serde(default)]
+    pub spent_prevouts: Option<Vec<BitcoinTrxInfoStruct>>,
+    /// x-only vault group key (hex), required by both circuits: deposits pay its vault script or
+    /// a per-request script derived from it, and the payout's vault input must be signed by it. Its vault script
+    /// is committed for the contract to check against its own.
+    #[serde(default)]
+    pub vault_public_key: Option<String>,
+    #[serde(default)]
+    pub witness_proof: Option<WitnessProofStruct>,
+    /// Coinbase proof of `block_height`, committed by the burn circuit for deadline checks.
+    #[serde(default)]
+    pub coinbase_proof: Option<CoinbaseProofStruct>,
+    /// Per-request deposit address the transaction pays instead of the vault.
+    #[serde(default)]
+    pub deposit_request: Option<DepositRequestStruct>,
+}
+
+/// A swap request whose deposit address is the group key tweaked with its request id.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositRequestStruct {
+    pub request_id: u64,
+    /// Expected deposit scriptPubKey (hex); checked against the derivation in the circuit.
+    pub script_pubkey: String,
+}
+
+/// Data needed to check a deposit's wtxid against the block's witness commitment.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WitnessProofStruct {
+    /// Coinbase transaction of the deposit's block, including its witness.
+    pub coinbase_tx_info: BitcoinTrxInfoStruct,
+    /// Merkle proof of the coinbase txid (position 0) under the block's Merkle root.
+    pub coinbase_merkle_proof: MerkleProof,
+    /// Merkle proof of the deposit's wtxid in the block's witness tree.
+    pub wtxid_merkle_proof: MerkleProof,
+}
+
+/// Coinbase of the payout's block, whose BIP-34 height push proves `block_height`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinbaseProofStruct {
+    /// Coinbase transaction of the payout's block.
+    pub coinbase_tx_info: BitcoinTrxInfoStruct,
+    /// Merkle proof of the coinbase txid (position 0) under the block's Merkle root.
+    pub coinbase_merkle_proof: MerkleProof,
+}
+
+/// A deposit transaction and its Merkle proof, as included in a batch bundle.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositProofStruct {
+    pub merkle_proof: MerkleProof,
+    pub bit_tx_info: BitcoinTrxInfoStruct,
+}
+
+/// Several deposits included in the first block of one shared confirmation chain.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MintBatchBundleStruct {
+    pub deposits: Vec<DepositProofStruct>,
+    pub chains: Chain,
+    #[serde(default)]
+    pub retarget_info: Option<RetargetInfo>,
+    #[serde(default)]
+    pub mint_policy: MintPolicyStruct,
+    /// Trusted block hash the first block of `chains` must build on.
+    #[serde(default)]
+    pub checkpoint_hash: Option<String>,
+    /// Unix time no header may be more than two hours ahead of.
+    #[serde(default)]
+    pub reference_time: Option<u32>,
+    /// Number of transactions in the first block of `chains`, used to bound Merkle proofs.
+    #[serde(default)]
+    pub tx_count: Option<u32>,
+    /// Height of the first block of `chains`, used to check height lock times.
+    #[serde(default)]
+    pub block_height: Option<u32>,
+    /// Bitcoin network name, as in [`BundleInfoStruct::network`].
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Vault group key every deposit pays, as in [`BundleInfoStruct::vault_public_key`].
+    #[serde(default)]
+    pub vault_public_key: Option<String>,
+}
+
+impl From<BundleInfoStruct> for MintBatchBundleStruct {
+    /// Wraps a single-deposit bundle as a batch of one.
+    fn from(bundle: BundleInfoStruct) -> Self {
+        MintBatchBundleStruct {
+            deposits: vec![DepositProofStruct {
+                merkle_proof: bundle.merkle_proof,
+                bit_tx_info: bundle.bit_tx_info,
+            }],
+            chains: bundle.chains,
+            retarget_info: bundle.retarget_info,
+            mint_policy: bundle.mint_policy,
+            checkpoint_hash: bundle.checkpoint_hash,
+            reference_time: bundle.reference_time,
+            tx_count: bundle.tx_count,
+            block_height: bundle.block_height,
+            network: bundle.network,
+            vault_public_key: bundle.vault_public_key,
+        }
+    }
+}
+
+/// A burn request paid by a batched payout transaction.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BurnPayoutRequestStruct {
+    pub burner_btc_address: String,
+    /// Ethereum burn request id the payout fulfills.
+    pub burn_id: u64,
+    #[serde(default)]
+    pub burn_policy: BurnPayoutPolicyStruct,
+}
+
+/// One payout transaction settling several burn requests at once.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BurnBatchBundleStruct {
+    pub payouts: Vec<BurnPayoutRequestStruct>,
+    pub merkle_proof: MerkleProof,
+    pub chains: Chain,
+    pub bit_tx_info: BitcoinTrxInfoStruct,
+    /// Transactions spent by the payout's inputs, used to prove it spends from the vault.
+    #[serde(default)]
+    pub spent_prevouts: Option<Vec<BitcoinTrxInfoStruct>>,
+    /// Vault group key, as in [`BundleInfoStruct::vault_public_key`].
+    #[serde(default)]
+    pub vault_public_key: Option<String>,
+    /// Bitcoin network name, as in [`BundleInfoStruct::network`].
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub retarget_info: Option<RetargetInfo>,
+    /// Trusted block hash the first block of `chains` must build on.
+    #[serde(default)]
+    pub checkpoint_hash: Option<String>,
+    /// Unix time no header may be more than two hours ahead of.
+    #[serde(default)]
+    pub reference_time: Option<u32>,
+    /// Number of transactions in the first block of `chains`, used to bound Merkle proofs.
+    #[serde(default)]
+    pub tx_count: Option<u32>,
+}
+
+impl From<BundleInfoStruct> for BurnBatchBundleStruct {
+    /// Wraps a single-payout bundle as a batch of one.
+    fn from(bundle: BundleInfoStruct) -> Self {
+        BurnBatchBundleStruct {
+            payouts: vec![BurnPayoutRequestStruct {
+                burner_btc_address: bundle.burner_btc_address.unwrap_or_default(),
+                burn_id: bundle.burn_id.unwrap_or_default(),
+                burn_policy: bundle.burn_policy,
+            }],
+            merkle_proof: bundle.merkle_proof,
+            chains: bundle.chains,
+            bit_tx_info: bundle.bit_tx_info,
+            spent_prevouts: bundle.spent_prevouts,
+            vault_public_key: bundle.vault_public_key,
+            network: bundle.network,
+            retarget_info: bundle.retarget_info,
+            checkpoint_hash: bundle.checkpoint_hash,
+            reference_time: bundle.reference_time,
+            tx_count: bundle.tx_count,
+        }
+    }
+}
+
+/// Input of the mint and burn aggregation circuits. The compressed proofs themselves are
+/// passed separately through `SP1Stdin::write_proof`, in the same order as `public_values`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationInputStruct {
+    /// Verification key hash of the aggregated circuit, as returned by `HashableKey::hash_u32`.
+    pub vkey: [u32; 8],
+    /// ABI-encoded public values of each proof.
+    pub public_values: Vec<Vec<u8>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +589,83 @@ pub struct ZkpProofFixture {
     pub vkey: String,
     pub public_value: String,
     pub proof: String,
+    /// Operator signature over [`ZkpProofFixture::attestation_digest`], present when the
+    /// prover was given an operator key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<FixtureAttestation>,
+    /// TSS group signature over [`ZkpProofFixture::tss_attestation_digest`], present once the
+    /// signer set co-signed the fixture for its submission.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tss_attestation: Option<TssAttestation>,
+}
+
+/// Signature of the prover operator vouching that a fixture came from an authorized prover.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureAttestation {
+    /// Ethereum address of the operator key.
+    pub signer: String,
+    /// 65-byte `r || s || v` EIP-191 signature of the attestation digest, 0x-prefixed hex.
+    pub signature: String,
+}
+
+/// Tag of the BIP-340 tagged hash the TSS signer set co-signs a fixture under. No Bitcoin
+/// sighash is computed under it, so an attestation can never authorize a vault spend.
+pub const TSS_ATTESTATION_TAG: &str = "ZKBTC/attestation";
+
+/// Signature of the TSS signer set vouching for a fixture's statement, independently of its
+/// proof.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TssAttestation {
+    /// x-only group key, hex; the signature is made by its key-path-only Taproot output key,
+    /// the key of the vault.
+    pub group_key: String,
+    /// 64-byte BIP-340 signature of the TSS attestation digest, hex.
+    pub signature: String,
+}
+
+/// Digest the TSS signer set signs for a statement: the BIP-340 tagged hash, under
+/// [`TSS_ATTESTATION_TAG`], of `vkey || keccak256(publicValues)`.
+pub fn tss_attestation_digest(vkey: &[u8; 32], public_values_hash: &[u8; 32]) -> [u8; 32] {
+    let tag = Sha256::digest(TSS_ATTESTATION_TAG.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag);
+    hasher.update(tag);
+    hasher.update(vkey);
+    hasher.update(public_values_hash);
+    hasher.finalize().into()
+}
+
+impl ZkpProofFixture {
+    /// Digest the operator signs: `keccak256(abi.encode(vkey, keccak256(publicValues),
+    /// keccak256(proof)))`, so a signature covers exactly one proof of one program.
+    pub fn attestation_digest(&self) -> Result<B256, Box<dyn Error>> {
+        let field = |value: &str| hex::decode(value.trim_start_matches("0x"));
+        let vkey = B256::try_from(field(&self.vkey)?.as_slice())
+            .map_err(|_| "Verification key must be 32 bytes")?;
+        let mut encoded = Vec::with_capacity(96);
+        encoded.extend_from_slice(vkey.as_slice());
+        encoded.extend_from_slice(keccak256(field(&self.public_value)?).as_slice());
+        encoded.extend_from_slice(keccak256(field(&self.proof)?).as_slice());
+        Ok(keccak256(encoded))
+    }
+
+    /// The fixture's vkey and the keccak256 hash of its public values, the statement the TSS
+    /// signer set attests to.
+    pub fn tss_attestation_statement(&self) -> Result<([u8; 32], [u8; 32]), Box<dyn Error>> {
+        let field = |value: &str| hex::decode(value.trim_start_matches("0x"));
+        let vkey: [u8; 32] = field(&self.vkey)?
+            .try_into()
+            .map_err(|_| "Verification key must be 32 bytes")?;
+        Ok((vkey, keccak256(field(&self.public_value)?).0))
+    }
+
+    /// Digest the TSS signer set signs for this fixture, see [`tss_attestation_digest`].
+    pub fn tss_attestation_digest(&self) -> Result<[u8; 32], Box<dyn Error>> {
+        let (vkey, public_values_hash) = self.tss_attestation_statement()?;
+        Ok(tss_attestation_digest(&vkey, &public_values_hash))
+    }
 }
 
 /// Helper function to convert a hex string to a 32-byte array.
@@ -0,0 +1,1262 @@
+//! Bitcoin verification routines shared by the zkVM circuits.
+
+use crate::chain::{ChainParams, PowHash};
+use crate::{
+    double_sha256, hex_to_bytes, reverse_hash, Block, Chain, MerkleProof, MintPolicyStruct,
+    MintRejectReason, RetargetInfo,
+};
+use alloy_primitives::{keccak256, Address, B256};
+use bitcoin::absolute::LockTime;
+use bitcoin::block::Block as BitcoinBlock;
+use bitcoin::consensus::deserialize;
+use bitcoin::hash_types::{BlockHash, TxMerkleNode, Txid, WitnessMerkleNode};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::network::Network;
+use bitcoin::opcodes;
+use bitcoin::pow::{Target, Work};
+use bitcoin::script::{read_scriptint, Instruction, Script, ScriptBuf};
+use bitcoin::secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::taproot::{self, TapNodeHash};
+use bitcoin::Amount;
+use bitcoin::{Address as BitcoinAddress, CompactTarget};
+use bitcoin::{Transaction, TxOut};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::str::FromStr;
+
+/// Largest allowed step back in time between consecutive headers, and how far a header may
+/// lie ahead of the reference time (Bitcoin's two-hour future-block limit).
+pub const MAX_TIMESTAMP_DRIFT_SECS: u32 = 2 * 60 * 60;
+
+/// Upper bound on Merkle proof siblings. A 4M weight unit block holds well under 2^16
+/// transactions, so anything deeper cannot be a real inclusion proof.
+pub const MAX_MERKLE_PROOF_DEPTH: usize = 24;
+
+/// Headers a confirmation chain must contain, including the block with the transaction,
+/// unless a circuit is configured otherwise.
+pub const DEFAULT_CONFIRMATIONS: usize = 6;
+
+/// Bitcoin network a bundle is verified on when it does not name one.
+pub const DEFAULT_NETWORK: Network = Network::Testnet;
+
+/// Domain separator for per-request deposit address tweaks.
+pub const REQUEST_TWEAK_TAG: &[u8] = b"zkbtc/deposit-request";
+
+/// Prefix of the OP_RETURN tag linking a burn payout to its burn id.
+pub const BURN_TAG_PREFIX: &[u8] = b"zkbtc/burn";
+
+/// OP_RETURN OP_PUSHBYTES_36 followed by the BIP-141 commitment tag.
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Computes the Merkle root from a transaction ID and its Merkle proof.
+/// Returns the computed Merkle root as a TxMerkleNode.
+pub fn compute_merkle_root_with_crate(
+    tx_id_str: &str,
+    merkle_proof: &MerkleProof,
+) -> Result<TxMerkleNode, Box<dyn Error>> {
+    let txid = Txid::from_str(tx_id_str)?;
+    let mut current_hash_bytes: [u8; 32] = *txid.as_byte_array();
+    let mut pos = merkle_proof.pos;
+    for sibling_hex in &merkle_proof.siblings {
+        let sibling_node = TxMerkleNode::from_str(sibling_hex)?;
+        let sibling_bytes: [u8; 32] = *sibling_node.as_byte_array();
+
+        let (left, right) = if pos % 2 == 0 {
+            (current_hash_bytes, sibling_bytes)
+        } else {
+            (sibling_bytes, current_hash_bytes)
+        };
+
+        let mut concat = [0u8; 64];
+        concat[..32].copy_from_slice(&left);
+        concat[32..].copy_from_slice(&right);
+
+        current_hash_bytes = double_sha256(&concat);
+        pos >>= 1;
+    }
+
+    Ok(TxMerkleNode::from_byte_array(current_hash_bytes))
+}
+
+/// Builds the Merkle proof of the transaction at `pos` from the txids of its block, in block
+/// order. An odd node at the end of a level is paired with itself, as in Bitcoin.
+pub fn build_merkle_proof(txids: &[Txid], pos: u32) -> Result<MerkleProof, Box<dyn Error>> {
+    if pos as usize >= txids.len() {
+        return Err(format!(
+            "Position {} is outside a block of {} transactions",
+            pos,
+            txids.len()
+        )
+        .into());
+    }
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| *txid.as_byte_array()).collect();
+    let mut index = pos as usize;
+    let mut siblings = Vec::new();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level[level.len() - 1]);
+        }
+        siblings.push(TxMerkleNode::from_byte_array(level[index ^ 1]).to_string());
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut concat = [0u8; 64];
+                concat[..32].copy_from_slice(&pair[0]);
+                concat[32..].copy_from_slice(&pair[1]);
+                double_sha256(&concat)
+            })
+            .collect();
+        index /= 2;
+    }
+    Ok(MerkleProof { siblings, pos })
+}
+
+/// Checks that a Merkle proof is structurally plausible before hashing it: the depth is
+/// bounded, `pos` fits in the tree and, when the block's transaction count is known, the
+/// position is in range and the depth matches the tree height.
+pub fn check_merkle_proof_bounds(
+    merkle_proof: &MerkleProof,
+    tx_count: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let depth = merkle_proof.siblings.len();
+    if depth > MAX_MERKLE_PROOF_DEPTH {
+        return Err(format!(
+            "Merkle proof has {} siblings, more than the maximum of {}",
+            depth, MAX_MERKLE_PROOF_DEPTH
+        )
+        .into());
+    }
+    if (merkle_proof.pos as u64) >> depth != 0 {
+        return Err(format!(
+            "Merkle proof position {} does not fit in a tree of depth {}",
+            merkle_proof.pos, depth
+        )
+        .into());
+    }
+    if let Some(tx_count) = tx_count {
+        if merkle_proof.pos >= tx_count {
+            return Err(format!(
+                "Merkle proof position {} is outside a block of {} transactions",
+                merkle_proof.pos, tx_count
+            )
+            .into());
+        }
+        let expected_depth = (tx_count.max(1) - 1)
+            .checked_ilog2()
+            .map_or(0, |log| log as usize + 1);
+        if depth != expected_depth {
+            return Err(format!(
+                "Merkle proof has {} siblings, expected {} for a block of {} transactions",
+                depth, expected_depth, tx_count
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Verifies that a transaction is included in a block by comparing the computed Merkle root
+/// (from the transaction ID and Merkle proof) to the block's Merkle root.
+pub fn verify_tx_inclusion_str(
+    tx_id_str: &str,
+    merkle_proof: &MerkleProof,
+    target_root_str: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let computed_root: TxMerkleNode = compute_merkle_root_with_crate(tx_id_str, merkle_proof)?;
+    let target_root: TxMerkleNode = TxMerkleNode::from_str(target_root_str)?;
+    Ok(computed_root == target_root)
+}
+
+/// Verifies that the witness data of `tx` is committed to by the block.
+/// The coinbase is proven at position 0 under `merkle_root`, then the witness root rebuilt
+/// from `tx`'s wtxid is checked against the coinbase witness commitment (BIP-141).
+/// Returns `Ok(false)` on a mismatch and `Err` when the inputs are malformed.
+pub fn verify_witness_commitment(
+    tx: &Transaction,
+    tx_pos: u32,
+    wtxid_merkle_proof: &MerkleProof,
+    coinbase_tx: &Transaction,
+    coinbase_merkle_proof: &MerkleProof,
+    merkle_root: &str,
+) -> Result<bool, Box<dyn Error>> {
+    if !coinbase_tx.is_coinbase() || coinbase_merkle_proof.pos != 0 {
+        return Err("Witness proof does not supply the coinbase transaction".into());
+    }
+    if wtxid_merkle_proof.pos != tx_pos {
+        return Err(format!(
+            "Wtxid proof position {} does not match transaction position {}",
+            wtxid_merkle_proof.pos, tx_pos
+        )
+        .into());
+    }
+    if !verify_tx_inclusion_str(
+        coinbase_tx.compute_txid().to_string().as_str(),
+        coinbase_merkle_proof,
+        merkle_root,
+    )? {
+        return Ok(false);
+    }
+
+    let reserved_value = coinbase_tx.input[0]
+        .witness
+        .nth(0)
+        .filter(|value| value.len() == 32)
+        .ok_or("Coinbase has no witness reserved value")?;
+    // The last output matching the commitment pattern wins, as in BIP-141
+    let commitment = coinbase_tx
+        .output
+        .iter()
+        .rev()
+        .map(|output| output.script_pubkey.as_bytes())
+        .find(|script| script.len() >= 38 && script[..6] == WITNESS_COMMITMENT_HEADER)
+        .map(|script| &script[6..38])
+        .ok_or("Coinbase has no witness commitment output")?;
+
+    let witness_root = compute_merkle_root_with_crate(
+        tx.compute_wtxid().to_string().as_str(),
+        wtxid_merkle_proof,
+    )?;
+    let expected = BitcoinBlock::compute_witness_commitment(
+        &WitnessMerkleNode::from_byte_array(witness_root.to_byte_array()),
+        reserved_value,
+    );
+    Ok(expected.as_byte_array()[..] == *commitment)
+}
+
+/// Proves the height of the block whose Merkle root is `merkle_root`.
+/// The coinbase is proven at position 0, then the height is read from the first push of its
+/// scriptSig, which BIP-34 requires for blocks of version 2 and later; heights 1 to 16 are
+/// pushed as OP_1 to OP_16.
+pub fn verify_coinbase_height(
+    coinbase_tx: &Transaction,
+    coinbase_merkle_proof: &MerkleProof,
+    merkle_root: &str,
+    block_version: u32,
+) -> Result<u32, Box<dyn Error>> {
+    if block_version < 2 {
+        return Err(format!("Block version {} predates BIP-34", block_version).into());
+    }
+    if !coinbase_tx.is_coinbase() || coinbase_merkle_proof.pos != 0 {
+        return Err("Coinbase proof does not supply the coinbase transaction".into());
+    }
+    if !verify_tx_inclusion_str(
+        coinbase_tx.compute_txid().to_string().as_str(),
+        coinbase_merkle_proof,
+        merkle_root,
+    )? {
+        return Err("Coinbase is not included under the block's Merkle root".into());
+    }
+
+    let height = match coinbase_tx.input[0]
+        .script_sig
+        .instructions_minimal()
+        .next()
+    {
+        Some(Ok(Instruction::PushBytes(push))) => read_scriptint(push.as_bytes())?,
+        // Minimal encoding pushes heights 1 to 16 with OP_1 to OP_16.
+        Some(Ok(Instruction::Op(op)))
+            if (opcodes::all::OP_PUSHNUM_1.to_u8()..=opcodes::all::OP_PUSHNUM_16.to_u8())
+                .contains(&op.to_u8()) =>
+        {
+            i64::from(op.to_u8() - opcodes::all::OP_PUSHNUM_1.to_u8() + 1)
+        }
+        _ => return Err("Coinbase scriptSig does not start with a height push".into()),
+    };
+    u32::try_from(height).map_err(|_| format!("Invalid coinbase height {}", height).into())
+}
+
+/// Parses the `network` a bundle names ("bitcoin", "testnet", "testnet4", "signet" or
+/// "regtest"), falling back to [`DEFAULT_NETWORK`].
+pub fn parse_network(name: Option<&str>) -> Result<Network, Box<dyn Error>> {
+    match name {
+        Some(name) => Ok(Network::from_str(name)?),
+        None => Ok(DEFAULT_NETWORK),
+    }
+}
+
+/// Derives the scriptPubKey of a vault or burner address once per proof, so outputs can be
+/// matched by byte comparison instead of decoding every output script into an address.
+/// Covers every standard output type, including bech32m P2TR addresses.
+pub fn address_script_pubkey(address: &str, network: Network) -> Result<ScriptBuf, Box<dyn Error>> {
+    Ok(BitcoinAddress::from_str(address)?
+        .require_network(network)?
+        .script_pubkey())
+}
+
+/// Script tree root a swap request's deposit key is tweaked with:
+/// `sha256(REQUEST_TWEAK_TAG || request_id BE)`. The group signs with it to spend the deposit.
+pub fn request_tweak(request_id: u64) -> TapNodeHash {
+    let mut preimage = REQUEST_TWEAK_TAG.to_vec();
+    preimage.extend_from_slice(&request_id.to_be_bytes());
+    TapNodeHash::from_byte_array(Sha256::digest(&preimage).into())
+}
+
+/// Derives the taproot deposit script of a swap request: the group key tweaked with the
+/// request-specific script tree root of `request_tweak`.
+/// The group can spend it by signing with the same tweak.
+pub fn request_deposit_script_pubkey(group_key: &XOnlyPublicKey, request_id: u64) -> ScriptBuf {
+    ScriptBuf::new_p2tr(
+        &Secp256k1::verification_only(),
+        *group_key,
+        Some(request_tweak(request_id)),
+    )
+}
+
+/// Derives the vault's taproot script: the group key with a key-path-only (BIP-86) tweak.
+pub fn vault_script_pubkey(group_key: &XOnlyPublicKey) -> ScriptBuf {
+    ScriptBuf::new_p2tr(&Secp256k1::verification_only(), *group_key, None)
+}
+
+/// keccak256 of the vault script of `group_key`, the hash the contract keeps as
+/// `vaultScriptHash`.
+pub fn vault_script_hash(group_key: &XOnlyPublicKey) -> B256 {
+    keccak256(vault_script_pubkey(group_key).as_bytes())
+}
+
+/// Parses the vault group key a bundle names; the circuits take the vault from no other key.
+pub fn parse_vault_key(vault_public_key: Option<&str>) -> Result<XOnlyPublicKey, String> {
+    let key = vault_public_key.ok_or("The bundle names no vault public key")?;
+    XOnlyPublicKey::from_str(key).map_err(|e| format!("Invalid vault public key {}: {}", key, e))
+}
+
+/// Sums the value of every output of `tx` paying exactly `script_pubkey`.
+pub fn sum_outputs_to_script(tx: &Transaction, script_pubkey: &Script) -> u64 {
+    tx.output
+        .iter()
+        .filter(|output| output.script_pubkey.as_script() == script_pubkey)
+        .fold(0u64, |total, output| {
+            total.saturating_add(output.value.to_sat())
+        })
+}
+
+/// Returns the index of the first output of `tx` that neither pays one of `allowed_scripts`
+/// nor is an OP_RETURN, or `None` when the output set is closed.
+pub fn find_unexpected_output(tx: &Transaction, allowed_scripts: &[&Script]) -> Option<usize> {
+    tx.output.iter().position(|output| {
+        !output.script_pubkey.is_op_return()
+            && !allowed_scripts.contains(&output.script_pubkey.as_script())
+    })
+}
+
+/// What [`process_transaction_outputs`] finds in a deposit's outputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositOutputs {
+    /// Sats of the non-dust outputs paying the bridge script.
+    pub amount_sats: u64,
+    /// Indices of those outputs.
+    pub output_indices: Vec<u32>,
+    /// Data pushed by the first OP_RETURN output, if any.
+    pub memo: Option<Vec<u8>>,
+}
+
+/// Processes transaction outputs to:
+/// - Sum the value sent to a specific scriptPubKey, skipping dust outputs.
+/// - Collect the indices of the outputs that qualified.
+/// - Extract the first OP_RETURN memo (if present).
+pub fn process_transaction_outputs(
+    tx: &Transaction,
+    deposit_script_pubkey: &Script,
+) -> Result<DepositOutputs, Box<dyn Error>> {
+    let dust_threshold = deposit_script_pubkey.minimal_non_dust();
+    let mut total_value_to_me: u64 = 0;
+    let mut qualifying_outputs: Vec<u32> = Vec::new();
+    let mut op_return_data: Option<Vec<u8>> = None;
+
+    for (vout, output) in tx.output.iter().enumerate() {
+        // Extract OP_RETURN memo if present (only the first one)
+        if output.script_pubkey.is_op_return() {
+            if op_return_data.is_none() {
+                let mut instructions = output.script_pubkey.instructions();
+                if let Some(Ok(Instruction::Op(opcodes::all::OP_RETURN))) = instructions.next() {
+                    if let Some(Ok(Instruction::PushBytes(data))) = instructions.next() {
+                        op_return_data = Some(data.as_bytes().to_vec());
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Sum value sent to the monitored scriptPubKey, ignoring dust
+        if output.script_pubkey.as_script() == deposit_script_pubkey
+            && output.value >= dust_threshold
+        {
+            total_value_to_me = total_value_to_me.saturating_add(Amount::to_sat(output.value));
+            qualifying_outputs.push(vout as u32);
+        }
+    }
+
+    Ok(DepositOutputs {
+        amount_sats: total_value_to_me,
+        output_indices: qualifying_outputs,
+        memo: op_return_data,
+    })
+}
+
+/// Finds the burn id a payout is tagged with: an OP_RETURN pushing
+/// `BURN_TAG_PREFIX | burn_id (u64 BE)`. OP_RETURN outputs without the prefix are ignored;
+/// a tag of the wrong length or a second tag is an error.
+pub fn parse_burn_tag(tx: &Transaction) -> Result<Option<u64>, Box<dyn Error>> {
+    let mut burn_id = None;
+    for output in tx
+        .output
+        .iter()
+        .filter(|output| output.script_pubkey.is_op_return())
+    {
+        let mut instructions = output.script_pubkey.instructions();
+        instructions.next();
+        let Some(Ok(Instruction::PushBytes(data))) = instructions.next() else {
+            continue;
+        };
+        let Some(id_bytes) = data.as_bytes().strip_prefix(BURN_TAG_PREFIX) else {
+            continue;
+        };
+        let id_bytes: [u8; 8] = id_bytes
+            .try_into()
+            .map_err(|_| format!("Burn tag carries {} id bytes, expected 8", id_bytes.len()))?;
+        if burn_id.is_some() {
+            return Err("Payout carries more than one burn tag".into());
+        }
+        burn_id = Some(u64::from_be_bytes(id_bytes));
+    }
+    Ok(burn_id)
+}
+
+/// Leading byte of a v2 deposit memo.
+pub const MEMO_V2_VERSION: u8 = 0x02;
+
+/// A decoded deposit memo. v1 memos carry only the recipient and ABI memos no chain id; the
+/// missing fields are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositMemo {
+    /// Destination EVM chain id; 0 when the memo does not name one.
+    pub chain_id: u64,
+    pub recipient: Address,
+    /// Depositor-chosen nonce; 0 when absent.
+    pub nonce: u64,
+    /// Pre-registered swap request id from an ABI-encoded or v2 memo; 0 when absent.
+    pub request_id: u64,
+}
+
+/// Parses an OP_RETURN deposit memo.
+///
+/// v2 layout: `0x02 | chain_id (u64 BE) | recipient (20 bytes) | nonce (u64 BE, optional) |
+/// request_id (u64 BE, optional after the nonce)`.
+/// ABI layout: `abi.encode(address recipient, uint64 requestId)`, exactly two 32-byte words.
+/// Anything else is parsed as a v1 recipient-only memo by `parse_recipient_memo`.
+pub fn parse_deposit_memo(memo: &[u8]) -> Result<DepositMemo, Box<dyn Error>> {
+    if memo.len() == 64 {
+        let (address_word, request_id_word) = memo.split_at(32);
+        if address_word[..12].iter().any(|b| *b != 0)
+            || request_id_word[..24].iter().any(|b| *b != 0)
+        {
+            return Err("ABI memo words are not a valid (address, uint64) encoding".into());
+        }
+        return Ok(DepositMemo {
+            chain_id: 0,
+            recipient: Address::from_slice(&address_word[12..]),
+            nonce: 0,
+            request_id: u64::from_be_bytes(request_id_word[24..].try_into()?),
+        });
+    }
+
+    const V2_LEN: usize = 1 + 8 + 20;
+    if memo.first() == Some(&MEMO_V2_VERSION)
+        && [V2_LEN, V2_LEN + 8, V2_LEN + 16].contains(&memo.len())
+    {
+        // The optional trailing words, each 0 when the memo ends before it
+        let word = |start: usize| match memo.get(start..start + 8) {
+            Some(bytes) => bytes.try_into().map(u64::from_be_bytes),
+            None => Ok(0),
+        };
+        return Ok(DepositMemo {
+            chain_id: u64::from_be_bytes(memo[1..9].try_into()?),
+            recipient: Address::from_slice(&memo[9..V2_LEN]),
+            nonce: word(V2_LEN)?,
+            request_id: word(V2_LEN + 8)?,
+        });
+    }
+
+    Ok(DepositMemo {
+        chain_id: 0,
+        recipient: parse_recipient_memo(memo)?,
+        nonce: 0,
+        request_id: 0,
+    })
+}
+
+/// Encodes the v2 memo of a pre-registered swap request minted on `chain_id`, with a zero
+/// nonce, as read back by `parse_deposit_memo`. The ABI memo names no chain, so the contract
+/// refuses to mint it.
+pub fn encode_request_memo(recipient: Address, request_id: u64, chain_id: u64) -> Vec<u8> {
+    let mut memo = vec![MEMO_V2_VERSION];
+    memo.extend_from_slice(&chain_id.to_be_bytes());
+    memo.extend_from_slice(recipient.as_slice());
+    memo.extend_from_slice(&0u64.to_be_bytes());
+    memo.extend_from_slice(&request_id.to_be_bytes());
+    memo
+}
+
+/// Parses a v1 OP_RETURN memo into the Ethereum recipient address.
+///
+/// Accepted encodings:
+/// - exactly 20 raw address bytes,
+/// - a 0x-prefixed all-lowercase hex string,
+/// - a 0x-prefixed EIP-55 checksummed string.
+pub fn parse_recipient_memo(memo: &[u8]) -> Result<Address, Box<dyn Error>> {
+    if memo.len() == Address::len_bytes() {
+        return Ok(Address::from_slice(memo));
+    }
+
+    let memo_str = std::str::from_utf8(memo).map_err(|_| "Memo is not valid UTF-8")?;
+    let hex_part = memo_str
+        .strip_prefix("0x")
+        .ok_or("Memo address must be 0x-prefixed")?;
+    if hex_part.bytes().all(|b| !b.is_ascii_uppercase()) {
+        Ok(Address::from_str(memo_str)?)
+    } else {
+        Ok(Address::parse_checksummed(memo_str, None)?)
+    }
+}
+
+/// Aggregate proof-of-work figures collected while verifying a chain of blocks.
+#[derive(Clone, Copy)]
+pub struct ChainSummary {
+    /// Lowest difficulty among the verified headers.
+    pub min_difficulty: u128,
+    /// Sum of the work of every verified header.
+    pub total_work: Work,
+    /// Parent hash of the first header, i.e. the block the chain attaches to.
+    pub anchor_hash: BlockHash,
+    /// Timestamp of the last header.
+    pub tip_timestamp: u32,
+}
+
+/// Verifies the `bits` transition between two consecutive headers.
+///
+/// At a retarget boundary the new bits must match the value recomputed from the previous
+/// epoch's timespan. Elsewhere the bits must stay unchanged, unless the network allows
+/// minimum-difficulty blocks. Without `retarget_info` the block heights are unknown, so any
+/// change of bits is treated as an unvalidated retarget.
+pub fn verify_bits_transition(
+    prev_block: &Block,
+    current_bits: CompactTarget,
+    index: usize,
+    retarget_info: Option<&RetargetInfo>,
+    params: &ChainParams,
+) -> Result<(), Box<dyn Error>> {
+    let prev_bits = CompactTarget::from_consensus(prev_block.difficulty);
+
+    if let Some(info) = retarget_info {
+        let height = info.start_height as u64 + index as u64;
+        if height % params.pow.difficulty_adjustment_interval() == 0 {
+            let timespan =
+                (prev_block.timestamp as i64 - info.epoch_start_timestamp as i64).max(0) as u64;
+            let expected_bits = params.next_work_required(prev_bits, timespan);
+            if current_bits != expected_bits {
+                return Err(format!(
+                    "Chain validation failed at block index {}: Retarget bits {:#010x} do not match expected {:#010x}",
+                    index, current_bits.to_consensus(), expected_bits.to_consensus()
+                ).into());
+            }
+            return Ok(());
+        }
+    }
+
+    if current_bits != prev_bits && !params.pow.allow_min_difficulty_blocks {
+        return Err(format!(
+            "Chain validation failed at block index {}: Bits changed from {:#010x} to {:#010x} outside a validated retarget boundary",
+            index, prev_bits.to_consensus(), current_bits.to_consensus()
+        ).into());
+    }
+
+    Ok(())
+}
+
+/// Serializes a header into its 80-byte consensus encoding.
+fn header_bytes(block: &Block) -> Result<[u8; 80], Box<dyn Error>> {
+    let mut header = [0u8; 80];
+    header[0..4].copy_from_slice(&block.version.to_le_bytes());
+    header[4..36].copy_from_slice(&reverse_hash(hex_to_bytes(&block.parent_hash)?));
+    header[36..68].copy_from_slice(&reverse_hash(hex_to_bytes(&block.merkle_root)?));
+    header[68..72].copy_from_slice(&block.timestamp.to_le_bytes());
+    header[72..76].copy_from_slice(&block.difficulty.to_le_bytes());
+    header[76..80].copy_from_slice(&block.nonce.to_le_bytes());
+    Ok(header)
+}
+
+/// Verifies the integrity and linkage of a chain of exactly `confirmations` blocks.
+/// Checks block hash correctness, proof-of-work, difficulty transitions and parent linkage.
+/// When `checkpoint_hash` is given, the first block must build directly on it.
+/// When `reference_time` is given, no header may be more than `MAX_TIMESTAMP_DRIFT_SECS` ahead of it.
+/// Proof-of-work and difficulty follow the rules of `params`, e.g. scrypt on Litecoin.
+/// Returns the minimum difficulty and cumulative work observed across the chain.
+pub fn verify_chain_with_crate(
+    chain: &Chain,
+    confirmations: usize,
+    retarget_info: Option<&RetargetInfo>,
+    checkpoint_hash: Option<&str>,
+    reference_time: Option<u32>,
+    params: &ChainParams,
+) -> Result<ChainSummary, Box<dyn Error>> {
+    if confirmations == 0 {
+        return Err("Chain validation failed: At least one confirmation is required".into());
+    }
+    if chain.blocks.len() != confirmations {
+        return Err(format!(
+            "Chain validation failed: Expected exactly {} blocks, found {}",
+            confirmations,
+            chain.blocks.len()
+        )
+        .into());
+    }
+
+    // Check the chain attaches to the trusted checkpoint
+    let anchor_hash = BlockHash::from_str(&chain.blocks[0].parent_hash)?;
+    if let Some(checkpoint_str) = checkpoint_hash {
+        let checkpoint = BlockHash::from_str(checkpoint_str)?;
+        if anchor_hash != checkpoint {
+            return Err(format!(
+                "Chain validation failed: First block's parent {} does not match checkpoint {}",
+                anchor_hash, checkpoint
+            )
+            .into());
+        }
+    }
+
+    let mut computed_hashes: Vec<BlockHash> = Vec::with_capacity(confirmations);
+    let mut min_difficulty: u128 = u128::MAX;
+    let mut total_work = Work::from_be_bytes([0u8; 32]);
+
+    for (i, user_block) in chain.blocks.iter().enumerate() {
+        // Parse each hash once into a fixed byte array and hash the raw 80-byte header
+        // through `sha2`, which SP1 patches to its SHA-256 precompile
+        let header = header_bytes(user_block)?;
+        let expected_block_hash =
+            BlockHash::from_byte_array(reverse_hash(hex_to_bytes(&user_block.block_hash)?));
+        let prev_blockhash = BlockHash::from_byte_array(header[4..36].try_into()?);
+        let bits = CompactTarget::from_consensus(user_block.difficulty);
+
+        // Check block hash correctness
+        let computed_block_hash = BlockHash::from_byte_array(double_sha256(&header));
+        if computed_block_hash != expected_block_hash {
+            return Err(format!(
+                "Chain validation failed at block index {}: Computed hash {} does not match provided block_hash {}",
+                i, computed_block_hash, expected_block_hash
+            ).into());
+        }
+
+        // Check proof-of-work: the target from `bits` must not exceed the network limit,
+        // and the proof-of-work hash must be at or below that target
+        let target = Target::from_compact(bits);
+        if target > params.pow.max_attainable_target {
+            return Err(format!(
+                "Chain validation failed at block index {}: Target from bits {:#010x} is above the network proof-of-work limit",
+                i, user_block.difficulty
+            ).into());
+        }
+        let pow_hash = match params.pow_hash {
+            PowHash::Sha256d => computed_block_hash,
+            PowHash::Scrypt => params.pow_hash(&header)?,
+        };
+        if !target.is_met_by(pow_hash) {
+            return Err(format!(
+                "Chain validation failed at block index {}: Proof-of-work hash {} does not meet the target from bits {:#010x}",
+                i, pow_hash, user_block.difficulty
+            ).into());
+        }
+        min_difficulty = min_difficulty.min(target.difficulty(&params.pow));
+        total_work = total_work + target.to_work();
+
+        computed_hashes.push(computed_block_hash);
+
+        // Check the timestamp is not implausibly far in the future
+        if let Some(reference_time) = reference_time {
+            if user_block.timestamp > reference_time.saturating_add(MAX_TIMESTAMP_DRIFT_SECS) {
+                return Err(format!(
+                    "Chain validation failed at block index {}: Timestamp {} is too far ahead of reference time {}",
+                    i, user_block.timestamp, reference_time
+                ).into());
+            }
+        }
+
+        // Check parent linkage and difficulty transition (skip for the first block)
+        if i > 0 {
+            let prev_computed_hash = computed_hashes[i - 1];
+            if prev_blockhash != prev_computed_hash {
+                return Err(format!(
+                    "Chain validation failed at block index {}: Parent hash {} does not match previous block's computed hash {}",
+                    i, prev_blockhash, prev_computed_hash
+                ).into());
+            }
+            let prev_timestamp = chain.blocks[i - 1].timestamp;
+            if user_block
+                .timestamp
+                .saturating_add(MAX_TIMESTAMP_DRIFT_SECS)
+                < prev_timestamp
+            {
+                return Err(format!(
+                    "Chain validation failed at block index {}: Timestamp {} is too far behind previous block's timestamp {}",
+                    i, user_block.timestamp, prev_timestamp
+                ).into());
+            }
+            verify_bits_transition(&chain.blocks[i - 1], bits, i, retarget_info, params)?;
+        }
+    }
+
+    Ok(ChainSummary {
+        min_difficulty,
+        total_work,
+        anchor_hash,
+        tip_timestamp: chain.blocks[chain.blocks.len() - 1].timestamp,
+    })
+}
+
+/// Outcome of checking one deposit transaction against the bridge rules.
+pub struct DepositOutcome {
+    pub txid: Txid,
+    pub recipient: Address,
+    /// Destination chain id from a v2 memo; 0 otherwise.
+    pub chain_id: u64,
+    /// Nonce from a v2 memo; 0 otherwise.
+    pub nonce: u64,
+    /// Swap request id from an ABI-encoded memo; 0 otherwise.
+    pub request_id: u64,
+    /// Value paid to the bridge address.
+    pub amount_sats: u64,
+    /// Value left to mint after the policy fees.
+    pub net_amount_sats: u64,
+    /// Indices of the non-dust outputs paying the bridge.
+    pub output_indices: Vec<u32>,
+    pub reject_reason: MintRejectReason,
+    /// Human-readable detail for a rejection; empty when the deposit is valid.
+    pub reject_detail: String,
+}
+
+impl DepositOutcome {
+    pub fn is_valid(&self) -> bool {
+        self.reject_reason == MintRejectReason::None
+    }
+
+    fn rejected(txid: Txid, reason: MintRejectReason, detail: String) -> Self {
+        DepositOutcome {
+            txid,
+            recipient: Address::ZERO,
+            chain_id: 0,
+            nonce: 0,
+            request_id: 0,
+            amount_sats: 0,
+            net_amount_sats: 0,
+            output_indices: Vec::new(),
+            reject_reason: reason,
+            reject_detail: detail,
+        }
+    }
+}
+
+/// Checks that `tx` was final when mined in a block at `block_height` with `block_time`.
+/// A lock time only applies when some input sequence enables it; height lock times need
+/// the block height to be known. Time lock times are compared with the block timestamp,
+/// which is never stricter than the median-time-past rule used by consensus.
+pub fn check_transaction_finality(
+    tx: &Transaction,
+    block_height: Option<u32>,
+    block_time: u32,
+) -> Result<(), Box<dyn Error>> {
+    if tx.lock_time == LockTime::ZERO || !tx.is_lock_time_enabled() {
+        return Ok(());
+    }
+    match tx.lock_time {
+        LockTime::Blocks(lock_height) => {
+            let block_height =
+                block_height.ok_or("Block height is required to check a height lock time")?;
+            if lock_height.to_consensus_u32() >= block_height {
+                return Err(format!(
+                    "Lock time height {} is not below block height {}",
+                    lock_height, block_height
+                )
+                .into());
+            }
+        }
+        LockTime::Seconds(lock_time) => {
+            if lock_time.to_consensus_u32() >= block_time {
+                return Err(format!(
+                    "Lock time {} is not below block time {}",
+                    lock_time, block_time
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes a raw transaction hex string.
+pub fn decode_transaction(raw_hex: &str) -> Result<Transaction, Box<dyn Error>> {
+    let tx_bytes =
+        Vec::<u8>::from_hex(raw_hex).map_err(|e| format!("Failed to decode raw hex: {}", e))?;
+    Ok(deserialize(&tx_bytes)?)
+}
+
+/// Resolves the address that funded the first input of `tx`, used as the refund address.
+/// `prev_tx` must be the transaction the first input spends; its txid is checked against
+/// the outpoint so the committed address is bound to the deposit.
+pub fn extract_refund_address(
+    tx: &Transaction,
+    prev_tx: &Transaction,
+    params: &ChainParams,
+) -> Result<String, Box<dyn Error>> {
+    let outpoint = tx
+        .input
+        .first()
+        .ok_or("Transaction has no inputs")?
+        .previous_output;
+    let prev_txid = prev_tx.compute_txid();
+    if prev_txid != outpoint.txid {
+        return Err(format!(
+            "Prevout transaction {} does not match first input outpoint {}",
+            prev_txid, outpoint.txid
+        )
+        .into());
+    }
+    let prev_output = prev_tx
+        .output
+        .get(outpoint.vout as usize)
+        .ok_or_else(|| format!("Prevout transaction has no output {}", outpoint.vout))?;
+    params.address_from_script(&prev_output.script_pubkey)
+}
+
+/// Finds the first input of `tx` that spends an output paying `vault_script_pubkey`.
+///
+/// Spent scripts are read from `prev_txs`, each matched to an input by txid, so they are
+/// bound to the transaction's outpoints. Inputs without a supplied prevout are skipped.
+pub fn find_vault_input(
+    tx: &Transaction,
+    prev_txs: &[Transaction],
+    vault_script_pubkey: &Script,
+) -> Result<Option<usize>, Box<dyn Error>> {
+    for (index, input) in tx.input.iter().enumerate() {
+        let outpoint = input.previous_output;
+        let Some(prev_tx) = prev_txs
+            .iter()
+            .find(|prev_tx| prev_tx.compute_txid() == outpoint.txid)
+        else {
+            continue;
+        };
+        let prev_output = prev_tx.output.get(outpoint.vout as usize).ok_or_else(|| {
+            format!(
+                "Prevout transaction {} has no output {}",
+                outpoint.txid, outpoint.vout
+            )
+        })?;
+        if prev_output.script_pubkey.as_script() == vault_script_pubkey {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the outputs spent by every input of `tx`, in input order, read from `prev_txs`.
+/// Fails if any input's prevout transaction was not provided.
+pub fn spent_outputs(
+    tx: &Transaction,
+    prev_txs: &[Transaction],
+) -> Result<Vec<TxOut>, Box<dyn Error>> {
+    tx.input
+        .iter()
+        .map(|input| {
+            let outpoint = input.previous_output;
+            prev_txs
+                .iter()
+                .find(|prev_tx| prev_tx.compute_txid() == outpoint.txid)
+                .and_then(|prev_tx| prev_tx.output.get(outpoint.vout as usize))
+                .cloned()
+                .ok_or_else(|| format!("Spent output {} was not provided", outpoint).into())
+        })
+        .collect()
+}
+
+/// Computes the miner fee of `tx`: the value of its spent outputs minus the value it pays out.
+pub fn compute_fee(tx: &Transaction, prev_txs: &[Transaction]) -> Result<u64, Box<dyn Error>> {
+    let input_value = spent_outputs(tx, prev_txs)?
+        .iter()
+        .try_fold(Amount::ZERO, |total, output| {
+            total.checked_add(output.value)
+        })
+        .ok_or("Spent output values overflow")?;
+    let output_value = tx
+        .output
+        .iter()
+        .try_fold(Amount::ZERO, |total, output| {
+            total.checked_add(output.value)
+        })
+        .ok_or("Output values overflow")?;
+    input_value
+        .checked_sub(output_value)
+        .map(|fee| fee.to_sat())
+        .ok_or_else(|| "Transaction pays out more than it spends".into())
+}
+
+/// Verifies that input `input_index` of `tx` is a taproot key-path spend signed by the vault:
+/// its single witness element must be a valid BIP-340 signature, under the vault's output
+/// key (`group_key` with a key-path-only tweak), of the BIP-341 sighash of `tx`.
+///
+/// The sighash commits to every spent output, so `prev_txs` must cover all inputs.
+pub fn verify_vault_key_spend(
+    tx: &Transaction,
+    input_index: usize,
+    prev_txs: &[Transaction],
+    group_key: &XOnlyPublicKey,
+) -> Result<(), Box<dyn Error>> {
+    let input = tx
+        .input
+        .get(input_index)
+        .ok_or_else(|| format!("Transaction has no input {}", input_index))?;
+    if input.witness.len() != 1 {
+        return Err(format!(
+            "Input {} has {} witness elements, expected a single key-path signature",
+            input_index,
+            input.witness.len()
+        )
+        .into());
+    }
+    let signature = taproot::Signature::from_slice(&input.witness[0])?;
+
+    let spent_outputs = spent_outputs(tx, prev_txs)?;
+    let sighash = SighashCache::new(tx).taproot_key_spend_signature_hash(
+        input_index,
+        &Prevouts::All(&spent_outputs),
+        signature.sighash_type,
+    )?;
+
+    let secp = Secp256k1::verification_only();
+    let (output_key, _) = group_key.tap_tweak(&secp, None);
+    secp.verify_schnorr(
+        &signature.signature,
+        &Message::from(sighash),
+        &output_key.to_inner(),
+    )?;
+    Ok(())
+}
+
+/// Verifies a single deposit: checks Merkle inclusion against `merkle_root` after bounding the
+/// proof by the block's `tx_count` if known, then sums the value paid to
+/// `deposit_script_pubkey`, enforces the policy minimum and fee schedule and parses the
+/// OP_RETURN recipient. Memo deposits pay the vault script, [`vault_script_pubkey`].
+///
+/// With `request_id` set, `deposit_script_pubkey` is that request's deposit script: the memo
+/// becomes optional and the request id is reported in the outcome.
+///
+/// Rule violations are reported through `DepositOutcome::reject_reason`; an `Err` is only
+/// returned when the transaction itself cannot be processed.
+pub fn verify_deposit(
+    tx: &Transaction,
+    merkle_proof: &MerkleProof,
+    merkle_root: &str,
+    tx_count: Option<u32>,
+    deposit_script_pubkey: &Script,
+    request_id: Option<u64>,
+    policy: &MintPolicyStruct,
+) -> Result<DepositOutcome, Box<dyn Error>> {
+    let txid = tx.compute_txid();
+
+    // Reject structurally nonsensical proofs before doing any other work
+    if let Err(e) = check_merkle_proof_bounds(merkle_proof, tx_count) {
+        return Ok(DepositOutcome::rejected(
+            txid,
+            MintRejectReason::InvalidMerkleProof,
+            e.to_string(),
+        ));
+    }
+    // A transaction not proven in the block is rejected before any rule about its contents:
+    // those reasons make the deposit refundable, and only a mined deposit may be refunded
+    match verify_tx_inclusion_str(txid.to_string().as_str(), merkle_proof, merkle_root) {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(DepositOutcome::rejected(
+                txid,
+                MintRejectReason::MerkleRootMismatch,
+                "Merkle root mismatch".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Ok(DepositOutcome::rejected(
+                txid,
+                MintRejectReason::InvalidMerkleProof,
+                e.to_string(),
+            ))
+        }
+    }
+
+    let DepositOutputs {
+        amount_sats,
+        output_indices,
+        memo: memo_bytes,
+    } = process_transaction_outputs(tx, deposit_script_pubkey)?;
+
+    if amount_sats < policy.min_deposit_sats {
+        return Ok(DepositOutcome::rejected(
+            txid,
+            MintRejectReason::BelowMinimumDeposit,
+            format!(
+                "Deposit of {} sats is below the minimum of {} sats",
+                amount_sats, policy.min_deposit_sats
+            ),
+        ));
+    }
+
+    let net_amount_sats = match policy.net_amount(amount_sats) {
+        Some(net) => net,
+        None => {
+            return Ok(DepositOutcome::rejected(
+                txid,
+                MintRejectReason::FeeExceedsDeposit,
+                format!(
+                    "Fees of {} bps + {} sats consume the deposit of {} sats",
+                    policy.fee_bps, policy.flat_fee_sats, amount_sats
+                ),
+            ))
+        }
+    };
+
+    let mut memo = match (memo_bytes, request_id) {
+        (Some(bytes), _) => match parse_deposit_memo(&bytes) {
+            Ok(memo) => memo,
+            Err(e) => {
+                return Ok(DepositOutcome::rejected(
+                    txid,
+                    MintRejectReason::InvalidMemo,
+                    format!("{} (memo hex: {})", e, hex::encode(&bytes)),
+                ))
+            }
+        },
+        // Per-request deposits are resolved to a recipient on the EVM side by request id
+        (None, Some(_)) => DepositMemo {
+            chain_id: 0,
+            recipient: Address::ZERO,
+            nonce: 0,
+            request_id: 0,
+        },
+        (None, None) => {
+            return Ok(DepositOutcome::rejected(
+                txid,
+                MintRejectReason::MissingMemo,
+                "No OP_RETURN memo found".to_string(),
+            ))
+        }
+    };
+    if let Some(request_id) = request_id {
+        if memo.request_id != 0 && memo.request_id != request_id {
+            return Ok(DepositOutcome::rejected(
+                txid,
+                MintRejectReason::InvalidMemo,
+                format!(
+                    "Memo request id {} does not match deposit address request id {}",
+                    memo.request_id, request_id
+                ),
+            ));
+        }
+        memo.request_id = request_id;
+    }
+
+    Ok(DepositOutcome {
+        txid,
+        recipient: memo.recipient,
+        chain_id: memo.chain_id,
+        nonce: memo.nonce,
+        request_id: memo.request_id,
+        amount_sats,
+        net_amount_sats,
+        output_indices,
+        reject_reason: MintRejectReason::None,
+        reject_detail: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::script::Builder;
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, Sequence, TxIn, Witness};
+
+    /// A coinbase whose scriptSig starts with `height` pushed the way Bitcoin Core does, and
+    /// the Merkle root of a block holding only it.
+    fn coinbase_block(height: i64) -> (Transaction, String) {
+        let tx = Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Builder::new().push_int(height).push_int(0).into_script(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(50 * 100_000_000),
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        };
+        let merkle_root = tx.compute_txid().to_string();
+        (tx, merkle_root)
+    }
+
+    fn coinbase_proof() -> MerkleProof {
+        MerkleProof {
+            siblings: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    #[test]
+    fn coinbase_height_reads_op_n_heights() {
+        for height in [1, 16] {
+            let (tx, merkle_root) = coinbase_block(height);
+            assert_eq!(
+                verify_coinbase_height(&tx, &coinbase_proof(), &merkle_root, 4).unwrap(),
+                height as u32
+            );
+        }
+    }
+
+    #[test]
+    fn coinbase_height_reads_pushed_heights() {
+        for height in [17, 840_000] {
+            let (tx, merkle_root) = coinbase_block(height);
+            assert_eq!(
+                verify_coinbase_height(&tx, &coinbase_proof(), &merkle_root, 4).unwrap(),
+                height as u32
+            );
+        }
+    }
+
+    #[test]
+    fn request_memo_names_its_chain() {
+        let recipient = Address::repeat_byte(0xa8);
+        let memo = parse_deposit_memo(&encode_request_memo(recipient, 7, 31_337)).unwrap();
+        assert_eq!(
+            memo,
+            DepositMemo {
+                chain_id: 31_337,
+                recipient,
+                nonce: 0,
+                request_id: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn deposit_inclusion_is_checked_before_its_contents() {
+        // Pays nothing to the bridge, so its contents alone would reject it as too small
+        let (tx, merkle_root) = coinbase_block(1);
+        let bridge_spk = ScriptBuf::new_op_return([1]);
+        let policy = MintPolicyStruct {
+            min_deposit_sats: 1_000,
+            ..Default::default()
+        };
+        let outside = verify_deposit(
+            &tx,
+            &coinbase_proof(),
+            &BlockHash::all_zeros().to_string(),
+            Some(1),
+            &bridge_spk,
+            None,
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(outside.reject_reason, MintRejectReason::MerkleRootMismatch);
+        let included = verify_deposit(
+            &tx,
+            &coinbase_proof(),
+            &merkle_root,
+            Some(1),
+            &bridge_spk,
+            None,
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(
+            included.reject_reason,
+            MintRejectReason::BelowMinimumDeposit
+        );
+    }
+
+    /// x-only key of a BIP-350 P2TR test vector, as a version 1 witness program.
+    const P2TR_PROGRAM: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn p2tr_script(program: &str) -> ScriptBuf {
+        ScriptBuf::from_hex(&format!("5120{}", program)).unwrap()
+    }
+
+    /// Encodes a version 1 witness program with the given checksum, which for a valid P2TR
+    /// address must be bech32m.
+    fn encode_v1<Ck: bitcoin::bech32::Checksum>(hrp: &str, program: &str) -> String {
+        use bitcoin::bech32::{ByteIterExt, Fe32, Fe32IterExt, Hrp};
+        hex::decode(program)
+            .unwrap()
+            .into_iter()
+            .bytes_to_fes()
+            .with_checksum::<Ck>(&Hrp::parse(hrp).unwrap())
+            .with_witness_version(Fe32::P)
+            .chars()
+            .collect()
+    }
+
+    #[test]
+    fn p2tr_addresses_resolve_on_each_network() {
+        for (address, network) in [
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                Network::Bitcoin,
+            ),
+            (
+                &*encode_v1::<bitcoin::bech32::Bech32m>("tb", P2TR_PROGRAM),
+                Network::Testnet,
+            ),
+            (
+                &*encode_v1::<bitcoin::bech32::Bech32m>("bcrt", P2TR_PROGRAM),
+                Network::Regtest,
+            ),
+        ] {
+            assert_eq!(
+                address_script_pubkey(address, network).unwrap(),
+                p2tr_script(P2TR_PROGRAM),
+                "{} on {}",
+                address,
+                network
+            );
+        }
+    }
+
+    #[test]
+    fn p2tr_address_of_another_network_is_rejected() {
+        let testnet = encode_v1::<bitcoin::bech32::Bech32m>("tb", P2TR_PROGRAM);
+        assert!(address_script_pubkey(&testnet, Network::Bitcoin).is_err());
+        let regtest = encode_v1::<bitcoin::bech32::Bech32m>("bcrt", P2TR_PROGRAM);
+        assert!(address_script_pubkey(&regtest, Network::Testnet).is_err());
+        assert!(address_script_pubkey(
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+            Network::Regtest
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn bech32_encoded_p2tr_address_is_rejected() {
+        // BIP-350: witness version 1 and above must use the bech32m checksum
+        for (hrp, network) in [
+            ("bc", Network::Bitcoin),
+            ("tb", Network::Testnet),
+            ("bcrt", Network::Regtest),
+        ] {
+            let address = encode_v1::<bitcoin::bech32::Bech32>(hrp, P2TR_PROGRAM);
+            assert!(
+                address_script_pubkey(&address, network).is_err(),
+                "{} must not decode",
+                address
+            );
+        }
+    }
+}
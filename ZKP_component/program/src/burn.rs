@@ -1,196 +1,75 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
-use alloy_primitives::U256;
-use bitcoin::block::{Header, Version};
-use bitcoin::consensus::deserialize;
-use bitcoin::hash_types::{BlockHash, TxMerkleNode, Txid};
-use bitcoin::hashes::hex::FromHex;
-use bitcoin::hashes::{sha256d, Hash};
-use bitcoin::network::Network;
-use bitcoin::Amount;
+use alloy_primitives::{FixedBytes, U256};
+use alloy_sol_types::SolType;
+use bitcoin::hash_types::{BlockHash, Txid};
+use bitcoin::hashes::Hash;
 use bitcoin::Transaction;
-use bitcoin::{Address as BitcoinAddress, CompactTarget};
-use lib_struct::{BundleInfoStruct, Chain, MerkleProof};
-use std::error::Error;
-use std::str::FromStr;
-
-/// The Bitcoin network type (Testnet in this example).
-const NETWORK_TYPE: Network = Network::Testnet;
-
-/// Computes the Merkle root from a transaction ID and its Merkle proof.
-/// Returns the computed Merkle root as a TxMerkleNode.
-fn compute_merkle_root_with_crate(
-    tx_id_str: &str,
-    merkle_proof: &MerkleProof,
-) -> Result<TxMerkleNode, Box<dyn Error>> {
-    let txid = Txid::from_str(tx_id_str)?;
-    let mut current_hash_bytes: [u8; 32] = *txid.as_byte_array();
-    let mut pos = merkle_proof.pos;
-
-    for sibling_hex in &merkle_proof.siblings {
-        let sibling_node = TxMerkleNode::from_str(sibling_hex)?;
-        let sibling_bytes: [u8; 32] = *sibling_node.as_byte_array();
-
-        let (left, right) = if pos % 2 == 0 {
-            (current_hash_bytes, sibling_bytes)
-        } else {
-            (sibling_bytes, current_hash_bytes)
-        };
-
-        let mut concat = [0u8; 64];
-        concat[..32].copy_from_slice(&left);
-        concat[32..].copy_from_slice(&right);
-
-        let combined_hash: sha256d::Hash = sha256d::Hash::hash(&concat);
-        current_hash_bytes = *combined_hash.as_byte_array();
-        pos >>= 1;
-    }
-
-    Ok(TxMerkleNode::from_byte_array(current_hash_bytes))
+use lib_struct::chain::parse_chain;
+use lib_struct::verify::{
+    check_merkle_proof_bounds, compute_fee, decode_transaction, find_unexpected_output,
+    find_vault_input, parse_burn_tag, parse_vault_key, sum_outputs_to_script, vault_script_pubkey,
+    verify_chain_with_crate, verify_coinbase_height, verify_tx_inclusion_str,
+    verify_vault_key_spend, DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{BundleInfoStruct, BurnRejectReason, ZkpBurnPublicValuesStruct};
+
+/// Converts a txid into the `bytes32` committed on-chain (display byte order).
+fn txid_to_bytes32(txid: Txid) -> FixedBytes<32> {
+    txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
 }
 
-/// Verifies that a transaction is included in a block by comparing the computed Merkle root
-/// (from the transaction ID and Merkle proof) to the block's Merkle root.
-pub fn verify_tx_inclusion_str(
-    tx_id_str: &str,
-    merkle_proof: &MerkleProof,
-    target_root_str: &str,
-) -> Result<bool, Box<dyn Error>> {
-    let computed_root: TxMerkleNode = compute_merkle_root_with_crate(tx_id_str, merkle_proof)?;
-    let target_root: TxMerkleNode = TxMerkleNode::from_str(target_root_str)?;
-    Ok(computed_root == target_root)
+/// Converts a block hash into the `bytes32` committed on-chain (display byte order).
+fn block_hash_to_bytes32(hash: BlockHash) -> FixedBytes<32> {
+    hash.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
 }
 
-/// Sums the value sent to a specific Bitcoin address in a transaction's outputs.
-fn sum_outputs_to_address(
-    raw_hex: &str,
-    target_address_str: &str,
-    network: Network,
-) -> Result<u64, Box<dyn Error>> {
-    let tx_bytes =
-        Vec::<u8>::from_hex(raw_hex).map_err(|e| format!("Failed to decode raw hex: {}", e))?;
-    let tx: Transaction = deserialize(&tx_bytes)?;
-
-    let target_address = BitcoinAddress::from_str(target_address_str)
-        .unwrap()
-        .require_network(network)
-        .unwrap();
-
-    let mut total_value: u64 = 0;
-
-    for output in &tx.output {
-        if let Ok(derived_address) = BitcoinAddress::from_script(&output.script_pubkey, network) {
-            if derived_address == target_address {
-                total_value = total_value.saturating_add(Amount::to_sat(output.value));
-            }
-        }
-    }
-
-    Ok(total_value)
+/// Commits public values for a rejected payout: `is_valid = false` with a reason code,
+/// so a failed redemption attempt still yields a proof instead of a prover crash.
+fn commit_rejection(txid: Txid, reason: BurnRejectReason, detail: &str, bundle: &BundleInfoStruct) {
+    let burn_policy = &bundle.burn_policy;
+    println!("Payout rejected ({:?}): {}", reason, detail);
+    let payload = ZkpBurnPublicValuesStruct::abi_encode_params(&ZkpBurnPublicValuesStruct {
+        burner_btc_address: bundle.burner_btc_address.clone().unwrap_or_default(),
+        amount: U256::ZERO,
+        is_valid: false,
+        payout_tx_id: txid_to_bytes32(txid),
+        vault_script_pubkey: Default::default(),
+        burn_id: U256::from(bundle.burn_id.unwrap_or_default()),
+        reject_reason: reason as u8,
+        expected_payout_sats: U256::from(burn_policy.expected_payout()),
+        payout_tolerance_sats: U256::from(burn_policy.tolerance_sats),
+        underpaid: false,
+        min_difficulty: U256::ZERO,
+        chain_work: U256::ZERO,
+        confirmations: U256::from(required_confirmations(bundle)),
+        vault_public_key: FixedBytes::ZERO,
+        vault_change_sats: U256::ZERO,
+        network_magic: FixedBytes::from(
+            parse_chain(bundle.network.as_deref()).map_or([0; 4], |chain| chain.magic),
+        ),
+        payout_block_height: U256::ZERO,
+        rbf_signaled: false,
+        fee_sats: U256::ZERO,
+        max_fee_sats: U256::from(burn_policy.max_fee_sats),
+        checkpoint_hash: FixedBytes::ZERO,
+        contested: false,
+        tagged: false,
+    });
+    sp1_zkvm::io::commit_slice(&payload);
+    println!("Burn circuit completed and rejection committed.");
 }
 
-/// Verifies the integrity and linkage of a chain of blocks.
-/// Checks block hash correctness and parent linkage.
-pub fn verify_chain_with_crate(chain: &Chain) -> Result<(), Box<dyn Error>> {
-    if chain.blocks.len() != 6 {
-        return Err(format!(
-            "Chain validation failed: Expected exactly 6 blocks, found {}",
-            chain.blocks.len()
-        )
-        .into());
-    }
-
-    let mut computed_hashes: Vec<BlockHash> = Vec::with_capacity(6);
-
-    for (i, user_block) in chain.blocks.iter().enumerate() {
-        let expected_block_hash = BlockHash::from_str(&user_block.block_hash)?;
-        let prev_blockhash = BlockHash::from_str(&user_block.parent_hash)?;
-        let merkle_root = TxMerkleNode::from_str(&user_block.merkle_root)?;
-
-        let current_header = Header {
-            version: Version::from_consensus(user_block.version as i32),
-            prev_blockhash,
-            merkle_root,
-            time: user_block.timestamp,
-            bits: CompactTarget::from_consensus(user_block.difficulty),
-            nonce: user_block.nonce,
-        };
-
-        let computed_block_hash = current_header.block_hash();
-        if computed_block_hash != expected_block_hash {
-            return Err(format!(
-                "Chain validation failed at block index {}: Computed hash {} does not match provided block_hash {}",
-                i, computed_block_hash, expected_block_hash
-            ).into());
-        }
-
-        computed_hashes.push(computed_block_hash);
-
-        if i > 0 {
-            let prev_computed_hash = computed_hashes[i - 1];
-            if current_header.prev_blockhash != prev_computed_hash {
-                return Err(format!(
-                    "Chain validation failed at block index {}: Parent hash {} does not match previous block's computed hash {}",
-                    i, current_header.prev_blockhash, prev_computed_hash
-                ).into());
-            }
-        }
-    }
-
-    Ok(())
+/// Number of headers `chains` must contain: the bundle's setting, never below the default
+/// depth, since the contract settles a payout without reading the committed depth.
+fn required_confirmations(bundle: &BundleInfoStruct) -> usize {
+    bundle
+        .confirmations
+        .map_or(DEFAULT_CONFIRMATIONS, |confirmations| {
+            (confirmations as usize).max(DEFAULT_CONFIRMATIONS)
+        })
 }
 
-/// Manually ABI-encodes the tuple (string burner_btc_address, uint256 amount, bool is_valid)
-/// according to Solidity's ABI spec.
-///
-/// # Layout:
-/// head (3 slots):
-///   [0] offset to start of string data = 3 * 32 = 96
-///   [1] amount (uint256)
-///   [2] is_valid (bool as uint256)
-/// tail:
-///   [3] string length (uint256)
-///   [4+] string bytes (padded to 32-byte boundary)
-///
-/// # Parameters
-/// - `burner_btc_address`: the UTF-8 Bitcoin address string
-/// - `amount`: the amount to encode (u64)
-/// - `is_valid`: whether the proof is valid (bool)
-pub fn abi_encode_zkp_burn(burner_btc_address: &str, amount: u64, is_valid: bool) -> Vec<u8> {
-    let mut encoded = Vec::new();
-
-    // 1) Head slot 0: offset to dynamic string = 3 * 32 = 96
-    let offset_bytes: [u8; 32] = U256::from(96u64).to_be_bytes();
-    encoded.extend_from_slice(&offset_bytes);
-
-    // 2) Head slot 1: amount as uint256
-    let amount_bytes: [u8; 32] = U256::from(amount).to_be_bytes();
-    encoded.extend_from_slice(&amount_bytes);
-
-    // 3) Head slot 2: is_valid as 0 or 1 in uint256
-    let valid_u256 = if is_valid { U256::ONE } else { U256::ZERO };
-    let valid_bytes: [u8; 32] = valid_u256.to_be_bytes();
-    encoded.extend_from_slice(&valid_bytes);
-
-    // Tail: dynamic string data
-    let s_bytes = burner_btc_address.as_bytes();
-
-    // 4) Length prefix
-    let len_bytes: [u8; 32] = U256::from(s_bytes.len() as u64).to_be_bytes();
-    encoded.extend_from_slice(&len_bytes);
-
-    // 5) Actual string bytes
-    encoded.extend_from_slice(s_bytes);
-
-    // 6) Padding for string data to 32-byte boundary
-    let rem = s_bytes.len() % 32;
-    if rem != 0 {
-        let pad_len = 32 - rem;
-        encoded.extend(std::iter::repeat(0u8).take(pad_len));
-    }
-
-    encoded
-}
 /// zkVM entrypoint: verifies a Bitcoin burn and prepares public values for proof.
 ///
 /// This circuit proves, in zero-knowledge, that a Bitcoin transaction sent funds to a
@@ -199,49 +78,424 @@ pub fn abi_encode_zkp_burn(burner_btc_address: &str, amount: u64, is_valid: bool
 pub fn main() {
     // Read input bundle from zkVM host
     let bundle: BundleInfoStruct = sp1_zkvm::io::read();
-    // Extract the burner BTC address from the bundle
-    let burner_btc_address = &bundle
-        .burner_btc_address
-        .as_ref()
-        .expect("Burner BTC address must be provided");
 
     // === Parse and validate transaction ===
-    let tx_bytes = hex::decode(&bundle.bit_tx_info.raw_tx_hex).unwrap();
-    let tx: Transaction = deserialize(&tx_bytes).unwrap();
+    println!("cycle-tracker-report-start: parse_tx");
+    let tx = decode_transaction(&bundle.bit_tx_info.raw_tx_hex);
+    println!("cycle-tracker-report-end: parse_tx");
+    let tx = match tx {
+        Ok(tx) => tx,
+        Err(e) => {
+            commit_rejection(
+                Txid::all_zeros(),
+                BurnRejectReason::InvalidTransaction,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
     let txid = tx.compute_txid();
     println!("Transaction ID: {}", txid);
 
+    // The chain is committed, so one ELF and vkey serve every chain and network
+    let chain = match parse_chain(bundle.network.as_deref()) {
+        Ok(chain) => chain,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::UnknownChain,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+    println!("Source chain: {}", chain);
+
+    // The burn request id binds the proof to exactly one pending burn on the contract
+    let (burner_btc_address, burn_id) = match (bundle.burner_btc_address.as_deref(), bundle.burn_id)
+    {
+        (Some(burner_btc_address), Some(burn_id)) => (burner_btc_address, burn_id),
+        _ => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::MissingBurnRequest,
+                "Burner BTC address and burn request id must be provided",
+                &bundle,
+            );
+            return;
+        }
+    };
+    println!("Burn request id: {}", burn_id);
+
+    // === Verify block chain ===
+    // Same header checks as the mint circuit: a forged confirmation chain for a payout is as
+    // damaging as one for a deposit.
+    // The anchor is committed so the contract can tie the chain to its header relay.
+    // Verified first, so every later check reads headers known to exist and be linked.
+    let confirmations = required_confirmations(&bundle);
+    println!("cycle-tracker-report-start: verify_chain");
+    let chain_result = verify_chain_with_crate(
+        &bundle.chains,
+        confirmations,
+        bundle.retarget_info.as_ref(),
+        bundle.checkpoint_hash.as_deref(),
+        None,
+        &chain,
+    );
+    println!("cycle-tracker-report-end: verify_chain");
+    let chain_summary = match chain_result {
+        Ok(summary) => {
+            println!(
+                "Chain verified successfully ({} confirmations, minimum difficulty: {}, cumulative work: {:#x})",
+                confirmations, summary.min_difficulty, summary.total_work
+            );
+            summary
+        }
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::InvalidChain,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+
+    // === Verify Merkle inclusion ===
+    println!("cycle-tracker-report-start: verify_merkle");
+    let inclusion =
+        check_merkle_proof_bounds(&bundle.merkle_proof, bundle.tx_count).and_then(|_| {
+            verify_tx_inclusion_str(
+                txid.to_string().as_str(),
+                &bundle.merkle_proof,
+                &bundle.chains.blocks[0].merkle_root,
+            )
+        });
+    println!("cycle-tracker-report-end: verify_merkle");
+    let inclusion_rejection = match inclusion {
+        Ok(true) => None,
+        Ok(false) => Some((
+            BurnRejectReason::MerkleRootMismatch,
+            "Merkle root mismatch".to_string(),
+        )),
+        Err(e) => Some((BurnRejectReason::InvalidMerkleProof, e.to_string())),
+    };
+    if let Some((reason, detail)) = inclusion_rejection {
+        commit_rejection(txid, reason, &detail, &bundle);
+        return;
+    }
+    println!("Transaction inclusion verified successfully");
+
     // === Sum outputs to burner address ===
-    let total_sats_to_burner = sum_outputs_to_address(
-        &bundle.bit_tx_info.raw_tx_hex,
-        burner_btc_address,
-        NETWORK_TYPE,
-    )
-    .unwrap();
+    let burner_spk = match chain.address_script_pubkey(burner_btc_address) {
+        Ok(spk) => spk,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::InvalidRecipient,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+    let total_sats_to_burner = sum_outputs_to_script(&tx, &burner_spk);
     println!(
         "Total satoshis sent to burner address {}: {}",
         burner_btc_address, total_sats_to_burner
     );
 
-    // === Verify Merkle inclusion ===
-    match verify_tx_inclusion_str(
-        txid.to_string().as_str(),
-        &bundle.merkle_proof,
-        &bundle.chains.blocks[0].merkle_root,
-    ) {
-        Ok(true) => println!("Transaction inclusion verified successfully"),
-        Ok(false) => panic!("Merkle root mismatch"),
-        Err(e) => panic!("Verification failed: {}", e),
+    // === Check the payout against the expected burn amount ===
+    let burn_policy = &bundle.burn_policy;
+    let underpaid = burn_policy.is_underpaid(total_sats_to_burner);
+    if underpaid {
+        println!(
+            "Payout underpaid: {} sats paid, {} expected with {} sats tolerance",
+            total_sats_to_burner,
+            burn_policy.expected_payout(),
+            burn_policy.tolerance_sats
+        );
     }
 
-    // === Verify block chain ===
-    match verify_chain_with_crate(&bundle.chains) {
-        Ok(_) => println!("Chain verified successfully"),
-        Err(e) => panic!("Chain verification failed: {}", e),
+    // === Verify the payout spends from the bridge vault ===
+    // The key is the bundle's; the committed vault script is what the contract checks it by
+    let group_key = match parse_vault_key(bundle.vault_public_key.as_deref()) {
+        Ok(group_key) => group_key,
+        Err(e) => {
+            commit_rejection(txid, BurnRejectReason::InvalidVaultKey, &e, &bundle);
+            return;
+        }
+    };
+    let vault_spk = vault_script_pubkey(&group_key);
+    let prev_txs = match bundle
+        .spent_prevouts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|prev_tx_info| decode_transaction(&prev_tx_info.raw_tx_hex))
+        .collect::<Result<Vec<Transaction>, _>>()
+    {
+        Ok(prev_txs) => prev_txs,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::NotFromVault,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+    let vault_input = match find_vault_input(&tx, &prev_txs, &vault_spk) {
+        Ok(Some(index)) => {
+            println!(
+                "Input {} spends from vault script {}",
+                index,
+                vault_spk.to_hex_string()
+            );
+            index
+        }
+        Ok(None) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::NotFromVault,
+                &format!(
+                    "No input spends from vault script {}",
+                    vault_spk.to_hex_string()
+                ),
+                &bundle,
+            );
+            return;
+        }
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::NotFromVault,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+
+    // === Verify the vault input was signed by the group key ===
+    println!("cycle-tracker-report-start: verify_vault_signature");
+    let signature_result = verify_vault_key_spend(&tx, vault_input, &prev_txs, &group_key);
+    println!("cycle-tracker-report-end: verify_vault_signature");
+    if let Err(e) = signature_result {
+        commit_rejection(
+            txid,
+            BurnRejectReason::InvalidVaultSignature,
+            &e.to_string(),
+            &bundle,
+        );
+        return;
     }
+    println!(
+        "Vault key-path signature verified for group key {}",
+        group_key
+    );
+
+    // === Enforce the fee cap ===
+    // Every spent output is known after the signature check, so the fee is fully determined;
+    // without a cap a signer majority could burn vault funds as fees in a valid payout.
+    let fee_sats = match compute_fee(&tx, &prev_txs) {
+        Ok(fee_sats) => fee_sats,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::NotFromVault,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+    println!("Payout miner fee: {} satoshis", fee_sats);
+    if burn_policy.exceeds_fee_cap(fee_sats) {
+        commit_rejection(
+            txid,
+            BurnRejectReason::FeeTooHigh,
+            &format!(
+                "Fee of {} sats exceeds the cap of {} sats",
+                fee_sats, burn_policy.max_fee_sats
+            ),
+            &bundle,
+        );
+        return;
+    }
+
+    // === Record whether the payout signaled replaceability ===
+    // A replaceable payout seen before confirmation could have been swapped for another
+    // spend of the vault, so the contract may treat it differently from a final one.
+    let rbf_signaled = tx.is_explicitly_rbf();
+    if rbf_signaled {
+        println!("Payout signals replace-by-fee (BIP-125)");
+    }
+
+    // === Sum the change returned to the vault ===
+    let vault_change_sats = sum_outputs_to_script(&tx, &vault_spk);
+    println!(
+        "Change returned to the vault: {} satoshis",
+        vault_change_sats
+    );
+
+    // === Enforce a closed output set: burner payout, vault change or OP_RETURN only ===
+    if let Some(index) = find_unexpected_output(&tx, &[&burner_spk, &vault_spk]) {
+        commit_rejection(
+            txid,
+            BurnRejectReason::UnexpectedOutput,
+            &format!(
+                "Output {} pays unexpected script {}",
+                index,
+                tx.output[index].script_pubkey.to_hex_string()
+            ),
+            &bundle,
+        );
+        return;
+    }
+
+    // === Cross-check the OP_RETURN burn tag ===
+    // Untagged payouts still prove; a tag naming another burn means the payout was built
+    // for a different redemption.
+    let tagged = match parse_burn_tag(&tx) {
+        Ok(Some(tagged_id)) if tagged_id == burn_id => {
+            println!("Payout is tagged with burn id {}", tagged_id);
+            true
+        }
+        Ok(Some(tagged_id)) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::BurnTagMismatch,
+                &format!("Payout is tagged for burn {}, not {}", tagged_id, burn_id),
+                &bundle,
+            );
+            return;
+        }
+        Ok(None) => false,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::BurnTagMismatch,
+                &e.to_string(),
+                &bundle,
+            );
+            return;
+        }
+    };
+
+    // === Flag a competing chain from the same checkpoint ===
+    // A valid sibling of the payout's block means an observed fork could still drop the
+    // payout, so the contract can delay finalization instead of settling blind to it.
+    let contested = match bundle.competing_chain.as_ref() {
+        Some(competing_chain) => {
+            let anchor_hash = chain_summary.anchor_hash.to_string();
+            match verify_chain_with_crate(
+                competing_chain,
+                competing_chain.blocks.len(),
+                bundle.retarget_info.as_ref(),
+                Some(anchor_hash.as_str()),
+                None,
+                &chain,
+            ) {
+                Ok(summary) => {
+                    let forked = !competing_chain.blocks[0]
+                        .block_hash
+                        .eq_ignore_ascii_case(&bundle.chains.blocks[0].block_hash);
+                    println!(
+                        "Competing chain of {} blocks verified (cumulative work: {:#x}){}",
+                        competing_chain.blocks.len(),
+                        summary.total_work,
+                        if forked {
+                            ", payout is contested"
+                        } else {
+                            ", it keeps the payout's block"
+                        }
+                    );
+                    forked
+                }
+                Err(e) => {
+                    println!("Competing chain ignored: {}", e);
+                    false
+                }
+            }
+        }
+        None => false,
+    };
+
+    // === Prove the payout block's height from its coinbase ===
+    // The height lets the contract enforce payout deadlines; 0 means it was not proven.
+    let payout_block_height = match bundle.coinbase_proof.as_ref() {
+        Some(coinbase_proof) => {
+            let proven = decode_transaction(&coinbase_proof.coinbase_tx_info.raw_tx_hex)
+                .and_then(|coinbase_tx| {
+                    verify_coinbase_height(
+                        &coinbase_tx,
+                        &coinbase_proof.coinbase_merkle_proof,
+                        &bundle.chains.blocks[0].merkle_root,
+                        bundle.chains.blocks[0].version,
+                    )
+                })
+                .and_then(|height| match bundle.block_height {
+                    Some(claimed) if claimed != height => Err(format!(
+                        "Coinbase height {} does not match block height {}",
+                        height, claimed
+                    )
+                    .into()),
+                    _ => Ok(height),
+                });
+            match proven {
+                Ok(height) => {
+                    println!("Payout confirmed at block height {}", height);
+                    height
+                }
+                Err(e) => {
+                    commit_rejection(
+                        txid,
+                        BurnRejectReason::InvalidBlockHeight,
+                        &e.to_string(),
+                        &bundle,
+                    );
+                    return;
+                }
+            }
+        }
+        None => 0,
+    };
 
     // === Commit public values ===
-    let payload = abi_encode_zkp_burn(burner_btc_address, total_sats_to_burner, true);
+    // The payout txid is committed so the contract can refuse to release a second burn
+    // against the same Bitcoin payment.
+    println!("cycle-tracker-report-start: abi_encode");
+    let payload = ZkpBurnPublicValuesStruct::abi_encode_params(&ZkpBurnPublicValuesStruct {
+        burner_btc_address: burner_btc_address.to_string(),
+        amount: U256::from(total_sats_to_burner),
+        is_valid: true,
+        payout_tx_id: txid_to_bytes32(txid),
+        vault_script_pubkey: vault_spk.to_bytes().into(),
+        burn_id: U256::from(burn_id),
+        reject_reason: BurnRejectReason::None as u8,
+        expected_payout_sats: U256::from(burn_policy.expected_payout()),
+        payout_tolerance_sats: U256::from(burn_policy.tolerance_sats),
+        underpaid,
+        min_difficulty: U256::from(chain_summary.min_difficulty),
+        chain_work: U256::from_be_bytes(chain_summary.total_work.to_be_bytes()),
+        confirmations: U256::from(confirmations),
+        vault_public_key: FixedBytes::from(group_key.serialize()),
+        vault_change_sats: U256::from(vault_change_sats),
+        network_magic: FixedBytes::from(chain.magic),
+        payout_block_height: U256::from(payout_block_height),
+        rbf_signaled,
+        fee_sats: U256::from(fee_sats),
+        max_fee_sats: U256::from(burn_policy.max_fee_sats),
+        checkpoint_hash: block_hash_to_bytes32(chain_summary.anchor_hash),
+        contested,
+        tagged,
+    });
+    println!("cycle-tracker-report-end: abi_encode");
     println!("Encoded public values: {}", hex::encode(&payload));
     sp1_zkvm::io::commit_slice(&payload);
     println!("Burn circuit completed and public values committed.");
@@ -0,0 +1,25 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_sol_types::SolType;
+use lib_struct::aggregation::verify_and_fold;
+use lib_struct::{AggregationInputStruct, ZkpBurnAggregationPublicValuesStruct};
+
+/// zkVM entrypoint: verifies N compressed burn proofs and commits a Merkle root over their
+/// public values, so settling many redemptions costs a single on-chain verification.
+pub fn main() {
+    // Read the aggregation input from zkVM host
+    let input: AggregationInputStruct = sp1_zkvm::io::read();
+    println!("Aggregating {} burn proofs", input.public_values.len());
+
+    // === Verify each burn proof against the shared verification key ===
+    let aggregate = verify_and_fold(&input, sp1_zkvm::lib::verify::verify_sp1_proof);
+    println!("Public values root: {}", aggregate.public_values_root);
+
+    // === Prepare and commit public values ===
+    let public_values: ZkpBurnAggregationPublicValuesStruct = aggregate.into();
+    let bytes = ZkpBurnAggregationPublicValuesStruct::abi_encode(&public_values);
+
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Burn aggregation circuit completed and public values committed.");
+}
@@ -0,0 +1,322 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_primitives::{FixedBytes, U256};
+use alloy_sol_types::SolType;
+use bitcoin::hash_types::Txid;
+use bitcoin::hashes::Hash;
+use bitcoin::script::{Script, ScriptBuf};
+use bitcoin::Transaction;
+use lib_struct::chain::{parse_chain, ChainParams};
+use lib_struct::verify::{
+    check_merkle_proof_bounds, compute_fee, decode_transaction, find_unexpected_output,
+    find_vault_input, parse_burn_tag, parse_vault_key, sum_outputs_to_script, vault_script_pubkey,
+    verify_chain_with_crate, verify_tx_inclusion_str, verify_vault_key_spend,
+    DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{
+    BurnBatchBundleStruct, BurnPayoutRequestStruct, BurnPayoutStruct, BurnRejectReason,
+    ZkpBurnBatchPublicValuesStruct,
+};
+
+/// Builds a rejected payout entry.
+fn rejected_entry(payout: &BurnPayoutRequestStruct, reason: BurnRejectReason) -> BurnPayoutStruct {
+    BurnPayoutStruct {
+        burner_btc_address: payout.burner_btc_address.clone(),
+        amount: U256::ZERO,
+        burn_id: U256::from(payout.burn_id),
+        is_valid: false,
+        reject_reason: reason as u8,
+        expected_payout_sats: U256::from(payout.burn_policy.expected_payout()),
+        underpaid: false,
+    }
+}
+
+/// Commits public values rejecting every entry with `reason`, for a payout transaction that
+/// cannot be checked at all: undecodable, or on an unknown chain (network magic zero).
+fn commit_rejection(
+    txid: Txid,
+    reason: BurnRejectReason,
+    detail: &str,
+    bundle: &BurnBatchBundleStruct,
+    magic: [u8; 4],
+) {
+    println!("Payout rejected ({:?}): {}", reason, detail);
+    let bytes =
+        ZkpBurnBatchPublicValuesStruct::abi_encode_params(&ZkpBurnBatchPublicValuesStruct {
+            payout_tx_id: txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap(),
+            vault_script_pubkey: Default::default(),
+            payouts: bundle
+                .payouts
+                .iter()
+                .map(|payout| rejected_entry(payout, reason))
+                .collect(),
+            vault_change_sats: U256::ZERO,
+            network_magic: FixedBytes::from(magic),
+        });
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Batch burn circuit completed and rejection committed.");
+}
+
+/// What the transaction-wide checks establish for the entries.
+struct VerifiedPayout {
+    vault_spk: ScriptBuf,
+    fee_sats: u64,
+}
+
+/// Runs the burn circuit's checks that concern the payout transaction as a whole, in the same
+/// order: confirmation chain, inclusion, vault input and its signature, fee, closed output set
+/// and burn tag. A failure rejects every entry.
+fn verify_payout(
+    bundle: &BurnBatchBundleStruct,
+    tx: &Transaction,
+    txid: Txid,
+    chain: &ChainParams,
+) -> Result<VerifiedPayout, (BurnRejectReason, String)> {
+    // The chain is verified before the inclusion proof reads its first header
+    verify_chain_with_crate(
+        &bundle.chains,
+        DEFAULT_CONFIRMATIONS,
+        bundle.retarget_info.as_ref(),
+        bundle.checkpoint_hash.as_deref(),
+        bundle.reference_time,
+        chain,
+    )
+    .map_err(|e| (BurnRejectReason::InvalidChain, e.to_string()))?;
+
+    let inclusion =
+        check_merkle_proof_bounds(&bundle.merkle_proof, bundle.tx_count).and_then(|_| {
+            verify_tx_inclusion_str(
+                txid.to_string().as_str(),
+                &bundle.merkle_proof,
+                &bundle.chains.blocks[0].merkle_root,
+            )
+        });
+    match inclusion {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((
+                BurnRejectReason::MerkleRootMismatch,
+                "Merkle root mismatch".to_string(),
+            ))
+        }
+        Err(e) => return Err((BurnRejectReason::InvalidMerkleProof, e.to_string())),
+    }
+
+    // The key is the bundle's; the committed vault script is what the contract checks it by
+    let group_key = parse_vault_key(bundle.vault_public_key.as_deref())
+        .map_err(|e| (BurnRejectReason::InvalidVaultKey, e))?;
+    let vault_spk = vault_script_pubkey(&group_key);
+    let prev_txs = bundle
+        .spent_prevouts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|prev_tx_info| decode_transaction(&prev_tx_info.raw_tx_hex))
+        .collect::<Result<Vec<Transaction>, _>>()
+        .map_err(|e| (BurnRejectReason::NotFromVault, e.to_string()))?;
+    let vault_input = match find_vault_input(tx, &prev_txs, &vault_spk) {
+        Ok(Some(index)) => index,
+        Ok(None) => {
+            return Err((
+                BurnRejectReason::NotFromVault,
+                format!(
+                    "No input spends from vault script {}",
+                    vault_spk.to_hex_string()
+                ),
+            ))
+        }
+        Err(e) => return Err((BurnRejectReason::NotFromVault, e.to_string())),
+    };
+    println!(
+        "Input {} spends from vault script {}",
+        vault_input,
+        vault_spk.to_hex_string()
+    );
+    verify_vault_key_spend(tx, vault_input, &prev_txs, &group_key)
+        .map_err(|e| (BurnRejectReason::InvalidVaultSignature, e.to_string()))?;
+    println!(
+        "Vault key-path signature verified for group key {}",
+        group_key
+    );
+
+    // Every spent output is known after the signature check, so the fee is fully determined;
+    // each entry holds it against its own cap
+    let fee_sats =
+        compute_fee(tx, &prev_txs).map_err(|e| (BurnRejectReason::NotFromVault, e.to_string()))?;
+    println!("Payout miner fee: {} satoshis", fee_sats);
+
+    // Closed output set: burner payouts, vault change or OP_RETURN only
+    let mut allowed_scripts: Vec<ScriptBuf> = bundle
+        .payouts
+        .iter()
+        .filter_map(|payout| chain.address_script_pubkey(&payout.burner_btc_address).ok())
+        .collect();
+    allowed_scripts.push(vault_spk.clone());
+    let allowed_scripts: Vec<&Script> = allowed_scripts.iter().map(|spk| spk.as_script()).collect();
+    if let Some(index) = find_unexpected_output(tx, &allowed_scripts) {
+        return Err((
+            BurnRejectReason::UnexpectedOutput,
+            format!(
+                "Output {} pays unexpected script {}",
+                index,
+                tx.output[index].script_pubkey.to_hex_string()
+            ),
+        ));
+    }
+
+    // Untagged payouts still prove; a tag naming a burn outside the batch means the payout
+    // was built for a different redemption
+    match parse_burn_tag(tx) {
+        Ok(Some(tagged_id)) if bundle.payouts.iter().any(|p| p.burn_id == tagged_id) => {
+            println!("Payout is tagged with burn id {}", tagged_id);
+        }
+        Ok(Some(tagged_id)) => {
+            return Err((
+                BurnRejectReason::BurnTagMismatch,
+                format!(
+                    "Payout is tagged for burn {}, not one of the batch",
+                    tagged_id
+                ),
+            ))
+        }
+        Ok(None) => {}
+        Err(e) => return Err((BurnRejectReason::BurnTagMismatch, e.to_string())),
+    }
+
+    Ok(VerifiedPayout {
+        vault_spk,
+        fee_sats,
+    })
+}
+
+/// zkVM entrypoint: verifies one Bitcoin payout transaction paying several burn requests and
+/// commits one (address, amount, burn id) entry per request.
+pub fn main() {
+    // Read batch bundle from zkVM host
+    let bundle: BurnBatchBundleStruct = sp1_zkvm::io::read();
+    println!("Batch size: {}", bundle.payouts.len());
+
+    // === Parse transaction ===
+    let tx = match decode_transaction(&bundle.bit_tx_info.raw_tx_hex) {
+        Ok(tx) => tx,
+        Err(e) => {
+            let magic = parse_chain(bundle.network.as_deref()).map_or([0; 4], |chain| chain.magic);
+            commit_rejection(
+                Txid::all_zeros(),
+                BurnRejectReason::InvalidTransaction,
+                &e.to_string(),
+                &bundle,
+                magic,
+            );
+            return;
+        }
+    };
+    let txid = tx.compute_txid();
+    println!("Transaction ID: {}", txid);
+
+    // The chain is committed, so one ELF and vkey serve every chain and network
+    let chain = match parse_chain(bundle.network.as_deref()) {
+        Ok(chain) => chain,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                BurnRejectReason::UnknownChain,
+                &e.to_string(),
+                &bundle,
+                [0; 4],
+            );
+            return;
+        }
+    };
+    println!("Source chain: {}", chain);
+
+    // === Verify the payout transaction once; a failure rejects every entry ===
+    let verified = verify_payout(&bundle, &tx, txid, &chain);
+    match &verified {
+        Ok(_) => println!("Transaction inclusion and chain verified successfully"),
+        Err((reason, detail)) => println!("Payout rejected ({:?}): {}", reason, detail),
+    }
+
+    // === Sum the payout to each burner address ===
+    let mut payouts = Vec::with_capacity(bundle.payouts.len());
+    let mut seen_scripts = Vec::with_capacity(bundle.payouts.len());
+    for (i, payout) in bundle.payouts.iter().enumerate() {
+        let fee_sats = match &verified {
+            Ok(verified) => verified.fee_sats,
+            Err((reason, _)) => {
+                payouts.push(rejected_entry(payout, *reason));
+                continue;
+            }
+        };
+
+        let burner_spk = match chain.address_script_pubkey(&payout.burner_btc_address) {
+            Ok(spk) => spk,
+            Err(e) => {
+                println!("Payout #{} rejected: {}", i, e);
+                payouts.push(rejected_entry(payout, BurnRejectReason::InvalidRecipient));
+                continue;
+            }
+        };
+
+        // Outputs to one script cannot be split between burns, so each address is paid once
+        if seen_scripts.contains(&burner_spk) {
+            println!(
+                "Payout #{} ({}) rejected: duplicate in batch",
+                i, payout.burner_btc_address
+            );
+            payouts.push(rejected_entry(payout, BurnRejectReason::DuplicateRecipient));
+            continue;
+        }
+
+        // Without a cap a signer majority could burn vault funds as fees in a valid payout
+        if payout.burn_policy.exceeds_fee_cap(fee_sats) {
+            println!(
+                "Payout #{} rejected: fee of {} sats exceeds the cap of {} sats",
+                i, fee_sats, payout.burn_policy.max_fee_sats
+            );
+            payouts.push(rejected_entry(payout, BurnRejectReason::FeeTooHigh));
+            continue;
+        }
+
+        let amount_sats = sum_outputs_to_script(&tx, &burner_spk);
+        let underpaid = payout.burn_policy.is_underpaid(amount_sats);
+        println!(
+            "Payout #{} (burn {}): {} satoshis to {}{}",
+            i,
+            payout.burn_id,
+            amount_sats,
+            payout.burner_btc_address,
+            if underpaid { " (underpaid)" } else { "" }
+        );
+        seen_scripts.push(burner_spk);
+        payouts.push(BurnPayoutStruct {
+            burner_btc_address: payout.burner_btc_address.clone(),
+            amount: U256::from(amount_sats),
+            burn_id: U256::from(payout.burn_id),
+            is_valid: true,
+            reject_reason: BurnRejectReason::None as u8,
+            expected_payout_sats: U256::from(payout.burn_policy.expected_payout()),
+            underpaid,
+        });
+    }
+
+    // === Prepare and commit public values ===
+    let bytes =
+        ZkpBurnBatchPublicValuesStruct::abi_encode_params(&ZkpBurnBatchPublicValuesStruct {
+            payout_tx_id: txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap(),
+            vault_script_pubkey: match &verified {
+                Ok(verified) => verified.vault_spk.to_bytes().into(),
+                Err(_) => Default::default(),
+            },
+            payouts,
+            vault_change_sats: match &verified {
+                Ok(verified) => U256::from(sum_outputs_to_script(&tx, &verified.vault_spk)),
+                Err(_) => U256::ZERO,
+            },
+            network_magic: FixedBytes::from(chain.magic),
+        });
+
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Batch burn circuit completed and public values committed.");
+}
@@ -3,165 +3,78 @@ sp1_zkvm::entrypoint!(main);
 
 use alloy_primitives::{Address, FixedBytes, U256};
 use alloy_sol_types::SolType;
-use bitcoin::block::{Header, Version};
-use bitcoin::consensus::deserialize;
-use bitcoin::hash_types::{BlockHash, TxMerkleNode, Txid};
-use bitcoin::hashes::hex::FromHex;
-use bitcoin::hashes::{sha256d, Hash};
-use bitcoin::network::Network;
-use bitcoin::opcodes;
-use bitcoin::script::Instruction;
-use bitcoin::Amount;
-use bitcoin::Transaction;
-use bitcoin::{Address as BitcoinAddress, CompactTarget};
-use lib_struct::{BundleInfoStruct, Chain, MerkleProof, ZkpMintPublicValuesStruct};
-use std::error::Error;
-use std::str::FromStr;
-
-/// The deposit address that the bridge monitors for incoming funds.
-const BRIDGE_ADDRESS: &str = "tb1qzfqwyxc70pmlw7l7vmx9nmhmqtgh5z3lp3j9hf";
-/// The Bitcoin network type (Testnet in this example).
-const NETWORK_TYPE: Network = Network::Testnet;
-
-/// Computes the Merkle root from a transaction ID and its Merkle proof.
-/// Returns the computed Merkle root as a TxMerkleNode.
-fn compute_merkle_root_with_crate(
-    tx_id_str: &str,
-    merkle_proof: &MerkleProof,
-) -> Result<TxMerkleNode, Box<dyn Error>> {
-    let txid = Txid::from_str(tx_id_str)?;
-    let mut current_hash_bytes: [u8; 32] = *txid.as_byte_array();
-    let mut pos = merkle_proof.pos;
-    for sibling_hex in &merkle_proof.siblings {
-        let sibling_node = TxMerkleNode::from_str(sibling_hex)?;
-        let sibling_bytes: [u8; 32] = *sibling_node.as_byte_array();
-
-        let (left, right) = if pos % 2 == 0 {
-            (current_hash_bytes, sibling_bytes)
-        } else {
-            (sibling_bytes, current_hash_bytes)
-        };
-
-        let mut concat = [0u8; 64];
-        concat[..32].copy_from_slice(&left);
-        concat[32..].copy_from_slice(&right);
-
-        let combined_hash: sha256d::Hash = sha256d::Hash::hash(&concat);
-        current_hash_bytes = *combined_hash.as_byte_array();
-        pos >>= 1;
-    }
+use bitcoin::hash_types::{BlockHash, Txid};
+use bitcoin::hashes::Hash;
+use lib_struct::chain::parse_chain;
+use lib_struct::verify::{
+    check_transaction_finality, decode_transaction, extract_refund_address, parse_vault_key,
+    request_deposit_script_pubkey, vault_script_hash, vault_script_pubkey, verify_chain_with_crate,
+    verify_deposit, verify_witness_commitment, ChainSummary, DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{BundleInfoStruct, MintRejectReason, ZkpMintPublicValuesStruct};
 
-    Ok(TxMerkleNode::from_byte_array(current_hash_bytes))
+/// Converts a txid into the `bytes32` committed on-chain (display byte order).
+fn txid_to_bytes32(txid: Txid) -> FixedBytes<32> {
+    txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
 }
 
-/// Verifies that a transaction is included in a block by comparing the computed Merkle root
-/// (from the transaction ID and Merkle proof) to the block's Merkle root.
-pub fn verify_tx_inclusion_str(
-    tx_id_str: &str,
-    merkle_proof: &MerkleProof,
-    target_root_str: &str,
-) -> Result<bool, Box<dyn Error>> {
-    let computed_root: TxMerkleNode = compute_merkle_root_with_crate(tx_id_str, merkle_proof)?;
-    let target_root: TxMerkleNode = TxMerkleNode::from_str(target_root_str)?;
-    Ok(computed_root == target_root)
+/// Converts a block hash into the `bytes32` committed on-chain (display byte order).
+fn block_hash_to_bytes32(hash: BlockHash) -> FixedBytes<32> {
+    hash.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
 }
 
-/// Processes transaction outputs to:
-/// - Sum the value sent to a specific address.
-/// - Extract the first OP_RETURN memo (if present).
-fn process_transaction_outputs(
-    raw_hex: &str,
-    my_address_str: &str,
-    network: Network,
-) -> Result<(u64, Option<Vec<u8>>), Box<dyn Error>> {
-    let tx_bytes =
-        Vec::<u8>::from_hex(raw_hex).map_err(|e| format!("Failed to decode raw hex: {}", e))?;
-    let tx: Transaction = deserialize(&tx_bytes)?;
-
-    let my_address = BitcoinAddress::from_str(my_address_str)
-        .unwrap()
-        .require_network(network)
-        .unwrap();
-
-    let mut total_value_to_me: u64 = 0;
-    let mut op_return_data: Option<Vec<u8>> = None;
-
-    for output in &tx.output {
-        // Extract OP_RETURN memo if present (only the first one)
-        if output.script_pubkey.is_op_return() {
-            if op_return_data.is_none() {
-                let mut instructions = output.script_pubkey.instructions();
-                if let Some(Ok(Instruction::Op(opcodes::all::OP_RETURN))) = instructions.next() {
-                    if let Some(Ok(Instruction::PushBytes(data))) = instructions.next() {
-                        op_return_data = Some(data.as_bytes().to_vec());
-                    }
-                }
-            }
-            continue;
-        }
-
-        // Sum value sent to the monitored address
-        if let Ok(derived_address) = BitcoinAddress::from_script(&output.script_pubkey, network) {
-            if derived_address == my_address {
-                total_value_to_me = total_value_to_me.saturating_add(Amount::to_sat(output.value));
-            }
-        }
-    }
-
-    Ok((total_value_to_me, op_return_data))
-}
-
-/// Verifies the integrity and linkage of a chain of blocks.
-/// Checks block hash correctness and parent linkage.
-pub fn verify_chain_with_crate(chain: &Chain) -> Result<(), Box<dyn Error>> {
-    if chain.blocks.len() != 6 {
-        return Err(format!(
-            "Chain validation failed: Expected exactly 6 blocks, found {}",
-            chain.blocks.len()
-        )
-        .into());
-    }
-
-    let mut computed_hashes: Vec<BlockHash> = Vec::with_capacity(6);
-
-    for (i, user_block) in chain.blocks.iter().enumerate() {
-        let expected_block_hash = BlockHash::from_str(&user_block.block_hash)?;
-        let prev_blockhash = BlockHash::from_str(&user_block.parent_hash)?;
-        let merkle_root = TxMerkleNode::from_str(&user_block.merkle_root)?;
-
-        let current_header = Header {
-            version: Version::from_consensus(user_block.version as i32),
-            prev_blockhash,
-            merkle_root,
-            time: user_block.timestamp,
-            bits: CompactTarget::from_consensus(user_block.difficulty),
-            nonce: user_block.nonce,
-        };
-
-        // Check block hash correctness
-        let computed_block_hash = current_header.block_hash();
-        if computed_block_hash != expected_block_hash {
-            return Err(format!(
-                "Chain validation failed at block index {}: Computed hash {} does not match provided block_hash {}",
-                i, computed_block_hash, expected_block_hash
-            ).into());
-        }
-
-        computed_hashes.push(computed_block_hash);
-
-        // Check parent linkage (skip for the first block)
-        if i > 0 {
-            let prev_computed_hash = computed_hashes[i - 1];
-            if current_header.prev_blockhash != prev_computed_hash {
-                return Err(format!(
-                    "Chain validation failed at block index {}: Parent hash {} does not match previous block's computed hash {}",
-                    i, current_header.prev_blockhash, prev_computed_hash
-                ).into());
-            }
-        }
-    }
+/// Commits public values for a rejected deposit: `is_valid = false` with a reason code,
+/// so the proving job still yields an artifact instead of aborting. Once the chain is
+/// verified, its `chain_summary` is committed too, so a refundable rejection is tied to the
+/// checkpoint and work of the block that includes the deposit. The vault script hash is the
+/// bundle's key's, or zero without a valid one, and the network magic zero for an unknown chain.
+fn commit_rejection(
+    txid: Txid,
+    reason: MintRejectReason,
+    detail: &str,
+    bundle: &BundleInfoStruct,
+    chain_summary: Option<&ChainSummary>,
+    refund_btc_address: &str,
+) {
+    let policy = &bundle.mint_policy;
+    println!("Deposit rejected ({:?}): {}", reason, detail);
+    let bytes = ZkpMintPublicValuesStruct::abi_encode_params(&ZkpMintPublicValuesStruct {
+        tx_id: txid_to_bytes32(txid),
+        depositer_address: Address::ZERO,
+        amount: U256::ZERO,
+        is_valid: false,
+        min_difficulty: chain_summary
+            .map_or(U256::ZERO, |summary| U256::from(summary.min_difficulty)),
+        chain_work: chain_summary.map_or(U256::ZERO, |summary| {
+            U256::from_be_bytes(summary.total_work.to_be_bytes())
+        }),
+        reject_reason: reason as u8,
+        min_deposit_sats: U256::from(policy.min_deposit_sats),
+        gross_amount: U256::ZERO,
+        fee_bps: U256::from(policy.fee_bps),
+        flat_fee_sats: U256::from(policy.flat_fee_sats),
+        checkpoint_hash: chain_summary.map_or(FixedBytes::ZERO, |summary| {
+            block_hash_to_bytes32(summary.anchor_hash)
+        }),
+        refund_btc_address: refund_btc_address.to_string(),
+        witness_verified: false,
+        dest_chain_id: U256::ZERO,
+        memo_nonce: U256::ZERO,
+        vault_output_count: U256::ZERO,
+        tip_timestamp: chain_summary
+            .map_or(U256::ZERO, |summary| U256::from(summary.tip_timestamp)),
+        reference_time: U256::from(bundle.reference_time.unwrap_or(0)),
+        request_id: U256::ZERO,
+        vault_output_indices: Vec::new(),
+        network_magic: FixedBytes::from(
+            parse_chain(bundle.network.as_deref()).map_or([0; 4], |chain| chain.magic),
+        ),
+        vault_script_hash: parse_vault_key(bundle.vault_public_key.as_deref())
+            .map_or(FixedBytes::ZERO, |group_key| vault_script_hash(&group_key)),
+    });
 
-    Ok(())
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Mint circuit completed and rejection committed.");
 }
 
 /// zkVM entrypoint: verifies a Bitcoin deposit and prepares public values for minting.
@@ -169,62 +82,272 @@ pub fn main() {
     // Read input bundle from zkVM host
     let bundle: BundleInfoStruct = sp1_zkvm::io::read();
 
-    // === Parse transaction and extract outputs ===
-    let tx_bytes = hex::decode(&bundle.bit_tx_info.raw_tx_hex).unwrap();
-    let tx: Transaction = deserialize(&tx_bytes).unwrap();
+    // === Parse transaction ===
+    println!("cycle-tracker-report-start: parse_tx");
+    let tx = decode_transaction(&bundle.bit_tx_info.raw_tx_hex);
+    println!("cycle-tracker-report-end: parse_tx");
+    let tx = match tx {
+        Ok(tx) => tx,
+        Err(e) => {
+            commit_rejection(
+                Txid::all_zeros(),
+                MintRejectReason::InvalidTransaction,
+                &e.to_string(),
+                &bundle,
+                None,
+                "",
+            );
+            return;
+        }
+    };
     let txid = tx.compute_txid();
     println!("Transaction ID: {}", txid);
 
-    // Extract total deposited amount and OP_RETURN memo (Ethereum address)
-    let (total_sats_to_me, memo_bytes) =
-        process_transaction_outputs(&bundle.bit_tx_info.raw_tx_hex, BRIDGE_ADDRESS, NETWORK_TYPE)
-            .unwrap();
-    println!(
-        "Total satoshis sent to {}: {}",
-        BRIDGE_ADDRESS, total_sats_to_me
+    // The chain is committed, so one ELF and vkey serve every chain and network
+    let chain = match parse_chain(bundle.network.as_deref()) {
+        Ok(chain) => chain,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                MintRejectReason::UnknownChain,
+                &e.to_string(),
+                &bundle,
+                None,
+                "",
+            );
+            return;
+        }
+    };
+    println!("Source chain: {}", chain);
+
+    // === Resolve refund address from the first input's prevout ===
+    let refund_btc_address = match bundle.prevout_tx_info.as_ref() {
+        Some(prevout_tx_info) => decode_transaction(&prevout_tx_info.raw_tx_hex)
+            .and_then(|prev_tx| extract_refund_address(&tx, &prev_tx, &chain))
+            .unwrap_or_else(|e| {
+                println!("Refund address not proven: {}", e);
+                String::new()
+            }),
+        None => String::new(),
+    };
+    println!("Refund address: {:?}", refund_btc_address);
+
+    // === Verify block chain ===
+    println!("cycle-tracker-report-start: verify_chain");
+    let chain_result = verify_chain_with_crate(
+        &bundle.chains,
+        DEFAULT_CONFIRMATIONS,
+        bundle.retarget_info.as_ref(),
+        bundle.checkpoint_hash.as_deref(),
+        bundle.reference_time,
+        &chain,
     );
+    println!("cycle-tracker-report-end: verify_chain");
+    let chain_summary = match chain_result {
+        Ok(summary) => {
+            println!(
+                "Chain verified successfully (minimum difficulty: {}, cumulative work: {:#x})",
+                summary.min_difficulty, summary.total_work
+            );
+            summary
+        }
+        Err(e) => {
+            commit_rejection(
+                txid,
+                MintRejectReason::InvalidChain,
+                &e.to_string(),
+                &bundle,
+                None,
+                &refund_btc_address,
+            );
+            return;
+        }
+    };
 
-    // === Parse OP_RETURN memo as Ethereum address ===
-    let deposit_eth_address: String = if let Some(bytes) = memo_bytes {
-        match String::from_utf8(bytes.clone()) {
-            Ok(memo_str) => {
-                println!("Found OP_RETURN memo (UTF-8): {}", memo_str);
-                memo_str
-            }
-            Err(_) => {
-                println!("Found OP_RETURN memo (Hex): {}", hex::encode(bytes));
-                panic!("Memo is not valid UTF-8");
+    // === Resolve the vault group key the bundle names ===
+    // The contract refuses any vault script hash but its own, so no other key mints
+    let group_key = match parse_vault_key(bundle.vault_public_key.as_deref()) {
+        Ok(group_key) => group_key,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                MintRejectReason::InvalidVaultKey,
+                &e,
+                &bundle,
+                Some(&chain_summary),
+                &refund_btc_address,
+            );
+            return;
+        }
+    };
+
+    // Deposit scriptPubKey is derived once; outputs are matched by byte comparison.
+    // Per-request deposits pay the group key tweaked with their request id instead of the
+    // vault, so the supplied script must match that derivation. Checked once the
+    // chain is verified, so its rejection commits the chain too.
+    let (deposit_spk, request_id) = match bundle.deposit_request.as_ref() {
+        Some(request) => {
+            let expected_spk = request_deposit_script_pubkey(&group_key, request.request_id);
+            if expected_spk.to_hex_string() != request.script_pubkey.to_lowercase() {
+                commit_rejection(
+                    txid,
+                    MintRejectReason::DepositScriptMismatch,
+                    &format!(
+                        "Script {} is not the deposit script {} of request {}",
+                        request.script_pubkey,
+                        expected_spk.to_hex_string(),
+                        request.request_id
+                    ),
+                    &bundle,
+                    Some(&chain_summary),
+                    &refund_btc_address,
+                );
+                return;
             }
+            println!(
+                "Verifying deposit to request {} script {}",
+                request.request_id,
+                expected_spk.to_hex_string()
+            );
+            (expected_spk, Some(request.request_id))
         }
-    } else {
-        println!("No valid OP_RETURN memo found in this transaction.");
-        panic!("No OP_RETURN memo found");
+        // Memo deposits pay the vault itself, whose script is the same on every network
+        None => (vault_script_pubkey(&group_key), None),
     };
 
-    // === Verify Merkle inclusion ===
-    match verify_tx_inclusion_str(
-        txid.to_string().as_str(),
+    // === Check the transaction was final in its block ===
+    if let Err(e) =
+        check_transaction_finality(&tx, bundle.block_height, bundle.chains.blocks[0].timestamp)
+    {
+        commit_rejection(
+            txid,
+            MintRejectReason::NonFinalTransaction,
+            &e.to_string(),
+            &bundle,
+            Some(&chain_summary),
+            &refund_btc_address,
+        );
+        return;
+    }
+
+    // === Verify deposit outputs, OP_RETURN recipient and Merkle inclusion ===
+    println!("cycle-tracker-report-start: verify_deposit");
+    let outcome = verify_deposit(
+        &tx,
         &bundle.merkle_proof,
         &bundle.chains.blocks[0].merkle_root,
-    ) {
-        Ok(true) => println!("Transaction inclusion verified successfully"),
-        Ok(false) => panic!("Merkle root mismatch"),
-        Err(e) => panic!("Verification failed: {}", e),
+        bundle.tx_count,
+        &deposit_spk,
+        request_id,
+        &bundle.mint_policy,
+    );
+    println!("cycle-tracker-report-end: verify_deposit");
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            commit_rejection(
+                txid,
+                MintRejectReason::InvalidDeposit,
+                &e.to_string(),
+                &bundle,
+                Some(&chain_summary),
+                &refund_btc_address,
+            );
+            return;
+        }
+    };
+    if !outcome.is_valid() {
+        commit_rejection(
+            txid,
+            outcome.reject_reason,
+            &outcome.reject_detail,
+            &bundle,
+            Some(&chain_summary),
+            &refund_btc_address,
+        );
+        return;
     }
+    println!(
+        "Total satoshis sent to script {}: {} across non-dust outputs {:?}",
+        deposit_spk.to_hex_string(),
+        outcome.amount_sats,
+        outcome.output_indices
+    );
+    println!(
+        "Net satoshis to mint after fees: {}",
+        outcome.net_amount_sats
+    );
+    println!(
+        "Found OP_RETURN recipient: {} (chain id: {}, nonce: {}, request id: {})",
+        outcome.recipient, outcome.chain_id, outcome.nonce, outcome.request_id
+    );
+    println!("Transaction inclusion verified successfully");
 
-    // === Verify block chain ===
-    match verify_chain_with_crate(&bundle.chains) {
-        Ok(_) => println!("Chain verified successfully"),
-        Err(e) => panic!("Chain verification failed: {}", e),
-    }
+    // === Optionally verify witness data against the coinbase witness commitment ===
+    let witness_verified = match bundle.witness_proof.as_ref() {
+        Some(witness_proof) => {
+            let result = decode_transaction(&witness_proof.coinbase_tx_info.raw_tx_hex).and_then(
+                |coinbase_tx| {
+                    verify_witness_commitment(
+                        &tx,
+                        bundle.merkle_proof.pos,
+                        &witness_proof.wtxid_merkle_proof,
+                        &coinbase_tx,
+                        &witness_proof.coinbase_merkle_proof,
+                        &bundle.chains.blocks[0].merkle_root,
+                    )
+                },
+            );
+            let detail = match result {
+                Ok(true) => None,
+                Ok(false) => Some("Witness commitment mismatch".to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(detail) = detail {
+                commit_rejection(
+                    txid,
+                    MintRejectReason::WitnessCommitmentMismatch,
+                    &detail,
+                    &bundle,
+                    Some(&chain_summary),
+                    &refund_btc_address,
+                );
+                return;
+            }
+            println!("Witness commitment verified successfully");
+            true
+        }
+        None => false,
+    };
 
     // === Prepare and commit public values ===
-    let bytes = ZkpMintPublicValuesStruct::abi_encode(&ZkpMintPublicValuesStruct {
-        tx_id: txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap(),
-        depositer_address: Address::parse_checksummed(deposit_eth_address, None).unwrap(),
-        amount: U256::from(total_sats_to_me),
+    println!("cycle-tracker-report-start: abi_encode");
+    let bytes = ZkpMintPublicValuesStruct::abi_encode_params(&ZkpMintPublicValuesStruct {
+        tx_id: txid_to_bytes32(txid),
+        depositer_address: outcome.recipient,
+        amount: U256::from(outcome.net_amount_sats),
         is_valid: true,
+        min_difficulty: U256::from(chain_summary.min_difficulty),
+        chain_work: U256::from_be_bytes(chain_summary.total_work.to_be_bytes()),
+        reject_reason: MintRejectReason::None as u8,
+        min_deposit_sats: U256::from(bundle.mint_policy.min_deposit_sats),
+        gross_amount: U256::from(outcome.amount_sats),
+        fee_bps: U256::from(bundle.mint_policy.fee_bps),
+        flat_fee_sats: U256::from(bundle.mint_policy.flat_fee_sats),
+        checkpoint_hash: block_hash_to_bytes32(chain_summary.anchor_hash),
+        refund_btc_address,
+        witness_verified,
+        dest_chain_id: U256::from(outcome.chain_id),
+        memo_nonce: U256::from(outcome.nonce),
+        vault_output_count: U256::from(outcome.output_indices.len()),
+        tip_timestamp: U256::from(chain_summary.tip_timestamp),
+        reference_time: U256::from(bundle.reference_time.unwrap_or(0)),
+        request_id: U256::from(outcome.request_id),
+        vault_output_indices: outcome.output_indices,
+        network_magic: FixedBytes::from(chain.magic),
+        vault_script_hash: vault_script_hash(&group_key),
     });
+    println!("cycle-tracker-report-end: abi_encode");
 
     sp1_zkvm::io::commit_slice(&bytes);
     println!("Mint circuit completed and public values committed.");
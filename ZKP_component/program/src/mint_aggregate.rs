@@ -0,0 +1,25 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_sol_types::SolType;
+use lib_struct::aggregation::verify_and_fold;
+use lib_struct::{AggregationInputStruct, ZkpMintAggregationPublicValuesStruct};
+
+/// zkVM entrypoint: verifies N compressed mint proofs and commits a Merkle root over their
+/// public values, so a single on-chain verification covers every deposit in the set.
+pub fn main() {
+    // Read the aggregation input from zkVM host
+    let input: AggregationInputStruct = sp1_zkvm::io::read();
+    println!("Aggregating {} mint proofs", input.public_values.len());
+
+    // === Verify each mint proof against the shared verification key ===
+    let aggregate = verify_and_fold(&input, sp1_zkvm::lib::verify::verify_sp1_proof);
+    println!("Public values root: {}", aggregate.public_values_root);
+
+    // === Prepare and commit public values ===
+    let public_values: ZkpMintAggregationPublicValuesStruct = aggregate.into();
+    let bytes = ZkpMintAggregationPublicValuesStruct::abi_encode(&public_values);
+
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Mint aggregation circuit completed and public values committed.");
+}
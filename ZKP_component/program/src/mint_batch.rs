@@ -0,0 +1,199 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_sol_types::SolType;
+use lib_struct::chain::parse_chain;
+use lib_struct::verify::{
+    check_transaction_finality, decode_transaction, parse_vault_key, vault_script_hash,
+    vault_script_pubkey, verify_chain_with_crate, verify_deposit, DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{
+    MintBatchBundleStruct, MintDepositStruct, MintRejectReason, ZkpMintBatchPublicValuesStruct,
+};
+
+/// zkVM entrypoint: verifies a batch of Bitcoin deposits sharing one confirmation chain and
+/// commits one (txid, recipient, amount) entry per deposit.
+pub fn main() {
+    // Read batch bundle from zkVM host
+    let bundle: MintBatchBundleStruct = sp1_zkvm::io::read();
+    println!("Batch size: {}", bundle.deposits.len());
+
+    // The chain is committed, so one ELF and vkey serve every chain and network. An unknown
+    // one rejects every deposit and commits a zero network magic.
+    let chain = parse_chain(bundle.network.as_deref());
+    match &chain {
+        Ok(chain) => println!("Source chain: {}", chain),
+        Err(e) => println!("Unknown source chain: {}", e),
+    }
+
+    // Vault scriptPubKey is derived once; outputs are matched by byte comparison. Without a
+    // valid group key every deposit is rejected, and a zero vault script hash is committed.
+    let vault_key = parse_vault_key(bundle.vault_public_key.as_deref());
+    let vault_spk = vault_key.as_ref().map(vault_script_pubkey);
+    if let Err(e) = &vault_key {
+        println!("Vault key rejected: {}", e);
+    }
+
+    // === Verify the shared block chain ===
+    let chain_result = match &chain {
+        Ok(chain) => verify_chain_with_crate(
+            &bundle.chains,
+            DEFAULT_CONFIRMATIONS,
+            bundle.retarget_info.as_ref(),
+            bundle.checkpoint_hash.as_deref(),
+            bundle.reference_time,
+            chain,
+        )
+        .map_err(|e| (MintRejectReason::InvalidChain, e.to_string())),
+        Err(e) => Err((MintRejectReason::UnknownChain, e.to_string())),
+    };
+    match &chain_result {
+        Ok(summary) => println!(
+            "Chain verified successfully (minimum difficulty: {}, cumulative work: {:#x})",
+            summary.min_difficulty, summary.total_work
+        ),
+        Err((reason, detail)) => println!("Chain verification failed ({:?}): {}", reason, detail),
+    }
+
+    // === Verify each deposit against the first block of the chain ===
+    let mut deposits = Vec::with_capacity(bundle.deposits.len());
+    let mut seen_txids = Vec::with_capacity(bundle.deposits.len());
+    for (i, deposit) in bundle.deposits.iter().enumerate() {
+        // An undecodable deposit has no txid, so its entry commits a zero one
+        let tx = match decode_transaction(&deposit.bit_tx_info.raw_tx_hex) {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Deposit #{} rejected: {}", i, e);
+                deposits.push(rejected_entry(
+                    FixedBytes::ZERO,
+                    MintRejectReason::InvalidTransaction,
+                ));
+                continue;
+            }
+        };
+        let txid = tx.compute_txid();
+        let tx_id = txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap();
+
+        // A transaction may only be minted once per batch
+        if seen_txids.contains(&txid) {
+            println!("Deposit #{} ({}) rejected: duplicate in batch", i, txid);
+            deposits.push(rejected_entry(tx_id, MintRejectReason::DuplicateDeposit));
+            continue;
+        }
+        seen_txids.push(txid);
+
+        if let Err((reason, _)) = &chain_result {
+            deposits.push(rejected_entry(tx_id, *reason));
+            continue;
+        }
+        let Ok(vault_spk) = &vault_spk else {
+            deposits.push(rejected_entry(tx_id, MintRejectReason::InvalidVaultKey));
+            continue;
+        };
+
+        if let Err(e) =
+            check_transaction_finality(&tx, bundle.block_height, bundle.chains.blocks[0].timestamp)
+        {
+            println!("Deposit #{} ({}) rejected: {}", i, txid, e);
+            deposits.push(rejected_entry(tx_id, MintRejectReason::NonFinalTransaction));
+            continue;
+        }
+
+        let outcome = verify_deposit(
+            &tx,
+            &deposit.merkle_proof,
+            &bundle.chains.blocks[0].merkle_root,
+            bundle.tx_count,
+            vault_spk,
+            None,
+            &bundle.mint_policy,
+        );
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("Deposit #{} ({}) rejected: {}", i, txid, e);
+                deposits.push(rejected_entry(tx_id, MintRejectReason::InvalidDeposit));
+                continue;
+            }
+        };
+        if !outcome.is_valid() {
+            println!(
+                "Deposit #{} ({}) rejected ({:?}): {}",
+                i, txid, outcome.reject_reason, outcome.reject_detail
+            );
+            deposits.push(rejected_entry(tx_id, outcome.reject_reason));
+            continue;
+        }
+
+        println!(
+            "Deposit #{} ({}): {} satoshis ({} net of fees) to {}",
+            i, txid, outcome.amount_sats, outcome.net_amount_sats, outcome.recipient
+        );
+        deposits.push(MintDepositStruct {
+            tx_id,
+            depositer_address: outcome.recipient,
+            amount: U256::from(outcome.net_amount_sats),
+            is_valid: true,
+            reject_reason: MintRejectReason::None as u8,
+            gross_amount: U256::from(outcome.amount_sats),
+            dest_chain_id: U256::from(outcome.chain_id),
+            memo_nonce: U256::from(outcome.nonce),
+            vault_output_count: U256::from(outcome.output_indices.len()),
+            request_id: U256::from(outcome.request_id),
+            vault_output_indices: outcome.output_indices,
+        });
+    }
+
+    // === Prepare and commit public values ===
+    let (min_difficulty, chain_work, checkpoint_hash, tip_timestamp) = match chain_result {
+        Ok(summary) => (
+            U256::from(summary.min_difficulty),
+            U256::from_be_bytes(summary.total_work.to_be_bytes()),
+            summary
+                .anchor_hash
+                .to_string()
+                .as_str()
+                .parse::<FixedBytes<32>>()
+                .unwrap(),
+            U256::from(summary.tip_timestamp),
+        ),
+        Err(_) => (U256::ZERO, U256::ZERO, FixedBytes::ZERO, U256::ZERO),
+    };
+    let bytes =
+        ZkpMintBatchPublicValuesStruct::abi_encode_params(&ZkpMintBatchPublicValuesStruct {
+            deposits,
+            min_difficulty,
+            chain_work,
+            min_deposit_sats: U256::from(bundle.mint_policy.min_deposit_sats),
+            fee_bps: U256::from(bundle.mint_policy.fee_bps),
+            flat_fee_sats: U256::from(bundle.mint_policy.flat_fee_sats),
+            checkpoint_hash,
+            tip_timestamp,
+            reference_time: U256::from(bundle.reference_time.unwrap_or(0)),
+            network_magic: FixedBytes::from(chain.map_or([0; 4], |chain| chain.magic)),
+            vault_script_hash: vault_key
+                .as_ref()
+                .map_or(FixedBytes::ZERO, vault_script_hash),
+        });
+
+    sp1_zkvm::io::commit_slice(&bytes);
+    println!("Mint batch circuit completed and public values committed.");
+}
+
+/// Builds the committed entry for a rejected deposit.
+fn rejected_entry(tx_id: FixedBytes<32>, reason: MintRejectReason) -> MintDepositStruct {
+    MintDepositStruct {
+        tx_id,
+        depositer_address: Address::ZERO,
+        amount: U256::ZERO,
+        is_valid: false,
+        reject_reason: reason as u8,
+        gross_amount: U256::ZERO,
+        dest_chain_id: U256::ZERO,
+        memo_nonce: U256::ZERO,
+        vault_output_count: U256::ZERO,
+        request_id: U256::ZERO,
+        vault_output_indices: Vec::new(),
+    }
+}
@@ -0,0 +1,153 @@
+//! Operator signatures over proof fixtures. A prover given `--operator-key` signs the digest
+//! of each fixture it writes; `submit --authorized-prover` refuses a fixture that is unsigned
+//! or signed by any other key, so the relayer only settles artifacts of known provers.
+//!
+//! The TSS signer set co-signs a fixture's statement (vkey and public values hash) before it
+//! is submitted, through `coordinator attest`. That control is independent of the SNARK and of
+//! the prover operators: a fixture the signers were never asked about is refused by `submit
+//! --tss-group-key`.
+
+use alloy::primitives::{Address, Signature};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use bitcoin::key::TapTweak;
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+use lib_struct::{FixtureAttestation, TssAttestation, ZkpProofFixture};
+use std::error::Error;
+use std::process::Command;
+
+/// Parses the operator key given as 0x-prefixed or bare hex.
+pub fn operator_signer(key: &str) -> Result<PrivateKeySigner, Box<dyn Error>> {
+    key.parse()
+        .map_err(|e| format!("invalid operator key: {}", e).into())
+}
+
+/// Signs the fixture's attestation digest, replacing an earlier attestation.
+pub fn sign_fixture(
+    fixture: &mut ZkpProofFixture,
+    signer: &PrivateKeySigner,
+) -> Result<(), Box<dyn Error>> {
+    let digest = fixture.attestation_digest()?;
+    let signature = signer.sign_message_sync(digest.as_slice())?;
+    fixture.attestation = Some(FixtureAttestation {
+        signer: signer.address().to_string(),
+        signature: format!("0x{}", hex::encode(signature.as_bytes())),
+    });
+    Ok(())
+}
+
+/// Recovers the operator that signed the fixture, checking it is the declared signer.
+pub fn fixture_signer(fixture: &ZkpProofFixture) -> Result<Address, Box<dyn Error>> {
+    let attestation = fixture
+        .attestation
+        .as_ref()
+        .ok_or("the fixture is not signed by an operator")?;
+    let signature = Signature::from_raw(&hex::decode(
+        attestation.signature.trim_start_matches("0x"),
+    )?)?;
+    let signer = signature.recover_address_from_msg(fixture.attestation_digest()?.as_slice())?;
+    if attestation.signer.parse::<Address>()? != signer {
+        // A fixture edited after signing recovers some other address
+        return Err(format!(
+            "the signature does not match the fixture or its signer {} (recovers {})",
+            attestation.signer, signer
+        )
+        .into());
+    }
+    Ok(signer)
+}
+
+/// Checks that the fixture is signed by one of the authorized operators.
+pub fn verify_fixture(
+    fixture: &ZkpProofFixture,
+    authorized: &[Address],
+) -> Result<Address, Box<dyn Error>> {
+    let signer = fixture_signer(fixture)?;
+    if !authorized.contains(&signer) {
+        return Err(format!("{} is not an authorized prover", signer).into());
+    }
+    Ok(signer)
+}
+
+/// Group key whose signers attested the fixture, checking the signature matches its statement.
+pub fn tss_attester(fixture: &ZkpProofFixture) -> Result<XOnlyPublicKey, Box<dyn Error>> {
+    let attestation = fixture
+        .tss_attestation
+        .as_ref()
+        .ok_or("the fixture is not attested by the TSS signer set")?;
+    let group_key: XOnlyPublicKey = attestation
+        .group_key
+        .parse()
+        .map_err(|e| format!("invalid attestation group key: {}", e))?;
+    let signature = schnorr::Signature::from_slice(&hex::decode(&attestation.signature)?)
+        .map_err(|e| format!("invalid TSS signature: {}", e))?;
+    let secp = Secp256k1::verification_only();
+    // The signers sign with the key-path-only output key, as for a vault spend
+    let (output_key, _) = group_key.tap_tweak(&secp, None);
+    secp.verify_schnorr(
+        &signature,
+        &Message::from_digest(fixture.tss_attestation_digest()?),
+        &output_key.to_inner(),
+    )
+    .map_err(|_| "the TSS signature does not match the fixture's vkey and public values")?;
+    Ok(group_key)
+}
+
+/// Checks that the fixture carries a TSS attestation by `group_key` matching its statement.
+pub fn verify_tss_attestation(
+    fixture: &ZkpProofFixture,
+    group_key: &XOnlyPublicKey,
+) -> Result<(), Box<dyn Error>> {
+    let attested_by = tss_attester(fixture)?;
+    if attested_by != *group_key {
+        return Err(format!(
+            "the fixture is attested by group key {}, not {}",
+            attested_by, group_key
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Has the TSS signer set co-sign the fixture: runs `command` with `--vkey-hex <vkey>
+/// --public-values-hash-hex <keccak256 of the public values>` appended, which must print the
+/// group signature as `coordinator attest` does, and attaches it once it verifies against
+/// `group_key`. The command gets the trace context and `env` in its environment.
+pub fn request_tss_attestation(
+    fixture: &mut ZkpProofFixture,
+    command: &str,
+    group_key: &XOnlyPublicKey,
+    env: &[(String, String)],
+) -> Result<(), Box<dyn Error>> {
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or("the attestation command is empty")?;
+    let (vkey, public_values_hash) = fixture.tss_attestation_statement()?;
+    let output = Command::new(program)
+        .args(words)
+        .arg("--vkey-hex")
+        .arg(hex::encode(vkey))
+        .arg("--public-values-hash-hex")
+        .arg(hex::encode(public_values_hash))
+        .envs(crate::telemetry::trace_env())
+        .envs(env.iter().cloned())
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Attestation command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    let signature = String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .ok_or("the attestation command printed no signature")?
+        .to_string();
+    fixture.tss_attestation = Some(TssAttestation {
+        group_key: group_key.to_string(),
+        signature,
+    });
+    verify_tss_attestation(fixture, group_key)
+}
@@ -0,0 +1,189 @@
+//! Aggregates several mint or burn proofs into one EVM-verifiable proof, so the on-chain
+//! verification cost stays constant regardless of how many deposits or redemptions were
+//! processed.
+
+// Usage Example:
+// cargo run --release --bin aggregate -- --system groth16 --input-json ./a.json --input-json ./b.json
+// cargo run --release --bin aggregate -- --execute --input-json ./a.json --input-json ./b.json
+// cargo run --release --bin aggregate -- --circuit burn --input-json ./a.json --input-json ./b.json
+// OPERATOR_PRIVATE_KEY=0x... cargo run --release --bin aggregate -- --input-json ./a.json --input-json ./b.json
+use alloy_primitives::B256;
+use bitcoin_verify_script::attestation::{operator_signer, sign_fixture};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::{
+    print_burn_aggregation_public_values, print_mint_aggregation_public_values,
+};
+use clap::{Parser, ValueEnum};
+use lib_struct::aggregation::{public_values_leaf, public_values_proof, vkey_bytes32};
+use lib_struct::{AggregationInputStruct, BundleInfoStruct, ZkpProofFixture};
+use sp1_sdk::{include_elf, HashableKey, ProverClient, SP1Proof, SP1Stdin};
+use std::path::PathBuf;
+
+pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("mint_aggregate_circuit");
+pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("burn_aggregate_circuit");
+
+/// Enum representing the available proof systems
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum ProofSystem {
+    Plonk,
+    Groth16,
+}
+
+/// Enum representing the circuits whose proofs can be aggregated
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    Burn,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Execute the aggregation program instead of proving it.
+    #[clap(long)]
+    execute: bool,
+    #[clap(long, value_enum, env = "PROOF_SYSTEM", default_value = "groth16")]
+    system: ProofSystem,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
+    circuit: CircuitType,
+    /// Input bundles, one per deposit or payout; repeat the flag for each file.
+    #[clap(long, required = true)]
+    input_json: Vec<PathBuf>,
+    /// Directory the fixture is written to; defaults to `../contracts/src/fixtures`.
+    #[clap(long, env = "FIXTURE_DIR")]
+    output: Option<PathBuf>,
+    /// Operator key that signs the fixture, so the relayer can tell it came from an authorized
+    /// prover.
+    #[clap(long, env = "OPERATOR_PRIVATE_KEY", hide_env_values = true)]
+    operator_key: Option<String>,
+}
+
+fn main() {
+    sp1_sdk::utils::setup_logger();
+    apply_config_file();
+    dotenv::dotenv().ok();
+
+    let args = Args::parse();
+    let client = ProverClient::from_env();
+    let operator = args
+        .operator_key
+        .as_deref()
+        .map(|key| operator_signer(key).expect("failed to load the operator key"));
+
+    // Select the leaf circuit, the aggregation circuit and the fixture name
+    let (leaf_elf, aggregate_elf, name) = match args.circuit {
+        CircuitType::Mint => (MINT_CIRCUIT_ELF, MINT_AGGREGATE_CIRCUIT_ELF, "mint"),
+        CircuitType::Burn => (BURN_CIRCUIT_ELF, BURN_AGGREGATE_CIRCUIT_ELF, "burn"),
+    };
+    let (leaf_pk, leaf_vk) = client.setup(leaf_elf);
+    // The aggregation circuit commits the key it checked against in the `bytes32` form the
+    // contract stores, re-encoded from the words it verifies with
+    assert_eq!(
+        vkey_bytes32(&leaf_vk.hash_u32()),
+        B256::from(leaf_vk.bytes32_raw()),
+        "{} vkey words do not encode to its bytes32",
+        name
+    );
+    let (aggregate_pk, aggregate_vk) = client.setup(aggregate_elf);
+
+    // Generate a compressed proof per input bundle
+    let mut leaf_proofs = Vec::with_capacity(args.input_json.len());
+    for path in &args.input_json {
+        let file = std::fs::File::open(path).expect("failed to open input JSON");
+        let bundle: BundleInfoStruct =
+            serde_json::from_reader(file).expect("failed to parse input JSON");
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&bundle);
+        let proof = client
+            .prove(&leaf_pk, &stdin)
+            .compressed()
+            .run()
+            .unwrap_or_else(|e| panic!("failed to generate {} proof: {}", name, e));
+        println!("Generated compressed {} proof for {}", name, path.display());
+        leaf_proofs.push(proof);
+    }
+
+    // Feed the public values and the proofs to the aggregation program
+    let public_values: Vec<Vec<u8>> = leaf_proofs
+        .iter()
+        .map(|proof| proof.public_values.to_vec())
+        .collect();
+    let leaves: Vec<_> = public_values
+        .iter()
+        .map(|bytes| public_values_leaf(bytes))
+        .collect();
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&AggregationInputStruct {
+        vkey: leaf_vk.hash_u32(),
+        public_values,
+    });
+    for proof in leaf_proofs {
+        let SP1Proof::Compressed(proof) = proof.proof else {
+            panic!("{} proof is not compressed", name);
+        };
+        stdin.write_proof(*proof, leaf_vk.vk.clone());
+    }
+
+    // Each proof is later checked against the committed root with its leaf and sibling path
+    for (i, leaf) in leaves.iter().enumerate() {
+        println!("{} #{} leaf: {}", name, i, leaf);
+        println!(
+            "{} #{} proof: {:?}",
+            name,
+            i,
+            public_values_proof(&leaves, i)
+        );
+    }
+
+    if args.execute {
+        let (output, report) = client.execute(aggregate_elf, &stdin).run().unwrap();
+        let printed = match args.circuit {
+            CircuitType::Mint => print_mint_aggregation_public_values(output.as_slice()),
+            CircuitType::Burn => print_burn_aggregation_public_values(output.as_slice()),
+        };
+        if let Err(e) = printed {
+            eprintln!("Failed to decode the aggregation output: {}", e);
+            std::process::exit(1);
+        }
+        println!("Number of cycles: {:?}", report.total_instruction_count());
+        println!("Completed execution successfully!");
+        return;
+    }
+
+    let proof = match args.system {
+        ProofSystem::Plonk => client.prove(&aggregate_pk, &stdin).plonk().run(),
+        ProofSystem::Groth16 => client.prove(&aggregate_pk, &stdin).groth16().run(),
+    }
+    .expect("failed to generate aggregation proof");
+
+    let mut fixture = ZkpProofFixture {
+        vkey: aggregate_vk.bytes32().to_string(),
+        public_value: format!("0x{}", hex::encode(proof.public_values.as_slice())),
+        proof: format!("0x{}", hex::encode(proof.bytes())),
+        attestation: None,
+        tss_attestation: None,
+    };
+    println!("Verification Key: {}", fixture.vkey);
+    println!("Public Values: {}", fixture.public_value);
+    println!("Proof Bytes: {}", fixture.proof);
+    if let Some(operator) = &operator {
+        sign_fixture(&mut fixture, operator).expect("failed to sign the fixture");
+        println!("Signed by operator: {}", operator.address());
+    }
+
+    let fixture_path = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../contracts/src/fixtures")
+    });
+    std::fs::create_dir_all(&fixture_path).expect("failed to create fixture path");
+    std::fs::write(
+        fixture_path
+            .join(format!("{:?}-fixture_{}_aggregate.json", args.system, name).to_lowercase()),
+        serde_json::to_string_pretty(&fixture).unwrap(),
+    )
+    .expect("failed to write fixture");
+}
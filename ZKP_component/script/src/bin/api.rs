@@ -0,0 +1,719 @@
+//! User-facing swap API: a frontend registers a mint swap and gets the deposit to make, looks
+//! up the swap's progress, and registers its burn for the BTC payout to be tracked. Swaps are
+//! kept in the bridge-core swap database, which the services moving them forward update.
+
+// Usage Example:
+// cargo run --release --bin api -- --listen 127.0.0.1:3100 --db ./swap-db
+// curl -X POST -H 'Content-Type: application/json' --data '{"recipient":"0x..."}' http://127.0.0.1:3100/swaps
+// curl http://127.0.0.1:3100/swaps/1
+// curl -X POST -H 'Content-Type: application/json' --data '{"burn_id":7,"btc_address":"tb1q...","amount_sats":50000}' http://127.0.0.1:3100/redemptions
+// curl http://127.0.0.1:3100/redemptions/7
+// curl 'http://127.0.0.1:3100/quote?amount_sats=50000'
+// curl http://127.0.0.1:3100/deposit-addresses
+// curl -X POST -H 'Content-Type: application/json' --data '{"orphaned":[{"hash":"...","txids":["..."]}]}' http://127.0.0.1:3100/reorgs
+// curl -X POST -H 'Content-Type: application/json' --data '{"burn_id":7,"payout_txid":"...","job_id":3,"eth_tx":"0x..."}' http://127.0.0.1:3100/settlements
+// curl -X POST -H 'Content-Type: application/json' --data '{"deposit_txid":"...","request_id":1,"amount_sats":50000,"btc_address":"tb1q...","reason":"policy: amount cap"}' http://127.0.0.1:3100/refunds
+// cargo run --release --bin api -- --config ./bridge.toml --deposit-method address
+use alloy::primitives::Address;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::deposit_address::DepositAddress;
+use bitcoin_verify_script::fees::fetch_quote;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::{setup_logging, BitcoinNetwork, LogFormat};
+use bridge_core::store::SwapStore;
+use bridge_core::{next_state, Direction, Swap, SwapEvent, SwapState};
+use clap::{Parser, ValueEnum};
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::{encode_request_memo, DEFAULT_NETWORK};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Virtual size of a payout: one key-path vault input, the burner's output and the change.
+const PAYOUT_VBYTES: u64 = 58 + 43 + 43 + 10;
+
+/// How users are told to make their deposit.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum DepositMethod {
+    /// Pay the vault address with an OP_RETURN memo naming the recipient and the swap.
+    Memo,
+    /// Pay the swap's own address, the group key tweaked with the swap id; no memo needed.
+    Address,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(long, env = "API_LISTEN", default_value = "127.0.0.1:3100")]
+    listen: SocketAddr,
+    /// sled database of the swaps; without it, swaps are lost on exit.
+    #[clap(long, env = "SWAP_DB")]
+    db: Option<PathBuf>,
+    /// Vault address memo deposits pay; the mint circuit only accepts the vault of
+    /// `--group-key`, which it is by default, encoded for `--network`.
+    #[clap(long, env = "BRIDGE_ADDRESS")]
+    vault_address: Option<String>,
+    /// Group key of the vault and the per-request deposit addresses.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    #[clap(long, value_enum, env = "DEPOSIT_METHOD", default_value = "memo")]
+    deposit_method: DepositMethod,
+    /// EVM chain id the deposit memos name; the contract only mints deposits naming its own.
+    #[clap(long, env = "EVM_CHAIN_ID")]
+    evm_chain_id: u64,
+    /// Chain of the deposit and payout addresses, a Bitcoin network or Litecoin's.
+    #[clap(long, value_enum, env = "BITCOIN_NETWORK")]
+    network: Option<BitcoinNetwork>,
+    /// Base URL of the fee oracle (`fee-oracle`) quotes are priced with; `GET /quote` answers
+    /// `503` without it.
+    #[clap(long, env = "FEE_ORACLE_URL")]
+    fee_oracle: Option<String>,
+    /// Gas a mint settlement is quoted at: `verifyAndMint` with a Groth16 proof.
+    #[clap(long, default_value_t = 350_000)]
+    mint_gas: u64,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Deposit that funds a mint swap, as returned with it.
+#[derive(Serialize, Debug)]
+struct DepositInstructions {
+    /// Address to pay.
+    address: String,
+    /// OP_RETURN data the deposit must carry, hex-encoded; absent for a swap's own address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memo_hex: Option<String>,
+    /// Request id the deposit is credited to; the swap id.
+    request_id: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct SwapResponse {
+    swap: Swap,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deposit: Option<DepositInstructions>,
+}
+
+/// Deposit address of a swap waiting for its deposit, as listed for `watch`.
+#[derive(Serialize, Debug)]
+struct AllocatedAddress {
+    request_id: u64,
+    address: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateSwap {
+    /// Ethereum account the zkBTC is minted to.
+    recipient: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateRedemption {
+    /// Burn request id from the contract's `BurnInitiated` event.
+    burn_id: u64,
+    btc_address: String,
+    amount_sats: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct QuoteQuery {
+    /// Amount to redeem, for the BTC the burner receives after the payout fee.
+    amount_sats: Option<u64>,
+}
+
+/// Fees of a swap at the current rates, from the fee oracle.
+#[derive(Serialize, Default, Debug)]
+struct Quote {
+    /// Fee rate the payout is built with, in sat/vB: the oracle's 3-block estimate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    btc_fee_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payout_fee_sats: Option<u64>,
+    /// `amount_sats` less the payout fee.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    receive_sats: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mint_gas_cost_wei: Option<u128>,
+    /// Unix time of the oracle's quote.
+    fetched_at: u64,
+}
+
+/// A block that left the best chain, with the transactions it held.
+#[derive(Deserialize, Debug)]
+struct OrphanedBlock {
+    hash: String,
+    txids: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReorgReport {
+    orphaned: Vec<OrphanedBlock>,
+}
+
+/// A settlement confirmed on the EVM chain, with what its proof shows of the swap.
+#[derive(Deserialize, Debug)]
+struct SettlementReport {
+    /// Deposit of a mint settlement, the request id its memo names, and the amount it paid.
+    deposit_txid: Option<String>,
+    request_id: Option<u64>,
+    amount_sats: Option<u64>,
+    /// Burn request of a burn settlement, and its payout.
+    burn_id: Option<u64>,
+    payout_txid: Option<String>,
+    /// Proof job the settled proof came from, if known.
+    job_id: Option<u64>,
+    /// Settlement transaction.
+    eth_tx: String,
+}
+
+/// A rejected deposit being refunded, as `submit` reports it when it requests the refund and
+/// `payout-scheduler` once the refund is final.
+#[derive(Deserialize)]
+struct RefundReport {
+    /// The deposit, the request id its memo names, and the amount it paid.
+    deposit_txid: String,
+    request_id: Option<u64>,
+    amount_sats: Option<u64>,
+    /// Address the deposit is refunded to.
+    btc_address: String,
+    /// Why the deposit was rejected.
+    reason: String,
+    /// The refund transaction, once final.
+    refund_txid: Option<String>,
+}
+
+/// State shared by the request handlers.
+struct ApiState {
+    store: SwapStore,
+    fee_oracle: Option<String>,
+    mint_gas: u64,
+    vault_address: String,
+    group_key: XOnlyPublicKey,
+    deposit_method: DepositMethod,
+    evm_chain_id: u64,
+    chain: ChainParams,
+}
+
+impl ApiState {
+    fn deposit_instructions(&self, swap: &Swap) -> Result<DepositInstructions, Box<dyn Error>> {
+        let recipient = Address::from_str(swap.recipient.as_deref().unwrap_or_default())?;
+        Ok(match self.deposit_method {
+            DepositMethod::Memo => DepositInstructions {
+                address: self.vault_address.clone(),
+                memo_hex: Some(hex::encode(encode_request_memo(
+                    recipient,
+                    swap.id,
+                    self.evm_chain_id,
+                ))),
+                request_id: swap.id,
+            },
+            DepositMethod::Address => DepositInstructions {
+                address: DepositAddress::derive(&self.group_key, swap.id, &self.chain)?.address,
+                memo_hex: None,
+                request_id: swap.id,
+            },
+        })
+    }
+
+    /// The swap, with the deposit instructions of a mint swap.
+    fn response(&self, swap: Swap) -> Result<SwapResponse, Box<dyn Error>> {
+        let deposit = match swap.direction {
+            Direction::Mint => Some(self.deposit_instructions(&swap)?),
+            Direction::Burn => None,
+        };
+        Ok(SwapResponse { swap, deposit })
+    }
+}
+
+fn error_response(status: StatusCode, message: impl ToString) -> Response {
+    (status, Json(json!({ "error": message.to_string() }))).into_response()
+}
+
+fn internal_error(e: Box<dyn Error>) -> Response {
+    error!(error = %e, "Swap database request failed");
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, e)
+}
+
+/// `POST /swaps`: registers a mint swap and returns it with the deposit to make.
+async fn create_swap(State(state): State<Arc<ApiState>>, Json(body): Json<CreateSwap>) -> Response {
+    let recipient = match Address::from_str(&body.recipient) {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                format!("invalid recipient {}: {}", body.recipient, e),
+            )
+        }
+    };
+    let result = state
+        .store
+        .create_mint(recipient.to_string())
+        .and_then(|swap| state.response(swap));
+    match result {
+        Ok(response) => {
+            info!(swap = response.swap.id, %recipient, "Mint swap registered");
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `GET /swaps/{id}`: returns the swap with its state and transition history.
+async fn swap_status(State(state): State<Arc<ApiState>>, Path(id): Path<u64>) -> Response {
+    match state.store.get(id) {
+        Ok(Some(swap)) => match state.response(swap) {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => internal_error(e),
+        },
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("unknown swap {}", id)),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `POST /redemptions`: registers a burn so its payout can be tracked. Registering the same
+/// burn again returns its swap; the same burn with another address or amount is a conflict.
+async fn create_redemption(
+    State(state): State<Arc<ApiState>>,
+    Json(body): Json<CreateRedemption>,
+) -> Response {
+    if body.amount_sats == 0 {
+        return error_response(StatusCode::BAD_REQUEST, "amount_sats must be positive");
+    }
+    if let Err(e) = state.chain.address_script_pubkey(&body.btc_address) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "invalid {} address {}: {}",
+                state.chain, body.btc_address, e
+            ),
+        );
+    }
+    let swap =
+        match state
+            .store
+            .create_burn(body.burn_id, body.btc_address.clone(), body.amount_sats)
+        {
+            Ok(swap) => swap,
+            Err(e) => return internal_error(e),
+        };
+    if swap.btc_address.as_deref() != Some(body.btc_address.as_str())
+        || swap.amount_sats != Some(body.amount_sats)
+    {
+        return error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "burn {} is registered with another address or amount",
+                body.burn_id
+            ),
+        );
+    }
+    info!(
+        swap = swap.id,
+        burn_id = body.burn_id,
+        "Redemption registered"
+    );
+    match state.response(swap) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `GET /redemptions/{burn_id}`: returns the swap of a burn.
+async fn redemption_status(
+    State(state): State<Arc<ApiState>>,
+    Path(burn_id): Path<u64>,
+) -> Response {
+    match state.store.find_by_burn(burn_id) {
+        Ok(Some(swap)) => match state.response(swap) {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => internal_error(e),
+        },
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("no redemption for burn {}", burn_id),
+        ),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `GET /quote?amount_sats=`: what a swap costs at the current fees. A payout is priced as
+/// one key-path vault input paying the burner and the change, and a mint settlement at
+/// `--mint-gas`.
+async fn quote(State(state): State<Arc<ApiState>>, Query(query): Query<QuoteQuery>) -> Response {
+    let Some(url) = state.fee_oracle.clone() else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "no fee oracle configured");
+    };
+    // The oracle is read with a blocking client
+    let fees = tokio::task::spawn_blocking(move || {
+        fetch_quote(&reqwest::blocking::Client::new(), &url).map_err(|e| e.to_string())
+    })
+    .await;
+    let fees = match fees {
+        Ok(Ok(fees)) => fees,
+        Ok(Err(e)) => {
+            warn!(error = %e, "Failed to read the fee oracle");
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, e);
+        }
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    let mut quote = Quote {
+        fetched_at: fees.fetched_at,
+        ..Quote::default()
+    };
+    if let Some(btc) = fees.btc {
+        let payout_fee_sats = (btc.medium * PAYOUT_VBYTES as f64).ceil() as u64;
+        quote.btc_fee_rate = Some(btc.medium);
+        quote.payout_fee_sats = Some(payout_fee_sats);
+        quote.receive_sats = query
+            .amount_sats
+            .map(|amount| amount.saturating_sub(payout_fee_sats));
+    }
+    if let Some(evm) = fees.evm {
+        quote.mint_gas_cost_wei = Some(state.mint_gas as u128 * evm.max_fee_per_gas);
+    }
+    Json(quote).into_response()
+}
+
+/// `GET /deposit-addresses`: the deposit address of every mint swap still waiting for its
+/// deposit, for `watch` to look for; empty when deposits pay the vault with a memo.
+async fn deposit_addresses(State(state): State<Arc<ApiState>>) -> Response {
+    if state.deposit_method == DepositMethod::Memo {
+        return Json(Vec::<AllocatedAddress>::new()).into_response();
+    }
+    let result = state.store.swaps().and_then(|swaps| {
+        swaps
+            .into_iter()
+            .filter(|swap| swap.direction == Direction::Mint && swap.state == SwapState::Requested)
+            .map(|swap| {
+                let address = DepositAddress::derive(&state.group_key, swap.id, &state.chain)?;
+                Ok(AllocatedAddress {
+                    request_id: swap.id,
+                    address: address.address,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()
+    });
+    match result {
+        Ok(addresses) => Json(addresses).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Applies the reorganization to the swaps of the orphaned transactions. Returns the swaps
+/// rewound, and those already minted, settled or refunded on the orphaned transaction.
+fn rewind(store: &SwapStore, report: &ReorgReport) -> Result<(Vec<u64>, Vec<u64>), Box<dyn Error>> {
+    // Payouts are not indexed, so the burn swaps are looked up by payout txid once
+    let mut payouts: HashMap<String, Vec<Swap>> = HashMap::new();
+    for swap in store.swaps()? {
+        if swap.direction == Direction::Burn {
+            if let Some(txid) = swap.btc_txid.clone() {
+                payouts.entry(txid).or_default().push(swap);
+            }
+        }
+    }
+
+    let mut rewound = Vec::new();
+    let mut settled = Vec::new();
+    for block in &report.orphaned {
+        for txid in &block.txids {
+            let swaps = match store.find_by_deposit(txid)? {
+                Some(swap) => vec![swap],
+                None => payouts.remove(txid).unwrap_or_default(),
+            };
+            for swap in swaps {
+                let event = SwapEvent::ChainReorganized {
+                    block_hash: block.hash.clone(),
+                };
+                if next_state(swap.direction, swap.state, &event).is_some() {
+                    let (swap, _) = store.apply(swap.id, event)?;
+                    info!(swap = swap.id, %txid, block = block.hash, state = %swap.state, "Swap rewound");
+                    rewound.push(swap.id);
+                } else if matches!(
+                    swap.state,
+                    SwapState::Minted
+                        | SwapState::Settled
+                        | SwapState::Refunding
+                        | SwapState::Refunded
+                ) {
+                    settled.push(swap.id);
+                }
+            }
+        }
+    }
+    store.flush()?;
+    Ok((rewound, settled))
+}
+
+/// `POST /reorgs`: reported by `watch` when blocks leave the best chain after their
+/// transactions were counted final. A mint swap whose deposit was orphaned goes back to wait
+/// for it, a burn swap being proven waits for its payout to be final again. Returns the swaps
+/// rewound, and in `settled` those already minted, settled or refunded on an orphaned
+/// transaction, which need an operator.
+async fn report_reorg(
+    State(state): State<Arc<ApiState>>,
+    Json(report): Json<ReorgReport>,
+) -> Response {
+    match rewind(&state.store, &report) {
+        Ok((rewound, settled)) => {
+            if !settled.is_empty() {
+                error!(
+                    alert = true,
+                    ?settled,
+                    "Swaps were settled on transactions a reorganization orphaned"
+                );
+            }
+            Json(json!({ "rewound": rewound, "settled": settled })).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Swap a settlement is for: a mint swap by its deposit, or by its request id while it waits
+/// for the deposit; a burn swap by its burn request.
+fn settled_swap(
+    store: &SwapStore,
+    report: &SettlementReport,
+) -> Result<Option<Swap>, Box<dyn Error>> {
+    if let Some(burn_id) = report.burn_id {
+        return store.find_by_burn(burn_id);
+    }
+    mint_swap(store, report.deposit_txid.as_deref(), report.request_id)
+}
+
+/// Mint swap of a deposit, or the swap `request_id` names while it waits for the deposit.
+fn mint_swap(
+    store: &SwapStore,
+    deposit_txid: Option<&str>,
+    request_id: Option<u64>,
+) -> Result<Option<Swap>, Box<dyn Error>> {
+    if let Some(swap) = deposit_txid
+        .map(|txid| store.find_by_deposit(txid))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(Some(swap));
+    }
+    Ok(request_id
+        .map(|id| store.get(id))
+        .transpose()?
+        .flatten()
+        .filter(|swap| swap.direction == Direction::Mint && swap.state == SwapState::Requested))
+}
+
+/// Applies `events` the swap is ready for, in order, skipping the others.
+fn catch_up(
+    store: &SwapStore,
+    mut swap: Swap,
+    events: Vec<SwapEvent>,
+) -> Result<Swap, Box<dyn Error>> {
+    for event in events {
+        if next_state(swap.direction, swap.state, &event).is_some() {
+            swap = store.apply(swap.id, event)?.0;
+        }
+    }
+    Ok(swap)
+}
+
+/// `POST /settlements`: reported by `submit` once a settlement is confirmed. The swap records
+/// the settlement transaction and is minted or settled; the steps before it the proof shows
+/// happened, the deposit, payout and proof request, are applied first if the swap lacks them.
+/// A swap the settlement cannot apply to is a conflict.
+async fn record_settlement(
+    State(state): State<Arc<ApiState>>,
+    Json(report): Json<SettlementReport>,
+) -> Response {
+    let swap = match settled_swap(&state.store, &report) {
+        Ok(Some(swap)) => swap,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, "no swap for the settlement");
+        }
+        Err(e) => return internal_error(e),
+    };
+    let mut events = Vec::new();
+    if let (Some(txid), Some(amount_sats)) = (&report.deposit_txid, report.amount_sats) {
+        events.push(SwapEvent::DepositConfirmed {
+            txid: txid.clone(),
+            amount_sats,
+        });
+    }
+    if let Some(txid) = &report.payout_txid {
+        events.push(SwapEvent::PayoutBroadcast { txid: txid.clone() });
+    }
+    if let Some(job_id) = report.job_id {
+        events.push(SwapEvent::ProofRequested { job_id });
+    }
+    let settled = SwapEvent::ProofSettled {
+        eth_tx: report.eth_tx.clone(),
+    };
+    let current = match catch_up(&state.store, swap, events) {
+        Ok(swap) => swap,
+        Err(e) => return internal_error(e),
+    };
+    if current.eth_tx.as_deref() != Some(report.eth_tx.as_str())
+        && next_state(current.direction, current.state, &settled).is_none()
+    {
+        warn!(swap = current.id, state = %current.state, eth_tx = report.eth_tx, "Settlement does not apply to its swap");
+        return error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "swap {} is {}: the settlement does not apply",
+                current.id, current.state
+            ),
+        );
+    }
+    let result = state
+        .store
+        .apply(current.id, settled)
+        .and_then(|(swap, _)| {
+            state.store.flush()?;
+            state.response(swap)
+        });
+    match result {
+        Ok(response) => {
+            info!(swap = response.swap.id, state = %response.swap.state, eth_tx = report.eth_tx, "Settlement recorded");
+            Json(response).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `POST /refunds`: reported by `submit` when a deposit it cannot mint is queued for a refund,
+/// and by `payout-scheduler` once the refund is final. The swap goes to `refunding`, and with
+/// the refund transaction to `refunded`; its deposit is applied first if the swap lacks it. A
+/// swap the refund cannot apply to is a conflict.
+async fn record_refund(
+    State(state): State<Arc<ApiState>>,
+    Json(report): Json<RefundReport>,
+) -> Response {
+    let swap = match mint_swap(&state.store, Some(&report.deposit_txid), report.request_id) {
+        Ok(Some(swap)) => swap,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "no swap for the refund"),
+        Err(e) => return internal_error(e),
+    };
+    let mut events = Vec::new();
+    if let Some(amount_sats) = report.amount_sats {
+        events.push(SwapEvent::DepositConfirmed {
+            txid: report.deposit_txid.clone(),
+            amount_sats,
+        });
+    }
+    events.push(SwapEvent::RefundRequested {
+        address: report.btc_address.clone(),
+        reason: report.reason.clone(),
+    });
+    let current = match catch_up(&state.store, swap, events) {
+        Ok(swap) => swap,
+        Err(e) => return internal_error(e),
+    };
+    let paid = report
+        .refund_txid
+        .clone()
+        .map(|txid| SwapEvent::RefundPaid { txid });
+    let paid_applies = paid.as_ref().is_none_or(|paid| {
+        current.refund_txid == report.refund_txid
+            || next_state(current.direction, current.state, paid).is_some()
+    });
+    if current.refund_address.as_deref() != Some(report.btc_address.as_str()) || !paid_applies {
+        warn!(swap = current.id, state = %current.state, deposit = report.deposit_txid, "Refund does not apply to its swap");
+        return error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "swap {} is {}: the refund does not apply",
+                current.id, current.state
+            ),
+        );
+    }
+    let result = match paid {
+        Some(paid) => state.store.apply(current.id, paid).map(|(swap, _)| swap),
+        None => Ok(current),
+    }
+    .and_then(|swap| {
+        state.store.flush()?;
+        state.response(swap)
+    });
+    match result {
+        Ok(response) => {
+            info!(swap = response.swap.id, state = %response.swap.state, deposit = report.deposit_txid, reason = report.reason, "Refund recorded");
+            Json(response).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let chain = args.network.map_or_else(
+        || ChainParams::from(DEFAULT_NETWORK),
+        |network| network.chain(),
+    );
+    let vault_address = match &args.vault_address {
+        Some(address) => address.clone(),
+        None => args
+            .group_key
+            .vault_address(&chain)
+            .expect("vault address encodes on every chain"),
+    };
+    if let Err(e) = chain.address_script_pubkey(&vault_address) {
+        error!(error = %e, vault = vault_address, %chain, "Invalid vault address");
+        std::process::exit(1);
+    }
+    let store = SwapStore::open(args.db.as_deref()).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the swap database");
+        std::process::exit(1);
+    });
+    let state = Arc::new(ApiState {
+        store,
+        fee_oracle: args.fee_oracle.clone(),
+        mint_gas: args.mint_gas,
+        vault_address,
+        group_key: args.group_key.group_key,
+        deposit_method: args.deposit_method,
+        evm_chain_id: args.evm_chain_id,
+        chain: chain.clone(),
+    });
+
+    let app = Router::new()
+        .route("/swaps", post(create_swap))
+        .route("/swaps/:id", get(swap_status))
+        .route("/redemptions", post(create_redemption))
+        .route("/redemptions/:burn_id", get(redemption_status))
+        .route("/quote", get(quote))
+        .route("/deposit-addresses", get(deposit_addresses))
+        .route("/reorgs", post(report_reorg))
+        .route("/settlements", post(record_settlement))
+        .route("/refunds", post(record_refund))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .expect("failed to bind listen address");
+    info!(
+        listen = %args.listen,
+        %chain,
+        deposit_method = ?args.deposit_method,
+        "Swap API listening"
+    );
+    axum::serve(listener, app).await.expect("server error");
+}
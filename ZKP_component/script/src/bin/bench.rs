@@ -0,0 +1,138 @@
+//! Executes a circuit on one or more bundles and writes a JSON report of total cycles and the
+//! per-stage `cycle-tracker-report` sections, so circuit optimizations can be measured.
+
+// Usage Example:
+// cargo run --release --bin bench -- --circuit mint --input-json ./input.json
+// cargo run --release --bin bench -- --circuit burn --input-json ./a.json --input-json ./b.json --output ./bench_burn.json
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::input::{read_input, InputSchema};
+use clap::{Parser, ValueEnum};
+use lib_struct::{BundleInfoStruct, BurnBatchBundleStruct, MintBatchBundleStruct};
+use serde::Serialize;
+use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("mint_batch_circuit");
+pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    MintBatch,
+    Burn,
+    BurnBatch,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
+    circuit: CircuitType,
+    /// Input bundles to execute; repeat the flag for each file.
+    #[clap(long, required = true)]
+    input_json: Vec<PathBuf>,
+    /// Path of the JSON report; defaults to `./bench_<circuit>.json`.
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+/// Cycle counts of one execution.
+#[derive(Serialize)]
+struct BenchEntry {
+    input: String,
+    total_cycles: u64,
+    syscalls: u64,
+    /// Cycles per `cycle-tracker-report` section of the circuit.
+    stages: BTreeMap<String, u64>,
+    /// Cycles outside the tracked sections: input deserialization, logging and the checks
+    /// between stages.
+    untracked_cycles: u64,
+}
+
+/// Benchmark report written to `--output`.
+#[derive(Serialize)]
+struct BenchReport {
+    circuit: String,
+    entries: Vec<BenchEntry>,
+}
+
+/// Reads a circuit input bundle from a JSON file, exiting with the offending field when it does
+/// not match the input schema.
+fn read_input_json<T: InputSchema>(path: &Path) -> T {
+    read_input(path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+fn main() {
+    sp1_sdk::utils::setup_logger();
+    apply_config_file();
+    dotenv::dotenv().ok();
+
+    let args = Args::parse();
+    let client = ProverClient::builder().cpu().build();
+
+    let (elf, circuit_name) = match args.circuit {
+        CircuitType::Mint => (MINT_CIRCUIT_ELF, "mint"),
+        CircuitType::MintBatch => (MINT_BATCH_CIRCUIT_ELF, "mint_batch"),
+        CircuitType::Burn => (BURN_CIRCUIT_ELF, "burn"),
+        CircuitType::BurnBatch => (BURN_BATCH_CIRCUIT_ELF, "burn_batch"),
+    };
+
+    let mut entries = Vec::with_capacity(args.input_json.len());
+    for path in &args.input_json {
+        let mut stdin = SP1Stdin::new();
+        match args.circuit {
+            CircuitType::MintBatch => stdin.write(&read_input_json::<MintBatchBundleStruct>(path)),
+            CircuitType::BurnBatch => stdin.write(&read_input_json::<BurnBatchBundleStruct>(path)),
+            _ => stdin.write(&read_input_json::<BundleInfoStruct>(path)),
+        }
+
+        let (_, report) = client
+            .execute(elf, &stdin)
+            .run()
+            .expect("failed to execute circuit");
+        let total_cycles = report.total_instruction_count();
+        let stages: BTreeMap<String, u64> = report
+            .cycle_tracker
+            .iter()
+            .map(|(section, cycles)| (section.clone(), *cycles))
+            .collect();
+        let tracked_cycles: u64 = stages.values().sum();
+        entries.push(BenchEntry {
+            input: path.display().to_string(),
+            total_cycles,
+            syscalls: report.total_syscall_count(),
+            stages,
+            untracked_cycles: total_cycles.saturating_sub(tracked_cycles),
+        });
+    }
+
+    // === Print a summary and write the report ===
+    println!("-------------------------------------------");
+    for entry in &entries {
+        println!("{}: {} cycles", entry.input, entry.total_cycles);
+        for (section, cycles) in &entry.stages {
+            println!("  {}: {} cycles", section, cycles);
+        }
+        println!("  untracked: {} cycles", entry.untracked_cycles);
+    }
+
+    let report = BenchReport {
+        circuit: circuit_name.to_string(),
+        entries,
+    };
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("bench_{}.json", circuit_name)));
+    std::fs::write(&output, serde_json::to_string_pretty(&report).unwrap())
+        .expect("failed to write bench report");
+    println!("Report written to {}", output.display());
+}
@@ -0,0 +1,711 @@
+//! Operator tooling over the state the bridge services keep:
+//! - `signer` and `coordinator` inspect the TSS databases: a signer's key shares and nonces,
+//!   and the coordinator's group key, unfinished sessions and payout volume per epoch;
+//! - `utxos` lists the vault's outputs, and `descriptor` exports its output descriptor;
+//! - `jobs` lists the work in flight: the payout queue, the payout batches and the proof
+//!   service's journaled jobs;
+//! - `swaps` dumps the swap state, and `audit` exports every recorded step as JSON lines;
+//! - `resign` has a stuck payout batch signed again by `payout-scheduler`;
+//! - `queue` lists the job queue of `serve --bus` or `submit --bus` with its dead letters, and
+//!   `requeue` puts dead letters back to be retried.
+//!
+//! sled lets one process at a time open a database, so a database is read with its service
+//! stopped, or from a copy.
+
+// Usage Example:
+// cargo run --release --bin bridgectl -- signer ./signer1-state/nonces_db
+// cargo run --release --bin bridgectl -- coordinator --db ../../TSS_component/tss-coordinator/coordinator-db --network testnet
+// cargo run --release --bin bridgectl -- utxos --source esplora --payout-db ./payout-scheduler-db
+// cargo run --release --bin bridgectl -- descriptor --network testnet
+// cargo run --release --bin bridgectl -- jobs --payout-queue http://127.0.0.1:3300 --payout-db ./payout-scheduler-db
+// cargo run --release --bin bridgectl -- swaps --db ./swap-db --pending
+// cargo run --release --bin bridgectl -- audit --swap-db ./swap-db --payout-db ./payout-scheduler-db --since 1735689600 > audit.jsonl
+// cargo run --release --bin bridgectl -- resign --payout-db ./payout-scheduler-db 3
+// cargo run --release --bin bridgectl -- queue --db ./submit-mint-queue --dead
+// cargo run --release --bin bridgectl -- requeue --db ./submit-mint-queue 12 15
+// cargo run --release --bin bridgectl -- requeue --db ./proof-queue --all
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::Address;
+use bitcoin_verify_script::bundle::SourceArgs;
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::job_queue::{JobQueue, QueuedJob};
+use bitcoin_verify_script::journal::{self, Journal};
+use bitcoin_verify_script::payout::BatchStore;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::store::{PayoutJob, PayoutStatus};
+use bitcoin_verify_script::BitcoinNetwork;
+use bridge_core::store::SwapStore;
+use clap::{Parser, Subcommand};
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::vault_script_pubkey;
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Entries of a signer's database, `<prefix><participant id hex>` as `rust_tss` writes them.
+const SIGNER_ENTRIES: [(&str, &str); 5] = [
+    ("keypkg_", "key share"),
+    ("pubkeypkg_", "public key package"),
+    ("r1_", "DKG round-1 secret"),
+    ("r2_", "DKG round-2 secret"),
+    ("nonces_", "nonces of its last signing round 1"),
+];
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prints the key shares, DKG secrets and signing nonces a TSS signer holds.
+    Signer {
+        /// The signer's `rust_tss` database, `/state/nonces_db` in its container.
+        db: PathBuf,
+    },
+    /// Prints the TSS coordinator's group key, its unfinished sessions and the payout volume
+    /// its policy recorded per epoch.
+    Coordinator {
+        /// The coordinator's `--db`.
+        #[clap(long, env = "COORDINATOR_DB")]
+        db: PathBuf,
+        /// Network of the vault address printed for the group key.
+        #[clap(long, value_enum, env = "BITCOIN_NETWORK", default_value = "testnet")]
+        network: BitcoinNetwork,
+        /// The coordinator's `--policy-epoch-secs`, to date the epochs.
+        #[clap(long, env = "POLICY_EPOCH_SECS", default_value_t = 86_400)]
+        epoch_secs: u64,
+        /// Print the finished sessions too.
+        #[clap(long)]
+        all: bool,
+    },
+    /// Lists the unspent outputs of the vault.
+    Utxos {
+        #[clap(flatten)]
+        source: SourceArgs,
+        #[clap(flatten)]
+        group_key: GroupKeyArgs,
+        /// Batch database of `payout-scheduler`, to flag the outputs its batches reserve.
+        #[clap(long, env = "PAYOUT_SCHEDULER_DB")]
+        payout_db: Option<PathBuf>,
+    },
+    /// Prints the vault's output descriptor, with its checksum, and its address.
+    Descriptor {
+        #[clap(flatten)]
+        group_key: GroupKeyArgs,
+        #[clap(long, value_enum, env = "BITCOIN_NETWORK", default_value = "testnet")]
+        network: BitcoinNetwork,
+    },
+    /// Lists the work in flight: the unpaid jobs of the payout queue, the unfinished payout
+    /// batches and the jobs journaled by the proof service.
+    Jobs {
+        /// Base URL of `burn-listener`'s payout queue.
+        #[clap(long, env = "PAYOUT_QUEUE_URL")]
+        payout_queue: Option<String>,
+        /// Batch database of `payout-scheduler`.
+        #[clap(long, env = "PAYOUT_SCHEDULER_DB")]
+        payout_db: Option<PathBuf>,
+        /// Journal of `serve`; defaults to `script/.proving-journal/serve.json`.
+        #[clap(long)]
+        journal: Option<PathBuf>,
+    },
+    /// Prints swaps of the swap API's database as JSON.
+    Swaps {
+        /// Swap database of `api`.
+        #[clap(long, env = "SWAP_DB")]
+        db: PathBuf,
+        /// Only this swap.
+        id: Option<u64>,
+        /// Only the swaps not minted, settled or failed yet.
+        #[clap(long, conflicts_with = "id")]
+        pending: bool,
+    },
+    /// Writes every recorded step to stdout as JSON lines, oldest first: the swaps'
+    /// transitions, the payout batches and the coordinator's sessions.
+    Audit {
+        #[clap(long, env = "SWAP_DB")]
+        swap_db: Option<PathBuf>,
+        #[clap(long, env = "PAYOUT_SCHEDULER_DB")]
+        payout_db: Option<PathBuf>,
+        #[clap(long, env = "COORDINATOR_DB")]
+        coordinator_db: Option<PathBuf>,
+        /// Leave out the steps before this Unix time.
+        #[clap(long, default_value_t = 0)]
+        since: u64,
+    },
+    /// Has payout batch `batch_id` signed again by the next round of `payout-scheduler`: a
+    /// batch still signing drops its signatures, and a batch that failed before its broadcast,
+    /// e.g. denied by a policy since relaxed, goes back to signing.
+    Resign {
+        #[clap(long, env = "PAYOUT_SCHEDULER_DB")]
+        payout_db: PathBuf,
+        batch_id: u64,
+    },
+    /// Lists the jobs of a job queue, with their attempts and last error, and its dead letters.
+    Queue {
+        /// The service's `--queue-db`.
+        #[clap(long)]
+        db: PathBuf,
+        /// Only the dead letters, with their payloads.
+        #[clap(long)]
+        dead: bool,
+    },
+    /// Puts dead letters of a job queue back, due at once with their attempts reset.
+    Requeue {
+        /// The service's `--queue-db`.
+        #[clap(long)]
+        db: PathBuf,
+        /// Dead letters to put back.
+        #[clap(required_unless_present = "all")]
+        ids: Vec<u64>,
+        /// Put every dead letter back.
+        #[clap(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+/// Opens a service's database with `open`. Opening creates a missing database, so a wrong
+/// path is caught first, and a database its service holds is reported as such.
+fn open_existing<T>(
+    path: &Path,
+    open: impl FnOnce(&Path) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()).into());
+    }
+    open(path).map_err(|e| {
+        format!(
+            "Cannot open {}: {}; if its service is running, stop it or use a copy",
+            path.display(),
+            e
+        )
+        .into()
+    })
+}
+
+fn open_sled(path: &Path) -> Result<sled::Db, Box<dyn Error>> {
+    open_existing(path, |path| Ok(sled::open(path)?))
+}
+
+/// Tree `name` of `db`, if it was ever created; opening it would create it.
+fn existing_tree(db: &sled::Db, name: &str) -> Result<Option<sled::Tree>, Box<dyn Error>> {
+    if !db.tree_names().iter().any(|tree| tree == name.as_bytes()) {
+        return Ok(None);
+    }
+    Ok(Some(db.open_tree(name)?))
+}
+
+fn vault_address(
+    group_key: &XOnlyPublicKey,
+    chain: &ChainParams,
+) -> Result<String, Box<dyn Error>> {
+    chain.address_from_script(&vault_script_pubkey(group_key))
+}
+
+/// Participant number of a FROST identifier, a big-endian scalar, when it is a small one.
+fn party_number(id_hex: &str) -> Option<u16> {
+    let bytes = hex::decode(id_hex).ok()?;
+    let (high, low) = bytes.split_at(bytes.len().checked_sub(2)?);
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    Some(u16::from_be_bytes([low[0], low[1]]))
+}
+
+fn signer(path: &Path) -> Result<(), Box<dyn Error>> {
+    let db = open_sled(path)?;
+    let mut participants: BTreeMap<String, Vec<(&str, usize)>> = BTreeMap::new();
+    for entry in db.iter() {
+        let (key, value) = entry?;
+        let key = String::from_utf8_lossy(&key).into_owned();
+        let found = SIGNER_ENTRIES.iter().find_map(|(prefix, label)| {
+            key.strip_prefix(prefix).map(|id| (id.to_string(), *label))
+        });
+        match found {
+            Some((id, label)) => participants
+                .entry(id)
+                .or_default()
+                .push((label, value.len())),
+            None => println!("unknown entry {} ({} bytes)", key, value.len()),
+        }
+    }
+    if participants.is_empty() {
+        println!("no key material: the signer never took part in a DKG");
+    }
+    for (id, entries) in participants {
+        match party_number(&id) {
+            Some(number) => println!("participant {} ({})", number, id),
+            None => println!("participant {}", id),
+        }
+        for (label, size) in &entries {
+            println!("  {} ({} bytes)", label, size);
+        }
+        if !entries
+            .iter()
+            .any(|(label, _)| *label == SIGNER_ENTRIES[0].1)
+        {
+            println!("  no key share: its DKG did not complete");
+        }
+    }
+    Ok(())
+}
+
+fn coordinator(
+    path: &Path,
+    chain: &ChainParams,
+    epoch_secs: u64,
+    all: bool,
+) -> Result<(), Box<dyn Error>> {
+    let db = open_sled(path)?;
+    match db.get(b"group_key")? {
+        Some(bytes) => {
+            let key: Value = serde_json::from_slice(&bytes)?;
+            let key_hex = key["verify_key_hex"]
+                .as_str()
+                .ok_or("Group key without verify_key_hex")?;
+            println!("group key {}", key_hex);
+            let group_key = XOnlyPublicKey::from_str(key_hex)?;
+            println!("vault address {}", vault_address(&group_key, chain)?);
+        }
+        None => println!("no group key: no DKG completed"),
+    }
+
+    for (tree, kind) in [("dkg", "DKG"), ("signing", "signing")] {
+        let Some(tree) = existing_tree(&db, tree)? else {
+            continue;
+        };
+        for value in tree.iter().values() {
+            let session: Value = serde_json::from_slice(&value?)?;
+            let state = session["state"]["state"].as_str().unwrap_or("unknown");
+            if !all && matches!(state, "done" | "failed") {
+                continue;
+            }
+            let mut line = format!(
+                "{} session {}: {}, updated at {}",
+                kind,
+                session["id"].as_str().unwrap_or_default(),
+                state,
+                session["updated_at"]
+            );
+            if let Some(message_hex) = session["message_hex"].as_str() {
+                line.push_str(&format!(", message {}", message_hex));
+            }
+            if let Some(reason) = session["state"]["reason"].as_str() {
+                line.push_str(&format!(", {}", reason));
+            }
+            println!("{}", line);
+        }
+    }
+
+    // Keyed by big-endian epoch then payout id, valued by big-endian sats
+    let mut epochs: BTreeMap<u64, (u64, usize)> = BTreeMap::new();
+    if let Some(tree) = existing_tree(&db, "policy_volume")? {
+        for entry in tree.iter() {
+            let (key, value) = entry?;
+            let epoch: [u8; 8] = key
+                .get(..8)
+                .and_then(|epoch| epoch.try_into().ok())
+                .ok_or("Corrupt epoch volume entry")?;
+            let sats: [u8; 8] = value
+                .as_ref()
+                .try_into()
+                .map_err(|_| "Corrupt epoch volume entry")?;
+            let volume = epochs.entry(u64::from_be_bytes(epoch)).or_default();
+            volume.0 = volume.0.saturating_add(u64::from_be_bytes(sats));
+            volume.1 += 1;
+        }
+    }
+    for (epoch, (sats, payouts)) in epochs {
+        println!(
+            "epoch {} (from {}): {} sats in {} payouts",
+            epoch,
+            epoch.saturating_mul(epoch_secs),
+            sats,
+            payouts
+        );
+    }
+    Ok(())
+}
+
+fn utxos(
+    source: &SourceArgs,
+    group_key: &XOnlyPublicKey,
+    payout_db: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let store = payout_db
+        .map(|path| open_existing(path, |path| BatchStore::open(Some(path))))
+        .transpose()?;
+    let source = source.open()?;
+    let address = Address::from_script(&vault_script_pubkey(group_key), source.network()?)?;
+    let utxos = source.address_utxos(&address)?;
+    let total: u64 = utxos.iter().map(|utxo| utxo.value_sats).sum();
+    println!("vault {}: {} outputs, {} sats", address, utxos.len(), total);
+    for utxo in utxos {
+        let txid = utxo.txid.to_string();
+        let reserved = match &store {
+            Some(store) if store.is_reserved(&txid, utxo.vout)? => ", reserved by a payout batch",
+            _ => "",
+        };
+        println!(
+            "  {}:{} {} sats at height {}{}",
+            txid, utxo.vout, utxo.value_sats, utxo.height, reserved
+        );
+    }
+    Ok(())
+}
+
+/// Checksum of an output descriptor (BIP 380), which Bitcoin Core requires on import.
+fn descriptor_checksum(descriptor: &str) -> Result<String, Box<dyn Error>> {
+    const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+    const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GENERATORS: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+    let polymod = |c: u64, value: u64| {
+        let top = c >> 35;
+        let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+        for (bit, generator) in GENERATORS.iter().enumerate() {
+            if (top >> bit) & 1 == 1 {
+                c ^= generator;
+            }
+        }
+        c
+    };
+    let mut c = 1u64;
+    let mut class = 0u64;
+    let mut count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("Invalid descriptor character {:?}", ch))?
+            as u64;
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        count += 1;
+        if count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            count = 0;
+        }
+    }
+    if count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+fn descriptor(group_key: &XOnlyPublicKey, chain: &ChainParams) -> Result<(), Box<dyn Error>> {
+    let descriptor = format!("tr({})", group_key);
+    println!("{}#{}", descriptor, descriptor_checksum(&descriptor)?);
+    println!("{}", vault_address(group_key, chain)?);
+    Ok(())
+}
+
+fn jobs(
+    payout_queue: Option<&str>,
+    payout_db: Option<&Path>,
+    journal: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(url) = payout_queue {
+        let jobs: Vec<PayoutJob> = Client::new()
+            .get(format!("{}/payouts", url.trim_end_matches('/')))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let unpaid: Vec<&PayoutJob> = jobs
+            .iter()
+            .filter(|job| !matches!(job.status, PayoutStatus::Paid { .. }))
+            .collect();
+        println!("payout queue: {} jobs not paid", unpaid.len());
+        for job in unpaid {
+            let status = match &job.status {
+                PayoutStatus::Confirming => "confirming".to_string(),
+                PayoutStatus::Ready => "ready".to_string(),
+                PayoutStatus::Invalid { reason } => format!("invalid: {}", reason),
+                PayoutStatus::Paid { payout_txid } => format!("paid by {}", payout_txid),
+            };
+            println!(
+                "  burn {}: {} sats to {}, {}",
+                job.burn_id, job.amount_sats, job.btc_address, status
+            );
+        }
+    }
+
+    if let Some(path) = payout_db {
+        let store = open_existing(path, |path| BatchStore::open(Some(path)))?;
+        let batches: Vec<_> = store
+            .batches()?
+            .into_iter()
+            .filter(|batch| !batch.is_finished())
+            .collect();
+        println!("payout batches: {} unfinished", batches.len());
+        for batch in batches {
+            let pays = match &batch.refund {
+                Some(refund) => format!("refund of {}", refund.deposit_txid),
+                None => {
+                    let burns: Vec<String> = batch
+                        .payouts
+                        .iter()
+                        .map(|payout| payout.burn_id.to_string())
+                        .collect();
+                    format!("burns {}", burns.join(","))
+                }
+            };
+            println!(
+                "  batch {}: {}, {}, {}",
+                batch.id, pays, batch.txid, batch.status
+            );
+        }
+    }
+
+    let journal = Journal::open(journal.unwrap_or_else(|| journal::default_path("serve")))?;
+    let entries = journal.entries();
+    println!(
+        "proof jobs journaled in {}: {}",
+        journal.path().display(),
+        entries.len()
+    );
+    for entry in entries {
+        let request = entry
+            .request_id
+            .map(|request_id| format!(", network request {}", request_id))
+            .unwrap_or_default();
+        println!(
+            "  job {}: {} ({}), {:?}, input {}{}",
+            entry.job, entry.circuit, entry.proof_mode, entry.phase, entry.input_hash, request
+        );
+    }
+    Ok(())
+}
+
+fn swaps(path: &Path, id: Option<u64>, pending: bool) -> Result<(), Box<dyn Error>> {
+    let store = open_existing(path, |path| SwapStore::open(Some(path)))?;
+    let dump = match id {
+        Some(id) => {
+            let swap = store.get(id)?.ok_or_else(|| format!("No swap {}", id))?;
+            serde_json::to_string_pretty(&swap)?
+        }
+        None => {
+            let swaps: Vec<_> = store
+                .swaps()?
+                .into_iter()
+                .filter(|swap| !pending || !swap.state.is_terminal())
+                .collect();
+            serde_json::to_string_pretty(&swaps)?
+        }
+    };
+    println!("{}", dump);
+    Ok(())
+}
+
+/// Adds `fields` to a JSON object, in front of its own.
+fn record(fields: Value, rest: Value) -> Value {
+    let mut record = fields;
+    if let (Some(record), Value::Object(rest)) = (record.as_object_mut(), rest) {
+        record.extend(rest);
+    }
+    record
+}
+
+fn audit(
+    swap_db: Option<&Path>,
+    payout_db: Option<&Path>,
+    coordinator_db: Option<&Path>,
+    since: u64,
+) -> Result<(), Box<dyn Error>> {
+    if swap_db.is_none() && payout_db.is_none() && coordinator_db.is_none() {
+        return Err("Give at least one of --swap-db, --payout-db and --coordinator-db".into());
+    }
+    let mut records: Vec<(u64, Value)> = Vec::new();
+    if let Some(path) = swap_db {
+        let store = open_existing(path, |path| SwapStore::open(Some(path)))?;
+        for swap in store.swaps()? {
+            records.push((
+                swap.created_at,
+                json!({
+                    "source": "swap",
+                    "swap": swap.id,
+                    "direction": swap.direction,
+                    "event": "created",
+                    "at": swap.created_at,
+                }),
+            ));
+            for transition in &swap.history {
+                records.push((
+                    transition.at,
+                    record(
+                        json!({ "source": "swap", "swap": swap.id, "direction": swap.direction }),
+                        serde_json::to_value(transition)?,
+                    ),
+                ));
+            }
+        }
+    }
+    if let Some(path) = payout_db {
+        let store = open_existing(path, |path| BatchStore::open(Some(path)))?;
+        for batch in store.batches()? {
+            records.push((
+                batch.created_at,
+                record(
+                    json!({ "source": "payout_batch", "at": batch.created_at }),
+                    serde_json::to_value(&batch)?,
+                ),
+            ));
+        }
+    }
+    if let Some(path) = coordinator_db {
+        let db = open_sled(path)?;
+        for (tree, source) in [("dkg", "dkg_session"), ("signing", "signing_session")] {
+            let Some(tree) = existing_tree(&db, tree)? else {
+                continue;
+            };
+            for value in tree.iter().values() {
+                let session: Value = serde_json::from_slice(&value?)?;
+                let at = session["updated_at"].as_u64().unwrap_or_default();
+                records.push((at, record(json!({ "source": source, "at": at }), session)));
+            }
+        }
+    }
+    records.sort_by_key(|(at, _)| *at);
+    for (_, record) in records.into_iter().filter(|(at, _)| *at >= since) {
+        println!("{}", serde_json::to_string(&record)?);
+    }
+    Ok(())
+}
+
+fn resign(payout_db: &Path, batch_id: u64) -> Result<(), Box<dyn Error>> {
+    let store = open_existing(payout_db, |path| BatchStore::open(Some(path)))?;
+    let batch = store.resign(batch_id)?;
+    store.flush()?;
+    println!(
+        "batch {} ({}) is signing again; the next round of payout-scheduler requests its signatures",
+        batch.id, batch.txid
+    );
+    Ok(())
+}
+
+fn queued_line(job: &QueuedJob) -> String {
+    let error = job
+        .last_error
+        .as_ref()
+        .map(|error| format!(", last error: {}", error))
+        .unwrap_or_default();
+    format!(
+        "  job {}: {} {}, {} attempt(s){}",
+        job.id, job.kind, job.swap_id, job.attempts, error
+    )
+}
+
+fn queue(path: &Path, dead: bool) -> Result<(), Box<dyn Error>> {
+    let queue = open_existing(path, |path| JobQueue::open(Some(path)))?;
+    if !dead {
+        let jobs = queue.jobs()?;
+        println!("queued: {}", jobs.len());
+        for job in jobs {
+            println!("{}, due at {}", queued_line(&job), job.next_attempt_at);
+        }
+    }
+    let dead_letters = queue.dead_letters()?;
+    println!("dead letters: {}", dead_letters.len());
+    for job in dead_letters {
+        println!(
+            "{}, dead since {}",
+            queued_line(&job),
+            job.dead_at.unwrap_or_default()
+        );
+        if dead {
+            println!("    payload: {}", job.payload);
+        }
+    }
+    Ok(())
+}
+
+fn requeue(path: &Path, ids: &[u64], all: bool) -> Result<(), Box<dyn Error>> {
+    let queue = open_existing(path, |path| JobQueue::open(Some(path)))?;
+    let ids = if all {
+        queue.dead_letters()?.iter().map(|job| job.id).collect()
+    } else {
+        ids.to_vec()
+    };
+    for id in ids {
+        let job = queue.requeue(id)?;
+        println!(
+            "job {} ({} {}) is queued again",
+            job.id, job.kind, job.swap_id
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+
+    let result = match &args.command {
+        Command::Signer { db } => signer(db),
+        Command::Coordinator {
+            db,
+            network,
+            epoch_secs,
+            all,
+        } => coordinator(db, &network.chain(), *epoch_secs, *all),
+        Command::Utxos {
+            source,
+            group_key,
+            payout_db,
+        } => utxos(source, &group_key.group_key, payout_db.as_deref()),
+        Command::Descriptor { group_key, network } => {
+            descriptor(&group_key.group_key, &network.chain())
+        }
+        Command::Jobs {
+            payout_queue,
+            payout_db,
+            journal,
+        } => jobs(
+            payout_queue.as_deref(),
+            payout_db.as_deref(),
+            journal.clone(),
+        ),
+        Command::Swaps { db, id, pending } => swaps(db, *id, *pending),
+        Command::Audit {
+            swap_db,
+            payout_db,
+            coordinator_db,
+            since,
+        } => audit(
+            swap_db.as_deref(),
+            payout_db.as_deref(),
+            coordinator_db.as_deref(),
+            *since,
+        ),
+        Command::Resign {
+            payout_db,
+            batch_id,
+        } => resign(payout_db, *batch_id),
+        Command::Queue { db, dead } => queue(db, *dead),
+        Command::Requeue { db, ids, all } => requeue(db, ids, *all),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,203 @@
+//! Builds the circuit input bundle for a confirmed Bitcoin transaction: raw transaction,
+//! Merkle proof and confirmation headers are fetched from a Bitcoin Core node over JSON-RPC
+//! or from an Esplora HTTP API, so `input.json` no longer has to be assembled by hand.
+
+// Usage Example:
+// cargo run --release --bin build-bundle -- --txid <txid> --rpc-url http://127.0.0.1:18332 --rpc-user <user> --rpc-password <password> --output ./input.json
+// cargo run --release --bin build-bundle -- --txid <txid> --burner-btc-address <address> --burn-id 0 --output ./burn.json
+// cargo run --release --bin build-bundle -- --source esplora --url https://blockstream.info/testnet/api --txid <txid>
+// cargo run --release --bin build-bundle -- --config ./bridge.toml --txid <txid>
+// cargo run --release --bin build-bundle -- --txid <txid> --headers-db ./headers-db --checkpoint <height>:<hash>
+// cargo run --release --bin build-bundle -- --circuit burn --payout-txid <txid> --burn-id 0 --group-key <key> --eth-rpc-url http://127.0.0.1:8545 --contract 0x... --output ./burn.json
+use alloy::primitives::{Address, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use bitcoin::hash_types::Txid;
+use bitcoin_verify_script::bundle::{
+    build_bundle, BundleRequest, BurnTarget, RpcResponse, SourceArgs,
+};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use clap::{Parser, ValueEnum};
+use lib_struct::verify::DEFAULT_CONFIRMATIONS;
+use lib_struct::BurnPayoutPolicyStruct;
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+sol! {
+    /// Getter of the pending burn requests of the ZKBTC bridge contract.
+    interface IZKBTC {
+        function burnRequests(uint256 burnId) external view returns (address user, uint256 total_amount, uint256 zkbtcToReimburse, uint256 exactBtcUserReceive, uint256 rewardOperator, uint256 rewardStaker, uint256 dust, string btcAddress, uint256 timestamp, bool fulfilled, bool reclaimed);
+    }
+}
+
+/// Enum representing the circuits a bundle can be built for
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    Burn,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Bundle to build; `--burner-btc-address` also selects burn.
+    #[clap(long, value_enum, default_value = "mint")]
+    circuit: CircuitType,
+    /// Confirmed transaction to build the bundle for: the deposit, or the payout of a burn.
+    #[clap(long, visible_alias = "payout-txid")]
+    txid: Txid,
+    /// Block containing the transaction; required when the node runs without `-txindex`.
+    #[clap(long)]
+    block_hash: Option<String>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Headers to put in `chains`, counting the transaction's block.
+    #[clap(long, default_value_t = DEFAULT_CONFIRMATIONS)]
+    confirmations: usize,
+    /// Builds a burn bundle paying this address, with the payout's spent prevouts and a
+    /// coinbase proof of its block height. Without it, `--circuit burn` reads the address and
+    /// the amount owed from the burn request on the contract.
+    #[clap(long)]
+    burner_btc_address: Option<String>,
+    /// Burn request id on the contract, for burn bundles.
+    #[clap(long)]
+    burn_id: Option<u64>,
+    /// Group key of the vault, written as `vaultPublicKey`; a burn bundle's build fails
+    /// unless an input of the payout spends the vault.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Ethereum JSON-RPC endpoint the burn request is read from.
+    #[clap(long, env = "ETH_RPC_URL")]
+    eth_rpc_url: Option<String>,
+    /// Address of the ZKBTC contract holding the burn request.
+    #[clap(long, env = "ZKBTC_CONTRACT")]
+    contract: Option<Address>,
+    /// Path of the bundle JSON; printed to stdout when omitted.
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Format of the progress logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Reads burn request `burn_id` from the ZKBTC contract with `eth_call`.
+fn fetch_burn_request(
+    client: &Client,
+    rpc_url: &str,
+    contract: Address,
+    burn_id: u64,
+) -> Result<IZKBTC::burnRequestsReturn, Box<dyn Error>> {
+    let calldata = IZKBTC::burnRequestsCall {
+        burnId: U256::from(burn_id),
+    }
+    .abi_encode();
+    let response: RpcResponse<String> = client
+        .post(rpc_url)
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_call",
+            "params": [
+                { "to": contract.to_string(), "data": format!("0x{}", hex::encode(calldata)) },
+                "latest",
+            ],
+        }))
+        .send()?
+        .json()?;
+    let result = match (response.result, response.error) {
+        (_, Some(error)) => {
+            return Err(format!("eth_call failed ({}): {}", error.code, error.message).into())
+        }
+        (Some(result), None) => result,
+        (None, None) => return Err("eth_call returned no result".into()),
+    };
+    let returns = hex::decode(result.trim_start_matches("0x"))?;
+    Ok(IZKBTC::burnRequestsCall::abi_decode_returns(&returns)?)
+}
+
+/// Burn the bundle is built for, if any: `--burner-btc-address`, or with `--circuit burn` the
+/// pending burn request `--burn-id`, whose amount owed becomes the expected payout.
+fn burn_target(args: &Args) -> Result<Option<BurnTarget>, Box<dyn Error>> {
+    if let Some(burner_btc_address) = &args.burner_btc_address {
+        return Ok(Some(BurnTarget {
+            burner_btc_address: burner_btc_address.clone(),
+            burn_policy: BurnPayoutPolicyStruct::default(),
+            burn_id: args.burn_id,
+        }));
+    }
+    if args.circuit == CircuitType::Mint {
+        return Ok(None);
+    }
+
+    let burn_id = args.burn_id.ok_or("--circuit burn requires --burn-id")?;
+    let rpc_url = args
+        .eth_rpc_url
+        .as_deref()
+        .ok_or("Reading the burn request requires --eth-rpc-url, or pass --burner-btc-address")?;
+    let contract = args
+        .contract
+        .ok_or("Reading the burn request requires --contract, or pass --burner-btc-address")?;
+    let request = fetch_burn_request(&Client::new(), rpc_url, contract, burn_id)?;
+    if request.user == Address::ZERO {
+        return Err(format!("Burn request {} does not exist on {}", burn_id, contract).into());
+    }
+    if request.fulfilled || request.reclaimed {
+        return Err(format!("Burn request {} is no longer pending", burn_id).into());
+    }
+    info!(
+        burn_id,
+        sats = %request.exactBtcUserReceive,
+        address = %request.btcAddress,
+        "Burn request read from the contract"
+    );
+    Ok(Some(BurnTarget {
+        burner_btc_address: request.btcAddress,
+        burn_policy: BurnPayoutPolicyStruct {
+            expected_amount_sats: request.exactBtcUserReceive.try_into()?,
+            ..BurnPayoutPolicyStruct::default()
+        },
+        burn_id: Some(burn_id),
+    }))
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let bundle = burn_target(&args)
+        .and_then(|burn| {
+            let source = args.source.open()?;
+            let request = BundleRequest {
+                txid: args.txid,
+                block_hash: args.block_hash.clone(),
+                network: args.source.network,
+                confirmations: args.confirmations,
+                group_key: args.group_key.group_key,
+                burn,
+            };
+            build_bundle(source.as_ref(), &request)
+        })
+        .unwrap_or_else(|e| {
+            error!(error = %e, "Failed to build bundle");
+            std::process::exit(1);
+        });
+
+    let json = serde_json::to_string_pretty(&bundle).unwrap();
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, json).expect("failed to write bundle JSON");
+            info!(path = %path.display(), "Bundle written");
+        }
+        None => println!("{}", json),
+    }
+}
@@ -0,0 +1,618 @@
+//! Listens for the bridge contract's `BurnInitiated` events and turns every burn request into
+//! a payout job (amount, burner BTC address, burn id) in a persistent queue consumed by the TSS
+//! payout pipeline. Past events are backfilled with `eth_getLogs`, new ones arrive over a
+//! WebSocket subscription, and a job only becomes ready once its block is `--finality-depth`
+//! blocks deep. With `--listen`, the queue is served over HTTP to `payout-scheduler`, which
+//! marks the jobs it paid, and to `submit`, which queues the refunds of deposits it cannot
+//! mint. With `--bus`, every job that becomes ready is also published on the
+//! job bus as `payout-requested`.
+
+// Usage Example:
+// cargo run --release --bin burn-listener -- --rpc http://127.0.0.1:8545 --ws-url ws://127.0.0.1:8546 --contract 0x... --db ./payout-db
+// cargo run --release --bin burn-listener -- --config ./bridge.toml --from-block 7000000 --finality-depth 64
+// cargo run --release --bin burn-listener -- --db ./payout-db --listen 127.0.0.1:3300
+// cargo run --release --bin burn-listener -- --db ./payout-db --listen 127.0.0.1:3300 --bus redis://127.0.0.1:6379
+// cargo run --release --bin burn-listener -- --db ./payout-db --list
+// curl http://127.0.0.1:3300/payouts/ready
+// curl -X POST -H 'Content-Type: application/json' --data '{"deposit_txid":"...","btc_address":"tb1q...","reason":"invalid memo"}' http://127.0.0.1:3300/refunds
+use alloy::primitives::Address;
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use alloy::pubsub::Subscription;
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol;
+use alloy::sol_types::SolEvent;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use bitcoin_verify_script::bus::{BusArgs, BusEvent, JobBus};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::store::{PayoutJob, PayoutQueue, PayoutStatus, RefundJob};
+use bitcoin_verify_script::{setup_logging, BitcoinNetwork, LogFormat};
+use bridge_core::now;
+use clap::Parser;
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::DEFAULT_NETWORK;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+sol! {
+    /// Burn request event of the ZKBTC bridge contract; `amount` is in satoshis.
+    interface IZKBTC {
+        event BurnInitiated(uint256 indexed burnId, address indexed user, uint256 amount, string btcAddress);
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Ethereum JSON-RPC endpoint logs are backfilled and finalized from.
+    #[clap(long, env = "ETH_RPC_URL", required_unless_present = "list")]
+    rpc: Option<String>,
+    /// WebSocket endpoint burn events are subscribed on; without it, only `--rpc` is polled.
+    #[clap(long, env = "ETH_WS_URL")]
+    ws_url: Option<String>,
+    /// Address of the ZKBTC contract.
+    #[clap(long, env = "ZKBTC_CONTRACT", required_unless_present = "list")]
+    contract: Option<Address>,
+    /// Blocks that must be built on a burn's block before its payout job is ready.
+    #[clap(long, env = "ETH_FINALITY_DEPTH", default_value_t = 12)]
+    finality_depth: u64,
+    /// First block to read burn events from; defaults to the saved position, else the last
+    /// final block.
+    #[clap(long)]
+    from_block: Option<u64>,
+    /// Blocks per `eth_getLogs` request while backfilling.
+    #[clap(long, default_value_t = 2_000)]
+    backfill_chunk: u64,
+    /// Seconds between two finality checks.
+    #[clap(long, default_value_t = 12)]
+    poll_interval: u64,
+    /// Chain burner addresses must belong to, a Bitcoin network or Litecoin's; a burn to
+    /// another chain's address becomes an invalid job instead of a payout.
+    #[clap(long, value_enum, env = "BITCOIN_NETWORK")]
+    network: Option<BitcoinNetwork>,
+    /// sled database of the payout queue and the next block to read, kept across restarts so
+    /// that no burn is missed or paid twice; without it, the queue is lost on exit.
+    #[clap(long, env = "PAYOUT_DB")]
+    db: Option<PathBuf>,
+    /// Address the payout queue is served on, for `payout-scheduler`; not served without it.
+    #[clap(long, env = "PAYOUT_QUEUE_LISTEN")]
+    listen: Option<SocketAddr>,
+    #[clap(flatten)]
+    bus: BusArgs,
+    /// Print the payout jobs recorded in `--db` and exit.
+    #[clap(long, requires = "db")]
+    list: bool,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Filter of the contract's burn events.
+fn burn_filter(contract: Address) -> Filter {
+    Filter::new()
+        .address(contract)
+        .event_signature(IZKBTC::BurnInitiated::SIGNATURE_HASH)
+}
+
+/// Decodes a burn event into a payout job that is still confirming.
+fn payout_job(log: &Log) -> Result<PayoutJob, Box<dyn Error>> {
+    let event = log.log_decode::<IZKBTC::BurnInitiated>()?;
+    let burn = &event.inner.data;
+    Ok(PayoutJob {
+        burn_id: burn.burnId.try_into()?,
+        user: burn.user.to_string(),
+        amount_sats: burn.amount.try_into()?,
+        btc_address: burn.btcAddress.clone(),
+        block_number: log
+            .block_number
+            .ok_or("Burn event without a block number")?,
+        block_hash: log
+            .block_hash
+            .ok_or("Burn event without a block hash")?
+            .to_string(),
+        tx_hash: log
+            .transaction_hash
+            .ok_or("Burn event without a transaction hash")?
+            .to_string(),
+        status: PayoutStatus::Confirming,
+    })
+}
+
+/// Checks that the payout of a final burn can be made.
+fn check_payout(job: &PayoutJob, chain: &ChainParams) -> Result<(), String> {
+    if job.amount_sats == 0 {
+        return Err("nothing to pay".to_string());
+    }
+    check_address(&job.btc_address, chain)
+}
+
+/// Checks that `btc_address` is an address of the chain, for a payout or a refund.
+fn check_address(btc_address: &str, chain: &ChainParams) -> Result<(), String> {
+    chain
+        .address_script_pubkey(btc_address)
+        .map(|_| ())
+        .map_err(|e| format!("invalid {} address: {}", chain, e))
+}
+
+/// Records a burn event from the subscription as confirming, or forgets it when a
+/// reorganization removed it.
+fn record_seen(queue: &PayoutQueue, log: &Log) -> Result<(), Box<dyn Error>> {
+    let job = payout_job(log)?;
+    if log.removed {
+        warn!(
+            burn_id = job.burn_id,
+            block = job.block_number,
+            "Burn event removed by a reorganization"
+        );
+        return queue.remove_confirming(job.burn_id);
+    }
+    if queue.record(&job)? {
+        info!(
+            burn_id = job.burn_id,
+            amount_sats = job.amount_sats,
+            btc_address = job.btc_address,
+            block = job.block_number,
+            "Burn seen, waiting for finality"
+        );
+    }
+    Ok(())
+}
+
+/// Reads the burn events of every block that became final since the last call and makes their
+/// payout jobs ready. Jobs still confirming in those blocks that the final logs no longer
+/// contain were reorganized out and are dropped.
+async fn finalize(
+    provider: &impl Provider,
+    args: &Args,
+    contract: Address,
+    chain: &ChainParams,
+    queue: &PayoutQueue,
+    bus: Option<&dyn JobBus>,
+    next_block: &mut u64,
+) -> Result<(), Box<dyn Error>> {
+    let head = provider.get_block_number().await?;
+    let Some(final_block) = head.checked_sub(args.finality_depth) else {
+        return Ok(());
+    };
+    while *next_block <= final_block {
+        let to_block = (*next_block + args.backfill_chunk.max(1) - 1).min(final_block);
+        let logs = provider
+            .get_logs(
+                &burn_filter(contract)
+                    .from_block(*next_block)
+                    .to_block(to_block),
+            )
+            .await?;
+        let mut final_burns = HashSet::new();
+        for log in &logs {
+            let mut job = payout_job(log)?;
+            final_burns.insert(job.burn_id);
+            job.status = match check_payout(&job, chain) {
+                Ok(()) => PayoutStatus::Ready,
+                Err(reason) => PayoutStatus::Invalid { reason },
+            };
+            if queue.record(&job)? {
+                match &job.status {
+                    PayoutStatus::Invalid { reason } => {
+                        warn!(burn_id = job.burn_id, %reason, "Final burn cannot be paid out")
+                    }
+                    _ => {
+                        info!(
+                            burn_id = job.burn_id,
+                            amount_sats = job.amount_sats,
+                            btc_address = job.btc_address,
+                            "Payout job ready"
+                        );
+                        // The queue stays the record of the job; the event only hands it on
+                        if let Some(bus) = bus {
+                            let requested = BusEvent::payout_requested(&job);
+                            if let Err(e) = tokio::task::block_in_place(|| bus.publish(&requested))
+                            {
+                                warn!(burn_id = job.burn_id, error = %e, "Failed to publish the payout job");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for job in queue.jobs()? {
+            if job.status == PayoutStatus::Confirming
+                && job.block_number <= to_block
+                && !final_burns.contains(&job.burn_id)
+            {
+                warn!(
+                    burn_id = job.burn_id,
+                    block = job.block_number,
+                    "Burn event not in the final chain, dropped"
+                );
+                queue.remove_confirming(job.burn_id)?;
+            }
+        }
+        *next_block = to_block + 1;
+        queue.set_next_block(*next_block)?;
+    }
+    queue.flush()
+}
+
+/// State of the queue's HTTP handlers.
+struct QueueState {
+    queue: Arc<PayoutQueue>,
+    /// Chain refund addresses must belong to.
+    chain: ChainParams,
+}
+
+fn error_response(status: StatusCode, message: impl ToString) -> Response {
+    (status, Json(json!({ "error": message.to_string() }))).into_response()
+}
+
+/// `GET /payouts`: every job, in burn order.
+async fn payouts(State(state): State<Arc<QueueState>>) -> Response {
+    match state.queue.jobs() {
+        Ok(jobs) => Json(jobs).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// `GET /payouts/ready`: the jobs waiting to be paid, in burn order.
+async fn ready_payouts(State(state): State<Arc<QueueState>>) -> Response {
+    match state.queue.ready() {
+        Ok(jobs) => Json(jobs).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MarkPaid {
+    payout_txid: String,
+}
+
+/// `POST /payouts/{burn_id}/paid`: records the transaction that paid the burn. Returns `409`
+/// when the job is not ready, or was paid by another transaction.
+async fn mark_paid(
+    State(state): State<Arc<QueueState>>,
+    Path(burn_id): Path<u64>,
+    Json(body): Json<MarkPaid>,
+) -> Response {
+    let queue = &state.queue;
+    match queue.get(burn_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("no payout job for burn {}", burn_id),
+            )
+        }
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+    if let Err(e) = queue.mark_paid(burn_id, &body.payout_txid) {
+        return error_response(StatusCode::CONFLICT, e);
+    }
+    if let Err(e) = queue.flush() {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, e);
+    }
+    info!(burn_id, payout_txid = body.payout_txid, "Payout job paid");
+    match queue.get(burn_id) {
+        Ok(job) => Json(job).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// A refund `submit` requests for a deposit it cannot mint.
+#[derive(Deserialize, Debug)]
+struct RefundRequest {
+    deposit_txid: String,
+    request_id: Option<u64>,
+    btc_address: String,
+    reason: String,
+}
+
+/// `POST /refunds`: queues the refund of a rejected deposit, ready at once; the payout pipeline
+/// checks the deposit is final before paying it. A refund to an address not of the chain is
+/// recorded invalid, and a deposit whose refund was already requested keeps it. Returns the
+/// deposit's refund.
+async fn request_refund(
+    State(state): State<Arc<QueueState>>,
+    Json(request): Json<RefundRequest>,
+) -> Response {
+    let status = match check_address(&request.btc_address, &state.chain) {
+        Ok(()) => PayoutStatus::Ready,
+        Err(reason) => PayoutStatus::Invalid { reason },
+    };
+    let job = RefundJob {
+        deposit_txid: request.deposit_txid,
+        request_id: request.request_id,
+        btc_address: request.btc_address,
+        reason: request.reason,
+        requested_at: now(),
+        status,
+    };
+    let result = state.queue.request_refund(&job).and_then(|written| {
+        state.queue.flush()?;
+        Ok(written)
+    });
+    match result {
+        Ok(true) => match &job.status {
+            PayoutStatus::Invalid { reason } => {
+                error!(alert = true, deposit = job.deposit_txid, btc_address = job.btc_address, %reason, "Rejected deposit cannot be refunded")
+            }
+            _ => info!(
+                deposit = job.deposit_txid,
+                btc_address = job.btc_address,
+                reason = job.reason,
+                "Refund ready"
+            ),
+        },
+        Ok(false) => {}
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+    match state.queue.refund(&job.deposit_txid) {
+        Ok(refund) => Json(refund).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// `GET /refunds`: every refund.
+async fn refunds(State(state): State<Arc<QueueState>>) -> Response {
+    match state.queue.refunds() {
+        Ok(jobs) => Json(jobs).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// `GET /refunds/{deposit_txid}`: the deposit's refund, which `submit` checks before minting
+/// it.
+async fn refund(
+    State(state): State<Arc<QueueState>>,
+    Path(deposit_txid): Path<String>,
+) -> Response {
+    match state.queue.refund(&deposit_txid) {
+        Ok(Some(job)) => Json(job).into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("no refund for deposit {}", deposit_txid),
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// `GET /refunds/ready`: the refunds waiting to be paid, oldest first.
+async fn ready_refunds(State(state): State<Arc<QueueState>>) -> Response {
+    match state.queue.ready_refunds() {
+        Ok(jobs) => Json(jobs).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MarkRefunded {
+    refund_txid: String,
+}
+
+/// `POST /refunds/{deposit_txid}/paid`: records the transaction that refunded the deposit,
+/// answering as `POST /payouts/{burn_id}/paid` does.
+async fn mark_refunded(
+    State(state): State<Arc<QueueState>>,
+    Path(deposit_txid): Path<String>,
+    Json(body): Json<MarkRefunded>,
+) -> Response {
+    let queue = &state.queue;
+    match queue.refund(&deposit_txid) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("no refund for deposit {}", deposit_txid),
+            )
+        }
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+    if let Err(e) = queue.mark_refunded(&deposit_txid, &body.refund_txid) {
+        return error_response(StatusCode::CONFLICT, e);
+    }
+    if let Err(e) = queue.flush() {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, e);
+    }
+    info!(
+        deposit = deposit_txid,
+        refund_txid = body.refund_txid,
+        "Deposit refunded"
+    );
+    match queue.refund(&deposit_txid) {
+        Ok(job) => Json(job).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+fn describe(status: PayoutStatus) -> String {
+    match status {
+        PayoutStatus::Confirming => "confirming".to_string(),
+        PayoutStatus::Ready => "ready".to_string(),
+        PayoutStatus::Invalid { reason } => format!("invalid: {}", reason),
+        PayoutStatus::Paid { payout_txid } => format!("paid by {}", payout_txid),
+    }
+}
+
+/// Prints the payout jobs in burn order, then the refunds.
+fn list(queue: &PayoutQueue) -> Result<(), Box<dyn Error>> {
+    for job in queue.jobs()? {
+        println!(
+            "burn {}: {} sats to {} (block {}), {}",
+            job.burn_id,
+            job.amount_sats,
+            job.btc_address,
+            job.block_number,
+            describe(job.status)
+        );
+    }
+    for job in queue.refunds()? {
+        println!(
+            "refund of {} to {} ({}), {}",
+            job.deposit_txid,
+            job.btc_address,
+            job.reason,
+            describe(job.status)
+        );
+    }
+    if let Some(next_block) = queue.next_block()? {
+        println!("next block to finalize: {}", next_block);
+    }
+    Ok(())
+}
+
+fn chain(args: &Args) -> ChainParams {
+    args.network.map_or_else(
+        || ChainParams::from(DEFAULT_NETWORK),
+        |network| network.chain(),
+    )
+}
+
+async fn run(
+    args: &Args,
+    queue: &PayoutQueue,
+    bus: Option<&dyn JobBus>,
+) -> Result<(), Box<dyn Error>> {
+    let rpc = args.rpc.as_deref().ok_or("--rpc is required")?;
+    let contract = args.contract.ok_or("--contract is required")?;
+    let chain = chain(args);
+    let provider = ProviderBuilder::new().connect_http(rpc.parse()?);
+
+    let mut next_block = match (args.from_block, queue.next_block()?) {
+        (Some(from_block), _) => from_block,
+        (None, Some(saved)) => saved,
+        (None, None) => {
+            let head = provider.get_block_number().await?;
+            head.saturating_sub(args.finality_depth) + 1
+        }
+    };
+
+    // The WebSocket provider is kept alive with its subscription
+    let mut subscription: Option<(_, Subscription<Log>)> = match &args.ws_url {
+        Some(ws_url) => {
+            let ws_provider = ProviderBuilder::new()
+                .connect_ws(WsConnect::new(ws_url.clone()))
+                .await?;
+            let logs = ws_provider.subscribe_logs(&burn_filter(contract)).await?;
+            Some((ws_provider, logs))
+        }
+        None => None,
+    };
+    info!(
+        %contract,
+        %chain,
+        from_block = next_block,
+        finality_depth = args.finality_depth,
+        subscribed = subscription.is_some(),
+        ready = queue.ready()?.len(),
+        "Listening for burn events"
+    );
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(args.poll_interval));
+    loop {
+        let seen = match &mut subscription {
+            Some((_, logs)) => tokio::select! {
+                _ = ticker.tick() => None,
+                log = logs.recv() => Some(log),
+            },
+            None => {
+                ticker.tick().await;
+                None
+            }
+        };
+        match seen {
+            Some(Ok(log)) => {
+                if let Err(e) = record_seen(queue, &log) {
+                    warn!(error = %e, "Failed to record a burn event");
+                }
+                continue;
+            }
+            Some(Err(e)) => {
+                // Burns are still read from `--rpc` once final, only later
+                warn!(error = %e, "Burn event subscription ended, polling only");
+                subscription = None;
+                continue;
+            }
+            None => {}
+        }
+        if let Err(e) = finalize(
+            &provider,
+            args,
+            contract,
+            &chain,
+            queue,
+            bus,
+            &mut next_block,
+        )
+        .await
+        {
+            warn!(error = %e, "Finality check failed, retrying");
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let queue = PayoutQueue::open(args.db.as_deref()).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the payout queue");
+        std::process::exit(1);
+    });
+    let queue = Arc::new(queue);
+    if args.list {
+        if let Err(e) = list(&queue) {
+            error!(error = %e, "Failed to read the payout queue");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(listen) = args.listen {
+        let app = Router::new()
+            .route("/payouts", get(payouts))
+            .route("/payouts/ready", get(ready_payouts))
+            .route("/payouts/:burn_id/paid", post(mark_paid))
+            .route("/refunds", get(refunds).post(request_refund))
+            .route("/refunds/ready", get(ready_refunds))
+            .route("/refunds/:deposit_txid", get(refund))
+            .route("/refunds/:deposit_txid/paid", post(mark_refunded))
+            .with_state(Arc::new(QueueState {
+                queue: queue.clone(),
+                chain: chain(&args),
+            }));
+        let listener = tokio::net::TcpListener::bind(listen)
+            .await
+            .unwrap_or_else(|e| {
+                error!(error = %e, %listen, "Failed to bind the listen address");
+                std::process::exit(1);
+            });
+        info!(%listen, "Serving the payout queue");
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                error!(error = %e, "Payout queue server failed");
+            }
+        });
+    }
+    let bus = args.bus.open("burn-listener").unwrap_or_else(|e| {
+        error!(error = %e, "Failed to connect to the job bus");
+        std::process::exit(1);
+    });
+    if let Err(e) = run(&args, &queue, bus.as_deref()).await {
+        error!(error = %e, "Burn listener failed");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,274 @@
+//! Smoke test of the whole ZKP path against a regtest `bitcoind`: sends a deposit to the vault
+//! with an OP_RETURN memo, mines its confirmations, builds the bundle, executes the
+//! mint circuit and prints the public values it commits.
+
+// Usage Example:
+// bitcoind -regtest -daemon -rpcuser=bridge -rpcpassword=bridge -fallbackfee=0.0001
+// cargo run --release --bin e2e -- --rpc-user bridge --rpc-password bridge --group-key <x-only key>
+// cargo run --release --bin e2e -- --rpc-user bridge --rpc-password bridge --group-key <x-only key> --recipient 0x... --amount-sats 250000 --output ./e2e.json
+use alloy_primitives::Address;
+use bitcoin::hash_types::Txid;
+use bitcoin::network::Network;
+use bitcoin::Amount;
+use bitcoin_verify_script::bundle::{
+    build_bundle, BundleRequest, RpcResponse, SourceArgs, SourceType,
+};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::native::{run_mint, Rejection};
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::{print_mint_public_values, setup_logging, LogFormat};
+use clap::Parser;
+use lib_struct::verify::DEFAULT_CONFIRMATIONS;
+use lib_struct::BundleInfoStruct;
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{error, info, info_span, warn};
+
+pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+
+/// Blocks a regtest coinbase needs before the wallet can spend it.
+const COINBASE_MATURITY: u32 = 101;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// JSON-RPC endpoint of the regtest node.
+    #[clap(
+        long,
+        env = "BITCOIN_RPC_URL",
+        default_value = "http://127.0.0.1:18443"
+    )]
+    rpc_url: String,
+    #[clap(long, env = "BITCOIN_RPC_USER")]
+    rpc_user: Option<String>,
+    #[clap(long, env = "BITCOIN_RPC_PASSWORD")]
+    rpc_password: Option<String>,
+    /// Node wallet that funds the deposit; created on first use.
+    #[clap(long, default_value = "bridge-e2e")]
+    wallet: String,
+    /// Ethereum address put in the deposit memo.
+    #[clap(long, default_value = "0xa86Ed347B8D1043533fe30c07Fc47f3E3b849a42")]
+    recipient: Address,
+    /// Sats the deposit pays to the vault.
+    #[clap(long, default_value_t = 100_000)]
+    amount_sats: u64,
+    /// Blocks mined on top of the deposit, counting its block.
+    #[clap(long, default_value_t = DEFAULT_CONFIRMATIONS)]
+    confirmations: usize,
+    /// Group key the bundle names as the vault's.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Also writes the bundle JSON here, to rerun it with `main` or `evm`.
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Wallet and mining calls of the regtest node.
+struct Node {
+    client: Client,
+    args: Args,
+}
+
+impl Node {
+    fn call<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut request = self
+            .client
+            .post(format!(
+                "{}{}",
+                self.args.rpc_url.trim_end_matches('/'),
+                path
+            ))
+            .json(&json!({ "jsonrpc": "1.0", "id": "e2e", "method": method, "params": params }));
+        if let Some(user) = &self.args.rpc_user {
+            request = request.basic_auth(user, self.args.rpc_password.as_ref());
+        }
+        let response: RpcResponse<T> = request.send()?.json()?;
+        match (response.result, response.error) {
+            (_, Some(error)) => {
+                Err(format!("{} failed ({}): {}", method, error.code, error.message).into())
+            }
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(format!("{} returned no result", method).into()),
+        }
+    }
+
+    /// Calls a wallet method on `--wallet`.
+    fn wallet_call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn Error>> {
+        self.call(&format!("/wallet/{}", self.args.wallet), method, params)
+    }
+
+    /// Loads `--wallet`, creating it on the first run.
+    fn open_wallet(&self) -> Result<(), Box<dyn Error>> {
+        let loaded: Vec<String> = self.call("", "listwallets", json!([]))?;
+        if loaded.contains(&self.args.wallet) {
+            return Ok(());
+        }
+        if self
+            .call::<Value>("", "loadwallet", json!([self.args.wallet]))
+            .is_err()
+        {
+            self.call::<Value>("", "createwallet", json!([self.args.wallet]))?;
+            info!(wallet = %self.args.wallet, "Created wallet");
+        }
+        Ok(())
+    }
+
+    /// Mines `blocks` blocks to the wallet and returns their hashes.
+    fn mine(&self, blocks: u32) -> Result<Vec<String>, Box<dyn Error>> {
+        let address: String = self.wallet_call("getnewaddress", json!([]))?;
+        self.call("", "generatetoaddress", json!([blocks, address]))
+    }
+}
+
+#[derive(Deserialize)]
+struct RawTransaction {
+    hex: String,
+}
+
+#[derive(Deserialize)]
+struct SignedTransaction {
+    hex: String,
+    complete: bool,
+}
+
+/// Sends the deposit from the wallet: `--amount-sats` to the vault of `--group-key`, then the
+/// recipient memo in an OP_RETURN output.
+fn send_deposit(node: &Node) -> Result<Txid, Box<dyn Error>> {
+    let balance: f64 = node.wallet_call("getbalance", json!([]))?;
+    if Amount::from_btc(balance)? <= Amount::from_sat(node.args.amount_sats) {
+        info!(blocks = COINBASE_MATURITY, "Mining to fund the wallet");
+        node.mine(COINBASE_MATURITY)?;
+    }
+
+    let vault_address =
+        bitcoin::Address::from_script(&node.args.group_key.vault_script(), Network::Regtest)?;
+    let memo = hex::encode(node.args.recipient.to_string());
+    let mut vault_output = Map::new();
+    vault_output.insert(
+        vault_address.to_string(),
+        json!(Amount::from_sat(node.args.amount_sats).to_btc()),
+    );
+    let outputs = json!([vault_output, { "data": memo }]);
+    let unfunded: String = node.call("", "createrawtransaction", json!([[], outputs]))?;
+    let funded: RawTransaction = node.wallet_call(
+        "fundrawtransaction",
+        json!([unfunded, { "changePosition": 2 }]),
+    )?;
+    let signed: SignedTransaction =
+        node.wallet_call("signrawtransactionwithwallet", json!([funded.hex]))?;
+    if !signed.complete {
+        return Err("the wallet could not sign the deposit".into());
+    }
+    let txid: String = node.call("", "sendrawtransaction", json!([signed.hex]))?;
+    info!(%txid, vault = %vault_address, recipient = %node.args.recipient, "Deposit sent");
+    Ok(Txid::from_str(&txid)?)
+}
+
+/// Runs the deposit through the bundle builder and the mint circuit, and reports whether the
+/// circuit committed the public values the native model expects.
+fn run(node: &Node) -> Result<bool, Box<dyn Error>> {
+    let network: String = node
+        .call::<Value>("", "getblockchaininfo", json!([]))?
+        .get("chain")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    if network != "regtest" {
+        return Err(format!("{} serves {}, not regtest", node.args.rpc_url, network).into());
+    }
+    node.open_wallet()?;
+    let txid = send_deposit(node)?;
+
+    let blocks = node.mine(node.args.confirmations as u32)?;
+    let block_hash = blocks.first().ok_or("generatetoaddress mined no block")?;
+    info!(blocks = blocks.len(), %block_hash, "Confirmations mined");
+
+    let source = SourceArgs {
+        source: SourceType::Rpc,
+        network: Some(Network::Regtest),
+        rpc_url: node.args.rpc_url.clone(),
+        rpc_user: node.args.rpc_user.clone(),
+        rpc_password: node.args.rpc_password.clone(),
+        url: String::new(),
+        headers_db: None,
+        checkpoint: None,
+    }
+    .connect();
+    let request = BundleRequest {
+        txid,
+        block_hash: Some(block_hash.clone()),
+        network: Some(Network::Regtest),
+        confirmations: node.args.confirmations,
+        group_key: node.args.group_key.group_key,
+        burn: None,
+    };
+    let bundle: BundleInfoStruct = build_bundle(source.as_ref(), &request)?;
+    if let Some(path) = &node.args.output {
+        std::fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+        info!(path = %path.display(), "Bundle written");
+    }
+
+    let native = run_mint(&bundle);
+    match &native.failure {
+        None => info!("Native checks passed"),
+        Some((check, Rejection { reason, detail })) => {
+            warn!(check, %reason, %detail, "Native checks expect a rejection")
+        }
+    }
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&bundle);
+    let client = ProverClient::builder().cpu().build();
+    let (output, report) =
+        info_span!("execute").in_scope(|| client.execute(MINT_CIRCUIT_ELF, &stdin).run())?;
+    info!(
+        cycles = report.total_instruction_count(),
+        "Mint circuit executed"
+    );
+    print_mint_public_values(output.as_slice())?;
+    Ok(native.public_values == output.as_slice())
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let node = Node {
+        client: Client::new(),
+        args,
+    };
+    match run(&node) {
+        Ok(true) => info!("End-to-end run passed: the circuit matches the native model"),
+        Ok(false) => {
+            error!("End-to-end run failed: the circuit output differs from the native model");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!(error = %e, "End-to-end run failed");
+            std::process::exit(1);
+        }
+    }
+}
@@ -3,212 +3,816 @@
 
 // Usage Example:
 // cargo run --release --bin evm -- --circuit mint --system groth16 --input-json ./input.json
+// cargo run --release --bin evm -- --circuit mint --system groth16 --input-dir ./bundles/
+// cargo run --release --bin evm -- --circuit burn --input-json ./burn.json --stdout > fixture.json
+// cargo run --release --bin evm -- --circuit mint --input-json ./input.json --force
+// cargo run --release --bin evm -- --circuit mint --input-json ./input.json --prover mock
+// cargo run --release --bin evm -- --circuit mint --input-json ./input.json --prover network --resume 0x...
+// cargo run --release --bin evm -- --circuit mint --input-json ./input.json --log-format json 2> prove.log
+// cargo run --release --bin evm -- --config ./bridge.toml --input-json ./input.json
+// cargo run --release --bin evm -- --circuit mint --proof-mode compressed --input-json ./input.json
+// cargo run --release --bin evm -- --circuit mint --resume-journal
+// cargo run --release --bin evm -- --circuit mint --input-json ./signet.json --network signet
+// OPERATOR_PRIVATE_KEY=0x... cargo run --release --bin evm -- --circuit mint --input-json ./input.json
+use alloy::signers::local::PrivateKeySigner;
+use alloy_primitives::B256;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin_verify_script::attestation::{operator_signer, sign_fixture};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::input::{read_input, InputSchema};
+use bitcoin_verify_script::journal::{self, exit_on_signal, JobPhase, Journal, JournalEntry};
+use bitcoin_verify_script::{
+    apply_network, setup_logging, BitcoinNetwork, LogFormat, ProverBackend, ProvingProgress,
+};
 use clap::{Parser, ValueEnum};
 use lib_struct::{
-    BitcoinTrxInfoStruct, Block, BundleInfoStruct, Chain, MerkleProof, ZkpProofFixture,
+    BitcoinTrxInfoStruct, Block, BundleInfoStruct, BurnBatchBundleStruct, BurnPayoutPolicyStruct,
+    Chain, MerkleProof, MintBatchBundleStruct, MintPolicyStruct, ZkpProofFixture,
 };
+use serde::Serialize;
 use sp1_sdk::{
-    include_elf, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey,
+    include_elf, EnvProver, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1ProvingKey,
+    SP1Stdin, SP1VerifyingKey,
 };
-use std::path::PathBuf;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, info_span, warn};
 
 /// ELF files for the Bitcoin transaction verification zkVM programs
 pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("mint_batch_circuit");
 pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
+
+/// Seconds to wait before the first retry of a network call; later retries wait longer.
+const RETRY_BASE_DELAY_SECS: u64 = 30;
 
-/// Enum representing the available proof systems
+/// Enum representing the available proof modes: the EVM-verifiable SNARKs settled on-chain,
+/// and the cheaper STARKs for off-chain verifiers such as auditors and watchtowers
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
-enum ProofSystem {
+enum ProofMode {
     Plonk,
     Groth16,
+    /// Constant-size STARK, verified with the SP1 SDK.
+    Compressed,
+    /// STARK per execution shard; the cheapest to generate, the largest to ship.
+    Core,
+}
+
+impl ProofMode {
+    /// Whether the proof can be verified on-chain, and is therefore written as an EVM fixture.
+    fn is_evm(self) -> bool {
+        matches!(self, ProofMode::Plonk | ProofMode::Groth16)
+    }
+}
+
+/// Output of a proving run: an EVM fixture for on-chain settlement, or the SP1 proof itself
+/// for off-chain verification.
+enum ProofArtifact {
+    Fixture(ZkpProofFixture),
+    Proof(Box<SP1ProofWithPublicValues>),
 }
 
 /// Enum representing the available circuits
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 enum CircuitType {
     Mint,
+    MintBatch,
     Burn,
+    BurnBatch,
 }
 
 /// The arguments for the EVM command.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct EVMArgs {
-    #[clap(long, value_enum, default_value = "groth16")]
-    system: ProofSystem,
-    #[clap(long, value_enum, default_value = "burn")]
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// `groth16` and `plonk` write an EVM fixture; `compressed` and `core` write an SP1 proof
+    /// file (`<mode>-proof_<circuit>.bin`) that off-chain verifiers check with the SP1 SDK.
+    #[clap(
+        long,
+        visible_alias = "system",
+        value_enum,
+        env = "PROOF_SYSTEM",
+        default_value = "groth16"
+    )]
+    proof_mode: ProofMode,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "burn")]
     circuit: CircuitType,
-    #[clap(long)]
+    #[clap(long, conflicts_with = "input_dir")]
     input_json: Option<PathBuf>, // Optional: path to a JSON file with public input
+    /// Proves every `*.json` bundle in this directory, writing one fixture per input and a
+    /// summary report.
+    #[clap(long)]
+    input_dir: Option<PathBuf>,
+    /// Bitcoin network the circuit verifies each bundle on, written into its `network` field
+    /// before hashing; without it the bundle's own `network` applies, else testnet.
+    #[clap(long, value_enum, env = "BITCOIN_NETWORK")]
+    network: Option<BitcoinNetwork>,
+    /// Directory the fixtures and the proof cache are written to; defaults to
+    /// `../contracts/src/fixtures`.
+    #[clap(long, env = "FIXTURE_DIR")]
+    output: Option<PathBuf>,
+    /// Prints only the fixture JSON to stdout instead of writing a file.
+    #[clap(long, conflicts_with = "input_dir")]
+    stdout: bool,
+    /// Proves again even when a cached fixture exists for the same input.
+    #[clap(long)]
+    force: bool,
+    /// SP1 prover backend; falls back to `SP1_PROVER`, then the CPU prover.
+    #[clap(long, value_enum, env = "SP1_PROVER", default_value = "cpu")]
+    prover: ProverBackend,
+    /// Waits for an earlier prover network request instead of submitting a new one.
+    #[clap(long, conflicts_with = "input_dir")]
+    resume: Option<B256>,
+    /// Seconds to wait for a prover network proof before the wait counts as failed.
+    #[clap(long, default_value_t = 4 * 60 * 60)]
+    timeout_secs: u64,
+    /// Retries of a failed prover network request or wait.
+    #[clap(long, default_value_t = 3)]
+    retries: u32,
+    /// Journal of the jobs in flight; defaults to `script/.proving-journal/evm.json`.
+    #[clap(long)]
+    journal: Option<PathBuf>,
+    /// Proves the jobs an interrupted run left in the journal, then exits.
+    #[clap(long, conflicts_with_all = ["input_json", "input_dir", "resume", "stdout"])]
+    resume_journal: bool,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+    /// Operator key that signs each EVM fixture, so the relayer can tell it came from an
+    /// authorized prover.
+    #[clap(long, env = "OPERATOR_PRIVATE_KEY", hide_env_values = true)]
+    operator_key: Option<String>,
+}
+
+/// Program, cache and journal shared by every input of a run.
+struct ProvingSetup<'a> {
+    client: &'a EnvProver,
+    elf: &'a [u8],
+    pk: &'a SP1ProvingKey,
+    vk: &'a SP1VerifyingKey,
+    /// `--circuit` value, as recorded in the journal.
+    circuit: String,
+    cache_dir: PathBuf,
+    journal: Arc<Journal>,
+    /// Signer of the fixtures, from `--operator-key`.
+    operator: Option<PrivateKeySigner>,
+}
+
+/// Outcome of one input of an `--input-dir` run, as written to the summary report.
+#[derive(Serialize)]
+struct BatchReportEntry {
+    input: String,
+    fixture: Option<String>,
+    cached: bool,
+    error: Option<String>,
 }
 
 fn main() {
-    // Setup the logger.
-    sp1_sdk::utils::setup_logger();
+    // Parse the command line arguments, on top of the config file.
+    apply_config_file();
+    let mut args = EVMArgs::parse();
 
-    // Parse the command line arguments.
-    let args = EVMArgs::parse();
+    // Setup the logger.
+    setup_logging(args.log_format);
+    args.prover = args.prover.resolve();
 
     // Setup the prover client.
-    let client = ProverClient::from_env();
+    let client = args.prover.client();
 
     // Select the circuit ELF and fixture name based on CLI argument
     let (elf, fixture_name) = match args.circuit {
         CircuitType::Mint => (MINT_CIRCUIT_ELF, "mint"),
+        CircuitType::MintBatch => (MINT_BATCH_CIRCUIT_ELF, "mint_batch"),
         CircuitType::Burn => (BURN_CIRCUIT_ELF, "burn"),
+        CircuitType::BurnBatch => (BURN_BATCH_CIRCUIT_ELF, "burn_batch"),
     };
 
     // Setup the program.
     let (pk, vk) = client.setup(elf);
 
-    // Prepare the bundle input (from file or fallback to mock data)
-    let bundle_data: BundleInfoStruct = if let Some(ref path) = args.input_json {
-        let file = std::fs::File::open(path).expect("failed to open input JSON");
-        serde_json::from_reader(file).expect("failed to parse input JSON")
+    info!(proof_mode = ?args.proof_mode, circuit = ?args.circuit, vkey = %vk.bytes32(), "Program set up");
+    let output_dir = fixture_dir(args.output.as_deref());
+
+    // Journal the jobs in flight, so an interrupted run can be resumed
+    let journal_path = args
+        .journal
+        .clone()
+        .unwrap_or_else(|| journal::default_path("evm"));
+    let journal = Journal::open(journal_path).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the journal");
+        std::process::exit(1);
+    });
+    let journal = Arc::new(journal);
+    exit_on_signal(journal.clone());
+    let operator = args.operator_key.as_deref().map(|key| {
+        let signer = operator_signer(key).unwrap_or_else(|e| {
+            error!(error = %e, "Failed to load the operator key");
+            std::process::exit(1);
+        });
+        info!(operator = %signer.address(), "Fixtures are signed by the operator");
+        signer
+    });
+    let setup = ProvingSetup {
+        client: &client,
+        elf,
+        pk: &pk,
+        vk: &vk,
+        circuit: value_name(args.circuit),
+        cache_dir: output_dir.join("cache"),
+        journal,
+        operator,
+    };
+
+    if args.resume_journal {
+        resume_journal(&setup, &args, fixture_name, &output_dir);
+        return;
+    }
+
+    // Prove a whole directory of bundles, e.g. the backlog left by prover downtime
+    if let Some(input_dir) = args.input_dir.as_ref() {
+        let inputs = list_input_jsons(input_dir).expect("failed to read input directory");
+        info!(
+            count = inputs.len(),
+            dir = %input_dir.display(),
+            "Proving bundles from directory"
+        );
+
+        let mut report = Vec::with_capacity(inputs.len());
+        for (i, path) in inputs.iter().enumerate() {
+            let _input_span = info_span!(
+                "input",
+                index = i + 1,
+                total = inputs.len(),
+                input = %path.display()
+            )
+            .entered();
+            let name = format!(
+                "{}_{}",
+                fixture_name,
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            );
+            let result =
+                prove_input(&setup, &args, Some(path), &name).and_then(|(artifact, cached)| {
+                    let path = write_artifact(&artifact, args.proof_mode, &name, &output_dir)?;
+                    Ok((path, cached))
+                });
+            let (fixture, cached, error) = match result {
+                Ok((fixture_path, cached)) => {
+                    (Some(fixture_path.display().to_string()), cached, None)
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to prove bundle");
+                    (None, false, Some(e.to_string()))
+                }
+            };
+            report.push(BatchReportEntry {
+                input: path.display().to_string(),
+                fixture,
+                cached,
+                error,
+            });
+        }
+
+        let failed = report.iter().filter(|entry| entry.error.is_some()).count();
+        let report_path = output_dir
+            .join(format!("{:?}-summary_{}.json", args.proof_mode, fixture_name).to_lowercase());
+        std::fs::write(&report_path, serde_json::to_string_pretty(&report).unwrap())
+            .expect("failed to write summary report");
+        info!(
+            proved = report.len() - failed,
+            total = report.len(),
+            summary = %report_path.display(),
+            "Finished proving bundles"
+        );
+        if failed > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // An SP1 proof is binary and can be large, so it only goes to a file
+    if args.stdout && !args.proof_mode.is_evm() {
+        error!(proof_mode = ?args.proof_mode, "--stdout only supports groth16 and plonk fixtures");
+        std::process::exit(1);
+    }
+
+    // Prove the bundle input (from file or fallback to mock data)
+    let (artifact, _) = prove_input(&setup, &args, args.input_json.as_deref(), fixture_name)
+        .expect("failed to generate proof");
+
+    match artifact {
+        ProofArtifact::Fixture(fixture) if args.stdout => {
+            println!("{}", serde_json::to_string(&fixture).unwrap());
+        }
+        artifact => {
+            write_artifact(&artifact, args.proof_mode, fixture_name, &output_dir)
+                .expect("failed to write proof");
+        }
+    }
+}
+
+/// Proves one circuit input and returns its fixture or proof, and whether it came from the
+/// cache. Outputs are cached under a hash of the input, the program and the proof mode, so a
+/// retry after a failed submission does not prove the same bundle again. `name` is the
+/// fixture name the caller writes the output under, kept in the journal for a resume.
+/// Fixtures are cached unsigned and signed on the way out, so a new operator key applies to
+/// cached proofs too.
+fn prove_input(
+    setup: &ProvingSetup,
+    args: &EVMArgs,
+    input_json: Option<&Path>,
+    name: &str,
+) -> Result<(ProofArtifact, bool), Box<dyn Error>> {
+    let (stdin, canonical_input) = info_span!("bundle_parse")
+        .in_scope(|| circuit_input(args.circuit, input_json, args.network))?;
+    let hash = input_hash(&canonical_input, setup.vk, args.proof_mode);
+    let extension = if args.proof_mode.is_evm() {
+        "json"
     } else {
-        // Fallback to hardcoded mock data
-        let mock_tx_2 = BitcoinTrxInfoStruct {
-            raw_tx_hex: "010000000001015564819f67c2803761c4370d9a5fd950c8e6ff34d68ebacc47fd21413aa833ea0100000000ffffffff03e8030000000000001600141240e21b1e7877f77bfe66cc59eefb02d17a0a3f00000000000000002c6a2a3078613836456433343742384431303433353333666533306330374663343766334533623834396134329b020000000000001600144cf2f041e4acc16071306ab41414cab4c76cfd5002483045022100bf43ff7d1ae782368550cb14cc916d389277a0f103643fa352ea76ba2ccd731502205028ba84f39deb9ff71db91153c6f71e7f9f5f6df9258c29bb49ec0461785b75012103292a330133c26afde92f10737cc3e38ebcf7403b4e2232c4b65821c1aa55cdf800000000".into(),
-        };
-        let mock_merkle_proof = MerkleProof {
-            siblings: vec![
-                "cc4522617a92f7b27416f3cedad721949df7aec91d6e87f23ef2895c760e6eee".to_string(),
-            ],
-            pos: 1,
-        };
-        let block_1 = Block {
-            block_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f"
-                .to_string(),
-            version: 633618432,
-            parent_hash: "0000000000000bf53edcfa982a0cbcaab1abf62660ec3ec67149df036891b32b"
-                .to_string(),
-            merkle_root: "214101dabc8c2b1e02999995163f31b187351c8ac1dad611e2660c2c4cae5ac6"
-                .to_string(),
-            timestamp: 1744638928,
-            difficulty: 437256176,
-            nonce: 4137494058,
-        };
-        let block_2 = Block {
-            block_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82"
-                .to_string(),
-            version: 770375680,
-            parent_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f"
-                .to_string(),
-            merkle_root: "b4ce4f3646fd93a8ffed7711840a09039722919c45ff1beb029d5f3027c32858"
-                .to_string(),
-            timestamp: 1744638928,
-            difficulty: 437256176,
-            nonce: 2932452395,
-        };
-        let block_3 = Block {
-            block_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c"
-                .to_string(),
-            version: 710811648,
-            parent_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82"
-                .to_string(),
-            merkle_root: "1d065531f64d5662ba174f7533bddd96632d4e530ed9df2b3d1470336f5c9daa"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2559894718,
-        };
-        let block_4 = Block {
-            block_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a"
-                .to_string(),
-            version: 565084160,
-            parent_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c"
-                .to_string(),
-            merkle_root: "e4781238e680b8712b32696569a8f7f8a7964612cccb1cc4564c252ba0c545cf"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2621199785,
-        };
-        let block_5 = Block {
-            block_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f"
-                .to_string(),
-            version: 565624832,
-            parent_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a"
-                .to_string(),
-            merkle_root: "e4b951c8dc1318c92de34759d26098c47c0b7562b05949fc741ee80b44a3d665"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2556017316,
-        };
-        let block_6 = Block {
-            block_hash: "0000000000000d76abee84857450cfec57f49c9a2bc0e5ecbf018dc72bc8bbf7"
-                .to_string(),
-            version: 585113600,
-            parent_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f"
-                .to_string(),
-            merkle_root: "3eae91ae2faac30f4694b548caedab64b41c2147e04e5111f0f5b43de4e39904"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 3028696670,
-        };
-        let mock_chain = Chain {
-            blocks: vec![block_1, block_2, block_3, block_4, block_5, block_6],
+        "bin"
+    };
+    let cache_path = setup.cache_dir.join(format!("{}.{}", hash, extension));
+    if !args.force && cache_path.is_file() {
+        info!(cache = %cache_path.display(), "Reusing cached proof");
+        let artifact = match args.proof_mode.is_evm() {
+            true => {
+                let file = std::fs::File::open(&cache_path)?;
+                ProofArtifact::Fixture(attest(setup, serde_json::from_reader(file)?)?)
+            }
+            false => ProofArtifact::Proof(Box::new(SP1ProofWithPublicValues::load(&cache_path)?)),
         };
-        let burner_btc_address = "tb1qzfqwyxc70pmlw7l7vmx9nmhmqtgh5z3lp3j9hf".to_string();
-        BundleInfoStruct {
-            merkle_proof: mock_merkle_proof,
-            chains: mock_chain,
-            bit_tx_info: mock_tx_2,
-            burner_btc_address: burner_btc_address.into(),
+        return Ok((artifact, true));
+    }
+
+    // The input path is made absolute, so a resume does not depend on the working directory
+    setup.journal.record(JournalEntry {
+        job: hash.clone(),
+        input_hash: hash.clone(),
+        circuit: setup.circuit.clone(),
+        proof_mode: value_name(args.proof_mode),
+        phase: JobPhase::Executing,
+        input: input_json.map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf())),
+        output: Some(name.to_string()),
+        bundle: None,
+        request_id: None,
+    });
+    let proof = prove_uncached(setup, args, &stdin, &hash);
+    setup.journal.remove(&hash);
+    let proof = proof?;
+
+    std::fs::create_dir_all(&setup.cache_dir)?;
+    if !args.proof_mode.is_evm() {
+        proof.save(&cache_path)?;
+        return Ok((ProofArtifact::Proof(Box::new(proof)), false));
+    }
+    let fixture = proof_fixture(&proof, setup.vk);
+    std::fs::write(&cache_path, serde_json::to_string_pretty(&fixture)?)?;
+    Ok((ProofArtifact::Fixture(attest(setup, fixture)?), false))
+}
+
+/// Signs a fixture with the operator key, if one was given.
+fn attest(
+    setup: &ProvingSetup,
+    mut fixture: ZkpProofFixture,
+) -> Result<ZkpProofFixture, Box<dyn Error>> {
+    fixture.attestation = None;
+    if let Some(operator) = &setup.operator {
+        sign_fixture(&mut fixture, operator)?;
+    }
+    Ok(fixture)
+}
+
+/// Executes and proves an input that is not cached, journaling each phase under its hash.
+fn prove_uncached(
+    setup: &ProvingSetup,
+    args: &EVMArgs,
+    stdin: &SP1Stdin,
+    hash: &str,
+) -> Result<SP1ProofWithPublicValues, Box<dyn Error>> {
+    let ProvingSetup {
+        client, elf, pk, ..
+    } = *setup;
+
+    // Execute first: a bundle the circuit panics on fails in seconds instead of hours, and
+    // the cycle count drives the progress ETA. Mock proofs return at once and need neither.
+    let _prove_span = info_span!("prove", input_hash = %hash, prover = ?args.prover).entered();
+    let progress = match args.prover {
+        ProverBackend::Mock => None,
+        _ => {
+            let (_, report) =
+                info_span!("execute").in_scope(|| client.execute(elf, stdin).run())?;
+            let label = format!("{:?}-{:?}", args.prover, args.proof_mode).to_lowercase();
+            Some(ProvingProgress::start(
+                &label,
+                report.total_instruction_count(),
+            ))
         }
     };
+    setup.journal.set_phase(hash, JobPhase::Proving);
 
-    // Setup the inputs.
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&bundle_data);
+    // Generate the proof based on the selected proof system.
+    let proof = match (args.prover, args.proof_mode) {
+        (ProverBackend::Network, _) => prove_on_network(setup, stdin, args, hash)?,
+        (_, ProofMode::Plonk) => client.prove(pk, stdin).plonk().run()?,
+        (_, ProofMode::Groth16) => client.prove(pk, stdin).groth16().run()?,
+        (_, ProofMode::Compressed) => client.prove(pk, stdin).compressed().run()?,
+        (_, ProofMode::Core) => client.prove(pk, stdin).core().run()?,
+    };
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    Ok(proof)
+}
 
-    println!("Proof System: {:?}", args.system);
-    println!("Circuit: {:?}", args.circuit);
+/// Proves again the jobs of this circuit and proof mode that an interrupted run left in the
+/// journal; jobs of another circuit or proof mode stay journaled for a matching run.
+fn resume_journal(setup: &ProvingSetup, args: &EVMArgs, fixture_name: &str, output_dir: &Path) {
+    let proof_mode = value_name(args.proof_mode);
+    let (jobs, others): (Vec<_>, Vec<_>) = setup
+        .journal
+        .entries()
+        .into_iter()
+        .partition(|entry| entry.circuit == setup.circuit && entry.proof_mode == proof_mode);
+    for entry in &others {
+        warn!(
+            job = %entry.job,
+            circuit = %entry.circuit,
+            proof_mode = %entry.proof_mode,
+            "Journaled job is for another --circuit or --proof-mode; leaving it in the journal"
+        );
+    }
+    info!(
+        count = jobs.len(),
+        journal = %setup.journal.path().display(),
+        "Resuming journaled jobs"
+    );
 
-    // Generate the proof based on the selected proof system.
-    let proof = match args.system {
-        ProofSystem::Plonk => client.prove(&pk, &stdin).plonk().run(),
-        ProofSystem::Groth16 => client.prove(&pk, &stdin).groth16().run(),
+    let mut failed = 0;
+    for entry in jobs {
+        let _resume_span = info_span!("resume", job = %entry.job, phase = ?entry.phase).entered();
+        let name = entry.output.as_deref().unwrap_or(fixture_name);
+        let result = prove_input(setup, args, entry.input.as_deref(), name)
+            .and_then(|(artifact, _)| write_artifact(&artifact, args.proof_mode, name, output_dir));
+        // An input edited since the interruption is journaled under a new hash
+        setup.journal.remove(&entry.job);
+        match result {
+            Ok(path) => info!(output = %path.display(), "Journaled job finished"),
+            Err(e) => {
+                error!(error = %e, "Failed to resume journaled job");
+                failed += 1;
+            }
+        }
     }
-    .expect("failed to generate proof");
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
 
-    create_proof_fixture(&proof, &vk, args.system, fixture_name);
+/// Name of a flag value as typed on the command line, e.g. `mint-batch`.
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
 }
 
-/// Create a fixture for the given proof.
-fn create_proof_fixture(
-    proof: &SP1ProofWithPublicValues,
-    vk: &SP1VerifyingKey,
-    system: ProofSystem,
-    circuit_name: &str,
-) {
-    let bytes = proof.public_values.as_slice();
+/// Proves on the SP1 prover network. The request id is persisted next to the cache entry and
+/// in the journal, so a run interrupted while waiting resumes the same request instead of
+/// paying for a new proof.
+fn prove_on_network(
+    setup: &ProvingSetup,
+    stdin: &SP1Stdin,
+    args: &EVMArgs,
+    hash: &str,
+) -> Result<SP1ProofWithPublicValues, Box<dyn Error>> {
+    let prover = ProverClient::builder().network().build();
+    let request_path = setup.cache_dir.join(format!("{}.request", hash));
+    let persisted = match args.force {
+        true => None,
+        false => std::fs::read_to_string(&request_path)
+            .ok()
+            .and_then(|request_id| request_id.trim().parse::<B256>().ok())
+            .or_else(|| setup.journal.get(hash).and_then(|entry| entry.request_id)),
+    };
+    let request_id = match args.resume.or(persisted) {
+        Some(request_id) => {
+            info!(%request_id, "Resuming prover network request");
+            request_id
+        }
+        None => {
+            let request_id = with_retries("Proof request", args.retries, || {
+                let builder = prover.prove(setup.pk, stdin);
+                match args.proof_mode {
+                    ProofMode::Plonk => builder.plonk(),
+                    ProofMode::Groth16 => builder.groth16(),
+                    ProofMode::Compressed => builder.compressed(),
+                    ProofMode::Core => builder.core(),
+                }
+                .request()
+            })?;
+            if let Some(dir) = request_path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&request_path, request_id.to_string())?;
+            setup.journal.set_request_id(hash, request_id);
+            info!(
+                %request_id,
+                "Submitted prover network request; rerun with --resume if interrupted"
+            );
+            request_id
+        }
+    };
+
+    // The proof keeps running on the network while the wait is retried
+    let runtime = tokio::runtime::Runtime::new()?;
+    let timeout = Duration::from_secs(args.timeout_secs);
+    let proof = with_retries("Proof wait", args.retries, || {
+        runtime.block_on(prover.wait_proof::<SP1ProofWithPublicValues>(request_id, Some(timeout)))
+    })?;
+    // A finished request must not be resumed for a later proof of the same input
+    let _ = std::fs::remove_file(&request_path);
+    Ok(proof)
+}
+
+/// Runs `attempt` up to `retries + 1` times, waiting longer after each failure.
+fn with_retries<T, E: Display + Into<Box<dyn Error>>>(
+    what: &str,
+    retries: u32,
+    mut attempt: impl FnMut() -> Result<T, E>,
+) -> Result<T, Box<dyn Error>> {
+    let mut failures = 0;
+    loop {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(e) if failures < retries => {
+                failures += 1;
+                let delay = RETRY_BASE_DELAY_SECS * u64::from(failures);
+                warn!(
+                    error = %e,
+                    retry = failures,
+                    retries,
+                    delay_secs = delay,
+                    "{} failed",
+                    what
+                );
+                std::thread::sleep(Duration::from_secs(delay));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Writes the circuit input to stdin, from a JSON file or the mock bundle, with `--network`
+/// applied, and returns it with its canonical JSON encoding.
+fn circuit_input(
+    circuit: CircuitType,
+    input_json: Option<&Path>,
+    network: Option<BitcoinNetwork>,
+) -> Result<(SP1Stdin, Vec<u8>), Box<dyn Error>> {
+    let mut stdin = SP1Stdin::new();
+    let canonical_input = match circuit {
+        CircuitType::MintBatch => {
+            let mut batch = match input_json {
+                Some(path) => read_input_json::<MintBatchBundleStruct>(path)?,
+                None => MintBatchBundleStruct::from(mock_bundle()),
+            };
+            apply_network(network, &mut batch.network);
+            write_input(&mut stdin, &batch)
+        }
+        CircuitType::BurnBatch => {
+            let mut batch = match input_json {
+                Some(path) => read_input_json::<BurnBatchBundleStruct>(path)?,
+                None => BurnBatchBundleStruct::from(mock_bundle()),
+            };
+            apply_network(network, &mut batch.network);
+            write_input(&mut stdin, &batch)
+        }
+        CircuitType::Mint | CircuitType::Burn => {
+            let mut bundle = match input_json {
+                Some(path) => read_input_json::<BundleInfoStruct>(path)?,
+                None => mock_bundle(),
+            };
+            apply_network(network, &mut bundle.network);
+            write_input(&mut stdin, &bundle)
+        }
+    };
+    Ok((stdin, canonical_input))
+}
+
+/// Writes an input to stdin and returns its JSON re-serialization, which does not depend on
+/// the whitespace or key order of the file it was read from.
+fn write_input<T: Serialize>(stdin: &mut SP1Stdin, input: &T) -> Vec<u8> {
+    stdin.write(input);
+    serde_json::to_vec(input).unwrap()
+}
+
+/// Hex SHA-256 over the program verification key, the proof mode and the canonical input.
+fn input_hash(canonical_input: &[u8], vk: &SP1VerifyingKey, proof_mode: ProofMode) -> String {
+    let mut engine = sha256::Hash::engine();
+    engine.input(vk.bytes32().as_bytes());
+    engine.input(format!("{:?}", proof_mode).as_bytes());
+    engine.input(canonical_input);
+    sha256::Hash::from_engine(engine).to_string()
+}
+
+/// Lists the `*.json` files of a directory in name order.
+fn list_input_jsons(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// Directory the fixtures are written to, created if missing.
+fn fixture_dir(output: Option<&Path>) -> PathBuf {
+    let fixture_path = match output {
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../contracts/src/fixtures"),
+    };
+    std::fs::create_dir_all(&fixture_path).expect("failed to create fixture path");
+    fixture_path
+}
 
-    let fixture = ZkpProofFixture {
+/// Builds the fixture consumed by the contracts for the given proof.
+fn proof_fixture(proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey) -> ZkpProofFixture {
+    ZkpProofFixture {
         vkey: vk.bytes32().to_string(),
-        public_value: format!("0x{}", hex::encode(bytes)),
+        public_value: format!("0x{}", hex::encode(proof.public_values.as_slice())),
         proof: format!("0x{}", hex::encode(proof.bytes())),
-    };
+        attestation: None,
+        tss_attestation: None,
+    }
+}
+
+/// Writes the fixture or the SP1 proof of a proving run and returns the path written to.
+fn write_artifact(
+    artifact: &ProofArtifact,
+    proof_mode: ProofMode,
+    circuit_name: &str,
+    output_dir: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    match artifact {
+        ProofArtifact::Fixture(fixture) => Ok(create_proof_fixture(
+            fixture,
+            proof_mode,
+            circuit_name,
+            output_dir,
+        )),
+        ProofArtifact::Proof(proof) => {
+            let _span = info_span!("write_proof", circuit = circuit_name).entered();
+            let proof_path = output_dir
+                .join(format!("{:?}-proof_{}.bin", proof_mode, circuit_name).to_lowercase());
+            proof.save(&proof_path)?;
+            info!(
+                path = %proof_path.display(),
+                public_values = %format!("0x{}", hex::encode(proof.public_values.as_slice())),
+                "SP1 proof written"
+            );
+            Ok(proof_path)
+        }
+    }
+}
+
+/// Write the fixture of a proof and return the path it was written to.
+fn create_proof_fixture(
+    fixture: &ZkpProofFixture,
+    system: ProofMode,
+    circuit_name: &str,
+    output_dir: &Path,
+) -> PathBuf {
+    let _span = info_span!("write_fixture", circuit = circuit_name).entered();
 
     // The verification key is used to verify that the proof corresponds to the execution of the
     // program on the given input.
-    println!("Verification Key: {}", fixture.vkey);
-
     // The public values are the values which are publicly committed to by the zkVM.
-    println!("Public Values: {}", fixture.public_value);
-
     // The proof proves to the verifier that the program was executed with some inputs that led to
     // the give public values.
-    println!("Proof Bytes: {}", fixture.proof);
+    info!(
+        vkey = %fixture.vkey,
+        public_values = %fixture.public_value,
+        proof = %fixture.proof,
+        "Proof fixture"
+    );
 
     // Save the fixture to a file.
-    let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../contracts/src/fixtures");
-    std::fs::create_dir_all(&fixture_path).expect("failed to create fixture path");
+    let fixture_path =
+        output_dir.join(format!("{:?}-fixture_{}.json", system, circuit_name).to_lowercase());
     std::fs::write(
-        fixture_path.join(format!("{:?}-fixture_{}.json", system, circuit_name).to_lowercase()),
-        serde_json::to_string_pretty(&fixture).unwrap(),
+        &fixture_path,
+        serde_json::to_string_pretty(fixture).unwrap(),
     )
     .expect("failed to write fixture");
+    info!(path = %fixture_path.display(), "Fixture written");
+    fixture_path
+}
+
+/// Reads a circuit input bundle from a JSON file and checks it against the input schema.
+fn read_input_json<T: InputSchema>(path: &Path) -> Result<T, Box<dyn Error>> {
+    read_input(path)
+}
+
+/// Hardcoded mock deposit used when no input JSON is provided.
+fn mock_bundle() -> BundleInfoStruct {
+    let mock_tx_2 = BitcoinTrxInfoStruct {
+        raw_tx_hex: "010000000001015564819f67c2803761c4370d9a5fd950c8e6ff34d68ebacc47fd21413aa833ea0100000000ffffffff03e8030000000000001600141240e21b1e7877f77bfe66cc59eefb02d17a0a3f00000000000000002c6a2a3078613836456433343742384431303433353333666533306330374663343766334533623834396134329b020000000000001600144cf2f041e4acc16071306ab41414cab4c76cfd5002483045022100bf43ff7d1ae782368550cb14cc916d389277a0f103643fa352ea76ba2ccd731502205028ba84f39deb9ff71db91153c6f71e7f9f5f6df9258c29bb49ec0461785b75012103292a330133c26afde92f10737cc3e38ebcf7403b4e2232c4b65821c1aa55cdf800000000".into(),
+    };
+    let mock_merkle_proof = MerkleProof {
+        siblings: vec![
+            "cc4522617a92f7b27416f3cedad721949df7aec91d6e87f23ef2895c760e6eee".to_string(),
+        ],
+        pos: 1,
+    };
+    let block_1 = Block {
+        block_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f".to_string(),
+        version: 633618432,
+        parent_hash: "0000000000000bf53edcfa982a0cbcaab1abf62660ec3ec67149df036891b32b".to_string(),
+        merkle_root: "214101dabc8c2b1e02999995163f31b187351c8ac1dad611e2660c2c4cae5ac6".to_string(),
+        timestamp: 1744638928,
+        difficulty: 437256176,
+        nonce: 4137494058,
+    };
+    let block_2 = Block {
+        block_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82".to_string(),
+        version: 770375680,
+        parent_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f".to_string(),
+        merkle_root: "b4ce4f3646fd93a8ffed7711840a09039722919c45ff1beb029d5f3027c32858".to_string(),
+        timestamp: 1744638928,
+        difficulty: 437256176,
+        nonce: 2932452395,
+    };
+    let block_3 = Block {
+        block_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c".to_string(),
+        version: 710811648,
+        parent_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82".to_string(),
+        merkle_root: "1d065531f64d5662ba174f7533bddd96632d4e530ed9df2b3d1470336f5c9daa".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2559894718,
+    };
+    let block_4 = Block {
+        block_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a".to_string(),
+        version: 565084160,
+        parent_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c".to_string(),
+        merkle_root: "e4781238e680b8712b32696569a8f7f8a7964612cccb1cc4564c252ba0c545cf".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2621199785,
+    };
+    let block_5 = Block {
+        block_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f".to_string(),
+        version: 565624832,
+        parent_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a".to_string(),
+        merkle_root: "e4b951c8dc1318c92de34759d26098c47c0b7562b05949fc741ee80b44a3d665".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2556017316,
+    };
+    let block_6 = Block {
+        block_hash: "0000000000000d76abee84857450cfec57f49c9a2bc0e5ecbf018dc72bc8bbf7".to_string(),
+        version: 585113600,
+        parent_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f".to_string(),
+        merkle_root: "3eae91ae2faac30f4694b548caedab64b41c2147e04e5111f0f5b43de4e39904".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 3028696670,
+    };
+    let mock_chain = Chain {
+        blocks: vec![block_1, block_2, block_3, block_4, block_5, block_6],
+    };
+    let burner_btc_address = "tb1qzfqwyxc70pmlw7l7vmx9nmhmqtgh5z3lp3j9hf".to_string();
+    BundleInfoStruct {
+        merkle_proof: mock_merkle_proof,
+        chains: mock_chain,
+        bit_tx_info: mock_tx_2,
+        burner_btc_address: burner_btc_address.into(),
+        burn_id: Some(0),
+        burn_policy: BurnPayoutPolicyStruct::default(),
+        network: None,
+        confirmations: None,
+        competing_chain: None,
+        retarget_info: None,
+        mint_policy: MintPolicyStruct::default(),
+        checkpoint_hash: None,
+        reference_time: None,
+        tx_count: None,
+        block_height: None,
+        deposit_request: None,
+        prevout_tx_info: None,
+        spent_prevouts: None,
+        vault_public_key: None,
+        witness_proof: None,
+        coinbase_proof: None,
+    }
 }
@@ -0,0 +1,83 @@
+//! Fee oracle service: serves the BTC fee rates and EVM gas prices aggregated by
+//! `fees::FeeOracle` at `GET /fees`, so the payout builder, `submit` and `api` share one
+//! cached, bounds-checked view of the fees instead of hardcoded values.
+
+// Usage Example:
+// cargo run --release --bin fee-oracle -- --btc-fee-source mempool=https://mempool.space/testnet/api,esplora=https://blockstream.info/testnet/api --evm-gas-rpc http://127.0.0.1:8545
+// cargo run --release --bin fee-oracle -- --config ./bridge.toml --max-fee-rate 200
+// curl http://127.0.0.1:3200/fees
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::fees::{FeeOracle, FeeOracleArgs};
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use clap::Parser;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(long, env = "FEE_ORACLE_LISTEN", default_value = "127.0.0.1:3200")]
+    listen: SocketAddr,
+    #[clap(flatten)]
+    oracle: FeeOracleArgs,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// `GET /fees`: the current quote, or `503` when no source gave a sane estimate.
+async fn fees(State(oracle): State<Arc<FeeOracle>>) -> Response {
+    // The sources are queried with a blocking client
+    let quote =
+        tokio::task::spawn_blocking(move || oracle.quote().map_err(|e| e.to_string())).await;
+    match quote {
+        Ok(Ok(quote)) => Json(quote).into_response(),
+        Ok(Err(e)) => {
+            error!(error = %e, "No fee quote");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": e }))).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let oracle = FeeOracle::new(&args.oracle).unwrap_or_else(|e| {
+        error!(error = %e, "Invalid fee oracle settings");
+        std::process::exit(1);
+    });
+    let app = Router::new()
+        .route("/fees", get(fees))
+        .with_state(Arc::new(oracle));
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .expect("failed to bind listen address");
+    info!(
+        listen = %args.listen,
+        btc_sources = args.oracle.btc_fee_source.len(),
+        evm_sources = args.oracle.evm_gas_rpc.len(),
+        "Fee oracle listening"
+    );
+    axum::serve(listener, app).await.expect("server error");
+}
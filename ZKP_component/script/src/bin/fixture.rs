@@ -0,0 +1,213 @@
+//! Reads proof fixtures without a prover: `inspect` decodes a fixture's public values into
+//! labelled fields, and `diff` compares two fixtures of the same circuit field by field.
+
+// Usage Example:
+// cargo run --release --bin fixture -- inspect --fixture ../contracts/src/fixtures/groth16-fixture_mint.json --circuit mint
+// cargo run --release --bin fixture -- diff --circuit burn ./old/groth16-fixture_burn.json ../contracts/src/fixtures/groth16-fixture_burn.json
+use alloy_sol_types::SolType;
+use bitcoin_verify_script::attestation::{fixture_signer, tss_attester};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::{
+    burn_aggregation_public_value_fields, burn_batch_public_value_fields, burn_public_value_fields,
+    mint_aggregation_public_value_fields, mint_batch_public_value_fields, mint_public_value_fields,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use lib_struct::{
+    ZkpBurnAggregationPublicValuesStruct, ZkpBurnBatchPublicValuesStruct,
+    ZkpBurnPublicValuesStruct, ZkpMintAggregationPublicValuesStruct,
+    ZkpMintBatchPublicValuesStruct, ZkpMintPublicValuesStruct, ZkpProofFixture,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    MintBatch,
+    MintAggregate,
+    Burn,
+    BurnBatch,
+    BurnAggregate,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    /// Circuit that produced the fixtures.
+    #[clap(
+        long,
+        value_enum,
+        env = "BRIDGE_CIRCUIT",
+        default_value = "mint",
+        global = true
+    )]
+    circuit: CircuitType,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prints a fixture's vkey, proof size and decoded public values.
+    Inspect {
+        /// Fixture JSON written by `evm` or `aggregate`.
+        #[clap(long)]
+        fixture: PathBuf,
+    },
+    /// Prints the fields that differ between two fixtures; exits 1 if any does.
+    Diff { old: PathBuf, new: PathBuf },
+}
+
+/// A fixture with its hex fields decoded.
+struct DecodedFixture {
+    vkey: String,
+    /// Operator attestation, as printed by `inspect`.
+    operator: String,
+    /// TSS attestation, as printed by `inspect`.
+    tss: String,
+    proof: Vec<u8>,
+    public_values: Vec<u8>,
+    fields: Vec<(String, String)>,
+}
+
+/// Labelled fields of the public values committed by `circuit`.
+fn public_value_fields(
+    circuit: CircuitType,
+    bytes: &[u8],
+) -> Result<Vec<(String, String)>, alloy_sol_types::Error> {
+    let owned = |fields: Vec<(&'static str, String)>| {
+        fields
+            .into_iter()
+            .map(|(label, value)| (label.to_string(), value))
+            .collect()
+    };
+    match circuit {
+        CircuitType::Mint => ZkpMintPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| owned(mint_public_value_fields(&decoded))),
+        CircuitType::MintBatch => ZkpMintBatchPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| mint_batch_public_value_fields(&decoded)),
+        CircuitType::MintAggregate => ZkpMintAggregationPublicValuesStruct::abi_decode(bytes)
+            .map(|decoded| owned(mint_aggregation_public_value_fields(&decoded))),
+        CircuitType::Burn => ZkpBurnPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| owned(burn_public_value_fields(&decoded))),
+        CircuitType::BurnBatch => ZkpBurnBatchPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| burn_batch_public_value_fields(&decoded)),
+        CircuitType::BurnAggregate => ZkpBurnAggregationPublicValuesStruct::abi_decode(bytes)
+            .map(|decoded| owned(burn_aggregation_public_value_fields(&decoded))),
+    }
+}
+
+fn read_fixture(circuit: CircuitType, path: &Path) -> Result<DecodedFixture, Box<dyn Error>> {
+    let file = std::fs::File::open(path)?;
+    let fixture: ZkpProofFixture = serde_json::from_reader(file)?;
+    let decode_hex = |name: &str, value: &str| {
+        hex::decode(value.trim_start_matches("0x"))
+            .map_err(|e| format!("Field {} is not valid hex: {}", name, e))
+    };
+    let public_values = decode_hex("publicValue", &fixture.public_value)?;
+    let proof = decode_hex("proof", &fixture.proof)?;
+    let fields = public_value_fields(circuit, &public_values).map_err(|e| {
+        format!(
+            "Public values do not decode as {:?} public values: {}",
+            circuit, e
+        )
+    })?;
+    let operator = match (&fixture.attestation, fixture_signer(&fixture)) {
+        (None, _) => "unsigned".to_string(),
+        (Some(_), Ok(signer)) => format!("{} (signature valid)", signer),
+        (Some(_), Err(e)) => format!("invalid attestation: {}", e),
+    };
+    let tss = match (&fixture.tss_attestation, tss_attester(&fixture)) {
+        (None, _) => "not attested".to_string(),
+        (Some(_), Ok(group_key)) => format!("group key {} (signature valid)", group_key),
+        (Some(_), Err(e)) => format!("invalid attestation: {}", e),
+    };
+    Ok(DecodedFixture {
+        vkey: fixture.vkey,
+        operator,
+        tss,
+        proof,
+        public_values,
+        fields,
+    })
+}
+
+fn inspect(fixture: &DecodedFixture) {
+    println!("vkey: {}", fixture.vkey);
+    println!("operator: {}", fixture.operator);
+    println!("tss: {}", fixture.tss);
+    println!("proof: {} bytes", fixture.proof.len());
+    println!("public values: {} bytes", fixture.public_values.len());
+    println!("-------------------------------------------");
+    for (label, value) in &fixture.fields {
+        println!("{}: {}", label, value);
+    }
+}
+
+/// Prints every difference between the fixtures and returns whether there was any.
+fn diff(old: &DecodedFixture, new: &DecodedFixture) -> bool {
+    let mut differs = false;
+    if old.vkey != new.vkey {
+        println!("vkey: {} -> {}", old.vkey, new.vkey);
+        differs = true;
+    }
+
+    // Batches may differ in length, so fields are matched by label, in the old fixture's order
+    let missing = "(absent)".to_string();
+    let value_of = |fields: &[(String, String)], label: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == label)
+            .map(|(_, value)| value.clone())
+    };
+    for (label, old_value) in &old.fields {
+        let new_value = value_of(&new.fields, label).unwrap_or_else(|| missing.clone());
+        if *old_value != new_value {
+            println!("{}: {} -> {}", label, old_value, new_value);
+            differs = true;
+        }
+    }
+    for (label, new_value) in &new.fields {
+        if value_of(&old.fields, label).is_none() {
+            println!("{}: {} -> {}", label, missing, new_value);
+            differs = true;
+        }
+    }
+
+    // Proofs of the same statement differ between runs, so this alone is reported separately
+    if old.proof != new.proof {
+        println!(
+            "proof: differs ({} -> {} bytes)",
+            old.proof.len(),
+            new.proof.len()
+        );
+        differs = true;
+    }
+    differs
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+
+    // Exit codes follow diff(1): 0 same, 1 different, 2 unreadable input
+    let load = |path: &Path| {
+        read_fixture(args.circuit, path).unwrap_or_else(|e| {
+            eprintln!("Failed to read fixture {}: {}", path.display(), e);
+            std::process::exit(2);
+        })
+    };
+    match &args.command {
+        Command::Inspect { fixture } => inspect(&load(fixture)),
+        Command::Diff { old, new } => {
+            if diff(&load(old), &load(new)) {
+                std::process::exit(1);
+            }
+            println!("Fixtures match");
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Generates conformance vectors for the mint circuit: a valid regtest deposit bundle and
+//! deliberately-broken variants of it, each with the public values the circuit must commit.
+//! The headers are mined at the regtest proof-of-work limit, so every vector is self-contained
+//! and breaks exactly one rule.
+
+// Usage Example:
+// cargo run --release --bin gen-vectors -- --group-key <x-only key>
+// cargo run --release --bin gen-vectors -- --group-key <x-only key> --output ./vectors --amount-sats 250000
+// cargo run --release --bin main -- --execute --input-json ./vectors/bad_merkle_proof.json
+use alloy_primitives::Address;
+use alloy_sol_types::SolType;
+use bitcoin::absolute::LockTime;
+use bitcoin::block::{Header, Version};
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hash_types::{BlockHash, TxMerkleNode, Txid};
+use bitcoin::hashes::Hash;
+use bitcoin::network::Network;
+use bitcoin::pow::{CompactTarget, Target};
+use bitcoin::script::{Builder, PushBytesBuf};
+use bitcoin::Witness;
+use bitcoin::{transaction, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::native::{run_mint, Rejection};
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::{mint_public_value_fields, setup_logging, LogFormat};
+use clap::Parser;
+use lib_struct::verify::{build_merkle_proof, encode_request_memo, DEFAULT_CONFIRMATIONS};
+use lib_struct::{
+    BitcoinTrxInfoStruct, Block, BundleInfoStruct, Chain, MintPolicyStruct, MintRejectReason,
+    ZkpMintPublicValuesStruct,
+};
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+/// Regtest proof-of-work limit; a header meets it within a few nonces.
+const REGTEST_BITS: u32 = 0x207f_ffff;
+/// Height of the block holding the deposit.
+const DEPOSIT_HEIGHT: u32 = 1;
+/// Minimum deposit of the vectors' policy. It is below the dust limit, so `dust_only` is
+/// rejected because its output is not counted, not because of the minimum.
+const MIN_DEPOSIT_SATS: u64 = 100;
+/// Header index whose parent hash `broken_linkage` points at the wrong block.
+const BROKEN_LINK_INDEX: usize = 3;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Directory the bundles and `manifest.json` are written to.
+    #[clap(long, default_value = "./vectors")]
+    output: PathBuf,
+    /// Ethereum address put in the deposit memo.
+    #[clap(long, default_value = "0xa86Ed347B8D1043533fe30c07Fc47f3E3b849a42")]
+    recipient: Address,
+    /// Chain id the memo names as the mint's destination; the default is Anvil's and Foundry's.
+    #[clap(long, default_value_t = 31_337)]
+    chain_id: u64,
+    /// Sats the valid deposit pays to the vault.
+    #[clap(long, default_value_t = 100_000)]
+    amount_sats: u64,
+    /// Timestamp of the deposit block; later headers follow at ten-minute intervals.
+    #[clap(long, default_value_t = 1_700_000_000)]
+    start_time: u32,
+    /// Group key the bundles name as the vault's.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// How a vector departs from the valid deposit.
+#[derive(Clone, Copy, PartialEq)]
+enum Breakage {
+    None,
+    BadMerkleProof,
+    BrokenLinkage,
+    MissingMemo,
+    DustOnly,
+}
+
+struct Case {
+    name: &'static str,
+    description: &'static str,
+    breakage: Breakage,
+    expected: MintRejectReason,
+}
+
+const CASES: [Case; 5] = [
+    Case {
+        name: "valid",
+        description: "Deposit to the vault with a recipient memo, six confirmations",
+        breakage: Breakage::None,
+        expected: MintRejectReason::None,
+    },
+    Case {
+        name: "bad_merkle_proof",
+        description: "The Merkle proof's sibling has a flipped byte, so it misses the root",
+        breakage: Breakage::BadMerkleProof,
+        expected: MintRejectReason::MerkleRootMismatch,
+    },
+    Case {
+        name: "broken_linkage",
+        description: "The fourth header builds on the second, skipping the third",
+        breakage: Breakage::BrokenLinkage,
+        expected: MintRejectReason::InvalidChain,
+    },
+    Case {
+        name: "missing_memo",
+        description: "The deposit carries no OP_RETURN recipient memo",
+        breakage: Breakage::MissingMemo,
+        expected: MintRejectReason::MissingMemo,
+    },
+    Case {
+        name: "dust_only",
+        description: "The only output to the vault is one sat below the dust limit",
+        breakage: Breakage::DustOnly,
+        expected: MintRejectReason::BelowMinimumDeposit,
+    },
+];
+
+/// Coinbase of the block at `height`, paying to an anyone-can-spend script.
+fn coinbase(height: u32) -> Transaction {
+    Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_int(height as i64)
+                .push_int(0)
+                .into_script(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::from_int_btc(50),
+            script_pubkey: ScriptBuf::new(),
+        }],
+    }
+}
+
+/// The deposit: value to the vault, then a v2 memo naming the recipient and destination chain
+/// in an OP_RETURN output.
+fn deposit(args: &Args, breakage: Breakage) -> Result<Transaction, Box<dyn Error>> {
+    let vault_spk = args.group_key.vault_script();
+    let value = match breakage {
+        Breakage::DustOnly => vault_spk.minimal_non_dust() - Amount::from_sat(1),
+        _ => Amount::from_sat(args.amount_sats),
+    };
+    let mut output = vec![TxOut {
+        value,
+        script_pubkey: vault_spk,
+    }];
+    if breakage != Breakage::MissingMemo {
+        let memo = PushBytesBuf::try_from(encode_request_memo(args.recipient, 0, args.chain_id))?;
+        output.push(TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::new_op_return(memo),
+        });
+    }
+    Ok(Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        // Spends a fixed, made-up outpoint: the circuit never looks at the funding transaction
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::from_byte_array([0x11; 32]), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output,
+    })
+}
+
+/// Mines a header on `parent` at the regtest proof-of-work limit.
+fn mine(parent: BlockHash, merkle_root: TxMerkleNode, time: u32) -> Header {
+    let bits = CompactTarget::from_consensus(REGTEST_BITS);
+    let target = Target::from_compact(bits);
+    let mut header = Header {
+        version: Version::from_consensus(0x2000_0000),
+        prev_blockhash: parent,
+        merkle_root,
+        time,
+        bits,
+        nonce: 0,
+    };
+    while header.validate_pow(target).is_err() {
+        header.nonce += 1;
+    }
+    header
+}
+
+fn block(header: &Header) -> Block {
+    Block {
+        block_hash: header.block_hash().to_string(),
+        version: header.version.to_consensus() as u32,
+        parent_hash: header.prev_blockhash.to_string(),
+        merkle_root: header.merkle_root.to_string(),
+        timestamp: header.time,
+        difficulty: header.bits.to_consensus(),
+        nonce: header.nonce,
+    }
+}
+
+/// Mines the confirmation headers on the regtest genesis block, the first one committing to
+/// `merkle_root` and the others to their coinbase alone.
+fn mine_chain(args: &Args, merkle_root: TxMerkleNode, breakage: Breakage) -> Chain {
+    let mut headers: Vec<Header> = Vec::with_capacity(DEFAULT_CONFIRMATIONS);
+    for i in 0..DEFAULT_CONFIRMATIONS {
+        let parent = match (i, breakage) {
+            (0, _) => genesis_block(Network::Regtest).block_hash(),
+            (BROKEN_LINK_INDEX, Breakage::BrokenLinkage) => headers[i - 2].block_hash(),
+            _ => headers[i - 1].block_hash(),
+        };
+        let root = match i {
+            0 => merkle_root,
+            _ => TxMerkleNode::from_raw_hash(
+                coinbase(DEPOSIT_HEIGHT + i as u32)
+                    .compute_txid()
+                    .to_raw_hash(),
+            ),
+        };
+        headers.push(mine(parent, root, args.start_time + 600 * i as u32));
+    }
+    Chain {
+        blocks: headers.iter().map(block).collect(),
+    }
+}
+
+/// Builds the bundle of one case: the deposit at position 1, after the coinbase.
+fn bundle(args: &Args, breakage: Breakage) -> Result<BundleInfoStruct, Box<dyn Error>> {
+    let tx = deposit(args, breakage)?;
+    let txids = [coinbase(DEPOSIT_HEIGHT).compute_txid(), tx.compute_txid()];
+    let mut merkle_proof = build_merkle_proof(&txids, 1)?;
+    let merkle_root = TxMerkleNode::from_raw_hash(
+        bitcoin::merkle_tree::calculate_root(txids.iter().map(|txid| txid.to_raw_hash()))
+            .ok_or("a block has at least one transaction")?,
+    );
+    if breakage == Breakage::BadMerkleProof {
+        let mut sibling = hex::decode(&merkle_proof.siblings[0])?;
+        sibling[0] ^= 0x01;
+        merkle_proof.siblings[0] = hex::encode(sibling);
+    }
+
+    Ok(BundleInfoStruct {
+        merkle_proof,
+        chains: mine_chain(args, merkle_root, breakage),
+        bit_tx_info: BitcoinTrxInfoStruct {
+            raw_tx_hex: serialize_hex(&tx),
+        },
+        burner_btc_address: None,
+        burn_id: None,
+        burn_policy: Default::default(),
+        network: Some(Network::Regtest.to_string()),
+        confirmations: None,
+        competing_chain: None,
+        retarget_info: None,
+        mint_policy: MintPolicyStruct {
+            min_deposit_sats: MIN_DEPOSIT_SATS,
+            fee_bps: 0,
+            flat_fee_sats: 0,
+        },
+        checkpoint_hash: None,
+        reference_time: None,
+        tx_count: Some(txids.len() as u32),
+        block_height: Some(DEPOSIT_HEIGHT),
+        prevout_tx_info: None,
+        spent_prevouts: None,
+        vault_public_key: Some(args.group_key.group_key.to_string()),
+        witness_proof: None,
+        coinbase_proof: None,
+        deposit_request: None,
+    })
+}
+
+/// Writes the bundle of `case` and returns its manifest entry, after checking the native
+/// model reaches the verdict the case was built for.
+fn write_vector(args: &Args, case: &Case) -> Result<Value, Box<dyn Error>> {
+    let bundle = bundle(args, case.breakage)?;
+    let native = run_mint(&bundle);
+    let (check, reason, detail) = match &native.failure {
+        None => (None, format!("{:?}", MintRejectReason::None), None),
+        Some((check, Rejection { reason, detail })) => {
+            (Some(*check), reason.clone(), Some(detail.clone()))
+        }
+    };
+    if reason != format!("{:?}", case.expected) {
+        return Err(format!(
+            "{}: expected {:?}, but the native model gives {}",
+            case.name, case.expected, reason
+        )
+        .into());
+    }
+    let public_values = native.public_values;
+    let decoded = ZkpMintPublicValuesStruct::abi_decode_params(&public_values)?;
+    let fields: Map<String, Value> = mint_public_value_fields(&decoded)
+        .into_iter()
+        .map(|(label, value)| (label.to_string(), Value::String(value)))
+        .collect();
+
+    let file = format!("{}.json", case.name);
+    std::fs::write(
+        args.output.join(&file),
+        serde_json::to_string_pretty(&bundle)?,
+    )?;
+    info!(vector = case.name, reason = %reason, "Vector written");
+    Ok(json!({
+        "name": case.name,
+        "description": case.description,
+        "bundle": file,
+        "expected": {
+            "isValid": decoded.is_valid,
+            "rejectReason": reason,
+            "check": check,
+            "detail": detail,
+            "publicValues": format!("0x{}", hex::encode(&public_values)),
+            "fields": fields,
+        },
+    }))
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(&args.output)?;
+    let vectors = CASES
+        .iter()
+        .map(|case| write_vector(args, case))
+        .collect::<Result<Vec<Value>, _>>()?;
+    let manifest = json!({
+        "circuit": "mint",
+        "network": Network::Regtest.to_string(),
+        "vaultScript": format!("0x{}", args.group_key.vault_script().to_hex_string()),
+        "vectors": vectors,
+    });
+    let path = args.output.join("manifest.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+    info!(path = %path.display(), vectors = CASES.len(), "Manifest written");
+    Ok(())
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    if let Err(e) = run(&args) {
+        error!(error = %e, "Vector generation failed");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// The checked-in vectors, generated with this group key and every other flag at its default.
+    const VECTORS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../vectors");
+    const GROUP_KEY: &str = "192c2a30cbd7d0352f94d4c31a43f767f1ef43d7ff6438839880ec075d81a9f6";
+
+    fn read_json(file: &str) -> Value {
+        let text = std::fs::read_to_string(Path::new(VECTORS_DIR).join(file)).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn checked_in_vectors_commit_their_public_values() {
+        let manifest = read_json("manifest.json");
+        for vector in manifest["vectors"].as_array().unwrap() {
+            let name = vector["name"].as_str().unwrap();
+            let bundle: BundleInfoStruct =
+                serde_json::from_value(read_json(vector["bundle"].as_str().unwrap())).unwrap();
+            let expected = &vector["expected"];
+            let public_values = hex::decode(
+                expected["publicValues"]
+                    .as_str()
+                    .unwrap()
+                    .trim_start_matches("0x"),
+            )
+            .unwrap();
+
+            let decoded = ZkpMintPublicValuesStruct::abi_decode_params(&public_values).unwrap();
+            assert_eq!(
+                ZkpMintPublicValuesStruct::abi_encode_params(&decoded),
+                public_values,
+                "{}: public values are not in the encoding the contract reads",
+                name
+            );
+            assert_eq!(decoded.is_valid, expected["isValid"].as_bool().unwrap());
+            assert_eq!(
+                format!(
+                    "{:?}",
+                    MintRejectReason::from_code(decoded.reject_reason).unwrap()
+                ),
+                expected["rejectReason"].as_str().unwrap(),
+                "{}",
+                name
+            );
+            assert_eq!(
+                hex::encode(run_mint(&bundle).public_values),
+                hex::encode(&public_values),
+                "{}: the native model commits different public values",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn generator_reproduces_checked_in_vectors() {
+        let args = Args::parse_from(["gen-vectors", "--group-key", GROUP_KEY]);
+        for case in &CASES {
+            let bundle = serde_json::to_value(bundle(&args, case.breakage).unwrap()).unwrap();
+            assert_eq!(
+                bundle,
+                read_json(&format!("{}.json", case.name)),
+                "{} is stale; regenerate the vectors",
+                case.name
+            );
+        }
+        assert_eq!(
+            read_json("manifest.json")["vaultScript"],
+            format!("0x{}", args.group_key.vault_script().to_hex_string())
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Syncs the local Bitcoin header chain (`--headers-db`) from the backend of `build-bundle`:
+//! every header from the checkpoint up is validated before it is stored. Run it once to catch
+//! up from a checkpoint before `watch` or `build-bundle` use the chain, or with `--follow` to
+//! keep it at the tip.
+
+// Usage Example:
+// cargo run --release --bin headers -- --headers-db ./headers-db --checkpoint <height>:<hash> --rpc-url http://127.0.0.1:18332
+// cargo run --release --bin headers -- --config ./bridge.toml --source esplora --follow
+// cargo run --release --bin headers -- --headers-db ./headers-db --height 2500000
+use bitcoin_verify_script::bundle::SourceArgs;
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::header_chain::{HeaderChain, StoredHeader};
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use clap::Parser;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Keep syncing every `--poll-interval` seconds instead of exiting at the tip.
+    #[clap(long)]
+    follow: bool,
+    /// Seconds between two syncs with `--follow`.
+    #[clap(long, default_value_t = 30)]
+    poll_interval: u64,
+    /// Print the stored header at this height and exit, without syncing.
+    #[clap(long)]
+    height: Option<u32>,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+fn print_header(label: &str, header: &StoredHeader) {
+    println!(
+        "{} {}: {} (bits {:#010x}, time {}, chain work {:#x})",
+        label,
+        header.height,
+        header.hash(),
+        header.header.bits.to_consensus(),
+        header.header.time,
+        header.chain_work,
+    );
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .source
+        .headers_db
+        .as_deref()
+        .ok_or("--headers-db is required")?;
+    let source = args.source.connect();
+    let network = match args.source.network {
+        Some(network) => network,
+        None => source.network()?,
+    };
+    let chain = HeaderChain::open(
+        path,
+        network,
+        args.source.checkpoint.as_ref(),
+        source.as_ref(),
+    )?;
+
+    if let Some(height) = args.height {
+        let header = chain
+            .get(height)?
+            .ok_or_else(|| format!("No stored header at height {}", height))?;
+        print_header("height", &header);
+        return Ok(());
+    }
+
+    print_header("checkpoint", &chain.base()?);
+    let tip = chain.sync(source.as_ref())?;
+    print_header("tip", &tip);
+    if !args.follow {
+        return Ok(());
+    }
+    let mut tip_height = tip.height;
+    loop {
+        std::thread::sleep(Duration::from_secs(args.poll_interval));
+        match chain.sync(source.as_ref()) {
+            Ok(tip) if tip.height != tip_height => {
+                info!(height = tip.height, hash = %tip.hash(), "Header chain tip");
+                tip_height = tip.height;
+            }
+            Ok(_) => {}
+            Err(e) => warn!(error = %e, "Header sync failed, retrying"),
+        }
+    }
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    if let Err(e) = run(&args) {
+        error!(error = %e, "Header chain sync failed");
+        std::process::exit(1);
+    }
+}
@@ -2,216 +2,328 @@
 //! Usage examples:
 //!   RUST_LOG=info cargo run --release --bin main -- --circuit mint --execute --input-json ./input.json
 //!   RUST_LOG=info cargo run --release --bin main -- --circuit burn --prove
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit burn --prove --prover mock
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit mint-batch --execute --input-json ./batch.json
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit burn-batch --execute --input-json ./burn_batch.json
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit mint --execute --log-format json
+//!   RUST_LOG=info cargo run --release --bin main -- --config ./bridge.toml --execute
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit burn --execute --input-json ./burn.json --dry-run
+//!   RUST_LOG=info cargo run --release --bin main -- --circuit mint --execute --input-json ./regtest.json --network regtest
 
-use alloy_primitives::U256;
 use alloy_sol_types::SolType;
-
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::input::{read_input, InputSchema};
+use bitcoin_verify_script::native::{run_burn, run_mint};
+use bitcoin_verify_script::{
+    apply_network, burn_public_value_fields, mint_public_value_fields,
+    print_burn_batch_public_values, print_burn_public_values, print_mint_batch_public_values,
+    print_mint_public_values, setup_logging, BitcoinNetwork, LogFormat, PrintPublicValues,
+    ProverBackend, ProvingProgress,
+};
 use clap::{Parser, ValueEnum};
 use lib_struct::{
-    BitcoinTrxInfoStruct, Block, BundleInfoStruct, Chain, MerkleProof, ZkpMintPublicValuesStruct,
+    BitcoinTrxInfoStruct, Block, BundleInfoStruct, BurnBatchBundleStruct, BurnPayoutPolicyStruct,
+    Chain, MerkleProof, MintBatchBundleStruct, MintPolicyStruct, ZkpBurnPublicValuesStruct,
+    ZkpMintPublicValuesStruct,
 };
-use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
-use std::path::PathBuf;
+use sp1_sdk::{include_elf, SP1Stdin};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, info_span};
 
 pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("mint_batch_circuit");
 pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 enum CircuitType {
     Mint,
+    MintBatch,
     Burn,
+    BurnBatch,
 }
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
     #[clap(long)]
     execute: bool,
     #[clap(long)]
     prove: bool,
-    #[clap(long, value_enum, default_value = "mint")]
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
     circuit: CircuitType,
     #[clap(long)]
     input_json: Option<PathBuf>,
+    /// Bitcoin network the circuit verifies the bundle on, written into its `network` field;
+    /// without it the bundle's own `network` applies, else testnet.
+    #[clap(long, value_enum, env = "BITCOIN_NETWORK")]
+    network: Option<BitcoinNetwork>,
+    /// SP1 prover backend; falls back to `SP1_PROVER`, then the CPU prover.
+    #[clap(long, value_enum, env = "SP1_PROVER", default_value = "cpu")]
+    prover: ProverBackend,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+    /// Derives the expected public values natively from the bundle and compares them with the
+    /// ones the circuit commits; exits non-zero on any difference. Mint and burn only.
+    #[clap(long)]
+    dry_run: bool,
 }
 
 fn main() {
-    sp1_sdk::utils::setup_logger();
+    apply_config_file();
     dotenv::dotenv().ok();
 
-    let args = Args::parse();
+    let mut args = Args::parse();
+    setup_logging(args.log_format);
+    args.prover = args.prover.resolve();
 
     if args.execute == args.prove {
-        eprintln!("Error: Specify either --execute or --prove, not both or neither");
+        error!("Specify either --execute or --prove, not both or neither");
         std::process::exit(1);
     }
 
-    let client = ProverClient::from_env();
+    let client = args.prover.client();
 
     // Select circuit ELF and output decoder
-    let (elf, decode_output): (&[u8], fn(&[u8])) = match args.circuit {
-        CircuitType::Mint => (MINT_CIRCUIT_ELF, |bytes| {
-            let decoded = ZkpMintPublicValuesStruct::abi_decode(bytes).unwrap();
-            println!("-------------------------------------------");
-            println!("tx_id: {:?}", decoded.tx_id);
-            println!("depositer eth address: {:?}", decoded.depositer_address);
-            println!("amount: {:?}", decoded.amount);
-            println!("is valid or not: {:?}", decoded.is_valid);
-        }),
-        CircuitType::Burn => (BURN_CIRCUIT_ELF, |bytes| {
-            // Manual decoding to match abi_encode_zkp_burn in burn.rs
-
-            // 1) Offset to string (skip, always 96)
-            // 2) Amount (uint256)
-            // 3) is_valid (uint256)
-            // 4) String length (uint256)
-            // 5) String bytes (padded to 32 bytes)
-
-            if bytes.len() < 128 {
-                println!("Output too short for decoding burn public values");
-                return;
-            }
-
-            // Amount: bytes 32..64
-            let amount = U256::from_be_slice(&bytes[32..64]);
-            // is_valid: bytes 64..96
-            let is_valid = U256::from_be_slice(&bytes[64..96]) == U256::from(1u8);
-
-            // String length: bytes 96..128
-            let str_len = U256::from_be_slice(&bytes[96..128]).to::<usize>();
-            // String bytes: bytes 128..128+str_len
-            let str_start = 128;
-            let str_end = str_start + str_len;
-            let btc_addr = if bytes.len() >= str_end {
-                String::from_utf8_lossy(&bytes[str_start..str_end]).to_string()
-            } else {
-                "<decode error>".to_string()
-            };
-
-            println!("-------------------------------------------");
-            println!("Burner btc address: {:?}", btc_addr);
-            println!("amount: {:?}", amount);
-            println!("is valid or not: {:?}", is_valid);
-        }),
+    let (elf, decode_output): (&[u8], PrintPublicValues) = match args.circuit {
+        CircuitType::Mint => (MINT_CIRCUIT_ELF, print_mint_public_values),
+        CircuitType::MintBatch => (MINT_BATCH_CIRCUIT_ELF, print_mint_batch_public_values),
+        CircuitType::Burn => (BURN_CIRCUIT_ELF, print_burn_public_values),
+        CircuitType::BurnBatch => (BURN_BATCH_CIRCUIT_ELF, print_burn_batch_public_values),
     };
 
     // Load input from JSON if provided, else fallback to mock data
-    let bundle_data: BundleInfoStruct = if let Some(ref path) = args.input_json {
-        let file = std::fs::File::open(path).expect("failed to open input JSON");
-        serde_json::from_reader(file).expect("failed to parse input JSON")
-    } else {
-        // Fallback to hardcoded mock data
-        let mock_tx = BitcoinTrxInfoStruct {
-            raw_tx_hex: "010000000001015564819f67c2803761c4370d9a5fd950c8e6ff34d68ebacc47fd21413aa833ea0100000000ffffffff03e8030000000000001600141240e21b1e7877f77bfe66cc59eefb02d17a0a3f00000000000000002c6a2a3078613836456433343742384431303433353333666533306330374663343766334533623834396134329b020000000000001600144cf2f041e4acc16071306ab41414cab4c76cfd5002483045022100bf43ff7d1ae782368550cb14cc916d389277a0f103643fa352ea76ba2ccd731502205028ba84f39deb9ff71db91153c6f71e7f9f5f6df9258c29bb49ec0461785b75012103292a330133c26afde92f10737cc3e38ebcf7403b4e2232c4b65821c1aa55cdf800000000".into(),
-        };
-        let mock_merkle_proof = MerkleProof {
-            siblings: vec![
-                "cc4522617a92f7b27416f3cedad721949df7aec91d6e87f23ef2895c760e6eee".to_string(),
-            ],
-            pos: 1,
-        };
-        let block_1 = Block {
-            block_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f"
-                .to_string(),
-            version: 633618432,
-            parent_hash: "0000000000000bf53edcfa982a0cbcaab1abf62660ec3ec67149df036891b32b"
-                .to_string(),
-            merkle_root: "214101dabc8c2b1e02999995163f31b187351c8ac1dad611e2660c2c4cae5ac6"
-                .to_string(),
-            timestamp: 1744638928,
-            difficulty: 437256176,
-            nonce: 4137494058,
-        };
-        let block_2 = Block {
-            block_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82"
-                .to_string(),
-            version: 770375680,
-            parent_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f"
-                .to_string(),
-            merkle_root: "b4ce4f3646fd93a8ffed7711840a09039722919c45ff1beb029d5f3027c32858"
-                .to_string(),
-            timestamp: 1744638928,
-            difficulty: 437256176,
-            nonce: 2932452395,
-        };
-        let block_3 = Block {
-            block_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c"
-                .to_string(),
-            version: 710811648,
-            parent_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82"
-                .to_string(),
-            merkle_root: "1d065531f64d5662ba174f7533bddd96632d4e530ed9df2b3d1470336f5c9daa"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2559894718,
-        };
-        let block_4 = Block {
-            block_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a"
-                .to_string(),
-            version: 565084160,
-            parent_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c"
-                .to_string(),
-            merkle_root: "e4781238e680b8712b32696569a8f7f8a7964612cccb1cc4564c252ba0c545cf"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2621199785,
-        };
-        let block_5 = Block {
-            block_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f"
-                .to_string(),
-            version: 565624832,
-            parent_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a"
-                .to_string(),
-            merkle_root: "e4b951c8dc1318c92de34759d26098c47c0b7562b05949fc741ee80b44a3d665"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 2556017316,
-        };
-        let block_6 = Block {
-            block_hash: "0000000000000d76abee84857450cfec57f49c9a2bc0e5ecbf018dc72bc8bbf7"
-                .to_string(),
-            version: 585113600,
-            parent_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f"
-                .to_string(),
-            merkle_root: "3eae91ae2faac30f4694b548caedab64b41c2147e04e5111f0f5b43de4e39904"
-                .to_string(),
-            timestamp: 1744638929,
-            difficulty: 437256176,
-            nonce: 3028696670,
-        };
-        let mock_chain = Chain {
-            blocks: vec![block_1, block_2, block_3, block_4, block_5, block_6],
-        };
-        let burner_btc_address = "tb1qzfqwyxc70pmlw7l7vmx9nmhmqtgh5z3lp3j9hf".to_string();
-        BundleInfoStruct {
-            merkle_proof: mock_merkle_proof,
-            chains: mock_chain,
-            bit_tx_info: mock_tx,
-            burner_btc_address: burner_btc_address.into(),
+    let parse_span = info_span!("bundle_parse", circuit = ?args.circuit).entered();
+    let mut stdin = SP1Stdin::new();
+    let bundle = match (args.circuit, args.input_json.as_ref()) {
+        (CircuitType::MintBatch, path) => {
+            let mut batch = match path {
+                Some(path) => read_input_json::<MintBatchBundleStruct>(path),
+                None => MintBatchBundleStruct::from(mock_bundle()),
+            };
+            apply_network(args.network, &mut batch.network);
+            stdin.write(&batch);
+            None
         }
+        (CircuitType::BurnBatch, path) => {
+            let mut batch = match path {
+                Some(path) => read_input_json::<BurnBatchBundleStruct>(path),
+                None => BurnBatchBundleStruct::from(mock_bundle()),
+            };
+            apply_network(args.network, &mut batch.network);
+            stdin.write(&batch);
+            None
+        }
+        (_, Some(path)) => Some(read_input_json::<BundleInfoStruct>(path)),
+        (_, None) => Some(mock_bundle()),
     };
+    let bundle = bundle.map(|mut bundle| {
+        apply_network(args.network, &mut bundle.network);
+        stdin.write(&bundle);
+        bundle
+    });
+    drop(parse_span);
 
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&bundle_data);
+    // The native model is derived before the circuit runs
+    let expected = match (args.dry_run, &bundle) {
+        (false, _) => None,
+        (true, None) => {
+            error!("--dry-run supports the mint and burn circuits only");
+            std::process::exit(1);
+        }
+        (true, Some(bundle)) => Some(match args.circuit {
+            CircuitType::Mint => run_mint(bundle).public_values,
+            _ => run_burn(bundle).public_values,
+        }),
+    };
 
     if args.execute {
+        let _span = info_span!("execute", circuit = ?args.circuit).entered();
         let (output, report) = client.execute(elf, &stdin).run().unwrap();
-        decode_output(output.as_slice());
-        println!("Number of cycles: {:?}", report.total_instruction_count());
-        println!("Completed execution successfully!");
+        if let Err(e) = decode_output(output.as_slice()) {
+            error!(error = %e, "Failed to decode the execution output");
+            std::process::exit(1);
+        }
+        if let Some(expected) = &expected {
+            if !matches_native(args.circuit, expected, output.as_slice()) {
+                std::process::exit(1);
+            }
+        }
+        info!(
+            cycles = report.total_instruction_count(),
+            "Completed execution successfully"
+        );
+        for (section, cycles) in &report.cycle_tracker {
+            info!(section = %section, cycles, "Cycle tracker section");
+        }
     } else {
         let (pk, vk) = client.setup(elf);
-        let proof = client
-            .prove(&pk, &stdin)
-            .run()
+        let prove_span = info_span!("prove", circuit = ?args.circuit, prover = ?args.prover);
+        let proof = prove_span
+            .in_scope(|| {
+                // The execution's cycle count drives the progress ETA
+                let (_, report) = client.execute(elf, &stdin).run()?;
+                let label = format!("{:?}-core", args.prover).to_lowercase();
+                let progress = ProvingProgress::start(&label, report.total_instruction_count());
+                let proof = client.prove(&pk, &stdin).run()?;
+                progress.finish();
+                Ok::<_, Box<dyn std::error::Error>>(proof)
+            })
             .expect("failed to generate proof");
-        println!("Successfully generated proof!");
-        decode_output(proof.public_values.as_slice());
-        client.verify(&proof, &vk).expect("failed to verify proof");
-        println!("Successfully verified proof!");
+        info!("Successfully generated proof");
+        if let Err(e) = decode_output(proof.public_values.as_slice()) {
+            error!(error = %e, "Failed to decode the proof's public values");
+            std::process::exit(1);
+        }
+        if let Some(expected) = &expected {
+            if !matches_native(args.circuit, expected, proof.public_values.as_slice()) {
+                std::process::exit(1);
+            }
+        }
+        info_span!("verify", circuit = ?args.circuit)
+            .in_scope(|| client.verify(&proof, &vk))
+            .expect("failed to verify proof");
+        info!("Successfully verified proof");
+    }
+}
+
+/// Compares the public values committed by the circuit with the natively derived ones and
+/// logs every field that differs.
+fn matches_native(circuit: CircuitType, expected: &[u8], produced: &[u8]) -> bool {
+    if expected == produced {
+        info!("Dry run: public values match the native model");
+        return true;
+    }
+
+    let fields = |bytes: &[u8]| match circuit {
+        CircuitType::Mint => ZkpMintPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| mint_public_value_fields(&decoded)),
+        _ => ZkpBurnPublicValuesStruct::abi_decode_params(bytes)
+            .map(|decoded| burn_public_value_fields(&decoded)),
+    };
+    match fields(produced) {
+        Ok(produced_fields) => {
+            let expected_fields = fields(expected).expect("native public values decode");
+            for ((label, expected), (_, produced)) in expected_fields.iter().zip(&produced_fields) {
+                if expected != produced {
+                    error!(field = *label, %expected, %produced, "Dry run: public value differs");
+                }
+            }
+        }
+        Err(e) => error!(error = %e, "Dry run: the committed public values do not decode"),
+    }
+    // Equal fields with different bytes mean the encoding itself drifted
+    error!(
+        expected = %hex::encode(expected),
+        produced = %hex::encode(produced),
+        "Dry run: public values differ from the native model"
+    );
+    false
+}
+
+/// Reads a circuit input bundle from a JSON file, exiting with the offending field when it does
+/// not match the input schema.
+fn read_input_json<T: InputSchema>(path: &Path) -> T {
+    read_input(path).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Hardcoded mock deposit used when no input JSON is provided.
+fn mock_bundle() -> BundleInfoStruct {
+    let mock_tx = BitcoinTrxInfoStruct {
+        raw_tx_hex: "010000000001015564819f67c2803761c4370d9a5fd950c8e6ff34d68ebacc47fd21413aa833ea0100000000ffffffff03e8030000000000001600141240e21b1e7877f77bfe66cc59eefb02d17a0a3f00000000000000002c6a2a3078613836456433343742384431303433353333666533306330374663343766334533623834396134329b020000000000001600144cf2f041e4acc16071306ab41414cab4c76cfd5002483045022100bf43ff7d1ae782368550cb14cc916d389277a0f103643fa352ea76ba2ccd731502205028ba84f39deb9ff71db91153c6f71e7f9f5f6df9258c29bb49ec0461785b75012103292a330133c26afde92f10737cc3e38ebcf7403b4e2232c4b65821c1aa55cdf800000000".into(),
+    };
+    let mock_merkle_proof = MerkleProof {
+        siblings: vec![
+            "cc4522617a92f7b27416f3cedad721949df7aec91d6e87f23ef2895c760e6eee".to_string(),
+        ],
+        pos: 1,
+    };
+    let block_1 = Block {
+        block_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f".to_string(),
+        version: 633618432,
+        parent_hash: "0000000000000bf53edcfa982a0cbcaab1abf62660ec3ec67149df036891b32b".to_string(),
+        merkle_root: "214101dabc8c2b1e02999995163f31b187351c8ac1dad611e2660c2c4cae5ac6".to_string(),
+        timestamp: 1744638928,
+        difficulty: 437256176,
+        nonce: 4137494058,
+    };
+    let block_2 = Block {
+        block_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82".to_string(),
+        version: 770375680,
+        parent_hash: "00000000000002ee8b7a2baff6fc9366166d75b97301a68b0eceb3bf60f38d8f".to_string(),
+        merkle_root: "b4ce4f3646fd93a8ffed7711840a09039722919c45ff1beb029d5f3027c32858".to_string(),
+        timestamp: 1744638928,
+        difficulty: 437256176,
+        nonce: 2932452395,
+    };
+    let block_3 = Block {
+        block_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c".to_string(),
+        version: 710811648,
+        parent_hash: "00000000000003fd04b9cb97cc0f1ce28a4588d965c595dfb4dbaf9bfd8b2a82".to_string(),
+        merkle_root: "1d065531f64d5662ba174f7533bddd96632d4e530ed9df2b3d1470336f5c9daa".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2559894718,
+    };
+    let block_4 = Block {
+        block_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a".to_string(),
+        version: 565084160,
+        parent_hash: "0000000000000764853fd899f37e85d2765a1ec763dfd8bf2a1e739a9cad370c".to_string(),
+        merkle_root: "e4781238e680b8712b32696569a8f7f8a7964612cccb1cc4564c252ba0c545cf".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2621199785,
+    };
+    let block_5 = Block {
+        block_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f".to_string(),
+        version: 565624832,
+        parent_hash: "0000000000000ef1e4b025cfb3cb6ad42482deaf8551ea2d158c23189483723a".to_string(),
+        merkle_root: "e4b951c8dc1318c92de34759d26098c47c0b7562b05949fc741ee80b44a3d665".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 2556017316,
+    };
+    let block_6 = Block {
+        block_hash: "0000000000000d76abee84857450cfec57f49c9a2bc0e5ecbf018dc72bc8bbf7".to_string(),
+        version: 585113600,
+        parent_hash: "00000000000003d773169c1c0dab0a2be623b8b2357b2029d889a3078328ee5f".to_string(),
+        merkle_root: "3eae91ae2faac30f4694b548caedab64b41c2147e04e5111f0f5b43de4e39904".to_string(),
+        timestamp: 1744638929,
+        difficulty: 437256176,
+        nonce: 3028696670,
+    };
+    let mock_chain = Chain {
+        blocks: vec![block_1, block_2, block_3, block_4, block_5, block_6],
+    };
+    let burner_btc_address = "tb1qzfqwyxc70pmlw7l7vmx9nmhmqtgh5z3lp3j9hf".to_string();
+    BundleInfoStruct {
+        merkle_proof: mock_merkle_proof,
+        chains: mock_chain,
+        bit_tx_info: mock_tx,
+        burner_btc_address: burner_btc_address.into(),
+        burn_id: Some(0),
+        burn_policy: BurnPayoutPolicyStruct::default(),
+        network: None,
+        confirmations: None,
+        competing_chain: None,
+        retarget_info: None,
+        mint_policy: MintPolicyStruct::default(),
+        checkpoint_hash: None,
+        reference_time: None,
+        tx_count: None,
+        block_height: None,
+        deposit_request: None,
+        prevout_tx_info: None,
+        spent_prevouts: None,
+        vault_public_key: None,
+        witness_proof: None,
+        coinbase_proof: None,
     }
-    println!("Finish");
 }
@@ -0,0 +1,958 @@
+//! Pays the burn requests of the payout queue in batches: the ready jobs are read from
+//! `burn-listener`'s HTTP API, and once they hit a threshold (count, value, wait time or a low
+//! fee rate) one transaction spending vault outputs pays all of them. Its inputs are signed by
+//! the TSS coordinator, one signing session per input, then it is broadcast, the jobs are
+//! marked paid, and once it is final its burn-batch proof is requested from the proof service
+//! (`serve`). The vault outputs a batch spends stay reserved until it is proven or fails.
+//!
+//! The `--policy-*` hooks are checked over each payout before it is batched, and again over
+//! the batch before it is signed; a payout they deny stays pending, and a batch they deny
+//! fails. The coordinator checks its own policy over the transaction's outputs as well.
+//!
+//! While a pause notice (`--pause-notice`) is in force, no batch is planned, signed, broadcast
+//! or proven; the jobs wait in the queue.
+//!
+//! With `--bus`, a `payout-requested` event on the job bus starts a round at once instead of at
+//! the end of the poll interval; the jobs are still read from the payout queue.
+//!
+//! The queue's refunds, of deposits `submit` could not mint, are paid first, each in a batch of
+//! its own: once the deposit is `DEFAULT_CONFIRMATIONS` deep, what it paid the vault goes back
+//! to the address that funded it, less the miner fee. A refund passes the same `--policy-*`
+//! hooks and signing as a payout; once it is final, the refund is marked paid on the queue and,
+//! with `--swap-api`, recorded on its swap.
+
+// Usage Example:
+// cargo run --release --bin payout-scheduler -- --db ./payout-scheduler-db --payout-queue http://127.0.0.1:3300 --sign-command "coordinator --db ./coordinator-db sign" --fee-oracle http://127.0.0.1:3200
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --batch-count 20 --max-wait 7200 --max-fee-rate 50
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --fee-rate 4 --low-fee-rate 5
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --policy-max-payout-sats 10000000 --policy-payout-volume-sats 100000000
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --pause-notice ./pause-notice.json
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --bus redis://127.0.0.1:6379
+// cargo run --release --bin payout-scheduler -- --config ./bridge.toml --swap-api http://127.0.0.1:3100
+// cargo run --release --bin payout-scheduler -- --db ./payout-scheduler-db --list
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hash_types::Txid;
+use bitcoin::network::Network;
+use bitcoin::{Address, ScriptBuf};
+use bitcoin_verify_script::bundle::{
+    build_bundle, BundleRequest, BundleSource, BurnTarget, SourceArgs,
+};
+use bitcoin_verify_script::bus::{BusArgs, BusEvent, JobBus, Topic};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::fees::fetch_quote;
+use bitcoin_verify_script::pause::{PauseArgs, PauseGuard};
+use bitcoin_verify_script::payout::{
+    apply_signatures, plan_batch, plan_refund, sighashes, BatchArgs, BatchPayout, BatchRefund,
+    BatchStatus, BatchStore, PayoutBatch, PendingPayout,
+};
+use bitcoin_verify_script::policy::PolicyArgs;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::store::{PayoutJob, RefundJob};
+use bitcoin_verify_script::telemetry::{trace_env, trace_headers, SWAP_IDS_ENV};
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use bridge_core::policy::{Decision, PolicyEngine, Transfer};
+use bridge_core::{now, swap_id, Direction};
+use clap::Parser;
+use lib_struct::verify::{decode_transaction, DEFAULT_CONFIRMATIONS};
+use lib_struct::{BurnBatchBundleStruct, BurnPayoutPolicyStruct, BurnPayoutRequestStruct};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{error, info, info_span, warn};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Group key of the vault the payouts spend from.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    #[clap(flatten)]
+    batch: BatchArgs,
+    #[clap(flatten)]
+    policy: PolicyArgs,
+    #[clap(flatten)]
+    pause: PauseArgs,
+    #[clap(flatten)]
+    bus: BusArgs,
+    /// Base URL of `burn-listener`'s payout queue (its `--listen` address).
+    #[clap(
+        long,
+        env = "PAYOUT_QUEUE_URL",
+        default_value = "http://127.0.0.1:3300"
+    )]
+    payout_queue: String,
+    /// Base URL of the swap API (`api`) the final refunds are recorded on; not recorded
+    /// without it.
+    #[clap(long, env = "SWAP_API_URL")]
+    swap_api: Option<String>,
+    /// Base URL of the proof service burn-batch proofs are queued on.
+    #[clap(
+        long,
+        env = "PROOF_SERVICE_URL",
+        default_value = "http://127.0.0.1:3000"
+    )]
+    service: String,
+    /// Command signing a batch's inputs with the TSS group key; `--tx-hex <unsigned tx>
+    /// --prevout-sats <input values>` is appended, and it must print one signature per input,
+    /// one per line, as `coordinator sign` does.
+    #[clap(long, env = "PAYOUT_SIGN_COMMAND", default_value = "coordinator sign")]
+    sign_command: String,
+    /// Base URL of the fee oracle (`fee-oracle`); batches are planned at its medium rate.
+    #[clap(long, env = "FEE_ORACLE_URL", required_unless_present_any = ["fee_rate", "list"])]
+    fee_oracle: Option<String>,
+    /// Fee rate in sat/vB used when no fee oracle is given, or it cannot be read.
+    #[clap(long, env = "PAYOUT_FEE_RATE")]
+    fee_rate: Option<f64>,
+    /// Directory the burn-batch fixtures are written to, as `burn-batch-<txid>.json`.
+    #[clap(long, default_value = "./payout-fixtures")]
+    fixture_dir: PathBuf,
+    /// Seconds between two rounds.
+    #[clap(long, default_value_t = 60)]
+    poll_interval: u64,
+    /// sled database of the batches and the vault outputs they reserve, kept across restarts
+    /// so that no output is spent twice; without it, the state is lost on exit.
+    #[clap(long, env = "PAYOUT_SCHEDULER_DB")]
+    db: Option<PathBuf>,
+    /// Print the batches recorded in `--db` and exit.
+    #[clap(long, requires = "db")]
+    list: bool,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// What a round works with.
+struct Scheduler<'a> {
+    args: &'a Args,
+    source: &'a dyn BundleSource,
+    client: Client,
+    store: BatchStore,
+    policy: PolicyEngine,
+    pause: PauseGuard,
+    network: Network,
+    vault_spk: ScriptBuf,
+    vault_address: Address,
+}
+
+fn job_transfer(job: &PayoutJob) -> Transfer {
+    Transfer {
+        direction: Direction::Burn,
+        id: job.burn_id.to_string(),
+        amount_sats: job.amount_sats,
+        recipient: job.btc_address.clone(),
+    }
+}
+
+fn payout_transfer(payout: &BatchPayout) -> Transfer {
+    Transfer {
+        direction: Direction::Burn,
+        id: payout.burn_id.to_string(),
+        amount_sats: payout.amount_sats,
+        recipient: payout.btc_address.clone(),
+    }
+}
+
+/// A refund as the payout policy sees it: value leaving the vault to a BTC address, under an
+/// id of its own.
+fn refund_transfer(refund: &BatchRefund) -> Transfer {
+    Transfer {
+        direction: Direction::Burn,
+        id: format!("refund:{}", refund.deposit_txid),
+        amount_sats: refund.amount_sats,
+        recipient: refund.btc_address.clone(),
+    }
+}
+
+/// Swap ids of the burns a batch pays, or of the mint it refunds, comma-separated.
+fn batch_swap_ids(batch: &PayoutBatch) -> String {
+    if let Some(refund) = &batch.refund {
+        return swap_id(Direction::Mint, &refund.deposit_txid);
+    }
+    batch
+        .payouts
+        .iter()
+        .map(|payout| swap_id(Direction::Burn, payout.burn_id))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Scheduler<'_> {
+    /// Fee rate batches are planned at: the oracle's medium rate, else `--fee-rate`.
+    fn fee_rate(&self) -> Result<f64, Box<dyn Error>> {
+        if let Some(url) = &self.args.fee_oracle {
+            let quote = fetch_quote(&self.client, url).and_then(|quote| {
+                quote
+                    .btc
+                    .ok_or_else(|| "Fee oracle has no BTC fee rate".into())
+            });
+            match (quote, self.args.fee_rate) {
+                (Ok(btc), _) => return Ok(btc.medium),
+                (Err(e), Some(fee_rate)) => {
+                    warn!(error = %e, fee_rate, "Fee oracle unavailable, using --fee-rate")
+                }
+                (Err(e), None) => return Err(e),
+            }
+        }
+        self.args
+            .fee_rate
+            .ok_or_else(|| "--fee-oracle or --fee-rate is required".into())
+    }
+
+    /// Ready jobs of the payout queue not paid by a batch yet, with the time they were first
+    /// seen ready.
+    fn pending(&self) -> Result<Vec<PendingPayout>, Box<dyn Error>> {
+        let jobs: Vec<PayoutJob> = self
+            .client
+            .get(format!(
+                "{}/payouts/ready",
+                self.args.payout_queue.trim_end_matches('/')
+            ))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let batched = self.store.batched_burns()?;
+        let at = now();
+        jobs.into_iter()
+            .filter(|job| !batched.contains(&job.burn_id))
+            .map(|job| {
+                let first_seen = self.store.first_seen(job.burn_id, at)?;
+                Ok(PendingPayout { job, first_seen })
+            })
+            .collect()
+    }
+
+    /// The pending payouts the policy approves, each checked together with the ones approved
+    /// before it. Denied payouts stay pending, and are checked again next round.
+    fn admitted(&self, pending: Vec<PendingPayout>) -> Result<Vec<PendingPayout>, Box<dyn Error>> {
+        let mut approved = Vec::new();
+        let mut admitted = Vec::with_capacity(pending.len());
+        for payout in pending {
+            let transfer = job_transfer(&payout.job);
+            match self.policy.check(&transfer, &approved)? {
+                Decision::Approve => {
+                    approved.push(transfer);
+                    admitted.push(payout);
+                }
+                Decision::Deny { reason } => {
+                    warn!(
+                        alert = true,
+                        burn_id = payout.job.burn_id,
+                        reason,
+                        "Payout denied by policy, left pending"
+                    )
+                }
+            }
+        }
+        Ok(admitted)
+    }
+
+    /// Starts a batch when the pending payouts hit a threshold.
+    fn schedule(&self) -> Result<(), Box<dyn Error>> {
+        let pending = self.admitted(self.pending()?)?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let fee_rate = self.fee_rate()?;
+        if self.args.batch.fee_too_high(fee_rate) {
+            warn!(
+                fee_rate,
+                pending = pending.len(),
+                "Fee rate above --max-fee-rate, postponing the payouts"
+            );
+            return Ok(());
+        }
+        let Some(trigger) = self.args.batch.trigger(&pending, fee_rate, now()) else {
+            return Ok(());
+        };
+
+        // Outputs of unfinished batches may still be unspent on chain; they are not offered
+        let mut utxos = Vec::new();
+        for utxo in self.source.address_utxos(&self.vault_address)? {
+            if !self.store.is_reserved(&utxo.txid.to_string(), utxo.vout)? {
+                utxos.push(utxo);
+            }
+        }
+
+        let jobs: Vec<PayoutJob> = pending
+            .into_iter()
+            .take(self.args.batch.max_batch.max(1))
+            .map(|payout| payout.job)
+            .collect();
+        let (batch, too_small) = plan_batch(
+            &jobs,
+            &utxos,
+            &self.vault_spk,
+            self.network,
+            fee_rate,
+            trigger,
+        )?;
+        if !too_small.is_empty() {
+            warn!(burn_ids = ?too_small, fee_share = batch.fee_share_sats, "Burns too small to pay their share of the fee, left pending");
+        }
+        let batch = self.store.create(batch)?;
+        self.store.flush()?;
+        info!(
+            batch_id = batch.id,
+            ?trigger,
+            payouts = batch.payouts.len(),
+            inputs = batch.inputs.len(),
+            fee_sats = batch.fee_sats,
+            fee_rate,
+            txid = batch.txid,
+            "Payout batch planned"
+        );
+        self.advance(batch)
+    }
+
+    /// Ready refunds of the payout queue not paid by a batch yet.
+    fn pending_refunds(&self) -> Result<Vec<RefundJob>, Box<dyn Error>> {
+        let jobs: Vec<RefundJob> = self
+            .client
+            .get(format!(
+                "{}/refunds/ready",
+                self.args.payout_queue.trim_end_matches('/')
+            ))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let refunded = self.store.refunded_deposits()?;
+        Ok(jobs
+            .into_iter()
+            .filter(|job| !refunded.contains(&job.deposit_txid))
+            .collect())
+    }
+
+    /// Block hash of `txid` once it is `DEFAULT_CONFIRMATIONS` deep; `None` before.
+    fn final_block(&self, txid: &Txid) -> Result<Option<String>, Box<dyn Error>> {
+        let block_hash = match self.source.transaction_block(txid, None) {
+            Ok(block_hash) => block_hash,
+            Err(e) => {
+                info!(%txid, reason = %e, "Transaction not confirmed yet");
+                return Ok(None);
+            }
+        };
+        let height = self.source.block_header(&block_hash)?.height;
+        let confirmations = self.source.tip_height()?.saturating_sub(height) as usize + 1;
+        if confirmations < DEFAULT_CONFIRMATIONS {
+            return Ok(None);
+        }
+        Ok(Some(block_hash))
+    }
+
+    /// Sats the deposit paid the vault address, read from the chain once it is final; `None`
+    /// while it is not. The rejection a refund comes from does not prove the deposit was mined,
+    /// nor commit its amount, so neither is taken from it.
+    fn deposit_value(&self, deposit_txid: &str) -> Result<Option<u64>, Box<dyn Error>> {
+        let txid = Txid::from_str(deposit_txid)?;
+        let Some(block_hash) = self.final_block(&txid)? else {
+            return Ok(None);
+        };
+        let tx = decode_transaction(&self.source.raw_transaction(&txid, Some(&block_hash))?)?;
+        Ok(Some(
+            tx.output
+                .iter()
+                .filter(|output| output.script_pubkey == self.vault_spk)
+                .map(|output| output.value.to_sat())
+                .sum(),
+        ))
+    }
+
+    /// Plans a batch for each ready refund whose deposit is final and paid the vault, and
+    /// takes it as far as it goes. A refund the policy denies stays pending; a deposit that
+    /// paid the vault nothing, e.g. one to a per-request deposit address, is left to an
+    /// operator.
+    fn schedule_refunds(&self) -> Result<(), Box<dyn Error>> {
+        let refunds = self.pending_refunds()?;
+        if refunds.is_empty() {
+            return Ok(());
+        }
+        let fee_rate = self.fee_rate()?;
+        if self.args.batch.fee_too_high(fee_rate) {
+            warn!(
+                fee_rate,
+                refunds = refunds.len(),
+                "Fee rate above --max-fee-rate, postponing the refunds"
+            );
+            return Ok(());
+        }
+        for job in refunds {
+            let Some(amount_sats) = self.deposit_value(&job.deposit_txid)? else {
+                continue;
+            };
+            if amount_sats == 0 {
+                error!(
+                    alert = true,
+                    deposit = job.deposit_txid,
+                    "Deposit to refund paid the vault address nothing, left pending"
+                );
+                continue;
+            }
+            let mut utxos = Vec::new();
+            for utxo in self.source.address_utxos(&self.vault_address)? {
+                if !self.store.is_reserved(&utxo.txid.to_string(), utxo.vout)? {
+                    utxos.push(utxo);
+                }
+            }
+            let batch = match plan_refund(
+                &job,
+                amount_sats,
+                &utxos,
+                &self.vault_spk,
+                self.network,
+                fee_rate,
+            ) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    warn!(deposit = job.deposit_txid, error = %e, "Refund cannot be planned, left pending");
+                    continue;
+                }
+            };
+            let transfer = refund_transfer(batch.refund.as_ref().expect("planned refund"));
+            if let Decision::Deny { reason } = self.policy.check(&transfer, &[])? {
+                warn!(
+                    alert = true,
+                    deposit = job.deposit_txid,
+                    reason,
+                    "Refund denied by policy, left pending"
+                );
+                continue;
+            }
+            let batch = self.store.create(batch)?;
+            self.store.flush()?;
+            info!(
+                batch_id = batch.id,
+                deposit = job.deposit_txid,
+                amount_sats,
+                reason = job.reason,
+                inputs = batch.inputs.len(),
+                fee_sats = batch.fee_sats,
+                fee_rate,
+                txid = batch.txid,
+                "Refund planned"
+            );
+            self.advance(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Moves a batch as far as it can go this round.
+    fn advance(&self, mut batch: PayoutBatch) -> Result<(), Box<dyn Error>> {
+        let _span = info_span!(
+            "batch",
+            batch_id = batch.id,
+            swap_id = %batch_swap_ids(&batch)
+        )
+        .entered();
+        loop {
+            let status = batch.status.clone();
+            match status {
+                BatchStatus::Signing => self.sign_and_broadcast(&mut batch)?,
+                BatchStatus::Broadcast if batch.refund.is_some() => {
+                    self.finish_refund(&mut batch)?
+                }
+                BatchStatus::Broadcast => self.request_proof(&mut batch)?,
+                BatchStatus::Proving { job_id } => self.poll_proof(&mut batch, job_id)?,
+                BatchStatus::Proved | BatchStatus::Refunded | BatchStatus::Failed { .. } => {
+                    return Ok(())
+                }
+            }
+            if batch.status == status {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs `--sign-command` over the batch's unsigned transaction and returns the signatures
+    /// it printed. The command gets the batch's trace context in `TRACEPARENT` and its swap
+    /// ids in `SWAP_IDS`, which the TSS coordinator tags its signing spans with.
+    fn sign(&self, batch: &PayoutBatch) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut words = self.args.sign_command.split_whitespace();
+        let program = words.next().ok_or("--sign-command is empty")?;
+        let prevout_sats: Vec<String> = batch
+            .inputs
+            .iter()
+            .map(|input| input.value_sats.to_string())
+            .collect();
+        let output = Command::new(program)
+            .args(words)
+            .arg("--tx-hex")
+            .arg(&batch.unsigned_tx_hex)
+            .arg("--prevout-sats")
+            .arg(prevout_sats.join(","))
+            .envs(trace_env())
+            .env(SWAP_IDS_ENV, batch_swap_ids(batch))
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Signing command exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Checks the batch's payouts, or its refund, against the policy once more, as its limits
+    /// may have moved since the batch was planned. Returns the first denial.
+    fn policy_denial(&self, batch: &PayoutBatch) -> Result<Option<String>, Box<dyn Error>> {
+        if let Some(refund) = &batch.refund {
+            return Ok(match self.policy.check(&refund_transfer(refund), &[])? {
+                Decision::Deny { reason } => {
+                    Some(format!("refund of {} {}", refund.deposit_txid, reason))
+                }
+                Decision::Approve => None,
+            });
+        }
+        let transfers: Vec<Transfer> = batch.payouts.iter().map(payout_transfer).collect();
+        for (index, transfer) in transfers.iter().enumerate() {
+            if let Decision::Deny { reason } = self.policy.check(transfer, &transfers[..index])? {
+                return Ok(Some(format!("burn {} {}", transfer.id, reason)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Signs the batch's inputs, unless it already is, and broadcasts it. A batch the policy
+    /// denies, or whose outputs were spent by another transaction, fails, releasing its burns
+    /// or refund.
+    fn sign_and_broadcast(&self, batch: &mut PayoutBatch) -> Result<(), Box<dyn Error>> {
+        let mut tx = batch.transaction()?;
+        if batch.signed_tx_hex.is_none() {
+            if let Some(reason) = self.policy_denial(batch)? {
+                error!(
+                    alert = true,
+                    batch_id = batch.id,
+                    reason,
+                    "Payout batch denied by policy, failing it"
+                );
+                batch.status = BatchStatus::Failed {
+                    reason: format!("denied by policy: {}", reason),
+                };
+                self.store.put(batch)?;
+                return Ok(());
+            }
+            let sighashes = sighashes(&tx, &batch.inputs, &self.vault_spk)?;
+            info!(
+                batch_id = batch.id,
+                inputs = sighashes.len(),
+                "Signing the payout batch"
+            );
+            let signatures = self.sign(batch)?;
+            apply_signatures(&mut tx, &sighashes, &signatures, &self.vault_spk)?;
+            for payout in &batch.payouts {
+                self.policy.record(&payout_transfer(payout))?;
+            }
+            if let Some(refund) = &batch.refund {
+                self.policy.record(&refund_transfer(refund))?;
+            }
+            batch.signed_tx_hex = Some(serialize_hex(&tx));
+            self.store.put(batch)?;
+        }
+
+        let txid = tx.compute_txid();
+        if let Err(e) = self.source.broadcast(&tx) {
+            // A broadcast that reached the network before a crash is refused as known
+            if self.source.raw_transaction(&txid, None).is_err() {
+                let unspent = self.source.address_utxos(&self.vault_address)?;
+                let spent = batch.inputs.iter().any(|input| {
+                    !unspent
+                        .iter()
+                        .any(|utxo| utxo.txid.to_string() == input.txid && utxo.vout == input.vout)
+                });
+                if !spent {
+                    return Err(e);
+                }
+                error!(alert = true, batch_id = batch.id, error = %e, "Payout batch spends outputs no longer unspent, failing it");
+                batch.status = BatchStatus::Failed {
+                    reason: format!("inputs spent elsewhere: {}", e),
+                };
+                self.store.put(batch)?;
+                return Ok(());
+            }
+        }
+        info!(batch_id = batch.id, %txid, "Payout batch broadcast");
+        batch.status = BatchStatus::Broadcast;
+        self.store.put(batch)?;
+        self.store.flush()
+    }
+
+    /// Marks the batch's jobs paid on the payout queue, then, once the batch is final, queues
+    /// its burn-batch proof.
+    fn request_proof(&self, batch: &mut PayoutBatch) -> Result<(), Box<dyn Error>> {
+        if !batch.marked_paid {
+            let queue = self.args.payout_queue.trim_end_matches('/');
+            for payout in &batch.payouts {
+                self.client
+                    .post(format!("{}/payouts/{}/paid", queue, payout.burn_id))
+                    .json(&json!({ "payout_txid": batch.txid }))
+                    .send()?
+                    .error_for_status()?;
+            }
+            batch.marked_paid = true;
+            self.store.forget_first_seen(batch)?;
+            self.store.put(batch)?;
+            info!(
+                batch_id = batch.id,
+                payouts = batch.payouts.len(),
+                "Payout jobs marked paid"
+            );
+        }
+
+        let txid = Txid::from_str(&batch.txid)?;
+        let Some(block_hash) = self.final_block(&txid)? else {
+            return Ok(());
+        };
+
+        // The bundle of a single burn carries the transaction, its proofs and the prevouts
+        let first = &batch.payouts[0];
+        let request = BundleRequest {
+            txid,
+            block_hash: Some(block_hash),
+            network: Some(self.network),
+            confirmations: DEFAULT_CONFIRMATIONS,
+            group_key: self.args.group_key.group_key,
+            burn: Some(BurnTarget {
+                burner_btc_address: first.btc_address.clone(),
+                burn_policy: BurnPayoutPolicyStruct::default(),
+                burn_id: Some(first.burn_id),
+            }),
+        };
+        let mut bundle = BurnBatchBundleStruct::from(build_bundle(self.source, &request)?);
+        bundle.payouts = batch
+            .payouts
+            .iter()
+            .map(|payout| BurnPayoutRequestStruct {
+                burner_btc_address: payout.btc_address.clone(),
+                burn_id: payout.burn_id,
+                burn_policy: BurnPayoutPolicyStruct {
+                    expected_amount_sats: payout.amount_sats,
+                    declared_fee_sats: batch.fee_share_sats,
+                    tolerance_sats: 0,
+                    max_fee_sats: 0,
+                },
+            })
+            .collect();
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/prove/burn-batch",
+                self.args.service.trim_end_matches('/')
+            ))
+            .json(&bundle);
+        for (name, value) in trace_headers() {
+            request = request.header(name, value);
+        }
+        let response: Value = request.send()?.error_for_status()?.json()?;
+        let job_id = response["job_id"]
+            .as_u64()
+            .ok_or("Proof service answered without a job id")?;
+        info!(batch_id = batch.id, job_id, "Burn-batch proof queued");
+        batch.status = BatchStatus::Proving { job_id };
+        self.store.put(batch)?;
+        self.store.flush()
+    }
+
+    /// Marks the refund paid on the payout queue, then, once it is final, records it on its
+    /// swap with the swap API's `POST /refunds` and finishes the batch. The refund stands if the
+    /// swap cannot record it; only the swap's view of it lags.
+    fn finish_refund(&self, batch: &mut PayoutBatch) -> Result<(), Box<dyn Error>> {
+        let refund = batch.refund.clone().ok_or("Batch refunds no deposit")?;
+        if !batch.marked_paid {
+            self.client
+                .post(format!(
+                    "{}/refunds/{}/paid",
+                    self.args.payout_queue.trim_end_matches('/'),
+                    refund.deposit_txid
+                ))
+                .json(&json!({ "refund_txid": batch.txid }))
+                .send()?
+                .error_for_status()?;
+            batch.marked_paid = true;
+            self.store.put(batch)?;
+            info!(
+                batch_id = batch.id,
+                deposit = refund.deposit_txid,
+                "Refund marked paid"
+            );
+        }
+
+        if self.final_block(&Txid::from_str(&batch.txid)?)?.is_none() {
+            return Ok(());
+        }
+        if let Some(swap_api) = &self.args.swap_api {
+            let report = json!({
+                "deposit_txid": refund.deposit_txid,
+                "request_id": refund.request_id,
+                "amount_sats": refund.amount_sats,
+                "btc_address": refund.btc_address,
+                "reason": refund.reason,
+                "refund_txid": batch.txid,
+            });
+            let recorded = self
+                .client
+                .post(format!("{}/refunds", swap_api.trim_end_matches('/')))
+                .json(&report)
+                .send()
+                .and_then(|response| response.error_for_status());
+            match recorded {
+                Ok(_) => info!(
+                    batch_id = batch.id,
+                    deposit = refund.deposit_txid,
+                    "Refund recorded on the swap"
+                ),
+                Err(e) => {
+                    warn!(batch_id = batch.id, deposit = refund.deposit_txid, error = %e, "Failed to record the refund on the swap")
+                }
+            }
+        }
+        info!(
+            batch_id = batch.id,
+            deposit = refund.deposit_txid,
+            paid_sats = refund.paid_sats,
+            txid = batch.txid,
+            "Deposit refunded"
+        );
+        batch.status = BatchStatus::Refunded;
+        self.store.put(batch)?;
+        self.store.flush()
+    }
+
+    /// Follows the batch's proof job. The fixture of a done job is written to
+    /// `--fixture-dir`; a cancelled or forgotten job is queued again.
+    fn poll_proof(&self, batch: &mut PayoutBatch, job_id: u64) -> Result<(), Box<dyn Error>> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/jobs/{}",
+                self.args.service.trim_end_matches('/'),
+                job_id
+            ))
+            .send()?;
+        let status: Value = if response.status() == StatusCode::NOT_FOUND {
+            json!({ "status": "cancelled", "reason": "the proof service forgot the job" })
+        } else {
+            response.error_for_status()?.json()?
+        };
+        match status["status"].as_str() {
+            Some("done") => {
+                std::fs::create_dir_all(&self.args.fixture_dir)?;
+                let path = self
+                    .args
+                    .fixture_dir
+                    .join(format!("burn-batch-{}.json", batch.txid));
+                std::fs::write(&path, serde_json::to_string_pretty(&status["fixture"])?)?;
+                info!(batch_id = batch.id, fixture = %path.display(), "Burn-batch proof generated");
+                batch.status = BatchStatus::Proved;
+            }
+            Some("failed") => {
+                error!(alert = true, batch_id = batch.id, job_id, error = %status["error"], "Burn-batch proof failed");
+                batch.status = BatchStatus::Failed {
+                    reason: format!("proof failed: {}", status["error"]),
+                };
+            }
+            Some("cancelled") => {
+                warn!(batch_id = batch.id, job_id, reason = %status["reason"], "Burn-batch proof cancelled, requesting it again");
+                batch.status = BatchStatus::Broadcast;
+            }
+            _ => return Ok(()),
+        }
+        self.store.put(batch)?;
+        self.store.flush()
+    }
+
+    /// Whether a batch waits for its signatures; it keeps its outputs, burns or refund, and
+    /// the next batch waits for it.
+    fn signing(&self) -> Result<bool, Box<dyn Error>> {
+        Ok(self
+            .store
+            .batches()?
+            .iter()
+            .any(|batch| batch.status == BatchStatus::Signing))
+    }
+
+    /// Advances the unfinished batches, then pays the ready refunds and starts a new batch,
+    /// unless a batch still waits for its signatures.
+    fn round(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(notice) = self.pause.current()? {
+            warn!(%notice, "Payouts paused, no batch advances");
+            return Ok(());
+        }
+        for batch in self.store.batches()? {
+            if batch.is_finished() {
+                continue;
+            }
+            let id = batch.id;
+            if let Err(e) = self.advance(batch) {
+                warn!(batch_id = id, error = %e, "Payout batch did not advance, retrying next round");
+            }
+        }
+        if self.signing()? {
+            return Ok(());
+        }
+        self.schedule_refunds()?;
+        if self.signing()? {
+            return Ok(());
+        }
+        self.schedule()
+    }
+}
+
+/// Waits up to `timeout` for a `payout-requested` event, and acknowledges it: the job it names
+/// is read back from the payout queue by the next round.
+fn wait_for_payout(bus: &dyn JobBus, timeout: Duration) {
+    match bus.next(Topic::PayoutRequested, timeout) {
+        Ok(Some(delivery)) => {
+            if let BusEvent::PayoutRequested { burn_id, .. } = &delivery.event {
+                info!(burn_id, "Payout requested, starting a round");
+            }
+            if let Err(e) = bus.ack(&delivery) {
+                warn!(event = %delivery.id, error = %e, "Failed to acknowledge the payout request");
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            warn!(error = %e, "Failed to read the job bus");
+            std::thread::sleep(timeout);
+        }
+    }
+}
+
+/// Prints the recorded batches, oldest first.
+fn list(store: &BatchStore) -> Result<(), Box<dyn Error>> {
+    for batch in store.batches()? {
+        println!(
+            "batch {} ({:?}): {} payouts from {} inputs, {} sats fee at {} sat/vB, {} sats change, {}, {}",
+            batch.id,
+            batch.trigger,
+            batch.payouts.len(),
+            batch.inputs.len(),
+            batch.fee_sats,
+            batch.fee_rate,
+            batch.change_sats,
+            batch.txid,
+            batch.status
+        );
+        for payout in &batch.payouts {
+            println!(
+                "  burn {}: {} of {} sats to {}",
+                payout.burn_id, payout.paid_sats, payout.amount_sats, payout.btc_address
+            );
+        }
+        if let Some(refund) = &batch.refund {
+            println!(
+                "  refund of {}: {} of {} sats to {} ({})",
+                refund.deposit_txid,
+                refund.paid_sats,
+                refund.amount_sats,
+                refund.btc_address,
+                refund.reason
+            );
+        }
+    }
+    Ok(())
+}
+
+fn vault_address(
+    group_key: &GroupKeyArgs,
+    network: Network,
+) -> Result<(ScriptBuf, Address), Box<dyn Error>> {
+    let vault_spk = group_key.vault_script();
+    let address = Address::from_script(&vault_spk, network)?;
+    Ok((vault_spk, address))
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let store = BatchStore::open(args.db.as_deref()).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the batch database");
+        std::process::exit(1);
+    });
+    if args.list {
+        if let Err(e) = list(&store) {
+            error!(error = %e, "Failed to read the batch database");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let source = args.source.open().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the Bitcoin source");
+        std::process::exit(1);
+    });
+    // The vault address depends on the network, so the source must serve the expected one
+    let network = source.network().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to read the source's network");
+        std::process::exit(1);
+    });
+    if let Some(expected) = args.source.network {
+        if network != expected {
+            error!(%network, %expected, "Source serves another network");
+            std::process::exit(1);
+        }
+    }
+    let (vault_spk, vault_address) = vault_address(&args.group_key, network).unwrap_or_else(|e| {
+        error!(error = %e, "Invalid vault key");
+        std::process::exit(1);
+    });
+    let bus = args.bus.open("payout-scheduler").unwrap_or_else(|e| {
+        error!(error = %e, "Failed to connect to the job bus");
+        std::process::exit(1);
+    });
+    info!(
+        vault = %vault_address,
+        %network,
+        payout_queue = args.payout_queue,
+        service = args.service,
+        bus = args.bus.bus,
+        "Scheduling burn payouts"
+    );
+
+    let policy = args
+        .policy
+        .engine(Direction::Burn, Some(store.db()))
+        .unwrap_or_else(|e| {
+            error!(error = %e, "Invalid policy");
+            std::process::exit(1);
+        });
+    let scheduler = Scheduler {
+        args: &args,
+        source: source.as_ref(),
+        client: Client::new(),
+        store,
+        policy,
+        pause: PauseGuard::new(&args.pause, &args.group_key, "payout-scheduler"),
+        network,
+        vault_spk,
+        vault_address,
+    };
+    loop {
+        if let Err(e) = scheduler.round() {
+            warn!(error = %e, "Payout round failed, retrying");
+        }
+        let interval = Duration::from_secs(args.poll_interval);
+        match &bus {
+            Some(bus) => wait_for_payout(bus.as_ref(), interval),
+            None => std::thread::sleep(interval),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Runs the checks of the mint or burn circuit natively on a bundle and reports which one would
+//! fail, and whether the circuit would commit a rejection or panic, before any zkVM execution.
+
+// Usage Example:
+// cargo run --release --bin preflight -- --circuit mint --input-json ./input.json
+// cargo run --release --bin preflight -- --circuit burn --input-json ./burn.json
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::input::read_input;
+use bitcoin_verify_script::native::{run_burn, run_mint, Rejection};
+use clap::{Parser, ValueEnum};
+use lib_struct::BundleInfoStruct;
+use std::path::PathBuf;
+
+/// Enum representing the circuits that can be checked
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    Burn,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
+    circuit: CircuitType,
+    /// Bundle JSON to check.
+    #[clap(long)]
+    input_json: PathBuf,
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+
+    let bundle: BundleInfoStruct = read_input(&args.input_json).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let run = match args.circuit {
+        CircuitType::Mint => run_mint(&bundle),
+        CircuitType::Burn => run_burn(&bundle),
+    };
+
+    println!(
+        "Preflight of {} ({:?})",
+        args.input_json.display(),
+        args.circuit
+    );
+    for (check, detail) in &run.passed {
+        println!("  ok    {}: {}", check, detail);
+    }
+    match run.failure {
+        None => println!("All checks passed; the circuit commits a valid proof"),
+        Some((check, Rejection { reason, detail })) => {
+            println!("  FAIL  {}: {}", check, detail);
+            println!(
+                "The circuit would commit a rejection ({}); the contract will not settle it",
+                reason
+            );
+            std::process::exit(1);
+        }
+    }
+}
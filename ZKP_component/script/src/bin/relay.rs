@@ -0,0 +1,289 @@
+//! Relays Bitcoin headers to a light-client contract on the EVM chain, so proofs anchored to
+//! a checkpoint can be checked against headers the contract verified itself. Headers come from
+//! the local header chain (`--headers-db`), are sent in batches from the highest block the
+//! contract shares with it, and each batch waits for a gas estimate under `--max-fee-gwei`.
+//! When the contract follows a block the header chain orphaned, the batch starts below it and
+//! the contract switches to the chain with more work.
+
+// Usage Example:
+// cargo run --release --bin relay -- --headers-db ./relay-headers-db --rpc-url http://127.0.0.1:18332 --rpc http://127.0.0.1:8545 --light-client 0x...
+// cargo run --release --bin relay -- --config ./bridge.toml --batch-size 20 --max-fee-gwei 30
+// cargo run --release --bin relay -- --config ./bridge.toml --dry-run
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, FixedBytes, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::Hash;
+use bitcoin_verify_script::bundle::{BundleSource, SourceArgs};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::header_chain::HeaderChain;
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use clap::Parser;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Extra gas on top of the estimate, in percent, so small state changes between the estimate
+/// and inclusion do not run the transaction out of gas.
+const GAS_LIMIT_MARGIN_PERCENT: u64 = 20;
+
+sol! {
+    /// Bitcoin light-client contract the headers are relayed to. `submitHeaders` takes
+    /// consecutive 80-byte headers, the first at `startHeight` on top of a block it knows, and
+    /// keeps the chain with the most work.
+    interface IBtcLightClient {
+        function bestHeight() external view returns (uint256);
+        function blockHashAt(uint256 height) external view returns (bytes32);
+        function submitHeaders(uint256 startHeight, bytes calldata headers) external;
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Ethereum JSON-RPC endpoint.
+    #[clap(long, env = "ETH_RPC_URL")]
+    rpc: String,
+    /// Address of the light-client contract.
+    #[clap(long, env = "BTC_LIGHT_CLIENT")]
+    light_client: Address,
+    /// Key of the account paying for the relay transactions.
+    #[clap(long, env = "PRIVATE_KEY", hide_env_values = true)]
+    private_key: String,
+    /// Headers per transaction.
+    #[clap(long, env = "RELAY_BATCH_SIZE", default_value_t = 50)]
+    batch_size: u32,
+    /// Highest fee per gas paid, in gwei; while the network asks more, batches wait.
+    #[clap(long, env = "RELAY_MAX_FEE_GWEI")]
+    max_fee_gwei: Option<u64>,
+    /// Blocks to wait for on top of each relay transaction's block.
+    #[clap(long, default_value_t = 1)]
+    confirmations: u64,
+    /// Seconds between two rounds.
+    #[clap(long, default_value_t = 60)]
+    poll_interval: u64,
+    /// Stop once the contract has the header chain's tip.
+    #[clap(long)]
+    once: bool,
+    /// Simulates and estimates the first batch without sending it, then stops.
+    #[clap(long)]
+    dry_run: bool,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Read-only call to the light client.
+async fn view<C: SolCall>(
+    provider: &impl Provider,
+    light_client: Address,
+    call: C,
+) -> Result<C::Return, Box<dyn Error>> {
+    let tx = TransactionRequest::default()
+        .with_to(light_client)
+        .with_input(call.abi_encode());
+    let returns = provider.call(tx).await?;
+    Ok(C::abi_decode_returns(&returns)?)
+}
+
+/// Highest height at which the contract and the header chain hold the same block. A contract
+/// that never left the header chain's blocks only needs the headers above its best height.
+async fn common_height(
+    provider: &impl Provider,
+    light_client: Address,
+    chain: &HeaderChain,
+) -> Result<u32, Box<dyn Error>> {
+    let best: U256 = view(provider, light_client, IBtcLightClient::bestHeightCall {}).await?;
+    let best = u32::try_from(best)
+        .map_err(|_| format!("Contract best height {} is not a Bitcoin height", best))?;
+    let base = chain.base()?.height;
+    let mut height = best.min(chain.tip()?.height);
+    loop {
+        if height < base {
+            return Err(format!(
+                "Contract shares no block with the header chain from height {}",
+                base
+            )
+            .into());
+        }
+        let stored = chain
+            .get(height)?
+            .ok_or_else(|| format!("Header chain has no header at height {}", height))?;
+        let relayed: FixedBytes<32> = view(
+            provider,
+            light_client,
+            IBtcLightClient::blockHashAtCall {
+                height: U256::from(height),
+            },
+        )
+        .await?;
+        // Block hashes are kept in internal byte order, as the contract hashes the headers
+        if relayed.0 == stored.hash().to_byte_array() {
+            if height < best {
+                warn!(
+                    fork_height = height + 1,
+                    contract_best = best,
+                    "Contract follows blocks the header chain orphaned, relaying the competing chain"
+                );
+            }
+            return Ok(height);
+        }
+        height -= 1;
+    }
+}
+
+/// Whether the network's fee is under `--max-fee-gwei`.
+async fn fee_acceptable(provider: &impl Provider, args: &Args) -> Result<bool, Box<dyn Error>> {
+    let Some(max_fee_gwei) = args.max_fee_gwei else {
+        return Ok(true);
+    };
+    let fees = provider.estimate_eip1559_fees().await?;
+    let max_fee = max_fee_gwei as u128 * 1_000_000_000;
+    if fees.max_fee_per_gas > max_fee {
+        warn!(
+            max_fee_per_gas = fees.max_fee_per_gas,
+            cap = max_fee,
+            "Gas above --max-fee-gwei, postponing the relay"
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Relays the headers the contract is missing, one batch per transaction. Returns whether the
+/// contract reached the header chain's tip.
+async fn relay_round(
+    provider: &impl Provider,
+    args: &Args,
+    chain: &HeaderChain,
+    source: &dyn BundleSource,
+) -> Result<bool, Box<dyn Error>> {
+    // Headers are validated locally before any of them is paid for
+    let tip = tokio::task::block_in_place(|| chain.sync(source))?;
+    let mut next = common_height(provider, args.light_client, chain).await? + 1;
+    while next <= tip.height {
+        if !fee_acceptable(provider, args).await? {
+            return Ok(false);
+        }
+        let last = tip.height.min(next + args.batch_size.max(1) - 1);
+        let mut headers = Vec::with_capacity(((last - next + 1) * 80) as usize);
+        for height in next..=last {
+            let stored = chain
+                .get(height)?
+                .ok_or_else(|| format!("Header chain has no header at height {}", height))?;
+            headers.extend_from_slice(&serialize(&stored.header));
+        }
+        let tx = TransactionRequest::default()
+            .with_to(args.light_client)
+            .with_input(
+                IBtcLightClient::submitHeadersCall {
+                    startHeight: U256::from(next),
+                    headers: Bytes::from(headers),
+                }
+                .abi_encode(),
+            );
+
+        // === Simulate and estimate gas; a revert here costs nothing ===
+        provider
+            .call(tx.clone())
+            .await
+            .map_err(|e| format!("Relay of headers {}..={} reverts: {}", next, last, e))?;
+        let gas_estimate = provider.estimate_gas(tx.clone()).await?;
+        let gas_limit = gas_estimate * (100 + GAS_LIMIT_MARGIN_PERCENT) / 100;
+        if args.dry_run {
+            // Later batches build on this one, so they cannot be simulated before it lands
+            info!(
+                from = next,
+                to = last,
+                gas_estimate,
+                gas_limit,
+                "Dry run: batch not sent"
+            );
+            return Ok(true);
+        }
+
+        // === Send and wait for the receipt ===
+        let pending = provider
+            .send_transaction(tx.with_gas_limit(gas_limit))
+            .await?;
+        let tx_hash = *pending.tx_hash();
+        let receipt = pending
+            .with_required_confirmations(args.confirmations)
+            .get_receipt()
+            .await?;
+        if !receipt.status() {
+            return Err(format!("Relay transaction {} reverted", tx_hash).into());
+        }
+        info!(
+            from = next,
+            to = last,
+            %tx_hash,
+            gas_used = receipt.gas_used,
+            "Headers relayed"
+        );
+        next = last + 1;
+    }
+    Ok(true)
+}
+
+async fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .source
+        .headers_db
+        .as_deref()
+        .ok_or("--headers-db is required")?;
+    // The Bitcoin backend is blocking, so it is only called off the async workers
+    let source = args.source.connect();
+    let (network, chain) = tokio::task::block_in_place(|| {
+        let network = match args.source.network {
+            Some(network) => network,
+            None => source.network()?,
+        };
+        let chain = HeaderChain::open(
+            path,
+            network,
+            args.source.checkpoint.as_ref(),
+            source.as_ref(),
+        )?;
+        Ok::<_, Box<dyn Error>>((network, chain))
+    })?;
+
+    let signer: PrivateKeySigner = args.private_key.parse()?;
+    info!(relayer = %signer.address(), light_client = %args.light_client, %network, "Relaying headers");
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect_http(args.rpc.parse()?);
+
+    loop {
+        match relay_round(&provider, args, &chain, source.as_ref()).await {
+            Ok(true) if args.once || args.dry_run => return Ok(()),
+            Ok(_) => {}
+            Err(e) if args.once => return Err(e),
+            Err(e) => warn!(error = %e, "Relay round failed, retrying"),
+        }
+        tokio::time::sleep(Duration::from_secs(args.poll_interval)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    if let Err(e) = run(&args).await {
+        error!(error = %e, "Header relay failed");
+        std::process::exit(1);
+    }
+}
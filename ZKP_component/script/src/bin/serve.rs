@@ -0,0 +1,828 @@
+//! HTTP proof service: the relayer posts a bundle JSON and polls for the EVM proof fixture,
+//! instead of shelling out to `cargo run --bin evm` for every deposit or burn. Batched
+//! payouts are proven under `/prove/burn-batch`, which takes a burn-batch bundle.
+//! With `--bus`, the bundles of the job bus's `prove-requested` events are proven as well, and
+//! every finished proof is published as `proof-ready` with its fixture. The requests are kept in
+//! the job queue `--queue-db` until their proof is done: a failed proof is requested again with
+//! backoff, and dead-lettered after `--retry-max-attempts`; a bundle the circuit fails to execute
+//! is dead-lettered at once.
+
+// Usage Example:
+// cargo run --release --bin serve -- --listen 127.0.0.1:3000 --system groth16
+// curl -X POST -H 'Content-Type: application/json' --data @input.json http://127.0.0.1:3000/prove/mint
+// curl -X POST -H 'Content-Type: application/json' --data @burn-batch.json http://127.0.0.1:3000/prove/burn-batch
+// curl http://127.0.0.1:3000/jobs/1
+// curl http://127.0.0.1:3000/metrics
+// curl -X POST -H 'Content-Type: application/json' --data '{"block_hashes":["..."]}' http://127.0.0.1:3000/invalidate
+// cargo run --release --bin serve -- --resume-journal
+// OPERATOR_PRIVATE_KEY=0x... cargo run --release --bin serve -- --listen 127.0.0.1:3000
+// cargo run --release --bin serve -- --bus nats://127.0.0.1:4222
+// cargo run --release --bin serve -- --bus nats://127.0.0.1:4222 --queue-db ./proof-queue --retry-base-secs 60
+use alloy::signers::local::PrivateKeySigner;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Transaction;
+use bitcoin_verify_script::attestation::{operator_signer, sign_fixture};
+use bitcoin_verify_script::bus::{BusArgs, BusEvent, JobBus, Topic, BUS_WAIT};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::input::check_input;
+use bitcoin_verify_script::job_queue::{JobQueue, Retry, RetryArgs};
+use bitcoin_verify_script::journal::{self, exit_on_signal, JobPhase, Journal, JournalEntry};
+use bitcoin_verify_script::telemetry::set_parent_from_headers;
+use bitcoin_verify_script::{setup_logging, LogFormat, ProverBackend};
+use bridge_core::{swap_id, Direction};
+use clap::{Parser, ValueEnum};
+use lib_struct::{BundleInfoStruct, BurnBatchBundleStruct, Chain, ZkpProofFixture};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sp1_sdk::{include_elf, HashableKey, SP1Stdin};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{error, info, info_span, warn, Span};
+
+/// ELF files for the Bitcoin transaction verification zkVM programs
+pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
+
+/// Metrics served under `/metrics`, in the Prometheus text format.
+const PROOFS_GENERATED: &str = "bridge_proofs_generated_total";
+const PROOF_FAILURES: &str = "bridge_proof_failures_total";
+const PROOFS_CANCELLED: &str = "bridge_proofs_cancelled_total";
+const PROVING_DURATION: &str = "bridge_proving_duration_seconds";
+const PROOF_CYCLES: &str = "bridge_proof_cycles";
+const QUEUE_DEPTH: &str = "bridge_queue_depth";
+
+/// Histogram buckets: EVM proofs take minutes, bridge circuits run millions of cycles.
+const PROVING_DURATION_BUCKETS: &[f64] = &[30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0];
+const PROOF_CYCLES_BUCKETS: &[f64] = &[1e6, 5e6, 1e7, 2.5e7, 5e7, 1e8, 2.5e8, 5e8, 1e9];
+
+/// Enum representing the available proof systems
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum ProofSystem {
+    Plonk,
+    Groth16,
+}
+
+impl ProofSystem {
+    /// Value of the `system` metric label.
+    fn label(self) -> &'static str {
+        match self {
+            ProofSystem::Plonk => "plonk",
+            ProofSystem::Groth16 => "groth16",
+        }
+    }
+}
+
+/// Enum representing the circuits served under `/prove/{circuit}`
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum CircuitType {
+    Mint,
+    Burn,
+    #[serde(rename = "burn-batch")]
+    BurnBatch,
+}
+
+impl CircuitType {
+    /// Value of the `circuit` metric label.
+    fn label(self) -> &'static str {
+        match self {
+            CircuitType::Mint => "mint",
+            CircuitType::Burn => "burn",
+            CircuitType::BurnBatch => "burn-batch",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct ServeArgs {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(long, env = "PROOF_SERVICE_LISTEN", default_value = "127.0.0.1:3000")]
+    listen: SocketAddr,
+    #[clap(long, value_enum, env = "PROOF_SYSTEM", default_value = "groth16")]
+    system: ProofSystem,
+    /// SP1 prover backend; falls back to `SP1_PROVER`, then the CPU prover.
+    #[clap(long, value_enum, env = "SP1_PROVER", default_value = "cpu")]
+    prover: ProverBackend,
+    /// Format of the logs written to stderr; job logs carry the `job_id` of the job span.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+    /// Journal of the queued and running jobs; defaults to `script/.proving-journal/serve.json`.
+    #[clap(long)]
+    journal: Option<PathBuf>,
+    /// Queues again, under their job ids, the jobs a stopped service left in the journal.
+    #[clap(long)]
+    resume_journal: bool,
+    /// Operator key that signs each fixture, so the relayer can tell it came from an
+    /// authorized prover.
+    #[clap(long, env = "OPERATOR_PRIVATE_KEY", hide_env_values = true)]
+    operator_key: Option<String>,
+    #[clap(flatten)]
+    bus: BusArgs,
+    /// sled job queue the prove requests of the job bus are kept in until their proof is done,
+    /// so a failed proof is requested again after a restart too; without it, the queue is lost
+    /// on exit.
+    #[clap(long, env = "PROOF_QUEUE_DB")]
+    queue_db: Option<PathBuf>,
+    #[clap(flatten)]
+    retry: RetryArgs,
+}
+
+/// Progress of a proving job, as returned by `GET /jobs/{id}`.
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    Executing,
+    /// Execution succeeded; the EVM proof is being generated.
+    Proving {
+        cycles: u64,
+    },
+    Done {
+        fixture: ZkpProofFixture,
+    },
+    Failed {
+        error: String,
+    },
+    /// A block of the bundle's chain left the best chain; the proof is dropped.
+    Cancelled {
+        reason: String,
+    },
+}
+
+/// Bundle of a job, in the input type of its circuit.
+enum JobBundle {
+    /// Mint or burn of a single transaction.
+    Single(Box<BundleInfoStruct>),
+    /// Payout transaction settling several burns.
+    BurnBatch(Box<BurnBatchBundleStruct>),
+}
+
+impl JobBundle {
+    /// Reads the bundle `circuit` takes from its JSON.
+    fn parse(circuit: CircuitType, value: Value) -> Result<Self, String> {
+        match circuit {
+            CircuitType::Mint | CircuitType::Burn => {
+                serde_json::from_value(value).map(|bundle| JobBundle::Single(Box::new(bundle)))
+            }
+            CircuitType::BurnBatch => {
+                serde_json::from_value(value).map(|bundle| JobBundle::BurnBatch(Box::new(bundle)))
+            }
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn check(&self) -> Result<(), Vec<String>> {
+        match self {
+            JobBundle::Single(bundle) => check_input(bundle.as_ref()),
+            JobBundle::BurnBatch(bundle) => check_input(bundle.as_ref()),
+        }
+    }
+
+    fn chains(&self) -> &Chain {
+        match self {
+            JobBundle::Single(bundle) => &bundle.chains,
+            JobBundle::BurnBatch(bundle) => &bundle.chains,
+        }
+    }
+
+    fn write(&self, stdin: &mut SP1Stdin) {
+        match self {
+            JobBundle::Single(bundle) => stdin.write(bundle),
+            JobBundle::BurnBatch(bundle) => stdin.write(bundle),
+        }
+    }
+
+    /// Swap the bundle proves: the deposit of a mint, the burn request of a burn, or the
+    /// burn requests a batched payout settles, comma-separated. Empty for a burn bundle
+    /// without a burn id.
+    fn swap_id(&self, circuit: CircuitType) -> String {
+        match self {
+            JobBundle::Single(bundle) if circuit == CircuitType::Mint => {
+                let txid = hex::decode(&bundle.bit_tx_info.raw_tx_hex)
+                    .ok()
+                    .and_then(|raw| deserialize::<Transaction>(&raw).ok())
+                    .map(|tx| tx.compute_txid().to_string())
+                    .unwrap_or_default();
+                swap_id(Direction::Mint, txid)
+            }
+            JobBundle::Single(bundle) => bundle
+                .burn_id
+                .map(|burn_id| swap_id(Direction::Burn, burn_id))
+                .unwrap_or_default(),
+            JobBundle::BurnBatch(bundle) => bundle
+                .payouts
+                .iter()
+                .map(|payout| swap_id(Direction::Burn, payout.burn_id))
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+/// A bundle waiting for the prover.
+struct Job {
+    id: u64,
+    circuit: CircuitType,
+    bundle: JobBundle,
+    /// Span the job is proven in, carrying its swap id; part of the trace of the request that
+    /// queued it.
+    span: Span,
+    /// Job of the job queue the job proves, for a prove request of the job bus.
+    queued: Option<u64>,
+}
+
+impl Job {
+    fn new(id: u64, circuit: CircuitType, bundle: JobBundle) -> Self {
+        let span = info_span!(
+            "job",
+            job_id = id,
+            ?circuit,
+            swap_id = %bundle.swap_id(circuit)
+        );
+        Job {
+            id,
+            circuit,
+            bundle,
+            span,
+            queued: None,
+        }
+    }
+}
+
+/// State shared by the request handlers.
+struct AppState {
+    jobs: Mutex<HashMap<u64, JobStatus>>,
+    /// Hashes of the blocks in each job's bundle chain, lowercase, to find the jobs a
+    /// reorganization invalidates.
+    job_blocks: Mutex<HashMap<u64, Vec<String>>>,
+    next_id: AtomicU64,
+    queue: Mutex<Sender<Job>>,
+    metrics: PrometheusHandle,
+    journal: Arc<Journal>,
+    system: ProofSystem,
+    /// Job bus the finished proofs are published on.
+    bus: Option<Arc<dyn JobBus>>,
+    /// Job queue of the job bus's prove requests, with `--bus`.
+    bus_queue: Option<JobQueue>,
+    retry: RetryArgs,
+}
+
+impl AppState {
+    /// Records the job's progress; a cancelled job stays cancelled, whatever the worker was
+    /// doing with it.
+    fn set_status(&self, id: u64, status: JobStatus) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if !matches!(jobs.get(&id), Some(JobStatus::Cancelled { .. })) {
+            jobs.insert(id, status);
+        }
+    }
+
+    fn is_cancelled(&self, id: u64) -> bool {
+        matches!(
+            self.jobs.lock().unwrap().get(&id),
+            Some(JobStatus::Cancelled { .. })
+        )
+    }
+
+    /// Journals a checked bundle as a new job.
+    fn journal_job(&self, circuit: CircuitType, bundle_json: Value, bundle: JobBundle) -> Job {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.journal.record(JournalEntry {
+            job: id.to_string(),
+            input_hash: sha256::Hash::hash(bundle_json.to_string().as_bytes()).to_string(),
+            circuit: circuit.label().to_string(),
+            proof_mode: self.system.label().to_string(),
+            phase: JobPhase::Queued,
+            input: None,
+            output: None,
+            bundle: Some(bundle_json),
+            request_id: None,
+        });
+        Job::new(id, circuit, bundle)
+    }
+
+    /// Hands a journaled job to the worker; false when the worker is gone.
+    fn enqueue(&self, job: Job) -> bool {
+        let id = job.id;
+        self.set_status(id, JobStatus::Queued);
+        let blocks = job
+            .bundle
+            .chains()
+            .blocks
+            .iter()
+            .map(|block| block.block_hash.to_lowercase())
+            .collect();
+        self.job_blocks.lock().unwrap().insert(id, blocks);
+        if self.queue.lock().unwrap().send(job).is_err() {
+            self.jobs.lock().unwrap().remove(&id);
+            self.journal.remove(&id.to_string());
+            return false;
+        }
+        gauge!(QUEUE_DEPTH).increment(1);
+        true
+    }
+
+    /// Records in the job queue how a job taken from it ended: `None` when it is done or
+    /// cancelled, else its error and whether retrying it may help.
+    fn finish_queued(&self, job: &Job, failure: Option<(&str, bool)>) {
+        let (Some(queue), Some(id)) = (&self.bus_queue, job.queued) else {
+            return;
+        };
+        let result = match failure {
+            None => queue.complete(id),
+            Some((error, false)) => queue.dead_letter(id, error).map(|()| {
+                error!(alert = true, job = id, error, "Prove request dead-lettered");
+            }),
+            Some((error, true)) => queue.fail(id, error, &self.retry).map(|retry| match retry {
+                Retry::At(at) => warn!(job = id, retry_at = at, "Prove request retried later"),
+                Retry::DeadLettered => {
+                    error!(
+                        alert = true,
+                        job = id,
+                        error,
+                        "Prove request out of attempts, dead-lettered"
+                    )
+                }
+            }),
+        };
+        if let Err(e) = result {
+            error!(job = id, error = %e, "Failed to update the job queue");
+        }
+    }
+}
+
+/// `POST /prove/{mint|burn|burn-batch}`: queues the bundle and returns the job id. A bundle
+/// that does not match the input schema is refused with `422` and one line per offending
+/// field.
+async fn submit_job(
+    State(state): State<Arc<AppState>>,
+    Path(circuit): Path<CircuitType>,
+    headers: HeaderMap,
+    Json(bundle_json): Json<Value>,
+) -> Response {
+    let checked = JobBundle::parse(circuit, bundle_json.clone())
+        .map_err(|e| vec![e])
+        .and_then(|bundle| bundle.check().map(|_| bundle));
+    let bundle =
+        match checked {
+            Ok(bundle) => bundle,
+            Err(issues) => {
+                return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "bundle does not match the input schema", "issues": issues })),
+        )
+            .into_response();
+            }
+        };
+    let job = state.journal_job(circuit, bundle_json, bundle);
+    let id = job.id;
+    set_parent_from_headers(&job.span, &headers);
+    let _span = job.span.clone().entered();
+    if !state.enqueue(job) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "prover worker is not running" })),
+        )
+            .into_response();
+    }
+    info!("Job queued");
+    (StatusCode::ACCEPTED, Json(json!({ "job_id": id }))).into_response()
+}
+
+/// `GET /jobs/{id}`: returns the job status, with the fixture once the proof is done.
+async fn job_status(State(state): State<Arc<AppState>>, Path(id): Path<u64>) -> Response {
+    match state.jobs.lock().unwrap().get(&id) {
+        Some(status) => Json(status.clone()).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("unknown job {}", id) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Blocks a reorganization orphaned, as posted to `/invalidate`.
+#[derive(Deserialize, Debug)]
+struct Invalidation {
+    block_hashes: Vec<String>,
+    /// Why the blocks are invalid; reported in the status of the cancelled jobs.
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// `POST /invalidate`: cancels every job whose bundle chain holds one of `block_hashes`,
+/// queued, running or done, so no proof about an orphaned chain is handed out. Returns the
+/// cancelled job ids.
+async fn invalidate(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<Invalidation>,
+) -> Response {
+    let orphaned: Vec<String> = body
+        .block_hashes
+        .iter()
+        .map(|hash| hash.to_lowercase())
+        .collect();
+    let reason = body
+        .reason
+        .unwrap_or_else(|| "a block of the bundle's chain left the best chain".to_string());
+    let mut cancelled: Vec<u64> = state
+        .job_blocks
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, blocks)| blocks.iter().any(|hash| orphaned.contains(hash)))
+        .map(|(id, _)| *id)
+        .collect();
+    cancelled.sort_unstable();
+    {
+        let mut jobs = state.jobs.lock().unwrap();
+        cancelled.retain(|id| !matches!(jobs.get(id), Some(JobStatus::Cancelled { .. })));
+        for id in &cancelled {
+            jobs.insert(
+                *id,
+                JobStatus::Cancelled {
+                    reason: reason.clone(),
+                },
+            );
+        }
+    }
+    for id in &cancelled {
+        state.journal.remove(&id.to_string());
+        counter!(PROOFS_CANCELLED).increment(1);
+        warn!(job_id = id, reason, "Job cancelled");
+    }
+    Json(json!({ "cancelled": cancelled })).into_response()
+}
+
+/// `GET /metrics`: proof counters, failures by reason, proving durations, cycles and the
+/// number of queued jobs, for alerting on prover backlog and degradation.
+async fn render_metrics(State(state): State<Arc<AppState>>) -> String {
+    state.metrics.render()
+}
+
+/// Proves queued jobs one at a time; EVM proofs use the whole machine, so they never overlap.
+fn run_worker(
+    state: Arc<AppState>,
+    queue: Receiver<Job>,
+    prover: ProverBackend,
+    system: ProofSystem,
+    operator: Option<PrivateKeySigner>,
+) {
+    // Setup the prover client and the circuits once.
+    let client = prover.client();
+    let (mint_pk, mint_vk) = client.setup(MINT_CIRCUIT_ELF);
+    let (burn_pk, burn_vk) = client.setup(BURN_CIRCUIT_ELF);
+    let (burn_batch_pk, burn_batch_vk) = client.setup(BURN_BATCH_CIRCUIT_ELF);
+
+    for job in queue {
+        gauge!(QUEUE_DEPTH).decrement(1);
+        let _job_span = job.span.clone().entered();
+        if state.is_cancelled(job.id) {
+            info!("Job cancelled before it ran, skipping");
+            state.finish_queued(&job, None);
+            continue;
+        }
+        let circuit = job.circuit.label();
+        let (elf, pk, vk) = match job.circuit {
+            CircuitType::Mint => (MINT_CIRCUIT_ELF, &mint_pk, &mint_vk),
+            CircuitType::Burn => (BURN_CIRCUIT_ELF, &burn_pk, &burn_vk),
+            CircuitType::BurnBatch => (BURN_BATCH_CIRCUIT_ELF, &burn_batch_pk, &burn_batch_vk),
+        };
+        let mut stdin = SP1Stdin::new();
+        job.bundle.write(&mut stdin);
+        let journal_key = job.id.to_string();
+
+        // Execute first, so a bundle the circuit panics on fails without a proving run
+        state.set_status(job.id, JobStatus::Executing);
+        state.journal.set_phase(&journal_key, JobPhase::Executing);
+        let execution = info_span!("execute").in_scope(|| client.execute(elf, &stdin).run());
+        let report = match execution {
+            Ok((_, report)) => report,
+            Err(e) => {
+                error!(error = %e, "Job failed execution");
+                counter!(PROOF_FAILURES, "circuit" => circuit, "reason" => "execution")
+                    .increment(1);
+                let error = format!("execution failed: {}", e);
+                // The circuit fails the same bundle every time
+                state.finish_queued(&job, Some((&error, false)));
+                state.set_status(job.id, JobStatus::Failed { error });
+                state.journal.remove(&journal_key);
+                continue;
+            }
+        };
+        let cycles = report.total_instruction_count();
+        info!(cycles, "Job executed, proving");
+        histogram!(PROOF_CYCLES, "circuit" => circuit).record(cycles as f64);
+        state.set_status(job.id, JobStatus::Proving { cycles });
+        state.journal.set_phase(&journal_key, JobPhase::Proving);
+
+        let started = Instant::now();
+        let proof = info_span!("prove", ?system).in_scope(|| match system {
+            ProofSystem::Plonk => client.prove(pk, &stdin).plonk().run(),
+            ProofSystem::Groth16 => client.prove(pk, &stdin).groth16().run(),
+        });
+        let fixture = proof.map_err(|e| e.to_string()).and_then(|proof| {
+            histogram!(PROVING_DURATION, "circuit" => circuit, "system" => system.label())
+                .record(started.elapsed().as_secs_f64());
+            counter!(PROOFS_GENERATED, "circuit" => circuit, "system" => system.label())
+                .increment(1);
+            let mut fixture = ZkpProofFixture {
+                vkey: vk.bytes32().to_string(),
+                public_value: format!("0x{}", hex::encode(proof.public_values.as_slice())),
+                proof: format!("0x{}", hex::encode(proof.bytes())),
+                attestation: None,
+                tss_attestation: None,
+            };
+            if let Some(operator) = &operator {
+                sign_fixture(&mut fixture, operator)
+                    .map_err(|e| format!("signing the fixture: {}", e))?;
+            }
+            Ok(fixture)
+        });
+        let status = match fixture {
+            Ok(fixture) => JobStatus::Done { fixture },
+            Err(e) => {
+                error!(error = %e, "Job failed proving");
+                counter!(PROOF_FAILURES, "circuit" => circuit, "reason" => "proving").increment(1);
+                JobStatus::Failed {
+                    error: format!("proving failed: {}", e),
+                }
+            }
+        };
+        if state.is_cancelled(job.id) {
+            info!("Job cancelled while it ran, dropping its result");
+            state.finish_queued(&job, None);
+            continue;
+        }
+        info!("Job finished");
+        if let (Some(bus), JobStatus::Done { fixture }) = (&state.bus, &status) {
+            let ready = BusEvent::ProofReady {
+                swap_id: job.bundle.swap_id(job.circuit),
+                circuit: circuit.to_string(),
+                job_id: job.id,
+                fixture: fixture.clone(),
+            };
+            if let Err(e) = bus.publish(&ready) {
+                warn!(error = %e, "Failed to publish the proof on the job bus");
+            }
+        }
+        match &status {
+            JobStatus::Failed { error } => state.finish_queued(&job, Some((error, true))),
+            _ => state.finish_queued(&job, None),
+        }
+        state.set_status(job.id, status);
+        state.journal.remove(&journal_key);
+    }
+}
+
+/// Prove request of the job bus, as kept in the job queue.
+#[derive(Serialize, Deserialize, Debug)]
+struct QueuedBundle {
+    circuit: String,
+    bundle: Value,
+}
+
+/// Hands the due prove requests of the job queue to the worker. A bundle that does not match
+/// the input schema is dead-lettered. Returns false when the worker is gone.
+fn feed_queued(state: &AppState, queue: &JobQueue) -> Result<bool, Box<dyn std::error::Error>> {
+    while let Some(queued) = queue.next_due()? {
+        let checked = queued
+            .payload::<QueuedBundle>()
+            .map_err(|e| e.to_string())
+            .and_then(|request| {
+                let circuit = serde_json::from_value::<CircuitType>(json!(request.circuit))
+                    .map_err(|e| e.to_string())?;
+                let parsed = JobBundle::parse(circuit, request.bundle)?;
+                parsed.check().map_err(|issues| issues.join("; "))?;
+                Ok((circuit, parsed))
+            });
+        let (circuit, parsed) = match checked {
+            Ok(checked) => checked,
+            Err(e) => {
+                warn!(job = queued.id, swap_id = %queued.swap_id, kind = queued.kind, error = %e, "Refusing a bundle from the job bus");
+                queue.dead_letter(queued.id, &format!("bundle refused: {}", e))?;
+                continue;
+            }
+        };
+        // Not journaled: the job queue hands it out again after a restart
+        let id = state.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut job = Job::new(id, circuit, parsed);
+        job.queued = Some(queued.id);
+        let _span = job.span.clone().entered();
+        if !state.enqueue(job) {
+            error!("Prover worker is not running, leaving the request in the job queue");
+            return Ok(false);
+        }
+        info!(
+            job = queued.id,
+            attempt = queued.attempts,
+            "Job queued from the job queue"
+        );
+    }
+    Ok(true)
+}
+
+/// Keeps the bus's `prove-requested` events in the job queue, acknowledging each once it is
+/// written, and hands the due ones to the worker.
+fn consume_bus(state: Arc<AppState>, bus: Arc<dyn JobBus>) {
+    let queue = state
+        .bus_queue
+        .as_ref()
+        .expect("the job queue is opened with the job bus");
+    loop {
+        match bus.next(Topic::ProveRequested, BUS_WAIT) {
+            Ok(Some(delivery)) => {
+                if let BusEvent::ProveRequested {
+                    swap_id,
+                    circuit,
+                    bundle,
+                } = &delivery.event
+                {
+                    let request = QueuedBundle {
+                        circuit: circuit.clone(),
+                        bundle: bundle.clone(),
+                    };
+                    let kind = format!("prove-{}", circuit);
+                    match queue.push(&kind, swap_id, json!(request)) {
+                        Ok(queued) => {
+                            info!(event = %delivery.id, %swap_id, job = queued.id, "Prove request queued")
+                        }
+                        Err(e) => {
+                            // Left on the bus, to be delivered again
+                            error!(event = %delivery.id, %swap_id, error = %e, "Failed to queue the prove request");
+                            std::thread::sleep(BUS_WAIT);
+                            continue;
+                        }
+                    }
+                }
+                if let Err(e) = bus.ack(&delivery) {
+                    warn!(event = %delivery.id, error = %e, "Failed to acknowledge the prove request");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(error = %e, "Failed to read the job bus, retrying");
+                std::thread::sleep(BUS_WAIT);
+            }
+        }
+        match feed_queued(&state, queue) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => warn!(error = %e, "Failed to read the job queue"),
+        }
+    }
+}
+
+/// Queues the jobs of the journal again, under their job ids. A job whose bundle cannot be
+/// read back is dropped from the journal.
+fn resume_journal(state: &AppState, journaled: Vec<JournalEntry>) {
+    info!(count = journaled.len(), journal = %state.journal.path().display(), "Resuming journaled jobs");
+    for entry in journaled {
+        let job = entry
+            .job
+            .parse::<u64>()
+            .map_err(|e| e.to_string())
+            .and_then(|id| {
+                let circuit =
+                    serde_json::from_value(json!(entry.circuit)).map_err(|e| e.to_string())?;
+                let bundle = JobBundle::parse(circuit, entry.bundle.clone().unwrap_or_default())?;
+                Ok(Job::new(id, circuit, bundle))
+            });
+        match job {
+            Ok(job) => {
+                info!(job_id = job.id, circuit = ?job.circuit, phase = ?entry.phase, "Journaled job queued");
+                state.enqueue(job);
+            }
+            Err(e) => {
+                warn!(job = %entry.job, error = %e, "Dropping unreadable journaled job");
+                state.journal.remove(&entry.job);
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+
+    // Parse the command line arguments.
+    let mut args = ServeArgs::parse();
+
+    // Setup the logger.
+    setup_logging(args.log_format);
+    args.prover = args.prover.resolve();
+
+    // Setup the metrics recorder; `/metrics` renders it on each scrape.
+    let metrics = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(PROVING_DURATION.to_string()),
+            PROVING_DURATION_BUCKETS,
+        )
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full(PROOF_CYCLES.to_string()),
+                PROOF_CYCLES_BUCKETS,
+            )
+        })
+        .and_then(|builder| builder.install_recorder())
+        .expect("failed to install the metrics recorder");
+    gauge!(QUEUE_DEPTH).set(0.0);
+
+    // Journal the queued and running jobs, so a restart or deploy does not lose them
+    let journal_path = args
+        .journal
+        .clone()
+        .unwrap_or_else(|| journal::default_path("serve"));
+    let journal = Journal::open(journal_path).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the journal");
+        std::process::exit(1);
+    });
+    let journal = Arc::new(journal);
+    exit_on_signal(journal.clone());
+    let journaled = journal.entries();
+    let next_id = journaled
+        .iter()
+        .filter_map(|entry| entry.job.parse::<u64>().ok())
+        .max()
+        .map_or(1, |id| id + 1);
+
+    let bus = args.bus.open("serve").unwrap_or_else(|e| {
+        error!(error = %e, "Failed to connect to the job bus");
+        std::process::exit(1);
+    });
+    let bus_queue = bus.as_ref().map(|_| {
+        JobQueue::open(args.queue_db.as_deref()).unwrap_or_else(|e| {
+            error!(error = %e, "Failed to open the job queue");
+            std::process::exit(1);
+        })
+    });
+
+    let (sender, receiver) = mpsc::channel();
+    let state = Arc::new(AppState {
+        jobs: Mutex::new(HashMap::new()),
+        job_blocks: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(next_id),
+        queue: Mutex::new(sender),
+        metrics,
+        journal,
+        system: args.system,
+        bus: bus.clone(),
+        bus_queue,
+        retry: args.retry.clone(),
+    });
+    if args.resume_journal {
+        resume_journal(&state, journaled);
+    } else if !journaled.is_empty() {
+        warn!(
+            jobs = journaled.len(),
+            "The journal holds interrupted jobs; restart with --resume-journal to prove them"
+        );
+    }
+    let operator = args.operator_key.as_deref().map(|key| {
+        let signer = operator_signer(key).unwrap_or_else(|e| {
+            error!(error = %e, "Failed to load the operator key");
+            std::process::exit(1);
+        });
+        info!(operator = %signer.address(), "Fixtures are signed by the operator");
+        signer
+    });
+    let worker_state = state.clone();
+    std::thread::spawn(move || {
+        run_worker(worker_state, receiver, args.prover, args.system, operator)
+    });
+    if let Some(bus) = bus {
+        let bus_state = state.clone();
+        std::thread::spawn(move || consume_bus(bus_state, bus));
+    }
+
+    let app = Router::new()
+        .route("/prove/:circuit", post(submit_job))
+        .route("/jobs/:id", get(job_status))
+        .route("/metrics", get(render_metrics))
+        .route("/invalidate", post(invalidate))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .expect("failed to bind listen address");
+    info!(listen = %args.listen, system = ?args.system, bus = args.bus.bus, "Proof service listening");
+    axum::serve(listener, app).await.expect("server error");
+}
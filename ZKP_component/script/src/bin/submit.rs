@@ -0,0 +1,799 @@
+//! Submits a proof fixture to the bridge contract: `verifyAndMint` for a deposit or
+//! `submitBurnProof` for a payout, with a gas estimate up front and a wait for the receipt.
+//! With `--fee-oracle`, the fee per gas comes from the fee oracle instead of the node.
+//! Transactions go through the settlement relayer, which manages the account's nonce, replaces
+//! a settlement left unmined with higher fees, and reports a revert with the contract's error.
+//! A mint is checked against the `--policy-*` hooks first, and refused if one denies it.
+//!
+//! With `--tss-group-key`, the fixture must be attested by the TSS signer set: `--attest-command`
+//! has the signers co-sign its vkey and public values hash when it is not yet, and the
+//! attestation is written back to the fixture. The 64-byte group signature is appended to the
+//! settlement calldata, past the ABI-encoded arguments the contract reads, so it is recorded
+//! with the transaction for monitors to check.
+//!
+//! Nothing is submitted while a pause notice (`--pause-notice`) is in force.
+//!
+//! With `--bus` instead of `--fixture`, the submitter runs as a service: it settles the fixture
+//! of every `proof-ready` event of its circuit on the job bus, keeping each in `--fixture-dir`,
+//! and waits out a pause without dropping the events. Each event is kept in the job queue
+//! `--queue-db` until its settlement is confirmed; a failed settlement is retried with backoff,
+//! and dead-lettered after `--retry-max-attempts`. One the contract reverts is dead-lettered at
+//! once, as retrying does not change its mind, and one it has already is done.
+//!
+//! With `--swap-api`, a confirmed settlement is recorded on its swap through the swap API, with
+//! its transaction hash.
+//!
+//! With `--payout-queue`, a deposit that cannot be minted is refunded instead: when its proof
+//! rejects it for its memo or amount, or a policy hook denies it for good, its refund to the
+//! address the proof commits is queued on `burn-listener`'s payout queue for `payout-scheduler`
+//! to pay, and recorded on its swap with `--swap-api`. A deposit with a refund is never minted.
+
+// Usage Example:
+// cargo run --release --bin submit -- --fixture ../contracts/src/fixtures/groth16-fixture_mint.json --circuit mint --rpc http://127.0.0.1:8545 --contract 0x...
+// cargo run --release --bin submit -- --fixture ./groth16-fixture_burn.json --circuit burn --rpc http://127.0.0.1:8545 --contract 0x... --dry-run
+// cargo run --release --bin submit -- --fixture ./groth16-fixture_mint.json --rpc http://127.0.0.1:8545 --contract 0x... --authorized-prover 0x...
+// cargo run --release --bin submit -- --fixture ./groth16-fixture_mint.json --rpc http://127.0.0.1:8545 --contract 0x... --fee-oracle http://127.0.0.1:3200
+// cargo run --release --bin submit -- --fixture ./groth16-fixture_mint.json --rpc http://127.0.0.1:8545 --contract 0x... --tss-group-key <x-only group key> --attest-command "coordinator --db ./coordinator-db attest"
+// cargo run --release --bin submit -- --fixture ./groth16-fixture_mint.json --rpc http://127.0.0.1:8545 --contract 0x... --policy-max-mint-sats 10000000 --policy-db ./policy-db --policy-mint-volume-sats 100000000
+// cargo run --release --bin submit -- --bus redis://127.0.0.1:6379 --circuit mint --rpc http://127.0.0.1:8545 --contract 0x... --fixture-dir ./bus-fixtures
+// cargo run --release --bin submit -- --bus redis://127.0.0.1:6379 --circuit mint --rpc http://127.0.0.1:8545 --contract 0x... --queue-db ./submit-mint-queue --retry-max-attempts 12
+// cargo run --release --bin submit -- --bus redis://127.0.0.1:6379 --circuit burn --rpc http://127.0.0.1:8545 --contract 0x... --swap-api http://127.0.0.1:3100 --bump-after-secs 120 --max-fee-gwei 50
+// cargo run --release --bin submit -- --bus redis://127.0.0.1:6379 --circuit mint --rpc http://127.0.0.1:8545 --contract 0x... --payout-queue http://127.0.0.1:3300 --swap-api http://127.0.0.1:3100
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use alloy_sol_types::SolType;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin_verify_script::attestation::{
+    request_tss_attestation, verify_fixture, verify_tss_attestation,
+};
+use bitcoin_verify_script::bus::{BusArgs, BusEvent, JobBus, Topic, BUS_WAIT};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::fees::fetch_quote;
+use bitcoin_verify_script::job_queue::{JobQueue, QueuedJob, Retry, RetryArgs};
+use bitcoin_verify_script::pause::{PauseArgs, PauseGuard};
+use bitcoin_verify_script::policy::PolicyArgs;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::relayer::{Fees, Relayer, RelayerArgs, Revert};
+use bitcoin_verify_script::store::{PayoutStatus, RefundJob};
+use bitcoin_verify_script::telemetry::{self, set_parent_from_env, SWAP_IDS_ENV};
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use bridge_core::policy::{Decision, PolicyEngine, Transfer};
+use bridge_core::{swap_id, Direction};
+use clap::{Parser, ValueEnum};
+use lib_struct::{
+    MintRejectReason, ZkpBurnPublicValuesStruct, ZkpMintPublicValuesStruct, ZkpProofFixture,
+};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::field::{display, Empty};
+use tracing::{error, info, info_span, warn, Instrument, Span};
+
+sol! {
+    /// Settlement entrypoints of the ZKBTC bridge contract.
+    interface IZKBTC {
+        function verifyAndMint(bytes calldata _publicValues, bytes calldata _proofBytes) external returns (bytes32, address, uint256, bool);
+        function submitBurnProof(uint256 burnId, bytes calldata _publicValues, bytes calldata _proofBytes) external;
+    }
+}
+
+/// Enum representing the circuits whose fixtures can be settled on-chain
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    Burn,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Fixture JSON written by `evm`.
+    #[clap(long, required_unless_present = "bus")]
+    fixture: Option<PathBuf>,
+    /// Directory the fixtures received on the job bus are kept in, as `<circuit>-<job id>.json`,
+    /// with the TSS attestation once they have it.
+    #[clap(long, default_value = "./bus-fixtures")]
+    fixture_dir: PathBuf,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
+    circuit: CircuitType,
+    /// Ethereum JSON-RPC endpoint.
+    #[clap(long, env = "ETH_RPC_URL")]
+    rpc: String,
+    /// Chain id `--rpc` must serve, so a settlement never lands on another chain than the
+    /// network profile's; not checked when unset.
+    #[clap(long, env = "EVM_CHAIN_ID")]
+    chain_id: Option<u64>,
+    /// Address of the ZKBTC contract.
+    #[clap(long, env = "ZKBTC_CONTRACT")]
+    contract: Address,
+    /// Key of the account paying for the transaction.
+    #[clap(long, env = "PRIVATE_KEY", hide_env_values = true)]
+    private_key: String,
+    /// Blocks to wait for on top of the transaction's block.
+    #[clap(long, default_value_t = 1)]
+    confirmations: u64,
+    #[clap(flatten)]
+    relayer: RelayerArgs,
+    /// Simulates the call and estimates gas without sending the transaction.
+    #[clap(long)]
+    dry_run: bool,
+    /// Operator addresses whose signed fixtures are accepted; repeat the flag or separate
+    /// addresses with commas. When set, an unsigned fixture or one signed by another key is
+    /// refused before anything is sent.
+    #[clap(long, env = "AUTHORIZED_PROVERS", value_delimiter = ',')]
+    authorized_prover: Vec<Address>,
+    /// TSS group key the pause notices, and the attestations, are signed by.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Requires an attestation of the fixture by the group key; a fixture without it is
+    /// refused, unless `--attest-command` obtains it.
+    #[clap(long, env = "TSS_ATTESTATION")]
+    tss_attestation: bool,
+    /// Command having the TSS signer set attest the fixture, e.g. `coordinator attest`;
+    /// `--vkey-hex <vkey> --public-values-hash-hex <hash>` is appended, and it must print the
+    /// group signature.
+    #[clap(long, env = "TSS_ATTEST_COMMAND", requires = "tss_attestation")]
+    attest_command: Option<String>,
+    /// Base URL of the fee oracle (`fee-oracle`) the fee per gas is taken from; the node's
+    /// suggestion is used without it.
+    #[clap(long, env = "FEE_ORACLE_URL")]
+    fee_oracle: Option<String>,
+    /// Base URL of the swap API (`api`) the confirmed settlements are recorded on, moving their
+    /// swaps to minted or settled.
+    #[clap(long, env = "SWAP_API_URL")]
+    swap_api: Option<String>,
+    /// Base URL of `burn-listener`'s payout queue the refunds of the deposits that cannot be
+    /// minted are queued on; without it, such a deposit is only refused.
+    #[clap(long, env = "PAYOUT_QUEUE_URL")]
+    payout_queue: Option<String>,
+    #[clap(flatten)]
+    policy: PolicyArgs,
+    #[clap(flatten)]
+    pause: PauseArgs,
+    #[clap(flatten)]
+    bus: BusArgs,
+    /// sled job queue the proofs received on the job bus are kept in until they are settled,
+    /// so a failed settlement is retried after a restart too; without it, the queue is lost on
+    /// exit.
+    #[clap(long, env = "SUBMIT_QUEUE_DB")]
+    queue_db: Option<PathBuf>,
+    #[clap(flatten)]
+    retry: RetryArgs,
+    /// sled database the minted volume is recorded in, for `--policy-mint-volume-sats` and the
+    /// `--policy-mint-window-*` limits.
+    #[clap(long, env = "POLICY_DB")]
+    policy_db: Option<PathBuf>,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Settlement of a `proof-ready` event, as kept in the job queue.
+#[derive(Serialize, Deserialize, Debug)]
+struct QueuedProof {
+    /// Job id of the proof on the service that generated it.
+    job_id: u64,
+    fixture: ZkpProofFixture,
+}
+
+/// Settlement of a swap, as recorded with the swap API's `POST /settlements`.
+#[derive(Serialize, Default, Debug)]
+struct SettlementReport {
+    /// Deposit a mint settlement mints, its request id and the amount it paid the vault.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deposit_txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount_sats: Option<u64>,
+    /// Burn request a burn settlement fulfills, and its payout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    burn_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payout_txid: Option<String>,
+    /// Proof job of the settled proof, when it came off the job bus.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<u64>,
+    eth_tx: String,
+}
+
+/// Refund of a deposit that cannot be minted, as queued with the payout queue's
+/// `POST /refunds` and recorded with the swap API's.
+#[derive(Serialize, Debug)]
+struct RefundRequest {
+    deposit_txid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<u64>,
+    /// Amount the deposit paid the vault, when the proof commits it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount_sats: Option<u64>,
+    btc_address: String,
+    reason: String,
+}
+
+/// A mint refused for good, as its proof rejects the deposit or a policy hook denies it; it is
+/// owed a refund when the deposit paid the bridge and its sender's address is proven.
+#[derive(Debug)]
+struct Rejected {
+    message: String,
+    refund: Option<RefundRequest>,
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for Rejected {}
+
+/// The refund of a rejected deposit, unless its sender's address is not proven.
+fn refund_request(
+    decoded: &ZkpMintPublicValuesStruct,
+    amount_sats: Option<u64>,
+    reason: String,
+) -> Option<RefundRequest> {
+    if decoded.refund_btc_address.is_empty() {
+        return None;
+    }
+    Some(RefundRequest {
+        deposit_txid: hex::encode(decoded.tx_id),
+        request_id: u64::try_from(decoded.request_id).ok().filter(|id| *id != 0),
+        amount_sats,
+        btc_address: decoded.refund_btc_address.clone(),
+        reason,
+    })
+}
+
+/// Settlement call of a fixture.
+struct Settlement {
+    calldata: Vec<u8>,
+    /// Swap the fixture settles.
+    swap: String,
+    /// Mint to record once it is confirmed.
+    mint: Option<Transfer>,
+    report: SettlementReport,
+}
+
+/// Builds the settlement calldata, refusing public values the contract would revert on and
+/// mints the policy denies. A rejected deposit, or a mint a hook denies for good, is refused
+/// with a `Rejected` error.
+fn settlement_calldata(
+    circuit: CircuitType,
+    public_values: Bytes,
+    proof: Bytes,
+    policy: &PolicyEngine,
+) -> Result<Settlement, Box<dyn Error>> {
+    match circuit {
+        CircuitType::Mint => {
+            let decoded = ZkpMintPublicValuesStruct::abi_decode_params(&public_values)?;
+            if !decoded.is_valid {
+                let reason = MintRejectReason::from_code(decoded.reject_reason);
+                let refund = reason
+                    .filter(|reason| reason.is_refundable())
+                    .and_then(|reason| {
+                        refund_request(&decoded, None, format!("rejected: {:?}", reason))
+                    });
+                return Err(Box::new(Rejected {
+                    message: format!(
+                        "Mint proof for {} is a rejection (reason {}); the contract would revert",
+                        decoded.tx_id, decoded.reject_reason
+                    ),
+                    refund,
+                }));
+            }
+            let txid = hex::encode(decoded.tx_id);
+            let id = swap_id(Direction::Mint, &txid);
+            Span::current().record("swap_id", display(&id));
+            let transfer = Transfer {
+                direction: Direction::Mint,
+                id: decoded.tx_id.to_string(),
+                amount_sats: u64::try_from(decoded.amount)
+                    .map_err(|_| format!("Mint amount {} is not in sats", decoded.amount))?,
+                recipient: decoded.depositer_address.to_string(),
+            };
+            let report = SettlementReport {
+                deposit_txid: Some(txid),
+                request_id: u64::try_from(decoded.request_id).ok().filter(|id| *id != 0),
+                amount_sats: u64::try_from(decoded.gross_amount).ok(),
+                ..SettlementReport::default()
+            };
+            if let Decision::Deny { reason } = policy.check(&transfer, &[])? {
+                let message = format!("Mint for {} denied by policy: {}", decoded.tx_id, reason);
+                // A limit over the minted volume lifts with time; the mint is retried
+                let Some(lasting) = policy.lasting_denial(&transfer)? else {
+                    return Err(message.into());
+                };
+                return Err(Box::new(Rejected {
+                    message,
+                    refund: refund_request(
+                        &decoded,
+                        report.amount_sats,
+                        format!("policy: {}", lasting),
+                    ),
+                }));
+            }
+            println!(
+                "Minting {} sats to {} for deposit {}",
+                decoded.amount, decoded.depositer_address, decoded.tx_id
+            );
+            let calldata = IZKBTC::verifyAndMintCall {
+                _publicValues: public_values,
+                _proofBytes: proof,
+            }
+            .abi_encode();
+            Ok(Settlement {
+                calldata,
+                swap: id,
+                mint: Some(transfer),
+                report,
+            })
+        }
+        CircuitType::Burn => {
+            let decoded = ZkpBurnPublicValuesStruct::abi_decode_params(&public_values)?;
+            let id = swap_id(Direction::Burn, decoded.burn_id);
+            let report = SettlementReport {
+                burn_id: u64::try_from(decoded.burn_id).ok(),
+                payout_txid: Some(hex::encode(decoded.payout_tx_id)),
+                ..SettlementReport::default()
+            };
+            Span::current().record("swap_id", display(&id));
+            if !decoded.is_valid {
+                return Err(format!(
+                    "Burn proof for burn {} is a rejection (reason {}); the contract would revert",
+                    decoded.burn_id, decoded.reject_reason
+                )
+                .into());
+            }
+            println!(
+                "Settling burn {} with payout {} of {} sats to {}",
+                decoded.burn_id, decoded.payout_tx_id, decoded.amount, decoded.burner_btc_address
+            );
+            let calldata = IZKBTC::submitBurnProofCall {
+                burnId: decoded.burn_id,
+                _publicValues: public_values,
+                _proofBytes: proof,
+            }
+            .abi_encode();
+            Ok(Settlement {
+                calldata,
+                swap: id,
+                mint: None,
+                report,
+            })
+        }
+    }
+}
+
+/// Returns the TSS group signature over the fixture, having the signer set attest it first
+/// when it is not yet and `--attest-command` is given.
+fn tss_attestation(
+    args: &Args,
+    path: &Path,
+    fixture: &mut ZkpProofFixture,
+    group_key: &XOnlyPublicKey,
+    swap: &str,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Err(e) = verify_tss_attestation(fixture, group_key) {
+        let command = args
+            .attest_command
+            .as_ref()
+            .ok_or_else(|| format!("Refusing fixture {}: {}", path.display(), e))?;
+        println!("Requesting the TSS attestation of the fixture");
+        let env = [(SWAP_IDS_ENV.to_string(), swap.to_string())];
+        tokio::task::block_in_place(|| request_tss_attestation(fixture, command, group_key, &env))
+            .map_err(|e| format!("TSS attestation failed: {}", e))?;
+        // Kept with the fixture, so a retry or a monitor finds it
+        std::fs::write(path, serde_json::to_string_pretty(fixture)?)?;
+    }
+    let attestation = fixture
+        .tss_attestation
+        .as_ref()
+        .ok_or("the fixture is not attested")?;
+    println!("Fixture attested by TSS group key {}", group_key);
+    Ok(hex::decode(&attestation.signature)?)
+}
+
+/// Relayer sending from the `--private-key` account over `--rpc`, once the endpoint is checked
+/// to serve `--chain-id`.
+async fn connect(args: &Args) -> Result<Relayer<impl Provider>, Box<dyn Error>> {
+    let signer: PrivateKeySigner = args.private_key.parse()?;
+    let address = signer.address();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect_http(args.rpc.parse()?);
+    if let Some(expected) = args.chain_id {
+        let served = provider.get_chain_id().await?;
+        if served != expected {
+            return Err(format!(
+                "{} serves chain {}, not chain {}",
+                args.rpc, served, expected
+            )
+            .into());
+        }
+    }
+    Ok(Relayer::new(
+        provider,
+        address,
+        args.relayer.clone(),
+        args.confirmations,
+    ))
+}
+
+/// Refund of the deposit on the payout queue, if one was requested.
+fn queued_refund(
+    payout_queue: &str,
+    deposit_txid: &str,
+) -> Result<Option<RefundJob>, Box<dyn Error>> {
+    let response = Client::new()
+        .get(format!(
+            "{}/refunds/{}",
+            payout_queue.trim_end_matches('/'),
+            deposit_txid
+        ))
+        .send()?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.json()?))
+}
+
+/// Queues the refund on the payout queue, then records it on its swap with `--swap-api`; the
+/// refund stands if the swap cannot record it.
+fn request_refund(
+    args: &Args,
+    payout_queue: &str,
+    refund: &RefundRequest,
+) -> Result<(), Box<dyn Error>> {
+    let job: RefundJob = Client::new()
+        .post(format!("{}/refunds", payout_queue.trim_end_matches('/')))
+        .json(refund)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    match &job.status {
+        PayoutStatus::Invalid { reason } => {
+            error!(alert = true, deposit = job.deposit_txid, btc_address = job.btc_address, %reason, "Rejected deposit cannot be refunded")
+        }
+        status => {
+            info!(
+                deposit = job.deposit_txid,
+                btc_address = job.btc_address,
+                reason = job.reason,
+                ?status,
+                "Refund of the rejected deposit queued"
+            )
+        }
+    }
+    if let Some(swap_api) = &args.swap_api {
+        let recorded = Client::new()
+            .post(format!("{}/refunds", swap_api.trim_end_matches('/')))
+            .json(refund)
+            .send()
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = recorded {
+            warn!(deposit = refund.deposit_txid, error = %e, "Failed to record the refund on the swap");
+        }
+    }
+    Ok(())
+}
+
+/// Records a confirmed settlement on its swap with the swap API's `POST /settlements`.
+fn report_settlement(swap_api: &str, report: &SettlementReport) -> Result<Value, Box<dyn Error>> {
+    Ok(Client::new()
+        .post(format!("{}/settlements", swap_api.trim_end_matches('/')))
+        .json(report)
+        .send()?
+        .error_for_status()?
+        .json()?)
+}
+
+/// Settles the fixture at `path`; `job_id` is the proof job it came from, if known.
+async fn submit(
+    args: &Args,
+    path: &Path,
+    relayer: &mut Relayer<impl Provider>,
+    job_id: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(notice) = PauseGuard::new(&args.pause, &args.group_key, "submit").current()? {
+        return Err(format!("Submissions are paused ({})", notice).into());
+    }
+    let file = std::fs::File::open(path)?;
+    let mut fixture: ZkpProofFixture = serde_json::from_reader(file)?;
+    if !args.authorized_prover.is_empty() {
+        let operator = verify_fixture(&fixture, &args.authorized_prover)
+            .map_err(|e| format!("Refusing fixture {}: {}", path.display(), e))?;
+        println!("Fixture signed by authorized prover {}", operator);
+    }
+    let public_values = Bytes::from(hex::decode(fixture.public_value.trim_start_matches("0x"))?);
+    let proof = Bytes::from(hex::decode(fixture.proof.trim_start_matches("0x"))?);
+    let policy_db = args
+        .policy_db
+        .as_ref()
+        .map(sled::open)
+        .transpose()
+        .map_err(|e| format!("Failed to open the policy database: {}", e))?;
+    let policy = args.policy.engine(Direction::Mint, policy_db.as_ref())?;
+    let settlement = match settlement_calldata(args.circuit, public_values, proof, &policy) {
+        Ok(settlement) => settlement,
+        Err(e) => {
+            let refund = e
+                .downcast_ref::<Rejected>()
+                .and_then(|rejected| rejected.refund.as_ref());
+            let (Some(refund), Some(payout_queue)) = (refund, &args.payout_queue) else {
+                return Err(e);
+            };
+            println!(
+                "{}; refunding {} to {}",
+                e, refund.deposit_txid, refund.btc_address
+            );
+            tokio::task::block_in_place(|| request_refund(args, payout_queue, refund))?;
+            return Ok(());
+        }
+    };
+    let Settlement {
+        mut calldata,
+        swap,
+        mint,
+        mut report,
+    } = settlement;
+    if let (Some(payout_queue), Some(txid)) = (&args.payout_queue, &report.deposit_txid) {
+        let refund = tokio::task::block_in_place(|| queued_refund(payout_queue, txid))?;
+        if let Some(refund) =
+            refund.filter(|refund| !matches!(refund.status, PayoutStatus::Invalid { .. }))
+        {
+            return Err(Box::new(Rejected {
+                message: format!(
+                    "Deposit {} is refunded to {}; refusing to mint it",
+                    txid, refund.btc_address
+                ),
+                refund: None,
+            }));
+        }
+    }
+    if args.tss_attestation {
+        let signature =
+            tss_attestation(args, path, &mut fixture, &args.group_key.group_key, &swap)?;
+        calldata.extend_from_slice(&signature);
+    }
+
+    let from = args.private_key.parse::<PrivateKeySigner>()?.address();
+    println!("Submitting from {} to {}", from, args.contract);
+
+    // === Simulate and estimate gas; a revert here costs nothing ===
+    let tx = TransactionRequest::default()
+        .with_from(from)
+        .with_to(args.contract)
+        .with_input(calldata);
+    let mut fees = None;
+    if let Some(url) = &args.fee_oracle {
+        let quote = tokio::task::block_in_place(|| fetch_quote(&Client::new(), url))
+            .map_err(|e| format!("Failed to read the fee oracle: {}", e))?;
+        let evm = quote
+            .evm
+            .ok_or("Fee oracle has no EVM gas price; no sane source answered")?;
+        println!(
+            "Fee per gas from the oracle: {} wei (tip {} wei)",
+            evm.max_fee_per_gas, evm.max_priority_fee_per_gas
+        );
+        fees = Some(Fees {
+            max_fee_per_gas: evm.max_fee_per_gas,
+            max_priority_fee_per_gas: evm.max_priority_fee_per_gas,
+        });
+    }
+    let gas_limit = relayer.simulate(&tx).await?;
+    if args.dry_run {
+        println!("Dry run: transaction not sent");
+        return Ok(());
+    }
+
+    // === Send, bumping the fees until it is mined, and wait for the confirmations ===
+    let fees = relayer.fees(fees).await?;
+    let settled = relayer.send(tx, gas_limit, fees).await?;
+    println!(
+        "Transaction {} confirmed in block {} (gas used: {}, {} wei per gas)",
+        settled.tx_hash, settled.block, settled.gas_used, settled.gas_price
+    );
+    if let Some(mint) = mint {
+        policy.record(&mint)?;
+    }
+    if let Some(swap_api) = &args.swap_api {
+        report.job_id = job_id;
+        report.eth_tx = settled.tx_hash.to_string();
+        match tokio::task::block_in_place(|| report_settlement(swap_api, &report)) {
+            Ok(response) => {
+                info!(swap = %response["swap"]["id"], state = %response["swap"]["state"], eth_tx = report.eth_tx, "Settlement recorded on the swap")
+            }
+            // The settlement stands; only the swap's view of it lags
+            Err(e) => {
+                warn!(eth_tx = report.eth_tx, error = %e, "Failed to record the settlement on the swap")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Settles a queued proof, from its fixture in `--fixture-dir`; the fixture is written there
+/// on the first attempt, and kept with its TSS attestation for the next ones.
+async fn settle(
+    args: &Args,
+    circuit: &str,
+    job: &QueuedJob,
+    relayer: &mut Relayer<impl Provider>,
+) -> Result<(), Box<dyn Error>> {
+    let proof: QueuedProof = job.payload()?;
+    let path = args
+        .fixture_dir
+        .join(format!("{}-{}.json", circuit, proof.job_id));
+    if !path.exists() {
+        std::fs::write(&path, serde_json::to_string_pretty(&proof.fixture)?)?;
+    }
+    let span =
+        info_span!("submit", circuit, swap_id = %job.swap_id, job = job.id, attempt = job.attempts);
+    submit(args, &path, relayer, Some(proof.job_id))
+        .instrument(span)
+        .await?;
+    info!(swap_id = %job.swap_id, fixture = %path.display(), "Proof settled");
+    Ok(())
+}
+
+/// Settles the fixtures of the bus's `proof-ready` events of `--circuit`, one at a time. An
+/// event is acknowledged once it is in the job queue; a settlement that fails is retried from
+/// the queue with backoff, and none is attempted while the bridge is paused. A settlement the
+/// contract reverts is dead-lettered, or done if the contract has it already, and so is a mint
+/// refused for good that could not be refunded.
+async fn consume(args: &Args, bus: &dyn JobBus) -> Result<(), Box<dyn Error>> {
+    let circuit = match args.circuit {
+        CircuitType::Mint => "mint",
+        CircuitType::Burn => "burn",
+    };
+    let kind = format!("settle-{}", circuit);
+    std::fs::create_dir_all(&args.fixture_dir)?;
+    let queue = JobQueue::open(args.queue_db.as_deref())
+        .map_err(|e| format!("Failed to open the job queue: {}", e))?;
+    let pause = PauseGuard::new(&args.pause, &args.group_key, "submit");
+    let mut relayer = connect(args).await?;
+    info!(
+        circuit,
+        bus = args.bus.bus,
+        queued = queue.jobs()?.len(),
+        dead_letters = queue.dead_letters()?.len(),
+        "Settling the proofs of the job bus"
+    );
+    let mut paused = None;
+    loop {
+        match tokio::task::block_in_place(|| bus.next(Topic::ProofReady, BUS_WAIT)) {
+            Ok(Some(delivery)) => {
+                if let BusEvent::ProofReady {
+                    swap_id,
+                    circuit: proven,
+                    job_id,
+                    fixture,
+                } = &delivery.event
+                {
+                    if proven == circuit {
+                        let proof = QueuedProof {
+                            job_id: *job_id,
+                            fixture: fixture.clone(),
+                        };
+                        // Left on the bus when it cannot be queued, to be delivered again
+                        match queue.push(&kind, swap_id, serde_json::to_value(&proof)?) {
+                            Ok(job) => {
+                                info!(%swap_id, event = %delivery.id, job = job.id, "Proof queued")
+                            }
+                            Err(e) => {
+                                error!(%swap_id, event = %delivery.id, error = %e, "Failed to queue the proof");
+                                continue;
+                            }
+                        }
+                    }
+                }
+                if let Err(e) = tokio::task::block_in_place(|| bus.ack(&delivery)) {
+                    warn!(event = %delivery.id, error = %e, "Failed to acknowledge the proof");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(error = %e, "Failed to read the job bus, retrying");
+                tokio::time::sleep(BUS_WAIT).await;
+            }
+        }
+
+        match pause.current() {
+            Ok(notice) => {
+                let sequence = notice.as_ref().map(|notice| notice.sequence);
+                if sequence != paused {
+                    match &notice {
+                        Some(notice) => warn!(%notice, "Submissions paused, holding the proofs"),
+                        None => info!("Submissions resumed"),
+                    }
+                    paused = sequence;
+                }
+                if notice.is_some() {
+                    continue;
+                }
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to read the pause notice");
+                continue;
+            }
+        }
+        while let Some(job) = queue.next_due()? {
+            let Err(e) = settle(args, circuit, &job, &mut relayer).await else {
+                queue.complete(job.id)?;
+                continue;
+            };
+            if e.downcast_ref::<Rejected>().is_some() {
+                queue.dead_letter(job.id, &e.to_string())?;
+                error!(alert = true, swap_id = %job.swap_id, job = job.id, error = %e, "Mint refused for good, dead-lettered");
+                continue;
+            }
+            match e.downcast_ref::<Revert>() {
+                Some(revert) if revert.already_settled() => {
+                    warn!(swap_id = %job.swap_id, job = job.id, reason = revert.reason, "Proof already settled on the contract");
+                    queue.complete(job.id)?;
+                    continue;
+                }
+                Some(_) => {
+                    queue.dead_letter(job.id, &e.to_string())?;
+                    error!(alert = true, swap_id = %job.swap_id, job = job.id, error = %e, "Settlement reverts, dead-lettered");
+                    continue;
+                }
+                None => {}
+            }
+            match queue.fail(job.id, &e.to_string(), &args.retry)? {
+                Retry::At(at) => {
+                    warn!(swap_id = %job.swap_id, job = job.id, attempt = job.attempts, retry_at = at, error = %e, "Failed to settle the proof, retrying later")
+                }
+                Retry::DeadLettered => {
+                    error!(alert = true, swap_id = %job.swap_id, job = job.id, attempts = job.attempts, error = %e, "Failed to settle the proof, dead-lettered")
+                }
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let bus = args
+        .bus
+        .open(&format!("submit-{:?}", args.circuit).to_lowercase());
+    let result = match (bus, &args.fixture) {
+        (Err(e), _) => Err(format!("Failed to connect to the job bus: {}", e).into()),
+        (Ok(Some(bus)), _) => consume(&args, bus.as_ref()).await,
+        (Ok(None), Some(path)) => {
+            // The swap id is known once the fixture's public values are decoded
+            let span = info_span!("submit", circuit = ?args.circuit, swap_id = Empty);
+            set_parent_from_env(&span);
+            async {
+                let mut relayer = connect(&args).await?;
+                submit(&args, path, &mut relayer, None).await
+            }
+            .instrument(span)
+            .await
+        }
+        (Ok(None), None) => Err("--fixture or --bus is required".into()),
+    };
+    telemetry::flush();
+    if let Err(e) = result {
+        eprintln!("Failed to submit proof: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,173 @@
+//! Checks a proof fixture offline before it is submitted on-chain: the fixture's vkey must be
+//! the circuit's, the Groth16 or PLONK proof must verify against its public values, and the
+//! public values are decoded and printed. Compressed and core SP1 proofs written by
+//! `evm --proof-mode` are checked the same way with the SP1 SDK verifier.
+
+// Usage Example:
+// cargo run --release --bin verify -- --fixture ../contracts/src/fixtures/groth16-fixture_mint.json --circuit mint
+// cargo run --release --bin verify -- --fixture ./plonk-fixture_burn.json --circuit burn --system plonk
+// cargo run --release --bin verify -- --fixture ./compressed-proof_mint.bin --circuit mint --system compressed
+use bitcoin_verify_script::attestation::{fixture_signer, tss_attester};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::{
+    print_burn_aggregation_public_values, print_burn_batch_public_values, print_burn_public_values,
+    print_mint_aggregation_public_values, print_mint_batch_public_values, print_mint_public_values,
+    setup_logging, LogFormat, PrintPublicValues,
+};
+use clap::{Parser, ValueEnum};
+use lib_struct::ZkpProofFixture;
+use sp1_sdk::{include_elf, HashableKey, Prover, ProverClient, SP1ProofWithPublicValues};
+use sp1_verifier::{Groth16Verifier, PlonkVerifier, GROTH16_VK_BYTES, PLONK_VK_BYTES};
+use std::path::PathBuf;
+use tracing::{error, info, info_span};
+
+pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("mint_batch_circuit");
+pub const MINT_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("mint_aggregate_circuit");
+pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
+pub const BURN_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("burn_aggregate_circuit");
+
+/// Enum representing the available proof systems
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum ProofSystem {
+    Plonk,
+    Groth16,
+    Compressed,
+    Core,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum CircuitType {
+    Mint,
+    MintBatch,
+    MintAggregate,
+    Burn,
+    BurnBatch,
+    BurnAggregate,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Fixture JSON written by `evm` or `aggregate`, or with `--system compressed|core` the
+    /// SP1 proof file written by `evm`.
+    #[clap(long)]
+    fixture: PathBuf,
+    #[clap(long, value_enum, env = "BRIDGE_CIRCUIT", default_value = "mint")]
+    circuit: CircuitType,
+    /// Proof system the fixture was generated with.
+    #[clap(long, value_enum, env = "PROOF_SYSTEM", default_value = "groth16")]
+    system: ProofSystem,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Decodes a `0x`-prefixed hex field of the fixture.
+fn decode_hex_field(name: &str, value: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).unwrap_or_else(|e| {
+        error!(field = name, error = %e, "Fixture field is not valid hex");
+        std::process::exit(1);
+    })
+}
+
+fn main() {
+    apply_config_file();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let (elf, decode_output): (&[u8], PrintPublicValues) = match args.circuit {
+        CircuitType::Mint => (MINT_CIRCUIT_ELF, print_mint_public_values),
+        CircuitType::MintBatch => (MINT_BATCH_CIRCUIT_ELF, print_mint_batch_public_values),
+        CircuitType::MintAggregate => (
+            MINT_AGGREGATE_CIRCUIT_ELF,
+            print_mint_aggregation_public_values,
+        ),
+        CircuitType::Burn => (BURN_CIRCUIT_ELF, print_burn_public_values),
+        CircuitType::BurnBatch => (BURN_BATCH_CIRCUIT_ELF, print_burn_batch_public_values),
+        CircuitType::BurnAggregate => (
+            BURN_AGGREGATE_CIRCUIT_ELF,
+            print_burn_aggregation_public_values,
+        ),
+    };
+
+    // A fixture from another circuit or an older build would be rejected by the verifier contract
+    let prover = ProverClient::builder().cpu().build();
+    let (_, vk) = prover.setup(elf);
+    let vkey = vk.bytes32();
+
+    // A STARK carries its public values and is checked against the verifying key itself
+    if matches!(args.system, ProofSystem::Compressed | ProofSystem::Core) {
+        let proof = SP1ProofWithPublicValues::load(&args.fixture).unwrap_or_else(|e| {
+            error!(error = %e, "Failed to load SP1 proof");
+            std::process::exit(1);
+        });
+        let _span = info_span!("verify", circuit = ?args.circuit, system = ?args.system).entered();
+        if let Err(e) = prover.verify(&proof, &vk) {
+            error!(error = %e, "Proof verification failed");
+            std::process::exit(1);
+        }
+        info!(vkey = %vkey, "Successfully verified proof");
+        if let Err(e) = decode_output(proof.public_values.as_slice()) {
+            error!(fixture = %args.fixture.display(), error = %e, "Failed to decode the proof's public values");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let file = std::fs::File::open(&args.fixture).expect("failed to open fixture");
+    let fixture: ZkpProofFixture = serde_json::from_reader(file).expect("failed to parse fixture");
+    if fixture.vkey != vkey {
+        error!(
+            fixture_vkey = %fixture.vkey,
+            circuit = ?args.circuit,
+            circuit_vkey = %vkey,
+            "Fixture vkey does not match the circuit vkey"
+        );
+        std::process::exit(1);
+    }
+    info!(vkey = %vkey, "Fixture vkey matches the circuit");
+    match (&fixture.attestation, fixture_signer(&fixture)) {
+        (None, _) => info!("Fixture is not signed by an operator"),
+        (Some(_), Ok(operator)) => info!(%operator, "Fixture is signed by the operator"),
+        (Some(_), Err(e)) => {
+            error!(error = %e, "Fixture attestation is invalid");
+            std::process::exit(1);
+        }
+    }
+    match (&fixture.tss_attestation, tss_attester(&fixture)) {
+        (None, _) => info!("Fixture is not attested by the TSS signer set"),
+        (Some(_), Ok(group_key)) => info!(%group_key, "Fixture is attested by the TSS signer set"),
+        (Some(_), Err(e)) => {
+            error!(error = %e, "Fixture TSS attestation is invalid");
+            std::process::exit(1);
+        }
+    }
+
+    let public_values = decode_hex_field("public values", &fixture.public_value);
+    let proof = decode_hex_field("proof", &fixture.proof);
+    let _span = info_span!("verify", circuit = ?args.circuit, system = ?args.system).entered();
+    let result = match args.system {
+        ProofSystem::Groth16 => {
+            Groth16Verifier::verify(&proof, &public_values, &vkey, *GROTH16_VK_BYTES)
+                .map_err(|e| e.to_string())
+        }
+        ProofSystem::Plonk => PlonkVerifier::verify(&proof, &public_values, &vkey, *PLONK_VK_BYTES)
+            .map_err(|e| e.to_string()),
+        ProofSystem::Compressed | ProofSystem::Core => unreachable!("verified above"),
+    };
+    if let Err(e) = result {
+        error!(error = %e, "Proof verification failed");
+        std::process::exit(1);
+    }
+    info!("Successfully verified proof");
+
+    if let Err(e) = decode_output(&public_values) {
+        error!(fixture = %args.fixture.display(), error = %e, "Failed to decode the fixture's public values");
+        std::process::exit(1);
+    }
+}
@@ -1,18 +1,75 @@
 // For burn circuit:
 // cargo run --release --bin vkey -- --circuit burn
+// For batch burn circuit:
+// cargo run --release --bin vkey -- --circuit burn-batch
 // For mint circuit:
 // cargo run --release --bin vkey -- --circuit mint
+// For batch mint circuit:
+// cargo run --release --bin vkey -- --circuit mint-batch
+// For mint aggregation circuit:
+// cargo run --release --bin vkey -- --circuit mint-aggregate
+// For burn aggregation circuit:
+// cargo run --release --bin vkey -- --circuit burn-aggregate
+// For every circuit, as JSON and as Solidity constants:
+// cargo run --release --bin vkey -- --json ./vkeys.json --solidity ../contracts/src/VKeys.sol
 
+use bitcoin::hashes::{sha256, Hash};
 use clap::{Parser, ValueEnum};
-use sp1_sdk::{include_elf, HashableKey, Prover, ProverClient};
+use serde::Serialize;
+use sp1_sdk::{include_elf, CpuProver, HashableKey, Prover, ProverClient};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const MINT_CIRCUIT_ELF: &[u8] = include_elf!("mint_circuit");
+pub const MINT_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("mint_batch_circuit");
+pub const MINT_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("mint_aggregate_circuit");
 pub const BURN_CIRCUIT_ELF: &[u8] = include_elf!("burn_circuit");
+pub const BURN_BATCH_CIRCUIT_ELF: &[u8] = include_elf!("burn_batch_circuit");
+pub const BURN_AGGREGATE_CIRCUIT_ELF: &[u8] = include_elf!("burn_aggregate_circuit");
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 enum CircuitType {
     Mint,
+    MintBatch,
+    MintAggregate,
     Burn,
+    BurnBatch,
+    BurnAggregate,
+}
+
+const ALL_CIRCUITS: [CircuitType; 6] = [
+    CircuitType::Mint,
+    CircuitType::MintBatch,
+    CircuitType::MintAggregate,
+    CircuitType::Burn,
+    CircuitType::BurnBatch,
+    CircuitType::BurnAggregate,
+];
+
+impl CircuitType {
+    fn elf(self) -> &'static [u8] {
+        match self {
+            CircuitType::Mint => MINT_CIRCUIT_ELF,
+            CircuitType::MintBatch => MINT_BATCH_CIRCUIT_ELF,
+            CircuitType::MintAggregate => MINT_AGGREGATE_CIRCUIT_ELF,
+            CircuitType::Burn => BURN_CIRCUIT_ELF,
+            CircuitType::BurnBatch => BURN_BATCH_CIRCUIT_ELF,
+            CircuitType::BurnAggregate => BURN_AGGREGATE_CIRCUIT_ELF,
+        }
+    }
+
+    /// Name used as the JSON key, e.g. `mint_batch`.
+    fn name(self) -> &'static str {
+        match self {
+            CircuitType::Mint => "mint",
+            CircuitType::MintBatch => "mint_batch",
+            CircuitType::MintAggregate => "mint_aggregate",
+            CircuitType::Burn => "burn",
+            CircuitType::BurnBatch => "burn_batch",
+            CircuitType::BurnAggregate => "burn_aggregate",
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -20,17 +77,109 @@ enum CircuitType {
 struct Args {
     #[clap(long, value_enum, default_value = "burn")]
     circuit: CircuitType,
+    /// Writes the vkeys and ELF hashes of every circuit, with build metadata, to this file.
+    #[clap(long)]
+    json: Option<PathBuf>,
+    /// Writes the vkeys of every circuit as a Solidity constants library to this file.
+    #[clap(long)]
+    solidity: Option<PathBuf>,
+}
+
+/// Verification key of one circuit and the hash of the ELF it was derived from.
+#[derive(Serialize)]
+struct CircuitKey {
+    vkey: String,
+    elf_sha256: String,
+}
+
+/// Machine-readable vkey report written with `--json`.
+#[derive(Serialize)]
+struct VKeyReport {
+    package_version: String,
+    /// Commit checked out where the report was generated, if it is a git checkout.
+    git_commit: Option<String>,
+    /// Unix time the report was generated.
+    generated_at: u64,
+    circuits: BTreeMap<String, CircuitKey>,
+}
+
+fn circuit_key(prover: &CpuProver, circuit: CircuitType) -> CircuitKey {
+    let (_, vk) = prover.setup(circuit.elf());
+    CircuitKey {
+        vkey: vk.bytes32(),
+        elf_sha256: sha256::Hash::hash(circuit.elf()).to_string(),
+    }
+}
+
+fn git_commit() -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Renders the vkeys as a Solidity library, so deployments take the keys of the same build
+/// as the prover instead of a copied constant.
+fn solidity_constants(report: &VKeyReport) -> String {
+    let mut source = String::from(
+        "// SPDX-License-Identifier: MIT\n\
+         // Generated by `cargo run --release --bin vkey -- --solidity <path>`; do not edit.\n\
+         pragma solidity ^0.8.20;\n\n",
+    );
+    source.push_str("/// @notice SP1 program verification keys of the bridge circuits.\n");
+    if let Some(commit) = &report.git_commit {
+        source.push_str(&format!("/// @dev Generated at commit {}.\n", commit));
+    }
+    source.push_str("library VKeys {\n");
+    for circuit in ALL_CIRCUITS {
+        let key = &report.circuits[circuit.name()];
+        source.push_str(&format!(
+            "    /// ELF sha256: {}\n    bytes32 internal constant {} = {};\n",
+            key.elf_sha256,
+            circuit.name().to_uppercase(),
+            key.vkey
+        ));
+    }
+    source.push_str("}\n");
+    source
 }
 
 fn main() {
     let args = Args::parse();
 
-    let elf = match args.circuit {
-        CircuitType::Mint => MINT_CIRCUIT_ELF,
-        CircuitType::Burn => BURN_CIRCUIT_ELF,
+    let prover = ProverClient::builder().cpu().build();
+    if args.json.is_none() && args.solidity.is_none() {
+        println!("{}", circuit_key(&prover, args.circuit).vkey);
+        return;
+    }
+
+    let report = VKeyReport {
+        package_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: git_commit(),
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        circuits: ALL_CIRCUITS
+            .iter()
+            .map(|circuit| (circuit.name().to_string(), circuit_key(&prover, *circuit)))
+            .collect(),
     };
+    for (name, key) in &report.circuits {
+        println!("{}: {}", name, key.vkey);
+    }
 
-    let prover = ProverClient::builder().cpu().build();
-    let (_, vk) = prover.setup(elf);
-    println!("{}", vk.bytes32());
+    if let Some(path) = &args.json {
+        std::fs::write(path, serde_json::to_string_pretty(&report).unwrap())
+            .expect("failed to write vkey JSON");
+        println!("VKey JSON written to {}", path.display());
+    }
+    if let Some(path) = &args.solidity {
+        std::fs::write(path, solidity_constants(&report)).expect("failed to write VKeys.sol");
+        println!("Solidity constants written to {}", path.display());
+    }
 }
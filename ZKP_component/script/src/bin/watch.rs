@@ -0,0 +1,641 @@
+//! Watches the bridge vault for deposits: polls the Bitcoin backend for new blocks, builds the
+//! bundle of every transaction paying the vault once it has the required confirmations, and
+//! queues its mint proof on the proof service (`serve`), so no deposit needs a manual run.
+//! Deposits are kept in a sled database with their confirmation counts until they are queued.
+//! With `--swap-api`, the per-request deposit addresses the swap API hands out are watched too,
+//! and their deposits are proven for their request id without a memo.
+//! Reorganizations are followed over the latest blocks: the deposits of orphaned blocks are
+//! scanned again, the proof service drops the proofs about them, the swap API rewinds the
+//! swaps built on them, and a reorganization of `--reorg-threshold` blocks or more raises an
+//! alert.
+//! A pause notice (`--pause-notice`) does not stop the watcher: deposits are still recorded
+//! and proven, and only their submission waits. The pause and its lifting are logged.
+//! With `--bus`, every deposit found is published as `deposit-detected`, and a final one is
+//! published as `prove-requested` instead of being posted to the proof service; reorganizations
+//! are still reported to the service over HTTP.
+
+// Usage Example:
+// cargo run --release --bin watch -- --service http://127.0.0.1:3000 --rpc-url http://127.0.0.1:18332 --db ./watch-db
+// cargo run --release --bin watch -- --config ./bridge.toml --source esplora --start-height 2500000
+// cargo run --release --bin watch -- --db ./watch-db --swap-api http://127.0.0.1:3100
+// cargo run --release --bin watch -- --db ./watch-db --reorg-threshold 3 --alert-webhook https://hooks.example.com/bridge
+// cargo run --release --bin watch -- --db ./watch-db --headers-db ./watch-headers-db --checkpoint <height>:<hash>
+// cargo run --release --bin watch -- --db ./watch-db --bus redis://127.0.0.1:6379
+// cargo run --release --bin watch -- --db ./watch-db --list
+use bitcoin::hash_types::Txid;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::{Script, ScriptBuf, Transaction};
+use bitcoin_verify_script::bundle::{build_bundle, BundleRequest, BundleSource, SourceArgs};
+use bitcoin_verify_script::bus::{BusArgs, BusEvent, JobBus};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::deposit_address::{AddressFunding, DepositAddress, DepositAddressBook};
+use bitcoin_verify_script::pause::{PauseArgs, PauseGuard};
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::reorg::{Reorg, ReorgMonitor};
+use bitcoin_verify_script::store::{DepositRecord, DepositStatus, DepositStore, ScanPosition};
+use bitcoin_verify_script::telemetry::trace_headers;
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use bridge_core::{swap_id, Direction};
+use clap::Parser;
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::{address_script_pubkey, DEFAULT_CONFIRMATIONS};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{error, info, info_span, warn};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Address deposits pay; the mint circuit only accepts the vault of `--group-key`, which it
+    /// is by default, encoded for the source's network.
+    #[clap(long, env = "BRIDGE_ADDRESS")]
+    vault_address: Option<String>,
+    /// Group key of the vault and the per-request deposit addresses, named by the bundles.
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Base URL of the proof service mint proofs are queued on.
+    #[clap(
+        long,
+        env = "PROOF_SERVICE_URL",
+        default_value = "http://127.0.0.1:3000"
+    )]
+    service: String,
+    /// Base URL of the swap API (`api`) whose per-request deposit addresses are watched besides
+    /// the vault; the new ones are fetched at every poll.
+    #[clap(long, env = "SWAP_API_URL")]
+    swap_api: Option<String>,
+    /// Confirmations a deposit needs before it is proven, counting its block.
+    #[clap(long, default_value_t = DEFAULT_CONFIRMATIONS)]
+    confirmations: usize,
+    /// Seconds between two polls of the Bitcoin backend.
+    #[clap(long, default_value_t = 30)]
+    poll_interval: u64,
+    /// First block to scan; defaults to the saved scan position, else the current tip.
+    #[clap(long)]
+    start_height: Option<u32>,
+    /// sled database keeping the scan position and every deposit found across restarts, so
+    /// that none is missed or proven twice; without it, the state is lost on exit.
+    #[clap(long, env = "WATCH_DB")]
+    db: Option<PathBuf>,
+    /// Depth from which a reorganization raises an alert: an error log, and a post to
+    /// `--alert-webhook`. Shallower ones are handled the same way, with a warning.
+    #[clap(long, env = "REORG_THRESHOLD", default_value_t = 2)]
+    reorg_threshold: u32,
+    /// Latest blocks followed for reorganizations; a deeper one is reported from the lowest.
+    #[clap(long, default_value_t = 144)]
+    reorg_window: u32,
+    /// URL the alerts are posted to as JSON, e.g. a chat or paging webhook.
+    #[clap(long, env = "ALERT_WEBHOOK_URL")]
+    alert_webhook: Option<String>,
+    /// Print the deposits recorded in `--db` with their confirmations and status, and the
+    /// watched deposit addresses, and exit.
+    #[clap(long, requires = "db")]
+    list: bool,
+    #[clap(flatten)]
+    pause: PauseArgs,
+    #[clap(flatten)]
+    bus: BusArgs,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Deposit address handed out by the swap API.
+#[derive(Deserialize, Debug)]
+struct AllocatedAddress {
+    request_id: u64,
+    address: String,
+}
+
+/// Value `tx` pays to `script`, or `None` when no output pays it.
+fn paid_to(tx: &Transaction, script: &Script) -> Option<u64> {
+    let outputs: Vec<u64> = tx
+        .output
+        .iter()
+        .filter(|output| output.script_pubkey.as_script() == script)
+        .map(|output| output.value.to_sat())
+        .collect();
+    (!outputs.is_empty()).then(|| outputs.iter().sum())
+}
+
+/// Value a transaction deposits, and where.
+struct Payment {
+    amount_sats: u64,
+    /// Request whose deposit address is paid; `None` for the vault.
+    request_id: Option<u64>,
+}
+
+/// What `tx` deposits, or `None` when it pays neither the vault nor a deposit address. A
+/// request's address is preferred over the vault.
+fn deposit_paid(
+    tx: &Transaction,
+    vault_spk: &ScriptBuf,
+    book: &DepositAddressBook,
+) -> Result<Option<Payment>, Box<dyn Error>> {
+    for output in &tx.output {
+        if let Some(request_id) = book.request_for_script(&output.script_pubkey)? {
+            let amount_sats = paid_to(tx, &output.script_pubkey).unwrap_or_default();
+            return Ok(Some(Payment {
+                amount_sats,
+                request_id: Some(request_id),
+            }));
+        }
+    }
+    Ok(paid_to(tx, vault_spk).map(|amount_sats| Payment {
+        amount_sats,
+        request_id: None,
+    }))
+}
+
+/// Fetches the deposit addresses the swap API handed out and tracks the new ones. Each is
+/// derived again from the circuit's group key, so an address the circuit would not accept is
+/// never watched.
+fn refresh_addresses(
+    client: &Client,
+    swap_api: &str,
+    book: &DepositAddressBook,
+    group_key: &XOnlyPublicKey,
+    chain: &ChainParams,
+) -> Result<(), Box<dyn Error>> {
+    let allocated: Vec<AllocatedAddress> = client
+        .get(format!(
+            "{}/deposit-addresses",
+            swap_api.trim_end_matches('/')
+        ))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    for allocated in allocated {
+        let address = DepositAddress::derive(group_key, allocated.request_id, chain)?;
+        if address.address != allocated.address {
+            warn!(
+                request_id = allocated.request_id,
+                address = allocated.address,
+                expected = address.address,
+                "Swap API handed out an address of another group key, ignoring it"
+            );
+            continue;
+        }
+        if book.track(&address)? {
+            info!(
+                request_id = address.request_id,
+                address = address.address,
+                "Watching deposit address"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Builds the bundle of `deposit` and queues its mint proof, on the job bus when there is one,
+/// else on the proof service; returns the status the deposit moves to.
+fn queue_deposit(
+    source: &dyn BundleSource,
+    client: &Client,
+    args: &Args,
+    bus: Option<&dyn JobBus>,
+    book: &DepositAddressBook,
+    deposit: &DepositRecord,
+) -> Result<DepositStatus, Box<dyn Error>> {
+    let request = BundleRequest {
+        txid: Txid::from_str(&deposit.txid)?,
+        block_hash: Some(deposit.block_hash.clone()),
+        network: None,
+        confirmations: args.confirmations,
+        group_key: args.group_key.group_key,
+        burn: None,
+    };
+    let mut bundle = build_bundle(source, &request)?;
+    if let Some(request_id) = deposit.request_id {
+        let address = book
+            .get(request_id)?
+            .ok_or_else(|| format!("No deposit address for request {}", request_id))?;
+        bundle.deposit_request = Some(address.deposit_request());
+    }
+    if let Some(bus) = bus {
+        let event = bus.publish(&BusEvent::ProveRequested {
+            swap_id: swap_id(Direction::Mint, &deposit.txid),
+            circuit: "mint".to_string(),
+            bundle: serde_json::to_value(&bundle)?,
+        })?;
+        return Ok(DepositStatus::Requested { event });
+    }
+    // The proof service's job joins the deposit's trace
+    let mut request = client
+        .post(format!("{}/prove/mint", args.service.trim_end_matches('/')))
+        .json(&bundle);
+    for (name, value) in trace_headers() {
+        request = request.header(name, value);
+    }
+    let response: Value = request.send()?.error_for_status()?.json()?;
+    let job_id = response["job_id"]
+        .as_u64()
+        .ok_or("Proof service answered without a job id")?;
+    Ok(DepositStatus::Queued { job_id })
+}
+
+/// Forgets the deposits of the blocks `reorg` orphaned and rescans from its fork, so each
+/// deposit is found again in the new best chain, or not at all.
+fn rewind(
+    reorg: &Reorg,
+    store: &DepositStore,
+    book: &DepositAddressBook,
+    position: &mut ScanPosition,
+) -> Result<(), Box<dyn Error>> {
+    let dropped = store.drop_from(reorg.fork_height)?;
+    let txids: Vec<String> = dropped.iter().map(|deposit| deposit.txid.clone()).collect();
+    book.forget_funding(&txids)?;
+    let queued = dropped
+        .iter()
+        .filter(|deposit| deposit.status != DepositStatus::Confirming)
+        .count();
+    if reorg.fork_height < position.next_height {
+        position.next_height = reorg.fork_height;
+        position.last_hash = None;
+        store.set_scan_position(position)?;
+    }
+    warn!(
+        fork_height = reorg.fork_height,
+        depth = reorg.depth(),
+        dropped = dropped.len(),
+        queued,
+        "Blocks left the best chain, rescanning from the fork"
+    );
+    Ok(())
+}
+
+/// Reports `reorg`: the proof service cancels the jobs whose bundle chain holds an orphaned
+/// block, the swap API rewinds the swaps of their transactions, and a deep reorganization is
+/// posted to `--alert-webhook`. Fails if any of them could not be told, to be retried.
+fn report_reorg(
+    source: &dyn BundleSource,
+    client: &Client,
+    args: &Args,
+    reorg: &Reorg,
+) -> Result<(), Box<dyn Error>> {
+    let deep = reorg.depth() >= args.reorg_threshold || reorg.beyond_window;
+    if deep {
+        error!(
+            alert = true,
+            fork_height = reorg.fork_height,
+            depth = reorg.depth(),
+            tip = reorg.tip,
+            beyond_window = reorg.beyond_window,
+            orphaned = ?reorg.orphaned_hashes(),
+            "Deep reorganization"
+        );
+        if let Some(webhook) = &args.alert_webhook {
+            client
+                .post(webhook)
+                .json(&json!({ "alert": "bitcoin_reorg", "reorg": reorg }))
+                .send()?
+                .error_for_status()?;
+        }
+    }
+
+    let response: Value = client
+        .post(format!("{}/invalidate", args.service.trim_end_matches('/')))
+        .json(&json!({
+            "block_hashes": reorg.orphaned_hashes(),
+            "reason": format!("reorganization from height {}", reorg.fork_height),
+        }))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    info!(cancelled = %response["cancelled"], "Proof jobs of orphaned blocks cancelled");
+
+    if let Some(swap_api) = &args.swap_api {
+        // Stale blocks stay readable on the node, so their transactions can still be listed
+        let orphaned = reorg
+            .orphaned
+            .iter()
+            .map(|block| {
+                let txids: Vec<String> = source
+                    .block_transactions(&block.hash)?
+                    .iter()
+                    .map(|tx| tx.compute_txid().to_string())
+                    .collect();
+                Ok(json!({ "hash": block.hash, "txids": txids }))
+            })
+            .collect::<Result<Vec<Value>, Box<dyn Error>>>()?;
+        let response: Value = client
+            .post(format!("{}/reorgs", swap_api.trim_end_matches('/')))
+            .json(&json!({ "orphaned": orphaned }))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        info!(
+            rewound = %response["rewound"],
+            settled = %response["settled"],
+            "Swaps of orphaned blocks rewound"
+        );
+    }
+    Ok(())
+}
+
+/// Scans the blocks mined since the last poll and queues every deposit that reached
+/// `--confirmations`. Deposits that fail to queue keep confirming and are retried next poll.
+#[allow(clippy::too_many_arguments)]
+fn poll(
+    source: &dyn BundleSource,
+    client: &Client,
+    args: &Args,
+    bus: Option<&dyn JobBus>,
+    vault_spk: &ScriptBuf,
+    store: &DepositStore,
+    book: &DepositAddressBook,
+    monitor: &ReorgMonitor,
+    position: &mut ScanPosition,
+) -> Result<(), Box<dyn Error>> {
+    // === Follow reorganizations; the deposits of orphaned blocks are found again ===
+    if let Some(reorg) = monitor.observe(source)? {
+        rewind(&reorg, store, book, position)?;
+    }
+    for (id, reorg) in monitor.pending()? {
+        match report_reorg(source, client, args, &reorg) {
+            Ok(()) => monitor.reported(id)?,
+            Err(e) => {
+                warn!(fork_height = reorg.fork_height, error = %e, "Failed to report the reorganization, retrying next poll")
+            }
+        }
+    }
+    let tip = source.tip_height()?;
+
+    // === Scan new blocks for outputs paying the vault or a deposit address ===
+    while position.next_height <= tip {
+        let height = position.next_height;
+        let block_hash = source.block_hash(height)?;
+        for tx in source.block_transactions(&block_hash)? {
+            if let Some(Payment {
+                amount_sats,
+                request_id,
+            }) = deposit_paid(&tx, vault_spk, book)?
+            {
+                let txid = tx.compute_txid().to_string();
+                info!(
+                    %txid,
+                    swap_id = %swap_id(Direction::Mint, &txid),
+                    height,
+                    amount_sats,
+                    ?request_id,
+                    "Deposit found"
+                );
+                if let Some(request_id) = request_id {
+                    book.record_funding(
+                        request_id,
+                        AddressFunding {
+                            txid: txid.clone(),
+                            height,
+                            amount_sats,
+                        },
+                    )?;
+                }
+                let new = store.get(&txid)?.is_none();
+                if let Some(bus) = bus.filter(|_| new) {
+                    let detected = BusEvent::DepositDetected {
+                        swap_id: swap_id(Direction::Mint, &txid),
+                        txid: txid.clone(),
+                        block_hash: block_hash.clone(),
+                        height,
+                        amount_sats,
+                        request_id,
+                    };
+                    if let Err(e) = bus.publish(&detected) {
+                        warn!(%txid, error = %e, "Failed to publish the deposit");
+                    }
+                }
+                store.record_found(&DepositRecord {
+                    txid,
+                    block_hash: block_hash.clone(),
+                    height,
+                    amount_sats,
+                    request_id,
+                    confirmations: tip + 1 - height,
+                    status: DepositStatus::Confirming,
+                })?;
+            }
+        }
+        // Recorded after the block's deposits, so a crash rescans the block at worst
+        position.next_height = height + 1;
+        position.last_hash = Some(block_hash);
+        store.set_scan_position(position)?;
+    }
+
+    // === Queue the deposits that reached the finality threshold ===
+    for mut deposit in store.deposits()? {
+        if deposit.status != DepositStatus::Confirming {
+            continue;
+        }
+        deposit.confirmations = (tip + 1).saturating_sub(deposit.height);
+        if (deposit.confirmations as usize) >= args.confirmations {
+            let _span = info_span!(
+                "queue_deposit",
+                swap_id = %swap_id(Direction::Mint, &deposit.txid),
+                txid = %deposit.txid
+            )
+            .entered();
+            match queue_deposit(source, client, args, bus, book, &deposit) {
+                Ok(status) => {
+                    info!(txid = %deposit.txid, ?status, "Mint proof queued");
+                    deposit.status = status;
+                }
+                Err(e) => {
+                    warn!(txid = %deposit.txid, error = %e, "Failed to queue deposit, retrying next poll")
+                }
+            }
+        }
+        store.put(&deposit)?;
+    }
+    store.flush()
+}
+
+/// Prints the recorded deposits, oldest first, and the watched deposit addresses.
+fn list(store: &DepositStore, book: &DepositAddressBook) -> Result<(), Box<dyn Error>> {
+    for deposit in store.deposits()? {
+        let status = match deposit.status {
+            DepositStatus::Confirming => "confirming".to_string(),
+            DepositStatus::Queued { job_id } => format!("queued as job {}", job_id),
+            DepositStatus::Requested { event } => format!("requested as bus event {}", event),
+        };
+        let request = deposit
+            .request_id
+            .map(|request_id| format!(" for request {}", request_id))
+            .unwrap_or_default();
+        println!(
+            "{} height {}: {} sats{}, {} confirmations, {}",
+            deposit.txid,
+            deposit.height,
+            deposit.amount_sats,
+            request,
+            deposit.confirmations,
+            status
+        );
+    }
+    for address in book.addresses()? {
+        let funding = if address.is_funded() {
+            format!(
+                "funded by {} deposit(s), {} sats",
+                address.funding.len(),
+                address.funded_sats()
+            )
+        } else {
+            "unfunded".to_string()
+        };
+        println!(
+            "request {} at {}: {}",
+            address.request_id, address.address, funding
+        );
+    }
+    if let Some(position) = store.scan_position()? {
+        println!("next height to scan: {}", position.next_height);
+    }
+    Ok(())
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let (store, book, monitor) = DepositStore::open(args.db.as_deref())
+        .and_then(|store| {
+            let book = store.address_book()?;
+            let monitor = store.reorg_monitor(args.reorg_window)?;
+            Ok((store, book, monitor))
+        })
+        .unwrap_or_else(|e| {
+            error!(error = %e, "Failed to open the deposit database");
+            std::process::exit(1);
+        });
+    if args.list {
+        if let Err(e) = list(&store, &book) {
+            error!(error = %e, "Failed to read the deposit database");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let source = args.source.open().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the Bitcoin source");
+        std::process::exit(1);
+    });
+    let client = Client::new();
+    let bus = args.bus.open("watch").unwrap_or_else(|e| {
+        error!(error = %e, "Failed to connect to the job bus");
+        std::process::exit(1);
+    });
+
+    // The vault script depends on the network, so the source must serve the expected one
+    let network = source.network().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to read the source's network");
+        std::process::exit(1);
+    });
+    if let Some(expected) = args.source.network {
+        if network != expected {
+            error!(%network, %expected, "Source serves another network");
+            std::process::exit(1);
+        }
+    }
+    let group_key = args.group_key.group_key;
+    let chain = ChainParams::from(network);
+
+    let vault_address = match &args.vault_address {
+        Some(address) => address.clone(),
+        None => args
+            .group_key
+            .vault_address(&chain)
+            .expect("vault address encodes on every network"),
+    };
+    let vault_spk = address_script_pubkey(&vault_address, network).unwrap_or_else(|e| {
+        error!(address = vault_address, error = %e, "Invalid vault address");
+        std::process::exit(1);
+    });
+
+    let saved = store.scan_position().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to load the scan position");
+        std::process::exit(1);
+    });
+    let mut position = match (args.start_height, saved) {
+        (Some(start_height), _) => ScanPosition {
+            next_height: start_height,
+            last_hash: None,
+        },
+        (None, Some(saved)) => saved,
+        (None, None) => ScanPosition {
+            next_height: source.tip_height().unwrap_or_else(|e| {
+                error!(error = %e, "Failed to read the tip height");
+                std::process::exit(1);
+            }),
+            last_hash: None,
+        },
+    };
+    let confirming = store
+        .deposits()
+        .map(|deposits| {
+            deposits
+                .iter()
+                .filter(|deposit| deposit.status == DepositStatus::Confirming)
+                .count()
+        })
+        .unwrap_or_default();
+    info!(
+        vault = vault_address,
+        %network,
+        from_height = position.next_height,
+        confirming,
+        service = args.service,
+        bus = args.bus.bus,
+        swap_api = args.swap_api,
+        "Watching for deposits"
+    );
+
+    let pause = PauseGuard::new(&args.pause, &args.group_key, "watch");
+    let mut paused = None;
+    loop {
+        match pause.current() {
+            Ok(notice) => {
+                let sequence = notice.as_ref().map(|notice| notice.sequence);
+                if sequence != paused {
+                    match &notice {
+                        Some(notice) => {
+                            warn!(%notice, "Bridge paused, deposits are still recorded")
+                        }
+                        None => info!("Bridge resumed"),
+                    }
+                    paused = sequence;
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to read the pause notice"),
+        }
+        if let Some(swap_api) = &args.swap_api {
+            if let Err(e) = refresh_addresses(&client, swap_api, &book, &group_key, &chain) {
+                warn!(error = %e, "Failed to fetch the deposit addresses, retrying next poll");
+            }
+        }
+        if let Err(e) = poll(
+            source.as_ref(),
+            &client,
+            &args,
+            bus.as_deref(),
+            &vault_spk,
+            &store,
+            &book,
+            &monitor,
+            &mut position,
+        ) {
+            warn!(error = %e, "Poll failed, retrying");
+        }
+        std::thread::sleep(Duration::from_secs(args.poll_interval));
+    }
+}
@@ -0,0 +1,530 @@
+//! Vault watchtower: follows every output of the vault the group key spends payouts from, and
+//! raises an alert whenever a transaction spends one that no approved job of the payout queue
+//! accounts for: an error log, a post to `--alert-webhook`, a PagerDuty incident with
+//! `--pagerduty-routing-key`, and with `--evidence-command` the spend recorded as evidence on
+//! the TSS coordinator. Such a spend means the key signed outside the payout pipeline, so it is
+//! the earliest warning of a compromised key.
+//! Spends are looked for in the mempool at every poll, before they are mined, and in every new
+//! block. Each is checked once and kept in a sled database with its verdict; an alert that
+//! could not be delivered is retried at the next poll. See `bitcoin_verify_script::watchtower`
+//! for what a payout batch may pay.
+
+// Usage Example:
+// cargo run --release --bin watchtower -- --payout-queue http://127.0.0.1:3300 --db ./watchtower-db --alert-webhook https://hooks.example.com/bridge
+// cargo run --release --bin watchtower -- --config ./bridge.toml --pagerduty-routing-key <key> --start-height 2500000
+// cargo run --release --bin watchtower -- --db ./watchtower-db --evidence-command "coordinator --db ./coordinator-db evidence spend"
+// cargo run --release --bin watchtower -- --db ./watchtower-db --list
+use bitcoin::hash_types::Txid;
+use bitcoin::network::Network;
+use bitcoin::{Address, ScriptBuf, Transaction};
+use bitcoin_verify_script::bundle::{BundleSource, SourceArgs};
+use bitcoin_verify_script::config::apply_config_file;
+use bitcoin_verify_script::profile::GroupKeyArgs;
+use bitcoin_verify_script::store::{PayoutJob, RefundJob, ScanPosition};
+use bitcoin_verify_script::watchtower::{authorize, VaultOutput, VaultSpend, WatchtowerStore};
+use bitcoin_verify_script::{setup_logging, LogFormat};
+use clap::Parser;
+use lib_struct::verify::{address_script_pubkey, decode_transaction};
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// PagerDuty Events API v2 endpoint incidents are triggered on.
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Settings file (`bridge.toml`); flags and environment variables override its values.
+    #[clap(long, env = "BRIDGE_CONFIG")]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Vault address watched; defaults to the taproot address of the group key, the one the
+    /// payout pipeline spends from.
+    #[clap(long, env = "VAULT_ADDRESS")]
+    vault_address: Option<String>,
+    #[clap(flatten)]
+    group_key: GroupKeyArgs,
+    /// Base URL of the payout queue (`burn-listener`) spends are checked against.
+    #[clap(
+        long,
+        env = "PAYOUT_QUEUE_URL",
+        default_value = "http://127.0.0.1:3300"
+    )]
+    payout_queue: String,
+    /// URL the alerts are posted to as JSON, e.g. a chat or paging webhook.
+    #[clap(long, env = "ALERT_WEBHOOK_URL")]
+    alert_webhook: Option<String>,
+    /// Routing key of a PagerDuty service; each unauthorized spend triggers a critical
+    /// incident on it, deduplicated by txid.
+    #[clap(long, env = "PAGERDUTY_ROUTING_KEY")]
+    pagerduty_routing_key: Option<String>,
+    /// Command recording an unauthorized spend as evidence, e.g. `coordinator --db
+    /// ./coordinator-db evidence spend`; `--tx-hex <tx> --prevout-sats <input values>
+    /// --prevout-script-hex <input scripts> --reason <reason>` is appended.
+    #[clap(long, env = "WATCHTOWER_EVIDENCE_COMMAND")]
+    evidence_command: Option<String>,
+    /// Seconds between two polls of the Bitcoin backend.
+    #[clap(long, default_value_t = 30)]
+    poll_interval: u64,
+    /// First block to scan; defaults to the saved scan position, else the current tip.
+    #[clap(long)]
+    start_height: Option<u32>,
+    /// sled database keeping the vault outputs, the spends checked and the scan position
+    /// across restarts, so that no spend is missed or alerted twice; without it, the state is
+    /// lost on exit.
+    #[clap(long, env = "WATCHTOWER_DB")]
+    db: Option<PathBuf>,
+    /// Print the vault spends recorded in `--db` with their verdicts, and the unspent vault
+    /// outputs, and exit.
+    #[clap(long, requires = "db")]
+    list: bool,
+    /// Format of the logs written to stderr.
+    #[clap(long, value_enum, env = "LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// Payout and refund jobs of the payout queue.
+type Jobs = (Vec<PayoutJob>, Vec<RefundJob>);
+
+/// What a poll works with.
+struct Watchtower<'a> {
+    args: &'a Args,
+    source: &'a dyn BundleSource,
+    client: Client,
+    store: WatchtowerStore,
+    vault_spk: ScriptBuf,
+    vault_address: Address,
+    network: Network,
+    /// Jobs of the payout queue, fetched once per poll when a spend is to be checked.
+    jobs: Option<Jobs>,
+}
+
+impl Watchtower<'_> {
+    /// Payout and refund jobs of the queue, as of this poll.
+    fn jobs(&mut self) -> Result<&Jobs, Box<dyn Error>> {
+        if self.jobs.is_none() {
+            let queue = self.args.payout_queue.trim_end_matches('/');
+            let payouts: Vec<PayoutJob> = self
+                .client
+                .get(format!("{}/payouts", queue))
+                .send()?
+                .error_for_status()?
+                .json()?;
+            let refunds: Vec<RefundJob> = self
+                .client
+                .get(format!("{}/refunds", queue))
+                .send()?
+                .error_for_status()?
+                .json()?;
+            self.jobs = Some((payouts, refunds));
+        }
+        Ok(self.jobs.as_ref().expect("jobs fetched"))
+    }
+
+    /// Sats each deposit refunded to an address `tx` pays gave the vault, from the outputs
+    /// tracked, else from the chain.
+    fn deposit_values(&self, tx: &Transaction, refunds: &[RefundJob]) -> BTreeMap<String, u64> {
+        let mut values = BTreeMap::new();
+        let paid = |address: &str| {
+            address_script_pubkey(address, self.network).is_ok_and(|script| {
+                tx.output
+                    .iter()
+                    .any(|output| output.script_pubkey == script)
+            })
+        };
+        for job in refunds.iter().filter(|job| paid(&job.btc_address)) {
+            let value = self
+                .store
+                .paid_by(&job.deposit_txid)
+                .and_then(|value| match value {
+                    Some(value) => Ok(value),
+                    None => {
+                        let txid = Txid::from_str(&job.deposit_txid)?;
+                        let tx = decode_transaction(&self.source.raw_transaction(&txid, None)?)?;
+                        Ok(tx
+                            .output
+                            .iter()
+                            .filter(|output| output.script_pubkey == self.vault_spk)
+                            .map(|output| output.value.to_sat())
+                            .sum())
+                    }
+                });
+            match value {
+                Ok(value) => {
+                    values.insert(job.deposit_txid.clone(), value);
+                }
+                Err(e) => {
+                    warn!(deposit = job.deposit_txid, error = %e, "Failed to read what the deposit paid the vault, its refunds are not accepted")
+                }
+            }
+        }
+        values
+    }
+
+    /// Checks `tx` if it spends tracked vault outputs and was not checked yet; `height` is its
+    /// block's, `None` in the mempool. A spend checked in the mempool only gets its height.
+    fn check(&mut self, tx: &Transaction, height: Option<u32>) -> Result<(), Box<dyn Error>> {
+        let txid = tx.compute_txid().to_string();
+        if let Some(mut spend) = self.store.vault_spend(&txid)? {
+            if spend.height.is_none() && height.is_some() {
+                spend.height = height;
+                self.store.put_spend(&spend)?;
+                info!(%txid, ?height, authorized = spend.authorized, "Vault spend mined");
+            }
+            return Ok(());
+        }
+        // An output already spent in the mempool counts too: its spend may have been replaced
+        let mut spends_vault = false;
+        for input in &tx.input {
+            spends_vault |= self.store.output(&input.previous_output)?.is_some();
+        }
+        if !spends_vault {
+            return Ok(());
+        }
+
+        // Judged before the spend is recorded, so a queue outage retries the whole check
+        let (payouts, refunds) = match self.jobs() {
+            Ok(jobs) => jobs.clone(),
+            Err(e) => {
+                error!(alert = true, %txid, ?height, error = %e, "Vault spent, but the payout queue cannot be read to check it");
+                return Err(e);
+            }
+        };
+        let deposit_values = self.deposit_values(tx, &refunds);
+        let verdict = authorize(
+            tx,
+            &self.vault_spk,
+            &payouts,
+            &refunds,
+            &deposit_values,
+            self.network,
+        );
+        let spent = self.store.spend(tx)?;
+        let spend = VaultSpend::new(tx, &spent, verdict, height);
+        self.store.put_spend(&spend)?;
+        if spend.authorized {
+            info!(
+                %txid,
+                ?height,
+                spent_sats = spend.spent_sats,
+                jobs = spend.detail,
+                "Vault spend pays approved jobs"
+            );
+        } else {
+            error!(
+                alert = true,
+                %txid,
+                ?height,
+                in_mempool = height.is_none(),
+                spent_sats = spend.spent_sats,
+                reason = spend.detail,
+                "Unauthorized vault spend"
+            );
+        }
+        Ok(())
+    }
+
+    /// Tracks the outputs of `tx` paying the vault.
+    fn track_outputs(&self, tx: &Transaction, height: u32) -> Result<(), Box<dyn Error>> {
+        let txid = tx.compute_txid().to_string();
+        for (vout, output) in tx.output.iter().enumerate() {
+            if output.script_pubkey == self.vault_spk {
+                self.store.track(&VaultOutput {
+                    txid: txid.clone(),
+                    vout: vout as u32,
+                    value_sats: output.value.to_sat(),
+                    height,
+                    spent_by: None,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Value and script of each output `tx` spends, in input order, for its evidence.
+    fn prevouts(&self, tx: &Transaction) -> Result<(Vec<String>, Vec<String>), Box<dyn Error>> {
+        let mut sats = Vec::new();
+        let mut scripts = Vec::new();
+        for input in &tx.input {
+            let outpoint = input.previous_output;
+            let (value, script) = match self.store.output(&outpoint)? {
+                Some(output) => (output.value_sats, self.vault_spk.clone()),
+                None => {
+                    let parent =
+                        decode_transaction(&self.source.raw_transaction(&outpoint.txid, None)?)?;
+                    let output = parent.output.get(outpoint.vout as usize).ok_or_else(|| {
+                        format!("{} has no output {}", outpoint.txid, outpoint.vout)
+                    })?;
+                    (output.value.to_sat(), output.script_pubkey.clone())
+                }
+            };
+            sats.push(value.to_string());
+            scripts.push(script.to_hex_string());
+        }
+        Ok((sats, scripts))
+    }
+
+    /// Delivers the alert of `spend` everywhere configured; fails if any could not be told, to
+    /// be retried next poll.
+    fn alert(&self, spend: &VaultSpend) -> Result<(), Box<dyn Error>> {
+        if let Some(webhook) = &self.args.alert_webhook {
+            self.client
+                .post(webhook)
+                .json(&json!({ "alert": "unauthorized_vault_spend", "spend": spend }))
+                .send()?
+                .error_for_status()?;
+        }
+        if let Some(routing_key) = &self.args.pagerduty_routing_key {
+            self.client
+                .post(PAGERDUTY_EVENTS_URL)
+                .json(&json!({
+                    "routing_key": routing_key,
+                    "event_action": "trigger",
+                    "dedup_key": spend.txid,
+                    "payload": {
+                        "summary": format!(
+                            "Unauthorized spend of {} vault sats by {}: {}",
+                            spend.spent_sats, spend.txid, spend.detail
+                        ),
+                        "source": "bridge-watchtower",
+                        "severity": "critical",
+                        "custom_details": spend,
+                    },
+                }))
+                .send()?
+                .error_for_status()?;
+        }
+        if let Some(command) = &self.args.evidence_command {
+            let tx = decode_transaction(&spend.tx_hex)?;
+            let (prevout_sats, prevout_scripts) = self.prevouts(&tx)?;
+            let mut words = command.split_whitespace();
+            let program = words.next().ok_or("--evidence-command is empty")?;
+            let output = Command::new(program)
+                .args(words)
+                .arg("--tx-hex")
+                .arg(&spend.tx_hex)
+                .arg("--prevout-sats")
+                .arg(prevout_sats.join(","))
+                .arg("--prevout-script-hex")
+                .arg(prevout_scripts.join(","))
+                .arg("--reason")
+                .arg(&spend.detail)
+                .output()?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Evidence command exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Tracks the vault outputs the backend lists, scans the blocks mined since the last poll,
+    /// then the mempool, for spends of them, and delivers the alerts not delivered yet.
+    fn poll(&mut self, position: &mut ScanPosition) -> Result<(), Box<dyn Error>> {
+        self.jobs = None;
+
+        // === Track the vault's confirmed outputs, including those below the scan start ===
+        for utxo in self.source.address_utxos(&self.vault_address)? {
+            if self.store.track(&VaultOutput {
+                txid: utxo.txid.to_string(),
+                vout: utxo.vout,
+                value_sats: utxo.value_sats,
+                height: utxo.height,
+                spent_by: None,
+            })? {
+                info!(txid = %utxo.txid, vout = utxo.vout, value_sats = utxo.value_sats, "Tracking vault output");
+            }
+        }
+
+        // === Scan new blocks for spends of vault outputs, and new ones ===
+        let tip = self.source.tip_height()?;
+        while position.next_height <= tip {
+            let height = position.next_height;
+            let block_hash = self.source.block_hash(height)?;
+            for tx in self.source.block_transactions(&block_hash)? {
+                self.check(&tx, Some(height))?;
+                self.track_outputs(&tx, height)?;
+            }
+            // Recorded after the block's spends, so a crash rescans the block at worst
+            position.next_height = height + 1;
+            position.last_hash = Some(block_hash);
+            self.store.set_scan_position(position)?;
+        }
+
+        // === Check the mempool for spends of the outputs no mined transaction spends ===
+        let outpoints = self
+            .store
+            .unsettled()?
+            .iter()
+            .map(VaultOutput::outpoint)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (outpoint, txid) in self.source.mempool_spenders(&outpoints)? {
+            if self.store.vault_spend(&txid.to_string())?.is_some() {
+                continue;
+            }
+            let tx = decode_transaction(&self.source.raw_transaction(&txid, None)?)?;
+            info!(%txid, %outpoint, "Vault output spent in the mempool");
+            self.check(&tx, None)?;
+        }
+
+        // === Deliver the alerts not delivered yet ===
+        for mut spend in self.store.unalerted()? {
+            match self.alert(&spend) {
+                Ok(()) => {
+                    spend.alerted = true;
+                    self.store.put_spend(&spend)?;
+                }
+                Err(e) => {
+                    warn!(txid = spend.txid, error = %e, "Failed to deliver the alert, retrying next poll")
+                }
+            }
+        }
+        self.store.flush()
+    }
+}
+
+/// Prints the vault spends checked, oldest first, and the vault outputs unspent.
+fn list(store: &WatchtowerStore) -> Result<(), Box<dyn Error>> {
+    for spend in store.vault_spends()? {
+        let seen = match spend.height {
+            Some(height) if spend.seen_in_mempool => {
+                format!("seen in the mempool, mined at height {}", height)
+            }
+            Some(height) => format!("height {}", height),
+            None => "in the mempool".to_string(),
+        };
+        let verdict = if spend.authorized {
+            format!("pays {}", spend.detail)
+        } else {
+            let alerted = if spend.alerted {
+                "alerted"
+            } else {
+                "alert pending"
+            };
+            format!("UNAUTHORIZED, {} ({})", spend.detail, alerted)
+        };
+        println!(
+            "{} {}: {} vault sats from {} output(s), {}",
+            spend.txid,
+            seen,
+            spend.spent_sats,
+            spend.spent.len(),
+            verdict
+        );
+    }
+    for output in store.unspent()? {
+        println!(
+            "unspent {}:{} height {}: {} sats",
+            output.txid, output.vout, output.height, output.value_sats
+        );
+    }
+    if let Some(position) = store.scan_position()? {
+        println!("next height to scan: {}", position.next_height);
+    }
+    Ok(())
+}
+
+fn main() {
+    apply_config_file();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    setup_logging(args.log_format);
+
+    let store = WatchtowerStore::open(args.db.as_deref()).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the watchtower database");
+        std::process::exit(1);
+    });
+    if args.list {
+        if let Err(e) = list(&store) {
+            error!(error = %e, "Failed to read the watchtower database");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let source = args.source.open().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to open the Bitcoin source");
+        std::process::exit(1);
+    });
+
+    // The vault script depends on the network, so the source must serve the expected one
+    let network = source.network().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to read the source's network");
+        std::process::exit(1);
+    });
+    if let Some(expected) = args.source.network {
+        if network != expected {
+            error!(%network, %expected, "Source serves another network");
+            std::process::exit(1);
+        }
+    }
+    let vault_spk = match &args.vault_address {
+        Some(address) => address_script_pubkey(address, network).unwrap_or_else(|e| {
+            error!(address, error = %e, "Invalid vault address");
+            std::process::exit(1);
+        }),
+        None => args.group_key.vault_script(),
+    };
+    let vault_address = Address::from_script(&vault_spk, network).unwrap_or_else(|e| {
+        error!(error = %e, "Vault script has no address");
+        std::process::exit(1);
+    });
+
+    let saved = store.scan_position().unwrap_or_else(|e| {
+        error!(error = %e, "Failed to load the scan position");
+        std::process::exit(1);
+    });
+    let mut position = match (args.start_height, saved) {
+        (Some(start_height), _) => ScanPosition {
+            next_height: start_height,
+            last_hash: None,
+        },
+        (None, Some(saved)) => saved,
+        (None, None) => ScanPosition {
+            next_height: source.tip_height().unwrap_or_else(|e| {
+                error!(error = %e, "Failed to read the tip height");
+                std::process::exit(1);
+            }),
+            last_hash: None,
+        },
+    };
+    info!(
+        vault = %vault_address,
+        %network,
+        from_height = position.next_height,
+        payout_queue = args.payout_queue,
+        webhook = args.alert_webhook.is_some(),
+        pagerduty = args.pagerduty_routing_key.is_some(),
+        evidence = args.evidence_command.is_some(),
+        "Watching vault spends"
+    );
+
+    let mut watchtower = Watchtower {
+        args: &args,
+        source: source.as_ref(),
+        client: Client::new(),
+        store,
+        vault_spk,
+        vault_address,
+        network,
+        jobs: None,
+    };
+    loop {
+        if let Err(e) = watchtower.poll(&mut position) {
+            warn!(error = %e, "Poll failed, retrying");
+        }
+        std::thread::sleep(Duration::from_secs(args.poll_interval));
+    }
+}
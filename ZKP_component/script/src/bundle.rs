@@ -0,0 +1,829 @@
+//! Fetches circuit input bundles for confirmed Bitcoin transactions from a Bitcoin Core node
+//! over JSON-RPC or from an Esplora HTTP API. Shared by `build-bundle`, which builds one bundle
+//! on demand, and `watch`, which builds them for new vault deposits. With `--headers-db`, the
+//! headers come from the local header chain (`header_chain`) instead. `payout-scheduler` also
+//! lists the vault's outputs and broadcasts its payouts through the same backends.
+
+use crate::header_chain::{Checkpoint, HeaderChain, HeaderChainSource};
+use bitcoin::blockdata::constants::ChainHash;
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::hash_types::{BlockHash, Txid};
+use bitcoin::network::Network;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::{Address, Amount, OutPoint, Transaction};
+use clap::ValueEnum;
+use lib_struct::verify::{
+    build_merkle_proof, decode_transaction, find_vault_input, vault_script_pubkey,
+    verify_tx_inclusion_str, DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{
+    BitcoinTrxInfoStruct, Block, BundleInfoStruct, BurnPayoutPolicyStruct, Chain,
+    CoinbaseProofStruct, MerkleProof, MintPolicyStruct, RetargetInfo,
+};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Blocks between two difficulty retargets.
+const RETARGET_INTERVAL: u32 = 2016;
+
+/// Enum representing the backends a bundle can be fetched from
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SourceType {
+    Rpc,
+    Esplora,
+}
+
+/// Command-line settings of the bundle source, shared by the binaries that fetch bundles.
+#[derive(clap::Args, Debug)]
+pub struct SourceArgs {
+    #[clap(long, value_enum, env = "BITCOIN_SOURCE", default_value = "rpc")]
+    pub source: SourceType,
+    /// Network the bundle is for; the build fails if the source serves another network.
+    #[clap(long, env = "BITCOIN_NETWORK")]
+    pub network: Option<Network>,
+    #[clap(
+        long,
+        env = "BITCOIN_RPC_URL",
+        default_value = "http://127.0.0.1:18332"
+    )]
+    pub rpc_url: String,
+    #[clap(long, env = "BITCOIN_RPC_USER")]
+    pub rpc_user: Option<String>,
+    #[clap(long, env = "BITCOIN_RPC_PASSWORD")]
+    pub rpc_password: Option<String>,
+    /// Base URL of the Esplora API, used with `--source esplora`.
+    #[clap(
+        long,
+        env = "ESPLORA_URL",
+        default_value = "https://blockstream.info/testnet/api"
+    )]
+    pub url: String,
+    /// sled database of the local header chain; headers are then validated and answered from
+    /// it rather than taken from the backend. One process at a time can open it.
+    #[clap(long, env = "HEADERS_DB")]
+    pub headers_db: Option<PathBuf>,
+    /// Block a new header chain starts at, as `<height>:<hash>`; the genesis block if unset.
+    #[clap(long, env = "HEADERS_CHECKPOINT")]
+    pub checkpoint: Option<Checkpoint>,
+}
+
+impl SourceArgs {
+    /// Client of the selected backend.
+    pub fn connect(&self) -> Box<dyn BundleSource> {
+        match self.source {
+            SourceType::Rpc => Box::new(RpcClient {
+                client: Client::new(),
+                url: self.rpc_url.clone(),
+                auth: self
+                    .rpc_user
+                    .clone()
+                    .map(|user| (user, self.rpc_password.clone())),
+            }),
+            SourceType::Esplora => Box::new(EsploraClient {
+                client: Client::new(),
+                url: self.url.clone(),
+            }),
+        }
+    }
+
+    /// Client of the selected backend, behind the header chain of `--headers-db` if set. The
+    /// chain follows `--network`, else the backend's network.
+    pub fn open(&self) -> Result<Box<dyn BundleSource>, Box<dyn Error>> {
+        let source = self.connect();
+        let Some(path) = &self.headers_db else {
+            return Ok(source);
+        };
+        let network = match self.network {
+            Some(network) => network,
+            None => source.network()?,
+        };
+        let chain = HeaderChain::open(path, network, self.checkpoint.as_ref(), source.as_ref())?;
+        Ok(Box::new(HeaderChainSource::new(source, chain)))
+    }
+}
+
+/// A block header with its height and the hash of its successor on the best chain.
+pub struct HeaderInfo {
+    pub block: Block,
+    pub height: u32,
+    pub next_hash: Option<String>,
+}
+
+/// An unspent output of an address, confirmed on the best chain.
+pub struct Utxo {
+    pub txid: Txid,
+    pub vout: u32,
+    pub value_sats: u64,
+    pub height: u32,
+}
+
+/// Backend the transaction, proofs and headers of a bundle are fetched from.
+pub trait BundleSource {
+    /// Raw hex of `txid`; `block_hash` lets a node without `-txindex` find it.
+    fn raw_transaction(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>>;
+    /// Hash of the block that confirmed `txid`.
+    fn transaction_block(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>>;
+    /// Merkle proof of `txid` in `block_hash` and the block's transaction count.
+    fn merkle_proof(
+        &self,
+        txid: &Txid,
+        block_hash: &str,
+    ) -> Result<(MerkleProof, u32), Box<dyn Error>>;
+    /// Txid of the coinbase of `block_hash`.
+    fn coinbase_txid(&self, block_hash: &str) -> Result<Txid, Box<dyn Error>>;
+    /// Header of a block on the best chain.
+    fn block_header(&self, block_hash: &str) -> Result<HeaderInfo, Box<dyn Error>>;
+    /// Hash of the best-chain block at `height`.
+    fn block_hash(&self, height: u32) -> Result<String, Box<dyn Error>>;
+    /// Network the source follows.
+    fn network(&self) -> Result<Network, Box<dyn Error>>;
+    /// Height of the best-chain tip.
+    fn tip_height(&self) -> Result<u32, Box<dyn Error>>;
+    /// Transactions of `block_hash`, in block order.
+    fn block_transactions(&self, block_hash: &str) -> Result<Vec<Transaction>, Box<dyn Error>>;
+    /// Confirmed unspent outputs paying `address`.
+    fn address_utxos(&self, address: &Address) -> Result<Vec<Utxo>, Box<dyn Error>>;
+    /// Sends a signed transaction to the network and returns its txid.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Box<dyn Error>>;
+    /// Unconfirmed transactions spending `outpoints`, as `(outpoint, spending txid)` pairs; an
+    /// outpoint no mempool transaction spends is left out.
+    fn mempool_spenders(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<(OutPoint, Txid)>, Box<dyn Error>>;
+    /// Hash at `height` of a chain the source validated itself, which a bundle's confirmation
+    /// chain is pinned to; `None` when the source only relays a backend.
+    fn checkpoint_hash(&self, _height: u32) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(None)
+    }
+}
+
+/// JSON-RPC response envelope of Bitcoin Core, also used for Ethereum calls.
+#[derive(Deserialize)]
+pub struct RpcResponse<T> {
+    pub result: Option<T>,
+    pub error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// `getrawtransaction` verbose result.
+#[derive(Deserialize)]
+struct RpcTransaction {
+    hex: String,
+    blockhash: Option<String>,
+}
+
+/// `getblockheader` verbose result.
+#[derive(Deserialize)]
+struct RpcBlockHeader {
+    hash: String,
+    /// -1 when the block is not on the best chain.
+    confirmations: i64,
+    height: u32,
+    version: i32,
+    previousblockhash: Option<String>,
+    merkleroot: String,
+    time: u32,
+    bits: String,
+    nonce: u32,
+    nextblockhash: Option<String>,
+}
+
+/// `getblock` result at verbosity 1.
+#[derive(Deserialize)]
+struct RpcBlockTxids {
+    tx: Vec<String>,
+}
+
+/// `scantxoutset start` result.
+#[derive(Deserialize)]
+struct RpcScanResult {
+    success: bool,
+    unspents: Vec<RpcUnspent>,
+}
+
+#[derive(Deserialize)]
+struct RpcUnspent {
+    txid: String,
+    vout: u32,
+    /// In BTC.
+    amount: f64,
+    height: u32,
+}
+
+/// `gettxspendingprevout` entry.
+#[derive(Deserialize)]
+struct RpcSpendingPrevout {
+    txid: String,
+    vout: u32,
+    spendingtxid: Option<String>,
+}
+
+/// `getblockchaininfo` result.
+#[derive(Deserialize)]
+struct RpcBlockchainInfo {
+    chain: String,
+}
+
+/// Minimal Bitcoin Core JSON-RPC client.
+struct RpcClient {
+    client: Client,
+    url: String,
+    auth: Option<(String, Option<String>)>,
+}
+
+impl RpcClient {
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Box<dyn Error>> {
+        let mut request = self.client.post(&self.url).json(&json!({
+            "jsonrpc": "1.0",
+            "id": "build-bundle",
+            "method": method,
+            "params": params,
+        }));
+        if let Some((user, password)) = &self.auth {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        let response = request.send()?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err("RPC authentication failed; check --rpc-user and --rpc-password".into());
+        }
+        let response: RpcResponse<T> = response.json()?;
+        match (response.result, response.error) {
+            (_, Some(error)) => {
+                Err(format!("RPC {} failed ({}): {}", method, error.code, error.message).into())
+            }
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(format!("RPC {} returned no result", method).into()),
+        }
+    }
+
+    fn transaction(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<RpcTransaction, Box<dyn Error>> {
+        match block_hash {
+            Some(block_hash) => self.call(
+                "getrawtransaction",
+                json!([txid.to_string(), true, block_hash]),
+            ),
+            None => self.call("getrawtransaction", json!([txid.to_string(), true])),
+        }
+    }
+
+    fn block_txids(&self, block_hash: &str) -> Result<Vec<Txid>, Box<dyn Error>> {
+        let block: RpcBlockTxids = self.call("getblock", json!([block_hash, 1]))?;
+        Ok(block
+            .tx
+            .iter()
+            .map(|txid| Txid::from_str(txid))
+            .collect::<Result<Vec<Txid>, _>>()?)
+    }
+}
+
+impl BundleSource for RpcClient {
+    fn raw_transaction(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        Ok(self.transaction(txid, block_hash)?.hex)
+    }
+
+    fn transaction_block(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.transaction(txid, block_hash)?
+            .blockhash
+            .ok_or_else(|| format!("Transaction {} is not confirmed", txid).into())
+    }
+
+    fn merkle_proof(
+        &self,
+        txid: &Txid,
+        block_hash: &str,
+    ) -> Result<(MerkleProof, u32), Box<dyn Error>> {
+        let txids = self.block_txids(block_hash)?;
+        let pos = txids
+            .iter()
+            .position(|block_txid| block_txid == txid)
+            .ok_or_else(|| format!("Block {} does not contain {}", block_hash, txid))?;
+        Ok((build_merkle_proof(&txids, pos as u32)?, txids.len() as u32))
+    }
+
+    fn coinbase_txid(&self, block_hash: &str) -> Result<Txid, Box<dyn Error>> {
+        Ok(self.block_txids(block_hash)?[0])
+    }
+
+    fn block_header(&self, block_hash: &str) -> Result<HeaderInfo, Box<dyn Error>> {
+        let header: RpcBlockHeader = self.call("getblockheader", json!([block_hash, true]))?;
+        if header.confirmations < 0 {
+            return Err(format!("Block {} is not on the best chain", block_hash).into());
+        }
+        Ok(HeaderInfo {
+            block: Block {
+                block_hash: header.hash,
+                version: header.version as u32,
+                parent_hash: header
+                    .previousblockhash
+                    .ok_or("The genesis block cannot confirm a transaction")?,
+                merkle_root: header.merkleroot,
+                timestamp: header.time,
+                difficulty: u32::from_str_radix(&header.bits, 16)?,
+                nonce: header.nonce,
+            },
+            height: header.height,
+            next_hash: header.nextblockhash,
+        })
+    }
+
+    fn block_hash(&self, height: u32) -> Result<String, Box<dyn Error>> {
+        self.call("getblockhash", json!([height]))
+    }
+
+    fn network(&self) -> Result<Network, Box<dyn Error>> {
+        let info: RpcBlockchainInfo = self.call("getblockchaininfo", json!([]))?;
+        Ok(Network::from_core_arg(&info.chain)?)
+    }
+
+    fn tip_height(&self) -> Result<u32, Box<dyn Error>> {
+        self.call("getblockcount", json!([]))
+    }
+
+    fn block_transactions(&self, block_hash: &str) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let block_hex: String = self.call("getblock", json!([block_hash, 0]))?;
+        let block: bitcoin::Block = deserialize(&hex::decode(block_hex)?)?;
+        Ok(block.txdata)
+    }
+
+    fn address_utxos(&self, address: &Address) -> Result<Vec<Utxo>, Box<dyn Error>> {
+        // Reads the UTXO set, so the node needs no wallet watching the address
+        let scan: RpcScanResult = self.call(
+            "scantxoutset",
+            json!(["start", [format!("addr({})", address)]]),
+        )?;
+        if !scan.success {
+            return Err(format!("UTXO set scan for {} did not complete", address).into());
+        }
+        scan.unspents
+            .into_iter()
+            .map(|unspent| {
+                Ok(Utxo {
+                    txid: Txid::from_str(&unspent.txid)?,
+                    vout: unspent.vout,
+                    value_sats: Amount::from_btc(unspent.amount)?.to_sat(),
+                    height: unspent.height,
+                })
+            })
+            .collect()
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Box<dyn Error>> {
+        let txid: String = self.call("sendrawtransaction", json!([serialize_hex(tx)]))?;
+        Ok(Txid::from_str(&txid)?)
+    }
+
+    fn mempool_spenders(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<(OutPoint, Txid)>, Box<dyn Error>> {
+        if outpoints.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Needs Bitcoin Core 24 or later
+        let prevouts: Vec<Value> = outpoints
+            .iter()
+            .map(|outpoint| json!({ "txid": outpoint.txid.to_string(), "vout": outpoint.vout }))
+            .collect();
+        let spending: Vec<RpcSpendingPrevout> =
+            self.call("gettxspendingprevout", json!([prevouts]))?;
+        spending
+            .into_iter()
+            .filter_map(|entry| {
+                let spender = entry.spendingtxid?;
+                Some((|| {
+                    Ok((
+                        OutPoint::new(Txid::from_str(&entry.txid)?, entry.vout),
+                        Txid::from_str(&spender)?,
+                    ))
+                })())
+            })
+            .collect()
+    }
+}
+
+/// `GET /tx/:txid/status` result.
+#[derive(Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_hash: Option<String>,
+    block_height: Option<u32>,
+}
+
+/// `GET /address/:address/utxo` entry.
+#[derive(Deserialize)]
+struct EsploraUtxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    status: EsploraTxStatus,
+}
+
+/// `GET /tx/:txid/outspend/:vout` result.
+#[derive(Deserialize)]
+struct EsploraOutspend {
+    spent: bool,
+    txid: Option<String>,
+    status: Option<EsploraTxStatus>,
+}
+
+/// `GET /tx/:txid/merkle-proof` result.
+#[derive(Deserialize)]
+struct EsploraMerkleProof {
+    merkle: Vec<String>,
+    pos: u32,
+}
+
+/// `GET /block/:hash` result.
+#[derive(Deserialize)]
+struct EsploraBlock {
+    id: String,
+    height: u32,
+    version: i32,
+    timestamp: u32,
+    tx_count: u32,
+    merkle_root: String,
+    previousblockhash: Option<String>,
+    nonce: u32,
+    bits: u32,
+}
+
+/// `GET /block/:hash/status` result.
+#[derive(Deserialize)]
+struct EsploraBlockStatus {
+    in_best_chain: bool,
+    next_best: Option<String>,
+}
+
+/// Minimal Esplora HTTP API client, for operators without a full node next to the prover.
+struct EsploraClient {
+    client: Client,
+    url: String,
+}
+
+impl EsploraClient {
+    fn get(&self, path: &str) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+        let url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        Ok(self.client.get(url).send()?.error_for_status()?)
+    }
+
+    fn get_text(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        Ok(self.get(path)?.text()?.trim().to_string())
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Box<dyn Error>> {
+        Ok(self.get(path)?.json()?)
+    }
+}
+
+impl BundleSource for EsploraClient {
+    fn raw_transaction(
+        &self,
+        txid: &Txid,
+        _block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.get_text(&format!("/tx/{}/hex", txid))
+    }
+
+    fn transaction_block(
+        &self,
+        txid: &Txid,
+        _block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        let status: EsploraTxStatus = self.get_json(&format!("/tx/{}/status", txid))?;
+        match status.block_hash {
+            Some(block_hash) if status.confirmed => Ok(block_hash),
+            _ => Err(format!("Transaction {} is not confirmed", txid).into()),
+        }
+    }
+
+    fn merkle_proof(
+        &self,
+        txid: &Txid,
+        block_hash: &str,
+    ) -> Result<(MerkleProof, u32), Box<dyn Error>> {
+        let proof: EsploraMerkleProof = self.get_json(&format!("/tx/{}/merkle-proof", txid))?;
+        let block: EsploraBlock = self.get_json(&format!("/block/{}", block_hash))?;
+        Ok((
+            MerkleProof {
+                siblings: proof.merkle,
+                pos: proof.pos,
+            },
+            block.tx_count,
+        ))
+    }
+
+    fn coinbase_txid(&self, block_hash: &str) -> Result<Txid, Box<dyn Error>> {
+        Ok(Txid::from_str(
+            &self.get_text(&format!("/block/{}/txid/0", block_hash))?,
+        )?)
+    }
+
+    fn block_header(&self, block_hash: &str) -> Result<HeaderInfo, Box<dyn Error>> {
+        let block: EsploraBlock = self.get_json(&format!("/block/{}", block_hash))?;
+        let status: EsploraBlockStatus = self.get_json(&format!("/block/{}/status", block_hash))?;
+        if !status.in_best_chain {
+            return Err(format!("Block {} is not on the best chain", block_hash).into());
+        }
+        Ok(HeaderInfo {
+            block: Block {
+                block_hash: block.id,
+                version: block.version as u32,
+                parent_hash: block
+                    .previousblockhash
+                    .ok_or("The genesis block cannot confirm a transaction")?,
+                merkle_root: block.merkle_root,
+                timestamp: block.timestamp,
+                difficulty: block.bits,
+                nonce: block.nonce,
+            },
+            height: block.height,
+            next_hash: status.next_best,
+        })
+    }
+
+    fn block_hash(&self, height: u32) -> Result<String, Box<dyn Error>> {
+        self.get_text(&format!("/block-height/{}", height))
+    }
+
+    fn network(&self) -> Result<Network, Box<dyn Error>> {
+        // Esplora does not name its chain, so it is identified by the genesis block
+        let genesis_hash = BlockHash::from_str(&self.block_hash(0)?)?;
+        Network::from_chain_hash(ChainHash::from_genesis_block_hash(genesis_hash))
+            .ok_or_else(|| format!("Unknown genesis block {}", genesis_hash).into())
+    }
+
+    fn tip_height(&self) -> Result<u32, Box<dyn Error>> {
+        Ok(self.get_text("/blocks/tip/height")?.parse()?)
+    }
+
+    fn block_transactions(&self, block_hash: &str) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let raw_block = self.get(&format!("/block/{}/raw", block_hash))?.bytes()?;
+        let block: bitcoin::Block = deserialize(&raw_block)?;
+        Ok(block.txdata)
+    }
+
+    fn address_utxos(&self, address: &Address) -> Result<Vec<Utxo>, Box<dyn Error>> {
+        let utxos: Vec<EsploraUtxo> = self.get_json(&format!("/address/{}/utxo", address))?;
+        utxos
+            .into_iter()
+            .filter_map(|utxo| match utxo.status.block_height {
+                Some(height) if utxo.status.confirmed => Some((utxo, height)),
+                _ => None,
+            })
+            .map(|(utxo, height)| {
+                Ok(Utxo {
+                    txid: Txid::from_str(&utxo.txid)?,
+                    vout: utxo.vout,
+                    value_sats: utxo.value,
+                    height,
+                })
+            })
+            .collect()
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Box<dyn Error>> {
+        let url = format!("{}/tx", self.url.trim_end_matches('/'));
+        let response = self.client.post(url).body(serialize_hex(tx)).send()?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(format!("Broadcast refused ({}): {}", status, response.text()?).into());
+        }
+        Ok(Txid::from_str(response.text()?.trim())?)
+    }
+
+    fn mempool_spenders(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<(OutPoint, Txid)>, Box<dyn Error>> {
+        let mut spenders = Vec::new();
+        for outpoint in outpoints {
+            let outspend: EsploraOutspend =
+                self.get_json(&format!("/tx/{}/outspend/{}", outpoint.txid, outpoint.vout))?;
+            let confirmed = outspend.status.is_some_and(|status| status.confirmed);
+            if let Some(txid) = outspend.txid.filter(|_| outspend.spent && !confirmed) {
+                spenders.push((*outpoint, Txid::from_str(&txid)?));
+            }
+        }
+        Ok(spenders)
+    }
+}
+
+/// Recipient and amount a burn payout owes.
+pub struct BurnTarget {
+    pub burner_btc_address: String,
+    pub burn_policy: BurnPayoutPolicyStruct,
+    /// Burn request id on the contract.
+    pub burn_id: Option<u64>,
+}
+
+/// Transaction a bundle is built for, and how.
+pub struct BundleRequest {
+    pub txid: Txid,
+    /// Block containing the transaction, for a node without `-txindex`.
+    pub block_hash: Option<String>,
+    /// Network the source must serve; not checked when unset.
+    pub network: Option<Network>,
+    /// Headers to put in `chains`, counting the transaction's block.
+    pub confirmations: usize,
+    /// TSS group key of the vault, written as the bundle's `vault_public_key`: mint deposits
+    /// are checked against the scripts derived from it, and burn payouts must spend its vault.
+    pub group_key: XOnlyPublicKey,
+    /// Builds a burn bundle for this payout instead of a mint bundle.
+    pub burn: Option<BurnTarget>,
+}
+
+/// Fetches the raw transactions spent by `tx_hex`'s inputs.
+fn fetch_spent_prevouts(
+    source: &dyn BundleSource,
+    tx_hex: &str,
+) -> Result<Vec<BitcoinTrxInfoStruct>, Box<dyn Error>> {
+    let tx = decode_transaction(tx_hex)?;
+    let mut prev_txids: Vec<Txid> = Vec::new();
+    for input in &tx.input {
+        if !prev_txids.contains(&input.previous_output.txid) {
+            prev_txids.push(input.previous_output.txid);
+        }
+    }
+    prev_txids
+        .iter()
+        .map(|prev_txid| {
+            source
+                .raw_transaction(prev_txid, None)
+                .map(|raw_tx_hex| BitcoinTrxInfoStruct { raw_tx_hex })
+        })
+        .collect()
+}
+
+/// Fetches everything the mint or burn circuit needs about `request.txid`, checking the
+/// source's Merkle proof against the block header.
+pub fn build_bundle(
+    source: &dyn BundleSource,
+    request: &BundleRequest,
+) -> Result<BundleInfoStruct, Box<dyn Error>> {
+    // A node or Esplora instance of another network would yield a bundle the circuit rejects
+    if let Some(expected) = request.network {
+        let served = source.network()?;
+        if served != expected {
+            return Err(format!("Source serves {}, expected {}", served, expected).into());
+        }
+    }
+
+    // === Locate the transaction and its block ===
+    let tx_hex = source.raw_transaction(&request.txid, request.block_hash.as_deref())?;
+    let block_hash = source.transaction_block(&request.txid, request.block_hash.as_deref())?;
+    let (merkle_proof, tx_count) = source.merkle_proof(&request.txid, &block_hash)?;
+    info!(
+        txid = %request.txid,
+        pos = merkle_proof.pos,
+        tx_count,
+        block_hash,
+        "Transaction located"
+    );
+
+    // === Collect the confirmation headers ===
+    let mut headers = vec![source.block_header(&block_hash)?];
+    while headers.len() < request.confirmations {
+        let next_hash = headers[headers.len() - 1]
+            .next_hash
+            .clone()
+            .ok_or_else(|| {
+                format!(
+                    "Transaction has {} confirmations, {} required",
+                    headers.len(),
+                    request.confirmations
+                )
+            })?;
+        headers.push(source.block_header(&next_hash)?);
+    }
+    let start_height = headers[0].height;
+    let blocks: Vec<Block> = headers.into_iter().map(|header| header.block).collect();
+
+    // A proof from the source is only trusted once it leads to the header's Merkle root
+    if !verify_tx_inclusion_str(
+        request.txid.to_string().as_str(),
+        &merkle_proof,
+        &blocks[0].merkle_root,
+    )? {
+        return Err("Merkle proof from the source does not match the block's Merkle root".into());
+    }
+
+    // A window crossing a retarget boundary needs the previous epoch's first timestamp
+    let boundary = (start_height + 1..start_height + blocks.len() as u32)
+        .find(|height| height % RETARGET_INTERVAL == 0);
+    let retarget_info = match boundary {
+        Some(boundary) => {
+            let epoch_start_hash = source.block_hash(boundary - RETARGET_INTERVAL)?;
+            Some(RetargetInfo {
+                start_height,
+                epoch_start_timestamp: source.block_header(&epoch_start_hash)?.block.timestamp,
+            })
+        }
+        None => None,
+    };
+
+    // === Inputs needed by the mint or burn circuit ===
+    let burn = request.burn.as_ref();
+    let (prevout_tx_info, spent_prevouts, coinbase_proof) = match burn {
+        None => {
+            // The refund address is optional, so a node without -txindex still works
+            let first_prev_txid = decode_transaction(&tx_hex)?.input[0].previous_output.txid;
+            let prevout_tx_info = match source.raw_transaction(&first_prev_txid, None) {
+                Ok(raw_tx_hex) => Some(BitcoinTrxInfoStruct { raw_tx_hex }),
+                Err(e) => {
+                    warn!(error = %e, "Skipping prevout_tx_info");
+                    None
+                }
+            };
+            (prevout_tx_info, None, None)
+        }
+        Some(_) => {
+            let spent_prevouts = fetch_spent_prevouts(source, &tx_hex)?;
+
+            // A payout that spends no vault output would only prove a rejection
+            let prev_txs = spent_prevouts
+                .iter()
+                .map(|prev_tx_info| decode_transaction(&prev_tx_info.raw_tx_hex))
+                .collect::<Result<Vec<Transaction>, _>>()?;
+            let vault_spk = vault_script_pubkey(&request.group_key);
+            let index = find_vault_input(&decode_transaction(&tx_hex)?, &prev_txs, &vault_spk)?
+                .ok_or_else(|| {
+                    format!(
+                        "Payout {} spends no output of the vault of group key {}",
+                        request.txid, request.group_key
+                    )
+                })?;
+            info!(input = index, vault = %request.group_key, "Payout spends from the vault");
+
+            let coinbase_txid = source.coinbase_txid(&block_hash)?;
+            let (coinbase_merkle_proof, _) = source.merkle_proof(&coinbase_txid, &block_hash)?;
+            let coinbase_proof = CoinbaseProofStruct {
+                coinbase_tx_info: BitcoinTrxInfoStruct {
+                    raw_tx_hex: source.raw_transaction(&coinbase_txid, Some(&block_hash))?,
+                },
+                coinbase_merkle_proof,
+            };
+            (None, Some(spent_prevouts), Some(coinbase_proof))
+        }
+    };
+
+    Ok(BundleInfoStruct {
+        merkle_proof,
+        chains: Chain { blocks },
+        bit_tx_info: BitcoinTrxInfoStruct { raw_tx_hex: tx_hex },
+        burner_btc_address: burn.map(|burn| burn.burner_btc_address.clone()),
+        burn_id: burn.and_then(|burn| burn.burn_id),
+        burn_policy: burn
+            .map(|burn| burn.burn_policy.clone())
+            .unwrap_or_default(),
+        // Both circuits verify the chain on the network the bundle names
+        network: Some(source.network()?.to_string()),
+        confirmations: (request.confirmations != DEFAULT_CONFIRMATIONS)
+            .then_some(request.confirmations as u32),
+        competing_chain: None,
+        retarget_info,
+        mint_policy: MintPolicyStruct::default(),
+        checkpoint_hash: source.checkpoint_hash(start_height - 1)?,
+        reference_time: None,
+        tx_count: Some(tx_count),
+        block_height: Some(start_height),
+        prevout_tx_info,
+        spent_prevouts,
+        vault_public_key: Some(request.group_key.to_string()),
+        witness_proof: None,
+        coinbase_proof,
+        deposit_request: None,
+    })
+}
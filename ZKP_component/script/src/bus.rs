@@ -0,0 +1,515 @@
+//! Job bus: the events the services hand each other, so the watcher, the proof service, the
+//! submitter and the payout side can run as separate deployments, and several replicas of one
+//! of them share its work.
+//!
+//! - `watch` publishes `deposit-detected` for every deposit it finds and `prove-requested`
+//!   once a deposit is final, instead of posting the bundle to `serve`.
+//! - `serve` proves the `prove-requested` bundles like the ones posted to it, and publishes
+//!   `proof-ready` with the fixture of every proof it finishes.
+//! - `submit --bus` settles the `proof-ready` fixtures of its circuit.
+//! - `burn-listener` publishes `payout-requested` for every payout job that becomes ready, and
+//!   `payout-scheduler` starts a round on it instead of waiting out its poll interval.
+//!
+//! A bus is chosen by URL: `memory://` keeps the events in the process, `redis://` puts them
+//! on Redis streams and `nats://` on a NATS JetStream stream. On Redis and NATS, delivery is at
+//! least once: an event a consumer did not acknowledge is delivered again, on Redis to the same
+//! consumer name after a restart, so every consumer must tolerate seeing an event twice.
+
+use crate::store::PayoutJob;
+use bridge_core::{swap_id, Direction};
+use clap::Args;
+use lib_struct::ZkpProofFixture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Prefix of the Redis stream keys and NATS subjects, and name of the JetStream stream.
+pub const BUS_PREFIX: &str = "bridge";
+
+/// How long a consumer waits for an event before it looks at anything else.
+pub const BUS_WAIT: Duration = Duration::from_secs(5);
+
+/// Time a NATS consumer has to acknowledge an event before it is delivered again; a settlement
+/// waits for its confirmations before it is acknowledged.
+const NATS_ACK_WAIT: Duration = Duration::from_secs(3600);
+
+/// Kind of event, each on its own stream or subject.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Topic {
+    DepositDetected,
+    ProveRequested,
+    ProofReady,
+    PayoutRequested,
+}
+
+impl Topic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Topic::DepositDetected => "deposit-detected",
+            Topic::ProveRequested => "prove-requested",
+            Topic::ProofReady => "proof-ready",
+            Topic::PayoutRequested => "payout-requested",
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Event passed between the services. Each carries the swap id it is about, as the logs do.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum BusEvent {
+    /// A transaction paying the vault or a deposit address was found; it is proven once final.
+    #[serde(rename_all = "camelCase")]
+    DepositDetected {
+        swap_id: String,
+        txid: String,
+        block_hash: String,
+        height: u32,
+        amount_sats: u64,
+        /// Request whose deposit address is paid; `None` for the vault.
+        request_id: Option<u64>,
+    },
+    /// A bundle to prove, as posted to `serve`'s `/prove/{circuit}`.
+    #[serde(rename_all = "camelCase")]
+    ProveRequested {
+        swap_id: String,
+        /// `mint`, `burn` or `burn-batch`.
+        circuit: String,
+        bundle: Value,
+    },
+    /// A proof was generated; the fixture is the one `GET /jobs/{id}` returns.
+    #[serde(rename_all = "camelCase")]
+    ProofReady {
+        swap_id: String,
+        circuit: String,
+        /// Job id of the proof on the service that generated it.
+        job_id: u64,
+        fixture: ZkpProofFixture,
+    },
+    /// A burn's payout job became ready in `burn-listener`'s queue.
+    #[serde(rename_all = "camelCase")]
+    PayoutRequested {
+        swap_id: String,
+        burn_id: u64,
+        amount_sats: u64,
+        btc_address: String,
+    },
+}
+
+impl BusEvent {
+    pub fn topic(&self) -> Topic {
+        match self {
+            BusEvent::DepositDetected { .. } => Topic::DepositDetected,
+            BusEvent::ProveRequested { .. } => Topic::ProveRequested,
+            BusEvent::ProofReady { .. } => Topic::ProofReady,
+            BusEvent::PayoutRequested { .. } => Topic::PayoutRequested,
+        }
+    }
+
+    pub fn swap_id(&self) -> &str {
+        match self {
+            BusEvent::DepositDetected { swap_id, .. }
+            | BusEvent::ProveRequested { swap_id, .. }
+            | BusEvent::ProofReady { swap_id, .. }
+            | BusEvent::PayoutRequested { swap_id, .. } => swap_id,
+        }
+    }
+
+    /// `payout-requested` event of a ready payout job.
+    pub fn payout_requested(job: &PayoutJob) -> Self {
+        BusEvent::PayoutRequested {
+            swap_id: swap_id(Direction::Burn, job.burn_id),
+            burn_id: job.burn_id,
+            amount_sats: job.amount_sats,
+            btc_address: job.btc_address.clone(),
+        }
+    }
+}
+
+/// An event handed to a consumer, to acknowledge once it is handled.
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    /// Id the bus gave the event when it was published.
+    pub id: String,
+    pub topic: Topic,
+    pub event: BusEvent,
+}
+
+/// Events exchanged between the services. Consumers of one group share the events of a
+/// topic, each event going to one of them.
+pub trait JobBus: Send + Sync {
+    /// Publishes `event` on its topic and returns the id the bus gave it.
+    fn publish(&self, event: &BusEvent) -> Result<String, Box<dyn Error>>;
+
+    /// Waits up to `timeout` for the next event of `topic`, starting with the events this
+    /// consumer was handed before and did not acknowledge.
+    fn next(&self, topic: Topic, timeout: Duration) -> Result<Option<Delivery>, Box<dyn Error>>;
+
+    /// Marks `delivery` handled, so it is not delivered again.
+    fn ack(&self, delivery: &Delivery) -> Result<(), Box<dyn Error>>;
+}
+
+/// Which job bus a service uses.
+#[derive(Args, Debug)]
+pub struct BusArgs {
+    /// Job bus the service exchanges events on: `memory://`, `redis://<host>:6379` for Redis
+    /// streams or `nats://<host>:4222` for NATS JetStream. The HTTP endpoints are used without it.
+    #[clap(long, env = "JOB_BUS_URL")]
+    pub bus: Option<String>,
+    /// Consumer group the service reads its events in; the replicas of a service share one, so
+    /// each event is handled once. Defaults to the binary's name.
+    #[clap(long, env = "JOB_BUS_GROUP")]
+    pub bus_group: Option<String>,
+    /// Name of this replica in its group; a replica restarted under the same name is handed
+    /// again the events it did not acknowledge. Defaults to the group.
+    #[clap(long, env = "JOB_BUS_CONSUMER")]
+    pub bus_consumer: Option<String>,
+}
+
+impl BusArgs {
+    /// Connects to the bus, if one is given; `binary` names the default consumer group.
+    pub fn open(&self, binary: &str) -> Result<Option<Arc<dyn JobBus>>, Box<dyn Error>> {
+        let Some(url) = &self.bus else {
+            return Ok(None);
+        };
+        let group = self.bus_group.clone().unwrap_or_else(|| binary.to_string());
+        let consumer = self.bus_consumer.clone().unwrap_or_else(|| group.clone());
+        open_bus(url, &group, &consumer).map(Some)
+    }
+}
+
+/// Connects to the bus at `url` as `consumer` of `group`.
+pub fn open_bus(url: &str, group: &str, consumer: &str) -> Result<Arc<dyn JobBus>, Box<dyn Error>> {
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+    match scheme {
+        Some("memory") => Ok(InProcessBus::shared()),
+        Some("redis" | "rediss") => Ok(Arc::new(RedisBus::connect(url, group, consumer)?)),
+        Some("nats") => Ok(Arc::new(NatsBus::connect(url, group)?)),
+        _ => Err(format!(
+            "Unsupported job bus {}; expected memory://, redis:// or nats://",
+            url
+        )
+        .into()),
+    }
+}
+
+/// Bus of the services running in one process, and of a try-out without a broker. Each event
+/// is handed out once, and lost with the process.
+#[derive(Default)]
+pub struct InProcessBus {
+    state: Mutex<InProcessState>,
+    published: Condvar,
+}
+
+#[derive(Default)]
+struct InProcessState {
+    next_id: u64,
+    queues: HashMap<Topic, VecDeque<(String, BusEvent)>>,
+}
+
+impl InProcessBus {
+    /// The bus every `memory://` URL of the process opens.
+    pub fn shared() -> Arc<dyn JobBus> {
+        static SHARED: OnceLock<Arc<InProcessBus>> = OnceLock::new();
+        SHARED.get_or_init(Default::default).clone()
+    }
+}
+
+impl JobBus for InProcessBus {
+    fn publish(&self, event: &BusEvent) -> Result<String, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id.to_string();
+        state
+            .queues
+            .entry(event.topic())
+            .or_default()
+            .push_back((id.clone(), event.clone()));
+        self.published.notify_all();
+        Ok(id)
+    }
+
+    fn next(&self, topic: Topic, timeout: Duration) -> Result<Option<Delivery>, Box<dyn Error>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some((id, event)) = state.queues.entry(topic).or_default().pop_front() {
+                return Ok(Some(Delivery { id, topic, event }));
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(None);
+            }
+            state = self.published.wait_timeout(state, left).unwrap().0;
+        }
+    }
+
+    fn ack(&self, _delivery: &Delivery) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Events on Redis streams, `bridge:<topic>`, read with `XREADGROUP` in a consumer group.
+pub struct RedisBus {
+    group: String,
+    consumer: String,
+    /// Publishing is not held up by a read blocked on its own connection.
+    writer: Mutex<redis::Connection>,
+    reader: Mutex<redis::Connection>,
+    /// Topics whose group exists and whose entries left pending by an earlier run were read.
+    recovered: Mutex<HashSet<Topic>>,
+}
+
+impl RedisBus {
+    pub fn connect(url: &str, group: &str, consumer: &str) -> Result<Self, Box<dyn Error>> {
+        let client = redis::Client::open(url)?;
+        Ok(RedisBus {
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            writer: Mutex::new(client.get_connection()?),
+            reader: Mutex::new(client.get_connection()?),
+            recovered: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn key(topic: Topic) -> String {
+        format!("{}:{}", BUS_PREFIX, topic)
+    }
+
+    /// Reads one entry of `topic` past `start`: `0` for the entries pending for this
+    /// consumer, `>` for new ones.
+    fn read(
+        &self,
+        connection: &mut redis::Connection,
+        topic: Topic,
+        start: &str,
+        block: Option<Duration>,
+    ) -> Result<Option<Delivery>, Box<dyn Error>> {
+        use redis::streams::{StreamReadOptions, StreamReadReply};
+        use redis::Commands;
+
+        let mut options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(1);
+        if let Some(block) = block {
+            options = options.block(block.as_millis().max(1) as usize);
+        }
+        let reply: Option<StreamReadReply> =
+            connection.xread_options(&[Self::key(topic)], &[start], &options)?;
+        let Some(entry) = reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .next()
+        else {
+            return Ok(None);
+        };
+        let json: String = entry
+            .get("event")
+            .ok_or_else(|| format!("Bus entry {} of {} has no event", entry.id, topic))?;
+        let event = serde_json::from_str(&json)
+            .map_err(|e| format!("Bus entry {} of {} is corrupt: {}", entry.id, topic, e))?;
+        Ok(Some(Delivery {
+            id: entry.id,
+            topic,
+            event,
+        }))
+    }
+}
+
+impl JobBus for RedisBus {
+    fn publish(&self, event: &BusEvent) -> Result<String, Box<dyn Error>> {
+        use redis::Commands;
+
+        let json = serde_json::to_string(event)?;
+        let id: String =
+            self.writer
+                .lock()
+                .unwrap()
+                .xadd(Self::key(event.topic()), "*", &[("event", json)])?;
+        Ok(id)
+    }
+
+    fn next(&self, topic: Topic, timeout: Duration) -> Result<Option<Delivery>, Box<dyn Error>> {
+        let mut connection = self.reader.lock().unwrap();
+        if !self.recovered.lock().unwrap().contains(&topic) {
+            let created: redis::RedisResult<()> = redis::cmd("XGROUP")
+                .arg("CREATE")
+                .arg(Self::key(topic))
+                .arg(&self.group)
+                .arg("0")
+                .arg("MKSTREAM")
+                .query(&mut *connection);
+            match created {
+                Err(e) if e.code() != Some("BUSYGROUP") => return Err(e.into()),
+                _ => {}
+            }
+            if let Some(delivery) = self.read(&mut connection, topic, "0", None)? {
+                return Ok(Some(delivery));
+            }
+            self.recovered.lock().unwrap().insert(topic);
+        }
+        self.read(&mut connection, topic, ">", Some(timeout))
+    }
+
+    fn ack(&self, delivery: &Delivery) -> Result<(), Box<dyn Error>> {
+        use redis::Commands;
+
+        let _: u64 = self.writer.lock().unwrap().xack(
+            Self::key(delivery.topic),
+            &self.group,
+            &[&delivery.id],
+        )?;
+        Ok(())
+    }
+}
+
+/// Events on the NATS JetStream stream `bridge`, subjects `bridge.<topic>`, read by one
+/// durable pull consumer per group and topic.
+pub struct NatsBus {
+    /// Drives the client's connection between calls; shut down in the background on drop, so a
+    /// bus dropped inside an async service does not block it.
+    runtime: Option<tokio::runtime::Runtime>,
+    context: async_nats::jetstream::Context,
+    stream: async_nats::jetstream::stream::Stream,
+    group: String,
+    consumers: Mutex<HashMap<Topic, async_nats::jetstream::consumer::PullConsumer>>,
+    /// Acknowledgers of the events handed out, by id.
+    unacked: Mutex<HashMap<String, async_nats::jetstream::message::Acker>>,
+}
+
+impl NatsBus {
+    pub fn connect(url: &str, group: &str) -> Result<Self, Box<dyn Error>> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let (context, stream) = runtime.block_on(async {
+            let client = async_nats::connect(url).await?;
+            let context = async_nats::jetstream::new(client);
+            let stream = context
+                .get_or_create_stream(async_nats::jetstream::stream::Config {
+                    name: BUS_PREFIX.to_string(),
+                    subjects: vec![format!("{}.>", BUS_PREFIX)],
+                    ..Default::default()
+                })
+                .await?;
+            Ok::<_, Box<dyn Error>>((context, stream))
+        })?;
+        Ok(NatsBus {
+            runtime: Some(runtime),
+            context,
+            stream,
+            group: group.to_string(),
+            consumers: Mutex::new(HashMap::new()),
+            unacked: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn runtime(&self) -> &tokio::runtime::Runtime {
+        self.runtime
+            .as_ref()
+            .expect("runtime lives as long as the bus")
+    }
+
+    fn subject(topic: Topic) -> String {
+        format!("{}.{}", BUS_PREFIX, topic)
+    }
+
+    fn consumer(
+        &self,
+        topic: Topic,
+    ) -> Result<async_nats::jetstream::consumer::PullConsumer, Box<dyn Error>> {
+        if let Some(consumer) = self.consumers.lock().unwrap().get(&topic) {
+            return Ok(consumer.clone());
+        }
+        // Consumer names cannot hold dots
+        let name = format!("{}-{}", self.group, topic).replace('.', "-");
+        let consumer = self.runtime().block_on(self.stream.get_or_create_consumer(
+            &name,
+            async_nats::jetstream::consumer::pull::Config {
+                durable_name: Some(name.clone()),
+                filter_subject: Self::subject(topic),
+                ack_wait: NATS_ACK_WAIT,
+                ..Default::default()
+            },
+        ))?;
+        self.consumers
+            .lock()
+            .unwrap()
+            .insert(topic, consumer.clone());
+        Ok(consumer)
+    }
+}
+
+impl JobBus for NatsBus {
+    fn publish(&self, event: &BusEvent) -> Result<String, Box<dyn Error>> {
+        let payload = serde_json::to_vec(event)?;
+        let ack = self.runtime().block_on(async {
+            self.context
+                .publish(Self::subject(event.topic()), payload.into())
+                .await?
+                .await
+                .map_err(Box::<dyn Error>::from)
+        })?;
+        Ok(ack.sequence.to_string())
+    }
+
+    fn next(&self, topic: Topic, timeout: Duration) -> Result<Option<Delivery>, Box<dyn Error>> {
+        use futures::StreamExt;
+
+        let consumer = self.consumer(topic)?;
+        let message = self
+            .runtime()
+            .block_on(async {
+                let mut messages = consumer
+                    .batch()
+                    .max_messages(1)
+                    .expires(timeout)
+                    .messages()
+                    .await?;
+                messages.next().await.transpose()
+            })
+            .map_err(|e| format!("Reading {} from NATS: {}", topic, e))?;
+        let Some(message) = message else {
+            return Ok(None);
+        };
+        let id = message
+            .info()
+            .map_err(|e| format!("Bus message of {} has no stream info: {}", topic, e))?
+            .stream_sequence
+            .to_string();
+        let (message, acker) = message.split();
+        let event = serde_json::from_slice(&message.payload)
+            .map_err(|e| format!("Bus message {} of {} is corrupt: {}", id, topic, e))?;
+        self.unacked.lock().unwrap().insert(id.clone(), acker);
+        Ok(Some(Delivery { id, topic, event }))
+    }
+
+    fn ack(&self, delivery: &Delivery) -> Result<(), Box<dyn Error>> {
+        let acker = self.unacked.lock().unwrap().remove(&delivery.id);
+        if let Some(acker) = acker {
+            self.runtime()
+                .block_on(acker.ack())
+                .map_err(|e| format!("Acknowledging bus message {}: {}", delivery.id, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NatsBus {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
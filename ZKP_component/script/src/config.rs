@@ -0,0 +1,566 @@
+//! `bridge.toml`: the endpoints, prover backend, circuit, output paths and network shared by
+//! the script binaries, in one file instead of env vars and long command lines.
+//!
+//! Every setting maps to the environment variable the binaries already read through clap, so
+//! precedence is: command-line flag, then the process environment, then `bridge.toml`, then
+//! `.env`, then the network profile, if one is chosen, then the built-in default.
+
+use crate::profile::{Profile, GROUP_KEY_ENV, PROFILE_ENV};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the config file when `--config` is not passed.
+pub const CONFIG_ENV: &str = "BRIDGE_CONFIG";
+
+/// Contents of `bridge.toml`. Enum values are spelled as on the command line, e.g.
+/// `backend = "network"` or `circuit = "mint-batch"`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BridgeConfig {
+    /// Network profile filling in the network, vault address, endpoints and chain id left
+    /// unset: `BRIDGE_PROFILE`.
+    pub profile: Option<Profile>,
+    pub prover: ProverConfig,
+    pub bitcoin: BitcoinConfig,
+    pub ethereum: EthereumConfig,
+    pub service: ServiceConfig,
+    pub api: ApiConfig,
+    pub fees: FeeConfig,
+    pub payouts: PayoutConfig,
+    pub policy: PolicyConfig,
+    pub pause: PauseConfig,
+    pub bus: BusConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProverConfig {
+    /// `--prover` / `SP1_PROVER`.
+    pub backend: Option<String>,
+    /// `--system` / `PROOF_SYSTEM`.
+    pub system: Option<String>,
+    /// `--circuit` / `BRIDGE_CIRCUIT`.
+    pub circuit: Option<String>,
+    /// Fixture directory of `evm` and `aggregate`: `--output` / `FIXTURE_DIR`.
+    pub output: Option<PathBuf>,
+    /// `--log-format` / `LOG_FORMAT`.
+    pub log_format: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BitcoinConfig {
+    /// Network the bundles are built for: `--network` / `BITCOIN_NETWORK`.
+    pub network: Option<String>,
+    /// `--source` / `BITCOIN_SOURCE`.
+    pub source: Option<String>,
+    /// `--rpc-url` / `BITCOIN_RPC_URL`.
+    pub rpc_url: Option<String>,
+    /// `--rpc-user` / `BITCOIN_RPC_USER`.
+    pub rpc_user: Option<String>,
+    /// `--rpc-password` / `BITCOIN_RPC_PASSWORD`.
+    pub rpc_password: Option<String>,
+    /// `--url` / `ESPLORA_URL`.
+    pub esplora_url: Option<String>,
+    /// Address `watch` scans for deposits: `--vault-address` / `BRIDGE_ADDRESS`.
+    pub vault_address: Option<String>,
+    /// x-only TSS group key of the vault: `--group-key` / `TSS_GROUP_KEY`.
+    pub group_key: Option<String>,
+    /// Deposit database of `watch`: `--db` / `WATCH_DB`.
+    pub watch_db: Option<PathBuf>,
+    /// Reorganization depth `watch` alerts from: `--reorg-threshold` / `REORG_THRESHOLD`.
+    pub reorg_threshold: Option<u32>,
+    /// Where `watch` and `watchtower` post their alerts: `--alert-webhook` /
+    /// `ALERT_WEBHOOK_URL`.
+    pub alert_webhook: Option<String>,
+    /// Vault database of `watchtower`: `--db` / `WATCHTOWER_DB`.
+    pub watchtower_db: Option<PathBuf>,
+    /// PagerDuty service `watchtower` triggers incidents on: `--pagerduty-routing-key` /
+    /// `PAGERDUTY_ROUTING_KEY`.
+    pub pagerduty_routing_key: Option<String>,
+    /// Command `watchtower` records evidence with: `--evidence-command` /
+    /// `WATCHTOWER_EVIDENCE_COMMAND`.
+    pub evidence_command: Option<String>,
+    /// Local header chain: `--headers-db` / `HEADERS_DB`.
+    pub headers_db: Option<PathBuf>,
+    /// `<height>:<hash>` the header chain starts at: `--checkpoint` / `HEADERS_CHECKPOINT`.
+    pub checkpoint: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct EthereumConfig {
+    /// `--rpc` / `ETH_RPC_URL`.
+    pub rpc_url: Option<String>,
+    /// `--contract` / `ZKBTC_CONTRACT`.
+    pub contract: Option<String>,
+    /// Chain id of the contract: the one `submit` checks the endpoint serves (`--chain-id`) and
+    /// `api` names in deposit memos (`--evm-chain-id`); `EVM_CHAIN_ID`.
+    pub chain_id: Option<u64>,
+    /// WebSocket endpoint `burn-listener` subscribes on: `--ws-url` / `ETH_WS_URL`.
+    pub ws_url: Option<String>,
+    /// Payout queue of `burn-listener`: `--db` / `PAYOUT_DB`.
+    pub payout_db: Option<PathBuf>,
+    /// Light-client contract `relay` pushes headers to: `--light-client` / `BTC_LIGHT_CLIENT`.
+    pub light_client: Option<String>,
+    /// Fee cap of `relay`, in gwei: `--max-fee-gwei` / `RELAY_MAX_FEE_GWEI`.
+    pub relay_max_fee_gwei: Option<u64>,
+    /// Seconds before `submit` replaces an unmined settlement: `--bump-after-secs` /
+    /// `SETTLEMENT_BUMP_SECS`.
+    pub settlement_bump_secs: Option<u64>,
+    /// Percent a replacement raises the fees by: `--fee-bump-percent` /
+    /// `SETTLEMENT_FEE_BUMP_PERCENT`.
+    pub settlement_fee_bump_percent: Option<u64>,
+    /// Replacements of a settlement per attempt: `--max-fee-bumps` / `SETTLEMENT_MAX_FEE_BUMPS`.
+    pub settlement_max_fee_bumps: Option<u32>,
+    /// Fee cap of `submit`, in gwei: `--max-fee-gwei` / `SETTLEMENT_MAX_FEE_GWEI`.
+    pub settlement_max_fee_gwei: Option<u64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceConfig {
+    /// `--listen` / `PROOF_SERVICE_LISTEN`.
+    pub listen: Option<String>,
+    /// Service `watch` queues proofs on: `--service` / `PROOF_SERVICE_URL`.
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Listen address of the swap API: `--listen` / `API_LISTEN`.
+    pub listen: Option<String>,
+    /// Swap API `watch` fetches the deposit addresses from, and `submit` and `payout-scheduler`
+    /// record settlements and refunds on: `--swap-api` / `SWAP_API_URL`.
+    pub url: Option<String>,
+    /// Swap database of the swap API: `--db` / `SWAP_DB`.
+    pub swap_db: Option<PathBuf>,
+    /// How the swap API tells users to deposit, `memo` or `address`: `--deposit-method` /
+    /// `DEPOSIT_METHOD`.
+    pub deposit_method: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeConfig {
+    /// BTC fee estimators, as `kind=url`: `--btc-fee-source` / `BTC_FEE_SOURCES`.
+    pub btc_sources: Vec<String>,
+    /// Ethereum endpoints asked for gas prices: `--evm-gas-rpc` / `EVM_GAS_RPCS`.
+    pub evm_rpcs: Vec<String>,
+    /// Highest BTC fee rate accepted, in sat/vB: `--max-fee-rate` / `MAX_FEE_RATE`.
+    pub max_fee_rate: Option<f64>,
+    /// Highest gas price accepted, in gwei: `--max-gas-gwei` / `MAX_GAS_GWEI`.
+    pub max_gas_gwei: Option<u64>,
+    /// Listen address of `fee-oracle`: `--listen` / `FEE_ORACLE_LISTEN`.
+    pub listen: Option<String>,
+    /// Fee oracle `submit`, the swap API and the payout builder quote from: `--fee-oracle` /
+    /// `FEE_ORACLE_URL`.
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PayoutConfig {
+    /// Where `burn-listener` serves its payout queue: `--listen` / `PAYOUT_QUEUE_LISTEN`.
+    pub queue_listen: Option<String>,
+    /// Payout queue `payout-scheduler` reads and `submit` queues refunds on: `--payout-queue` /
+    /// `PAYOUT_QUEUE_URL`.
+    pub queue_url: Option<String>,
+    /// Batch database of `payout-scheduler`: `--db` / `PAYOUT_SCHEDULER_DB`.
+    pub db: Option<PathBuf>,
+    /// TSS signing command: `--sign-command` / `PAYOUT_SIGN_COMMAND`.
+    pub sign_command: Option<String>,
+    /// Fee rate without a fee oracle, in sat/vB: `--fee-rate` / `PAYOUT_FEE_RATE`.
+    pub fee_rate: Option<f64>,
+    /// `--batch-count` / `PAYOUT_BATCH_COUNT`.
+    pub batch_count: Option<usize>,
+    /// In sats: `--batch-value` / `PAYOUT_BATCH_VALUE`.
+    pub batch_value: Option<u64>,
+    /// In seconds: `--max-wait` / `PAYOUT_MAX_WAIT`.
+    pub max_wait: Option<u64>,
+    /// `--max-batch` / `PAYOUT_MAX_BATCH`.
+    pub max_batch: Option<usize>,
+    /// In sat/vB: `--max-fee-rate` / `PAYOUT_MAX_FEE_RATE`.
+    pub max_fee_rate: Option<f64>,
+    /// In sat/vB: `--low-fee-rate` / `PAYOUT_LOW_FEE_RATE`.
+    pub low_fee_rate: Option<f64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// In sats: `--policy-max-mint-sats` / `POLICY_MAX_MINT_SATS`.
+    pub max_mint_sats: Option<u64>,
+    /// In sats: `--policy-max-payout-sats` / `POLICY_MAX_PAYOUT_SATS`.
+    pub max_payout_sats: Option<u64>,
+    /// `--policy-mint-allow` / `POLICY_MINT_ALLOW`.
+    pub mint_allow: Vec<String>,
+    /// `--policy-payout-allow` / `POLICY_PAYOUT_ALLOW`.
+    pub payout_allow: Vec<String>,
+    /// `--policy-deny` / `POLICY_DENY`.
+    pub deny: Vec<String>,
+    /// In sats: `--policy-mint-volume-sats` / `POLICY_MINT_VOLUME_SATS`.
+    pub mint_volume_sats: Option<u64>,
+    /// In sats: `--policy-payout-volume-sats` / `POLICY_PAYOUT_VOLUME_SATS`.
+    pub payout_volume_sats: Option<u64>,
+    /// In seconds: `--policy-epoch-secs` / `POLICY_EPOCH_SECS`.
+    pub epoch_secs: Option<u64>,
+    /// In sats: `--policy-mint-window-sats` / `POLICY_MINT_WINDOW_SATS`.
+    pub mint_window_sats: Option<u64>,
+    /// In sats: `--policy-payout-window-sats` / `POLICY_PAYOUT_WINDOW_SATS`.
+    pub payout_window_sats: Option<u64>,
+    /// `--policy-mint-window-count` / `POLICY_MINT_WINDOW_COUNT`.
+    pub mint_window_count: Option<u64>,
+    /// `--policy-payout-window-count` / `POLICY_PAYOUT_WINDOW_COUNT`.
+    pub payout_window_count: Option<u64>,
+    /// In seconds: `--policy-window-secs` / `POLICY_WINDOW_SECS`.
+    pub window_secs: Option<u64>,
+    /// Minted-volume database of `submit`: `--policy-db` / `POLICY_DB`.
+    pub db: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PauseConfig {
+    /// Notice file of `coordinator pause`: `--pause-notice` / `PAUSE_NOTICE`.
+    pub notice: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BusConfig {
+    /// Job bus of the services, `memory://`, `redis://...` or `nats://...`: `--bus` /
+    /// `JOB_BUS_URL`.
+    pub url: Option<String>,
+    /// Attempts of a queued job before it is dead-lettered: `--retry-max-attempts` /
+    /// `JOB_MAX_ATTEMPTS`.
+    pub max_attempts: Option<u32>,
+    /// In seconds: `--retry-base-secs` / `JOB_RETRY_BASE_SECS`.
+    pub retry_base_secs: Option<u64>,
+    /// In seconds: `--retry-max-secs` / `JOB_RETRY_MAX_SECS`.
+    pub retry_max_secs: Option<u64>,
+}
+
+impl BridgeConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Settings as `(environment variable, value)` pairs.
+    fn env_values(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            ("SP1_PROVER", self.prover.backend.clone()),
+            ("PROOF_SYSTEM", self.prover.system.clone()),
+            ("BRIDGE_CIRCUIT", self.prover.circuit.clone()),
+            (
+                "FIXTURE_DIR",
+                self.prover
+                    .output
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("LOG_FORMAT", self.prover.log_format.clone()),
+            ("BITCOIN_NETWORK", self.bitcoin.network.clone()),
+            ("BITCOIN_SOURCE", self.bitcoin.source.clone()),
+            ("BITCOIN_RPC_URL", self.bitcoin.rpc_url.clone()),
+            ("BITCOIN_RPC_USER", self.bitcoin.rpc_user.clone()),
+            ("BITCOIN_RPC_PASSWORD", self.bitcoin.rpc_password.clone()),
+            ("ESPLORA_URL", self.bitcoin.esplora_url.clone()),
+            ("BRIDGE_ADDRESS", self.bitcoin.vault_address.clone()),
+            ("TSS_GROUP_KEY", self.bitcoin.group_key.clone()),
+            (
+                "WATCH_DB",
+                self.bitcoin
+                    .watch_db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            (
+                "REORG_THRESHOLD",
+                self.bitcoin.reorg_threshold.map(|depth| depth.to_string()),
+            ),
+            ("ALERT_WEBHOOK_URL", self.bitcoin.alert_webhook.clone()),
+            (
+                "WATCHTOWER_DB",
+                self.bitcoin
+                    .watchtower_db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            (
+                "PAGERDUTY_ROUTING_KEY",
+                self.bitcoin.pagerduty_routing_key.clone(),
+            ),
+            (
+                "WATCHTOWER_EVIDENCE_COMMAND",
+                self.bitcoin.evidence_command.clone(),
+            ),
+            (
+                "HEADERS_DB",
+                self.bitcoin
+                    .headers_db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("HEADERS_CHECKPOINT", self.bitcoin.checkpoint.clone()),
+            ("ETH_RPC_URL", self.ethereum.rpc_url.clone()),
+            ("ZKBTC_CONTRACT", self.ethereum.contract.clone()),
+            (
+                "EVM_CHAIN_ID",
+                self.ethereum.chain_id.map(|chain_id| chain_id.to_string()),
+            ),
+            ("ETH_WS_URL", self.ethereum.ws_url.clone()),
+            (
+                "PAYOUT_DB",
+                self.ethereum
+                    .payout_db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("BTC_LIGHT_CLIENT", self.ethereum.light_client.clone()),
+            (
+                "RELAY_MAX_FEE_GWEI",
+                self.ethereum.relay_max_fee_gwei.map(|fee| fee.to_string()),
+            ),
+            (
+                "SETTLEMENT_BUMP_SECS",
+                self.ethereum
+                    .settlement_bump_secs
+                    .map(|secs| secs.to_string()),
+            ),
+            (
+                "SETTLEMENT_FEE_BUMP_PERCENT",
+                self.ethereum
+                    .settlement_fee_bump_percent
+                    .map(|percent| percent.to_string()),
+            ),
+            (
+                "SETTLEMENT_MAX_FEE_BUMPS",
+                self.ethereum
+                    .settlement_max_fee_bumps
+                    .map(|bumps| bumps.to_string()),
+            ),
+            (
+                "SETTLEMENT_MAX_FEE_GWEI",
+                self.ethereum
+                    .settlement_max_fee_gwei
+                    .map(|fee| fee.to_string()),
+            ),
+            ("PROOF_SERVICE_LISTEN", self.service.listen.clone()),
+            ("PROOF_SERVICE_URL", self.service.url.clone()),
+            ("API_LISTEN", self.api.listen.clone()),
+            ("SWAP_API_URL", self.api.url.clone()),
+            (
+                "SWAP_DB",
+                self.api
+                    .swap_db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("DEPOSIT_METHOD", self.api.deposit_method.clone()),
+            (
+                "BTC_FEE_SOURCES",
+                Some(self.fees.btc_sources.join(",")).filter(|list| !list.is_empty()),
+            ),
+            (
+                "EVM_GAS_RPCS",
+                Some(self.fees.evm_rpcs.join(",")).filter(|list| !list.is_empty()),
+            ),
+            (
+                "MAX_FEE_RATE",
+                self.fees.max_fee_rate.map(|rate| rate.to_string()),
+            ),
+            (
+                "MAX_GAS_GWEI",
+                self.fees.max_gas_gwei.map(|gwei| gwei.to_string()),
+            ),
+            ("FEE_ORACLE_LISTEN", self.fees.listen.clone()),
+            ("FEE_ORACLE_URL", self.fees.url.clone()),
+            ("PAYOUT_QUEUE_LISTEN", self.payouts.queue_listen.clone()),
+            ("PAYOUT_QUEUE_URL", self.payouts.queue_url.clone()),
+            (
+                "PAYOUT_SCHEDULER_DB",
+                self.payouts
+                    .db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("PAYOUT_SIGN_COMMAND", self.payouts.sign_command.clone()),
+            (
+                "PAYOUT_FEE_RATE",
+                self.payouts.fee_rate.map(|rate| rate.to_string()),
+            ),
+            (
+                "PAYOUT_BATCH_COUNT",
+                self.payouts.batch_count.map(|count| count.to_string()),
+            ),
+            (
+                "PAYOUT_BATCH_VALUE",
+                self.payouts.batch_value.map(|sats| sats.to_string()),
+            ),
+            (
+                "PAYOUT_MAX_WAIT",
+                self.payouts.max_wait.map(|secs| secs.to_string()),
+            ),
+            (
+                "PAYOUT_MAX_BATCH",
+                self.payouts.max_batch.map(|count| count.to_string()),
+            ),
+            (
+                "PAYOUT_MAX_FEE_RATE",
+                self.payouts.max_fee_rate.map(|rate| rate.to_string()),
+            ),
+            (
+                "PAYOUT_LOW_FEE_RATE",
+                self.payouts.low_fee_rate.map(|rate| rate.to_string()),
+            ),
+            (
+                "POLICY_MAX_MINT_SATS",
+                self.policy.max_mint_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_MAX_PAYOUT_SATS",
+                self.policy.max_payout_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_MINT_ALLOW",
+                Some(self.policy.mint_allow.join(",")).filter(|list| !list.is_empty()),
+            ),
+            (
+                "POLICY_PAYOUT_ALLOW",
+                Some(self.policy.payout_allow.join(",")).filter(|list| !list.is_empty()),
+            ),
+            (
+                "POLICY_DENY",
+                Some(self.policy.deny.join(",")).filter(|list| !list.is_empty()),
+            ),
+            (
+                "POLICY_MINT_VOLUME_SATS",
+                self.policy.mint_volume_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_PAYOUT_VOLUME_SATS",
+                self.policy.payout_volume_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_EPOCH_SECS",
+                self.policy.epoch_secs.map(|secs| secs.to_string()),
+            ),
+            (
+                "POLICY_MINT_WINDOW_SATS",
+                self.policy.mint_window_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_PAYOUT_WINDOW_SATS",
+                self.policy.payout_window_sats.map(|sats| sats.to_string()),
+            ),
+            (
+                "POLICY_MINT_WINDOW_COUNT",
+                self.policy.mint_window_count.map(|count| count.to_string()),
+            ),
+            (
+                "POLICY_PAYOUT_WINDOW_COUNT",
+                self.policy
+                    .payout_window_count
+                    .map(|count| count.to_string()),
+            ),
+            (
+                "POLICY_WINDOW_SECS",
+                self.policy.window_secs.map(|secs| secs.to_string()),
+            ),
+            (
+                "POLICY_DB",
+                self.policy
+                    .db
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            (
+                "PAUSE_NOTICE",
+                self.pause
+                    .notice
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("JOB_BUS_URL", self.bus.url.clone()),
+            (
+                "JOB_MAX_ATTEMPTS",
+                self.bus.max_attempts.map(|attempts| attempts.to_string()),
+            ),
+            (
+                "JOB_RETRY_BASE_SECS",
+                self.bus.retry_base_secs.map(|secs| secs.to_string()),
+            ),
+            (
+                "JOB_RETRY_MAX_SECS",
+                self.bus.retry_max_secs.map(|secs| secs.to_string()),
+            ),
+        ];
+        values
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+    }
+}
+
+/// Path given with `--config <path>` or `--config=<path>`, else `BRIDGE_CONFIG`.
+fn config_path() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os(CONFIG_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn set_unset(values: Vec<(&'static str, String)>) {
+    for (name, value) in values {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+}
+
+/// Loads the config file, if one is given, and then the network profile, if one is chosen,
+/// into the environment variables clap reads, without replacing variables that are already
+/// set. Must run before `dotenv` and `Args::parse`, so the file takes precedence over `.env`
+/// and flags take precedence over the file. Exits if the file cannot be read or parsed, or
+/// the profile is unknown.
+pub fn apply_config_file() {
+    let config = match config_path() {
+        Some(path) => BridgeConfig::load(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to load config {}: {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        None => BridgeConfig::default(),
+    };
+    set_unset(config.env_values());
+
+    let profile = match std::env::var(PROFILE_ENV) {
+        Ok(name) if !name.is_empty() => Some(Profile::from_str(&name, true).unwrap_or_else(|e| {
+            eprintln!("Unknown {} {}: {}", PROFILE_ENV, name, e);
+            std::process::exit(1);
+        })),
+        _ => config.profile,
+    };
+    if let Some(profile) = profile {
+        // `.env` is loaded first, so its values still take precedence over the profile's, and
+        // the group key it sets still names the profile's vault
+        dotenv::dotenv().ok();
+        let group_key = std::env::var(GROUP_KEY_ENV)
+            .ok()
+            .and_then(|key| key.parse::<XOnlyPublicKey>().ok());
+        set_unset(profile.env_values(group_key.as_ref()));
+    }
+}
@@ -0,0 +1,189 @@
+//! Per-request deposit addresses, so a user whose wallet cannot add an OP_RETURN memo can still
+//! fund a mint swap. Each request gets its own address, the group key tweaked with the request
+//! id, and the address alone names the request its deposit is credited to:
+//! - `DepositAddress::derive` allocates the address of a request; request ids are unique, and
+//!   so are their addresses.
+//! - `DepositAddressBook` keeps the addresses `watch` looks for with the deposits that fund
+//!   each, and maps a deposit's output script back to its request id.
+//! - `DepositAddress::merkle_root_hex` is the tweak the signers sign with to spend a deposit.
+
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::Script;
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::{request_deposit_script_pubkey, request_tweak};
+use lib_struct::DepositRequestStruct;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// A deposit paying a request's address.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct AddressFunding {
+    pub txid: String,
+    pub height: u32,
+    pub amount_sats: u64,
+}
+
+/// Deposit address of a swap request.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DepositAddress {
+    pub request_id: u64,
+    pub address: String,
+    /// Output script of the address, hex-encoded.
+    pub script_pubkey: String,
+    /// Script tree root the group key is tweaked with, hex-encoded; the signers need it to
+    /// spend the deposit.
+    pub merkle_root_hex: String,
+    /// Deposits paying the address, in the order they were found.
+    #[serde(default)]
+    pub funding: Vec<AddressFunding>,
+}
+
+impl DepositAddress {
+    /// The address of request `request_id` under `group_key`, with no deposit yet.
+    pub fn derive(
+        group_key: &XOnlyPublicKey,
+        request_id: u64,
+        chain: &ChainParams,
+    ) -> Result<Self, Box<dyn Error>> {
+        let script = request_deposit_script_pubkey(group_key, request_id);
+        Ok(DepositAddress {
+            request_id,
+            address: chain.address_from_script(&script)?,
+            script_pubkey: script.to_hex_string(),
+            merkle_root_hex: spend_tweak_hex(request_id),
+            funding: Vec::new(),
+        })
+    }
+
+    pub fn is_funded(&self) -> bool {
+        !self.funding.is_empty()
+    }
+
+    /// Value paid to the address over all its deposits.
+    pub fn funded_sats(&self) -> u64 {
+        self.funding.iter().map(|funding| funding.amount_sats).sum()
+    }
+
+    /// What a mint bundle of a deposit paying this address carries, so the circuit checks the
+    /// address instead of the vault and commits the request id.
+    pub fn deposit_request(&self) -> DepositRequestStruct {
+        DepositRequestStruct {
+            request_id: self.request_id,
+            script_pubkey: self.script_pubkey.clone(),
+        }
+    }
+}
+
+/// Tweak the signers sign with to spend a deposit of request `request_id`: the script tree
+/// root of its address, hex-encoded.
+pub fn spend_tweak_hex(request_id: u64) -> String {
+    hex::encode(request_tweak(request_id).to_byte_array())
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// The deposit addresses `watch` looks for, kept in its database.
+pub struct DepositAddressBook {
+    /// Addresses keyed by big-endian request id.
+    addresses: sled::Tree,
+    /// Big-endian request ids keyed by output script.
+    scripts: sled::Tree,
+}
+
+impl DepositAddressBook {
+    /// Opens the address trees of `db`.
+    pub fn open(db: &sled::Db) -> Result<Self, Box<dyn Error>> {
+        Ok(DepositAddressBook {
+            addresses: db.open_tree("deposit_addresses")?,
+            scripts: db.open_tree("deposit_scripts")?,
+        })
+    }
+
+    /// Starts watching `address`. An address already tracked keeps its deposits; returns
+    /// whether the address is new.
+    pub fn track(&self, address: &DepositAddress) -> Result<bool, Box<dyn Error>> {
+        if self.get(address.request_id)?.is_some() {
+            return Ok(false);
+        }
+        let script = hex::decode(&address.script_pubkey)?;
+        self.addresses.insert(
+            address.request_id.to_be_bytes(),
+            serde_json::to_vec(address)?,
+        )?;
+        self.scripts
+            .insert(script, &address.request_id.to_be_bytes())?;
+        Ok(true)
+    }
+
+    pub fn get(&self, request_id: u64) -> Result<Option<DepositAddress>, Box<dyn Error>> {
+        self.addresses
+            .get(request_id.to_be_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Request whose address has output script `script`.
+    pub fn request_for_script(&self, script: &Script) -> Result<Option<u64>, Box<dyn Error>> {
+        Ok(self
+            .scripts
+            .get(script.as_bytes())?
+            .map(|bytes| u64::from_be_bytes(bytes.as_ref().try_into().unwrap_or_default())))
+    }
+
+    /// Every tracked address, in request order.
+    pub fn addresses(&self) -> Result<Vec<DepositAddress>, Box<dyn Error>> {
+        self.addresses
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Records a deposit paying request `request_id`'s address; a deposit already recorded is
+    /// kept as is.
+    pub fn record_funding(
+        &self,
+        request_id: u64,
+        funding: AddressFunding,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut address = self
+            .get(request_id)?
+            .ok_or_else(|| format!("No deposit address for request {}", request_id))?;
+        if address
+            .funding
+            .iter()
+            .any(|known| known.txid == funding.txid)
+        {
+            return Ok(());
+        }
+        address.funding.push(funding);
+        self.addresses
+            .insert(request_id.to_be_bytes(), serde_json::to_vec(&address)?)?;
+        Ok(())
+    }
+
+    /// Forgets the deposits `txids`, dropped by a reorganization, from every address they
+    /// funded.
+    pub fn forget_funding(&self, txids: &[String]) -> Result<(), Box<dyn Error>> {
+        if txids.is_empty() {
+            return Ok(());
+        }
+        for mut address in self.addresses()? {
+            let funded = address.funding.len();
+            address
+                .funding
+                .retain(|funding| !txids.contains(&funding.txid));
+            if address.funding.len() != funded {
+                self.addresses.insert(
+                    address.request_id.to_be_bytes(),
+                    serde_json::to_vec(&address)?,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+//! Fee oracle: BTC fee rates and EVM gas prices aggregated from several sources, so the payout
+//! builder, `submit` and the swap API's quotes stop relying on hardcoded values. Each estimate
+//! is the median of the sources that answered within the sanity bounds, and a quote is cached
+//! for `--fee-cache-secs` so a burst of callers costs one round of requests. `fee-oracle`
+//! serves the quotes over HTTP; `fetch_quote` reads them back.
+
+use crate::bundle::RpcResponse;
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Confirmation targets of the BTC fee rates, in blocks.
+const FAST_TARGET: u32 = 1;
+const MEDIUM_TARGET: u32 = 3;
+const SLOW_TARGET: u32 = 6;
+const WEI_PER_GWEI: u128 = 1_000_000_000;
+
+/// A backend BTC fee rates are read from, given as `<kind>=<url>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BtcFeeSource {
+    /// Bitcoin Core `estimatesmartfee`; credentials go in the URL.
+    Core(String),
+    /// Esplora `GET /fee-estimates`.
+    Esplora(String),
+    /// mempool.space `GET /v1/fees/recommended`.
+    Mempool(String),
+}
+
+impl FromStr for BtcFeeSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, url) = value
+            .split_once('=')
+            .ok_or_else(|| format!("Fee source {} is not <kind>=<url>", value))?;
+        let url = url.trim_end_matches('/').to_string();
+        match kind {
+            "core" => Ok(BtcFeeSource::Core(url)),
+            "esplora" => Ok(BtcFeeSource::Esplora(url)),
+            "mempool" => Ok(BtcFeeSource::Mempool(url)),
+            _ => Err(format!(
+                "Unknown fee source kind {}; expected core, esplora or mempool",
+                kind
+            )),
+        }
+    }
+}
+
+/// Command-line settings of the fee oracle, shared by the binaries that run one.
+#[derive(clap::Args, Debug)]
+pub struct FeeOracleArgs {
+    /// BTC fee rate sources as `<kind>=<url>` with kind `core`, `esplora` or `mempool`;
+    /// repeat the flag or separate sources with commas.
+    #[clap(long, env = "BTC_FEE_SOURCES", value_delimiter = ',')]
+    pub btc_fee_source: Vec<BtcFeeSource>,
+    /// Ethereum JSON-RPC endpoints gas prices are read from; repeat the flag or separate
+    /// endpoints with commas.
+    #[clap(long, env = "EVM_GAS_RPCS", value_delimiter = ',')]
+    pub evm_gas_rpc: Vec<String>,
+    /// Lowest BTC fee rate accepted from a source, in sat/vB.
+    #[clap(long, default_value_t = 1.0)]
+    pub min_fee_rate: f64,
+    /// Highest BTC fee rate accepted from a source, in sat/vB.
+    #[clap(long, env = "MAX_FEE_RATE", default_value_t = 500.0)]
+    pub max_fee_rate: f64,
+    /// Highest EVM fee per gas accepted from a source, in gwei.
+    #[clap(long, env = "MAX_GAS_GWEI", default_value_t = 500)]
+    pub max_gas_gwei: u64,
+    /// Seconds a quote is served from the cache.
+    #[clap(long, default_value_t = 30)]
+    pub fee_cache_secs: u64,
+}
+
+/// BTC fee rates in sat/vB, by confirmation target.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BtcFees {
+    /// Next block.
+    pub fast: f64,
+    /// Within 3 blocks.
+    pub medium: f64,
+    /// Within 6 blocks.
+    pub slow: f64,
+    /// Sources the rates are the median of.
+    pub sources: usize,
+}
+
+/// EIP-1559 gas prices in wei.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct EvmFees {
+    pub base_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// Twice the base fee plus the tip, so the fee holds through a few full blocks.
+    pub max_fee_per_gas: u128,
+    /// Endpoints the prices are the median of.
+    pub sources: usize,
+}
+
+/// Fees as served by `fee-oracle`. A side without any sane source is absent.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct FeeQuote {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub btc: Option<BtcFees>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm: Option<EvmFees>,
+    /// Unix time the quote was fetched at.
+    pub fetched_at: u64,
+}
+
+/// `estimatesmartfee` result.
+#[derive(Deserialize)]
+struct SmartFee {
+    /// BTC per kvB; absent when the node has too little data.
+    feerate: Option<f64>,
+}
+
+/// mempool.space `GET /v1/fees/recommended` result.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendedFees {
+    fastest_fee: f64,
+    half_hour_fee: f64,
+    hour_fee: f64,
+}
+
+/// Median of `values`, which must not be empty.
+fn median<T: Copy + PartialOrd>(mut values: Vec<T>) -> T {
+    values.sort_by(|a, b| a.partial_cmp(b).expect("fees are comparable"));
+    values[values.len() / 2]
+}
+
+/// `max_fee_per_gas` of an EIP-1559 price; `None` when it overflows, as a base fee or tip read
+/// from an RPC can be any `u128`.
+fn max_fee_per_gas(base_fee: u128, tip: u128) -> Option<u128> {
+    base_fee.checked_mul(2)?.checked_add(tip)
+}
+
+fn parse_quantity(value: &Value) -> Result<u128, Box<dyn Error>> {
+    let hex = value
+        .as_str()
+        .and_then(|value| value.strip_prefix("0x"))
+        .ok_or_else(|| format!("{} is not a hex quantity", value))?;
+    Ok(u128::from_str_radix(hex, 16)?)
+}
+
+pub struct FeeOracle {
+    client: Client,
+    btc_sources: Vec<BtcFeeSource>,
+    evm_rpcs: Vec<String>,
+    fee_rate_bounds: (f64, f64),
+    max_gas: u128,
+    ttl: Duration,
+    cache: Mutex<Option<(Instant, FeeQuote)>>,
+}
+
+impl FeeOracle {
+    pub fn new(args: &FeeOracleArgs) -> Result<Self, Box<dyn Error>> {
+        if args.btc_fee_source.is_empty() && args.evm_gas_rpc.is_empty() {
+            return Err("The fee oracle needs --btc-fee-source or --evm-gas-rpc".into());
+        }
+        if args.min_fee_rate > args.max_fee_rate {
+            return Err(format!(
+                "--min-fee-rate {} is above --max-fee-rate {}",
+                args.min_fee_rate, args.max_fee_rate
+            )
+            .into());
+        }
+        Ok(FeeOracle {
+            client: Client::new(),
+            btc_sources: args.btc_fee_source.clone(),
+            evm_rpcs: args.evm_gas_rpc.clone(),
+            fee_rate_bounds: (args.min_fee_rate, args.max_fee_rate),
+            max_gas: args.max_gas_gwei as u128 * WEI_PER_GWEI,
+            ttl: Duration::from_secs(args.fee_cache_secs),
+            cache: Mutex::new(None),
+        })
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Box<dyn Error>> {
+        Ok(self.client.get(url).send()?.error_for_status()?.json()?)
+    }
+
+    fn rpc<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn Error>> {
+        let response: RpcResponse<T> = self
+            .client
+            .post(url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()?
+            .json()?;
+        match (response.result, response.error) {
+            (_, Some(error)) => {
+                Err(format!("RPC {} failed ({}): {}", method, error.code, error.message).into())
+            }
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(format!("RPC {} returned no result", method).into()),
+        }
+    }
+
+    /// Fast, medium and slow fee rates of one source, in sat/vB.
+    fn btc_rates(&self, source: &BtcFeeSource) -> Result<[f64; 3], Box<dyn Error>> {
+        match source {
+            BtcFeeSource::Core(url) => {
+                let mut rates = [0.0; 3];
+                for (rate, target) in
+                    rates
+                        .iter_mut()
+                        .zip([FAST_TARGET, MEDIUM_TARGET, SLOW_TARGET])
+                {
+                    let estimate: SmartFee = self.rpc(url, "estimatesmartfee", json!([target]))?;
+                    let btc_per_kvb = estimate
+                        .feerate
+                        .ok_or_else(|| format!("Node has no fee estimate for {} blocks", target))?;
+                    *rate = btc_per_kvb * 100_000_000.0 / 1000.0;
+                }
+                Ok(rates)
+            }
+            BtcFeeSource::Esplora(url) => {
+                let estimates: HashMap<String, f64> =
+                    self.get_json(&format!("{}/fee-estimates", url))?;
+                let rate = |target: u32| {
+                    estimates
+                        .get(&target.to_string())
+                        .copied()
+                        .ok_or_else(|| format!("No fee estimate for {} blocks", target))
+                };
+                Ok([rate(FAST_TARGET)?, rate(MEDIUM_TARGET)?, rate(SLOW_TARGET)?])
+            }
+            BtcFeeSource::Mempool(url) => {
+                let fees: RecommendedFees =
+                    self.get_json(&format!("{}/v1/fees/recommended", url))?;
+                Ok([fees.fastest_fee, fees.half_hour_fee, fees.hour_fee])
+            }
+        }
+    }
+
+    /// Median BTC rates of the sources within bounds; `None` when no source gave any.
+    fn btc_fees(&self) -> Option<BtcFees> {
+        let (min, max) = self.fee_rate_bounds;
+        let rates: Vec<[f64; 3]> = self
+            .btc_sources
+            .iter()
+            .filter_map(|source| match self.btc_rates(source) {
+                Ok(rates) if rates.iter().all(|rate| (min..=max).contains(rate)) => Some(rates),
+                Ok(rates) => {
+                    warn!(
+                        ?source,
+                        ?rates,
+                        min,
+                        max,
+                        "BTC fee rates out of bounds, ignored"
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!(?source, error = %e, "BTC fee source failed");
+                    None
+                }
+            })
+            .collect();
+        if rates.is_empty() {
+            return None;
+        }
+        Some(BtcFees {
+            fast: median(rates.iter().map(|rates| rates[0]).collect()),
+            medium: median(rates.iter().map(|rates| rates[1]).collect()),
+            slow: median(rates.iter().map(|rates| rates[2]).collect()),
+            sources: rates.len(),
+        })
+    }
+
+    /// Base fee of the latest block and suggested tip of one endpoint, in wei.
+    fn evm_prices(&self, url: &str) -> Result<(u128, u128), Box<dyn Error>> {
+        let block: Value = self.rpc(url, "eth_getBlockByNumber", json!(["latest", false]))?;
+        let base_fee = parse_quantity(&block["baseFeePerGas"])?;
+        let tip: Value = self.rpc(url, "eth_maxPriorityFeePerGas", json!([]))?;
+        Ok((base_fee, parse_quantity(&tip)?))
+    }
+
+    /// Median gas prices of the endpoints within bounds; `None` when no endpoint gave any. A
+    /// price whose max fee overflows is out of bounds.
+    fn evm_fees(&self) -> Option<EvmFees> {
+        let prices: Vec<(u128, u128)> = self
+            .evm_rpcs
+            .iter()
+            .filter_map(|url| match self.evm_prices(url) {
+                Ok((base_fee, tip))
+                    if max_fee_per_gas(base_fee, tip)
+                        .is_some_and(|max_fee| max_fee <= self.max_gas) =>
+                {
+                    Some((base_fee, tip))
+                }
+                Ok((base_fee, tip)) => {
+                    warn!(
+                        url,
+                        base_fee,
+                        tip,
+                        max = self.max_gas,
+                        "Gas price out of bounds, ignored"
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!(url, error = %e, "Gas price source failed");
+                    None
+                }
+            })
+            .collect();
+        if prices.is_empty() {
+            return None;
+        }
+        let base_fee_per_gas = median(prices.iter().map(|prices| prices.0).collect());
+        let max_priority_fee_per_gas = median(prices.iter().map(|prices| prices.1).collect());
+        let Some(max_fee) = max_fee_per_gas(base_fee_per_gas, max_priority_fee_per_gas) else {
+            warn!(
+                base_fee_per_gas,
+                max_priority_fee_per_gas, "Median gas price overflows, ignored"
+            );
+            return None;
+        };
+        Some(EvmFees {
+            base_fee_per_gas,
+            max_priority_fee_per_gas,
+            max_fee_per_gas: max_fee,
+            sources: prices.len(),
+        })
+    }
+
+    /// Current fees, from the cache while it is fresh. A side whose sources all failed is left
+    /// out; fails when both are.
+    pub fn quote(&self) -> Result<FeeQuote, Box<dyn Error>> {
+        if let Some((fetched, quote)) = self.cache.lock().expect("fee cache lock").as_ref() {
+            if fetched.elapsed() < self.ttl {
+                return Ok(quote.clone());
+            }
+        }
+        let quote = FeeQuote {
+            btc: self.btc_fees(),
+            evm: self.evm_fees(),
+            fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        };
+        if quote.btc.is_none() && quote.evm.is_none() {
+            return Err("No fee source gave an estimate within bounds".into());
+        }
+        *self.cache.lock().expect("fee cache lock") = Some((Instant::now(), quote.clone()));
+        Ok(quote)
+    }
+}
+
+/// Reads the current quote from a `fee-oracle` service at `url`.
+pub fn fetch_quote(client: &Client, url: &str) -> Result<FeeQuote, Box<dyn Error>> {
+    Ok(client
+        .get(format!("{}/fees", url.trim_end_matches('/')))
+        .send()?
+        .error_for_status()?
+        .json()?)
+}
@@ -0,0 +1,524 @@
+//! Local Bitcoin header chain: the best-chain headers from a trusted checkpoint up, each with
+//! its height and the cumulative work since the checkpoint, kept in a sled database. Headers
+//! fetched from the backend are only stored once their hash, proof-of-work, difficulty
+//! transition and linkage are checked, and a competing chain only replaces the stored one when
+//! it has more work. `HeaderChainSource` answers the confirmation headers of `build-bundle` and
+//! `watch` from it, so a bundle's chain is the bridge's own view rather than the backend's.
+
+use crate::bundle::{BundleSource, HeaderInfo, Utxo};
+use bitcoin::block::{Header, Version};
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hash_types::{BlockHash, TxMerkleNode, Txid};
+use bitcoin::hashes::Hash;
+use bitcoin::network::Network;
+use bitcoin::pow::{CompactTarget, Work};
+use bitcoin::{Address, OutPoint, Transaction};
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::verify_chain_with_crate;
+use lib_struct::{Block, Chain, MerkleProof, RetargetInfo};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Blocks between two difficulty retargets.
+const RETARGET_INTERVAL: u32 = 2016;
+/// Headers fetched and validated at once.
+const SYNC_BATCH: u32 = 500;
+/// Key of the checkpoint the chain starts at in the default tree.
+const CHECKPOINT_KEY: &[u8] = b"checkpoint";
+
+/// Block the header chain is trusted from, given as `<height>:<hash>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Checkpoint {
+    pub height: u32,
+    pub hash: BlockHash,
+}
+
+impl FromStr for Checkpoint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (height, hash) = value
+            .split_once(':')
+            .ok_or_else(|| format!("Checkpoint {} is not <height>:<hash>", value))?;
+        Ok(Checkpoint {
+            height: height
+                .parse()
+                .map_err(|e| format!("Invalid checkpoint height {}: {}", height, e))?,
+            hash: BlockHash::from_str(hash)
+                .map_err(|e| format!("Invalid checkpoint hash {}: {}", hash, e))?,
+        })
+    }
+}
+
+/// A validated header of the best chain.
+#[derive(Clone, Debug)]
+pub struct StoredHeader {
+    pub height: u32,
+    pub header: Header,
+    /// Work of the chain from the checkpoint up to this header, both included.
+    pub chain_work: Work,
+}
+
+impl StoredHeader {
+    /// The 80-byte header followed by the big-endian chain work.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = serialize(&self.header);
+        bytes.extend_from_slice(&self.chain_work.to_be_bytes());
+        bytes
+    }
+
+    fn decode(height: u32, bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() != 112 {
+            return Err(format!("Stored header {} has {} bytes", height, bytes.len()).into());
+        }
+        Ok(StoredHeader {
+            height,
+            header: deserialize(&bytes[..80])?,
+            chain_work: Work::from_be_bytes(bytes[80..].try_into()?),
+        })
+    }
+
+    pub fn hash(&self) -> BlockHash {
+        self.header.block_hash()
+    }
+
+    /// The header in the form of the circuit inputs.
+    pub fn block(&self) -> Block {
+        header_block(&self.header)
+    }
+}
+
+fn header_block(header: &Header) -> Block {
+    Block {
+        block_hash: header.block_hash().to_string(),
+        version: header.version.to_consensus() as u32,
+        parent_hash: header.prev_blockhash.to_string(),
+        merkle_root: header.merkle_root.to_string(),
+        timestamp: header.time,
+        difficulty: header.bits.to_consensus(),
+        nonce: header.nonce,
+    }
+}
+
+fn block_header(block: &Block) -> Result<Header, Box<dyn Error>> {
+    Ok(Header {
+        version: Version::from_consensus(block.version as i32),
+        prev_blockhash: BlockHash::from_str(&block.parent_hash)?,
+        merkle_root: TxMerkleNode::from_str(&block.merkle_root)?,
+        time: block.timestamp,
+        bits: CompactTarget::from_consensus(block.difficulty),
+        nonce: block.nonce,
+    })
+}
+
+pub struct HeaderChain {
+    db: sled::Db,
+    /// Stored headers keyed by big-endian height.
+    headers: sled::Tree,
+    /// Heights keyed by block hash.
+    heights: sled::Tree,
+    network: Network,
+}
+
+impl HeaderChain {
+    /// Opens the header chain at `path` for `network`. A new chain starts at `checkpoint`,
+    /// fetched from `source`, or at the genesis block; an existing one must contain the
+    /// checkpoint, if given.
+    pub fn open(
+        path: &Path,
+        network: Network,
+        checkpoint: Option<&Checkpoint>,
+        source: &dyn BundleSource,
+    ) -> Result<Self, Box<dyn Error>> {
+        let db = sled::open(path)?;
+        let chain = HeaderChain {
+            headers: db.open_tree("headers")?,
+            heights: db.open_tree("header_heights")?,
+            db,
+            network,
+        };
+        match chain.db.get(CHECKPOINT_KEY)? {
+            Some(stored) => {
+                let stored = Checkpoint::from_str(std::str::from_utf8(&stored)?)?;
+                if let Some(checkpoint) = checkpoint {
+                    let hash = chain.get(checkpoint.height)?.map(|header| header.hash());
+                    if hash != Some(checkpoint.hash) {
+                        return Err(format!(
+                            "Header chain from {}:{} does not contain checkpoint {}:{}",
+                            stored.height, stored.hash, checkpoint.height, checkpoint.hash
+                        )
+                        .into());
+                    }
+                }
+            }
+            None => chain.start(checkpoint, source)?,
+        }
+        Ok(chain)
+    }
+
+    /// Stores the first header: the checkpoint's, or the genesis block's.
+    fn start(
+        &self,
+        checkpoint: Option<&Checkpoint>,
+        source: &dyn BundleSource,
+    ) -> Result<(), Box<dyn Error>> {
+        let (height, header) = match checkpoint {
+            Some(checkpoint) => {
+                let info = source.block_header(&checkpoint.hash.to_string())?;
+                let header = block_header(&info.block)?;
+                if header.block_hash() != checkpoint.hash || info.height != checkpoint.height {
+                    return Err(format!(
+                        "Source returned block {} at height {} for checkpoint {}:{}",
+                        header.block_hash(),
+                        info.height,
+                        checkpoint.height,
+                        checkpoint.hash
+                    )
+                    .into());
+                }
+                header.validate_pow(header.target())?;
+                (checkpoint.height, header)
+            }
+            None => (0, genesis_block(self.network).header),
+        };
+        self.put(&StoredHeader {
+            height,
+            header,
+            chain_work: header.work(),
+        })?;
+        self.db.insert(
+            CHECKPOINT_KEY,
+            format!("{}:{}", height, header.block_hash()).as_bytes(),
+        )?;
+        self.db.flush()?;
+        info!(height, hash = %header.block_hash(), network = %self.network, "Header chain started");
+        Ok(())
+    }
+
+    fn put(&self, header: &StoredHeader) -> Result<(), Box<dyn Error>> {
+        self.headers
+            .insert(header.height.to_be_bytes(), header.encode())?;
+        self.heights.insert(
+            header.hash().to_byte_array(),
+            &header.height.to_be_bytes()[..],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, height: u32) -> Result<Option<StoredHeader>, Box<dyn Error>> {
+        self.headers
+            .get(height.to_be_bytes())?
+            .map(|bytes| StoredHeader::decode(height, &bytes))
+            .transpose()
+    }
+
+    /// Height of `hash`, if it is on the stored chain.
+    pub fn height_of(&self, hash: &BlockHash) -> Result<Option<u32>, Box<dyn Error>> {
+        self.heights
+            .get(hash.to_byte_array())?
+            .map(|height| Ok(u32::from_be_bytes(height.as_ref().try_into()?)))
+            .transpose()
+    }
+
+    /// The checkpoint header, lowest of the chain.
+    pub fn base(&self) -> Result<StoredHeader, Box<dyn Error>> {
+        let (height, bytes) = self.headers.first()?.ok_or("Header chain is empty")?;
+        StoredHeader::decode(u32::from_be_bytes(height.as_ref().try_into()?), &bytes)
+    }
+
+    pub fn tip(&self) -> Result<StoredHeader, Box<dyn Error>> {
+        let (height, bytes) = self.headers.last()?.ok_or("Header chain is empty")?;
+        StoredHeader::decode(u32::from_be_bytes(height.as_ref().try_into()?), &bytes)
+    }
+
+    fn stored(&self, height: u32) -> Result<StoredHeader, Box<dyn Error>> {
+        self.get(height)?
+            .ok_or_else(|| format!("Header chain has no header at height {}", height).into())
+    }
+
+    /// Timestamp of the first block of the epoch that ends before `boundary`. An epoch that
+    /// started below the checkpoint is read from the source, the only header trusted as is.
+    fn epoch_start_timestamp(
+        &self,
+        boundary: u32,
+        source: &dyn BundleSource,
+    ) -> Result<u32, Box<dyn Error>> {
+        let height = boundary - RETARGET_INTERVAL;
+        match self.get(height)? {
+            Some(header) => Ok(header.header.time),
+            None => {
+                warn!(
+                    height,
+                    "Epoch start is below the checkpoint, taking its timestamp from the source"
+                );
+                Ok(source
+                    .block_header(&source.block_hash(height)?)?
+                    .block
+                    .timestamp)
+            }
+        }
+    }
+
+    /// Fetches the headers above `parent` up to `tip`, stopping before a second retarget, and
+    /// validates them on top of `parent`.
+    fn fetch_batch(
+        &self,
+        source: &dyn BundleSource,
+        parent: &StoredHeader,
+        tip: u32,
+    ) -> Result<Vec<StoredHeader>, Box<dyn Error>> {
+        let first = parent.height + 1;
+        let boundary = first.next_multiple_of(RETARGET_INTERVAL);
+        let last = tip
+            .min(parent.height + SYNC_BATCH)
+            .min(boundary + RETARGET_INTERVAL - 1);
+
+        let mut blocks = vec![parent.block()];
+        let mut hash = source.block_hash(first)?;
+        for height in first..=last {
+            let info = source.block_header(&hash)?;
+            if info.height != height {
+                return Err(format!(
+                    "Source returned height {} for block {}, expected {}",
+                    info.height, hash, height
+                )
+                .into());
+            }
+            blocks.push(info.block);
+            if height < last {
+                hash = info
+                    .next_hash
+                    .ok_or_else(|| format!("Block {} has no successor yet", hash))?;
+            }
+        }
+
+        // The parent is validated again as the anchor, with its bits transition to the batch
+        let retarget_info = RetargetInfo {
+            start_height: parent.height,
+            epoch_start_timestamp: if boundary <= last {
+                self.epoch_start_timestamp(boundary, source)?
+            } else {
+                0
+            },
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+        let chain = Chain { blocks };
+        verify_chain_with_crate(
+            &chain,
+            chain.blocks.len(),
+            Some(&retarget_info),
+            Some(&chain.blocks[0].parent_hash),
+            Some(now),
+            &ChainParams::from(self.network),
+        )?;
+
+        let mut chain_work = parent.chain_work;
+        chain.blocks[1..]
+            .iter()
+            .zip(first..)
+            .map(|(block, height)| {
+                let header = block_header(block)?;
+                chain_work = chain_work + header.work();
+                Ok(StoredHeader {
+                    height,
+                    header,
+                    chain_work,
+                })
+            })
+            .collect()
+    }
+
+    /// Highest stored height whose header the source still has on its best chain.
+    fn fork_point(
+        &self,
+        source: &dyn BundleSource,
+        source_tip: u32,
+    ) -> Result<u32, Box<dyn Error>> {
+        let base = self.base()?;
+        let mut height = self.tip()?.height.min(source_tip);
+        loop {
+            if height < base.height {
+                return Err(format!(
+                    "Source's best chain does not contain checkpoint {}:{}",
+                    base.height,
+                    base.hash()
+                )
+                .into());
+            }
+            if source.block_hash(height)? == self.stored(height)?.hash().to_string() {
+                return Ok(height);
+            }
+            height -= 1;
+        }
+    }
+
+    /// Brings the chain up to the source's tip. A chain of the source that forks from the
+    /// stored one replaces it only once it has more work; the headers above the fork are then
+    /// dropped. Returns the tip.
+    pub fn sync(&self, source: &dyn BundleSource) -> Result<StoredHeader, Box<dyn Error>> {
+        let source_tip = source.tip_height()?;
+        let stored_tip = self.tip()?;
+        let fork = self.fork_point(source, source_tip)?;
+
+        let mut parent = self.stored(fork)?;
+        let mut competing = fork < stored_tip.height;
+        let mut pending: Vec<StoredHeader> = Vec::new();
+        while parent.height < source_tip {
+            let batch = self.fetch_batch(source, &parent, source_tip)?;
+            parent = batch[batch.len() - 1].clone();
+            pending.extend(batch);
+            if competing {
+                if parent.chain_work <= stored_tip.chain_work {
+                    continue;
+                }
+                for height in fork + 1..=stored_tip.height {
+                    let orphaned = self.stored(height)?;
+                    self.heights.remove(orphaned.hash().to_byte_array())?;
+                    self.headers.remove(height.to_be_bytes())?;
+                }
+                warn!(
+                    fork_height = fork + 1,
+                    orphaned = stored_tip.height - fork,
+                    "Header chain switched to a chain with more work"
+                );
+                competing = false;
+            }
+            for header in pending.drain(..) {
+                self.put(&header)?;
+            }
+            self.db.flush()?;
+            if parent.height % 10_000 < SYNC_BATCH {
+                info!(height = parent.height, source_tip, "Header chain syncing");
+            }
+        }
+        if competing {
+            warn!(
+                fork_height = fork + 1,
+                source_tip,
+                stored_tip = stored_tip.height,
+                "Source follows a chain with less work than the stored one, keeping it"
+            );
+        }
+        self.tip()
+    }
+
+    /// Header of `hash` in the form of `BundleSource::block_header`.
+    fn header_info(&self, hash: &BlockHash) -> Result<Option<HeaderInfo>, Box<dyn Error>> {
+        let Some(height) = self.height_of(hash)? else {
+            return Ok(None);
+        };
+        Ok(Some(HeaderInfo {
+            block: self.stored(height)?.block(),
+            height,
+            next_hash: self.get(height + 1)?.map(|next| next.hash().to_string()),
+        }))
+    }
+}
+
+/// A backend whose headers are answered from the local header chain, synced before each
+/// lookup the chain cannot answer yet. Transactions and Merkle proofs still come from the
+/// backend; `build_bundle` checks them against the validated headers.
+pub struct HeaderChainSource {
+    source: Box<dyn BundleSource>,
+    chain: HeaderChain,
+}
+
+impl HeaderChainSource {
+    pub fn new(source: Box<dyn BundleSource>, chain: HeaderChain) -> Self {
+        HeaderChainSource { source, chain }
+    }
+}
+
+impl BundleSource for HeaderChainSource {
+    fn raw_transaction(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.source.raw_transaction(txid, block_hash)
+    }
+
+    fn transaction_block(
+        &self,
+        txid: &Txid,
+        block_hash: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.source.transaction_block(txid, block_hash)
+    }
+
+    fn merkle_proof(
+        &self,
+        txid: &Txid,
+        block_hash: &str,
+    ) -> Result<(MerkleProof, u32), Box<dyn Error>> {
+        self.source.merkle_proof(txid, block_hash)
+    }
+
+    fn coinbase_txid(&self, block_hash: &str) -> Result<Txid, Box<dyn Error>> {
+        self.source.coinbase_txid(block_hash)
+    }
+
+    fn block_header(&self, block_hash: &str) -> Result<HeaderInfo, Box<dyn Error>> {
+        let hash = BlockHash::from_str(block_hash)?;
+        if let Some(info) = self.chain.header_info(&hash)? {
+            return Ok(info);
+        }
+        self.chain.sync(self.source.as_ref())?;
+        self.chain.header_info(&hash)?.ok_or_else(|| {
+            format!("Block {} is not on the validated header chain", block_hash).into()
+        })
+    }
+
+    fn block_hash(&self, height: u32) -> Result<String, Box<dyn Error>> {
+        if height > self.chain.tip()?.height {
+            self.chain.sync(self.source.as_ref())?;
+        }
+        match self.chain.get(height)? {
+            Some(header) => Ok(header.hash().to_string()),
+            None => Err(format!(
+                "Height {} is not on the validated header chain, which starts at {}",
+                height,
+                self.chain.base()?.height
+            )
+            .into()),
+        }
+    }
+
+    fn network(&self) -> Result<Network, Box<dyn Error>> {
+        Ok(self.chain.network)
+    }
+
+    fn tip_height(&self) -> Result<u32, Box<dyn Error>> {
+        Ok(self.chain.sync(self.source.as_ref())?.height)
+    }
+
+    fn block_transactions(&self, block_hash: &str) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.source.block_transactions(block_hash)
+    }
+
+    fn address_utxos(&self, address: &Address) -> Result<Vec<Utxo>, Box<dyn Error>> {
+        self.source.address_utxos(address)
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Box<dyn Error>> {
+        self.source.broadcast(tx)
+    }
+
+    fn mempool_spenders(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<(OutPoint, Txid)>, Box<dyn Error>> {
+        self.source.mempool_spenders(outpoints)
+    }
+
+    fn checkpoint_hash(&self, height: u32) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self
+            .chain
+            .get(height)?
+            .map(|header| header.hash().to_string()))
+    }
+}
@@ -0,0 +1,256 @@
+//! Reads circuit input JSON and checks it against the bundle schema before it is written to
+//! `SP1Stdin`: a missing or mistyped field is reported with its path, line and column, and a
+//! hash of the wrong length or a chain of the wrong depth with its path, instead of a panic
+//! inside the zkVM or a bare `failed to parse input JSON`.
+
+use lib_struct::chain::parse_chain;
+use lib_struct::verify::DEFAULT_CONFIRMATIONS;
+use lib_struct::{
+    BitcoinTrxInfoStruct, BundleInfoStruct, BurnBatchBundleStruct, Chain, MerkleProof,
+    MintBatchBundleStruct,
+};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::path::Path;
+
+/// Problems found in an input, as `(field path, problem)` pairs; paths use the JSON field names.
+#[derive(Default, Debug)]
+pub struct Issues(Vec<(String, String)>);
+
+impl Issues {
+    fn add(&mut self, field: &str, problem: impl Into<String>) {
+        self.0.push((field.to_string(), problem.into()));
+    }
+
+    /// Checks that `value` is hex, of exactly `bytes` bytes when given.
+    fn hex(&mut self, field: &str, value: &str, bytes: Option<usize>) {
+        if let Some(position) = value.find(|c: char| !c.is_ascii_hexdigit()) {
+            self.add(
+                field,
+                format!("not hex: {:?} at character {}", value, position),
+            );
+            return;
+        }
+        match bytes {
+            Some(bytes) if value.len() != 2 * bytes => self.add(
+                field,
+                format!(
+                    "expected {} hex characters ({} bytes), found {}",
+                    2 * bytes,
+                    bytes,
+                    value.len()
+                ),
+            ),
+            None if value.is_empty() || value.len() % 2 == 1 => self.add(
+                field,
+                format!(
+                    "expected an even, non-zero number of hex characters, found {}",
+                    value.len()
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    fn transaction(&mut self, field: &str, tx: &BitcoinTrxInfoStruct) {
+        self.hex(&format!("{}.rawTxHex", field), &tx.raw_tx_hex, None);
+    }
+
+    fn merkle_proof(&mut self, field: &str, proof: &MerkleProof) {
+        for (i, sibling) in proof.siblings.iter().enumerate() {
+            self.hex(&format!("{}.siblings[{}]", field, i), sibling, Some(32));
+        }
+    }
+
+    fn network(&mut self, network: Option<&str>) {
+        if let Err(e) = parse_chain(network) {
+            self.add("network", e.to_string());
+        }
+    }
+
+    /// Checks the header hashes of a chain and, when `depth` is given, its length.
+    fn chain(&mut self, field: &str, chain: &Chain, depth: Option<(usize, &str)>) {
+        if let Some((depth, declared_by)) = depth {
+            if chain.blocks.len() != depth {
+                self.add(
+                    &format!("{}.blocks", field),
+                    format!(
+                        "{} blocks, but {} requires {}",
+                        chain.blocks.len(),
+                        declared_by,
+                        depth
+                    ),
+                );
+            }
+        }
+        for (i, block) in chain.blocks.iter().enumerate() {
+            let block_field = format!("{}.blocks[{}]", field, i);
+            self.hex(
+                &format!("{}.blockHash", block_field),
+                &block.block_hash,
+                Some(32),
+            );
+            self.hex(
+                &format!("{}.parentHash", block_field),
+                &block.parent_hash,
+                Some(32),
+            );
+            self.hex(
+                &format!("{}.merkleRoot", block_field),
+                &block.merkle_root,
+                Some(32),
+            );
+        }
+    }
+}
+
+/// Circuit inputs with a schema beyond what deserializing them checks.
+pub trait InputSchema: DeserializeOwned {
+    fn check(&self, issues: &mut Issues);
+}
+
+impl InputSchema for BundleInfoStruct {
+    fn check(&self, issues: &mut Issues) {
+        issues.merkle_proof("merkleProof", &self.merkle_proof);
+        let depth = match self.confirmations {
+            Some(confirmations) if confirmations as usize >= DEFAULT_CONFIRMATIONS => {
+                (confirmations as usize, "`confirmations`")
+            }
+            Some(confirmations) => {
+                issues.add(
+                    "confirmations",
+                    format!(
+                        "{} is below the minimum depth of {}",
+                        confirmations, DEFAULT_CONFIRMATIONS
+                    ),
+                );
+                (DEFAULT_CONFIRMATIONS, "the minimum confirmation depth")
+            }
+            None => (DEFAULT_CONFIRMATIONS, "the default confirmation depth"),
+        };
+        issues.chain("chains", &self.chains, Some(depth));
+        issues.transaction("bitTxInfo", &self.bit_tx_info);
+        if let Some(chain) = &self.competing_chain {
+            issues.chain("competingChain", chain, None);
+        }
+        if let Some(hash) = &self.checkpoint_hash {
+            issues.hex("checkpointHash", hash, Some(32));
+        }
+        if let Some(tx) = &self.prevout_tx_info {
+            issues.transaction("prevoutTxInfo", tx);
+        }
+        for (i, tx) in self.spent_prevouts.iter().flatten().enumerate() {
+            issues.transaction(&format!("spentPrevouts[{}]", i), tx);
+        }
+        if let Some(key) = &self.vault_public_key {
+            issues.hex("vaultPublicKey", key, Some(32));
+        }
+        if let Some(proof) = &self.witness_proof {
+            issues.transaction("witnessProof.coinbaseTxInfo", &proof.coinbase_tx_info);
+            issues.merkle_proof(
+                "witnessProof.coinbaseMerkleProof",
+                &proof.coinbase_merkle_proof,
+            );
+            issues.merkle_proof("witnessProof.wtxidMerkleProof", &proof.wtxid_merkle_proof);
+        }
+        if let Some(proof) = &self.coinbase_proof {
+            issues.transaction("coinbaseProof.coinbaseTxInfo", &proof.coinbase_tx_info);
+            issues.merkle_proof(
+                "coinbaseProof.coinbaseMerkleProof",
+                &proof.coinbase_merkle_proof,
+            );
+        }
+        if let Some(request) = &self.deposit_request {
+            issues.hex("depositRequest.scriptPubkey", &request.script_pubkey, None);
+        }
+        issues.network(self.network.as_deref());
+    }
+}
+
+impl InputSchema for MintBatchBundleStruct {
+    fn check(&self, issues: &mut Issues) {
+        if self.deposits.is_empty() {
+            issues.add("deposits", "no deposits");
+        }
+        for (i, deposit) in self.deposits.iter().enumerate() {
+            issues.merkle_proof(
+                &format!("deposits[{}].merkleProof", i),
+                &deposit.merkle_proof,
+            );
+            issues.transaction(&format!("deposits[{}].bitTxInfo", i), &deposit.bit_tx_info);
+        }
+        let depth = (DEFAULT_CONFIRMATIONS, "the default confirmation depth");
+        issues.chain("chains", &self.chains, Some(depth));
+        if let Some(hash) = &self.checkpoint_hash {
+            issues.hex("checkpointHash", hash, Some(32));
+        }
+        if let Some(key) = &self.vault_public_key {
+            issues.hex("vaultPublicKey", key, Some(32));
+        }
+        issues.network(self.network.as_deref());
+    }
+}
+
+impl InputSchema for BurnBatchBundleStruct {
+    fn check(&self, issues: &mut Issues) {
+        if self.payouts.is_empty() {
+            issues.add("payouts", "no payouts");
+        }
+        issues.merkle_proof("merkleProof", &self.merkle_proof);
+        let depth = (DEFAULT_CONFIRMATIONS, "the default confirmation depth");
+        issues.chain("chains", &self.chains, Some(depth));
+        issues.transaction("bitTxInfo", &self.bit_tx_info);
+        for (i, tx) in self.spent_prevouts.iter().flatten().enumerate() {
+            issues.transaction(&format!("spentPrevouts[{}]", i), tx);
+        }
+        if let Some(hash) = &self.checkpoint_hash {
+            issues.hex("checkpointHash", hash, Some(32));
+        }
+        if let Some(key) = &self.vault_public_key {
+            issues.hex("vaultPublicKey", key, Some(32));
+        }
+        issues.network(self.network.as_deref());
+    }
+}
+
+/// Checks a parsed input against its schema, returning one `field: problem` line per issue.
+pub fn check_input<T: InputSchema>(input: &T) -> Result<(), Vec<String>> {
+    let mut issues = Issues::default();
+    input.check(&mut issues);
+    match issues.0.is_empty() {
+        true => Ok(()),
+        false => Err(issues
+            .0
+            .into_iter()
+            .map(|(field, problem)| format!("{}: {}", field, problem))
+            .collect()),
+    }
+}
+
+/// Reads a circuit input from a JSON file and checks it against its schema.
+pub fn read_input<T: InputSchema>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let deserializer = &mut serde_json::Deserializer::from_str(&json);
+    let input: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        // serde_json appends the position to its message; it is reported up front instead
+        let message = e.inner().to_string();
+        let message = message.split(" at line ").next().unwrap_or_default();
+        format!(
+            "{}:{}:{}: {} (at `{}`)",
+            path.display(),
+            e.inner().line(),
+            e.inner().column(),
+            message,
+            e.path()
+        )
+    })?;
+    check_input(&input).map_err(|issues| {
+        format!(
+            "{} does not match the input schema:\n  {}",
+            path.display(),
+            issues.join("\n  ")
+        )
+    })?;
+    Ok(input)
+}
@@ -0,0 +1,268 @@
+//! Persistent job queue of the relayer pipeline: the work a service takes off the job bus is
+//! kept in a sled database until it is done, so an attempt failing on a transient error, e.g. an
+//! RPC or prover-network outage, is retried with exponential backoff instead of being dropped
+//! with the event. A job that fails `--retry-max-attempts` times, or fails in a way retrying
+//! cannot fix, moves to the dead letters, where `bridgectl queue` lists it and `bridgectl
+//! requeue` puts it back.
+//!
+//! `serve --bus` keeps its prove requests in one, and `submit --bus` its settlements.
+
+use bridge_core::now;
+use clap::Args;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sled::transaction::{ConflictableTransactionError, Transactional};
+use std::error::Error;
+use std::path::Path;
+
+/// How failed jobs are retried.
+#[derive(Args, Clone, Debug)]
+pub struct RetryArgs {
+    /// Attempts a job of the queue gets before it is dead-lettered.
+    #[clap(long, env = "JOB_MAX_ATTEMPTS", default_value_t = 8)]
+    pub retry_max_attempts: u32,
+    /// Seconds before a failed job is retried the first time; every further retry waits twice
+    /// as long.
+    #[clap(long, env = "JOB_RETRY_BASE_SECS", default_value_t = 30)]
+    pub retry_base_secs: u64,
+    /// Longest wait before a retry, in seconds.
+    #[clap(long, env = "JOB_RETRY_MAX_SECS", default_value_t = 3600)]
+    pub retry_max_secs: u64,
+}
+
+impl RetryArgs {
+    /// Seconds to wait after a job's `attempts`-th attempt failed.
+    pub fn delay(&self, attempts: u32) -> u64 {
+        let doublings = attempts.saturating_sub(1).min(63);
+        self.retry_base_secs
+            .saturating_mul(1 << doublings)
+            .min(self.retry_max_secs)
+    }
+}
+
+/// A unit of work of the queue.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct QueuedJob {
+    pub id: u64,
+    /// What the job does, e.g. `prove-mint` or `settle-burn`.
+    pub kind: String,
+    pub swap_id: String,
+    /// What the service needs to run the job, e.g. the bundle to prove.
+    pub payload: Value,
+    /// Attempts started so far.
+    pub attempts: u32,
+    pub enqueued_at: u64,
+    /// Unix time from which the job is due.
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    /// Handed to a worker and not finished; cleared when the queue is opened again, as that
+    /// worker is gone.
+    #[serde(default)]
+    pub running: bool,
+    /// Unix time the job was dead-lettered, for a dead letter.
+    #[serde(default)]
+    pub dead_at: Option<u64>,
+}
+
+impl QueuedJob {
+    /// The payload, as the type the service queued.
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, Box<dyn Error>> {
+        serde_json::from_value(self.payload.clone())
+            .map_err(|e| format!("Payload of job {} is corrupt: {}", self.id, e).into())
+    }
+}
+
+/// What became of a failed job.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Retry {
+    /// Due again at this Unix time.
+    At(u64),
+    /// Out of attempts, moved to the dead letters.
+    DeadLettered,
+}
+
+pub struct JobQueue {
+    db: sled::Db,
+    /// Jobs to run, keyed by big-endian id, so they iterate in the order they were queued.
+    jobs: sled::Tree,
+    /// Jobs given up on, under the same keys.
+    dead: sled::Tree,
+}
+
+fn decode(bytes: &[u8]) -> Result<QueuedJob, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+impl JobQueue {
+    /// Opens the queue at `path`, creating it if needed; without a path the queue is temporary
+    /// and dropped on exit. Jobs a previous run left running are due again.
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let config = match path {
+            Some(path) => sled::Config::new().path(path),
+            None => sled::Config::new().temporary(true),
+        };
+        let db = config.open()?;
+        let queue = JobQueue {
+            jobs: db.open_tree("jobs")?,
+            dead: db.open_tree("dead_letters")?,
+            db,
+        };
+        for mut job in queue.jobs()? {
+            if job.running {
+                job.running = false;
+                queue.put(&job)?;
+            }
+        }
+        Ok(queue)
+    }
+
+    fn put(&self, job: &QueuedJob) -> Result<(), Box<dyn Error>> {
+        self.jobs
+            .insert(job.id.to_be_bytes(), serde_json::to_vec(job)?)?;
+        Ok(())
+    }
+
+    fn get(&self, id: u64) -> Result<QueuedJob, Box<dyn Error>> {
+        let bytes = self
+            .jobs
+            .get(id.to_be_bytes())?
+            .ok_or_else(|| format!("No job {} in the queue", id))?;
+        decode(&bytes)
+    }
+
+    /// Moves `job`, written as it is, from the tree `from` to `to`, in one transaction.
+    fn transfer(
+        &self,
+        job: &QueuedJob,
+        from: &sled::Tree,
+        to: &sled::Tree,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = job.id.to_be_bytes();
+        let bytes = serde_json::to_vec(job)?;
+        (from, to).transaction(|(from, to)| {
+            from.remove(&key)?;
+            to.insert(&key, bytes.as_slice())?;
+            Ok::<_, ConflictableTransactionError<sled::Error>>(())
+        })?;
+        Ok(())
+    }
+
+    /// Queues a job, due at once, and writes it to disk; the event it came from can be
+    /// acknowledged once this returns.
+    pub fn push(
+        &self,
+        kind: &str,
+        swap_id: &str,
+        payload: Value,
+    ) -> Result<QueuedJob, Box<dyn Error>> {
+        let at = now();
+        let job = QueuedJob {
+            id: self.db.generate_id()?,
+            kind: kind.to_string(),
+            swap_id: swap_id.to_string(),
+            payload,
+            attempts: 0,
+            enqueued_at: at,
+            next_attempt_at: at,
+            last_error: None,
+            running: false,
+            dead_at: None,
+        };
+        self.put(&job)?;
+        self.db.flush()?;
+        Ok(job)
+    }
+
+    /// Takes the job due the longest, counting an attempt and marking it running until it is
+    /// completed or failed.
+    pub fn next_due(&self) -> Result<Option<QueuedJob>, Box<dyn Error>> {
+        let at = now();
+        let due = self
+            .jobs()?
+            .into_iter()
+            .filter(|job| !job.running && job.next_attempt_at <= at)
+            .min_by_key(|job| job.next_attempt_at);
+        let Some(mut job) = due else {
+            return Ok(None);
+        };
+        job.attempts += 1;
+        job.running = true;
+        self.put(&job)?;
+        Ok(Some(job))
+    }
+
+    /// Drops a job that is done.
+    pub fn complete(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        self.jobs.remove(id.to_be_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Records a failed attempt: the job is due again after its backoff, or dead-lettered once
+    /// it has had `--retry-max-attempts`.
+    pub fn fail(&self, id: u64, error: &str, retry: &RetryArgs) -> Result<Retry, Box<dyn Error>> {
+        let job = self.get(id)?;
+        if job.attempts >= retry.retry_max_attempts {
+            self.dead_letter(id, error)?;
+            return Ok(Retry::DeadLettered);
+        }
+        let at = now() + retry.delay(job.attempts);
+        self.put(&QueuedJob {
+            next_attempt_at: at,
+            last_error: Some(error.to_string()),
+            running: false,
+            ..job
+        })?;
+        self.db.flush()?;
+        Ok(Retry::At(at))
+    }
+
+    /// Moves a job to the dead letters at once, for a failure retrying cannot fix.
+    pub fn dead_letter(&self, id: u64, error: &str) -> Result<(), Box<dyn Error>> {
+        let job = QueuedJob {
+            last_error: Some(error.to_string()),
+            running: false,
+            dead_at: Some(now()),
+            ..self.get(id)?
+        };
+        self.transfer(&job, &self.jobs, &self.dead)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Puts dead letter `id` back in the queue, due at once with its attempts reset.
+    pub fn requeue(&self, id: u64) -> Result<QueuedJob, Box<dyn Error>> {
+        let bytes = self
+            .dead
+            .get(id.to_be_bytes())?
+            .ok_or_else(|| format!("No dead letter {}", id))?;
+        let job = QueuedJob {
+            attempts: 0,
+            next_attempt_at: now(),
+            dead_at: None,
+            ..decode(&bytes)?
+        };
+        self.transfer(&job, &self.dead, &self.jobs)?;
+        self.db.flush()?;
+        Ok(job)
+    }
+
+    /// Every job to run, in the order they were queued.
+    pub fn jobs(&self) -> Result<Vec<QueuedJob>, Box<dyn Error>> {
+        self.jobs
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Every dead letter, in the order they were queued.
+    pub fn dead_letters(&self) -> Result<Vec<QueuedJob>, Box<dyn Error>> {
+        self.dead
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+}
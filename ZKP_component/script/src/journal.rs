@@ -0,0 +1,166 @@
+//! Journal of the proving jobs in flight. Every phase change is written to disk, and SIGINT or
+//! SIGTERM only exits once no write is pending, so a container restart or a deploy leaves a
+//! journal that `--resume-journal` picks the interrupted jobs up from.
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::warn;
+
+/// Exit code of a process stopped by a signal, as a shell reports SIGINT.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Phase a journaled job was in when it was last recorded.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPhase {
+    /// Accepted, waiting for the prover.
+    Queued,
+    Executing,
+    Proving,
+}
+
+/// A job that started but has not finished.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct JournalEntry {
+    /// Job key: the input hash in `evm`, the job id in `serve`.
+    pub job: String,
+    /// Hash of the circuit input.
+    pub input_hash: String,
+    pub circuit: String,
+    pub proof_mode: String,
+    pub phase: JobPhase,
+    /// Input file of an `evm` job; `None` proves the mock bundle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<PathBuf>,
+    /// Name of the fixture or proof file an `evm` job writes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Bundle posted to `serve`, kept so the job can be queued again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<serde_json::Value>,
+    /// Prover network request of the proof, once submitted; resuming waits for it instead of
+    /// paying for a new proof.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<B256>,
+}
+
+/// Journal file shared by the prover threads and the signal handler.
+pub struct Journal {
+    path: PathBuf,
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+/// Default journal of `binary`, next to the proving rate history under `script/`.
+pub fn default_path(binary: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(".proving-journal")
+        .join(format!("{}.json", binary))
+}
+
+impl Journal {
+    /// Opens the journal at `path`, with the jobs a previous run left in it.
+    pub fn open(path: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("journal {} is corrupt: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("journal {}: {}", path.display(), e).into()),
+        };
+        Ok(Journal {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Jobs recorded and not finished yet, oldest first.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.lock().clone()
+    }
+
+    pub fn get(&self, job: &str) -> Option<JournalEntry> {
+        self.lock().iter().find(|entry| entry.job == job).cloned()
+    }
+
+    /// Records a job, replacing an earlier entry with the same key but keeping its network
+    /// request, which stays valid for the same input.
+    pub fn record(&self, mut entry: JournalEntry) {
+        let mut entries = self.lock();
+        match entries.iter_mut().find(|known| known.job == entry.job) {
+            Some(known) => {
+                entry.request_id = entry.request_id.or(known.request_id);
+                *known = entry;
+            }
+            None => entries.push(entry),
+        }
+        self.save(&entries);
+    }
+
+    pub fn set_phase(&self, job: &str, phase: JobPhase) {
+        self.update(job, |entry| entry.phase = phase);
+    }
+
+    pub fn set_request_id(&self, job: &str, request_id: B256) {
+        self.update(job, |entry| entry.request_id = Some(request_id));
+    }
+
+    /// Drops a job that finished, whether it succeeded or failed.
+    pub fn remove(&self, job: &str) {
+        let mut entries = self.lock();
+        entries.retain(|entry| entry.job != job);
+        self.save(&entries);
+    }
+
+    fn update(&self, job: &str, change: impl FnOnce(&mut JournalEntry)) {
+        let mut entries = self.lock();
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.job == job) {
+            change(entry);
+            self.save(&entries);
+        }
+    }
+
+    /// A panicking prover thread must not keep the other threads from journaling.
+    fn lock(&self) -> MutexGuard<'_, Vec<JournalEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Writes the entries through a temporary file, so a killed process never leaves a
+    /// truncated journal. A failed write is logged rather than failing the proof.
+    fn save(&self, entries: &[JournalEntry]) {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            std::fs::write(&tmp, serde_json::to_string_pretty(entries)?)?;
+            std::fs::rename(&tmp, &self.path)
+        };
+        if let Err(e) = write() {
+            warn!(error = %e, journal = %self.path.display(), "Failed to write the journal");
+        }
+    }
+}
+
+/// Exits on SIGINT or SIGTERM once no journal write is in progress, logging how to resume the
+/// jobs left in the journal.
+pub fn exit_on_signal(journal: Arc<Journal>) {
+    let handler = ctrlc::set_handler(move || {
+        // Holding the lock until exit keeps a prover thread from starting another write
+        let entries = journal.lock();
+        warn!(
+            jobs = entries.len(),
+            journal = %journal.path.display(),
+            "Interrupted; rerun with --resume-journal to continue the journaled jobs"
+        );
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    });
+    if let Err(e) = handler {
+        warn!(error = %e, "Cannot handle SIGINT and SIGTERM; jobs are journaled up to their last phase");
+    }
+}
@@ -0,0 +1,600 @@
+//! Helpers shared by the script binaries.
+
+pub mod attestation;
+pub mod bundle;
+pub mod bus;
+pub mod config;
+pub mod deposit_address;
+pub mod fees;
+pub mod header_chain;
+pub mod input;
+pub mod job_queue;
+pub mod journal;
+pub mod native;
+pub mod pause;
+pub mod payout;
+pub mod policy;
+pub mod profile;
+pub mod relayer;
+pub mod reorg;
+pub mod store;
+pub mod telemetry;
+pub mod watchtower;
+
+use alloy_primitives::FixedBytes;
+use alloy_sol_types::SolType;
+use bitcoin::network::Network;
+use clap::ValueEnum;
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::DEFAULT_NETWORK;
+use lib_struct::{
+    ZkpBurnAggregationPublicValuesStruct, ZkpBurnBatchPublicValuesStruct,
+    ZkpBurnPublicValuesStruct, ZkpMintAggregationPublicValuesStruct,
+    ZkpMintBatchPublicValuesStruct, ZkpMintPublicValuesStruct,
+};
+use sp1_sdk::{EnvProver, ProverClient};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{info, warn, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Seconds between two progress events of a running proof.
+pub const PROGRESS_INTERVAL_SECS: u64 = 60;
+
+/// Enum representing the log formats selectable with `--log-format`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum LogFormat {
+    /// Human-readable lines, as printed by the SP1 logger.
+    Text,
+    /// One JSON object per event, with the fields of its enclosing spans, for log pipelines.
+    Json,
+}
+
+/// Installs the global tracing subscriber in place of `sp1_sdk::utils::setup_logger`. The
+/// level comes from `RUST_LOG` (default `info`). Logs go to stderr, so stdout only carries
+/// command output such as `evm --stdout` fixtures. Closed spans are logged with their
+/// duration, which gives the time of each stage (bundle parse, execute, prove, verify,
+/// write-fixture). With `OTEL_EXPORTER_OTLP_ENDPOINT` set, the spans are also exported
+/// ([`telemetry`]).
+pub fn setup_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE);
+    let fmt = match format {
+        LogFormat::Text => fmt.boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(fmt)
+        .with(telemetry::otlp_layer())
+        .with(filter)
+        .init();
+}
+
+/// Enum representing the SP1 prover backends selectable with `--prover`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ProverBackend {
+    /// Skips proving and returns mock proofs, for end-to-end tests of the relayer pipeline.
+    Mock,
+    /// Local CPU proving; `local` is accepted as in `.env.example`.
+    #[value(alias = "local")]
+    Cpu,
+    /// Local GPU proving; needs a build with `--features cuda`, else it falls back to the CPU.
+    Cuda,
+    Network,
+}
+
+impl ProverBackend {
+    /// Backend that will actually prove: `Cuda` falls back to `Cpu`, with a warning, when this
+    /// build or machine cannot run the GPU prover. Called once at startup, before `client`,
+    /// so that logs and metrics name the backend in use.
+    pub fn resolve(self) -> Self {
+        if self != ProverBackend::Cuda {
+            return self;
+        }
+        match cuda_capability() {
+            Ok(gpu) => {
+                info!(%gpu, "CUDA prover available");
+                self
+            }
+            Err(reason) => {
+                warn!(%reason, "CUDA prover unavailable, falling back to the CPU prover");
+                ProverBackend::Cpu
+            }
+        }
+    }
+
+    /// Builds a prover client for this backend. `SP1_PROVER` is set to the selected backend
+    /// because that is what `ProverClient::from_env` dispatches on.
+    pub fn client(self) -> EnvProver {
+        let name = match self {
+            ProverBackend::Mock => "mock",
+            ProverBackend::Cpu => "cpu",
+            ProverBackend::Cuda => "cuda",
+            ProverBackend::Network => "network",
+        };
+        std::env::set_var("SP1_PROVER", name);
+        ProverClient::from_env()
+    }
+}
+
+/// Enum representing the source chains selectable with `--network`: the Bitcoin networks and
+/// Litecoin's.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum BitcoinNetwork {
+    /// `bitcoin` is accepted as in `bridge.toml` and the Bitcoin Core `-chain` names.
+    #[value(alias = "bitcoin")]
+    Mainnet,
+    Testnet,
+    Testnet4,
+    Signet,
+    Regtest,
+    Litecoin,
+    LitecoinTestnet,
+    LitecoinRegtest,
+}
+
+impl BitcoinNetwork {
+    pub fn chain(self) -> ChainParams {
+        match self {
+            BitcoinNetwork::Mainnet => ChainParams::bitcoin(Network::Bitcoin),
+            BitcoinNetwork::Testnet => ChainParams::bitcoin(Network::Testnet),
+            BitcoinNetwork::Testnet4 => ChainParams::bitcoin(Network::Testnet4),
+            BitcoinNetwork::Signet => ChainParams::bitcoin(Network::Signet),
+            BitcoinNetwork::Regtest => ChainParams::bitcoin(Network::Regtest),
+            BitcoinNetwork::Litecoin => ChainParams::litecoin(),
+            BitcoinNetwork::LitecoinTestnet => ChainParams::litecoin_testnet(),
+            BitcoinNetwork::LitecoinRegtest => ChainParams::litecoin_regtest(),
+        }
+    }
+
+    /// Name the circuits read from the bundle's `network` field.
+    pub fn bundle_name(self) -> String {
+        self.chain().name
+    }
+}
+
+/// Writes `--network`, when given, into a bundle's `network` field, and logs the network the
+/// circuit will verify the bundle on.
+pub fn apply_network(flag: Option<BitcoinNetwork>, network: &mut Option<String>) {
+    if let Some(flag) = flag {
+        *network = Some(flag.bundle_name());
+    }
+    match network {
+        Some(network) => info!(%network, "Bitcoin network"),
+        None => info!(network = %DEFAULT_NETWORK, "Bitcoin network (bundle default)"),
+    }
+}
+
+/// Checks that the GPU prover can start and returns the name of the GPU. SP1 runs the CUDA
+/// prover in a Docker container, so besides the `cuda` feature this needs a GPU visible to
+/// `nvidia-smi` and a running Docker daemon.
+fn cuda_capability() -> Result<String, String> {
+    if !cfg!(feature = "cuda") {
+        return Err("built without the `cuda` feature; rebuild with `--features cuda`".into());
+    }
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=name", "--format=csv,noheader"])
+        .output()
+        .map_err(|e| format!("nvidia-smi cannot run: {}", e))?;
+    let gpu = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .map(str::to_string);
+    let gpu = match gpu {
+        Some(gpu) if output.status.success() => gpu,
+        _ => return Err("nvidia-smi lists no GPU".into()),
+    };
+    let docker = Command::new("docker")
+        .arg("info")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("docker cannot run: {}", e))?;
+    if !docker.success() {
+        return Err("the Docker daemon is not running".into());
+    }
+    Ok(gpu)
+}
+
+/// Logs a progress event every `PROGRESS_INTERVAL_SECS` while a proof runs, so a slow prover
+/// can be told apart from a hung one. The SDK does not expose SP1's shard progress, so the ETA
+/// assumes the cycles-per-second rate of the last proof finished with the same label, kept
+/// under `script/.proving-stats`. The events stop when the value is dropped.
+pub struct ProvingProgress {
+    started: Instant,
+    cycles: u64,
+    rate_path: PathBuf,
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ProvingProgress {
+    /// Starts reporting a proof of an execution of `cycles` cycles. `label` names the prover
+    /// setup the rate is measured for, e.g. `cpu-groth16`.
+    pub fn start(label: &str, cycles: u64) -> Self {
+        let rate_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join(".proving-stats")
+            .join(format!("{}.rate", label));
+        let cycles_per_sec = std::fs::read_to_string(&rate_path)
+            .ok()
+            .and_then(|rate| rate.trim().parse::<f64>().ok())
+            .filter(|rate| *rate > 0.0);
+        let estimate_secs = cycles_per_sec.map(|rate| (cycles as f64 / rate) as u64);
+        info!(cycles, estimate_secs, "Proving started");
+
+        let started = Instant::now();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let span = Span::current();
+        let handle = std::thread::spawn(move || {
+            let _entered = span.enter();
+            // Dropping the sender disconnects the channel and ends the loop
+            while let Err(RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(Duration::from_secs(PROGRESS_INTERVAL_SECS))
+            {
+                let elapsed_secs = started.elapsed().as_secs();
+                let eta_secs = estimate_secs.map(|estimate| estimate.saturating_sub(elapsed_secs));
+                info!(elapsed_secs, cycles, eta_secs, "Proving in progress");
+            }
+        });
+        ProvingProgress {
+            started,
+            cycles,
+            rate_path,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops reporting and records the rate of the finished proof for later ETAs.
+    pub fn finish(self) {
+        let elapsed = self.started.elapsed();
+        info!(elapsed_secs = elapsed.as_secs(), "Proving finished");
+        if elapsed.as_secs_f64() > 0.0 {
+            let rate = self.cycles as f64 / elapsed.as_secs_f64();
+            if let Some(dir) = self.rate_path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = std::fs::write(&self.rate_path, rate.to_string());
+        }
+    }
+}
+
+impl Drop for ProvingProgress {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Committed network magic with the name of its chain, e.g. `0x0b110907 (testnet)`.
+fn network_magic_field(magic: &FixedBytes<4>) -> String {
+    match ChainParams::from_magic(magic.0) {
+        Some(chain) => format!("{:?} ({})", magic, chain),
+        None => format!("{:?} (unknown network)", magic),
+    }
+}
+
+/// Labelled fields of the public values committed by the mint circuit.
+pub fn mint_public_value_fields(
+    decoded: &ZkpMintPublicValuesStruct,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("tx_id", format!("{:?}", decoded.tx_id)),
+        (
+            "depositer eth address",
+            format!("{:?}", decoded.depositer_address),
+        ),
+        ("amount", format!("{:?}", decoded.amount)),
+        ("is valid or not", format!("{:?}", decoded.is_valid)),
+        ("min difficulty", format!("{:?}", decoded.min_difficulty)),
+        ("chain work", format!("{:?}", decoded.chain_work)),
+        ("reject reason", format!("{:?}", decoded.reject_reason)),
+        (
+            "min deposit sats",
+            format!("{:?}", decoded.min_deposit_sats),
+        ),
+        ("gross amount", format!("{:?}", decoded.gross_amount)),
+        ("fee bps", format!("{:?}", decoded.fee_bps)),
+        ("flat fee sats", format!("{:?}", decoded.flat_fee_sats)),
+        ("checkpoint hash", format!("{:?}", decoded.checkpoint_hash)),
+        (
+            "refund btc address",
+            format!("{:?}", decoded.refund_btc_address),
+        ),
+        (
+            "witness verified",
+            format!("{:?}", decoded.witness_verified),
+        ),
+        ("dest chain id", format!("{:?}", decoded.dest_chain_id)),
+        ("memo nonce", format!("{:?}", decoded.memo_nonce)),
+        (
+            "vault output count",
+            format!("{:?}", decoded.vault_output_count),
+        ),
+        (
+            "vault output indices",
+            format!("{:?}", decoded.vault_output_indices),
+        ),
+        ("tip timestamp", format!("{:?}", decoded.tip_timestamp)),
+        ("reference time", format!("{:?}", decoded.reference_time)),
+        ("request id", format!("{:?}", decoded.request_id)),
+        ("network magic", network_magic_field(&decoded.network_magic)),
+        (
+            "vault script hash",
+            format!("{:?}", decoded.vault_script_hash),
+        ),
+    ]
+}
+
+/// Decodes and prints a circuit's public values; errs when the bytes do not decode as them.
+pub type PrintPublicValues = fn(&[u8]) -> Result<(), Box<dyn Error>>;
+
+/// Decodes and prints the public values committed by the mint circuit.
+pub fn print_mint_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpMintPublicValuesStruct::abi_decode_params(bytes)
+        .map_err(|e| format!("Not valid mint public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in mint_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
+
+/// Labelled fields of the public values committed by the batch mint circuit; per-deposit
+/// labels are prefixed with the deposit's index.
+pub fn mint_batch_public_value_fields(
+    decoded: &ZkpMintBatchPublicValuesStruct,
+) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for (i, deposit) in decoded.deposits.iter().enumerate() {
+        let deposit_fields = [
+            ("tx_id", format!("{:?}", deposit.tx_id)),
+            (
+                "depositer eth address",
+                format!("{:?}", deposit.depositer_address),
+            ),
+            ("amount", format!("{:?}", deposit.amount)),
+            ("gross amount", format!("{:?}", deposit.gross_amount)),
+            ("is valid or not", format!("{:?}", deposit.is_valid)),
+            ("reject reason", format!("{:?}", deposit.reject_reason)),
+            ("dest chain id", format!("{:?}", deposit.dest_chain_id)),
+            ("memo nonce", format!("{:?}", deposit.memo_nonce)),
+            (
+                "vault output count",
+                format!("{:?}", deposit.vault_output_count),
+            ),
+            (
+                "vault output indices",
+                format!("{:?}", deposit.vault_output_indices),
+            ),
+            ("request id", format!("{:?}", deposit.request_id)),
+        ];
+        fields.extend(
+            deposit_fields
+                .into_iter()
+                .map(|(label, value)| (format!("deposit #{} {}", i, label), value)),
+        );
+    }
+    let batch_fields = [
+        ("min difficulty", format!("{:?}", decoded.min_difficulty)),
+        ("chain work", format!("{:?}", decoded.chain_work)),
+        (
+            "min deposit sats",
+            format!("{:?}", decoded.min_deposit_sats),
+        ),
+        ("fee bps", format!("{:?}", decoded.fee_bps)),
+        ("flat fee sats", format!("{:?}", decoded.flat_fee_sats)),
+        ("checkpoint hash", format!("{:?}", decoded.checkpoint_hash)),
+        ("tip timestamp", format!("{:?}", decoded.tip_timestamp)),
+        ("reference time", format!("{:?}", decoded.reference_time)),
+        ("network magic", network_magic_field(&decoded.network_magic)),
+        (
+            "vault script hash",
+            format!("{:?}", decoded.vault_script_hash),
+        ),
+    ];
+    fields.extend(
+        batch_fields
+            .into_iter()
+            .map(|(label, value)| (label.to_string(), value)),
+    );
+    fields
+}
+
+/// Decodes and prints the public values committed by the batch mint circuit.
+pub fn print_mint_batch_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpMintBatchPublicValuesStruct::abi_decode_params(bytes)
+        .map_err(|e| format!("Not valid batch mint public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in mint_batch_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
+
+/// Labelled fields of the public values committed by the burn circuit.
+pub fn burn_public_value_fields(
+    decoded: &ZkpBurnPublicValuesStruct,
+) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "Burner btc address",
+            format!("{:?}", decoded.burner_btc_address),
+        ),
+        ("amount", format!("{:?}", decoded.amount)),
+        ("is valid or not", format!("{:?}", decoded.is_valid)),
+        ("payout tx id", format!("{:?}", decoded.payout_tx_id)),
+        (
+            "vault script pubkey",
+            format!("{:?}", decoded.vault_script_pubkey),
+        ),
+        ("burn id", format!("{:?}", decoded.burn_id)),
+        ("reject reason", format!("{:?}", decoded.reject_reason)),
+        (
+            "expected payout sats",
+            format!("{:?}", decoded.expected_payout_sats),
+        ),
+        (
+            "payout tolerance sats",
+            format!("{:?}", decoded.payout_tolerance_sats),
+        ),
+        ("underpaid", format!("{:?}", decoded.underpaid)),
+        ("min difficulty", format!("{:?}", decoded.min_difficulty)),
+        ("chain work", format!("{:?}", decoded.chain_work)),
+        ("confirmations", format!("{:?}", decoded.confirmations)),
+        (
+            "vault public key",
+            format!("{:?}", decoded.vault_public_key),
+        ),
+        (
+            "vault change sats",
+            format!("{:?}", decoded.vault_change_sats),
+        ),
+        ("network magic", network_magic_field(&decoded.network_magic)),
+        (
+            "payout block height",
+            format!("{:?}", decoded.payout_block_height),
+        ),
+        ("rbf signaled", format!("{:?}", decoded.rbf_signaled)),
+        ("fee sats", format!("{:?}", decoded.fee_sats)),
+        ("max fee sats", format!("{:?}", decoded.max_fee_sats)),
+        ("checkpoint hash", format!("{:?}", decoded.checkpoint_hash)),
+        ("contested", format!("{:?}", decoded.contested)),
+        ("tagged", format!("{:?}", decoded.tagged)),
+    ]
+}
+
+/// Decodes and prints the public values committed by the burn circuit.
+pub fn print_burn_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpBurnPublicValuesStruct::abi_decode_params(bytes)
+        .map_err(|e| format!("Not valid burn public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in burn_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
+
+/// Labelled fields of the public values committed by the batch burn circuit; per-payout
+/// labels are prefixed with the payout's index.
+pub fn burn_batch_public_value_fields(
+    decoded: &ZkpBurnBatchPublicValuesStruct,
+) -> Vec<(String, String)> {
+    let mut fields = vec![
+        (
+            "payout tx id".to_string(),
+            format!("{:?}", decoded.payout_tx_id),
+        ),
+        (
+            "vault script pubkey".to_string(),
+            format!("{:?}", decoded.vault_script_pubkey),
+        ),
+    ];
+    for (i, payout) in decoded.payouts.iter().enumerate() {
+        let payout_fields = [
+            (
+                "burner btc address",
+                format!("{:?}", payout.burner_btc_address),
+            ),
+            ("amount", format!("{:?}", payout.amount)),
+            ("burn id", format!("{:?}", payout.burn_id)),
+            ("is valid or not", format!("{:?}", payout.is_valid)),
+            ("reject reason", format!("{:?}", payout.reject_reason)),
+            (
+                "expected payout sats",
+                format!("{:?}", payout.expected_payout_sats),
+            ),
+            ("underpaid", format!("{:?}", payout.underpaid)),
+        ];
+        fields.extend(
+            payout_fields
+                .into_iter()
+                .map(|(label, value)| (format!("payout #{} {}", i, label), value)),
+        );
+    }
+    fields.push((
+        "vault change sats".to_string(),
+        format!("{:?}", decoded.vault_change_sats),
+    ));
+    fields.push((
+        "network magic".to_string(),
+        network_magic_field(&decoded.network_magic),
+    ));
+    fields
+}
+
+/// Decodes and prints the public values committed by the batch burn circuit.
+pub fn print_burn_batch_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpBurnBatchPublicValuesStruct::abi_decode_params(bytes)
+        .map_err(|e| format!("Not valid batch burn public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in burn_batch_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
+
+/// Labelled fields of the public values committed by the mint aggregation circuit.
+pub fn mint_aggregation_public_value_fields(
+    decoded: &ZkpMintAggregationPublicValuesStruct,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("mint vkey hash", format!("{:?}", decoded.mint_vkey_hash)),
+        (
+            "public values root",
+            format!("{:?}", decoded.public_values_root),
+        ),
+        ("proof count", format!("{:?}", decoded.proof_count)),
+    ]
+}
+
+/// Decodes and prints the public values committed by the mint aggregation circuit.
+pub fn print_mint_aggregation_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpMintAggregationPublicValuesStruct::abi_decode(bytes)
+        .map_err(|e| format!("Not valid mint aggregation public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in mint_aggregation_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
+
+/// Labelled fields of the public values committed by the burn aggregation circuit.
+pub fn burn_aggregation_public_value_fields(
+    decoded: &ZkpBurnAggregationPublicValuesStruct,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("burn vkey hash", format!("{:?}", decoded.burn_vkey_hash)),
+        (
+            "public values root",
+            format!("{:?}", decoded.public_values_root),
+        ),
+        ("proof count", format!("{:?}", decoded.proof_count)),
+    ]
+}
+
+/// Decodes and prints the public values committed by the burn aggregation circuit.
+pub fn print_burn_aggregation_public_values(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let decoded = ZkpBurnAggregationPublicValuesStruct::abi_decode(bytes)
+        .map_err(|e| format!("Not valid burn aggregation public values: {}", e))?;
+    println!("-------------------------------------------");
+    for (label, value) in burn_aggregation_public_value_fields(&decoded) {
+        println!("{}: {}", label, value);
+    }
+    Ok(())
+}
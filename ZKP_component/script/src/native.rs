@@ -0,0 +1,646 @@
+//! Native model of the mint and burn circuits: the checks of `program/src/mint.rs` and
+//! `program/src/burn.rs`, run on the host in the same order, with the public values the
+//! circuit should commit. `preflight` reports the checks; `main --dry-run` diffs the public
+//! values against the circuit's, so host and circuit logic cannot drift apart unnoticed.
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_sol_types::SolType;
+use bitcoin::hash_types::{BlockHash, Txid};
+use bitcoin::hashes::Hash;
+use bitcoin::Transaction;
+use lib_struct::chain::parse_chain;
+use lib_struct::verify::{
+    check_merkle_proof_bounds, check_transaction_finality, compute_fee, decode_transaction,
+    extract_refund_address, find_unexpected_output, find_vault_input, parse_burn_tag,
+    parse_vault_key, request_deposit_script_pubkey, sum_outputs_to_script, vault_script_hash,
+    vault_script_pubkey, verify_chain_with_crate, verify_coinbase_height, verify_deposit,
+    verify_tx_inclusion_str, verify_vault_key_spend, verify_witness_commitment, ChainSummary,
+    DEFAULT_CONFIRMATIONS,
+};
+use lib_struct::{
+    BundleInfoStruct, BurnRejectReason, MintRejectReason, ZkpBurnPublicValuesStruct,
+    ZkpMintPublicValuesStruct,
+};
+use std::fmt::Display;
+
+/// How a failed check surfaces in the circuit: it commits `is_valid = false` with this
+/// reason, so a proof is generated, but the contract refuses to settle it.
+pub struct Rejection {
+    pub reason: String,
+    pub detail: String,
+}
+
+/// Outcome of running a circuit's checks natively.
+pub struct NativeRun {
+    /// Checks that passed, in circuit order, with what they established.
+    pub passed: Vec<(&'static str, String)>,
+    /// The check that failed, if any, and how.
+    pub failure: Option<(&'static str, Rejection)>,
+    /// ABI-encoded public values the circuit should commit.
+    pub public_values: Vec<u8>,
+}
+
+/// A failed check with the circuit's typed reject reason.
+type CheckError<R> = (&'static str, R, String);
+
+fn reject<R>(check: &'static str, reason: R, detail: impl Display) -> CheckError<R> {
+    (check, reason, detail.to_string())
+}
+
+/// Checks that passed so far.
+#[derive(Default)]
+struct Checks {
+    passed: Vec<(&'static str, String)>,
+}
+
+impl Checks {
+    fn pass(&mut self, check: &'static str, detail: impl Into<String>) {
+        self.passed.push((check, detail.into()));
+    }
+}
+
+/// Converts a txid into the `bytes32` committed on-chain (display byte order).
+fn txid_to_bytes32(txid: Txid) -> FixedBytes<32> {
+    txid.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
+}
+
+/// Converts a block hash into the `bytes32` committed on-chain (display byte order).
+fn block_hash_to_bytes32(hash: BlockHash) -> FixedBytes<32> {
+    hash.to_string().as_str().parse::<FixedBytes<32>>().unwrap()
+}
+
+/// What a mint rejection commits besides its reason, as known when the check fails.
+#[derive(Default)]
+struct MintContext {
+    txid: Option<Txid>,
+    refund_btc_address: String,
+    /// Summary of the verified chain, committed by rejections that follow it.
+    chain_summary: Option<ChainSummary>,
+}
+
+/// Runs the checks of the mint circuit and derives the public values it should commit.
+pub fn run_mint(bundle: &BundleInfoStruct) -> NativeRun {
+    let mut checks = Checks::default();
+    let mut context = MintContext::default();
+    let result = mint_checks(bundle, &mut checks, &mut context);
+    let (failure, public_values) = match result {
+        Ok(values) => (None, values),
+        Err((check, reason, detail)) => {
+            let policy = &bundle.mint_policy;
+            // An undecodable transaction commits a zero txid, an unknown chain a zero magic
+            let magic = parse_chain(bundle.network.as_deref()).map_or([0; 4], |chain| chain.magic);
+            let summary = context.chain_summary.as_ref();
+            let values = ZkpMintPublicValuesStruct {
+                tx_id: txid_to_bytes32(context.txid.unwrap_or(Txid::all_zeros())),
+                depositer_address: Address::ZERO,
+                amount: U256::ZERO,
+                is_valid: false,
+                min_difficulty: summary
+                    .map_or(U256::ZERO, |summary| U256::from(summary.min_difficulty)),
+                chain_work: summary.map_or(U256::ZERO, |summary| {
+                    U256::from_be_bytes(summary.total_work.to_be_bytes())
+                }),
+                reject_reason: reason as u8,
+                min_deposit_sats: U256::from(policy.min_deposit_sats),
+                gross_amount: U256::ZERO,
+                fee_bps: U256::from(policy.fee_bps),
+                flat_fee_sats: U256::from(policy.flat_fee_sats),
+                checkpoint_hash: summary.map_or(FixedBytes::ZERO, |summary| {
+                    block_hash_to_bytes32(summary.anchor_hash)
+                }),
+                refund_btc_address: context.refund_btc_address,
+                witness_verified: false,
+                dest_chain_id: U256::ZERO,
+                memo_nonce: U256::ZERO,
+                vault_output_count: U256::ZERO,
+                tip_timestamp: summary
+                    .map_or(U256::ZERO, |summary| U256::from(summary.tip_timestamp)),
+                reference_time: U256::from(bundle.reference_time.unwrap_or(0)),
+                request_id: U256::ZERO,
+                vault_output_indices: Vec::new(),
+                network_magic: FixedBytes::from(magic),
+                vault_script_hash: parse_vault_key(bundle.vault_public_key.as_deref())
+                    .map_or(FixedBytes::ZERO, |group_key| vault_script_hash(&group_key)),
+            };
+            let failure = Rejection {
+                reason: format!("{:?}", reason),
+                detail,
+            };
+            (Some((check, failure)), values)
+        }
+    };
+    NativeRun {
+        passed: checks.passed,
+        failure,
+        public_values: ZkpMintPublicValuesStruct::abi_encode_params(&public_values),
+    }
+}
+
+fn mint_checks(
+    bundle: &BundleInfoStruct,
+    checks: &mut Checks,
+    context: &mut MintContext,
+) -> Result<ZkpMintPublicValuesStruct, CheckError<MintRejectReason>> {
+    let tx = decode_transaction(&bundle.bit_tx_info.raw_tx_hex)
+        .map_err(|e| reject("parse_tx", MintRejectReason::InvalidTransaction, e))?;
+    let txid = tx.compute_txid();
+    context.txid = Some(txid);
+    checks.pass("parse_tx", format!("txid {}", txid));
+
+    let chain = parse_chain(bundle.network.as_deref())
+        .map_err(|e| reject("network", MintRejectReason::UnknownChain, e))?;
+    checks.pass("network", chain.to_string());
+
+    // A refund address that cannot be proven is committed empty, never rejected
+    if let Some(prevout_tx_info) = bundle.prevout_tx_info.as_ref() {
+        match decode_transaction(&prevout_tx_info.raw_tx_hex)
+            .and_then(|prev_tx| extract_refund_address(&tx, &prev_tx, &chain))
+        {
+            Ok(address) => {
+                checks.pass("refund_address", address.clone());
+                context.refund_btc_address = address;
+            }
+            Err(e) => checks.pass("refund_address", format!("not proven ({}), left empty", e)),
+        }
+    }
+
+    let summary = verify_chain_with_crate(
+        &bundle.chains,
+        DEFAULT_CONFIRMATIONS,
+        bundle.retarget_info.as_ref(),
+        bundle.checkpoint_hash.as_deref(),
+        bundle.reference_time,
+        &chain,
+    )
+    .map_err(|e| reject("verify_chain", MintRejectReason::InvalidChain, e))?;
+    context.chain_summary = Some(summary);
+    checks.pass(
+        "verify_chain",
+        format!(
+            "minimum difficulty {}, cumulative work {:#x}",
+            summary.min_difficulty, summary.total_work
+        ),
+    );
+
+    let group_key = parse_vault_key(bundle.vault_public_key.as_deref())
+        .map_err(|e| reject("vault_key", MintRejectReason::InvalidVaultKey, e))?;
+    checks.pass("vault_key", group_key.to_string());
+
+    let (deposit_spk, request_id) = match bundle.deposit_request.as_ref() {
+        Some(request) => {
+            let expected_spk = request_deposit_script_pubkey(&group_key, request.request_id);
+            if expected_spk.to_hex_string() != request.script_pubkey.to_lowercase() {
+                return Err(reject(
+                    "deposit_script",
+                    MintRejectReason::DepositScriptMismatch,
+                    format!(
+                        "Script {} is not the deposit script {} of request {}",
+                        request.script_pubkey,
+                        expected_spk.to_hex_string(),
+                        request.request_id
+                    ),
+                ));
+            }
+            (expected_spk, Some(request.request_id))
+        }
+        // Memo deposits pay the vault itself, whose script is the same on every network
+        None => (vault_script_pubkey(&group_key), None),
+    };
+    checks.pass("deposit_script", deposit_spk.to_hex_string());
+
+    check_transaction_finality(&tx, bundle.block_height, bundle.chains.blocks[0].timestamp)
+        .map_err(|e| reject("finality", MintRejectReason::NonFinalTransaction, e))?;
+    checks.pass("finality", "final in its block");
+
+    let outcome = verify_deposit(
+        &tx,
+        &bundle.merkle_proof,
+        &bundle.chains.blocks[0].merkle_root,
+        bundle.tx_count,
+        &deposit_spk,
+        request_id,
+        &bundle.mint_policy,
+    )
+    .map_err(|e| reject("verify_deposit", MintRejectReason::InvalidDeposit, e))?;
+    if !outcome.is_valid() {
+        return Err(reject(
+            "verify_deposit",
+            outcome.reject_reason,
+            outcome.reject_detail,
+        ));
+    }
+    checks.pass(
+        "verify_deposit",
+        format!(
+            "{} sats ({} after fees) to {} in outputs {:?}",
+            outcome.amount_sats, outcome.net_amount_sats, outcome.recipient, outcome.output_indices
+        ),
+    );
+
+    let witness_verified = match bundle.witness_proof.as_ref() {
+        Some(witness_proof) => {
+            let result = decode_transaction(&witness_proof.coinbase_tx_info.raw_tx_hex).and_then(
+                |coinbase_tx| {
+                    verify_witness_commitment(
+                        &tx,
+                        bundle.merkle_proof.pos,
+                        &witness_proof.wtxid_merkle_proof,
+                        &coinbase_tx,
+                        &witness_proof.coinbase_merkle_proof,
+                        &bundle.chains.blocks[0].merkle_root,
+                    )
+                },
+            );
+            match result {
+                Ok(true) => checks.pass("witness_commitment", "witness data committed"),
+                Ok(false) => {
+                    return Err(reject(
+                        "witness_commitment",
+                        MintRejectReason::WitnessCommitmentMismatch,
+                        "Witness commitment mismatch",
+                    ))
+                }
+                Err(e) => {
+                    return Err(reject(
+                        "witness_commitment",
+                        MintRejectReason::WitnessCommitmentMismatch,
+                        e,
+                    ))
+                }
+            }
+            true
+        }
+        None => false,
+    };
+
+    Ok(ZkpMintPublicValuesStruct {
+        tx_id: txid_to_bytes32(txid),
+        depositer_address: outcome.recipient,
+        amount: U256::from(outcome.net_amount_sats),
+        is_valid: true,
+        min_difficulty: U256::from(summary.min_difficulty),
+        chain_work: U256::from_be_bytes(summary.total_work.to_be_bytes()),
+        reject_reason: MintRejectReason::None as u8,
+        min_deposit_sats: U256::from(bundle.mint_policy.min_deposit_sats),
+        gross_amount: U256::from(outcome.amount_sats),
+        fee_bps: U256::from(bundle.mint_policy.fee_bps),
+        flat_fee_sats: U256::from(bundle.mint_policy.flat_fee_sats),
+        checkpoint_hash: block_hash_to_bytes32(summary.anchor_hash),
+        refund_btc_address: context.refund_btc_address.clone(),
+        witness_verified,
+        dest_chain_id: U256::from(outcome.chain_id),
+        memo_nonce: U256::from(outcome.nonce),
+        vault_output_count: U256::from(outcome.output_indices.len()),
+        tip_timestamp: U256::from(summary.tip_timestamp),
+        reference_time: U256::from(bundle.reference_time.unwrap_or(0)),
+        request_id: U256::from(outcome.request_id),
+        vault_output_indices: outcome.output_indices,
+        network_magic: FixedBytes::from(chain.magic),
+        vault_script_hash: vault_script_hash(&group_key),
+    })
+}
+
+/// What a burn rejection commits besides its reason, as known when the check fails.
+#[derive(Default)]
+struct BurnContext {
+    txid: Option<Txid>,
+}
+
+/// Headers the burn circuit requires in `chains`, never fewer than the default depth.
+fn required_confirmations(bundle: &BundleInfoStruct) -> usize {
+    bundle
+        .confirmations
+        .map_or(DEFAULT_CONFIRMATIONS, |confirmations| {
+            (confirmations as usize).max(DEFAULT_CONFIRMATIONS)
+        })
+}
+
+/// Runs the checks of the burn circuit and derives the public values it should commit.
+pub fn run_burn(bundle: &BundleInfoStruct) -> NativeRun {
+    let mut checks = Checks::default();
+    let mut context = BurnContext::default();
+    let result = burn_checks(bundle, &mut checks, &mut context);
+    let (failure, public_values) = match result {
+        Ok(values) => (None, values),
+        Err((check, reason, detail)) => {
+            let burn_policy = &bundle.burn_policy;
+            // An undecodable transaction commits a zero txid, an unknown chain a zero magic
+            let magic = parse_chain(bundle.network.as_deref()).map_or([0; 4], |chain| chain.magic);
+            let values = ZkpBurnPublicValuesStruct {
+                burner_btc_address: bundle.burner_btc_address.clone().unwrap_or_default(),
+                amount: U256::ZERO,
+                is_valid: false,
+                payout_tx_id: txid_to_bytes32(context.txid.unwrap_or(Txid::all_zeros())),
+                vault_script_pubkey: Default::default(),
+                burn_id: U256::from(bundle.burn_id.unwrap_or_default()),
+                reject_reason: reason as u8,
+                expected_payout_sats: U256::from(burn_policy.expected_payout()),
+                payout_tolerance_sats: U256::from(burn_policy.tolerance_sats),
+                underpaid: false,
+                min_difficulty: U256::ZERO,
+                chain_work: U256::ZERO,
+                confirmations: U256::from(required_confirmations(bundle)),
+                vault_public_key: FixedBytes::ZERO,
+                vault_change_sats: U256::ZERO,
+                network_magic: FixedBytes::from(magic),
+                payout_block_height: U256::ZERO,
+                rbf_signaled: false,
+                fee_sats: U256::ZERO,
+                max_fee_sats: U256::from(burn_policy.max_fee_sats),
+                checkpoint_hash: FixedBytes::ZERO,
+                contested: false,
+                tagged: false,
+            };
+            let failure = Rejection {
+                reason: format!("{:?}", reason),
+                detail,
+            };
+            (Some((check, failure)), values)
+        }
+    };
+    NativeRun {
+        passed: checks.passed,
+        failure,
+        public_values: ZkpBurnPublicValuesStruct::abi_encode_params(&public_values),
+    }
+}
+
+fn burn_checks(
+    bundle: &BundleInfoStruct,
+    checks: &mut Checks,
+    context: &mut BurnContext,
+) -> Result<ZkpBurnPublicValuesStruct, CheckError<BurnRejectReason>> {
+    let tx = decode_transaction(&bundle.bit_tx_info.raw_tx_hex)
+        .map_err(|e| reject("parse_tx", BurnRejectReason::InvalidTransaction, e))?;
+    let txid = tx.compute_txid();
+    context.txid = Some(txid);
+    checks.pass("parse_tx", format!("txid {}", txid));
+
+    let chain = parse_chain(bundle.network.as_deref())
+        .map_err(|e| reject("bundle", BurnRejectReason::UnknownChain, e))?;
+    let (burner_btc_address, burn_id) = match (bundle.burner_btc_address.as_deref(), bundle.burn_id)
+    {
+        (Some(burner_btc_address), Some(burn_id)) => (burner_btc_address, burn_id),
+        _ => {
+            return Err(reject(
+                "bundle",
+                BurnRejectReason::MissingBurnRequest,
+                "Burner BTC address and burn request id must be provided",
+            ))
+        }
+    };
+    checks.pass(
+        "bundle",
+        format!("burn {} to {} on {}", burn_id, burner_btc_address, chain),
+    );
+
+    let confirmations = required_confirmations(bundle);
+    let summary = verify_chain_with_crate(
+        &bundle.chains,
+        confirmations,
+        bundle.retarget_info.as_ref(),
+        bundle.checkpoint_hash.as_deref(),
+        None,
+        &chain,
+    )
+    .map_err(|e| reject("verify_chain", BurnRejectReason::InvalidChain, e))?;
+    checks.pass(
+        "verify_chain",
+        format!(
+            "{} confirmations, minimum difficulty {}, cumulative work {:#x}",
+            confirmations, summary.min_difficulty, summary.total_work
+        ),
+    );
+
+    let first_block = &bundle.chains.blocks[0];
+    let inclusion =
+        check_merkle_proof_bounds(&bundle.merkle_proof, bundle.tx_count).and_then(|_| {
+            verify_tx_inclusion_str(
+                txid.to_string().as_str(),
+                &bundle.merkle_proof,
+                &first_block.merkle_root,
+            )
+        });
+    match inclusion {
+        Ok(true) => checks.pass(
+            "verify_merkle",
+            format!("in block {}", first_block.block_hash),
+        ),
+        Ok(false) => {
+            return Err(reject(
+                "verify_merkle",
+                BurnRejectReason::MerkleRootMismatch,
+                "Merkle root mismatch",
+            ))
+        }
+        Err(e) => {
+            return Err(reject(
+                "verify_merkle",
+                BurnRejectReason::InvalidMerkleProof,
+                e,
+            ))
+        }
+    }
+
+    let burner_spk = chain
+        .address_script_pubkey(burner_btc_address)
+        .map_err(|e| reject("burner_address", BurnRejectReason::InvalidRecipient, e))?;
+    let paid_sats = sum_outputs_to_script(&tx, &burner_spk);
+    let burn_policy = &bundle.burn_policy;
+    let underpaid = burn_policy.is_underpaid(paid_sats);
+    checks.pass(
+        "burner_address",
+        format!(
+            "{} sats paid, {} expected{}",
+            paid_sats,
+            burn_policy.expected_payout(),
+            if underpaid {
+                " (committed as underpaid)"
+            } else {
+                ""
+            }
+        ),
+    );
+
+    let group_key = parse_vault_key(bundle.vault_public_key.as_deref())
+        .map_err(|e| reject("vault_input", BurnRejectReason::InvalidVaultKey, e))?;
+    let vault_spk = vault_script_pubkey(&group_key);
+    let prev_txs = bundle
+        .spent_prevouts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|prev_tx_info| decode_transaction(&prev_tx_info.raw_tx_hex))
+        .collect::<Result<Vec<Transaction>, _>>()
+        .map_err(|e| reject("vault_input", BurnRejectReason::NotFromVault, e))?;
+    let vault_input = match find_vault_input(&tx, &prev_txs, &vault_spk) {
+        Ok(Some(index)) => index,
+        Ok(None) => {
+            return Err(reject(
+                "vault_input",
+                BurnRejectReason::NotFromVault,
+                format!(
+                    "No input spends from vault script {}",
+                    vault_spk.to_hex_string()
+                ),
+            ))
+        }
+        Err(e) => return Err(reject("vault_input", BurnRejectReason::NotFromVault, e)),
+    };
+    checks.pass(
+        "vault_input",
+        format!(
+            "input {} spends vault script {}",
+            vault_input,
+            vault_spk.to_hex_string()
+        ),
+    );
+
+    verify_vault_key_spend(&tx, vault_input, &prev_txs, &group_key).map_err(|e| {
+        reject(
+            "verify_vault_signature",
+            BurnRejectReason::InvalidVaultSignature,
+            e,
+        )
+    })?;
+    checks.pass(
+        "verify_vault_signature",
+        format!("signed by group key {}", group_key),
+    );
+
+    let fee_sats = compute_fee(&tx, &prev_txs)
+        .map_err(|e| reject("fee_cap", BurnRejectReason::NotFromVault, e))?;
+    if burn_policy.exceeds_fee_cap(fee_sats) {
+        return Err(reject(
+            "fee_cap",
+            BurnRejectReason::FeeTooHigh,
+            format!(
+                "Fee of {} sats exceeds the cap of {} sats",
+                fee_sats, burn_policy.max_fee_sats
+            ),
+        ));
+    }
+    checks.pass("fee_cap", format!("fee {} sats", fee_sats));
+
+    let vault_change_sats = sum_outputs_to_script(&tx, &vault_spk);
+    if let Some(index) = find_unexpected_output(&tx, &[&burner_spk, &vault_spk]) {
+        return Err(reject(
+            "outputs",
+            BurnRejectReason::UnexpectedOutput,
+            format!(
+                "Output {} pays unexpected script {}",
+                index,
+                tx.output[index].script_pubkey.to_hex_string()
+            ),
+        ));
+    }
+    checks.pass(
+        "outputs",
+        format!(
+            "only burner, OP_RETURN and {} sats vault change",
+            vault_change_sats
+        ),
+    );
+
+    let tagged = match parse_burn_tag(&tx) {
+        Ok(Some(tagged_id)) if tagged_id == burn_id => {
+            checks.pass("burn_tag", format!("tagged with burn {}", tagged_id));
+            true
+        }
+        Ok(Some(tagged_id)) => {
+            return Err(reject(
+                "burn_tag",
+                BurnRejectReason::BurnTagMismatch,
+                format!("Payout is tagged for burn {}, not {}", tagged_id, burn_id),
+            ))
+        }
+        Ok(None) => {
+            checks.pass("burn_tag", "untagged");
+            false
+        }
+        Err(e) => return Err(reject("burn_tag", BurnRejectReason::BurnTagMismatch, e)),
+    };
+
+    // An invalid competing chain is ignored, never rejected
+    let contested = match bundle.competing_chain.as_ref() {
+        Some(competing_chain) => {
+            let anchor_hash = summary.anchor_hash.to_string();
+            let forked = verify_chain_with_crate(
+                competing_chain,
+                competing_chain.blocks.len(),
+                bundle.retarget_info.as_ref(),
+                Some(anchor_hash.as_str()),
+                None,
+                &chain,
+            )
+            .is_ok()
+                && !competing_chain.blocks[0]
+                    .block_hash
+                    .eq_ignore_ascii_case(&first_block.block_hash);
+            checks.pass(
+                "competing_chain",
+                if forked {
+                    "payout is contested"
+                } else {
+                    "payout's block is not replaced"
+                },
+            );
+            forked
+        }
+        None => false,
+    };
+
+    let payout_block_height = match bundle.coinbase_proof.as_ref() {
+        Some(coinbase_proof) => {
+            let height = decode_transaction(&coinbase_proof.coinbase_tx_info.raw_tx_hex)
+                .and_then(|coinbase_tx| {
+                    verify_coinbase_height(
+                        &coinbase_tx,
+                        &coinbase_proof.coinbase_merkle_proof,
+                        &first_block.merkle_root,
+                        first_block.version,
+                    )
+                })
+                .map_err(|e| reject("block_height", BurnRejectReason::InvalidBlockHeight, e))?;
+            if let Some(claimed) = bundle.block_height.filter(|claimed| *claimed != height) {
+                return Err(reject(
+                    "block_height",
+                    BurnRejectReason::InvalidBlockHeight,
+                    format!(
+                        "Coinbase height {} does not match block height {}",
+                        height, claimed
+                    ),
+                ));
+            }
+            checks.pass("block_height", format!("height {}", height));
+            height
+        }
+        None => 0,
+    };
+
+    Ok(ZkpBurnPublicValuesStruct {
+        burner_btc_address: burner_btc_address.to_string(),
+        amount: U256::from(paid_sats),
+        is_valid: true,
+        payout_tx_id: txid_to_bytes32(txid),
+        vault_script_pubkey: vault_spk.to_bytes().into(),
+        burn_id: U256::from(burn_id),
+        reject_reason: BurnRejectReason::None as u8,
+        expected_payout_sats: U256::from(burn_policy.expected_payout()),
+        payout_tolerance_sats: U256::from(burn_policy.tolerance_sats),
+        underpaid,
+        min_difficulty: U256::from(summary.min_difficulty),
+        chain_work: U256::from_be_bytes(summary.total_work.to_be_bytes()),
+        confirmations: U256::from(confirmations),
+        vault_public_key: FixedBytes::from(group_key.serialize()),
+        vault_change_sats: U256::from(vault_change_sats),
+        network_magic: FixedBytes::from(chain.magic),
+        payout_block_height: U256::from(payout_block_height),
+        rbf_signaled: tx.is_explicitly_rbf(),
+        fee_sats: U256::from(fee_sats),
+        max_fee_sats: U256::from(burn_policy.max_fee_sats),
+        checkpoint_hash: block_hash_to_bytes32(summary.anchor_hash),
+        contested,
+        tagged,
+    })
+}
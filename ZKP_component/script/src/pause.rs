@@ -0,0 +1,204 @@
+//! Emergency pause of the services. The TSS coordinator signs a pause notice with the group key
+//! (`coordinator pause`), and the operators hand the notice file to every service: `submit`
+//! stops submitting, `payout-scheduler` stops paying out, and `watch` keeps recording deposits.
+//! Only a resume notice signed the same way (`coordinator unpause`) lifts the pause.
+//!
+//! Each service keeps the latest notice it accepted in its own state file, so a restart, or a
+//! notice file removed or replaced by an older one, does not lift a pause.
+
+use crate::profile::GroupKeyArgs;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::key::TapTweak;
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::warn;
+
+/// Tag of the BIP-340 tagged hash a notice is signed under. No Bitcoin sighash is computed
+/// under it, so a notice can never authorize a vault spend.
+pub const PAUSE_TAG: &str = "ZKBTC/pause";
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseAction {
+    Pause,
+    Resume,
+}
+
+/// A pause or its lifting, as signed by `coordinator pause` or `coordinator unpause`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseNotice {
+    pub action: PauseAction,
+    /// One above the notice it follows, so an older notice never undoes a newer one.
+    pub sequence: u64,
+    /// Unix time the notice was signed at.
+    pub issued_at: u64,
+    pub reason: String,
+    /// x-only group key, hex.
+    pub group_key: String,
+    /// 64-byte BIP-340 signature of [`PauseNotice::digest`] by the group key's key-path-only
+    /// Taproot output key, hex.
+    pub signature: String,
+}
+
+impl PauseNotice {
+    /// BIP-340 tagged hash, under [`PAUSE_TAG`], of the action byte (1 to pause, 0 to resume),
+    /// the big-endian sequence and issue time, and the SHA-256 of the reason.
+    pub fn digest(&self) -> [u8; 32] {
+        let tag = sha256::Hash::hash(PAUSE_TAG.as_bytes());
+        let mut engine = sha256::Hash::engine();
+        engine.input(tag.as_byte_array());
+        engine.input(tag.as_byte_array());
+        engine.input(&[u8::from(self.is_pause())]);
+        engine.input(&self.sequence.to_be_bytes());
+        engine.input(&self.issued_at.to_be_bytes());
+        engine.input(sha256::Hash::hash(self.reason.as_bytes()).as_byte_array());
+        sha256::Hash::from_engine(engine).to_byte_array()
+    }
+
+    pub fn is_pause(&self) -> bool {
+        self.action == PauseAction::Pause
+    }
+
+    /// Checks that the notice is signed by the signers of `group_key`.
+    pub fn verify(&self, group_key: &XOnlyPublicKey) -> Result<(), Box<dyn Error>> {
+        let signed_by = XOnlyPublicKey::from_str(&self.group_key)
+            .map_err(|e| format!("invalid notice group key: {}", e))?;
+        if signed_by != *group_key {
+            return Err(format!(
+                "the notice is signed by group key {}, not {}",
+                signed_by, group_key
+            )
+            .into());
+        }
+        let signature = schnorr::Signature::from_slice(&hex::decode(&self.signature)?)
+            .map_err(|e| format!("invalid notice signature: {}", e))?;
+        let secp = Secp256k1::verification_only();
+        let (output_key, _) = group_key.tap_tweak(&secp, None);
+        secp.verify_schnorr(
+            &signature,
+            &Message::from_digest(self.digest()),
+            &output_key.to_inner(),
+        )
+        .map_err(|_| {
+            format!(
+                "the signature of notice {} does not match it",
+                self.sequence
+            )
+        })?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for PauseNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            PauseAction::Pause => "pause",
+            PauseAction::Resume => "resume",
+        };
+        write!(
+            f,
+            "{} {} at {}: {}",
+            action, self.sequence, self.issued_at, self.reason
+        )
+    }
+}
+
+/// Where a service finds the pause notices.
+#[derive(Args, Debug)]
+pub struct PauseArgs {
+    /// Pause notice file, as written by `coordinator pause` or `coordinator unpause`; read
+    /// again before each action, so replacing it pauses or resumes a running service.
+    #[clap(long, env = "PAUSE_NOTICE")]
+    pub pause_notice: Option<PathBuf>,
+    /// File the service keeps the latest notice it accepted in; `.pause/<binary>.json` under
+    /// `script/` by default.
+    #[clap(long, env = "PAUSE_STATE")]
+    pub pause_state: Option<PathBuf>,
+}
+
+/// Default state file of `binary`, next to the proving journals under `script/`.
+pub fn default_state_path(binary: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(".pause")
+        .join(format!("{}.json", binary))
+}
+
+/// Pause state of one service.
+pub struct PauseGuard {
+    notice_path: Option<PathBuf>,
+    state_path: PathBuf,
+    group_key: XOnlyPublicKey,
+}
+
+impl PauseGuard {
+    /// Guard of `binary`, accepting the notices signed by `group_key`.
+    pub fn new(args: &PauseArgs, group_key: &GroupKeyArgs, binary: &str) -> Self {
+        PauseGuard {
+            notice_path: args.pause_notice.clone(),
+            state_path: args
+                .pause_state
+                .clone()
+                .unwrap_or_else(|| default_state_path(binary)),
+            group_key: group_key.group_key,
+        }
+    }
+
+    fn read(path: &PathBuf) -> Result<Option<PauseNotice>, Box<dyn Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json).map_err(|e| {
+                format!("pause notice {} is corrupt: {}", path.display(), e)
+            })?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("pause notice {}: {}", path.display(), e).into()),
+        }
+    }
+
+    /// Accepts a newer notice from the notice file, and returns the pause in force, if any. A
+    /// notice that does not verify is an error, so a service does not act on it either way.
+    pub fn current(&self) -> Result<Option<PauseNotice>, Box<dyn Error>> {
+        let mut accepted = Self::read(&self.state_path)?;
+        if let Some(notice) = accepted.as_ref() {
+            notice.verify(&self.group_key)?;
+        }
+        if let Some(notice) = self
+            .notice_path
+            .as_ref()
+            .map(Self::read)
+            .transpose()?
+            .flatten()
+        {
+            notice
+                .verify(&self.group_key)
+                .map_err(|e| format!("Refusing pause notice {}: {}", notice.sequence, e))?;
+            match &accepted {
+                Some(latest) if latest.sequence >= notice.sequence => {
+                    if latest.sequence > notice.sequence {
+                        warn!(
+                            notice = notice.sequence,
+                            accepted = latest.sequence,
+                            "Ignoring a pause notice older than the accepted one"
+                        );
+                    }
+                }
+                _ => {
+                    if let Some(dir) = self.state_path.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    // Written aside then renamed, so a crash leaves the old state or the new one
+                    let partial = self.state_path.with_extension("json.partial");
+                    std::fs::write(&partial, serde_json::to_string_pretty(&notice)?)?;
+                    std::fs::rename(&partial, &self.state_path)?;
+                    warn!(%notice, "Pause notice accepted");
+                    accepted = Some(notice);
+                }
+            }
+        }
+        Ok(accepted.filter(PauseNotice::is_pause))
+    }
+}
@@ -0,0 +1,791 @@
+//! Batching of burn payouts for `payout-scheduler`: the ready payout jobs accumulate until a
+//! threshold is hit, then one transaction spending vault outputs pays all of them. The miner
+//! fee is shared evenly between the payouts, and the rest of the spent value goes back to the
+//! vault as change.
+//! - `BatchArgs::trigger` tells whether the pending payouts should be sent now.
+//! - `plan_batch` picks the payouts and the vault outputs, and builds the unsigned transaction.
+//! - `sighashes` and `apply_signatures` sign its key-path inputs with the TSS signatures.
+//! - `BatchStore` keeps the batches and the vault outputs they reserve, so a restart neither
+//!   spends an output twice nor pays a burn twice.
+//!
+//! A rejected deposit is refunded the same way, in a batch of its own: `plan_refund` pays the
+//! deposit's value back to its sender, less the miner fee, spending the deposit's own vault
+//! outputs first. A refund needs no burn proof, so its batch is done once it is final.
+//!
+//! Only outputs paying the vault address itself are spent; deposits to per-request deposit
+//! addresses are signed with their request tweak and are not picked up here.
+
+use crate::bundle::Utxo;
+use crate::store::{PayoutJob, RefundJob};
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighash, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    absolute, taproot, Amount, Network, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn,
+    TxOut, Witness,
+};
+use bridge_core::now;
+use clap::Args;
+use lib_struct::verify::{address_script_pubkey, decode_transaction};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// Weight of the version, lock time, segwit marker and input and output counts.
+const TX_OVERHEAD_WU: u64 = 42;
+/// Weight of a key-path Taproot input: outpoint, empty script, sequence and a 64-byte
+/// signature in the witness.
+const KEY_SPEND_INPUT_WU: u64 = 230;
+/// Smallest payout sent; below it, some output types would be non-standard dust.
+pub const PAYOUT_DUST_SATS: u64 = 546;
+/// Smallest change paid back to the vault; smaller change is left to the miners.
+pub const CHANGE_DUST_SATS: u64 = 330;
+
+/// When pending payouts are sent.
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Pending payouts that trigger a batch.
+    #[clap(long, env = "PAYOUT_BATCH_COUNT", default_value_t = 10)]
+    pub batch_count: usize,
+    /// Sats owed by the pending payouts that trigger a batch.
+    #[clap(long, env = "PAYOUT_BATCH_VALUE")]
+    pub batch_value: Option<u64>,
+    /// Seconds the oldest pending payout may wait before a batch is sent anyway.
+    #[clap(long, env = "PAYOUT_MAX_WAIT", default_value_t = 3_600)]
+    pub max_wait: u64,
+    /// Most payouts in one transaction; the others wait for the next batch.
+    #[clap(long, env = "PAYOUT_MAX_BATCH", default_value_t = 50)]
+    pub max_batch: usize,
+    /// Fee rate in sat/vB above which every batch is postponed, whatever the thresholds.
+    #[clap(long, env = "PAYOUT_MAX_FEE_RATE")]
+    pub max_fee_rate: Option<f64>,
+    /// Fee rate in sat/vB at or below which the pending payouts are sent without waiting for
+    /// a threshold.
+    #[clap(long, env = "PAYOUT_LOW_FEE_RATE")]
+    pub low_fee_rate: Option<f64>,
+}
+
+/// Threshold that started a batch.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Count,
+    Value,
+    Wait,
+    LowFee,
+    /// A refund, paid as soon as it is ready.
+    Refund,
+}
+
+/// A ready payout job not in any batch yet, with the Unix time it was first seen ready.
+pub struct PendingPayout {
+    pub job: PayoutJob,
+    pub first_seen: u64,
+}
+
+impl BatchArgs {
+    /// Whether `fee_rate` is above `--max-fee-rate`.
+    pub fn fee_too_high(&self, fee_rate: f64) -> bool {
+        self.max_fee_rate.is_some_and(|max| fee_rate > max)
+    }
+
+    /// Threshold the pending payouts hit at `now`, if any; none while the fee rate is above
+    /// `--max-fee-rate`.
+    pub fn trigger(&self, pending: &[PendingPayout], fee_rate: f64, now: u64) -> Option<Trigger> {
+        if pending.is_empty() || self.fee_too_high(fee_rate) {
+            return None;
+        }
+        let owed: u64 = pending.iter().map(|payout| payout.job.amount_sats).sum();
+        let oldest = pending.iter().map(|payout| payout.first_seen).min()?;
+        if pending.len() >= self.batch_count.max(1) {
+            Some(Trigger::Count)
+        } else if self.batch_value.is_some_and(|value| owed >= value) {
+            Some(Trigger::Value)
+        } else if now.saturating_sub(oldest) >= self.max_wait {
+            Some(Trigger::Wait)
+        } else if self.low_fee_rate.is_some_and(|low| fee_rate <= low) {
+            Some(Trigger::LowFee)
+        } else {
+            None
+        }
+    }
+}
+
+/// A burn paid by a batch.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BatchPayout {
+    pub burn_id: u64,
+    pub btc_address: String,
+    /// Sats the burn request owes.
+    pub amount_sats: u64,
+    /// Sats the output pays: the amount owed minus the payout's share of the miner fee.
+    pub paid_sats: u64,
+}
+
+/// A rejected deposit refunded by a batch.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BatchRefund {
+    pub deposit_txid: String,
+    /// Request the deposit's memo names, if known.
+    #[serde(default)]
+    pub request_id: Option<u64>,
+    pub btc_address: String,
+    /// Sats the deposit paid the vault.
+    pub amount_sats: u64,
+    /// Sats the output pays: the deposit minus the miner fee.
+    pub paid_sats: u64,
+    pub reason: String,
+}
+
+/// A vault output spent by a batch.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BatchInput {
+    pub txid: String,
+    pub vout: u32,
+    pub value_sats: u64,
+}
+
+impl BatchInput {
+    /// Key of the output in the reservations.
+    fn key(&self) -> String {
+        format!("{}:{}", self.txid, self.vout)
+    }
+}
+
+/// Where a batch is in the payout pipeline.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum BatchStatus {
+    /// Waiting for the TSS signatures of its inputs, then for its broadcast.
+    Signing,
+    /// Sent to the network; waiting to be final before its burn proof is requested.
+    Broadcast,
+    /// Burn-batch proof queued on the proof service.
+    Proving { job_id: u64 },
+    /// Burn-batch proof generated; its outputs are no longer reserved.
+    Proved,
+    /// Refund final; its outputs are no longer reserved.
+    Refunded,
+    /// Given up; its outputs are no longer reserved and its burns can be batched again.
+    Failed { reason: String },
+}
+
+impl fmt::Display for BatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchStatus::Signing => write!(f, "signing"),
+            BatchStatus::Broadcast => write!(f, "broadcast"),
+            BatchStatus::Proving { job_id } => write!(f, "proving as job {}", job_id),
+            BatchStatus::Proved => write!(f, "proved"),
+            BatchStatus::Refunded => write!(f, "refunded"),
+            BatchStatus::Failed { reason } => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+/// One payout transaction settling several burns.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PayoutBatch {
+    pub id: u64,
+    /// Burns paid; none for a refund.
+    pub payouts: Vec<BatchPayout>,
+    /// Deposit refunded, for a refund.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund: Option<BatchRefund>,
+    pub inputs: Vec<BatchInput>,
+    /// Sats paid back to the vault; 0 when the change was too small to pay.
+    pub change_sats: u64,
+    /// Miner fee the transaction pays.
+    pub fee_sats: u64,
+    /// Share of the miner fee deducted from each payout.
+    pub fee_share_sats: u64,
+    /// Fee rate the batch was planned at, in sat/vB.
+    pub fee_rate: f64,
+    pub trigger: Trigger,
+    pub unsigned_tx_hex: String,
+    /// Transaction with its witnesses, once the signers signed it.
+    #[serde(default)]
+    pub signed_tx_hex: Option<String>,
+    pub txid: String,
+    /// Whether the burn listener recorded the payouts, or the refund, paid.
+    #[serde(default)]
+    pub marked_paid: bool,
+    pub status: BatchStatus,
+    pub created_at: u64,
+}
+
+impl PayoutBatch {
+    /// Whether the batch is proven, refunded or failed, so its outputs are no longer reserved.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            BatchStatus::Proved | BatchStatus::Refunded | BatchStatus::Failed { .. }
+        )
+    }
+
+    /// The transaction to sign, or the signed one once it is.
+    pub fn transaction(&self) -> Result<Transaction, Box<dyn Error>> {
+        let tx_hex = self
+            .signed_tx_hex
+            .as_deref()
+            .unwrap_or(&self.unsigned_tx_hex);
+        decode_transaction(tx_hex)
+    }
+}
+
+/// Weight of a transaction spending `inputs` key-path outputs to `outputs`.
+fn estimate_weight(inputs: usize, outputs: &[&Script]) -> u64 {
+    let outputs: u64 = outputs
+        .iter()
+        .map(|script| (9 + script.len() as u64) * 4)
+        .sum();
+    TX_OVERHEAD_WU + inputs as u64 * KEY_SPEND_INPUT_WU + outputs
+}
+
+/// Transaction spending `inputs`, unsigned, to `output`.
+fn unsigned_transaction(inputs: &[&Utxo], output: Vec<TxOut>) -> Transaction {
+    let input = inputs
+        .iter()
+        .map(|utxo| TxIn {
+            previous_output: OutPoint::new(utxo.txid, utxo.vout),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        })
+        .collect();
+    Transaction {
+        version: Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input,
+        output,
+    }
+}
+
+/// Batch inputs of the vault outputs `utxos`.
+fn batch_inputs(utxos: &[&Utxo]) -> Vec<BatchInput> {
+    utxos
+        .iter()
+        .map(|utxo| BatchInput {
+            txid: utxo.txid.to_string(),
+            vout: utxo.vout,
+            value_sats: utxo.value_sats,
+        })
+        .collect()
+}
+
+/// Plans a batch paying `jobs`, in order, from the vault outputs `utxos`, at `fee_rate`
+/// sat/vB. Burns beyond what the vault holds wait for a later batch, and so does a second burn
+/// to an address already paid, as the burn-batch circuit pays each address once. Returns the
+/// batch, not yet stored, and the burns whose amount does not cover their share of the fee.
+pub fn plan_batch(
+    jobs: &[PayoutJob],
+    utxos: &[Utxo],
+    vault_spk: &Script,
+    network: Network,
+    fee_rate: f64,
+    trigger: Trigger,
+) -> Result<(PayoutBatch, Vec<u64>), Box<dyn Error>> {
+    let mut utxos: Vec<&Utxo> = utxos.iter().collect();
+    utxos.sort_by_key(|utxo| Reverse(utxo.value_sats));
+    let available: u64 = utxos.iter().map(|utxo| utxo.value_sats).sum();
+
+    let mut payouts: Vec<(&PayoutJob, ScriptBuf)> = Vec::new();
+    let mut owed = 0u64;
+    for job in jobs {
+        let script = address_script_pubkey(&job.btc_address, network)?;
+        if payouts.iter().any(|(_, paid)| *paid == script) {
+            continue;
+        }
+        if owed + job.amount_sats > available {
+            break;
+        }
+        owed += job.amount_sats;
+        payouts.push((job, script));
+    }
+    if payouts.is_empty() {
+        return Err(match jobs.first() {
+            Some(job) => format!(
+                "Vault holds {} spendable sats, burn {} owes {}",
+                available, job.burn_id, job.amount_sats
+            ),
+            None => "No payout to batch".to_string(),
+        }
+        .into());
+    }
+
+    // Dropping a payout too small for its fee share raises the others' shares, so repeat
+    let mut too_small = Vec::new();
+    loop {
+        let owed: u64 = payouts.iter().map(|(job, _)| job.amount_sats).sum();
+        let mut selected = Vec::new();
+        let mut selected_sats = 0u64;
+        for utxo in &utxos {
+            if selected_sats >= owed {
+                break;
+            }
+            selected_sats += utxo.value_sats;
+            selected.push(*utxo);
+        }
+        let mut scripts: Vec<&Script> = payouts
+            .iter()
+            .map(|(_, script)| script.as_script())
+            .collect();
+        scripts.push(vault_spk);
+        let vsize = estimate_weight(selected.len(), &scripts).div_ceil(4);
+        let fee = (fee_rate * vsize as f64).ceil() as u64;
+        let fee_share = fee.div_ceil(payouts.len() as u64);
+
+        let before = payouts.len();
+        payouts.retain(|(job, _)| {
+            let covered = job.amount_sats >= fee_share + PAYOUT_DUST_SATS;
+            if !covered {
+                too_small.push(job.burn_id);
+            }
+            covered
+        });
+        if payouts.is_empty() {
+            return Err(format!(
+                "No payout of the batch covers its {} sats share of the fee",
+                fee_share
+            )
+            .into());
+        }
+        if payouts.len() != before {
+            continue;
+        }
+
+        // === Build the unsigned transaction ===
+        let change_sats = selected_sats - owed;
+        let mut output: Vec<TxOut> = payouts
+            .iter()
+            .map(|(job, script)| TxOut {
+                value: Amount::from_sat(job.amount_sats - fee_share),
+                script_pubkey: script.clone(),
+            })
+            .collect();
+        let change_sats = if change_sats >= CHANGE_DUST_SATS {
+            output.push(TxOut {
+                value: Amount::from_sat(change_sats),
+                script_pubkey: vault_spk.to_owned(),
+            });
+            change_sats
+        } else {
+            0
+        };
+        let tx = unsigned_transaction(&selected, output);
+        let paid: u64 = tx.output.iter().map(|output| output.value.to_sat()).sum();
+        let batch = PayoutBatch {
+            id: 0,
+            payouts: payouts
+                .iter()
+                .map(|(job, _)| BatchPayout {
+                    burn_id: job.burn_id,
+                    btc_address: job.btc_address.clone(),
+                    amount_sats: job.amount_sats,
+                    paid_sats: job.amount_sats - fee_share,
+                })
+                .collect(),
+            inputs: batch_inputs(&selected),
+            change_sats,
+            fee_sats: selected_sats - paid,
+            refund: None,
+            fee_share_sats: fee_share,
+            fee_rate,
+            trigger,
+            unsigned_tx_hex: serialize_hex(&tx),
+            signed_tx_hex: None,
+            txid: tx.compute_txid().to_string(),
+            marked_paid: false,
+            status: BatchStatus::Signing,
+            created_at: now(),
+        };
+        return Ok((batch, too_small));
+    }
+}
+
+/// Plans the refund of `job`, whose deposit paid the vault `amount_sats`, from the vault outputs
+/// `utxos` at `fee_rate` sat/vB. The deposit's own outputs are spent first, then the largest
+/// others; the refund pays the fee, and the rest of the spent value goes back to the vault.
+pub fn plan_refund(
+    job: &RefundJob,
+    amount_sats: u64,
+    utxos: &[Utxo],
+    vault_spk: &Script,
+    network: Network,
+    fee_rate: f64,
+) -> Result<PayoutBatch, Box<dyn Error>> {
+    let script = address_script_pubkey(&job.btc_address, network)?;
+    let mut utxos: Vec<&Utxo> = utxos.iter().collect();
+    utxos.sort_by_key(|utxo| {
+        (
+            utxo.txid.to_string() != job.deposit_txid,
+            Reverse(utxo.value_sats),
+        )
+    });
+    let mut selected = Vec::new();
+    let mut selected_sats = 0u64;
+    for utxo in &utxos {
+        if selected_sats >= amount_sats {
+            break;
+        }
+        selected_sats += utxo.value_sats;
+        selected.push(*utxo);
+    }
+    if selected_sats < amount_sats {
+        return Err(format!(
+            "Vault holds {} spendable sats, the refund of {} owes {}",
+            selected_sats, job.deposit_txid, amount_sats
+        )
+        .into());
+    }
+    let vsize = estimate_weight(selected.len(), &[&script, vault_spk]).div_ceil(4);
+    let fee = (fee_rate * vsize as f64).ceil() as u64;
+    if amount_sats < fee + PAYOUT_DUST_SATS {
+        return Err(format!(
+            "Deposit {} of {} sats does not cover the {} sats fee of its refund",
+            job.deposit_txid, amount_sats, fee
+        )
+        .into());
+    }
+
+    let paid_sats = amount_sats - fee;
+    let mut output = vec![TxOut {
+        value: Amount::from_sat(paid_sats),
+        script_pubkey: script,
+    }];
+    let change_sats = selected_sats - amount_sats;
+    let change_sats = if change_sats >= CHANGE_DUST_SATS {
+        output.push(TxOut {
+            value: Amount::from_sat(change_sats),
+            script_pubkey: vault_spk.to_owned(),
+        });
+        change_sats
+    } else {
+        0
+    };
+    let tx = unsigned_transaction(&selected, output);
+    let paid: u64 = tx.output.iter().map(|output| output.value.to_sat()).sum();
+    Ok(PayoutBatch {
+        id: 0,
+        payouts: Vec::new(),
+        refund: Some(BatchRefund {
+            deposit_txid: job.deposit_txid.clone(),
+            request_id: job.request_id,
+            btc_address: job.btc_address.clone(),
+            amount_sats,
+            paid_sats,
+            reason: job.reason.clone(),
+        }),
+        inputs: batch_inputs(&selected),
+        change_sats,
+        fee_sats: selected_sats - paid,
+        fee_share_sats: fee,
+        fee_rate,
+        trigger: Trigger::Refund,
+        unsigned_tx_hex: serialize_hex(&tx),
+        signed_tx_hex: None,
+        txid: tx.compute_txid().to_string(),
+        marked_paid: false,
+        status: BatchStatus::Signing,
+        created_at: now(),
+    })
+}
+
+/// Taproot key-spend sighash of each input of `tx`, which spends the vault outputs `inputs`.
+pub fn sighashes(
+    tx: &Transaction,
+    inputs: &[BatchInput],
+    vault_spk: &Script,
+) -> Result<Vec<TapSighash>, Box<dyn Error>> {
+    let prevouts: Vec<TxOut> = inputs
+        .iter()
+        .map(|input| TxOut {
+            value: Amount::from_sat(input.value_sats),
+            script_pubkey: vault_spk.to_owned(),
+        })
+        .collect();
+    let mut cache = SighashCache::new(tx);
+    (0..tx.input.len())
+        .map(|index| {
+            Ok(cache.taproot_key_spend_signature_hash(
+                index,
+                &Prevouts::All(&prevouts),
+                TapSighashType::Default,
+            )?)
+        })
+        .collect()
+}
+
+/// Puts the signers' signatures, one per input, in `tx`'s witnesses. Each must verify under
+/// the vault's output key, so a wrong key or message is caught before the broadcast.
+pub fn apply_signatures(
+    tx: &mut Transaction,
+    sighashes: &[TapSighash],
+    signatures: &[String],
+    vault_spk: &Script,
+) -> Result<(), Box<dyn Error>> {
+    if signatures.len() != tx.input.len() {
+        return Err(format!(
+            "Got {} signatures for {} inputs",
+            signatures.len(),
+            tx.input.len()
+        )
+        .into());
+    }
+    if !vault_spk.is_p2tr() {
+        return Err("Vault script is not a Taproot output".into());
+    }
+    let output_key = XOnlyPublicKey::from_slice(&vault_spk.as_bytes()[2..])?;
+    let secp = Secp256k1::verification_only();
+    for (index, (sighash, signature_hex)) in sighashes.iter().zip(signatures).enumerate() {
+        let signature = schnorr::Signature::from_slice(&hex::decode(signature_hex)?)?;
+        let message = Message::from_digest(sighash.to_byte_array());
+        secp.verify_schnorr(&signature, &message, &output_key)
+            .map_err(|e| format!("Signature of input {} does not verify: {}", index, e))?;
+        tx.input[index].witness = Witness::p2tr_key_spend(&taproot::Signature {
+            signature,
+            sighash_type: TapSighashType::Default,
+        });
+    }
+    Ok(())
+}
+
+/// Turns a transaction's abort message into the error it returns.
+fn transaction_error(e: TransactionError<String>) -> Box<dyn Error> {
+    match e {
+        TransactionError::Abort(message) => message.into(),
+        TransactionError::Storage(e) => e.into(),
+    }
+}
+
+fn abort_with<E: ToString>(e: E) -> ConflictableTransactionError<String> {
+    ConflictableTransactionError::Abort(e.to_string())
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+pub struct BatchStore {
+    db: sled::Db,
+    /// Batches keyed by big-endian id.
+    batches: sled::Tree,
+    /// Big-endian id of the batch spending each reserved output, keyed by `txid:vout`.
+    reserved: sled::Tree,
+    /// Unix time each burn id was first seen ready, keyed by big-endian burn id.
+    first_seen: sled::Tree,
+}
+
+impl BatchStore {
+    /// Opens the database at `path`, creating it if needed; without a path the database is
+    /// temporary and dropped on exit.
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let config = match path {
+            Some(path) => sled::Config::new().path(path),
+            None => sled::Config::new().temporary(true),
+        };
+        let db = config.open()?;
+        Ok(BatchStore {
+            batches: db.open_tree("batches")?,
+            reserved: db.open_tree("reserved")?,
+            first_seen: db.open_tree("first_seen")?,
+            db,
+        })
+    }
+
+    /// The database itself, which the policy hooks record the payout volume in.
+    pub fn db(&self) -> &sled::Db {
+        &self.db
+    }
+
+    /// Stores a planned batch under a new id and reserves its outputs, at once; an output
+    /// already reserved by another batch is an error.
+    pub fn create(&self, mut batch: PayoutBatch) -> Result<PayoutBatch, Box<dyn Error>> {
+        batch.id = self.db.generate_id()? + 1;
+        (&self.batches, &self.reserved)
+            .transaction(|(batches, reserved)| {
+                for input in &batch.inputs {
+                    if let Some(owner) = reserved.get(input.key())? {
+                        let owner =
+                            u64::from_be_bytes(owner.as_ref().try_into().unwrap_or_default());
+                        return Err(abort_with(format!(
+                            "Output {} is reserved by batch {}",
+                            input.key(),
+                            owner
+                        )));
+                    }
+                    reserved.insert(input.key().as_bytes(), &batch.id.to_be_bytes())?;
+                }
+                batches.insert(
+                    &batch.id.to_be_bytes(),
+                    serde_json::to_vec(&batch).map_err(abort_with)?,
+                )?;
+                Ok(())
+            })
+            .map_err(transaction_error)?;
+        Ok(batch)
+    }
+
+    /// Saves a batch's progress. A finished batch releases its outputs in the same write.
+    pub fn put(&self, batch: &PayoutBatch) -> Result<(), Box<dyn Error>> {
+        (&self.batches, &self.reserved)
+            .transaction(|(batches, reserved)| {
+                if batch.is_finished() {
+                    for input in &batch.inputs {
+                        if reserved.get(input.key())?.as_deref()
+                            == Some(&batch.id.to_be_bytes()[..])
+                        {
+                            reserved.remove(input.key().as_bytes())?;
+                        }
+                    }
+                }
+                batches.insert(
+                    &batch.id.to_be_bytes(),
+                    serde_json::to_vec(batch).map_err(abort_with)?,
+                )?;
+                Ok(())
+            })
+            .map_err(transaction_error)
+    }
+
+    /// Has batch `id` signed anew by the next round of `payout-scheduler`: a batch still
+    /// signing drops its signatures, and a batch that failed before its broadcast goes back to
+    /// signing with its outputs reserved again. Fails if another batch has since reserved one
+    /// of its outputs, or pays one of its burns or its refund.
+    pub fn resign(&self, id: u64) -> Result<PayoutBatch, Box<dyn Error>> {
+        let mut batch = self.get(id)?.ok_or_else(|| format!("No batch {}", id))?;
+        match &batch.status {
+            BatchStatus::Signing => {}
+            BatchStatus::Failed { .. } if !batch.marked_paid => {
+                let burns: HashSet<u64> = batch.payouts.iter().map(|payout| payout.burn_id).collect();
+                let deposit = batch.refund.as_ref().map(|refund| &refund.deposit_txid);
+                let other = self.batches()?.into_iter().find(|other| {
+                    other.id != id
+                        && !matches!(other.status, BatchStatus::Failed { .. })
+                        && (other
+                            .payouts
+                            .iter()
+                            .any(|payout| burns.contains(&payout.burn_id))
+                            || deposit.is_some()
+                                && other.refund.as_ref().map(|refund| &refund.deposit_txid)
+                                    == deposit)
+                });
+                if let Some(other) = other {
+                    return Err(format!(
+                        "Batch {} pays burns or the refund of batch {} since",
+                        other.id, id
+                    )
+                    .into());
+                }
+            }
+            status => {
+                return Err(format!(
+                    "Batch {} is {}; only a batch signing, or failed before its broadcast, is signed again",
+                    id, status
+                )
+                .into())
+            }
+        }
+        batch.status = BatchStatus::Signing;
+        batch.signed_tx_hex = None;
+        (&self.batches, &self.reserved)
+            .transaction(|(batches, reserved)| {
+                for input in &batch.inputs {
+                    if let Some(owner) = reserved.get(input.key())? {
+                        let owner =
+                            u64::from_be_bytes(owner.as_ref().try_into().unwrap_or_default());
+                        if owner != batch.id {
+                            return Err(abort_with(format!(
+                                "Output {} is reserved by batch {}",
+                                input.key(),
+                                owner
+                            )));
+                        }
+                    }
+                    reserved.insert(input.key().as_bytes(), &batch.id.to_be_bytes())?;
+                }
+                batches.insert(
+                    &batch.id.to_be_bytes(),
+                    serde_json::to_vec(&batch).map_err(abort_with)?,
+                )?;
+                Ok(())
+            })
+            .map_err(transaction_error)?;
+        Ok(batch)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<PayoutBatch>, Box<dyn Error>> {
+        self.batches
+            .get(id.to_be_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Every batch, oldest first.
+    pub fn batches(&self) -> Result<Vec<PayoutBatch>, Box<dyn Error>> {
+        self.batches
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Whether an unfinished batch spends output `vout` of `txid`.
+    pub fn is_reserved(&self, txid: &str, vout: u32) -> Result<bool, Box<dyn Error>> {
+        Ok(self
+            .reserved
+            .contains_key(format!("{}:{}", txid, vout).as_bytes())?)
+    }
+
+    /// Burns paid by a batch that did not fail; they are never batched again.
+    pub fn batched_burns(&self) -> Result<HashSet<u64>, Box<dyn Error>> {
+        Ok(self
+            .batches()?
+            .iter()
+            .filter(|batch| !matches!(batch.status, BatchStatus::Failed { .. }))
+            .flat_map(|batch| batch.payouts.iter().map(|payout| payout.burn_id))
+            .collect())
+    }
+
+    /// Deposits refunded by a batch that did not fail; they are never refunded again.
+    pub fn refunded_deposits(&self) -> Result<HashSet<String>, Box<dyn Error>> {
+        Ok(self
+            .batches()?
+            .into_iter()
+            .filter(|batch| !matches!(batch.status, BatchStatus::Failed { .. }))
+            .filter_map(|batch| batch.refund.map(|refund| refund.deposit_txid))
+            .collect())
+    }
+
+    /// Unix time burn `burn_id` was first seen ready, recording `now` the first time.
+    pub fn first_seen(&self, burn_id: u64, now: u64) -> Result<u64, Box<dyn Error>> {
+        let key = burn_id.to_be_bytes();
+        if let Some(bytes) = self.first_seen.get(key)? {
+            return decode(&bytes);
+        }
+        self.first_seen.insert(key, serde_json::to_vec(&now)?)?;
+        Ok(now)
+    }
+
+    /// Forgets when the burns of a batch were first seen, once they are paid.
+    pub fn forget_first_seen(&self, batch: &PayoutBatch) -> Result<(), Box<dyn Error>> {
+        for payout in &batch.payouts {
+            self.first_seen.remove(payout.burn_id.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending changes to disk.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
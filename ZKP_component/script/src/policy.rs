@@ -0,0 +1,121 @@
+//! Command-line settings of the built-in policy hooks of `bridge_core::policy`, shared by
+//! `submit`, which checks a mint before sending it, and `payout-scheduler`, which checks each
+//! payout before a batch is planned and again before it is signed.
+
+use bridge_core::policy::{AddressList, AmountCap, EpochVolume, PolicyEngine};
+use bridge_core::rate_limit::{RateLimit, VolumeLedger};
+use bridge_core::Direction;
+use clap::Args;
+use std::error::Error;
+
+/// Name of the sled tree the epoch volumes are recorded in.
+const VOLUME_TREE: &str = "policy_volume";
+/// Name of the sled tree the rolling window's transfers are recorded in.
+pub const WINDOW_TREE: &str = "policy_window";
+
+/// Limits on the mints and payouts; a hook is only enabled when its setting is given.
+#[derive(Args, Debug)]
+pub struct PolicyArgs {
+    /// Largest mint, in sats.
+    #[clap(long, env = "POLICY_MAX_MINT_SATS")]
+    pub policy_max_mint_sats: Option<u64>,
+    /// Largest payout, in sats.
+    #[clap(long, env = "POLICY_MAX_PAYOUT_SATS")]
+    pub policy_max_payout_sats: Option<u64>,
+    /// Ethereum accounts mints may go to; repeat the flag or separate them with commas. When
+    /// set, mints to any other account are denied.
+    #[clap(long, env = "POLICY_MINT_ALLOW", value_delimiter = ',')]
+    pub policy_mint_allow: Vec<String>,
+    /// BTC addresses payouts may go to; when set, payouts to any other address are denied.
+    #[clap(long, env = "POLICY_PAYOUT_ALLOW", value_delimiter = ',')]
+    pub policy_payout_allow: Vec<String>,
+    /// Ethereum accounts and BTC addresses never paid, in either direction.
+    #[clap(long, env = "POLICY_DENY", value_delimiter = ',')]
+    pub policy_deny: Vec<String>,
+    /// Sats that may be minted per epoch.
+    #[clap(long, env = "POLICY_MINT_VOLUME_SATS")]
+    pub policy_mint_volume_sats: Option<u64>,
+    /// Sats that may be paid out per epoch.
+    #[clap(long, env = "POLICY_PAYOUT_VOLUME_SATS")]
+    pub policy_payout_volume_sats: Option<u64>,
+    /// Length of a volume epoch, in seconds.
+    #[clap(long, env = "POLICY_EPOCH_SECS", default_value_t = 86_400)]
+    pub policy_epoch_secs: u64,
+    /// Sats that may be minted over any rolling window.
+    #[clap(long, env = "POLICY_MINT_WINDOW_SATS")]
+    pub policy_mint_window_sats: Option<u64>,
+    /// Sats that may be paid out over any rolling window.
+    #[clap(long, env = "POLICY_PAYOUT_WINDOW_SATS")]
+    pub policy_payout_window_sats: Option<u64>,
+    /// Mints allowed over any rolling window.
+    #[clap(long, env = "POLICY_MINT_WINDOW_COUNT")]
+    pub policy_mint_window_count: Option<u64>,
+    /// Payouts allowed over any rolling window.
+    #[clap(long, env = "POLICY_PAYOUT_WINDOW_COUNT")]
+    pub policy_payout_window_count: Option<u64>,
+    /// Length of the rolling window, in seconds.
+    #[clap(long, env = "POLICY_WINDOW_SECS", default_value_t = 86_400)]
+    pub policy_window_secs: u64,
+}
+
+impl PolicyArgs {
+    /// Engine with the hooks of `direction` that are set. Volume and window limits are recorded
+    /// in `db`, so they need one.
+    pub fn engine(
+        &self,
+        direction: Direction,
+        db: Option<&sled::Db>,
+    ) -> Result<PolicyEngine, Box<dyn Error>> {
+        let (max_sats, allow, volume_sats, window_sats, window_count) = match direction {
+            Direction::Mint => (
+                self.policy_max_mint_sats,
+                &self.policy_mint_allow,
+                self.policy_mint_volume_sats,
+                self.policy_mint_window_sats,
+                self.policy_mint_window_count,
+            ),
+            Direction::Burn => (
+                self.policy_max_payout_sats,
+                &self.policy_payout_allow,
+                self.policy_payout_volume_sats,
+                self.policy_payout_window_sats,
+                self.policy_payout_window_count,
+            ),
+        };
+        let mut engine = PolicyEngine::new();
+        if let Some(max_sats) = max_sats {
+            engine = engine.with(AmountCap {
+                direction,
+                max_sats,
+            });
+        }
+        if !allow.is_empty() || !self.policy_deny.is_empty() {
+            engine = engine.with(AddressList {
+                direction,
+                allow: allow.clone(),
+                deny: self.policy_deny.clone(),
+            });
+        }
+        if let Some(limit_sats) = volume_sats {
+            let db = db.ok_or("An epoch volume limit needs a database to record the volume in")?;
+            engine = engine.with(EpochVolume::new(
+                direction,
+                limit_sats,
+                self.policy_epoch_secs,
+                db.open_tree(VOLUME_TREE)?,
+            ));
+        }
+        if window_sats.is_some() || window_count.is_some() {
+            let db =
+                db.ok_or("A rolling window limit needs a database to record the transfers in")?;
+            engine = engine.with(RateLimit::new(
+                direction,
+                self.policy_window_secs,
+                window_sats,
+                window_count,
+                VolumeLedger::new(db.open_tree(WINDOW_TREE)?),
+            ));
+        }
+        Ok(engine)
+    }
+}
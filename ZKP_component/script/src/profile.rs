@@ -0,0 +1,157 @@
+//! Network profiles: one name that selects the source chain the bundles are built and proven
+//! on, the default node and Esplora endpoints, and the EVM chain the zkBTC contract lives on.
+//!
+//! A profile is chosen with `profile = "<name>"` in `bridge.toml` or `BRIDGE_PROFILE`, and only
+//! fills the environment variables that nothing else set: a flag, the environment, the config
+//! file and `.env` all take precedence over it.
+//!
+//! The TSS group key is the signer group's, not the network's, so no profile supplies it: every
+//! service holding the vault takes it from [`GroupKeyArgs`] and exits at startup without it.
+//! Deposits pay the key's vault, so the profile encodes the vault address (`BRIDGE_ADDRESS`)
+//! for its chain from the key set by any other source, and leaves it unset without one.
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::ScriptBuf;
+use clap::{Args, ValueEnum};
+use lib_struct::chain::ChainParams;
+use lib_struct::verify::vault_script_pubkey;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+
+/// Environment variable naming the profile when the config file does not.
+pub const PROFILE_ENV: &str = "BRIDGE_PROFILE";
+
+/// Environment variable of the TSS group key.
+pub const GROUP_KEY_ENV: &str = "TSS_GROUP_KEY";
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    Mainnet,
+    /// Bitcoin testnet3 with Ethereum Sepolia.
+    Testnet,
+    /// Bitcoin signet with Ethereum Sepolia.
+    Signet,
+    /// A local `bitcoind -regtest` with Anvil, as in the integration test.
+    Regtest,
+    /// Litecoin mainnet with Arbitrum One, so its zkLTC contract is not the zkBTC one of
+    /// `mainnet`.
+    Litecoin,
+    /// Litecoin testnet4 with Arbitrum Sepolia.
+    LitecoinTestnet,
+}
+
+impl Profile {
+    pub fn chain(self) -> ChainParams {
+        match self {
+            Profile::Mainnet => ChainParams::bitcoin(bitcoin::Network::Bitcoin),
+            Profile::Testnet => ChainParams::bitcoin(bitcoin::Network::Testnet),
+            Profile::Signet => ChainParams::bitcoin(bitcoin::Network::Signet),
+            Profile::Regtest => ChainParams::bitcoin(bitcoin::Network::Regtest),
+            Profile::Litecoin => ChainParams::litecoin(),
+            Profile::LitecoinTestnet => ChainParams::litecoin_testnet(),
+        }
+    }
+
+    /// Bitcoin Core, or Litecoin Core, RPC endpoint on its default port.
+    pub fn bitcoin_rpc_url(self) -> &'static str {
+        match self {
+            Profile::Mainnet => "http://127.0.0.1:8332",
+            Profile::Testnet => "http://127.0.0.1:18332",
+            Profile::Signet => "http://127.0.0.1:38332",
+            Profile::Regtest => "http://127.0.0.1:18443",
+            Profile::Litecoin => "http://127.0.0.1:9332",
+            Profile::LitecoinTestnet => "http://127.0.0.1:19332",
+        }
+    }
+
+    /// Public Esplora API; none for regtest.
+    pub fn esplora_url(self) -> Option<&'static str> {
+        match self {
+            Profile::Mainnet => Some("https://blockstream.info/api"),
+            Profile::Testnet => Some("https://blockstream.info/testnet/api"),
+            Profile::Signet => Some("https://mempool.space/signet/api"),
+            Profile::Regtest => None,
+            Profile::Litecoin => Some("https://litecoinspace.org/api"),
+            Profile::LitecoinTestnet => Some("https://litecoinspace.org/testnet/api"),
+        }
+    }
+
+    /// Chain id of the EVM chain the zkBTC contract is deployed on. Each source chain's mainnet
+    /// gets its own, so a v2 memo naming the chain id cannot be minted from another chain's
+    /// deposit.
+    pub fn evm_chain_id(self) -> u64 {
+        match self {
+            Profile::Mainnet => 1,
+            Profile::Testnet | Profile::Signet => 11_155_111,
+            Profile::Regtest => 31_337,
+            Profile::Litecoin => 42_161,
+            Profile::LitecoinTestnet => 421_614,
+        }
+    }
+
+    /// EVM RPC endpoint; only regtest has a well-known one, a local Anvil.
+    pub fn eth_rpc_url(self) -> Option<&'static str> {
+        match self {
+            Profile::Regtest => Some("http://127.0.0.1:8545"),
+            _ => None,
+        }
+    }
+
+    /// Settings as `(environment variable, value)` pairs, as the config file maps them. The
+    /// vault address is `group_key`'s, and only filled in with one.
+    pub fn env_values(self, group_key: Option<&XOnlyPublicKey>) -> Vec<(&'static str, String)> {
+        let chain = self.chain();
+        let values = [
+            ("BITCOIN_NETWORK", Some(chain.name.clone())),
+            (
+                "BRIDGE_ADDRESS",
+                group_key.and_then(|key| chain.address_from_script(&vault_script_pubkey(key)).ok()),
+            ),
+            ("BITCOIN_RPC_URL", Some(self.bitcoin_rpc_url().to_string())),
+            ("ESPLORA_URL", self.esplora_url().map(str::to_string)),
+            ("EVM_CHAIN_ID", Some(self.evm_chain_id().to_string())),
+            ("ETH_RPC_URL", self.eth_rpc_url().map(str::to_string)),
+        ];
+        values
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+    }
+}
+
+/// The TSS group key the vault is derived from, as the signer group's DKG produced it.
+#[derive(Args, Clone, Copy, Debug)]
+pub struct GroupKeyArgs {
+    /// x-only TSS group key; `[bitcoin] group_key` in the config file. Required: there is no
+    /// default key.
+    #[clap(long, env = GROUP_KEY_ENV)]
+    pub group_key: XOnlyPublicKey,
+}
+
+impl GroupKeyArgs {
+    /// Key-path-only taproot script of the vault.
+    pub fn vault_script(&self) -> ScriptBuf {
+        vault_script_pubkey(&self.group_key)
+    }
+
+    /// The vault's address on `chain`.
+    pub fn vault_address(&self, chain: &ChainParams) -> Result<String, Box<dyn Error>> {
+        chain.address_from_script(&self.vault_script())
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Profile::Mainnet => "mainnet",
+            Profile::Testnet => "testnet",
+            Profile::Signet => "signet",
+            Profile::Regtest => "regtest",
+            Profile::Litecoin => "litecoin",
+            Profile::LitecoinTestnet => "litecoin-testnet",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,491 @@
+//! Settlement relayer: sends the settlement transactions of `submit` from the relayer account
+//! and sees them mined.
+//!
+//! The relayer owns the account's nonce: every settlement is sent at the account's mined
+//! transaction count, so one an earlier attempt left pending is replaced instead of queued
+//! behind, and the fees it was sent with are remembered for the replacement to outbid. A
+//! settlement not mined within `--bump-after-secs` is sent again at the same nonce with fees
+//! `--fee-bump-percent` higher, up to `--max-fee-bumps` times and never above
+//! `--max-fee-gwei`. The account must be the relayer's alone: a `submit` per circuit needs an
+//! account each.
+//!
+//! A revert, in the simulation or on-chain, is reported as a [`Revert`] with the contract's
+//! error decoded, so a caller can tell a settlement the contract refuses from one that failed
+//! on the way.
+
+use alloy::eips::BlockId;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, TxHash};
+use alloy::providers::Provider;
+use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use alloy::sol;
+use alloy::sol_types::decode_revert_reason;
+use alloy::transports::TransportError;
+use clap::Args;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Extra gas on top of the estimate, in percent, so small state changes between the estimate
+/// and inclusion do not run the transaction out of gas.
+const GAS_LIMIT_MARGIN_PERCENT: u64 = 20;
+
+/// How often a sent settlement is looked for in the chain.
+const RECEIPT_POLL: Duration = Duration::from_secs(5);
+
+sol! {
+    /// Custom errors of the ZKBTC bridge contract, for decoding its reverts.
+    interface ZKBTC {
+        error InvalidProof();
+        error InvalidProofFromVerifier();
+        error MintingRequestAlreadyProcessed();
+        error MintingAmountZero();
+        error MintingAmountTooSmall();
+        error BurnRequestNotFound();
+        error BurnAlreadyFulfilled();
+        error BurnRequestExpired();
+        error BurnRequestAlreadyReclaimed();
+        error OperatorUnderpaid();
+        error ReclaimNotRequester();
+        error BurnAmountZero();
+        error OperatorSendWrongRecipent();
+        error PayoutAlreadyProcessed();
+        error BurnIdMismatch();
+        error UnauthorizedVault();
+        error WrongDestinationChain();
+        error WrongNetwork();
+        error UnknownCheckpoint();
+        error VaultScriptUnset();
+        error BurnAmountTooSmall();
+        error BurnInsufficientBalance();
+        error BurnRequestStillOpen();
+        error InvalidAddress();
+    }
+}
+
+/// Errors meaning the contract has the settlement already: the deposit is minted, or the burn
+/// or its payout settled.
+const ALREADY_SETTLED: [&str; 3] = [
+    "MintingRequestAlreadyProcessed",
+    "BurnAlreadyFulfilled",
+    "PayoutAlreadyProcessed",
+];
+
+/// Gas pricing and replacement of the settlements.
+#[derive(Args, Clone, Debug)]
+pub struct RelayerArgs {
+    /// Seconds a settlement may wait to be mined before it is replaced with higher fees.
+    #[clap(long, env = "SETTLEMENT_BUMP_SECS", default_value_t = 180)]
+    pub bump_after_secs: u64,
+    /// Percent a replacement raises both fees by; nodes refuse a replacement raising them by
+    /// less than 10.
+    #[clap(
+        long,
+        env = "SETTLEMENT_FEE_BUMP_PERCENT",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(10..)
+    )]
+    pub fee_bump_percent: u64,
+    /// Replacements of a settlement before the attempt is given up; the last one stays in the
+    /// mempool, and the next attempt replaces it.
+    #[clap(long, env = "SETTLEMENT_MAX_FEE_BUMPS", default_value_t = 5)]
+    pub max_fee_bumps: u32,
+    /// Highest fee per gas a settlement is sent with, in gwei; replacements stop there.
+    #[clap(long, env = "SETTLEMENT_MAX_FEE_GWEI")]
+    pub max_fee_gwei: Option<u64>,
+}
+
+/// EIP-1559 fees per gas, in wei.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Fees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl Fees {
+    fn bumped(self, percent: u64) -> Fees {
+        let bump = |fee: u128| fee + (fee * percent as u128).div_ceil(100);
+        Fees {
+            max_fee_per_gas: bump(self.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(self.max_priority_fee_per_gas),
+        }
+    }
+
+    /// The higher of each fee.
+    fn max(self, other: Fees) -> Fees {
+        Fees {
+            max_fee_per_gas: self.max_fee_per_gas.max(other.max_fee_per_gas),
+            max_priority_fee_per_gas: self
+                .max_priority_fee_per_gas
+                .max(other.max_priority_fee_per_gas),
+        }
+    }
+
+    /// The fees, the fee per gas held to `cap` wei and the tip to the fee per gas.
+    fn capped(self, cap: Option<u128>) -> Fees {
+        let max_fee_per_gas = cap.map_or(self.max_fee_per_gas, |cap| self.max_fee_per_gas.min(cap));
+        Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+}
+
+/// A settlement the contract reverts.
+#[derive(Debug)]
+pub struct Revert {
+    /// The contract's error, decoded, or the node's message when the revert carries no data.
+    pub reason: String,
+    /// Mined transaction that reverted; none for a revert of the simulation.
+    pub tx_hash: Option<TxHash>,
+}
+
+impl Revert {
+    /// Whether the contract refuses the settlement because it has it already.
+    pub fn already_settled(&self) -> bool {
+        ALREADY_SETTLED.contains(&self.reason.as_str())
+    }
+}
+
+impl fmt::Display for Revert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tx_hash {
+            Some(tx_hash) => write!(f, "Settlement {} reverted: {}", tx_hash, self.reason),
+            None => write!(f, "Settlement call reverts: {}", self.reason),
+        }
+    }
+}
+
+impl Error for Revert {}
+
+/// Decodes revert data: a custom error of the contract by name, an `Error(string)` by its
+/// message, or a `Panic(uint256)` by its code; other data is returned as hex.
+pub fn decode_revert(data: &[u8]) -> String {
+    if let Some(name) = data
+        .get(..4)
+        .and_then(|selector| ZKBTC::ZKBTCErrors::name_by_selector(selector.try_into().ok()?))
+    {
+        return name.to_string();
+    }
+    decode_revert_reason(data).unwrap_or_else(|| format!("0x{}", hex::encode(data)))
+}
+
+/// The revert an `eth_call` or `eth_estimateGas` failed with, if that is how it failed.
+fn revert_of(e: &TransportError) -> Option<Revert> {
+    let payload = e.as_error_resp()?;
+    let reason = match payload.as_revert_data() {
+        Some(data) => decode_revert(&data),
+        None if payload.message.contains("revert") => payload.message.to_string(),
+        None => return None,
+    };
+    Some(Revert {
+        reason,
+        tx_hash: None,
+    })
+}
+
+/// A node error reported as `message`.
+fn reports(e: &TransportError, message: &str) -> bool {
+    e.as_error_resp()
+        .is_some_and(|payload| payload.message.to_lowercase().contains(message))
+}
+
+/// Latest settlement sent, kept until its nonce is mined.
+struct Sent {
+    nonce: u64,
+    fees: Fees,
+    hashes: Vec<TxHash>,
+}
+
+/// Nonce of the relayer account.
+pub struct NonceManager {
+    address: Address,
+    sent: Option<Sent>,
+}
+
+impl NonceManager {
+    pub fn new(address: Address) -> Self {
+        NonceManager {
+            address,
+            sent: None,
+        }
+    }
+
+    /// Nonce of the next settlement, the account's mined transaction count, and the lowest
+    /// fees replacing the settlement pending at it, if this relayer sent one.
+    async fn next<P: Provider>(
+        &mut self,
+        provider: &P,
+        bump_percent: u64,
+    ) -> Result<(u64, Option<Fees>), Box<dyn Error>> {
+        let nonce = provider
+            .get_transaction_count(self.address)
+            .latest()
+            .await?;
+        let floor = match &self.sent {
+            Some(sent) if sent.nonce == nonce => {
+                warn!(nonce, pending = ?sent.hashes, "Replacing the pending settlement");
+                Some(sent.fees.bumped(bump_percent))
+            }
+            _ => None,
+        };
+        if floor.is_none() {
+            self.sent = None;
+        }
+        Ok((nonce, floor))
+    }
+
+    /// Records a transaction sent at `nonce`.
+    fn sent(&mut self, nonce: u64, fees: Fees, hash: TxHash) {
+        match &mut self.sent {
+            Some(sent) if sent.nonce == nonce => {
+                sent.fees = fees;
+                sent.hashes.push(hash);
+            }
+            _ => {
+                self.sent = Some(Sent {
+                    nonce,
+                    fees,
+                    hashes: vec![hash],
+                })
+            }
+        }
+    }
+
+    /// Hashes sent at `nonce`.
+    fn hashes(&self, nonce: u64) -> Vec<TxHash> {
+        self.sent
+            .as_ref()
+            .filter(|sent| sent.nonce == nonce)
+            .map_or_else(Vec::new, |sent| sent.hashes.clone())
+    }
+
+    /// Forgets the settlement at `nonce`, mined or beaten.
+    fn settled(&mut self, nonce: u64) {
+        if self.sent.as_ref().is_some_and(|sent| sent.nonce == nonce) {
+            self.sent = None;
+        }
+    }
+}
+
+/// A settlement mined with its confirmations.
+#[derive(Debug)]
+pub struct Settled {
+    pub tx_hash: TxHash,
+    pub block: u64,
+    pub gas_used: u64,
+    /// Fee per gas paid, in wei.
+    pub gas_price: u128,
+}
+
+/// Sender of the settlements, over a provider signing with the relayer account.
+pub struct Relayer<P> {
+    provider: P,
+    args: RelayerArgs,
+    nonces: NonceManager,
+    confirmations: u64,
+}
+
+impl<P: Provider> Relayer<P> {
+    /// Relayer sending from `address`, which `provider` signs for, and waiting for
+    /// `confirmations` blocks on top of the settlement's block.
+    pub fn new(provider: P, address: Address, args: RelayerArgs, confirmations: u64) -> Self {
+        Relayer {
+            provider,
+            args,
+            nonces: NonceManager::new(address),
+            confirmations,
+        }
+    }
+
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Simulates `tx` and returns its gas limit, the estimate with a margin; a revert here
+    /// costs nothing.
+    pub async fn simulate(&self, tx: &TransactionRequest) -> Result<u64, Box<dyn Error>> {
+        if let Err(e) = self.provider.call(tx.clone()).await {
+            return Err(match revert_of(&e) {
+                Some(revert) => revert.into(),
+                None => format!("Settlement call failed: {}", e).into(),
+            });
+        }
+        let gas_estimate =
+            self.provider
+                .estimate_gas(tx.clone())
+                .await
+                .map_err(|e| -> Box<dyn Error> {
+                    match revert_of(&e) {
+                        Some(revert) => revert.into(),
+                        None => e.into(),
+                    }
+                })?;
+        let gas_limit = gas_estimate * (100 + GAS_LIMIT_MARGIN_PERCENT) / 100;
+        println!("Estimated gas: {} (limit {})", gas_estimate, gas_limit);
+        Ok(gas_limit)
+    }
+
+    /// Fees a settlement starts at: `fees` if given, else the node's estimate.
+    pub async fn fees(&self, fees: Option<Fees>) -> Result<Fees, Box<dyn Error>> {
+        if let Some(fees) = fees {
+            return Ok(fees);
+        }
+        let estimate = self.provider.estimate_eip1559_fees().await?;
+        Ok(Fees {
+            max_fee_per_gas: estimate.max_fee_per_gas,
+            max_priority_fee_per_gas: estimate.max_priority_fee_per_gas,
+        })
+    }
+
+    /// Sends `tx` with `gas_limit`, starting at `fees`, and waits until it is mined with its
+    /// confirmations, replacing it with higher fees while it is not.
+    pub async fn send(
+        &mut self,
+        tx: TransactionRequest,
+        gas_limit: u64,
+        fees: Fees,
+    ) -> Result<Settled, Box<dyn Error>> {
+        let cap = self
+            .args
+            .max_fee_gwei
+            .map(|gwei| gwei as u128 * 1_000_000_000);
+        let (nonce, floor) = self
+            .nonces
+            .next(&self.provider, self.args.fee_bump_percent)
+            .await?;
+        let mut fees = floor.map_or(fees, |floor| fees.max(floor)).capped(cap);
+        for bump in 0..=self.args.max_fee_bumps {
+            if bump > 0 {
+                let bumped = fees.bumped(self.args.fee_bump_percent).capped(cap);
+                if bumped.max_fee_per_gas <= fees.max_fee_per_gas {
+                    warn!(
+                        nonce,
+                        max_fee_per_gas = fees.max_fee_per_gas,
+                        "Settlement fee at --max-fee-gwei, not bumped further"
+                    );
+                    break;
+                }
+                fees = bumped;
+            }
+            let request = tx
+                .clone()
+                .with_nonce(nonce)
+                .with_gas_limit(gas_limit)
+                .with_max_fee_per_gas(fees.max_fee_per_gas)
+                .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+            match self.provider.send_transaction(request).await {
+                Ok(pending) => {
+                    let hash = *pending.tx_hash();
+                    self.nonces.sent(nonce, fees, hash);
+                    println!("Transaction sent: {}", hash);
+                    info!(%hash, nonce, bump, max_fee_per_gas = fees.max_fee_per_gas, max_priority_fee_per_gas = fees.max_priority_fee_per_gas, "Settlement sent");
+                }
+                // A transaction of an earlier run is pending at the nonce, with higher fees
+                Err(e) if reports(&e, "underpriced") => {
+                    warn!(nonce, bump, error = %e, "Settlement underpriced, bumping the fees");
+                    continue;
+                }
+                Err(e) if reports(&e, "nonce too low") => {
+                    if let Some(receipt) = self.mined(nonce).await? {
+                        return self.confirm(&tx, receipt, nonce).await;
+                    }
+                    self.nonces.settled(nonce);
+                    return Err(format!(
+                        "Nonce {} was taken by another transaction of the relayer account",
+                        nonce
+                    )
+                    .into());
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            let deadline = Instant::now() + Duration::from_secs(self.args.bump_after_secs);
+            while Instant::now() < deadline {
+                tokio::time::sleep(RECEIPT_POLL).await;
+                if let Some(receipt) = self.mined(nonce).await? {
+                    return self.confirm(&tx, receipt, nonce).await;
+                }
+                let count = self
+                    .provider
+                    .get_transaction_count(self.nonces.address)
+                    .latest()
+                    .await?;
+                if count > nonce {
+                    // A replacement may be mined in the block the count was read after
+                    if let Some(receipt) = self.mined(nonce).await? {
+                        return self.confirm(&tx, receipt, nonce).await;
+                    }
+                    self.nonces.settled(nonce);
+                    return Err(format!(
+                        "Nonce {} was taken by another transaction of the relayer account",
+                        nonce
+                    )
+                    .into());
+                }
+            }
+            warn!(
+                nonce,
+                bump,
+                waited_secs = self.args.bump_after_secs,
+                "Settlement not mined yet"
+            );
+        }
+        Err(format!(
+            "Settlement at nonce {} not mined after {} fee bumps; left pending for the next attempt to replace",
+            nonce, self.args.max_fee_bumps
+        )
+        .into())
+    }
+
+    /// Receipt of whichever settlement sent at `nonce` is mined.
+    async fn mined(&self, nonce: u64) -> Result<Option<TransactionReceipt>, Box<dyn Error>> {
+        for hash in self.nonces.hashes(nonce) {
+            if let Some(receipt) = self.provider.get_transaction_receipt(hash).await? {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Waits for the confirmations of a mined settlement, and decodes its revert, if it
+    /// reverted, by replaying it on the state of its block.
+    async fn confirm(
+        &mut self,
+        tx: &TransactionRequest,
+        receipt: TransactionReceipt,
+        nonce: u64,
+    ) -> Result<Settled, Box<dyn Error>> {
+        self.nonces.settled(nonce);
+        let hash = receipt.transaction_hash;
+        let block = receipt.block_number.unwrap_or_default();
+        if !receipt.status() {
+            let replay = self.provider.call(tx.clone()).block(BlockId::number(block));
+            let reason = match replay.await {
+                Err(e) => revert_of(&e).map_or_else(|| e.to_string(), |revert| revert.reason),
+                Ok(_) => "no reason; the call succeeds on the block's state".to_string(),
+            };
+            return Err(Revert {
+                reason,
+                tx_hash: Some(hash),
+            }
+            .into());
+        }
+        // A reorganization may drop the settlement while it confirms
+        while self.provider.get_block_number().await? + 1 < block + self.confirmations {
+            tokio::time::sleep(RECEIPT_POLL).await;
+        }
+        let receipt = self
+            .provider
+            .get_transaction_receipt(hash)
+            .await?
+            .ok_or_else(|| format!("Settlement {} left the chain while confirming", hash))?;
+        Ok(Settled {
+            tx_hash: hash,
+            block: receipt.block_number.unwrap_or(block),
+            gas_used: receipt.gas_used,
+            gas_price: receipt.effective_gas_price,
+        })
+    }
+}
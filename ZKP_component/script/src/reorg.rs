@@ -0,0 +1,155 @@
+//! Reorganization monitor of the Bitcoin chain. It keeps the hashes of the latest blocks and,
+//! at each poll, walks them down from the highest until one is still in the best chain: the
+//! blocks above it were orphaned. Work built on an orphaned block, such as a bundle whose
+//! chain holds it, proves a chain that no longer exists and has to be redone. Each
+//! reorganization found is kept until it is reported, so a report that fails is retried.
+
+use crate::bundle::BundleSource;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// A block of the chain, by height and hash.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct ChainBlock {
+    pub height: u32,
+    pub hash: String,
+}
+
+/// A reorganization seen by the monitor.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Reorg {
+    /// First height whose block was replaced.
+    pub fork_height: u32,
+    /// Tracked blocks that left the best chain, lowest first.
+    pub orphaned: Vec<ChainBlock>,
+    /// Tip height after the reorganization.
+    pub tip: u32,
+    /// No tracked block is left in the best chain: the reorganization may go deeper than
+    /// `fork_height`, below the window the monitor keeps.
+    pub beyond_window: bool,
+}
+
+impl Reorg {
+    /// Blocks the reorganization removed, as far as the monitor knows.
+    pub fn depth(&self) -> u32 {
+        self.orphaned.len() as u32
+    }
+
+    pub fn orphaned_hashes(&self) -> Vec<String> {
+        self.orphaned
+            .iter()
+            .map(|block| block.hash.clone())
+            .collect()
+    }
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+pub struct ReorgMonitor {
+    db: sled::Db,
+    /// Hashes of the latest blocks of the best chain, keyed by big-endian height.
+    blocks: sled::Tree,
+    /// Reorganizations not reported yet, keyed by big-endian id.
+    pending: sled::Tree,
+    /// Blocks kept below the tip.
+    window: u32,
+}
+
+impl ReorgMonitor {
+    /// Opens the monitor's tree of `db`, keeping the latest `window` blocks.
+    pub fn open(db: &sled::Db, window: u32) -> Result<Self, Box<dyn Error>> {
+        Ok(ReorgMonitor {
+            db: db.clone(),
+            blocks: db.open_tree("recent_blocks")?,
+            pending: db.open_tree("pending_reorgs")?,
+            window: window.max(1),
+        })
+    }
+
+    /// Tracked blocks, lowest first.
+    pub fn tracked(&self) -> Result<Vec<ChainBlock>, Box<dyn Error>> {
+        self.blocks
+            .iter()
+            .map(|entry| {
+                let (height, hash) = entry?;
+                Ok(ChainBlock {
+                    height: u32::from_be_bytes(height.as_ref().try_into()?),
+                    hash: String::from_utf8(hash.to_vec())?,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the best chain from `source` and returns the reorganization since the last call,
+    /// if any, also kept as pending. The tracked blocks then follow the new best chain up to
+    /// its tip.
+    pub fn observe(&self, source: &dyn BundleSource) -> Result<Option<Reorg>, Box<dyn Error>> {
+        let tip = source.tip_height()?;
+        let tracked = self.tracked()?;
+
+        // === Walk down to the highest tracked block still in the best chain ===
+        let mut common = None;
+        let mut orphaned = Vec::new();
+        for block in tracked.iter().rev() {
+            if block.height <= tip && source.block_hash(block.height)? == block.hash {
+                common = Some(block.height);
+                break;
+            }
+            orphaned.push(block.clone());
+        }
+        orphaned.reverse();
+        for block in &orphaned {
+            self.blocks.remove(block.height.to_be_bytes())?;
+        }
+
+        // === Follow the best chain up to the tip, and forget what left the window ===
+        let lowest = (tip + 1).saturating_sub(self.window);
+        let next = match (common, orphaned.first()) {
+            (Some(height), _) => height + 1,
+            (None, Some(block)) => block.height.max(lowest),
+            (None, None) => lowest,
+        };
+        for height in next.max(lowest)..=tip {
+            self.blocks
+                .insert(height.to_be_bytes(), source.block_hash(height)?.as_bytes())?;
+        }
+        for block in tracked.iter().filter(|block| block.height < lowest) {
+            self.blocks.remove(block.height.to_be_bytes())?;
+        }
+
+        let reorg = orphaned.first().map(|first| Reorg {
+            fork_height: first.height,
+            tip,
+            beyond_window: common.is_none(),
+            orphaned: orphaned.clone(),
+        });
+        if let Some(reorg) = &reorg {
+            self.pending.insert(
+                self.db.generate_id()?.to_be_bytes(),
+                serde_json::to_vec(reorg)?,
+            )?;
+        }
+        self.db.flush()?;
+        Ok(reorg)
+    }
+
+    /// Reorganizations not reported yet, oldest first, with their ids.
+    pub fn pending(&self) -> Result<Vec<(u64, Reorg)>, Box<dyn Error>> {
+        self.pending
+            .iter()
+            .map(|entry| {
+                let (id, bytes) = entry?;
+                Ok((u64::from_be_bytes(id.as_ref().try_into()?), decode(&bytes)?))
+            })
+            .collect()
+    }
+
+    /// Forgets reorganization `id` once it is reported.
+    pub fn reported(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        self.pending.remove(id.to_be_bytes())?;
+        Ok(())
+    }
+}
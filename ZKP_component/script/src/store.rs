@@ -0,0 +1,399 @@
+//! sled databases of the chain watchers, so a restart neither misses nor repeats an event:
+//! - `watch`: every deposit paying the vault or a per-request deposit address with its
+//!   confirmation count and proving status, the Bitcoin scan position, and the deposit
+//!   addresses watched (`deposit_address::DepositAddressBook`), and the latest blocks with the
+//!   reorganizations not reported yet (`reorg::ReorgMonitor`).
+//! - `burn-listener`: the payout queue of the TSS payout pipeline, one job per burn request
+//!   and one per rejected deposit to refund, and the next Ethereum block to read burn events
+//!   from. `payout-scheduler` reads the queue
+//!   over the listener's HTTP API.
+
+use crate::deposit_address::DepositAddressBook;
+use crate::reorg::ReorgMonitor;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Key of the scan position in the default tree.
+const SCAN_POSITION_KEY: &[u8] = b"scan_position";
+/// Key of the next Ethereum block to read in the default tree.
+const NEXT_BLOCK_KEY: &[u8] = b"next_block";
+
+/// Where a deposit is in the proving pipeline.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum DepositStatus {
+    /// Waiting for the finality threshold.
+    Confirming,
+    /// Mint proof queued on the proof service.
+    Queued { job_id: u64 },
+    /// Mint proof requested on the job bus, under the id the bus gave the request.
+    Requested { event: String },
+}
+
+/// A transaction paying the vault or a request's deposit address.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DepositRecord {
+    pub txid: String,
+    pub block_hash: String,
+    pub height: u32,
+    /// Value paid to the deposit address, over all its outputs.
+    pub amount_sats: u64,
+    /// Request whose deposit address was paid; `None` for the vault address.
+    #[serde(default)]
+    pub request_id: Option<u64>,
+    /// Confirmations at the last poll, counting the deposit's block.
+    pub confirmations: u32,
+    pub status: DepositStatus,
+}
+
+/// Next block to scan, and the hash of the last scanned one to detect a reorganization.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ScanPosition {
+    pub next_height: u32,
+    pub last_hash: Option<String>,
+}
+
+pub struct DepositStore {
+    db: sled::Db,
+    /// Deposit records keyed by txid.
+    deposits: sled::Tree,
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// Opens the database at `path`, creating it if needed; without a path the database is
+/// temporary and dropped on exit.
+fn open_db(path: Option<&Path>) -> Result<sled::Db, Box<dyn Error>> {
+    let config = match path {
+        Some(path) => sled::Config::new().path(path),
+        None => sled::Config::new().temporary(true),
+    };
+    Ok(config.open()?)
+}
+
+impl DepositStore {
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let db = open_db(path)?;
+        let deposits = db.open_tree("deposits")?;
+        Ok(DepositStore { db, deposits })
+    }
+
+    /// The per-request deposit addresses watched, kept in the same database.
+    pub fn address_book(&self) -> Result<DepositAddressBook, Box<dyn Error>> {
+        DepositAddressBook::open(&self.db)
+    }
+
+    /// The reorganization monitor of `watch`, kept in the same database and following the
+    /// latest `window` blocks.
+    pub fn reorg_monitor(&self, window: u32) -> Result<ReorgMonitor, Box<dyn Error>> {
+        ReorgMonitor::open(&self.db, window)
+    }
+
+    pub fn scan_position(&self) -> Result<Option<ScanPosition>, Box<dyn Error>> {
+        self.db
+            .get(SCAN_POSITION_KEY)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn set_scan_position(&self, position: &ScanPosition) -> Result<(), Box<dyn Error>> {
+        self.db
+            .insert(SCAN_POSITION_KEY, serde_json::to_vec(position)?)?;
+        Ok(())
+    }
+
+    /// Records a deposit found in a scanned block. A deposit already queued keeps its record,
+    /// so rescanning a block after a crash or a reorganization never queues it twice.
+    pub fn record_found(&self, deposit: &DepositRecord) -> Result<(), Box<dyn Error>> {
+        if let Some(existing) = self.get(&deposit.txid)? {
+            if existing.status != DepositStatus::Confirming {
+                return Ok(());
+            }
+        }
+        self.put(deposit)
+    }
+
+    pub fn put(&self, deposit: &DepositRecord) -> Result<(), Box<dyn Error>> {
+        self.deposits
+            .insert(deposit.txid.as_bytes(), serde_json::to_vec(deposit)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, txid: &str) -> Result<Option<DepositRecord>, Box<dyn Error>> {
+        self.deposits
+            .get(txid.as_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Every recorded deposit, ordered by height.
+    pub fn deposits(&self) -> Result<Vec<DepositRecord>, Box<dyn Error>> {
+        let mut deposits = self
+            .deposits
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect::<Result<Vec<DepositRecord>, _>>()?;
+        deposits.sort_by_key(|deposit| deposit.height);
+        Ok(deposits)
+    }
+
+    /// Forgets the deposits at or above `height`, whose blocks left the best chain and are
+    /// about to be rescanned; a queued one is queued again once its deposit is final again.
+    /// Returns the deposits dropped.
+    pub fn drop_from(&self, height: u32) -> Result<Vec<DepositRecord>, Box<dyn Error>> {
+        let mut dropped = Vec::new();
+        for deposit in self.deposits()? {
+            if deposit.height >= height {
+                self.deposits.remove(deposit.txid.as_bytes())?;
+                dropped.push(deposit);
+            }
+        }
+        Ok(dropped)
+    }
+
+    /// Writes the pending changes to disk.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Where a payout job is in the payout pipeline.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum PayoutStatus {
+    /// Burn event seen in a block that has not reached the finality depth.
+    Confirming,
+    /// Final and valid; waiting for the payout pipeline.
+    Ready,
+    /// Final, but the payout cannot be made, e.g. the BTC address is not of the network.
+    Invalid { reason: String },
+    /// Paid out by the payout pipeline.
+    Paid { payout_txid: String },
+}
+
+/// A burn request of the bridge contract, to be paid out in BTC.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PayoutJob {
+    pub burn_id: u64,
+    /// Ethereum account that burned.
+    pub user: String,
+    /// Satoshis owed to the burner.
+    pub amount_sats: u64,
+    pub btc_address: String,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub status: PayoutStatus,
+}
+
+/// A deposit that cannot be minted, to be paid back to the address that funded it. The amount
+/// is not part of the job: a mint proof rejecting a deposit commits none, so the payout
+/// pipeline reads what the deposit paid the vault from the chain.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RefundJob {
+    pub deposit_txid: String,
+    /// Request the deposit's memo names, if known.
+    #[serde(default)]
+    pub request_id: Option<u64>,
+    /// Address the deposit's first input spent from, as the mint proof commits it.
+    pub btc_address: String,
+    /// Why the deposit was rejected, e.g. the proof's reject reason or the policy's denial.
+    pub reason: String,
+    pub requested_at: u64,
+    /// Never `Confirming`: the deposit is final when its refund is requested.
+    pub status: PayoutStatus,
+}
+
+pub struct PayoutQueue {
+    db: sled::Db,
+    /// Payout jobs keyed by big-endian burn id, so they iterate in burn order.
+    payouts: sled::Tree,
+    /// Refund jobs keyed by deposit txid.
+    refunds: sled::Tree,
+}
+
+impl PayoutQueue {
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let db = open_db(path)?;
+        let payouts = db.open_tree("payouts")?;
+        let refunds = db.open_tree("refunds")?;
+        Ok(PayoutQueue {
+            db,
+            payouts,
+            refunds,
+        })
+    }
+
+    /// Next Ethereum block whose burn events have not been finalized.
+    pub fn next_block(&self) -> Result<Option<u64>, Box<dyn Error>> {
+        self.db
+            .get(NEXT_BLOCK_KEY)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn set_next_block(&self, block: u64) -> Result<(), Box<dyn Error>> {
+        self.db
+            .insert(NEXT_BLOCK_KEY, serde_json::to_vec(&block)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, burn_id: u64) -> Result<Option<PayoutJob>, Box<dyn Error>> {
+        self.payouts
+            .get(burn_id.to_be_bytes())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    fn put(&self, job: &PayoutJob) -> Result<(), Box<dyn Error>> {
+        self.payouts
+            .insert(job.burn_id.to_be_bytes(), serde_json::to_vec(job)?)?;
+        Ok(())
+    }
+
+    /// Records a job, unless its burn is already final: a burn read again after a restart
+    /// keeps its status, so it is never paid twice. Returns whether the job was written.
+    pub fn record(&self, job: &PayoutJob) -> Result<bool, Box<dyn Error>> {
+        if let Some(existing) = self.get(job.burn_id)? {
+            if existing.status != PayoutStatus::Confirming {
+                return Ok(false);
+            }
+        }
+        self.put(job)?;
+        Ok(true)
+    }
+
+    /// Forgets a job still confirming, whose burn event was removed by a reorganization.
+    pub fn remove_confirming(&self, burn_id: u64) -> Result<(), Box<dyn Error>> {
+        if let Some(job) = self.get(burn_id)? {
+            if job.status == PayoutStatus::Confirming {
+                self.payouts.remove(burn_id.to_be_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every job, in burn order.
+    pub fn jobs(&self) -> Result<Vec<PayoutJob>, Box<dyn Error>> {
+        self.payouts
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Jobs the payout pipeline should pay, in burn order.
+    pub fn ready(&self) -> Result<Vec<PayoutJob>, Box<dyn Error>> {
+        Ok(self
+            .jobs()?
+            .into_iter()
+            .filter(|job| job.status == PayoutStatus::Ready)
+            .collect())
+    }
+
+    /// Marks a ready job paid by `payout_txid`; called by the payout pipeline. Marking it paid
+    /// by the same transaction again is a no-op, so a pipeline that crashed can retry.
+    pub fn mark_paid(&self, burn_id: u64, payout_txid: &str) -> Result<(), Box<dyn Error>> {
+        let mut job = self
+            .get(burn_id)?
+            .ok_or_else(|| format!("No payout job for burn {}", burn_id))?;
+        if let PayoutStatus::Paid {
+            payout_txid: paid_by,
+        } = &job.status
+        {
+            if paid_by == payout_txid {
+                return Ok(());
+            }
+        }
+        if job.status != PayoutStatus::Ready {
+            return Err(format!("Burn {} is not ready to pay: {:?}", burn_id, job.status).into());
+        }
+        job.status = PayoutStatus::Paid {
+            payout_txid: payout_txid.to_string(),
+        };
+        self.put(&job)
+    }
+
+    pub fn refund(&self, deposit_txid: &str) -> Result<Option<RefundJob>, Box<dyn Error>> {
+        self.refunds
+            .get(deposit_txid)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    fn put_refund(&self, job: &RefundJob) -> Result<(), Box<dyn Error>> {
+        self.refunds
+            .insert(job.deposit_txid.as_bytes(), serde_json::to_vec(job)?)?;
+        Ok(())
+    }
+
+    /// Records a refund, unless one was already requested for the deposit: a deposit rejected
+    /// again, e.g. by a retried proof, is never refunded twice. Returns whether the job was
+    /// written.
+    pub fn request_refund(&self, job: &RefundJob) -> Result<bool, Box<dyn Error>> {
+        if self.refund(&job.deposit_txid)?.is_some() {
+            return Ok(false);
+        }
+        self.put_refund(job)?;
+        Ok(true)
+    }
+
+    /// Every refund, in txid order.
+    pub fn refunds(&self) -> Result<Vec<RefundJob>, Box<dyn Error>> {
+        self.refunds
+            .iter()
+            .values()
+            .map(|bytes| decode(&bytes?))
+            .collect()
+    }
+
+    /// Refunds the payout pipeline should pay, oldest request first.
+    pub fn ready_refunds(&self) -> Result<Vec<RefundJob>, Box<dyn Error>> {
+        let mut ready: Vec<RefundJob> = self
+            .refunds()?
+            .into_iter()
+            .filter(|job| job.status == PayoutStatus::Ready)
+            .collect();
+        ready.sort_by_key(|job| job.requested_at);
+        Ok(ready)
+    }
+
+    /// Marks a ready refund paid by `refund_txid`, as `mark_paid` does a payout.
+    pub fn mark_refunded(
+        &self,
+        deposit_txid: &str,
+        refund_txid: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut job = self
+            .refund(deposit_txid)?
+            .ok_or_else(|| format!("No refund for deposit {}", deposit_txid))?;
+        if let PayoutStatus::Paid { payout_txid } = &job.status {
+            if payout_txid == refund_txid {
+                return Ok(());
+            }
+        }
+        if job.status != PayoutStatus::Ready {
+            return Err(format!(
+                "Deposit {} is not ready to refund: {:?}",
+                deposit_txid, job.status
+            )
+            .into());
+        }
+        job.status = PayoutStatus::Paid {
+            payout_txid: refund_txid.to_string(),
+        };
+        self.put_refund(&job)
+    }
+
+    /// Writes the pending changes to disk.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
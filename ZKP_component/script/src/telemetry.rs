@@ -0,0 +1,158 @@
+//! OpenTelemetry tracing of the binaries. With `OTEL_EXPORTER_OTLP_ENDPOINT` set, every span
+//! is exported over OTLP/gRPC as well as logged, under the binary's name unless
+//! `OTEL_SERVICE_NAME` names the service. The other `OTEL_*` variables of the OTLP exporter
+//! (headers, timeout, batch delay) apply as usual.
+//!
+//! The spans of one swap carry its `swap_id` ([`bridge_core::swap_id`]) in every process, and
+//! the W3C trace context goes along with each hand-over: as the `traceparent` header of an
+//! HTTP request (`watch` to `serve`), and as `TRACEPARENT` in the environment of a command run
+//! for the swap (`payout-scheduler` to the TSS coordinator), so the spans join one trace.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Collector the spans are exported to; no export without it.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+/// Trace context handed to a child process, as the W3C `traceparent` and `tracestate` headers.
+const TRACEPARENT_ENV: &str = "TRACEPARENT";
+const TRACESTATE_ENV: &str = "TRACESTATE";
+/// Swap ids handed to a child process along with the trace context, comma-separated.
+pub const SWAP_IDS_ENV: &str = "SWAP_IDS";
+
+/// Runtime of the exporter, apart from any runtime of the binary, so synchronous binaries
+/// export too and a busy `serve` worker does not hold the export back.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+static PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+/// Name of the running binary, the default service name.
+fn binary_name() -> String {
+    std::env::args()
+        .next()
+        .and_then(|path| {
+            std::path::Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "bridge".to_string())
+}
+
+fn exporting_tracer() -> Result<Tracer, Box<dyn Error>> {
+    let runtime = match RUNTIME.get() {
+        Some(runtime) => runtime,
+        None => {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("otlp-export")
+                .enable_all()
+                .build()?;
+            RUNTIME.get_or_init(|| runtime)
+        }
+    };
+    // The gRPC channel and the batch task are spawned on the exporter's runtime
+    let _guard = runtime.enter();
+    let exporter = SpanExporter::builder().with_tonic().build()?;
+    let mut resource = Resource::default();
+    if std::env::var_os(SERVICE_NAME_ENV).is_none() {
+        resource = resource.merge(&Resource::new([KeyValue::new(
+            "service.name",
+            binary_name(),
+        )]));
+    }
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource)
+        .build();
+    let tracer = provider.tracer("bridge");
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let _ = PROVIDER.set(provider);
+    Ok(tracer)
+}
+
+/// Layer exporting the spans when `OTEL_EXPORTER_OTLP_ENDPOINT` is set. An exporter that
+/// cannot be set up is reported on stderr, and the binary runs without it.
+pub(crate) fn otlp_layer<S>() -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    std::env::var_os(OTLP_ENDPOINT_ENV)?;
+    match exporting_tracer() {
+        Ok(tracer) => Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        Err(e) => {
+            eprintln!("OTLP export disabled: {}", e);
+            None
+        }
+    }
+}
+
+/// Exports the spans still batched; a short-lived binary calls it before exiting.
+pub fn flush() {
+    if let Some(provider) = PROVIDER.get() {
+        for result in provider.force_flush() {
+            if let Err(e) = result {
+                eprintln!("Failed to export spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Trace context of the current span, as header name and value pairs for an outgoing
+/// request. Empty without export.
+pub fn trace_headers() -> Vec<(String, String)> {
+    let mut fields = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&Span::current().context(), &mut fields)
+    });
+    fields
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+}
+
+/// Trace context of the current span, as environment variables of a command run for it.
+pub fn trace_env() -> Vec<(String, String)> {
+    trace_headers()
+        .into_iter()
+        .map(|(name, value)| (name.to_uppercase(), value))
+        .collect()
+}
+
+fn set_parent(span: &Span, fields: HashMap<String, String>) {
+    if fields.is_empty() {
+        return;
+    }
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&fields));
+    span.set_parent(parent);
+}
+
+/// Makes `span` part of the trace of an incoming request carrying a `traceparent` header.
+pub fn set_parent_from_headers(span: &Span, headers: &axum::http::HeaderMap) {
+    let fields = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+    set_parent(span, fields);
+}
+
+/// Makes `span` part of the trace in `TRACEPARENT`, when another process of the swap ran this
+/// binary.
+pub fn set_parent_from_env(span: &Span) {
+    let fields = [TRACEPARENT_ENV, TRACESTATE_ENV]
+        .into_iter()
+        .filter_map(|name| Some((name.to_lowercase(), std::env::var(name).ok()?)))
+        .collect();
+    set_parent(span, fields);
+}
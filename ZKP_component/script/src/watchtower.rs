@@ -0,0 +1,322 @@
+//! Vault watchtower of the `watchtower` binary: every output paying the vault is tracked until
+//! it is spent, and every transaction spending one, seen in the mempool or in a block, is
+//! checked against the payout queue. The payout pipeline only signs transactions paying its
+//! jobs, so a spend it does not account for means the group key signed outside of it, and is
+//! the earliest sign of a compromised key.
+//!
+//! A spend is authorized when each of its outputs pays the vault back or pays a distinct
+//! payout or refund job of the queue, to the job's address, that is ready or paid by this
+//! transaction, and no more than the job is owed: a payout its burn's amount, a refund what
+//! its deposit paid the vault. The batch fee is taken from those outputs, so a batch never
+//! pays more.
+
+use crate::store::{PayoutJob, PayoutStatus, RefundJob, ScanPosition};
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hash_types::Txid;
+use bitcoin::network::Network;
+use bitcoin::{Address, OutPoint, Script, Transaction};
+use bridge_core::now;
+use lib_struct::verify::address_script_pubkey;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Key of the scan position in the default tree.
+const SCAN_POSITION_KEY: &[u8] = b"scan_position";
+
+/// An output paying the vault.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct VaultOutput {
+    pub txid: String,
+    pub vout: u32,
+    pub value_sats: u64,
+    /// Height of its block.
+    pub height: u32,
+    /// Transaction spending it, once seen; a replacement of that transaction takes its place.
+    pub spent_by: Option<String>,
+}
+
+impl VaultOutput {
+    pub fn outpoint(&self) -> Result<OutPoint, Box<dyn Error>> {
+        Ok(OutPoint::new(Txid::from_str(&self.txid)?, self.vout))
+    }
+}
+
+/// A transaction spending vault outputs, and whether the payout queue accounts for it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct VaultSpend {
+    pub txid: String,
+    pub tx_hex: String,
+    /// Vault outputs it spends, as `txid:vout`.
+    pub spent: Vec<String>,
+    /// Sats of the vault outputs it spends.
+    pub spent_sats: u64,
+    pub authorized: bool,
+    /// Jobs it pays when authorized, else why it is not.
+    pub detail: String,
+    /// Found in the mempool before it was mined.
+    pub seen_in_mempool: bool,
+    /// Height of its block once mined.
+    pub height: Option<u32>,
+    pub detected_at: u64,
+    /// Alert of an unauthorized spend delivered; `false` for an authorized one.
+    pub alerted: bool,
+}
+
+impl VaultSpend {
+    /// A spend of `spent` by `tx`, found now, judged by `verdict` of [`authorize`].
+    pub fn new(
+        tx: &Transaction,
+        spent: &[VaultOutput],
+        verdict: Result<Vec<String>, String>,
+        height: Option<u32>,
+    ) -> Self {
+        let authorized = verdict.is_ok();
+        VaultSpend {
+            txid: tx.compute_txid().to_string(),
+            tx_hex: serialize_hex(tx),
+            spent: spent
+                .iter()
+                .map(|output| format!("{}:{}", output.txid, output.vout))
+                .collect(),
+            spent_sats: spent.iter().map(|output| output.value_sats).sum(),
+            authorized,
+            detail: verdict.map(|jobs| jobs.join(", ")).unwrap_or_else(|e| e),
+            seen_in_mempool: height.is_none(),
+            height,
+            detected_at: now(),
+            alerted: false,
+        }
+    }
+}
+
+/// Checks `tx` against the payout queue's `payouts` and `refunds`. Returns the jobs it pays,
+/// e.g. `burn 3`, or why it is unauthorized. `deposit_values` holds what each deposit paid the
+/// vault by txid, bounding its refund; a refund whose deposit value is unknown is not accepted.
+pub fn authorize(
+    tx: &Transaction,
+    vault_spk: &Script,
+    payouts: &[PayoutJob],
+    refunds: &[RefundJob],
+    deposit_values: &BTreeMap<String, u64>,
+    network: Network,
+) -> Result<Vec<String>, String> {
+    let txid = tx.compute_txid().to_string();
+    let payable = |status: &PayoutStatus| match status {
+        PayoutStatus::Ready => true,
+        PayoutStatus::Paid { payout_txid } => *payout_txid == txid,
+        _ => false,
+    };
+    let pays = |address: &str, script: &Script| {
+        address_script_pubkey(address, network).is_ok_and(|expected| expected == *script)
+    };
+
+    let mut used_payouts = BTreeSet::new();
+    let mut used_refunds = BTreeSet::new();
+    let mut paid = Vec::new();
+    for (vout, output) in tx.output.iter().enumerate() {
+        let script = output.script_pubkey.as_script();
+        if script == vault_spk {
+            continue;
+        }
+        let value_sats = output.value.to_sat();
+        let payout = payouts.iter().find(|job| {
+            !used_payouts.contains(&job.burn_id)
+                && payable(&job.status)
+                && value_sats <= job.amount_sats
+                && pays(&job.btc_address, script)
+        });
+        if let Some(job) = payout {
+            used_payouts.insert(job.burn_id);
+            paid.push(format!("burn {}", job.burn_id));
+            continue;
+        }
+        let refund = refunds.iter().find(|job| {
+            !used_refunds.contains(&job.deposit_txid)
+                && payable(&job.status)
+                && deposit_values
+                    .get(&job.deposit_txid)
+                    .is_some_and(|deposited| value_sats <= *deposited)
+                && pays(&job.btc_address, script)
+        });
+        if let Some(job) = refund {
+            used_refunds.insert(job.deposit_txid.clone());
+            paid.push(format!("refund of {}", job.deposit_txid));
+            continue;
+        }
+        return Err(format!(
+            "output {} pays {} sats to {}, which no ready payout or refund job is owed",
+            vout,
+            value_sats,
+            Address::from_script(script, network)
+                .map(|address| address.to_string())
+                .unwrap_or_else(|_| script.to_hex_string())
+        ));
+    }
+    if paid.is_empty() {
+        return Err("pays no payout or refund job".to_string());
+    }
+    Ok(paid)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+pub struct WatchtowerStore {
+    db: sled::Db,
+    /// Vault outputs keyed by `txid:vout`, spent ones included.
+    outputs: sled::Tree,
+    /// Vault spends keyed by txid.
+    spends: sled::Tree,
+}
+
+impl WatchtowerStore {
+    /// Opens the database at `path`, creating it if needed; without a path the database is
+    /// temporary and dropped on exit.
+    pub fn open(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let config = match path {
+            Some(path) => sled::Config::new().path(path),
+            None => sled::Config::new().temporary(true),
+        };
+        let db = config.open()?;
+        let outputs = db.open_tree("vault_outputs")?;
+        let spends = db.open_tree("vault_spends")?;
+        Ok(WatchtowerStore {
+            db,
+            outputs,
+            spends,
+        })
+    }
+
+    pub fn scan_position(&self) -> Result<Option<ScanPosition>, Box<dyn Error>> {
+        self.db
+            .get(SCAN_POSITION_KEY)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn set_scan_position(&self, position: &ScanPosition) -> Result<(), Box<dyn Error>> {
+        self.db
+            .insert(SCAN_POSITION_KEY, serde_json::to_vec(position)?)?;
+        Ok(())
+    }
+
+    pub fn output(&self, outpoint: &OutPoint) -> Result<Option<VaultOutput>, Box<dyn Error>> {
+        self.outputs
+            .get(outpoint.to_string())?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Tracks `output`; returns `false` if it already was.
+    pub fn track(&self, output: &VaultOutput) -> Result<bool, Box<dyn Error>> {
+        let key = format!("{}:{}", output.txid, output.vout);
+        if self.outputs.contains_key(&key)? {
+            return Ok(false);
+        }
+        self.outputs.insert(key, serde_json::to_vec(output)?)?;
+        Ok(true)
+    }
+
+    /// Every vault output seen, spent or not.
+    pub fn outputs(&self) -> Result<Vec<VaultOutput>, Box<dyn Error>> {
+        self.outputs.iter().map(|entry| decode(&entry?.1)).collect()
+    }
+
+    /// Vault outputs no transaction seen spends.
+    pub fn unspent(&self) -> Result<Vec<VaultOutput>, Box<dyn Error>> {
+        Ok(self
+            .outputs()?
+            .into_iter()
+            .filter(|output| output.spent_by.is_none())
+            .collect())
+    }
+
+    /// Vault outputs no mined transaction spends: unspent ones, and those spent in the mempool,
+    /// whose spend may still be replaced.
+    pub fn unsettled(&self) -> Result<Vec<VaultOutput>, Box<dyn Error>> {
+        let mut unsettled = Vec::new();
+        for output in self.outputs()? {
+            let mined = match &output.spent_by {
+                Some(txid) => self
+                    .vault_spend(txid)?
+                    .is_some_and(|spend| spend.height.is_some()),
+                None => false,
+            };
+            if !mined {
+                unsettled.push(output);
+            }
+        }
+        Ok(unsettled)
+    }
+
+    /// Sats `txid` paid the vault, as far as its tracked outputs tell; `None` when none is
+    /// tracked.
+    pub fn paid_by(&self, txid: &str) -> Result<Option<u64>, Box<dyn Error>> {
+        let values: Vec<u64> = self
+            .outputs
+            .scan_prefix(format!("{}:", txid))
+            .map(|entry| Ok(decode::<VaultOutput>(&entry?.1)?.value_sats))
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        Ok((!values.is_empty()).then(|| values.iter().sum()))
+    }
+
+    /// Marks the tracked outputs `tx` spends as spent by it, and returns them.
+    pub fn spend(&self, tx: &Transaction) -> Result<Vec<VaultOutput>, Box<dyn Error>> {
+        let txid = tx.compute_txid().to_string();
+        let mut spent = Vec::new();
+        for input in &tx.input {
+            if let Some(mut output) = self.output(&input.previous_output)? {
+                output.spent_by = Some(txid.clone());
+                self.outputs.insert(
+                    input.previous_output.to_string(),
+                    serde_json::to_vec(&output)?,
+                )?;
+                spent.push(output);
+            }
+        }
+        Ok(spent)
+    }
+
+    pub fn vault_spend(&self, txid: &str) -> Result<Option<VaultSpend>, Box<dyn Error>> {
+        self.spends
+            .get(txid)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    pub fn put_spend(&self, spend: &VaultSpend) -> Result<(), Box<dyn Error>> {
+        self.spends
+            .insert(spend.txid.as_bytes(), serde_json::to_vec(spend)?)?;
+        Ok(())
+    }
+
+    /// Every vault spend checked, oldest first.
+    pub fn vault_spends(&self) -> Result<Vec<VaultSpend>, Box<dyn Error>> {
+        let mut spends: Vec<VaultSpend> = self
+            .spends
+            .iter()
+            .map(|entry| decode(&entry?.1))
+            .collect::<Result<_, _>>()?;
+        spends.sort_by_key(|spend| spend.detected_at);
+        Ok(spends)
+    }
+
+    /// Unauthorized spends whose alert was not delivered yet.
+    pub fn unalerted(&self) -> Result<Vec<VaultSpend>, Box<dyn Error>> {
+        Ok(self
+            .vault_spends()?
+            .into_iter()
+            .filter(|spend| !spend.authorized && !spend.alerted)
+            .collect())
+    }
+
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+}